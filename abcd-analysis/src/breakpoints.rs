@@ -0,0 +1,330 @@
+//! Where to put a breakpoint, and what else has to change to put it there.
+//!
+//! On-device patchers stop a method by inserting a `debugger` instruction
+//! into its code. [`plan`] finds the instructions a source line or an
+//! instruction pattern names, and for each returns a [`PatchSite`]: the
+//! byte offset to insert at, the bytes to insert, and the [`Fixup`]s that
+//! keep the method valid once everything after the site has moved — jump
+//! offsets, try and catch ranges, the code size and the line number
+//! program.
+//!
+//! Every site is planned on its own, against the unpatched file. A patcher
+//! applying several sites to one method has to plan again after each.
+//!
+//! ```no_run
+//! use abcd_analysis::breakpoints::{self, Target};
+//! use abcd_file::EntityId;
+//!
+//! let abc = abcd_file::File::open_path("modules.abc".as_ref()).unwrap();
+//! for site in breakpoints::plan(&abc, EntityId(0x1a4), Target::Line(12)).unwrap() {
+//!     println!("insert {:02x?} at {:#x}", site.insert, site.file_offset);
+//!     for fixup in &site.fixups {
+//!         println!("  {fixup:?}");
+//!     }
+//! }
+//! ```
+
+use std::fmt;
+
+use abcd_file::util::leb128::decode_uleb128;
+use abcd_file::{EntityId, File};
+use abcd_isa::{Bytecode, DecodeError, OpcodeInfo, TypedEntityRef, opcode_table, opcodes};
+
+/// What to break on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target<'a> {
+    /// The first instruction of every run of instructions the line number
+    /// table gives this source line. Matches nothing when the crate is
+    /// built without the `debug-info` feature.
+    Line(u32),
+    /// Every instruction whose disassembly contains this text. String
+    /// operands are followed by their quoted contents, so `"log"` finds
+    /// the instructions that name `log`.
+    Pattern(&'a str),
+}
+
+/// A breakpoint: insert [`insert`](Self::insert) at
+/// [`file_offset`](Self::file_offset), then apply every fixup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatchSite {
+    pub method_off: EntityId,
+    /// Offset within the method's code of the instruction that will follow
+    /// the inserted bytes.
+    pub pc: u32,
+    /// The same position in the file.
+    pub file_offset: u32,
+    pub insert: Vec<u8>,
+    /// Disassembly of the instruction at `pc`.
+    pub instruction: String,
+    pub fixups: Vec<Fixup>,
+}
+
+/// A change the insertion forces elsewhere in the method.
+///
+/// Branch targets, try ranges and handlers that start at the site keep
+/// their offset, so they reach the breakpoint first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fixup {
+    /// The jump at `pc` needs a new relative offset. Its operand is `width`
+    /// bits at `file_offset`; when `new` does not fit there, the jump has to
+    /// be re-encoded in a wider form.
+    Jump {
+        pc: u32,
+        file_offset: u32,
+        width: u8,
+        old: i32,
+        new: i32,
+    },
+    /// The ULEB128 code size at `file_offset` grows. A longer encoding
+    /// moves the instructions too, so most patchers relocate the code item.
+    CodeSize {
+        file_offset: u32,
+        old: u32,
+        new: u32,
+    },
+    /// Try block `index` covers a new range.
+    TryBlock {
+        index: usize,
+        old_start: u32,
+        old_length: u32,
+        start: u32,
+        length: u32,
+    },
+    /// Catch `catch` of try block `try_index` has a new handler range.
+    Handler {
+        try_index: usize,
+        catch: usize,
+        old_pc: u32,
+        old_size: u32,
+        pc: u32,
+        size: u32,
+    },
+    /// Line number program addresses past `pc` grow by `by`.
+    LineProgram { pc: u32, by: u32 },
+}
+
+impl Fixup {
+    /// Whether a jump's new offset still fits its encoding. Other fixups
+    /// always fit.
+    pub fn fits(&self) -> bool {
+        match *self {
+            Fixup::Jump { width, new, .. } => {
+                let bound = 1i64 << (width - 1);
+                (-bound..bound).contains(&i64::from(new))
+            }
+            _ => true,
+        }
+    }
+}
+
+/// Why a method cannot be planned.
+#[derive(Debug)]
+pub enum PlanError {
+    File(abcd_file::Error),
+    /// The method is native or abstract.
+    NoCode(EntityId),
+    Decode(DecodeError),
+}
+
+impl fmt::Display for PlanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlanError::File(e) => write!(f, "{e}"),
+            PlanError::NoCode(off) => write!(f, "method at {off} has no code"),
+            PlanError::Decode(e) => write!(f, "cannot decode method: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for PlanError {}
+
+impl From<abcd_file::Error> for PlanError {
+    fn from(e: abcd_file::Error) -> Self {
+        PlanError::File(e)
+    }
+}
+
+/// Breakpoint sites in the method at `method_off` for `target`, in code
+/// order.
+pub fn plan(abc: &File, method_off: EntityId, target: Target) -> Result<Vec<PatchSite>, PlanError> {
+    let method = abc.method(method_off)?;
+    let code_off = method.code_off().ok_or(PlanError::NoCode(method_off))?;
+    let code = abc.code(code_off)?;
+    let bytes = code.instructions();
+    let decoded = abcd_isa::decode(bytes).map_err(PlanError::Decode)?;
+    let offsets: Vec<u32> = decoded.iter().map(|&(_, off)| off).collect();
+
+    let pcs: Vec<u32> = match target {
+        Target::Line(line) => line_starts(abc, method_off, &offsets, line),
+        Target::Pattern(pattern) => decoded
+            .iter()
+            .filter(|(bc, _)| instruction_text(abc, method_off, bc).contains(pattern))
+            .map(|&(_, off)| off)
+            .collect(),
+    };
+
+    // Header: num_vregs, num_args, code_size, tries_size, then the
+    // instructions.
+    let data = abc.raw_data();
+    let mut pos = code_off.0 as usize;
+    let mut field_offsets = [0; 4];
+    for field in &mut field_offsets {
+        *field = pos as u32;
+        pos += decode_uleb128(data, pos)?.1;
+    }
+    let code_start = pos as u32;
+    let insert = debugger_bytes();
+    let shift = insert.len() as u32;
+    let try_blocks = code.try_blocks();
+
+    let sites = pcs
+        .into_iter()
+        .map(|site| {
+            // Whatever starts at the site stays put and reaches the
+            // breakpoint first; everything later moves.
+            let moved = |x: u32| if x <= site { x } else { x + shift };
+            let mut fixups = Vec::new();
+            for (i, (bc, pc)) in decoded.iter().enumerate() {
+                let Some(label_idx) = bc.jump_label_arg_index() else {
+                    continue;
+                };
+                let (_, args, _) = bc.emit_args();
+                let target = offsets
+                    .get(args[label_idx] as usize)
+                    .copied()
+                    .unwrap_or(bytes.len() as u32);
+                let old = target as i64 - i64::from(*pc);
+                let source = if *pc < site { *pc } else { pc + shift };
+                let new = i64::from(moved(target)) - i64::from(source);
+                if new == old {
+                    continue;
+                }
+                let size = offsets.get(i + 1).copied().unwrap_or(bytes.len() as u32) - pc;
+                let Some(desc) = row_for(bc, size).and_then(|row| row.operands.get(label_idx))
+                else {
+                    continue;
+                };
+                fixups.push(Fixup::Jump {
+                    pc: *pc,
+                    file_offset: code_start + pc + u32::from(desc.byte_offset),
+                    width: desc.width,
+                    old: old as i32,
+                    new: new as i32,
+                });
+            }
+            fixups.push(Fixup::CodeSize {
+                file_offset: field_offsets[2],
+                old: bytes.len() as u32,
+                new: bytes.len() as u32 + shift,
+            });
+            for (index, tb) in try_blocks.iter().enumerate() {
+                let start = moved(tb.start_pc);
+                let length = moved(tb.start_pc + tb.length) - start;
+                if (start, length) != (tb.start_pc, tb.length) {
+                    fixups.push(Fixup::TryBlock {
+                        index,
+                        old_start: tb.start_pc,
+                        old_length: tb.length,
+                        start,
+                        length,
+                    });
+                }
+                for (catch, cb) in tb.catches.iter().enumerate() {
+                    let pc = moved(cb.handler_pc);
+                    let size = moved(cb.handler_pc + cb.code_size) - pc;
+                    if (pc, size) != (cb.handler_pc, cb.code_size) {
+                        fixups.push(Fixup::Handler {
+                            try_index: index,
+                            catch,
+                            old_pc: cb.handler_pc,
+                            old_size: cb.code_size,
+                            pc,
+                            size,
+                        });
+                    }
+                }
+            }
+            if method.debug_info_off().is_some() {
+                fixups.push(Fixup::LineProgram {
+                    pc: site,
+                    by: shift,
+                });
+            }
+            let k = offsets.binary_search(&site).unwrap_or_default();
+            PatchSite {
+                method_off,
+                pc: site,
+                file_offset: code_start + site,
+                insert: insert.clone(),
+                instruction: instruction_text(abc, method_off, &decoded[k].0),
+                fixups,
+            }
+        })
+        .collect();
+    Ok(sites)
+}
+
+/// The instructions in `offsets` that start a run of `line` in the line
+/// number table.
+#[cfg(feature = "debug-info")]
+fn line_starts(abc: &File, method_off: EntityId, offsets: &[u32], line: u32) -> Vec<u32> {
+    let table = abc
+        .debug_info()
+        .map(|d| d.line_table(method_off))
+        .unwrap_or_default();
+    let mut pcs = Vec::new();
+    let mut previous = None;
+    for &pc in offsets {
+        let n = table.partition_point(|e| e.offset <= pc);
+        let current = n.checked_sub(1).map(|i| table[i].line);
+        if current == Some(line) && previous != Some(line) {
+            pcs.push(pc);
+        }
+        previous = current;
+    }
+    pcs
+}
+
+/// Without debug info support there is no line table, as in a file
+/// stripped of one.
+#[cfg(not(feature = "debug-info"))]
+fn line_starts(_: &File, _: EntityId, _: &[u32], _: u32) -> Vec<u32> {
+    Vec::new()
+}
+
+/// The encoding of `debugger`.
+fn debugger_bytes() -> Vec<u8> {
+    if opcodes::DEBUGGER > 0xff {
+        opcodes::DEBUGGER.to_le_bytes().to_vec()
+    } else {
+        vec![opcodes::DEBUGGER as u8]
+    }
+}
+
+/// The encoding `bc` was decoded from, told apart from the other encodings
+/// of its mnemonic by size.
+fn row_for(bc: &Bytecode, size: u32) -> Option<&'static OpcodeInfo> {
+    opcode_table()
+        .iter()
+        .find(|r| r.mnemonic == bc.mnemonic() && u32::from(r.size) == size)
+}
+
+/// Disassembly of `bc` with each string operand followed by its quoted
+/// contents.
+fn instruction_text(abc: &File, method_off: EntityId, bc: &Bytecode) -> String {
+    let mut text = String::new();
+    let _ = bc.write_formatted(&mut text);
+    let (_, _, n) = bc.emit_args();
+    for idx in 0..n {
+        let Some(TypedEntityRef::String(id)) = bc.typed_id(idx) else {
+            continue;
+        };
+        let s = abc
+            .resolve_offset_by_index(method_off, id.0 as u16)
+            .and_then(|off| abc.get_string(off).ok());
+        if let Some(s) = s {
+            text.push_str(&format!(" {s:?}"));
+        }
+    }
+    text
+}
@@ -0,0 +1,272 @@
+//! Duplicate method bodies.
+//!
+//! Obfuscators and bundlers copy helpers into every module that uses them,
+//! and injected payloads tend to be pasted into several classes at once.
+//! [`find`] groups methods whose bytecode matches, either exactly or up to a
+//! few changed instructions.
+//!
+//! Bodies are compared as instruction sequences with everything that
+//! differs between copies of the same code normalized away: entity IDs are
+//! resolved to the offsets they name (each index region numbers them
+//! differently), inline-cache slots are dropped, and wide encodings and
+//! redundant moves are folded by [`abcd_isa::normalize`]. Registers and
+//! jump targets are kept; they only move when the code does.
+//!
+//! ```no_run
+//! use abcd_analysis::clones;
+//!
+//! let abc = abcd_file::File::open_path("modules.abc".as_ref()).unwrap();
+//! for group in clones::find(&abc, 20) {
+//!     let names: Vec<_> = group.methods.iter().map(|m| m.name.as_str()).collect();
+//!     println!("{:?} {:.2}: {}", group.kind, group.similarity, names.join(", "));
+//! }
+//! ```
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+use abcd_file::{EntityId, File};
+
+/// Bodies at least this similar are reported as near-identical.
+pub const SIMILARITY_THRESHOLD: f64 = 0.85;
+
+/// Instructions per shingle when comparing bodies that are not identical.
+const SHINGLE_LEN: usize = 3;
+
+/// How the methods of a [`CloneGroup`] match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloneKind {
+    /// Normalized bodies are equal.
+    Identical,
+    /// Bodies share at least [`SIMILARITY_THRESHOLD`] of their shingles.
+    Similar,
+}
+
+/// A method with code, normalized for comparison.
+#[derive(Debug, Clone)]
+pub struct MethodBody {
+    pub method_off: EntityId,
+    /// `Class.method`.
+    pub name: String,
+    /// One token per instruction.
+    pub insns: Vec<String>,
+}
+
+/// Methods with matching bodies.
+#[derive(Debug, Clone)]
+pub struct CloneGroup {
+    pub kind: CloneKind,
+    /// Lowest similarity between two members that were matched directly;
+    /// `1.0` for identical bodies.
+    pub similarity: f64,
+    /// Members in file order.
+    pub methods: Vec<MethodRef>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MethodRef {
+    pub method_off: EntityId,
+    pub name: String,
+    /// Number of instructions.
+    pub len: usize,
+}
+
+/// Clone groups among the methods of `abc` with at least `min_len`
+/// instructions, largest bodies first.
+pub fn find(abc: &File, min_len: usize) -> Vec<CloneGroup> {
+    let bodies = method_bodies(abc)
+        .into_iter()
+        .filter(|b| b.insns.len() >= min_len)
+        .collect();
+    group(bodies)
+}
+
+/// Every local method with code, normalized.
+pub fn method_bodies(abc: &File) -> Vec<MethodBody> {
+    let mut bodies = Vec::new();
+    for class_off in abc.class_offsets() {
+        if abc.is_external(class_off) {
+            continue;
+        }
+        let Ok(class) = abc.class(class_off) else {
+            continue;
+        };
+        let class_name = abc
+            .get_string(class_off)
+            .unwrap_or_else(|_| format!("<{class_off}>"));
+        for method_off in class.method_offsets() {
+            if let Some(body) = method_body(abc, &class_name, method_off) {
+                bodies.push(body);
+            }
+        }
+    }
+    bodies
+}
+
+fn method_body(abc: &File, class_name: &str, method_off: EntityId) -> Option<MethodBody> {
+    let method = abc.method(method_off).ok()?;
+    let code = abc.code(method.code_off()?).ok()?;
+    let decoded = abcd_isa::decode(&abcd_isa::normalize(code.instructions())).ok()?;
+    let method_name = abc
+        .get_string(method.name_off())
+        .unwrap_or_else(|_| format!("<{method_off}>"));
+    let mut text = String::new();
+    let insns = decoded
+        .iter()
+        .map(|(bc, _)| normalize(abc, method_off, bc, &mut text))
+        .collect();
+    Some(MethodBody {
+        method_off,
+        name: format!("{class_name}.{method_name}"),
+        insns,
+    })
+}
+
+/// One instruction as a token, with its IC slot dropped and entity IDs
+/// replaced by the offsets they resolve to. `text` is scratch space for the
+/// raw rendering.
+fn normalize(
+    abc: &File,
+    method_off: EntityId,
+    bc: &abcd_isa::Bytecode,
+    text: &mut String,
+) -> String {
+    text.clear();
+    let _ = bc.write_formatted(text);
+    let mut parts = text.split_whitespace();
+    let mut out = String::from(parts.next().unwrap_or_default());
+    let slot = bc.ic_slot();
+    for (i, part) in parts.enumerate() {
+        if slot == Some(i) {
+            continue;
+        }
+        out.push(' ');
+        let resolved = part
+            .strip_prefix("id:")
+            .and_then(|id| id.parse::<u16>().ok())
+            .and_then(|id| abc.resolve_offset_by_index(method_off, id));
+        match resolved {
+            Some(off) => out.push_str(&format!("@{:#x}", off.0)),
+            None => out.push_str(part),
+        }
+    }
+    out
+}
+
+/// Group `bodies` into clones, largest bodies first.
+pub fn group(bodies: Vec<MethodBody>) -> Vec<CloneGroup> {
+    // Identical bodies first; each class of equal bodies is then compared
+    // against the others through one representative. Classes are keyed by
+    // the bodies themselves, so only equal bodies are reported as
+    // identical, whatever their hashes.
+    let mut exact: BTreeMap<&[String], Vec<usize>> = BTreeMap::new();
+    for (i, body) in bodies.iter().enumerate() {
+        exact.entry(body.insns.as_slice()).or_default().push(i);
+    }
+    let classes: Vec<Vec<usize>> = exact.into_values().collect();
+
+    let shingles: Vec<HashSet<u64>> = classes
+        .iter()
+        .map(|members| shingle_set(&bodies[members[0]].insns))
+        .collect();
+    // Jaccard similarity is at most the ratio of the set sizes, so only
+    // neighbours in size order can be similar enough.
+    let mut by_size: Vec<usize> = (0..classes.len()).collect();
+    by_size.sort_by_key(|&c| shingles[c].len());
+
+    let mut parent: Vec<usize> = (0..classes.len()).collect();
+    let mut similarity = vec![1.0f64; classes.len()];
+    for (i, &a) in by_size.iter().enumerate() {
+        for &b in &by_size[i + 1..] {
+            let (small, large) = (shingles[a].len(), shingles[b].len());
+            if (small as f64) < SIMILARITY_THRESHOLD * large as f64 {
+                break;
+            }
+            let sim = jaccard(&shingles[a], &shingles[b]);
+            if sim >= SIMILARITY_THRESHOLD {
+                let (ra, rb) = (find_root(&mut parent, a), find_root(&mut parent, b));
+                let s = similarity[ra].min(similarity[rb]).min(sim);
+                if ra != rb {
+                    parent[rb] = ra;
+                }
+                similarity[ra] = s;
+            }
+        }
+    }
+
+    let mut merged: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for c in 0..classes.len() {
+        let root = find_root(&mut parent, c);
+        merged.entry(root).or_default().push(c);
+    }
+
+    let mut groups: Vec<CloneGroup> = merged
+        .into_iter()
+        .filter_map(|(root, members)| {
+            let mut methods: Vec<usize> = members
+                .iter()
+                .flat_map(|&c| classes[c].iter().copied())
+                .collect();
+            if methods.len() < 2 {
+                return None;
+            }
+            methods.sort_unstable();
+            let kind = if members.len() == 1 {
+                CloneKind::Identical
+            } else {
+                CloneKind::Similar
+            };
+            Some(CloneGroup {
+                kind,
+                similarity: similarity[root],
+                methods: methods
+                    .into_iter()
+                    .map(|i| MethodRef {
+                        method_off: bodies[i].method_off,
+                        name: bodies[i].name.clone(),
+                        len: bodies[i].insns.len(),
+                    })
+                    .collect(),
+            })
+        })
+        .collect();
+    groups.sort_by_key(|g| {
+        let len = g.methods.iter().map(|m| m.len).max().unwrap_or(0);
+        (std::cmp::Reverse(len), g.methods[0].method_off.0)
+    });
+    groups
+}
+
+fn hash_of<T: Hash + ?Sized>(value: &T) -> u64 {
+    let mut h = DefaultHasher::new();
+    value.hash(&mut h);
+    h.finish()
+}
+
+/// Hashes of every run of [`SHINGLE_LEN`] instructions; a shorter body is
+/// its own single shingle.
+fn shingle_set(insns: &[String]) -> HashSet<u64> {
+    if insns.len() < SHINGLE_LEN {
+        return HashSet::from([hash_of(insns)]);
+    }
+    insns.windows(SHINGLE_LEN).map(hash_of).collect()
+}
+
+fn jaccard(a: &HashSet<u64>, b: &HashSet<u64>) -> f64 {
+    let common = a.intersection(b).count();
+    let total = a.len() + b.len() - common;
+    if total == 0 {
+        1.0
+    } else {
+        common as f64 / total as f64
+    }
+}
+
+fn find_root(parent: &mut [usize], mut x: usize) -> usize {
+    while parent[x] != x {
+        parent[x] = parent[parent[x]];
+        x = parent[x];
+    }
+    x
+}
@@ -0,0 +1,316 @@
+//! Numeric constants loaded by the code, and where.
+//!
+//! Crypto and hashing routines give themselves away by their constants:
+//! the TEA delta `0x9e3779b9`, MD5's `0x67452301`, CRC polynomials. Finding
+//! the methods that load one is a quick way into an obfuscated file.
+//!
+//! Constants come from two places. `ldai` (32-bit integers) and `fldai`
+//! (doubles) carry them as immediates; every other immediate is a slot,
+//! count or index. Array and object literals, where lookup tables such as
+//! S-boxes and CRC tables live, keep theirs as the `Integer`, `Float` and
+//! `Double` entries of the literal array the loading instruction names,
+//! nested arrays included; the payloads of typed `ARRAY_*` entries are not
+//! read.
+//!
+//! es2abc emits integers outside the `i32` range as `fldai`, and `x | 0`
+//! code folds them back into `i32`, so [`Constant::matches`] treats
+//! `0x9e3779b9`, `-1640531527` and `2654435769.0` as the same constant.
+//!
+//! ```no_run
+//! use abcd_analysis::constants::{self, Constant};
+//!
+//! let abc = abcd_file::File::open_path("modules.abc".as_ref()).unwrap();
+//! let delta = Constant::parse("0x9e3779b9").unwrap();
+//! for site in constants::find(&abc, delta) {
+//!     println!("{} +{:#x}", site.name, site.offset);
+//! }
+//! ```
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+use abcd_file::literal::{LiteralArray, LiteralValue};
+use abcd_file::{EntityId, File};
+use abcd_isa::{Bytecode, Imm, TypedEntityRef};
+
+/// A constant, as an immediate or a literal array entry.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Constant {
+    /// `ldai`, or an `Integer` literal.
+    Int(i32),
+    /// `fldai`, or a `Float` or `Double` literal.
+    Float(f64),
+}
+
+impl Constant {
+    /// Parse a query: decimal or `0x` hex integers, or a decimal float.
+    /// Hex values up to `0xffffffff` read as the `i32` with those bits,
+    /// larger ones as doubles.
+    pub fn parse(s: &str) -> Option<Constant> {
+        let s = s.trim();
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s),
+        };
+        if let Some(hex) = digits
+            .strip_prefix("0x")
+            .or_else(|| digits.strip_prefix("0X"))
+        {
+            let value = u64::from_str_radix(hex, 16).ok()?;
+            return Some(match u32::try_from(value) {
+                Ok(bits) if negative => Constant::Int((bits as i32).wrapping_neg()),
+                Ok(bits) => Constant::Int(bits as i32),
+                Err(_) if negative => Constant::Float(-(value as f64)),
+                Err(_) => Constant::Float(value as f64),
+            });
+        }
+        if let Ok(value) = s.parse::<i64>() {
+            return Some(match (i32::try_from(value), u32::try_from(value)) {
+                (Ok(v), _) => Constant::Int(v),
+                (_, Ok(bits)) => Constant::Int(bits as i32),
+                _ => Constant::Float(value as f64),
+            });
+        }
+        s.parse::<f64>().ok().map(Constant::Float)
+    }
+
+    /// Whether the two load the same number, counting an `i32` as equal to
+    /// both its signed and its unsigned value as a double.
+    pub fn matches(self, other: Constant) -> bool {
+        match (self, other) {
+            (Constant::Int(a), Constant::Int(b)) => a == b,
+            (Constant::Float(a), Constant::Float(b)) => a == b,
+            (Constant::Int(i), Constant::Float(f)) | (Constant::Float(f), Constant::Int(i)) => {
+                f == f64::from(i) || f == f64::from(i as u32)
+            }
+        }
+    }
+
+    /// Whether the constant is unusual enough to point at an algorithm.
+    ///
+    /// Small integers, powers of two and masks below them, and round
+    /// decimal numbers are everywhere; so are doubles with few significant
+    /// digits. What is left is mostly hash, cipher and PRNG constants.
+    pub fn is_distinctive(self) -> bool {
+        match self {
+            Constant::Int(v) => distinctive_int(i64::from(v)),
+            Constant::Float(f) if !f.is_finite() => false,
+            Constant::Float(f) if f.fract() == 0.0 && f.abs() < 2f64.powi(53) => {
+                distinctive_int(f as i64)
+            }
+            Constant::Float(f) => significant_digits(f) > 6,
+        }
+    }
+
+    /// Order for listings: integers by value, then doubles by value.
+    fn total_cmp(&self, other: &Constant) -> Ordering {
+        match (self, other) {
+            (Constant::Int(a), Constant::Int(b)) => a.cmp(b),
+            (Constant::Float(a), Constant::Float(b)) => a.total_cmp(b),
+            (Constant::Int(_), Constant::Float(_)) => Ordering::Less,
+            (Constant::Float(_), Constant::Int(_)) => Ordering::Greater,
+        }
+    }
+}
+
+impl fmt::Display for Constant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Constant::Int(v) => write!(f, "{:#010x} ({v})", *v as u32),
+            Constant::Float(v) => write!(f, "{v:?}"),
+        }
+    }
+}
+
+fn distinctive_int(v: i64) -> bool {
+    let magnitude = v.unsigned_abs();
+    magnitude > 0xffff
+        && !magnitude.is_power_of_two()
+        && !(magnitude + 1).is_power_of_two()
+        && !magnitude.is_multiple_of(1000)
+}
+
+/// Significant digits in the shortest decimal that reads back as `f`.
+fn significant_digits(f: f64) -> usize {
+    let text = format!("{:e}", f.abs());
+    let mantissa = text.split('e').next().unwrap_or_default();
+    mantissa.chars().filter(char::is_ascii_digit).count()
+}
+
+/// One instruction loading a constant.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConstantSite {
+    pub method_off: EntityId,
+    /// `Class.method`.
+    pub name: String,
+    /// Byte offset of the instruction within the method's code.
+    pub offset: u32,
+    /// For a constant in a literal array, the entry holding it; `None` for
+    /// an immediate.
+    pub entry: Option<LiteralEntry>,
+}
+
+/// An entry of a literal array loaded by an instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LiteralEntry {
+    /// The array holding the entry: the one the instruction names, or an
+    /// array nested in it.
+    pub array_off: EntityId,
+    /// Position of the entry in that array.
+    pub index: u32,
+}
+
+impl fmt::Display for LiteralEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "literal_array@{:#x}[{}]", self.array_off.0, self.index)
+    }
+}
+
+/// A distinctive constant and every instruction that loads it.
+#[derive(Debug, Clone)]
+pub struct ConstantUses {
+    pub value: Constant,
+    /// Sites in file order.
+    pub sites: Vec<ConstantSite>,
+}
+
+/// Every [distinctive](Constant::is_distinctive) constant loaded by a local
+/// method, integers first, each in ascending order.
+pub fn index(abc: &File) -> Vec<ConstantUses> {
+    let mut by_value: BTreeMap<(bool, u64), ConstantUses> = BTreeMap::new();
+    for (value, site) in sites(abc) {
+        if !value.is_distinctive() {
+            continue;
+        }
+        let key = match value {
+            Constant::Int(v) => (false, u64::from(v as u32)),
+            Constant::Float(f) => (true, f.to_bits()),
+        };
+        by_value
+            .entry(key)
+            .or_insert_with(|| ConstantUses {
+                value,
+                sites: Vec::new(),
+            })
+            .sites
+            .push(site);
+    }
+    let mut uses: Vec<_> = by_value.into_values().collect();
+    uses.sort_by(|a, b| a.value.total_cmp(&b.value));
+    uses
+}
+
+/// Instructions that load a constant [matching](Constant::matches) `query`,
+/// distinctive or not, in file order.
+pub fn find(abc: &File, query: Constant) -> Vec<ConstantSite> {
+    sites(abc)
+        .into_iter()
+        .filter(|(value, _)| value.matches(query))
+        .map(|(_, site)| site)
+        .collect()
+}
+
+/// Every `ldai` and `fldai` in the local methods of `abc`, and every
+/// number in the literal arrays they load.
+fn sites(abc: &File) -> Vec<(Constant, ConstantSite)> {
+    let mut out = Vec::new();
+    for class_off in abc.class_offsets() {
+        if abc.is_external(class_off) {
+            continue;
+        }
+        let Ok(class) = abc.class(class_off) else {
+            continue;
+        };
+        let class_name = abc.get_string_lossy(class_off);
+        for method_off in class.method_offsets() {
+            let Ok(method) = abc.method(method_off) else {
+                continue;
+            };
+            let Some(code) = method.code_off().and_then(|off| abc.code(off).ok()) else {
+                continue;
+            };
+            let Ok(decoded) = abcd_isa::decode(code.instructions()) else {
+                continue;
+            };
+            let method_name = abc.get_string_lossy(method.name_off());
+            let site = |offset, entry| ConstantSite {
+                method_off,
+                name: format!("{class_name}.{method_name}"),
+                offset,
+                entry,
+            };
+            for (bc, offset) in decoded {
+                let value = match bc {
+                    Bytecode::Ldai(Imm(v)) => Constant::Int(v as i32),
+                    Bytecode::Fldai(Imm(bits)) => Constant::Float(f64::from_bits(bits as u64)),
+                    _ => {
+                        for array_off in literal_arrays(abc, method_off, &bc) {
+                            let mut seen = HashSet::new();
+                            for (value, entry) in literal_numbers(abc, array_off, &mut seen) {
+                                out.push((value, site(offset, Some(entry))));
+                            }
+                        }
+                        continue;
+                    }
+                };
+                out.push((value, site(offset, None)));
+            }
+        }
+    }
+    out
+}
+
+/// The literal arrays `bc`, in the method at `method_off`, names.
+fn literal_arrays(abc: &File, method_off: EntityId, bc: &Bytecode) -> Vec<EntityId> {
+    let (_, _, n) = bc.emit_args();
+    let ids: Vec<u16> = (0..n)
+        .filter_map(|i| match bc.typed_id(i) {
+            Some(TypedEntityRef::LiteralArray(id)) => Some(id.0 as u16),
+            _ => None,
+        })
+        .collect();
+    if ids.is_empty() {
+        return Vec::new();
+    }
+    let Ok(literals) = abc.literal_for(method_off) else {
+        return Vec::new();
+    };
+    ids.into_iter()
+        .filter_map(|id| literals.array_id(id))
+        .collect()
+}
+
+/// The numbers in the literal array at `array_off` and the arrays nested
+/// in it, each array read once; `seen` holds the arrays already read.
+fn literal_numbers(
+    abc: &File,
+    array_off: EntityId,
+    seen: &mut HashSet<EntityId>,
+) -> Vec<(Constant, LiteralEntry)> {
+    if !seen.insert(array_off) {
+        return Vec::new();
+    }
+    let Ok(array) = LiteralArray::read(abc, array_off) else {
+        return Vec::new();
+    };
+    let mut out = Vec::new();
+    for (index, (_, value)) in array.entries.iter().enumerate() {
+        let entry = LiteralEntry {
+            array_off,
+            index: index as u32,
+        };
+        let value = match *value {
+            LiteralValue::Integer(v) => Constant::Int(v as i32),
+            LiteralValue::Float(v) => Constant::Float(f64::from(v)),
+            LiteralValue::Double(v) => Constant::Float(v),
+            LiteralValue::LiteralArray(nested) => {
+                out.extend(literal_numbers(abc, nested, seen));
+                continue;
+            }
+            _ => continue,
+        };
+        out.push((value, entry));
+    }
+    out
+}
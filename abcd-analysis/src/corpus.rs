@@ -0,0 +1,195 @@
+//! Instruction usage across a corpus of files.
+//!
+//! Each new API level adds instructions, and the decompiler only learns
+//! about them one at a time. [`opcode_usage`] counts how often every
+//! mnemonic occurs in each of a set of real-world files, so support can go
+//! first to the instructions apps actually use. [`UsageMatrix::by_version`]
+//! folds the files of one format version together, which shows when an
+//! instruction starts appearing in the wild.
+//!
+//! ```no_run
+//! use abcd_analysis::corpus;
+//!
+//! let old = abcd_file::File::open_path("app-v1/modules.abc".as_ref()).unwrap();
+//! let new = abcd_file::File::open_path("app-v2/modules.abc".as_ref()).unwrap();
+//! let usage = corpus::opcode_usage([("app-v1", &old), ("app-v2", &new)]);
+//! usage.by_version().write_csv(&mut std::io::stdout()).unwrap();
+//! ```
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use abcd_file::File;
+use abcd_isa::{Version, introduced_in, opcode_table};
+
+/// One column of a [`UsageMatrix`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Column {
+    /// The name the file was given, or the version for merged columns.
+    pub name: String,
+    pub version: Version,
+}
+
+/// Occurrences of each mnemonic (rows) in each file (columns).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsageMatrix {
+    columns: Vec<Column>,
+    /// Mnemonics used at least once, in opcode table order, with one count
+    /// per column.
+    rows: Vec<(&'static str, Vec<u64>)>,
+}
+
+/// Count the instructions of every local method of `files`, one column
+/// per `(name, file)`. Methods whose code does not decode are skipped.
+pub fn opcode_usage<'a>(files: impl IntoIterator<Item = (&'a str, &'a File)>) -> UsageMatrix {
+    let mut columns = Vec::new();
+    let mut counts: HashMap<&'static str, Vec<u64>> = HashMap::new();
+    for (index, (name, abc)) in files.into_iter().enumerate() {
+        columns.push(Column {
+            name: name.to_string(),
+            version: abc.version(),
+        });
+        for_each_mnemonic(abc, &mut |mnemonic| {
+            let row = counts.entry(mnemonic).or_default();
+            row.resize(index + 1, 0);
+            row[index] += 1;
+        });
+    }
+    for row in counts.values_mut() {
+        row.resize(columns.len(), 0);
+    }
+    UsageMatrix::new(columns, counts)
+}
+
+/// Call `f` with the mnemonic of every instruction in the local methods
+/// of `abc`.
+fn for_each_mnemonic(abc: &File, f: &mut dyn FnMut(&'static str)) {
+    for class_off in abc.class_offsets() {
+        if abc.is_external(class_off) {
+            continue;
+        }
+        let Ok(class) = abc.class(class_off) else {
+            continue;
+        };
+        for method_off in class.method_offsets() {
+            let Some(code) = abc
+                .method(method_off)
+                .ok()
+                .and_then(|m| m.code_off())
+                .and_then(|off| abc.code(off).ok())
+            else {
+                continue;
+            };
+            if let Ok(decoded) = abcd_isa::decode(code.instructions()) {
+                for (bc, _) in &decoded {
+                    f(bc.mnemonic());
+                }
+            }
+        }
+    }
+}
+
+impl UsageMatrix {
+    fn new(columns: Vec<Column>, counts: HashMap<&'static str, Vec<u64>>) -> Self {
+        let mut order: HashMap<&str, usize> = HashMap::new();
+        for (i, info) in opcode_table().iter().enumerate() {
+            order.entry(info.mnemonic).or_insert(i);
+        }
+        let mut rows: Vec<_> = counts.into_iter().collect();
+        rows.sort_by_key(|&(mnemonic, _)| (order.get(mnemonic).copied(), mnemonic));
+        UsageMatrix { columns, rows }
+    }
+
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    /// Mnemonics used anywhere in the corpus, in opcode table order.
+    pub fn mnemonics(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.rows.iter().map(|&(mnemonic, _)| mnemonic)
+    }
+
+    /// Occurrences of `mnemonic` in the column at `column`.
+    pub fn count(&self, mnemonic: &str, column: usize) -> u64 {
+        self.row(mnemonic)
+            .and_then(|counts| counts.get(column).copied())
+            .unwrap_or(0)
+    }
+
+    /// Occurrences of `mnemonic` over the whole corpus.
+    pub fn total(&self, mnemonic: &str) -> u64 {
+        self.row(mnemonic).map_or(0, |counts| counts.iter().sum())
+    }
+
+    /// Number of columns that use `mnemonic` at all.
+    pub fn columns_using(&self, mnemonic: &str) -> usize {
+        self.row(mnemonic)
+            .map_or(0, |counts| counts.iter().filter(|&&n| n > 0).count())
+    }
+
+    fn row(&self, mnemonic: &str) -> Option<&[u64]> {
+        self.rows
+            .iter()
+            .find(|&&(m, _)| m == mnemonic)
+            .map(|(_, counts)| counts.as_slice())
+    }
+
+    /// One column per file format version, oldest first, summing the
+    /// files of that version.
+    pub fn by_version(&self) -> UsageMatrix {
+        let mut versions: Vec<Version> = self.columns.iter().map(|c| c.version).collect();
+        versions.sort();
+        versions.dedup();
+        let columns = versions
+            .iter()
+            .map(|&version| Column {
+                name: version.to_string(),
+                version,
+            })
+            .collect();
+        let counts = self
+            .rows
+            .iter()
+            .map(|(mnemonic, counts)| {
+                let mut merged = vec![0; versions.len()];
+                for (column, n) in self.columns.iter().zip(counts) {
+                    // `versions` holds every column's version.
+                    let at = versions.binary_search(&column.version).unwrap();
+                    merged[at] += n;
+                }
+                (*mnemonic, merged)
+            })
+            .collect();
+        UsageMatrix::new(columns, counts)
+    }
+
+    /// Write the matrix as CSV: a header of
+    /// `mnemonic,introduced_in,<column names...>,total`, then one line per
+    /// mnemonic. `introduced_in` is empty for instructions that predate
+    /// API 9.
+    pub fn write_csv(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "mnemonic,introduced_in")?;
+        for column in &self.columns {
+            write!(out, ",{}", csv_field(&column.name))?;
+        }
+        writeln!(out, ",total")?;
+        for (mnemonic, counts) in &self.rows {
+            let since = introduced_in(mnemonic).map(|v| v.to_string());
+            write!(out, "{mnemonic},{}", since.unwrap_or_default())?;
+            for n in counts {
+                write!(out, ",{n}")?;
+            }
+            writeln!(out, ",{}", counts.iter().sum::<u64>())?;
+        }
+        Ok(())
+    }
+}
+
+/// `s` quoted if it holds a comma, quote or line break.
+fn csv_field(s: &str) -> std::borrow::Cow<'_, str> {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\"")).into()
+    } else {
+        s.into()
+    }
+}
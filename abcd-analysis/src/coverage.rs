@@ -0,0 +1,403 @@
+//! Execution coverage from runtime traces.
+//!
+//! Dynamic instrumentation (a patched runtime, a debugger script, a
+//! sampling profiler) records which bytecode ran as `(method offset, pc)`
+//! pairs. [`apply`] lays such a trace over the file's methods: each
+//! instruction is marked covered if the trace hit it, source lines are
+//! covered if any of their instructions are, and methods and classes get
+//! a percentage of instructions covered.
+//!
+//! Traces are plain text, one `METHOD_OFFSET PC` pair per line, each
+//! number hex with `0x` or decimal; see [`parse_trace`].
+//!
+//! ```no_run
+//! use abcd_analysis::coverage;
+//!
+//! let abc = abcd_file::File::open_path("modules.abc".as_ref()).unwrap();
+//! let text = std::fs::read_to_string("trace.txt").unwrap();
+//! let cov = coverage::apply(&abc, &coverage::parse_trace(&text).unwrap());
+//! for class in cov.classes() {
+//!     println!("{:5.1}%  {}", class.percent(), class.name);
+//! }
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use abcd_file::notes::parse_offset;
+use abcd_file::{EntityId, File};
+
+/// Where a method's instructions and source lines sit; what a trace is
+/// laid over.
+#[derive(Debug, Clone)]
+pub struct MethodMap {
+    pub method_off: EntityId,
+    pub class_off: EntityId,
+    pub class_name: String,
+    pub name: String,
+    /// Offset of every instruction, ascending.
+    pub insns: Vec<u32>,
+    pub code_size: u32,
+    /// `(pc, line)` entries of the line number table, ascending by pc.
+    pub lines: Vec<(u32, u32)>,
+}
+
+/// Static layout of every local method with code, in file order.
+pub fn method_maps(abc: &File) -> Vec<MethodMap> {
+    #[cfg(feature = "debug-info")]
+    let debug = abc.debug_info().ok();
+    let mut maps = Vec::new();
+    for class_off in abc.class_offsets() {
+        if abc.is_external(class_off) {
+            continue;
+        }
+        let Ok(class) = abc.class(class_off) else {
+            continue;
+        };
+        let class_name = abc
+            .get_string(class_off)
+            .unwrap_or_else(|_| format!("<{class_off}>"));
+        for method_off in class.method_offsets() {
+            let Ok(method) = abc.method(method_off) else {
+                continue;
+            };
+            let Some(code) = method.code_off().and_then(|c| abc.code(c).ok()) else {
+                continue;
+            };
+            let bytes = code.instructions();
+            let insns = abcd_isa::decode(bytes)
+                .map(|decoded| decoded.iter().map(|&(_, off)| off).collect())
+                .unwrap_or_default();
+            #[cfg(feature = "debug-info")]
+            let lines = debug
+                .as_ref()
+                .map(|d| d.line_table(method_off))
+                .unwrap_or_default()
+                .iter()
+                .map(|e| (e.offset, e.line))
+                .collect();
+            #[cfg(not(feature = "debug-info"))]
+            let lines = Vec::new();
+            maps.push(MethodMap {
+                method_off,
+                class_off,
+                class_name: class_name.clone(),
+                name: abc
+                    .get_string(method.name_off())
+                    .unwrap_or_else(|_| format!("<{method_off}>")),
+                insns,
+                code_size: bytes.len() as u32,
+                lines,
+            });
+        }
+    }
+    maps
+}
+
+/// Lay `trace` over the methods of `abc`.
+pub fn apply(abc: &File, trace: &[(EntityId, u32)]) -> Coverage {
+    compute(method_maps(abc), trace)
+}
+
+/// Lay `trace` over `maps`. A pc inside an instruction counts for that
+/// instruction; pairs naming no known method or a pc past its code are
+/// counted in [`Coverage::unmatched`].
+pub fn compute(maps: Vec<MethodMap>, trace: &[(EntityId, u32)]) -> Coverage {
+    let mut methods: Vec<MethodCoverage> = maps
+        .into_iter()
+        .map(|map| MethodCoverage {
+            hit: vec![false; map.insns.len()],
+            map,
+        })
+        .collect();
+    let index: HashMap<u32, usize> = methods
+        .iter()
+        .enumerate()
+        .map(|(i, m)| (m.map.method_off.0, i))
+        .collect();
+    let mut unmatched = 0;
+    for &(method_off, pc) in trace {
+        let insn = index.get(&method_off.0).and_then(|&i| {
+            let m = &methods[i];
+            let n = m.map.insns.partition_point(|&off| off <= pc);
+            (n > 0 && pc < m.map.code_size).then_some((i, n - 1))
+        });
+        match insn {
+            Some((i, k)) => methods[i].hit[k] = true,
+            None => unmatched += 1,
+        }
+    }
+    Coverage {
+        methods,
+        index,
+        unmatched,
+    }
+}
+
+/// A trace laid over a file.
+#[derive(Debug, Clone)]
+pub struct Coverage {
+    methods: Vec<MethodCoverage>,
+    index: HashMap<u32, usize>,
+    /// Trace entries that matched no instruction.
+    pub unmatched: usize,
+}
+
+impl Coverage {
+    /// Methods in file order.
+    pub fn methods(&self) -> &[MethodCoverage] {
+        &self.methods
+    }
+
+    pub fn method(&self, method_off: EntityId) -> Option<&MethodCoverage> {
+        self.index.get(&method_off.0).map(|&i| &self.methods[i])
+    }
+
+    /// Totals of the class at `class_off`; `None` if none of its methods
+    /// has code.
+    pub fn class(&self, class_off: EntityId) -> Option<ClassCoverage> {
+        let mut methods = self.methods.iter().filter(|m| m.map.class_off == class_off);
+        let first = methods.next()?;
+        let mut class = ClassCoverage {
+            class_off,
+            name: first.map.class_name.clone(),
+            covered: first.covered(),
+            total: first.total(),
+        };
+        for m in methods {
+            class.covered += m.covered();
+            class.total += m.total();
+        }
+        Some(class)
+    }
+
+    /// Totals per class, in file order.
+    pub fn classes(&self) -> Vec<ClassCoverage> {
+        let mut classes: Vec<ClassCoverage> = Vec::new();
+        for m in &self.methods {
+            let same = classes
+                .last()
+                .is_some_and(|c| c.class_off == m.map.class_off);
+            if !same {
+                classes.push(ClassCoverage {
+                    class_off: m.map.class_off,
+                    name: m.map.class_name.clone(),
+                    covered: 0,
+                    total: 0,
+                });
+            }
+            let class = classes.last_mut().unwrap();
+            class.covered += m.covered();
+            class.total += m.total();
+        }
+        classes
+    }
+}
+
+/// One method's instructions, marked.
+#[derive(Debug, Clone)]
+pub struct MethodCoverage {
+    map: MethodMap,
+    hit: Vec<bool>,
+}
+
+impl MethodCoverage {
+    pub fn map(&self) -> &MethodMap {
+        &self.map
+    }
+
+    /// Whether the instruction starting at `pc` ran; `None` if no
+    /// instruction starts there.
+    pub fn is_covered(&self, pc: u32) -> Option<bool> {
+        let k = self.map.insns.binary_search(&pc).ok()?;
+        Some(self.hit[k])
+    }
+
+    /// Number of instructions that ran.
+    pub fn covered(&self) -> usize {
+        self.hit.iter().filter(|&&h| h).count()
+    }
+
+    /// Number of instructions.
+    pub fn total(&self) -> usize {
+        self.hit.len()
+    }
+
+    pub fn percent(&self) -> f64 {
+        percent(self.covered(), self.total())
+    }
+
+    /// `covered/total (percent)`.
+    pub fn ratio(&self) -> String {
+        ratio(self.covered(), self.total())
+    }
+
+    /// Source lines with code, ascending, each covered if any of its
+    /// instructions ran. Empty without a line number table.
+    pub fn lines(&self) -> Vec<LineCoverage> {
+        // Instructions and table entries are both ascending by pc, so one
+        // pass over each finds the entry every instruction falls under.
+        let table = &self.map.lines;
+        let mut entry = 0;
+        let mut lines: BTreeMap<u32, bool> = BTreeMap::new();
+        for (&pc, &hit) in self.map.insns.iter().zip(&self.hit) {
+            while entry < table.len() && table[entry].0 <= pc {
+                entry += 1;
+            }
+            let Some(&(_, line)) = entry.checked_sub(1).map(|i| &table[i]) else {
+                continue;
+            };
+            *lines.entry(line).or_default() |= hit;
+        }
+        lines
+            .into_iter()
+            .map(|(line, covered)| LineCoverage { line, covered })
+            .collect()
+    }
+
+    /// Lines of [`lines`](Self::lines) that never ran.
+    pub fn uncovered_lines(&self) -> Vec<u32> {
+        self.lines()
+            .into_iter()
+            .filter(|l| !l.covered)
+            .map(|l| l.line)
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineCoverage {
+    pub line: u32,
+    pub covered: bool,
+}
+
+/// Instruction totals of one class.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassCoverage {
+    pub class_off: EntityId,
+    pub name: String,
+    pub covered: usize,
+    pub total: usize,
+}
+
+impl ClassCoverage {
+    pub fn percent(&self) -> f64 {
+        percent(self.covered, self.total)
+    }
+
+    /// `covered/total (percent)`.
+    pub fn ratio(&self) -> String {
+        ratio(self.covered, self.total)
+    }
+}
+
+fn percent(covered: usize, total: usize) -> f64 {
+    match total {
+        0 => 0.0,
+        _ => covered as f64 * 100.0 / total as f64,
+    }
+}
+
+fn ratio(covered: usize, total: usize) -> String {
+    format!("{covered}/{total} ({:.1}%)", percent(covered, total))
+}
+
+/// [`lines`](MethodCoverage::lines) as ranges marked `+` where they ran
+/// and `-` where they did not: `+1-3 -5 +7-9`. A range also spans the
+/// lines without code inside it, which no instruction can mark.
+pub fn line_marks(lines: &[LineCoverage]) -> String {
+    let mut out = String::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let LineCoverage {
+            line: start,
+            covered,
+        } = lines[i];
+        let mut end = start;
+        while i + 1 < lines.len() && lines[i + 1].covered == covered {
+            i += 1;
+            end = lines[i].line;
+        }
+        if !out.is_empty() {
+            out.push(' ');
+        }
+        out.push(if covered { '+' } else { '-' });
+        match start == end {
+            true => out.push_str(&start.to_string()),
+            false => out.push_str(&format!("{start}-{end}")),
+        }
+        i += 1;
+    }
+    out
+}
+
+/// Ascending `lines` as ranges: `3, 5-7, 10`.
+pub fn line_ranges(lines: &[u32]) -> String {
+    let mut out = String::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let start = lines[i];
+        let mut end = start;
+        while i + 1 < lines.len() && lines[i + 1] == end + 1 {
+            i += 1;
+            end = lines[i];
+        }
+        if !out.is_empty() {
+            out.push_str(", ");
+        }
+        match start == end {
+            true => out.push_str(&start.to_string()),
+            false => out.push_str(&format!("{start}-{end}")),
+        }
+        i += 1;
+    }
+    out
+}
+
+/// A trace line that is not `METHOD_OFFSET PC`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceError {
+    /// 1-based.
+    pub line: usize,
+    pub text: String,
+}
+
+impl fmt::Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "trace line {}: expected `METHOD_OFFSET PC`, got {:?}",
+            self.line, self.text
+        )
+    }
+}
+
+impl std::error::Error for TraceError {}
+
+/// Parse a trace: one `METHOD_OFFSET PC` pair per line, separated by
+/// whitespace or a comma. Blank lines and lines starting with `#` are
+/// skipped.
+pub fn parse_trace(text: &str) -> Result<Vec<(EntityId, u32)>, TraceError> {
+    let mut trace = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut parts = line
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|p| !p.is_empty());
+        let pair = match (parts.next(), parts.next(), parts.next()) {
+            (Some(method), Some(pc), None) => parse_offset(method).zip(parse_offset(pc)),
+            _ => None,
+        };
+        let Some((method, pc)) = pair else {
+            return Err(TraceError {
+                line: i + 1,
+                text: line.to_string(),
+            });
+        };
+        trace.push((method, pc.0));
+    }
+    Ok(trace)
+}
@@ -0,0 +1,19 @@
+//! Whole-file analyses over `.abc` bytecode.
+//!
+//! Where `abcd-decompiler` looks at one method at a time, the passes here
+//! compare methods across the whole file:
+//!
+//! - [`breakpoints`] — patch sites and fixups for inserting `debugger`.
+//! - [`clones`] — identical and near-identical method bodies.
+//! - [`constants`] — distinctive numeric constants and the methods loading
+//!   them.
+//! - [`corpus`] — instruction usage counted over many files, by file and
+//!   by format version.
+//! - [`coverage`] — executed instructions, lines and classes from a runtime
+//!   trace.
+
+pub mod breakpoints;
+pub mod clones;
+pub mod constants;
+pub mod corpus;
+pub mod coverage;
@@ -0,0 +1,210 @@
+use abcd_analysis::breakpoints::{self, Fixup, Target};
+use abcd_file::builder::CatchBlockDef;
+use abcd_file::{EntityId, File};
+use abcd_isa::{Imm, Label, encode, insn};
+use abcd_testgen::files::GlobalClass;
+
+/// `f`, with everything from the `jeqz` up to the `return` in a try block
+/// whose handler is that `return`:
+///
+/// ```text
+/// 0: ldai 1
+/// 1: jeqz 4
+/// 2: ldai 2
+/// 3: jmp 0
+/// 4: return
+/// ```
+///
+/// Returns the file, `f` and each instruction's offset.
+fn build() -> (File, EntityId, Vec<u32>) {
+    let (code, offsets) = encode(&[
+        insn::Ldai::new(Imm(1)),
+        insn::Jeqz::new(Label(4)),
+        insn::Ldai::new(Imm(2)),
+        insn::Jmp::new(Label(0)),
+        insn::Return::new(),
+    ])
+    .unwrap();
+    let mut global = GlobalClass::new();
+    let method = global.method("f", &code);
+    let b = &mut global.builder;
+    let c = b.create_code(1, 3, &code);
+    let catch = CatchBlockDef {
+        type_class: None,
+        handler_pc: offsets[4],
+        code_size: code.len() as u32 - offsets[4],
+    };
+    b.code_add_try_block(c, offsets[1], offsets[4] - offsets[1], &[catch]);
+    b.method_set_code(method, c);
+    let abc = global.open();
+    let class = abc.class_id_by_name("L_GLOBAL;").unwrap().unwrap();
+    let f = abc.class(class).unwrap().method_offsets()[0];
+    let mut offsets = offsets;
+    offsets.push(code.len() as u32);
+    (abc, f, offsets)
+}
+
+#[test]
+fn breakpoint_before_an_instruction_moves_what_follows() {
+    let (abc, f, pc) = build();
+    let sites = breakpoints::plan(&abc, f, Target::Pattern("ldai 2")).unwrap();
+    assert_eq!(sites.len(), 1);
+    let site = &sites[0];
+    assert_eq!(site.pc, pc[2]);
+    assert_eq!(site.instruction, "ldai 2");
+    assert_eq!(site.insert.len(), 1);
+    let data = abc.raw_data();
+    let at = |off: u32| data[off as usize];
+    let size = pc[5];
+    let mut jumps = Vec::new();
+    for fixup in &site.fixups {
+        match *fixup {
+            Fixup::Jump {
+                pc,
+                file_offset,
+                old,
+                new,
+                ..
+            } => {
+                assert_eq!(i32::from(at(file_offset) as i8), old);
+                jumps.push((pc, old, new));
+            }
+            Fixup::CodeSize { old, new, .. } => assert_eq!((old, new), (size, size + 1)),
+            Fixup::TryBlock { start, length, .. } => {
+                assert_eq!((start, length), (pc[1], pc[4] + 1 - pc[1]))
+            }
+            Fixup::Handler {
+                pc: handler, size, ..
+            } => {
+                assert_eq!((handler, size), (pc[4] + 1, 1))
+            }
+            Fixup::LineProgram { .. } => panic!("f has no debug info"),
+        }
+    }
+    let expected = [
+        (pc[1], (pc[4] - pc[1]) as i32, (pc[4] + 1 - pc[1]) as i32),
+        (pc[3], -(pc[3] as i32), -(pc[3] as i32) - 1),
+    ];
+    assert_eq!(jumps, expected);
+    assert_eq!(site.fixups.len(), 5);
+}
+
+#[test]
+fn jumps_to_the_site_reach_the_breakpoint() {
+    let (abc, f, pc) = build();
+    let site = breakpoints::plan(&abc, f, Target::Pattern("ldai 1"))
+        .unwrap()
+        .remove(0);
+    assert_eq!(site.pc, 0);
+    let jumps: Vec<(u32, i32, i32)> = site
+        .fixups
+        .iter()
+        .filter_map(|fixup| match *fixup {
+            Fixup::Jump { pc, old, new, .. } => Some((pc, old, new)),
+            _ => None,
+        })
+        .collect();
+    // `jeqz` and its target both move; `jmp 0` moves away from its target,
+    // which stays at the breakpoint.
+    assert_eq!(jumps, [(pc[3], -(pc[3] as i32), -(pc[3] as i32) - 1)]);
+}
+
+#[test]
+fn lines_need_a_line_number_table() {
+    let (abc, f, _) = build();
+    assert!(
+        breakpoints::plan(&abc, f, Target::Line(1))
+            .unwrap()
+            .is_empty()
+    );
+}
+
+/// `g`, straight-line code on lines 1, 2 and back to 1:
+///
+/// ```text
+/// 0: ldai 1    ; line 1
+/// 1: sta v0    ; line 1
+/// 2: ldai 2    ; line 2
+/// 3: return    ; line 1
+/// ```
+///
+/// Returns the file, `g` and each instruction's offset.
+#[cfg(feature = "debug-info")]
+fn build_with_lines() -> (File, EntityId, Vec<u32>) {
+    let (code, pc) = encode(&[
+        insn::Ldai::new(Imm(1)),
+        insn::Sta::new(abcd_isa::Reg(0)),
+        insn::Ldai::new(Imm(2)),
+        insn::Return::new(),
+    ])
+    .unwrap();
+    // Special opcodes (base 12, line base -4, line range 15) each add a
+    // row: pc and line advances packed into one byte.
+    let special = |pc: u32, line: i32| (12 + pc as i32 * 15 + line + 4) as u8;
+    let lnp = [
+        special(pc[2], 1),
+        special(pc[3] - pc[2], -1),
+        0x00, // END_SEQUENCE
+    ];
+    // Line start 1, no parameters, empty constant pool, program index.
+    let debug = [1, 0, 0, 0];
+    let mut global = GlobalClass::new();
+    let method = global.method("g", &code);
+    global
+        .builder
+        .method_set_raw_debug_info(method, &lnp, &debug)
+        .unwrap();
+    let abc = global.open();
+    let class = abc.class_id_by_name("L_GLOBAL;").unwrap().unwrap();
+    let g = abc.class(class).unwrap().method_offsets()[0];
+    (abc, g, pc)
+}
+
+#[cfg(feature = "debug-info")]
+#[test]
+fn a_line_breaks_where_each_of_its_runs_starts() {
+    let (abc, g, pc) = build_with_lines();
+    let lines: Vec<(u32, u32)> = abc
+        .debug_info()
+        .unwrap()
+        .line_table(g)
+        .iter()
+        .map(|e| (e.offset, e.line))
+        .collect();
+    assert_eq!(lines, [(0, 1), (pc[2], 2), (pc[3], 1)]);
+
+    let at = |line| -> Vec<u32> {
+        breakpoints::plan(&abc, g, Target::Line(line))
+            .unwrap()
+            .iter()
+            .map(|site| site.pc)
+            .collect()
+    };
+    assert_eq!(at(1), [0, pc[3]]);
+    assert_eq!(at(2), [pc[2]]);
+    assert_eq!(at(3), []);
+
+    let site = breakpoints::plan(&abc, g, Target::Line(2))
+        .unwrap()
+        .remove(0);
+    assert_eq!(site.instruction, "ldai 2");
+    assert!(site.fixups.iter().any(|fixup| matches!(
+        *fixup,
+        Fixup::LineProgram { pc, by } if pc == site.pc && by as usize == site.insert.len()
+    )));
+}
+
+#[test]
+fn jump_offsets_past_their_width_do_not_fit() {
+    let jump = |width, new| Fixup::Jump {
+        pc: 0,
+        file_offset: 0,
+        width,
+        old: 0,
+        new,
+    };
+    assert!(jump(8, 127).fits());
+    assert!(jump(8, -128).fits());
+    assert!(!jump(8, 128).fits());
+    assert!(jump(16, 128).fits());
+}
@@ -0,0 +1,86 @@
+use abcd_analysis::clones::{self, CloneKind, MethodBody};
+use abcd_file::{EntityId, File};
+use abcd_testgen::{CorpusSpec, Feature};
+
+fn body(off: u32, insns: &[&str]) -> MethodBody {
+    MethodBody {
+        method_off: EntityId(off),
+        name: format!("C.m{off:x}"),
+        insns: insns.iter().map(|s| s.to_string()).collect(),
+    }
+}
+
+/// `n` distinct instructions.
+fn program(n: usize) -> Vec<String> {
+    (0..n).map(|i| format!("ldai {i}")).collect()
+}
+
+fn refs(p: &[String]) -> Vec<&str> {
+    p.iter().map(String::as_str).collect()
+}
+
+#[test]
+fn identical_bodies_group_together() {
+    let p = program(30);
+    let groups = clones::group(vec![
+        body(0x10, &refs(&p)),
+        body(0x20, &["lda v0", "return"]),
+        body(0x30, &refs(&p)),
+    ]);
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].kind, CloneKind::Identical);
+    assert_eq!(groups[0].similarity, 1.0);
+    let offs: Vec<u32> = groups[0].methods.iter().map(|m| m.method_off.0).collect();
+    assert_eq!(offs, [0x10, 0x30]);
+}
+
+#[test]
+fn one_changed_instruction_is_similar() {
+    let p = program(100);
+    let mut q = p.clone();
+    q[50] = "ldundefined".into();
+    let groups = clones::group(vec![body(0x10, &refs(&p)), body(0x20, &refs(&q))]);
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].kind, CloneKind::Similar);
+    assert!(groups[0].similarity >= clones::SIMILARITY_THRESHOLD);
+    assert!(groups[0].similarity < 1.0);
+}
+
+#[test]
+fn unrelated_bodies_are_not_clones() {
+    let p = program(40);
+    let q: Vec<String> = (0..40).map(|i| format!("sta v{i}")).collect();
+    assert!(clones::group(vec![body(0x10, &refs(&p)), body(0x20, &refs(&q))]).is_empty());
+}
+
+#[test]
+fn larger_groups_come_first() {
+    let small = program(10);
+    let large = program(50);
+    let groups = clones::group(vec![
+        body(0x10, &refs(&small)),
+        body(0x20, &refs(&small)),
+        body(0x30, &refs(&large)),
+        body(0x40, &refs(&large)),
+    ]);
+    let lens: Vec<usize> = groups.iter().map(|g| g.methods[0].len).collect();
+    assert_eq!(lens, [50, 10]);
+}
+
+#[test]
+fn copies_of_a_generated_function_are_identical() {
+    let spec = CorpusSpec {
+        features: vec![Feature::TryCatch],
+        copies: 2,
+    };
+    let (_, bytes) = abcd_testgen::generate(&spec).pop().unwrap();
+    let abc = File::open(bytes).unwrap();
+    let groups = clones::find(&abc, 1);
+    let parse = groups
+        .iter()
+        .find(|g| g.methods[0].name.ends_with(".#*#parse"))
+        .unwrap();
+    assert_eq!(parse.kind, CloneKind::Identical);
+    let names: Vec<&str> = parse.methods.iter().map(|m| m.name.as_str()).collect();
+    assert_eq!(names, ["Ltry_catch;.#*#parse", "Ltry_catch_1;.#*#parse"]);
+}
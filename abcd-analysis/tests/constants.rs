@@ -0,0 +1,193 @@
+use abcd_analysis::constants::{self, Constant, LiteralEntry};
+use abcd_file::File;
+use abcd_file::builder::IndexDep;
+use abcd_file::literal::{LiteralArray, LiteralTag};
+use abcd_isa::{EntityId, Imm, encode, insn};
+use abcd_testgen::files::GlobalClass;
+
+/// `f` loads the TEA delta as an `i32` and as a double, plus a few
+/// everyday numbers; `g` loads the golden ratio.
+fn build() -> (File, Vec<u32>) {
+    let (f_code, offsets) = encode(&[
+        insn::Ldai::new(Imm(0x9e3779b9u32 as i32 as i64)),
+        insn::Ldai::new(Imm(0xffff)),
+        insn::Ldai::new(Imm(86_400_000)),
+        insn::Fldai::new(Imm(2654435769.0f64.to_bits() as i64)),
+        insn::Fldai::new(Imm(0.5f64.to_bits() as i64)),
+        insn::Returnundefined::new(),
+    ])
+    .unwrap();
+    let (g_code, _) = encode(&[
+        insn::Fldai::new(Imm(0.6180339887f64.to_bits() as i64)),
+        insn::Returnundefined::new(),
+    ])
+    .unwrap();
+    let mut global = GlobalClass::new();
+    global.method("f", &f_code);
+    global.method("g", &g_code);
+    (global.open(), offsets)
+}
+
+#[test]
+fn parse_reads_hex_as_i32_bits() {
+    assert_eq!(
+        Constant::parse("0x9e3779b9"),
+        Some(Constant::Int(-1640531527))
+    );
+    assert_eq!(
+        Constant::parse("2654435769"),
+        Some(Constant::Int(-1640531527))
+    );
+    assert_eq!(
+        Constant::parse("0x100000000"),
+        Some(Constant::Float(4294967296.0))
+    );
+    assert_eq!(
+        Constant::parse("-0x1fffffffffffff"),
+        Some(Constant::Float(-9007199254740991.0))
+    );
+    assert_eq!(Constant::parse("-7"), Some(Constant::Int(-7)));
+    assert_eq!(Constant::parse("0.5"), Some(Constant::Float(0.5)));
+    assert_eq!(Constant::parse("1e3"), Some(Constant::Float(1000.0)));
+    assert_eq!(Constant::parse("delta"), None);
+}
+
+#[test]
+fn ints_match_doubles_of_either_sign() {
+    let delta = Constant::Int(0x9e3779b9u32 as i32);
+    assert!(delta.matches(Constant::Float(2654435769.0)));
+    assert!(delta.matches(Constant::Float(-1640531527.0)));
+    assert!(!delta.matches(Constant::Float(2654435770.0)));
+    assert!(Constant::Float(0.5).matches(Constant::Float(0.5)));
+}
+
+#[test]
+fn everyday_numbers_are_not_distinctive() {
+    for c in [
+        Constant::Int(1),
+        Constant::Int(-1),
+        Constant::Int(0xffff),
+        Constant::Int(0x10000),
+        Constant::Int(0x7fffffff),
+        Constant::Int(86_400_000),
+        Constant::Float(0.5),
+        Constant::Float(1.25),
+        Constant::Float(4294967296.0),
+        Constant::Float(f64::NAN),
+    ] {
+        assert!(!c.is_distinctive(), "{c}");
+    }
+    for c in [
+        Constant::Int(0x67452301),
+        Constant::Int(0x9e3779b9u32 as i32),
+        Constant::Float(2654435769.0),
+        Constant::Float(0.6180339887),
+    ] {
+        assert!(c.is_distinctive(), "{c}");
+    }
+}
+
+#[test]
+fn index_groups_distinctive_loads() {
+    let (abc, _) = build();
+    let index = constants::index(&abc);
+    let listed: Vec<(String, Vec<&str>)> = index
+        .iter()
+        .map(|u| {
+            let names = u.sites.iter().map(|s| s.name.as_str()).collect();
+            (u.value.to_string(), names)
+        })
+        .collect();
+    assert_eq!(
+        listed,
+        [
+            ("0x9e3779b9 (-1640531527)".to_string(), vec!["L_GLOBAL;.f"]),
+            ("0.6180339887".to_string(), vec!["L_GLOBAL;.g"]),
+            ("2654435769.0".to_string(), vec!["L_GLOBAL;.f"]),
+        ]
+    );
+}
+
+#[test]
+fn find_reports_every_encoding_of_the_value() {
+    let (abc, offsets) = build();
+    let sites = constants::find(&abc, Constant::parse("0x9e3779b9").unwrap());
+    let found: Vec<u32> = sites.iter().map(|s| s.offset).collect();
+    assert_eq!(found, [offsets[0], offsets[3]]);
+    // Everyday values are still found when asked for.
+    let half = constants::find(&abc, Constant::Float(0.5));
+    assert_eq!(half.len(), 1);
+    assert_eq!(half[0].offset, offsets[4]);
+}
+
+/// `table` builds `[0x67452301, 0.5, [0xefcdab89]]` from a literal array,
+/// the inner array nested in the outer one.
+fn build_table() -> (File, u32, u32) {
+    let mut global = GlobalClass::new();
+    let table = global.method_without_code("table");
+    let b = &mut global.builder;
+    let inner = b.add_literal_array("inner").unwrap();
+    b.literal_array_add_u8(inner, LiteralTag::Integer as u8);
+    b.literal_array_add_u32(inner, 0xefcdab89);
+    let outer = b.add_literal_array("outer").unwrap();
+    b.literal_array_add_u8(outer, LiteralTag::Integer as u8);
+    b.literal_array_add_u32(outer, 0x67452301);
+    b.literal_array_add_u8(outer, LiteralTag::Double as u8);
+    b.literal_array_add_f64(outer, 0.5);
+    b.literal_array_add_u8(outer, LiteralTag::LiteralArray as u8);
+    b.literal_array_add_literalarray(outer, inner);
+    b.method_add_index_dependency(table, IndexDep::LiteralArray(outer));
+    let body = |id: u16| {
+        encode(&[
+            insn::Createarraywithbuffer::new(Imm(0), EntityId(id.into())),
+            insn::Return::new(),
+        ])
+        .unwrap()
+        .0
+    };
+    let code = b.create_code(0, 3, &body(0));
+    b.method_set_code(table, code);
+    b.finalize().unwrap();
+    let id = b
+        .method_index_of(table, IndexDep::LiteralArray(outer))
+        .unwrap();
+    b.code_set_instructions(code, &body(id));
+    let abc = global.open();
+    let arrays = abc.literal_array_offsets();
+    let len = |off: &EntityId| LiteralArray::read(&abc, *off).unwrap().entries.len();
+    let outer = arrays.iter().find(|off| len(off) == 3).unwrap().0;
+    let inner = arrays.iter().find(|off| len(off) == 1).unwrap().0;
+    (abc, outer, inner)
+}
+
+#[test]
+fn literal_array_numbers_are_indexed() {
+    let (abc, outer, inner) = build_table();
+    let index = constants::index(&abc);
+    let listed: Vec<(String, Vec<Option<LiteralEntry>>)> = index
+        .iter()
+        .map(|u| {
+            let entries = u.sites.iter().map(|s| s.entry).collect();
+            (u.value.to_string(), entries)
+        })
+        .collect();
+    let entry = |array_off: u32, index: u32| {
+        Some(LiteralEntry {
+            array_off: EntityId(array_off),
+            index,
+        })
+    };
+    assert_eq!(
+        listed,
+        [
+            ("0xefcdab89 (-271733879)".to_string(), vec![entry(inner, 0)]),
+            ("0x67452301 (1732584193)".to_string(), vec![entry(outer, 0)]),
+        ]
+    );
+    // Everyday values are there for `find`, at the loading instruction.
+    let half = constants::find(&abc, Constant::Float(0.5));
+    assert_eq!(half.len(), 1);
+    assert_eq!(half[0].name, "L_GLOBAL;.table");
+    assert_eq!(half[0].offset, 0);
+    assert_eq!(half[0].entry, entry(outer, 1));
+}
@@ -0,0 +1,62 @@
+use abcd_analysis::corpus;
+use abcd_file::File;
+use abcd_isa::{Bytecode, Reg, encode, insn};
+use abcd_testgen::files::GlobalClass;
+
+/// A file with one method running `program`.
+fn build(program: &[Bytecode]) -> File {
+    let mut global = GlobalClass::new();
+    global.method("f", &encode(program).unwrap().0);
+    global.open()
+}
+
+fn files() -> (File, File) {
+    let a = build(&[
+        insn::Lda::new(Reg(0)),
+        insn::Sta::new(Reg(0)),
+        insn::Lda::new(Reg(0)),
+        insn::Returnundefined::new(),
+    ]);
+    let b = build(&[insn::Ldundefined::new(), insn::Return::new()]);
+    (a, b)
+}
+
+#[test]
+fn counts_each_mnemonic_per_file() {
+    let (a, b) = files();
+    let usage = corpus::opcode_usage([("a", &a), ("b", &b)]);
+
+    let names: Vec<_> = usage.columns().iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, ["a", "b"]);
+    assert_eq!(usage.columns()[0].version, a.version());
+    assert_eq!(usage.count("lda", 0), 2);
+    assert_eq!(usage.count("lda", 1), 0);
+    assert_eq!(usage.count("return", 1), 1);
+    assert_eq!(usage.total("lda"), 2);
+    assert_eq!(usage.columns_using("lda"), 1);
+    assert_eq!(usage.total("jmp"), 0);
+    assert!(usage.mnemonics().all(|m| usage.total(m) > 0));
+}
+
+#[test]
+fn files_of_one_version_merge() {
+    let (a, b) = files();
+    let usage = corpus::opcode_usage([("a", &a), ("b", &b)]).by_version();
+    assert_eq!(usage.columns().len(), 1);
+    assert_eq!(usage.columns()[0].name, a.version().to_string());
+    assert_eq!(usage.count("lda", 0), 2);
+    assert_eq!(usage.count("return", 0), 1);
+}
+
+#[test]
+fn csv_has_a_row_per_used_mnemonic() {
+    let (a, b) = files();
+    let usage = corpus::opcode_usage([("a,1", &a), ("b", &b)]);
+    let mut out = Vec::new();
+    usage.write_csv(&mut out).unwrap();
+    let csv = String::from_utf8(out).unwrap();
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some("mnemonic,introduced_in,\"a,1\",b,total"));
+    assert!(csv.contains("\nlda,,2,0,2\n"), "{csv}");
+    assert_eq!(lines.count(), usage.mnemonics().count());
+}
@@ -0,0 +1,136 @@
+use abcd_analysis::coverage::{self, LineCoverage, MethodMap};
+use abcd_file::{EntityId, File};
+use abcd_testgen::{CorpusSpec, Feature};
+
+/// Class `0x10` with `m20` (instructions at 0, 2, 5; lines 1, 1, 2) and
+/// `m30` (one instruction, no line table); class `0x40` with `m50`.
+fn maps() -> Vec<MethodMap> {
+    let map = |class: u32, method: u32, insns: &[u32], code_size, lines: &[(u32, u32)]| MethodMap {
+        method_off: EntityId(method),
+        class_off: EntityId(class),
+        class_name: format!("C{class:x}"),
+        name: format!("m{method:x}"),
+        insns: insns.to_vec(),
+        code_size,
+        lines: lines.to_vec(),
+    };
+    vec![
+        map(0x10, 0x20, &[0, 2, 5], 6, &[(0, 1), (5, 2)]),
+        map(0x10, 0x30, &[0], 1, &[]),
+        map(0x40, 0x50, &[0, 1], 2, &[]),
+    ]
+}
+
+#[test]
+fn trace_marks_the_instruction_containing_each_pc() {
+    let cov = coverage::compute(maps(), &[(EntityId(0x20), 0), (EntityId(0x20), 3)]);
+    let m = cov.method(EntityId(0x20)).unwrap();
+    assert_eq!(m.is_covered(0), Some(true));
+    assert_eq!(m.is_covered(2), Some(true));
+    assert_eq!(m.is_covered(5), Some(false));
+    assert_eq!(m.is_covered(3), None);
+    assert_eq!((m.covered(), m.total()), (2, 3));
+    assert_eq!(cov.unmatched, 0);
+}
+
+#[test]
+fn unknown_methods_and_pcs_past_the_code_are_unmatched() {
+    let trace = [
+        (EntityId(0x99), 0),
+        (EntityId(0x20), 6),
+        (EntityId(0x30), 0),
+    ];
+    let cov = coverage::compute(maps(), &trace);
+    assert_eq!(cov.unmatched, 2);
+    assert_eq!(cov.method(EntityId(0x30)).unwrap().percent(), 100.0);
+}
+
+#[test]
+fn a_line_is_covered_when_any_of_its_instructions_ran() {
+    let cov = coverage::compute(maps(), &[(EntityId(0x20), 2)]);
+    let m = cov.method(EntityId(0x20)).unwrap();
+    assert_eq!(
+        m.lines(),
+        [
+            LineCoverage {
+                line: 1,
+                covered: true
+            },
+            LineCoverage {
+                line: 2,
+                covered: false
+            },
+        ]
+    );
+    assert_eq!(m.uncovered_lines(), [2]);
+    assert!(cov.method(EntityId(0x30)).unwrap().lines().is_empty());
+}
+
+#[test]
+fn classes_sum_their_methods() {
+    let cov = coverage::compute(maps(), &[(EntityId(0x20), 0), (EntityId(0x30), 0)]);
+    let classes = cov.classes();
+    let totals: Vec<(&str, usize, usize)> = classes
+        .iter()
+        .map(|c| (c.name.as_str(), c.covered, c.total))
+        .collect();
+    assert_eq!(totals, [("C10", 2, 4), ("C40", 0, 2)]);
+    assert_eq!(classes[0].percent(), 50.0);
+    assert_eq!(classes[1].percent(), 0.0);
+    assert_eq!(cov.class(EntityId(0x10)), Some(classes[0].clone()));
+    assert_eq!(cov.class(EntityId(0x20)), None);
+    assert_eq!(classes[0].ratio(), "2/4 (50.0%)");
+}
+
+#[test]
+fn line_ranges_collapse_runs() {
+    assert_eq!(coverage::line_ranges(&[3, 5, 6, 7, 10]), "3, 5-7, 10");
+    assert_eq!(coverage::line_ranges(&[]), "");
+}
+
+#[test]
+fn line_marks_group_runs_of_the_same_state() {
+    let line = |line, covered| LineCoverage { line, covered };
+    let lines = [
+        line(1, true),
+        line(3, true),
+        line(5, false),
+        line(7, true),
+        line(8, true),
+        line(9, true),
+    ];
+    assert_eq!(coverage::line_marks(&lines), "+1-3 -5 +7-9");
+    assert_eq!(coverage::line_marks(&[]), "");
+}
+
+#[test]
+fn trace_text_parses_hex_and_decimal() {
+    let text = "# method pc\n0x1a4 0\n\n420, 0x1c\n";
+    assert_eq!(
+        coverage::parse_trace(text).unwrap(),
+        [(EntityId(0x1a4), 0), (EntityId(420), 0x1c)]
+    );
+    let err = coverage::parse_trace("0x1a4 0\n0x1a4\n").unwrap_err();
+    assert_eq!(err.line, 2);
+}
+
+#[test]
+fn tracing_every_instruction_covers_a_generated_file() {
+    let spec = CorpusSpec {
+        features: vec![Feature::TryCatch],
+        copies: 1,
+    };
+    let (_, bytes) = abcd_testgen::generate(&spec).pop().unwrap();
+    let abc = File::open(bytes).unwrap();
+    let maps = coverage::method_maps(&abc);
+    let trace: Vec<(EntityId, u32)> = maps
+        .iter()
+        .flat_map(|m| m.insns.iter().map(|&pc| (m.method_off, pc)))
+        .collect();
+    let cov = coverage::apply(&abc, &trace);
+    assert!(!cov.methods().is_empty());
+    assert_eq!(cov.unmatched, 0);
+    for class in cov.classes() {
+        assert_eq!(class.percent(), 100.0, "{}", class.name);
+    }
+}
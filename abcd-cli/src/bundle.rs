@@ -0,0 +1,116 @@
+//! HarmonyOS bundle containers.
+//!
+//! Applications (`.hap`) and shared packages (`.hsp`) are zip archives; a
+//! static library (`.har`) is a gzipped tarball in npm layout, everything
+//! under `package/`. Bytecode builds of all three carry their compiled
+//! modules in `ets/modules.abc`, next to a manifest that names the module:
+//! `module.json` in a zip, `oh-package.json5` in a tarball.
+
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+use flate2::read::GzDecoder;
+
+use crate::package::json5_string_field;
+
+/// Where the compiled modules sit inside a container.
+const MODULES_ABC: &str = "ets/modules.abc";
+
+/// The bytecode of a container and the names other bundles import it by.
+pub(crate) struct Bundle {
+    /// `ets/modules.abc`, or the whole file for a bare `.abc`.
+    pub(crate) abc: Vec<u8>,
+    /// Module and package names from the manifests, most specific first.
+    pub(crate) names: Vec<String>,
+}
+
+impl Bundle {
+    /// Read the container at `path`, telling the format from its content.
+    pub(crate) fn open(path: &Path) -> io::Result<Self> {
+        let data = fs::read(path)?;
+        match data.get(..2) {
+            Some(b"PK") => Self::from_zip(data),
+            Some([0x1f, 0x8b]) => Self::from_har(&data),
+            _ => Ok(Bundle {
+                abc: data,
+                names: Vec::new(),
+            }),
+        }
+    }
+
+    fn from_zip(data: Vec<u8>) -> io::Result<Self> {
+        let mut archive = zip::ZipArchive::new(io::Cursor::new(data)).map_err(io::Error::other)?;
+        let mut read = |name: &str| -> io::Result<Option<Vec<u8>>> {
+            let mut entry = match archive.by_name(name) {
+                Ok(entry) => entry,
+                Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+                Err(e) => return Err(io::Error::other(e)),
+            };
+            let mut buf = Vec::new();
+            entry.read_to_end(&mut buf)?;
+            Ok(Some(buf))
+        };
+
+        let abc = read(MODULES_ABC)?.ok_or_else(|| missing(MODULES_ABC))?;
+        let mut names = Vec::new();
+        let module = read("module.json")?
+            .and_then(|json| serde_json::from_slice::<serde_json::Value>(&json).ok());
+        if let Some(name) = module
+            .as_ref()
+            .and_then(|m| m.pointer("/module/name"))
+            .and_then(|n| n.as_str())
+        {
+            names.push(name.to_string());
+        }
+        if let Some(name) = read("oh-package.json5")?
+            .and_then(|c| json5_string_field(&String::from_utf8_lossy(&c), "name"))
+        {
+            add_name(&mut names, name);
+        }
+        Ok(Bundle { abc, names })
+    }
+
+    fn from_har(data: &[u8]) -> io::Result<Self> {
+        let mut abc = None;
+        let mut names = Vec::new();
+        let mut archive = tar::Archive::new(GzDecoder::new(data));
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let path = entry.path()?.to_string_lossy().into_owned();
+            // npm tarballs put everything under one top-level directory,
+            // usually `package/`.
+            let Some((_, path)) = path.split_once('/') else {
+                continue;
+            };
+            if path == MODULES_ABC {
+                let mut buf = Vec::new();
+                entry.read_to_end(&mut buf)?;
+                abc = Some(buf);
+            } else if path == "oh-package.json5" {
+                let mut content = String::new();
+                entry.read_to_string(&mut content)?;
+                if let Some(name) = json5_string_field(&content, "name") {
+                    add_name(&mut names, name);
+                }
+            }
+        }
+        Ok(Bundle {
+            abc: abc.ok_or_else(|| missing(MODULES_ABC))?,
+            names,
+        })
+    }
+}
+
+fn add_name(names: &mut Vec<String>, name: String) {
+    if !name.is_empty() && !names.contains(&name) {
+        names.push(name);
+    }
+}
+
+fn missing(entry: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("no {entry} in bundle (source-only package?)"),
+    )
+}
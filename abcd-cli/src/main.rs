@@ -1,7 +1,28 @@
-use abcd_file::EntityId;
-use clap::{Parser, Subcommand};
+use abcd_analysis::coverage::{self, Coverage};
+use abcd_file::names::QualifiedName;
+use abcd_file::notes::{EntityNotes, NoteStore};
+use abcd_file::validation::{self, Finding, Level, ValidationReport};
+use abcd_file::{AnalysisObserver, EntityId};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::Write as _;
 use std::fs;
+use std::io::{self, BufWriter};
 use std::path::PathBuf;
+use std::sync::OnceLock;
+
+mod bundle;
+mod package;
+mod progress;
+mod report;
+#[cfg(feature = "selftest")]
+mod selftest;
+mod sources;
+mod status;
+mod watch;
+
+use sources::{ClassSource, SourceFiles};
 
 #[cfg(not(target_env = "msvc"))]
 #[global_allocator]
@@ -12,10 +33,42 @@ static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
 static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
 #[derive(Parser)]
-#[command(name = "abcd-rs", about = "ArkCompiler ABC bytecode decompiler")]
+#[command(
+    name = "abcd-rs",
+    about = "ArkCompiler ABC bytecode decompiler",
+    after_help = "Exit status: 0 clean, 1 findings (methods `verify` does not trust, or \
+                  anything --deny names), 2 errors."
+)]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// Exit with status 1 if the run reports any of these; repeatable or
+    /// comma-separated
+    #[arg(long, global = true, value_enum, value_delimiter = ',')]
+    deny: Vec<status::Deny>,
+    /// Parse and analyse in a child process with limited memory and CPU
+    /// time, and no way to start programs or open sockets, so a crafted
+    /// input that crashes the parser cannot take anything else down
+    #[arg(long, global = true)]
+    sandbox: bool,
+    /// When decompiling, follow references from a literal array to
+    /// another, or from a method to the methods it defines, at most this
+    /// many levels deep
+    #[arg(
+        long,
+        global = true,
+        value_name = "LEVELS",
+        default_value_t = abcd_decompiler::Budget::default().max_depth
+    )]
+    max_ref_depth: u32,
+    /// ... and to at most this many entities from any one starting point
+    #[arg(
+        long,
+        global = true,
+        value_name = "COUNT",
+        default_value_t = abcd_decompiler::Budget::default().max_nodes
+    )]
+    max_ref_count: u32,
 }
 
 #[derive(Subcommand)]
@@ -24,50 +77,528 @@ enum Commands {
     Disasm {
         /// Path to the .abc file
         input: PathBuf,
+        /// Listing format; `json` streams one JSON object per method
+        #[arg(long, value_enum, default_value_t = DisasmFormat::Text)]
+        format: DisasmFormat,
+        /// Keep running and print the listing again whenever the input or
+        /// its notes change
+        #[arg(long)]
+        watch: bool,
+        /// Runtime trace, one `METHOD_OFFSET PC` per line; the text listing
+        /// marks each instruction `+` if it ran and `-` if not
+        #[arg(long, value_name = "TRACE")]
+        coverage: Option<PathBuf>,
+        #[command(flatten)]
+        filter: RecordFilter,
+    },
+    /// Assemble an instruction listing, in the syntax `abcd_isa::asm`
+    /// reads, into method bytecode: each instruction with its offset and
+    /// bytes, or with --output the bytes alone. String and method
+    /// placeholders need a file to resolve against and are rejected
+    Asm {
+        /// Path to the listing
+        input: PathBuf,
+        /// Write the encoded code here instead of listing it
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Keep running and assemble again whenever the listing changes
+        #[arg(long)]
+        watch: bool,
     },
     /// Show ABC file header and metadata
     Info {
         /// Path to the .abc file
         input: PathBuf,
     },
+    /// Print the instruction set as JSON: every opcode's mnemonic, format
+    /// and operand layout, flags, exceptions, namespace and size
+    Isa,
     /// Decompile an ABC file to JavaScript
     Decompile {
-        /// Path to the .abc file
+        /// Path to the .abc file, or a .hap/.hsp/.har bundle
         input: PathBuf,
         /// Output directory (default: stdout)
         #[arg(short, long)]
         output: Option<PathBuf>,
+        /// Lay the output out as an npm-style package (package.json,
+        /// relative imports between modules); requires --output
+        #[arg(long, requires = "output")]
+        as_package: bool,
+        /// Shared package (.hsp or .har) the input imports from; its modules
+        /// are decompiled under oh_modules/ and imports into them resolved.
+        /// Repeatable; requires --as-package
+        #[arg(long, requires = "as_package")]
+        shared: Vec<PathBuf>,
+        /// Analysis database to cache results in; later runs on the same
+        /// file reuse them instead of decompiling again
+        #[arg(long)]
+        db: Option<PathBuf>,
+        /// Keep running and decompile again whenever the input, a shared
+        /// package or their notes change. Files under --output are only
+        /// rewritten when their content changes; with --db every build seen
+        /// stays cached, so going back to an earlier one is immediate
+        #[arg(long)]
+        watch: bool,
+        /// Runtime trace, one `METHOD_OFFSET PC` per line; each class is
+        /// preceded by how much of it ran and which source lines did not
+        #[arg(long, value_name = "TRACE")]
+        coverage: Option<PathBuf>,
+        /// Name registers and anonymous functions after a hash of their
+        /// code instead of their position, so output from two builds can
+        /// be compared with a plain diff
+        #[arg(long)]
+        stable_names: bool,
+        #[command(flatten)]
+        filter: RecordFilter,
     },
+    /// Rank methods by estimated bytecode cost
+    Stats {
+        /// Path to the .abc file
+        input: PathBuf,
+        /// Number of methods to list
+        #[arg(long, default_value_t = 20)]
+        top: usize,
+        #[command(flatten)]
+        filter: RecordFilter,
+    },
+    /// List methods with identical or near-identical bodies
+    Clones {
+        /// Path to the .abc file
+        input: PathBuf,
+        /// Ignore methods with fewer instructions
+        #[arg(long, default_value_t = 20)]
+        min_len: usize,
+    },
+    /// List distinctive numeric constants, such as hash and cipher magic
+    /// numbers, with the methods that load them
+    Constants {
+        /// Path to the .abc file, or a .hap/.hsp/.har bundle
+        input: PathBuf,
+        /// Only list the instructions loading this value (`0x9e3779b9`,
+        /// `-7`, `0.618`), whether it is distinctive or not
+        #[arg(long, value_name = "VALUE")]
+        find_const: Option<String>,
+    },
+    /// Print per-class, per-method and per-literal-array content hashes as
+    /// JSON, for pinpointing what changed between two builds
+    Manifest {
+        /// Path to the .abc file, or a .hap/.hsp/.har bundle
+        input: PathBuf,
+    },
+    /// List the methods that differ between two builds, matched by class and
+    /// method name, as `M`odified, `A`dded or `D`eleted
+    Diff {
+        /// The earlier build: an .abc file, or a .hap/.hsp/.har bundle
+        old: PathBuf,
+        /// The later build
+        new: PathBuf,
+        /// Also decompile every listed method into
+        /// `<output>/<class path>/<method>.old.js` and `.new.js`, for
+        /// reading the two versions side by side; added and deleted
+        /// methods only have the one side. Requires --output
+        #[arg(long, requires = "output")]
+        decompile_changed: bool,
+        /// Name registers and anonymous functions in the decompiled
+        /// methods after a hash of their code instead of their position,
+        /// so unchanged code reads the same on both sides
+        #[arg(long, requires = "decompile_changed")]
+        stable_names: bool,
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Print a digest of every method's code item, or with --allowlist list
+    /// only the methods whose digest is not on it and exit with status 1 if
+    /// there are any
+    Verify {
+        /// Path to the .abc file, or a .hap/.hsp/.har bundle
+        input: PathBuf,
+        /// Trusted digests in hex, one per line. Anything after the digest
+        /// and lines starting with `#` are ignored, so the output of a run
+        /// without --allowlist on a trusted build can be used as is
+        #[arg(long)]
+        allowlist: Option<PathBuf>,
+        #[arg(long, value_enum, default_value_t = DigestAlgo::Sha256)]
+        algo: DigestAlgo,
+        /// `sarif` prints a SARIF 2.1.0 log instead of digests: the methods
+        /// not on the allowlist (without one, every method as a note), plus
+        /// code that does not decode, that the decompiler cannot translate,
+        /// or whose ID operands name nothing
+        #[arg(long, value_enum, default_value_t = VerifyFormat::Text)]
+        format: VerifyFormat,
+    },
+    /// Write a static HTML report: summary, class tree with decompiled
+    /// sources, module graph, string search and findings
+    Report {
+        /// Path to the .abc file, or a .hap/.hsp/.har bundle
+        input: PathBuf,
+        /// Directory to write the site into
+        #[arg(short, long)]
+        output: PathBuf,
+        #[command(flatten)]
+        filter: RecordFilter,
+    },
+    /// Build a small file in memory, take it through parsing, disassembly,
+    /// decompilation and rewriting, and report what this build supports
+    /// and how long each stage took; exits with status 2 if any fails
+    #[cfg(feature = "selftest")]
+    Selftest {
+        #[arg(long, value_enum, default_value_t = SelftestFormat::Text)]
+        format: SelftestFormat,
+    },
+    /// List, add or remove analyst notes kept beside a file
+    /// (`<input>.notes.json`)
+    Notes {
+        /// Path to the .abc file, or a .hap/.hsp/.har bundle
+        input: PathBuf,
+        /// Attach a note to the entity at OFFSET (hex with 0x, or decimal)
+        #[arg(long, num_args = 2, value_names = ["OFFSET", "TEXT"])]
+        add: Option<Vec<String>>,
+        /// Tag the entity at OFFSET
+        #[arg(long, num_args = 2, value_names = ["OFFSET", "TAG"])]
+        tag: Option<Vec<String>>,
+        /// Remove every note and tag from the entity at OFFSET
+        #[arg(long, value_name = "OFFSET")]
+        remove: Option<String>,
+        /// Only list entities with this tag
+        #[arg(long, value_name = "TAG")]
+        with_tag: Option<String>,
+    },
+}
+
+/// Which records of a merged file to keep, by the parts of their
+/// `bundle&module/path&version` names.
+#[derive(Args, Clone, Default)]
+struct RecordFilter {
+    /// Only records of this bundle
+    #[arg(long)]
+    bundle: Option<String>,
+    /// Only records of this module
+    #[arg(long)]
+    module: Option<String>,
+}
+
+impl RecordFilter {
+    fn keeps(&self, record: &str) -> bool {
+        let name = QualifiedName::parse(record);
+        let matches = |want: &Option<String>, have: &Option<String>| want.is_none() || want == have;
+        matches(&self.bundle, &name.bundle) && matches(&self.module, &name.module)
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum DisasmFormat {
+    Text,
+    Json,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum VerifyFormat {
+    Text,
+    Sarif,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum SelftestFormat {
+    Text,
+    Json,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum DigestAlgo {
+    Crc32,
+    Sha256,
+}
+
+impl DigestAlgo {
+    /// Length of a digest in hex digits.
+    fn hex_len(self) -> usize {
+        match self {
+            DigestAlgo::Crc32 => 8,
+            DigestAlgo::Sha256 => 64,
+        }
+    }
 }
 
 fn main() {
-    env_logger::init();
+    status::init_logger();
     let cli = Cli::parse();
+    status::deny(&cli.deny);
+    BUDGET
+        .set(abcd_decompiler::Budget {
+            max_depth: cli.max_ref_depth,
+            max_nodes: cli.max_ref_count,
+        })
+        .expect("main sets the budget once");
+    if cli.sandbox {
+        run_sandboxed(cli.command);
+    }
+    run(cli.command);
+    std::process::exit(status::code());
+}
 
-    match cli.command {
-        Commands::Disasm { input } => cmd_disasm(&input),
+fn run(command: Commands) {
+    match command {
+        Commands::Disasm {
+            input,
+            format,
+            watch,
+            coverage,
+            filter,
+        } => {
+            if watch {
+                watch_disasm(&input, format, coverage.as_deref(), &filter)
+            } else {
+                cmd_disasm(&input, format, coverage.as_deref(), &filter)
+            }
+        }
+        Commands::Asm {
+            input,
+            output,
+            watch,
+        } => {
+            if watch {
+                watch_asm(&input, output.as_deref())
+            } else {
+                cmd_asm(&input, output.as_deref())
+            }
+        }
         Commands::Info { input } => cmd_info(&input),
-        Commands::Decompile { input, output } => cmd_decompile(&input, output.as_deref()),
+        Commands::Isa => cmd_isa(),
+        Commands::Decompile {
+            input,
+            output,
+            as_package,
+            shared,
+            db,
+            watch,
+            coverage,
+            stable_names,
+            filter,
+        } => {
+            if watch {
+                watch_decompile(
+                    &input,
+                    output.as_deref(),
+                    as_package,
+                    &shared,
+                    db.as_deref(),
+                    coverage.as_deref(),
+                    stable_names,
+                    &filter,
+                )
+            } else {
+                cmd_decompile(
+                    &input,
+                    output.as_deref(),
+                    as_package,
+                    &shared,
+                    db.as_deref(),
+                    coverage.as_deref(),
+                    stable_names,
+                    &filter,
+                )
+            }
+        }
+        Commands::Stats { input, top, filter } => cmd_stats(&input, top, &filter),
+        Commands::Clones { input, min_len } => cmd_clones(&input, min_len),
+        Commands::Constants { input, find_const } => cmd_constants(&input, find_const.as_deref()),
+        Commands::Manifest { input } => cmd_manifest(&input),
+        Commands::Diff {
+            old,
+            new,
+            decompile_changed,
+            stable_names,
+            output,
+        } => cmd_diff(
+            &old,
+            &new,
+            output.as_deref().filter(|_| decompile_changed),
+            stable_names,
+        ),
+        Commands::Verify {
+            input,
+            allowlist,
+            algo,
+            format,
+        } => cmd_verify(&input, allowlist.as_deref(), algo, format),
+        Commands::Report {
+            input,
+            output,
+            filter,
+        } => cmd_report(&input, &output, &filter),
+        #[cfg(feature = "selftest")]
+        Commands::Selftest { format } => cmd_selftest(format),
+        Commands::Notes {
+            input,
+            add,
+            tag,
+            remove,
+            with_tag,
+        } => cmd_notes(
+            &input,
+            add.as_deref(),
+            tag.as_deref(),
+            remove.as_deref(),
+            with_tag.as_deref(),
+        ),
+    }
+}
+
+/// Run `command` in a sandboxed child, streaming its output, and exit with
+/// its status. Files the command writes come back through the sandbox and
+/// are written here, under the directories the command names. A child the
+/// sandbox had to kill is an error.
+#[cfg(unix)]
+fn run_sandboxed(command: Commands) -> ! {
+    use abcd_file::sandbox::{self, Limits, SandboxError};
+
+    let dirs = match sandbox_outputs(&command) {
+        Ok(dirs) => dirs,
+        Err(option) => {
+            eprintln!("Error: {option} cannot be used with --sandbox");
+            std::process::exit(status::ERROR);
+        }
+    };
+    let ran = sandbox::isolate_with_outputs(&Limits::default(), &dirs, &mut io::stdout(), || {
+        run(command);
+        status::code()
+    });
+    match ran {
+        Ok(code) => std::process::exit(code),
+        Err(SandboxError::Killed(signal)) => {
+            eprintln!(
+                "Error: the sandboxed run was killed by signal {signal}; the input may be hostile"
+            );
+            std::process::exit(status::ERROR);
+        }
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(status::ERROR);
+        }
     }
 }
 
+/// The directories `command` writes files under, or the option that makes
+/// it do something a sandboxed child cannot: keep a database, or run
+/// forever.
+#[cfg(unix)]
+fn sandbox_outputs(command: &Commands) -> Result<Vec<PathBuf>, &'static str> {
+    let dirs = match command {
+        Commands::Disasm { watch: true, .. }
+        | Commands::Decompile { watch: true, .. }
+        | Commands::Asm { watch: true, .. } => {
+            return Err("--watch");
+        }
+        Commands::Decompile { db: Some(_), .. } => return Err("--db"),
+        Commands::Asm { output, .. } => output
+            .iter()
+            .filter_map(|path| path.parent())
+            .map(std::path::Path::to_path_buf)
+            .collect(),
+        Commands::Decompile { output, .. } | Commands::Diff { output, .. } => {
+            output.iter().cloned().collect()
+        }
+        Commands::Report { output, .. } => vec![output.clone()],
+        Commands::Notes { input, .. } => {
+            let sidecar = NoteStore::sidecar_path(input);
+            sidecar
+                .parent()
+                .map(std::path::Path::to_path_buf)
+                .into_iter()
+                .collect()
+        }
+        _ => Vec::new(),
+    };
+    Ok(dirs)
+}
+
+/// Write an output file, creating its directory; under `--sandbox` the
+/// parent process writes it.
+fn write_output(path: &std::path::Path, data: &[u8]) -> io::Result<()> {
+    #[cfg(unix)]
+    return abcd_file::sandbox::write_output(path, data);
+    #[cfg(not(unix))]
+    {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, data)
+    }
+}
+
+#[cfg(not(unix))]
+fn run_sandboxed(_: Commands) -> ! {
+    eprintln!("Error: --sandbox needs a Unix host");
+    std::process::exit(status::ERROR);
+}
+
+/// `--max-ref-depth` and `--max-ref-count`.
+static BUDGET: OnceLock<abcd_decompiler::Budget> = OnceLock::new();
+
+/// How far decompiling follows references; see [`abcd_decompiler::Budget`].
+fn budget() -> abcd_decompiler::Budget {
+    BUDGET.get().copied().unwrap_or_default()
+}
+
 // === StringResolver implementation for File ===
 
 struct AbcResolver<'a> {
     abc: &'a abcd_file::File,
+    debug: Option<&'a abcd_file::debug::DebugInfo<'a>>,
+    /// Offsets of every entity resolved so far, i.e. the method's xrefs.
+    xrefs: RefCell<BTreeSet<u32>>,
+    /// Names of anonymous functions under `--stable-names`.
+    functions: Option<&'a FunctionNames>,
 }
 
-impl<'a> abcd_decompiler::expr_recovery::StringResolver for AbcResolver<'a> {
-    fn resolve_string(&self, method_off: EntityId, entity_id: EntityId) -> Option<String> {
+/// Anonymous functions by method offset; see
+/// [`abcd_decompiler::stable_function_names`].
+type FunctionNames = HashMap<EntityId, String>;
+
+impl<'a> AbcResolver<'a> {
+    fn new(abc: &'a abcd_file::File, debug: Option<&'a abcd_file::debug::DebugInfo<'a>>) -> Self {
+        AbcResolver {
+            abc,
+            debug,
+            xrefs: RefCell::default(),
+            functions: None,
+        }
+    }
+
+    fn literal_array(
+        &self,
+        literal: &abcd_file::literal::Literal<'_>,
+        off: EntityId,
+    ) -> abcd_file::literal::LiteralArray {
+        let entries = literal
+            .enumerate_vals(off)
+            .iter()
+            .map(|v| {
+                let tag = v.tag.unwrap_or(abcd_file::literal::LiteralTag::TagValue);
+                let value = v.resolve_value(self.abc);
+                (tag, value)
+            })
+            .collect();
+        abcd_file::literal::LiteralArray { entries }
+    }
+
+    fn entity(&self, method_off: EntityId, entity_id: EntityId) -> Option<EntityId> {
         let off = self
             .abc
             .resolve_offset_by_index(method_off, entity_id.0 as u16)?;
-        self.abc.get_string(off).ok()
+        self.xrefs.borrow_mut().insert(off.0);
+        Some(off)
+    }
+}
+
+impl<'a> abcd_decompiler::expr_recovery::StringResolver for AbcResolver<'a> {
+    fn resolve_string(&self, method_off: EntityId, entity_id: EntityId) -> Option<String> {
+        let off = self.entity(method_off, entity_id)?;
+        Some(self.abc.get_string_lossy(off).into_owned())
     }
 
     fn resolve_offset(&self, method_off: EntityId, entity_id: EntityId) -> Option<EntityId> {
-        self.abc
-            .resolve_offset_by_index(method_off, entity_id.0 as u16)
+        self.entity(method_off, entity_id)
     }
 
     fn resolve_literal_array(
@@ -75,37 +606,60 @@ impl<'a> abcd_decompiler::expr_recovery::StringResolver for AbcResolver<'a> {
         method_off: EntityId,
         entity_id: EntityId,
     ) -> Option<abcd_file::literal::LiteralArray> {
-        let off = self
-            .abc
-            .resolve_offset_by_index(method_off, entity_id.0 as u16)?;
+        let literals = self.abc.literal_for(method_off).ok()?;
+        let off = literals.array_id(entity_id.0 as u16)?;
+        self.xrefs.borrow_mut().insert(off.0);
+        Some(self.literal_array(literals.literal(), off))
+    }
+
+    fn resolve_literal_array_at(&self, off: EntityId) -> Option<abcd_file::literal::LiteralArray> {
         let literal = self
             .abc
             .literal(EntityId(self.abc.literal_array_idx_off()))
             .ok()?;
-        let vals = literal.enumerate_vals(off);
-        let entries = vals
-            .iter()
-            .map(|v| {
-                let tag = v.tag.unwrap_or(abcd_file::literal::LiteralTag::TagValue);
-                let value = v.to_value();
-                (tag, value)
-            })
-            .collect();
-        Some(abcd_file::literal::LiteralArray { entries })
+        Some(self.literal_array(&literal, off))
     }
 
     fn get_string_at_offset(&self, offset: EntityId) -> Option<String> {
-        self.abc.get_string(offset).ok()
+        Some(self.abc.get_string_lossy(offset).into_owned())
+    }
+
+    fn file_version(&self) -> Option<abcd_isa::Version> {
+        Some(self.abc.version())
     }
 
     fn resolve_method_name(&self, method_off: EntityId, entity_id: EntityId) -> Option<String> {
-        let off = self
-            .abc
-            .resolve_offset_by_index(method_off, entity_id.0 as u16)?;
+        let off = self.entity(method_off, entity_id)?;
         let method = self.abc.method(off).ok()?;
-        let name = self.abc.get_string(method.name_off()).ok()?;
+        let name = self.abc.get_string_lossy(method.name_off()).into_owned();
         if name.is_empty() { None } else { Some(name) }
     }
+
+    fn function_name(&self, method_off: EntityId, entity_id: EntityId) -> Option<String> {
+        let off = self.entity(method_off, entity_id)?;
+        self.functions?.get(&off).cloned()
+    }
+
+    fn local_variables(
+        &self,
+        method_off: EntityId,
+    ) -> Vec<abcd_decompiler::expr_recovery::LocalVariable> {
+        let Some(debug) = self.debug else {
+            return Vec::new();
+        };
+        debug
+            .local_vars(method_off)
+            .into_iter()
+            .filter_map(|var| {
+                Some(abcd_decompiler::expr_recovery::LocalVariable {
+                    reg: u16::try_from(var.reg_number).ok()?,
+                    start_pc: var.start_offset,
+                    end_pc: var.end_offset,
+                    name: var.name,
+                })
+            })
+            .collect()
+    }
 }
 
 fn cmd_info(path: &PathBuf) {
@@ -113,9 +667,10 @@ fn cmd_info(path: &PathBuf) {
         Ok(f) => f,
         Err(e) => {
             eprintln!("Error: {e}");
-            std::process::exit(1);
+            std::process::exit(status::ERROR);
         }
     };
+    warn_on_version(&abc);
 
     let ver = abc.version();
     let checksum = abc.checksum();
@@ -125,6 +680,7 @@ fn cmd_info(path: &PathBuf) {
 
     println!("=== ABC File Info ===");
     println!("Version:          {ver}",);
+    println!("Type:             {}", abc.kind());
     println!("File size:        {} bytes", abc.file_size());
     println!("Checksum:         {checksum:#010x}");
     println!("Classes:          {}", abc.num_classes());
@@ -137,25 +693,741 @@ fn cmd_info(path: &PathBuf) {
     );
 }
 
-fn cmd_disasm(path: &PathBuf) {
-    let abc = match abcd_file::File::open_path(path.as_path()) {
+/// Static cost estimate of one method, for `stats`.
+struct MethodCost {
+    class: String,
+    name: String,
+    method_off: EntityId,
+    estimate: abcd_isa::CostEstimate,
+}
+
+fn cmd_stats(path: &std::path::Path, top: usize, filter: &RecordFilter) {
+    let abc = match abcd_file::File::open_path(path) {
         Ok(f) => f,
         Err(e) => {
             eprintln!("Error: {e}");
-            std::process::exit(1);
+            std::process::exit(status::ERROR);
         }
     };
+    warn_on_version(&abc);
+
+    let mut methods = Vec::new();
+    let mut categories = [0u32; abcd_isa::OpcodeCategory::ALL.len()];
+    for class_off in abc.class_offsets() {
+        if abc.is_external(class_off) {
+            continue;
+        }
+        let class_name = abc.get_string_lossy(class_off).into_owned();
+        if !filter.keeps(&class_name) {
+            continue;
+        }
+        let class = match abc.class(class_off) {
+            Ok(c) => c,
+            Err(e) => {
+                status::error(format_args!("# Error parsing class at {class_off}: {e}"));
+                continue;
+            }
+        };
+        for method_off in class.method_offsets() {
+            let Ok(method) = abc.method(method_off) else {
+                continue;
+            };
+            let Some(code) = method.code_off().and_then(|off| abc.code(off).ok()) else {
+                continue;
+            };
+            let decoded = abcd_decompiler::decode_method(code.instructions());
+            for insn in &decoded {
+                categories[insn.opcode.category() as usize] += 1;
+            }
+            methods.push(MethodCost {
+                class: class_name.clone(),
+                name: abc.get_string_lossy(method.name_off()).into_owned(),
+                method_off,
+                estimate: abcd_isa::CostEstimate::of(decoded.iter().map(|insn| &insn.opcode)),
+            });
+        }
+    }
+    methods.sort_by_key(|m| std::cmp::Reverse(m.estimate.total()));
+
+    let mut file_total = abcd_isa::CostEstimate::default();
+    for m in &methods {
+        file_total.merge(&m.estimate);
+    }
+    println!("Methods:          {}", methods.len());
+    println!("Estimated cost:   {}", file_total.total());
+    for class in abcd_isa::CostClass::ALL {
+        println!("  {:<16}{}", format!("{class}:"), file_total.count(class));
+    }
+    println!("Instruction mix:");
+    for category in abcd_isa::OpcodeCategory::ALL {
+        println!(
+            "  {:<16}{}",
+            format!("{category}:"),
+            categories[category as usize]
+        );
+    }
+    println!();
 
-    let ver = abc.version();
-    println!("# ABC Disassembly");
-    println!("# Version: {ver}",);
     println!(
+        "{:>8} {:>6} {:>6} {:>6} {:>6} {:>10}  METHOD",
+        "COST", "CALL", "ALLOC", "SUSP", "MOD", "OFFSET"
+    );
+    for m in methods.iter().take(top) {
+        let e = &m.estimate;
+        println!(
+            "{:>8} {:>6} {:>6} {:>6} {:>6} {:>#10x}  {}.{}",
+            e.total(),
+            e.count(abcd_isa::CostClass::Call),
+            e.count(abcd_isa::CostClass::Alloc),
+            e.count(abcd_isa::CostClass::Suspend),
+            e.count(abcd_isa::CostClass::Moderate),
+            m.method_off.0,
+            m.class,
+            m.name
+        );
+    }
+}
+
+fn cmd_clones(path: &std::path::Path, min_len: usize) {
+    let abc = match abcd_file::File::open_path(path) {
+        Ok(f) => f,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(status::ERROR);
+        }
+    };
+    warn_on_version(&abc);
+
+    for group in abcd_analysis::clones::find(&abc, min_len) {
+        let kind = match group.kind {
+            abcd_analysis::clones::CloneKind::Identical => "identical",
+            abcd_analysis::clones::CloneKind::Similar => "similar",
+        };
+        println!(
+            "# {} methods, {kind} ({:.0}%)",
+            group.methods.len(),
+            group.similarity * 100.0
+        );
+        for m in &group.methods {
+            println!("{:>#10x} {:>6}  {}", m.method_off.0, m.len, m.name);
+        }
+        println!();
+    }
+}
+
+fn cmd_constants(path: &std::path::Path, find_const: Option<&str>) {
+    use abcd_analysis::constants::{self, Constant, ConstantSite};
+
+    let (abc, _) = open_bundle(path);
+    let print = |site: &ConstantSite| match &site.entry {
+        Some(entry) => println!(
+            "{:>#10x} {:>#8x}  {} {entry}",
+            site.method_off.0, site.offset, site.name
+        ),
+        None => println!(
+            "{:>#10x} {:>#8x}  {}",
+            site.method_off.0, site.offset, site.name
+        ),
+    };
+
+    if let Some(query) = find_const {
+        let Some(value) = Constant::parse(query) else {
+            eprintln!("Error: {query:?} is not a number");
+            std::process::exit(status::ERROR);
+        };
+        for site in constants::find(&abc, value) {
+            print(&site);
+        }
+        return;
+    }
+
+    for uses in constants::index(&abc) {
+        println!("# {} ({} uses)", uses.value, uses.sites.len());
+        for site in &uses.sites {
+            print(site);
+        }
+        println!();
+    }
+}
+
+fn cmd_manifest(path: &std::path::Path) {
+    let (abc, _) = open_bundle(path);
+    match serde_json::to_string_pretty(&abc.manifest()) {
+        Ok(json) => println!("{json}"),
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(status::ERROR);
+        }
+    }
+}
+
+fn cmd_isa() {
+    match serde_json::to_string_pretty(&abcd_isa::export_metadata()) {
+        Ok(json) => println!("{json}"),
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(status::ERROR);
+        }
+    }
+}
+
+#[cfg(feature = "selftest")]
+fn cmd_selftest(format: SelftestFormat) {
+    let report = selftest::run();
+    let written = match format {
+        SelftestFormat::Text => report.write_text(io::stdout().lock()),
+        SelftestFormat::Json => {
+            serde_json::to_writer_pretty(io::stdout().lock(), &report.to_json())
+                .map(|()| println!())
+                .map_err(io::Error::from)
+        }
+    };
+    if let Err(e) = written {
+        eprintln!("Error: {e}");
+        std::process::exit(status::ERROR);
+    }
+    for (stage, e) in report.failures() {
+        status::error(format_args!("Error: selftest stage `{stage}` failed: {e}"));
+    }
+}
+
+fn cmd_diff(
+    old_path: &std::path::Path,
+    new_path: &std::path::Path,
+    decompile_to: Option<&std::path::Path>,
+    stable_names: bool,
+) {
+    use abcd_file::manifest::Change;
+
+    let (old, _) = open_bundle(old_path);
+    let (new, _) = open_bundle(new_path);
+    let diffs = old.manifest().diff_methods(&new.manifest());
+    for d in &diffs {
+        let tag = match d.change {
+            Change::Modified => 'M',
+            Change::Added => 'A',
+            Change::Removed => 'D',
+        };
+        println!("{tag}  {}.{}", d.class, d.name);
+    }
+
+    let Some(dir) = decompile_to else {
+        return;
+    };
+    for abc in [&old, &new] {
+        if abc.kind() == abcd_file::FileType::Static {
+            eprintln!("Error: --decompile-changed reads dynamic files only");
+            std::process::exit(status::ERROR);
+        }
+    }
+    let old_debug = old.debug_info().ok();
+    let new_debug = new.debug_info().ok();
+    let old_functions = stable_names.then(|| abcd_decompiler::stable_function_names(&old));
+    let new_functions = stable_names.then(|| abcd_decompiler::stable_function_names(&new));
+    for (d, stem) in diffs.iter().zip(diff_file_stems(&diffs)) {
+        let sides = [
+            (
+                &old,
+                old_debug.as_ref(),
+                old_functions.as_ref(),
+                d.old_offset,
+                "old",
+            ),
+            (
+                &new,
+                new_debug.as_ref(),
+                new_functions.as_ref(),
+                d.new_offset,
+                "new",
+            ),
+        ];
+        for (abc, debug, functions, offset, side) in sides {
+            let Some(offset) = offset else {
+                continue;
+            };
+            let mut js = String::new();
+            if let Err(e) =
+                decompile_method_to_string(abc, debug, None, EntityId(offset), functions, &mut js)
+            {
+                status::warn(e);
+            }
+            let out_path = dir.join(format!("{}.{side}.js", stem.display()));
+            write_output(&out_path, js.as_bytes()).unwrap_or_else(|e| {
+                status::error(format_args!("Error writing {}: {e}", out_path.display()));
+            });
+        }
+    }
+}
+
+/// Where `cmd_diff` writes each method, relative to the output directory
+/// and without the `.old.js`/`.new.js` suffix: `<class path>/<method>`,
+/// with the method's offset appended to names that would otherwise share
+/// a file, such as overloads.
+fn diff_file_stems(diffs: &[abcd_file::manifest::MethodDiff]) -> Vec<PathBuf> {
+    let stems: Vec<(PathBuf, String)> = diffs
+        .iter()
+        .map(|d| {
+            let class_dir = class_name_to_path(&d.class).with_extension("");
+            (class_dir, sanitize_filename(&d.name))
+        })
+        .collect();
+    let mut uses: HashMap<&(PathBuf, String), usize> = HashMap::new();
+    for stem in &stems {
+        *uses.entry(stem).or_default() += 1;
+    }
+    stems
+        .iter()
+        .zip(diffs)
+        .map(|(stem @ (class_dir, method), d)| {
+            let method = match uses[stem] {
+                1 => method.clone(),
+                _ => {
+                    let offset = d.old_offset.or(d.new_offset).unwrap_or_default();
+                    format!("{method}@{offset:#x}")
+                }
+            };
+            class_dir.join(method)
+        })
+        .collect()
+}
+
+fn cmd_verify(
+    path: &std::path::Path,
+    allowlist: Option<&std::path::Path>,
+    algo: DigestAlgo,
+    format: VerifyFormat,
+) {
+    let allowed: Option<HashSet<String>> = allowlist.map(|list| {
+        let parsed = fs::read_to_string(list)
+            .map_err(|e| e.to_string())
+            .and_then(|text| read_allowlist(&text, algo));
+        parsed.unwrap_or_else(|e| {
+            eprintln!("Error reading {}: {e}", list.display());
+            std::process::exit(status::ERROR);
+        })
+    });
+
+    let (abc, _) = open_bundle(path);
+    let num_classes = abc
+        .class_offsets()
+        .into_iter()
+        .filter(|&off| !abc.is_external(off))
+        .count();
+    let mut progress = progress::Progress::new(num_classes);
+    let (flagged, checked) = if format == VerifyFormat::Sarif {
+        let (report, checked) = verify_report(
+            &abc,
+            path.display().to_string(),
+            algo,
+            allowed.as_ref(),
+            &mut progress,
+        );
+        drop(progress);
+        println!("{:#}", report.to_sarif());
+        let flagged = report
+            .findings
+            .iter()
+            .filter(|f| f.rule == validation::UNTRUSTED_METHOD && f.level == Level::Error)
+            .count();
+        (flagged, checked)
+    } else {
+        let digests = hex_digests(&abc, algo, &mut progress);
+        let checked = digests.len();
+        let mut flagged = 0usize;
+        for (method_off, name, digest) in untrusted(digests, allowed.as_ref()) {
+            flagged += 1;
+            progress.clear();
+            println!("{digest}  {:#x}  {name}", method_off.0);
+        }
+        (flagged, checked)
+    };
+    if allowed.is_some() {
+        eprintln!("{flagged} of {checked} methods not on the allowlist");
+        if flagged > 0 {
+            status::flag();
+        }
+    }
+}
+
+/// `verify --format sarif`: every method checked against `allowed`, the
+/// ones on it left out, then the code checks of [`check_code`]. Without an
+/// allowlist each method is a note giving its digest. Also returns the
+/// number of methods checked.
+fn verify_report(
+    abc: &abcd_file::File,
+    artifact: String,
+    algo: DigestAlgo,
+    allowed: Option<&HashSet<String>>,
+    observer: &mut dyn AnalysisObserver,
+) -> (ValidationReport, usize) {
+    use abcd_file::digest::{Crc32, MethodDigest, Sha256, method_digests};
+
+    fn check<const N: usize>(
+        report: &mut ValidationReport,
+        digests: &[MethodDigest<N>],
+        allowed: Option<&HashSet<String>>,
+    ) -> usize {
+        let trusted = |digest: &[u8; N]| allowed.is_some_and(|set| set.contains(&hex(digest)));
+        report.check_digests(
+            digests,
+            allowed.map(|_| &trusted as &dyn Fn(&[u8; N]) -> bool),
+        );
+        digests.len()
+    }
+
+    let mut report = ValidationReport::new(artifact);
+    let checked = match algo {
+        DigestAlgo::Crc32 => {
+            let digests = method_digests(abc, Crc32::new, observer);
+            check(&mut report, &digests, allowed)
+        }
+        DigestAlgo::Sha256 => {
+            let digests = method_digests(abc, Sha256::default, observer);
+            check(&mut report, &digests, allowed)
+        }
+    };
+    check_code(abc, &RecordFilter::default(), true, Some(&mut report));
+    (report, checked)
+}
+
+/// Every method's digest under `algo`, as `(method, name, hex digest)`.
+fn hex_digests(
+    abc: &abcd_file::File,
+    algo: DigestAlgo,
+    observer: &mut dyn AnalysisObserver,
+) -> Vec<(EntityId, String, String)> {
+    use abcd_file::digest::{Crc32, Sha256, method_digests};
+
+    match algo {
+        DigestAlgo::Crc32 => method_digests(abc, Crc32::new, observer)
+            .into_iter()
+            .map(|d| (d.method, d.name, hex(&d.digest)))
+            .collect(),
+        DigestAlgo::Sha256 => method_digests(abc, Sha256::default, observer)
+            .into_iter()
+            .map(|d| (d.method, d.name, hex(&d.digest)))
+            .collect(),
+    }
+}
+
+/// The digests not on `allowed`; all of them without an allowlist.
+fn untrusted(
+    digests: Vec<(EntityId, String, String)>,
+    allowed: Option<&HashSet<String>>,
+) -> impl Iterator<Item = (EntityId, String, String)> {
+    digests
+        .into_iter()
+        .filter(move |(_, _, digest)| !allowed.is_some_and(|set| set.contains(digest)))
+}
+
+/// Parse an allowlist: a hex digest at the start of each line, `#`
+/// comments and blank lines skipped. A digest of another length than
+/// `algo` gives is an error, so a list made with the other algorithm is
+/// not silently taken as matching nothing.
+fn read_allowlist(text: &str, algo: DigestAlgo) -> Result<HashSet<String>, String> {
+    let mut allowed = HashSet::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.starts_with('#') {
+            continue;
+        }
+        let Some(digest) = line.split_whitespace().next() else {
+            continue;
+        };
+        if digest.len() != algo.hex_len() || !digest.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(format!(
+                "line {}: `{digest}` is not a {} digest ({} hex digits)",
+                i + 1,
+                algo.to_possible_value().unwrap().get_name(),
+                algo.hex_len()
+            ));
+        }
+        allowed.insert(digest.to_ascii_lowercase());
+    }
+    Ok(allowed)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn cmd_report(path: &std::path::Path, output_dir: &std::path::Path, filter: &RecordFilter) {
+    let (abc, _) = open_bundle(path);
+    check_code(&abc, filter, true, None);
+    if let Err(e) = report::write(&abc, path, output_dir, filter) {
+        eprintln!("Error writing report to {}: {e}", output_dir.display());
+        std::process::exit(status::ERROR);
+    }
+    eprintln!("wrote {}", output_dir.join("index.html").display());
+}
+
+fn cmd_notes(
+    path: &std::path::Path,
+    add: Option<&[String]>,
+    tag: Option<&[String]>,
+    remove: Option<&str>,
+    with_tag: Option<&str>,
+) {
+    let sidecar = NoteStore::sidecar_path(path);
+    let mut notes = NoteStore::load(&sidecar).unwrap_or_else(|e| {
+        eprintln!("Error reading {}: {e}", sidecar.display());
+        std::process::exit(status::ERROR);
+    });
+    let offset = |s: &str| {
+        abcd_file::notes::parse_offset(s).unwrap_or_else(|| {
+            eprintln!("Error: invalid entity offset {s:?}");
+            std::process::exit(status::ERROR);
+        })
+    };
+
+    // Check the input before anything is saved beside it: notes go on its
+    // classes and methods only, and tags are single words.
+    let abc = open_bundle(path).0;
+    let names = entity_names(&abc);
+    let entity = |s: &str| {
+        let id = offset(s);
+        if !names.contains_key(&id.0) {
+            eprintln!(
+                "Error: no class or method at {:#x} in {}",
+                id.0,
+                path.display()
+            );
+            std::process::exit(status::ERROR);
+        }
+        id
+    };
+
+    let mut changed = false;
+    if let Some([off, text]) = add {
+        if text.trim().is_empty() {
+            eprintln!("Error: the note is empty");
+            std::process::exit(status::ERROR);
+        }
+        notes.add_note(entity(off), text);
+        changed = true;
+    }
+    if let Some([off, name]) = tag {
+        if name.is_empty() || name.contains(|c: char| c.is_whitespace() || c == ',') {
+            eprintln!("Error: invalid tag {name:?}: tags are one word without commas");
+            std::process::exit(status::ERROR);
+        }
+        changed |= notes.add_tag(entity(off), name);
+    }
+    if let Some(off) = remove {
+        changed |= notes.remove(offset(off)).is_some();
+    }
+    if changed {
+        notes.save(&sidecar).unwrap_or_else(|e| {
+            eprintln!("Error writing {}: {e}", sidecar.display());
+            std::process::exit(status::ERROR);
+        });
+    }
+
+    for (id, entry) in notes.iter() {
+        if with_tag.is_some_and(|t| !entry.tags.contains(t)) {
+            continue;
+        }
+        let what = names.get(&id.0).map_or("", String::as_str);
+        println!("{:#010x}  {what}", id.0);
+        print!("{}", note_lines(entry, "    "));
+    }
+}
+
+/// Readable names for the classes and methods of `abc`, by offset.
+fn entity_names(abc: &abcd_file::File) -> HashMap<u32, String> {
+    let mut names = HashMap::new();
+    for class_off in abc.class_offsets() {
+        if abc.is_external(class_off) {
+            continue;
+        }
+        let Ok(class) = abc.class(class_off) else {
+            continue;
+        };
+        let class_name = abc.get_string_lossy(class_off).into_owned();
+        for method_off in class.method_offsets() {
+            let method_name = abc.method_name_lossy(method_off).into_owned();
+            names.insert(method_off.0, format!("method {class_name}.{method_name}"));
+        }
+        names.insert(class_off.0, format!("class {class_name}"));
+    }
+    names
+}
+
+/// The sidecar notes for the file at `path`. One that cannot be read is
+/// reported and skipped; notes never stop a listing.
+fn load_notes(path: &std::path::Path) -> NoteStore {
+    NoteStore::load_for(path).unwrap_or_else(|e| {
+        status::warn(format_args!(
+            "ignoring {}: {e}",
+            NoteStore::sidecar_path(path).display()
+        ));
+        NoteStore::default()
+    })
+}
+
+/// Characters that end a line in JavaScript, and with it a `//` comment.
+const LINE_TERMINATORS: [char; 4] = ['\n', '\r', '\u{2028}', '\u{2029}'];
+
+/// `text` with its line terminators escaped, to keep it inside a one-line
+/// comment.
+fn one_line(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\n' => "\\n".to_string(),
+            '\r' => "\\r".to_string(),
+            '\u{2028}' | '\u{2029}' => c.escape_unicode().to_string(),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+/// The notes, a multi-line note continued on lines of its own, then the
+/// tags, each line starting with `prefix`.
+fn note_lines(notes: &EntityNotes, prefix: &str) -> String {
+    let mut out = String::new();
+    for note in &notes.notes {
+        let mut lines = note.split(LINE_TERMINATORS);
+        let _ = writeln!(out, "{prefix}note: {}", lines.next().unwrap_or(""));
+        for line in lines {
+            let _ = writeln!(out, "{prefix}      {line}");
+        }
+    }
+    if !notes.tags.is_empty() {
+        let tags: Vec<String> = notes.tags.iter().map(|t| one_line(t)).collect();
+        let _ = writeln!(out, "{prefix}tags: {}", tags.join(", "));
+    }
+    out
+}
+
+/// The runtime trace at `trace` laid over `abc`.
+fn load_coverage(abc: &abcd_file::File, trace: &std::path::Path) -> Result<Coverage, String> {
+    let text =
+        fs::read_to_string(trace).map_err(|e| format!("Error reading {}: {e}", trace.display()))?;
+    let entries =
+        coverage::parse_trace(&text).map_err(|e| format!("Error: {}: {e}", trace.display()))?;
+    let coverage = coverage::apply(abc, &entries);
+    if coverage.unmatched > 0 {
+        status::warn(format_args!(
+            "{} trace entries match no instruction",
+            coverage.unmatched
+        ));
+    }
+    Ok(coverage)
+}
+
+fn cmd_asm(path: &std::path::Path, output: Option<&std::path::Path>) {
+    if let Err(e) = asm(path, output) {
+        eprintln!("{e}");
+        std::process::exit(status::ERROR);
+    }
+}
+
+/// [`cmd_asm`], with the error message instead of exiting.
+fn asm(path: &std::path::Path, output: Option<&std::path::Path>) -> Result<(), String> {
+    let source =
+        fs::read_to_string(path).map_err(|e| format!("Error reading {}: {e}", path.display()))?;
+    let program =
+        abcd_isa::asm::assemble(&source).map_err(|e| format!("Error: {}: {e}", path.display()))?;
+    let (code, offsets) = program
+        .emitter
+        .build()
+        .map_err(|e| format!("Error: {}: {e}", path.display()))?;
+    if let Some(out_path) = output {
+        return write_output(out_path, &code)
+            .map_err(|e| format!("Error writing {}: {e}", out_path.display()));
+    }
+    let mut out = BufWriter::new(io::stdout().lock());
+    let written = write_asm_listing(program.emitter.instructions(), &code, &offsets, &mut out)
+        .and_then(|()| io::Write::flush(&mut out));
+    match written {
+        Err(e) if e.kind() != io::ErrorKind::BrokenPipe => Err(format!("Error: {e}")),
+        _ => Ok(()),
+    }
+}
+
+/// One line per instruction: offset, encoded bytes, instruction.
+fn write_asm_listing(
+    instructions: &[abcd_isa::Bytecode],
+    code: &[u8],
+    offsets: &[u32],
+    mut out: impl io::Write,
+) -> io::Result<()> {
+    for (i, insn) in instructions.iter().enumerate() {
+        let start = offsets[i] as usize;
+        let end = offsets.get(i + 1).map_or(code.len(), |&o| o as usize);
+        writeln!(out, "{start:04x}: {:<20} {insn}", hex(&code[start..end]))?;
+    }
+    Ok(())
+}
+
+fn cmd_disasm(
+    path: &PathBuf,
+    format: DisasmFormat,
+    trace: Option<&std::path::Path>,
+    filter: &RecordFilter,
+) {
+    if let Err(e) = disasm(path, format, trace, filter) {
+        eprintln!("{e}");
+        std::process::exit(status::ERROR);
+    }
+}
+
+/// [`cmd_disasm`], with the error message instead of exiting.
+fn disasm(
+    path: &PathBuf,
+    format: DisasmFormat,
+    trace: Option<&std::path::Path>,
+    filter: &RecordFilter,
+) -> Result<(), String> {
+    let abc = abcd_file::File::open_path(path.as_path()).map_err(|e| format!("Error: {e}"))?;
+    warn_on_version(&abc);
+    let is_static = abc.kind() == abcd_file::FileType::Static;
+    if is_static && matches!(format, DisasmFormat::Json) {
+        return Err(format!(
+            "Error: JSON disassembly reads dynamic files only; {} is static",
+            path.display()
+        ));
+    }
+    if !is_static {
+        check_code(&abc, filter, false, None);
+    }
+    let coverage = trace.map(|trace| load_coverage(&abc, trace)).transpose()?;
+
+    let out = BufWriter::new(io::stdout().lock());
+    let written = match format {
+        DisasmFormat::Text if is_static => abcd_file::static_ir::write(&abc, out),
+        DisasmFormat::Json => abcd_decompiler::disasm::DisasmStream::new(&abc)
+            .retain_classes(|name| filter.keeps(name))
+            .write_ndjson(out),
+        DisasmFormat::Text => write_disasm(&abc, &load_notes(path), coverage.as_ref(), filter, out),
+    };
+    match written {
+        // A closed pipe (`| head`) is not an error worth reporting.
+        Err(e) if e.kind() != io::ErrorKind::BrokenPipe => Err(format!("Error: {e}")),
+        _ => Ok(()),
+    }
+}
+
+fn write_disasm(
+    abc: &abcd_file::File,
+    notes: &NoteStore,
+    coverage: Option<&Coverage>,
+    filter: &RecordFilter,
+    mut out: impl io::Write,
+) -> io::Result<()> {
+    writeln!(out, "# ABC Disassembly")?;
+    writeln!(out, "# Version: {}", abc.version())?;
+    writeln!(
+        out,
         "# Classes: {}, Literal arrays: {}",
         abc.num_classes(),
         abc.num_literal_arrays()
-    );
-    println!();
+    )?;
+    writeln!(out)?;
 
+    // One line buffer for the whole file; formatting an instruction into it
+    // reuses its capacity instead of allocating a String per instruction.
+    let mut line = String::new();
     for class_off in abc.class_offsets() {
         if abc.is_external(class_off) {
             continue;
@@ -164,97 +1436,170 @@ fn cmd_disasm(path: &PathBuf) {
         let class = match abc.class(class_off) {
             Ok(c) => c,
             Err(e) => {
-                eprintln!("# Error parsing class at {class_off}: {e}");
+                status::error(format_args!("# Error parsing class at {class_off}: {e}"));
                 continue;
             }
         };
 
-        let class_name = abc
-            .get_string(class_off)
-            .unwrap_or_else(|_| format!("<{class_off}>"));
+        let class_name = abc.get_string_lossy(class_off).into_owned();
+        if !filter.keeps(&class_name) {
+            continue;
+        }
         let source_file = class
             .source_file_off()
-            .and_then(|off| abc.get_string(off).ok());
+            .map(|off| abc.get_string_lossy(off).into_owned());
 
-        println!("# ============================================");
-        println!("# Class: {class_name}");
+        writeln!(out, "# ============================================")?;
+        writeln!(out, "# Class: {class_name}")?;
         if let Some(ref sf) = source_file {
-            println!("# Source: {sf}");
+            writeln!(out, "# Source: {sf}")?;
         }
-        println!(
+        writeln!(
+            out,
             "# Methods: {}, Fields: {}",
             class.num_methods(),
             class.num_fields()
-        );
-        println!();
+        )?;
+        if let Some(class) = coverage.and_then(|c| c.class(class_off)) {
+            writeln!(out, "# Coverage: {}", class.ratio())?;
+        }
+        if let Some(n) = notes.get(class_off) {
+            out.write_all(note_lines(n, "# ").as_bytes())?;
+        }
+        writeln!(out)?;
 
         for method_off in class.method_offsets() {
-            disasm_method(&abc, method_off);
+            disasm_method(abc, notes, coverage, method_off, &mut out, &mut line)?;
         }
     }
+    out.flush()
 }
 
-fn disasm_method(abc: &abcd_file::File, method_off: EntityId) {
+fn disasm_method(
+    abc: &abcd_file::File,
+    notes: &NoteStore,
+    coverage: Option<&Coverage>,
+    method_off: EntityId,
+    out: &mut impl io::Write,
+    line: &mut String,
+) -> io::Result<()> {
     let method = match abc.method(method_off) {
         Ok(m) => m,
         Err(e) => {
-            eprintln!("# Error parsing method at {method_off}: {e}");
-            return;
+            status::error(format_args!("# Error parsing method at {method_off}: {e}"));
+            return Ok(());
         }
     };
 
-    let method_name = abc
-        .get_string(method.name_off())
-        .unwrap_or_else(|_| format!("<{method_off}>"));
-    println!(".function {method_name} {{");
+    let method_name = abc.get_string_lossy(method.name_off()).into_owned();
+    writeln!(out, ".function {method_name} {{")?;
+    if let Some(n) = notes.get(method_off) {
+        out.write_all(note_lines(n, "    # ").as_bytes())?;
+    }
 
     let Some(code_off) = method.code_off() else {
-        println!("    # (no code - native or abstract)");
-        println!("}}");
-        println!();
-        return;
+        writeln!(out, "    # (no code - native or abstract)")?;
+        if let Some(n) = method.expected_arity() {
+            writeln!(out, "    # params: {n}")?;
+        }
+        return writeln!(out, "}}\n");
     };
 
     let code = match abc.code(code_off) {
         Ok(c) => c,
         Err(e) => {
-            eprintln!("    # Error parsing code at {code_off}: {e}");
-            println!("}}");
-            println!();
-            return;
+            status::error(format_args!("    # Error parsing code at {code_off}: {e}"));
+            return writeln!(out, "}}\n");
         }
     };
 
     let instructions = code.instructions();
-    println!(
+    write!(
+        out,
         "    # vregs: {}, args: {}, code_size: {}",
         code.num_vregs(),
         code.num_args(),
         instructions.len()
-    );
+    )?;
+    match method.profile().ic_slots {
+        Some(n) => writeln!(out, ", ic_slots: {n}")?,
+        None => writeln!(out)?,
+    }
+    let covered = coverage.and_then(|c| c.method(method_off));
+    if let Some(m) = covered {
+        writeln!(out, "    # coverage: {}", m.ratio())?;
+    }
 
-    let decoded = abcd_decompiler::decode_method(instructions);
+    let ids = MethodIds { abc, method_off };
+    let decoded = match abcd_decompiler::decode_method_in(abc, instructions) {
+        Ok(d) => d,
+        Err(e) => {
+            status::error(format_args!("    # Error decoding code at {code_off}: {e}"));
+            return writeln!(out, "}}\n");
+        }
+    };
     for insn in &decoded {
-        println!("    {:#06x}  {}", insn.offset, insn.opcode);
+        line.clear();
+        let mark = match covered.and_then(|m| m.is_covered(insn.offset)) {
+            Some(true) => '+',
+            Some(false) => '-',
+            None => ' ',
+        };
+        let _ = write!(line, "  {mark} {:#06x}  ", insn.offset);
+        let _ = write!(line, "{}", insn.opcode.format_with(&ids));
+        line.push('\n');
+        out.write_all(line.as_bytes())?;
     }
 
     for tb in &code.try_blocks() {
-        println!(
+        writeln!(
+            out,
             "    # try [{:#x}..{:#x}]",
             tb.start_pc,
             tb.start_pc + tb.length
-        );
+        )?;
         for cb in &tb.catches {
             if cb.type_idx == 0 {
-                println!("    #   catch_all -> {:#x}", cb.handler_pc);
+                writeln!(out, "    #   catch_all -> {:#x}", cb.handler_pc)?;
             } else {
-                println!("    #   catch type={} -> {:#x}", cb.type_idx, cb.handler_pc);
+                writeln!(
+                    out,
+                    "    #   catch type={} -> {:#x}",
+                    cb.type_idx, cb.handler_pc
+                )?;
             }
         }
     }
 
-    println!("}}");
-    println!();
+    writeln!(out, "}}\n")
+}
+
+/// Names behind the ID operands of one method, for its listing. Literal
+/// arrays have no name and go by their offset in the file.
+struct MethodIds<'a> {
+    abc: &'a abcd_file::File,
+    method_off: EntityId,
+}
+
+impl abcd_isa::fmt::IdResolver for MethodIds<'_> {
+    fn resolve(&self, kind: abcd_isa::IdKind, id: EntityId) -> Option<String> {
+        if kind == abcd_isa::IdKind::LiteralArray {
+            let literals = self.abc.literal_for(self.method_off).ok()?;
+            let off = literals.array_id(id.0 as u16)?;
+            return Some(format!("{:#x}", off.0));
+        }
+        let off = self
+            .abc
+            .resolve_offset_by_index(self.method_off, id.0 as u16)?;
+        match kind {
+            abcd_isa::IdKind::String => Some(self.abc.get_string_lossy(off).into_owned()),
+            abcd_isa::IdKind::Method => {
+                let method = self.abc.method(off).ok()?;
+                Some(self.abc.get_string_lossy(method.name_off()).into_owned())
+            }
+            abcd_isa::IdKind::LiteralArray => None,
+        }
+    }
 }
 
 // === Module record helpers ===
@@ -298,7 +1643,7 @@ fn resolve_module_record(
         .map(|i| {
             module
                 .request_off(i)
-                .and_then(|off| abc.get_string(off).ok())
+                .map(|off| abc.get_string_lossy(off).into_owned())
                 .unwrap_or_default()
         })
         .collect();
@@ -311,7 +1656,7 @@ fn resolve_module_record(
     let mut star_exports = Vec::new();
 
     for r in &records {
-        let s = |off: EntityId| abc.get_string(off).unwrap_or_default();
+        let s = |off: EntityId| abc.get_string_lossy(off).into_owned();
         match r.tag {
             abcd_file::ModuleTag::RegularImport => {
                 regular_imports.push(RegularImport {
@@ -348,269 +1693,893 @@ fn resolve_module_record(
         }
     }
 
-    ResolvedModuleRecord {
-        module_requests,
-        regular_imports,
-        namespace_imports,
-        local_exports,
-        indirect_exports,
-        star_exports,
+    ResolvedModuleRecord {
+        module_requests,
+        regular_imports,
+        namespace_imports,
+        local_exports,
+        indirect_exports,
+        star_exports,
+    }
+}
+
+/// Try to find the "moduleRecordIdx" field value from a class.
+fn find_module_record_offset(
+    abc: &abcd_file::File,
+    class: &abcd_file::class::Class,
+) -> Option<EntityId> {
+    for field_off in class.field_offsets() {
+        let field = abc.field(field_off).ok()?;
+        if abc
+            .string_bytes(field.name_off())
+            .is_ok_and(|name| name == b"moduleRecordIdx")
+        {
+            return field
+                .value_i32()
+                .and_then(|v| abc.resolve_literal_array_id(v as u32).ok());
+        }
+    }
+    None
+}
+
+#[allow(clippy::too_many_arguments)]
+fn cmd_decompile(
+    path: &PathBuf,
+    output_dir: Option<&std::path::Path>,
+    as_package: bool,
+    shared: &[PathBuf],
+    db_path: Option<&std::path::Path>,
+    trace: Option<&std::path::Path>,
+    stable_names: bool,
+    filter: &RecordFilter,
+) {
+    let decompiled = decompile(
+        path,
+        output_dir,
+        as_package,
+        shared,
+        db_path,
+        trace,
+        stable_names,
+        filter,
+    );
+    if let Err(e) = decompiled {
+        eprintln!("{e}");
+        std::process::exit(status::ERROR);
+    }
+}
+
+/// [`cmd_decompile`], with the error message instead of exiting.
+#[allow(clippy::too_many_arguments)]
+fn decompile(
+    path: &PathBuf,
+    output_dir: Option<&std::path::Path>,
+    as_package: bool,
+    shared: &[PathBuf],
+    db_path: Option<&std::path::Path>,
+    trace: Option<&std::path::Path>,
+    stable_names: bool,
+    filter: &RecordFilter,
+) -> Result<(), String> {
+    let abc = try_open_bundle(path)?.0;
+    if abc.kind() == abcd_file::FileType::Static {
+        return Err(format!(
+            "Error: {} is a static (PandaAssembly) file; use `disasm` to list it",
+            path.display()
+        ));
+    }
+    check_code(&abc, filter, true, None);
+    let coverage = trace.map(|trace| load_coverage(&abc, trace)).transpose()?;
+
+    if let Some(dir) = output_dir {
+        fs::create_dir_all(dir).map_err(|e| format!("Error creating output directory: {e}"))?;
+    }
+
+    let mut package = as_package.then(|| package::PackageLayout::collect(&abc));
+    let mut shared_abcs = Vec::new();
+    if let Some(layout) = package.as_mut() {
+        for shared_path in shared {
+            let (shared_abc, mut names) = try_open_bundle(shared_path)?;
+            if names.is_empty() {
+                names.push(
+                    shared_path
+                        .file_stem()
+                        .map(|s| s.to_string_lossy().into_owned())
+                        .unwrap_or_else(|| "shared".to_string()),
+                );
+            }
+            let shared_layout = package::PackageLayout::collect(&shared_abc);
+            layout
+                .add_shared(names, shared_layout)
+                .map_err(|e| format!("Error: {}: {e}", shared_path.display()))?;
+            shared_abcs.push((shared_path, shared_abc));
+        }
+    }
+    let cache = db_path
+        .map(|db| open_cache(db, path, &abc, stable_names))
+        .transpose()?;
+    let store = cache.as_ref().map(|(_, store)| store);
+    let functions = stable_names.then(|| abcd_decompiler::stable_function_names(&abc));
+
+    decompile_modules(
+        &abc,
+        output_dir,
+        package.as_ref(),
+        store,
+        &load_notes(path),
+        coverage.as_ref(),
+        functions.as_ref(),
+        filter,
+    );
+
+    let finished = cache
+        .as_ref()
+        .map(|(db, store)| store.mark_complete().and_then(|()| db.flush()));
+    if let Some(Err(e)) = finished {
+        status::error(format_args!("Error updating analysis database: {e}"));
+    }
+
+    let manifest_failed = |e: io::Error| format!("Error writing package.json: {e}");
+    if let (Some(layout), Some(dir)) = (&package, output_dir) {
+        layout.write_manifest(dir, path).map_err(manifest_failed)?;
+        for (pkg, (shared_path, shared_abc)) in layout.shared().iter().zip(&shared_abcs) {
+            let pkg_dir = dir.join(&pkg.root);
+            let functions =
+                stable_names.then(|| abcd_decompiler::stable_function_names(shared_abc));
+            decompile_modules(
+                shared_abc,
+                Some(&pkg_dir),
+                Some(&pkg.layout),
+                None,
+                &load_notes(shared_path),
+                None,
+                functions.as_ref(),
+                &RecordFilter::default(),
+            );
+            pkg.layout
+                .write_manifest(&pkg_dir, shared_path)
+                .map_err(manifest_failed)?;
+        }
+    }
+    Ok(())
+}
+
+/// `decompile --watch`. With an output directory each round decompiles
+/// into a staging directory first and then updates the real one, so
+/// unchanged files are left alone and modules that disappear are removed.
+#[allow(clippy::too_many_arguments)]
+fn watch_decompile(
+    input: &PathBuf,
+    output_dir: Option<&std::path::Path>,
+    as_package: bool,
+    shared: &[PathBuf],
+    db_path: Option<&std::path::Path>,
+    trace: Option<&std::path::Path>,
+    stable_names: bool,
+    filter: &RecordFilter,
+) {
+    let mut paths = Vec::new();
+    for path in std::iter::once(input).chain(shared) {
+        paths.push(path.clone());
+        paths.push(NoteStore::sidecar_path(path));
     }
+    paths.extend(trace.map(std::path::Path::to_path_buf));
+    let staging = std::env::temp_dir().join(format!("abcd-watch-{}", std::process::id()));
+    let mut synced = watch::Synced::default();
+    watch::run(&paths, || {
+        let target = output_dir.map(|_| staging.as_path());
+        if target.is_some() {
+            let _ = fs::remove_dir_all(&staging);
+        }
+        let decompiled = decompile(
+            input,
+            target,
+            as_package,
+            shared,
+            db_path,
+            trace,
+            stable_names,
+            filter,
+        );
+        // A broken build is reported and waited out, not fatal, and leaves
+        // the previous round's output in place.
+        if let Err(e) = decompiled {
+            status::error(e);
+            let _ = fs::remove_dir_all(&staging);
+            return;
+        }
+        let Some(dir) = output_dir else {
+            return;
+        };
+        match synced.sync(&staging, dir) {
+            Ok(stats) => eprintln!(
+                "{}: {} written, {} removed, {} unchanged",
+                dir.display(),
+                stats.written,
+                stats.removed,
+                stats.unchanged
+            ),
+            Err(e) => status::error(format!("Error updating {}: {e}", dir.display())),
+        }
+        let _ = fs::remove_dir_all(&staging);
+    })
 }
 
-/// Try to find the "moduleRecordIdx" field value from a class.
-fn find_module_record_offset(
-    abc: &abcd_file::File,
-    class: &abcd_file::class::Class,
-) -> Option<EntityId> {
-    for field_off in class.field_offsets() {
-        let field = abc.field(field_off).ok()?;
-        let name = abc.get_string(field.name_off()).ok()?;
-        if name == "moduleRecordIdx" {
-            return field.value_i32().map(|v| EntityId(v as u32));
+/// `asm --watch`. A listing that does not assemble is reported and the
+/// last good output left in place.
+fn watch_asm(input: &std::path::Path, output: Option<&std::path::Path>) {
+    watch::run(&[input.to_path_buf()], || {
+        if let Err(e) = asm(input, output) {
+            status::error(e);
         }
-    }
-    None
+    })
 }
 
-fn cmd_decompile(path: &PathBuf, output_dir: Option<&std::path::Path>) {
-    let abc = match abcd_file::File::open_path(path.as_path()) {
-        Ok(f) => f,
-        Err(e) => {
-            eprintln!("Error: {e}");
-            std::process::exit(1);
+/// `disasm --watch`.
+fn watch_disasm(
+    input: &PathBuf,
+    format: DisasmFormat,
+    trace: Option<&std::path::Path>,
+    filter: &RecordFilter,
+) {
+    let mut paths = vec![input.clone(), NoteStore::sidecar_path(input)];
+    paths.extend(trace.map(std::path::Path::to_path_buf));
+    watch::run(&paths, || {
+        if let Err(e) = disasm(input, format, trace, filter) {
+            status::error(e);
         }
-    };
+    })
+}
 
-    let resolver = AbcResolver { abc: &abc };
+/// Tell the user up front when `abc` comes from a toolchain this build's
+/// instruction set does not cover, since its code may then decode wrongly.
+fn warn_on_version(abc: &abcd_file::File) {
+    if let Err(e) = abc.check_version() {
+        status::warn(e);
+        status::warn("instructions may be misdecoded; output below may be wrong");
+    }
+}
 
-    if let Some(dir) = output_dir {
-        fs::create_dir_all(dir).unwrap_or_else(|e| {
-            eprintln!("Error creating output directory: {e}");
-            std::process::exit(1);
-        });
+/// Report the unknown opcodes and unresolved entities in the code of the
+/// classes `filter` keeps, for `--deny`, and add them to `report`. The
+/// checks are [`ValidationReport::check_code`]'s, so a cached decompile
+/// gates the same as a fresh one. `decompiling` also counts instructions
+/// the decompiler has no translation for as unknown.
+fn check_code(
+    abc: &abcd_file::File,
+    filter: &RecordFilter,
+    decompiling: bool,
+    report: Option<&mut ValidationReport>,
+) {
+    use status::Deny;
+
+    let denied =
+        status::is_denied(Deny::UnknownOpcodes) || status::is_denied(Deny::UnresolvedEntities);
+    if !denied && report.is_none() {
+        return;
+    }
+    let mut found = ValidationReport::default();
+    found.check_code(abc, &|name| filter.keeps(name));
+    if decompiling {
+        untranslated(abc, filter, &mut found);
+    }
+    for finding in &found.findings {
+        let kind = Deny::value_variants()
+            .iter()
+            .find(|kind| kind.name() == finding.rule);
+        if let Some(&kind) = kind {
+            status::finding(kind, &finding.message);
+        }
+    }
+    if let Some(report) = report {
+        report.findings.extend(found.findings);
     }
+}
 
+/// Warn about the methods with instructions the decompiler has no
+/// translation for, among the code that decodes.
+fn untranslated(abc: &abcd_file::File, filter: &RecordFilter, report: &mut ValidationReport) {
     for class_off in abc.class_offsets() {
         if abc.is_external(class_off) {
             continue;
         }
+        let Ok(class) = abc.class(class_off) else {
+            continue;
+        };
+        let class_name = abc.get_string_lossy(class_off);
+        if !filter.keeps(&class_name) {
+            continue;
+        }
+        for method_off in class.method_offsets() {
+            let Some(code) = abc
+                .method(method_off)
+                .ok()
+                .and_then(|m| m.code_off())
+                .and_then(|off| abc.code(off).ok())
+            else {
+                continue;
+            };
+            let bytes = code.instructions();
+            let Ok(decoded) = abcd_decompiler::decode_method_in(abc, bytes) else {
+                continue;
+            };
+            if let Some(unknown) = abcd_decompiler::expr_recovery::find_unknown(&decoded, bytes) {
+                let name = format!("{class_name}.{}", abc.method_name_lossy(method_off));
+                report.push(Finding {
+                    rule: validation::UNKNOWN_OPCODES.into(),
+                    level: Level::Warning,
+                    message: format!("{name}: {unknown}"),
+                    method: Some(method_off),
+                    location: Some(name),
+                });
+            }
+        }
+    }
+}
+
+/// Open an `.abc` file, or the bytecode inside a `.hap`, `.hsp` or `.har`,
+/// along with the names the bundle's manifests give it.
+fn open_bundle(path: &std::path::Path) -> (abcd_file::File, Vec<String>) {
+    try_open_bundle(path).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(status::ERROR);
+    })
+}
+
+/// [`open_bundle`], with the error message instead of exiting.
+fn try_open_bundle(path: &std::path::Path) -> Result<(abcd_file::File, Vec<String>), String> {
+    let bundle =
+        bundle::Bundle::open(path).map_err(|e| format!("Error reading {}: {e}", path.display()))?;
+    let abc = abcd_file::File::open(bundle.abc).map_err(|e| format!("Error: {e}"))?;
+    warn_on_version(&abc);
+    Ok((abc, bundle.names))
+}
 
+/// Decompile every local class of `abc`, then print each source file or
+/// write it under `output_dir`, with the classes that share it merged; see
+/// [`sources`].
+/// `functions` names anonymous functions for `--stable-names`; see
+/// [`abcd_decompiler::stable_function_names`].
+#[allow(clippy::too_many_arguments)]
+fn decompile_modules(
+    abc: &abcd_file::File,
+    output_dir: Option<&std::path::Path>,
+    package: Option<&package::PackageLayout>,
+    store: Option<&abcd_db::FileStore>,
+    notes: &NoteStore,
+    coverage: Option<&Coverage>,
+    functions: Option<&FunctionNames>,
+    filter: &RecordFilter,
+) {
+    // Names of source-level locals, where the file records them.
+    let debug = abc.debug_info().ok();
+    let mut sources = SourceFiles::default();
+
+    let classes: Vec<_> = abc
+        .class_offsets()
+        .into_iter()
+        .filter(|&off| !abc.is_external(off) && filter.keeps(&abc.get_string_lossy(off)))
+        .collect();
+    let mut progress = progress::Progress::new(classes.len());
+    for class_off in classes {
         let class = match abc.class(class_off) {
             Ok(c) => c,
             Err(e) => {
-                eprintln!("// Error parsing class at {class_off}: {e}");
+                status::error(format_args!("// Error parsing class at {class_off}: {e}"));
                 continue;
             }
         };
 
-        let class_name = abc
-            .get_string(class_off)
-            .unwrap_or_else(|_| format!("<{class_off}>"));
+        let class_name = abc.get_string_lossy(class_off).into_owned();
+        progress.on_class_start(class_off, &class_name);
         let source_file = class
             .source_file_off()
-            .and_then(|off| abc.get_string(off).ok())
+            .map(|off| abc.get_string_lossy(off).into_owned())
             .unwrap_or_else(|| class_name.clone());
 
-        let mut class_output = String::new();
-
-        // Try to parse module record from class fields
-        let module_record = find_module_record_offset(&abc, &class)
-            .and_then(|off| abc.module(off).ok())
-            .map(|m| resolve_module_record(&abc, &m));
-
-        // Generate import statements
-        if let Some(ref mr) = module_record {
-            for imp in &mr.regular_imports {
-                let module_path = mr
-                    .module_requests
-                    .get(imp.module_request_idx as usize)
-                    .map(|s| s.as_str())
-                    .unwrap_or("?");
-                if imp.import_name == "default" {
-                    class_output.push_str(&format!(
-                        "import {} from '{module_path}';\n",
-                        imp.local_name
-                    ));
-                } else if imp.local_name == imp.import_name {
-                    class_output.push_str(&format!(
-                        "import {{ {} }} from '{module_path}';\n",
-                        imp.import_name
-                    ));
-                } else {
-                    class_output.push_str(&format!(
-                        "import {{ {} as {} }} from '{module_path}';\n",
-                        imp.import_name, imp.local_name
-                    ));
-                }
-            }
-            for imp in &mr.namespace_imports {
-                let module_path = mr
-                    .module_requests
-                    .get(imp.module_request_idx as usize)
-                    .map(|s| s.as_str())
-                    .unwrap_or("?");
-                class_output.push_str(&format!(
-                    "import * as {} from '{module_path}';\n",
-                    imp.local_name
+        let rel_path = class_name_to_path(&source_file);
+        let module_record = class_module_record(abc, &class);
+        // Only the body is cached: imports and exports are cheap to redo
+        // and are merged with the other classes of the file below.
+        let cached = store.and_then(|store| match store.decompiled(class_off.0) {
+            Ok(js) => js,
+            Err(e) => {
+                status::error(format_args!(
+                    "// Error reading cached class at {class_off}: {e}"
                 ));
+                None
             }
-            for se in &mr.star_exports {
-                let module_path = mr
-                    .module_requests
-                    .get(se.module_request_idx as usize)
-                    .map(|s| s.as_str())
-                    .unwrap_or("?");
-                class_output.push_str(&format!("export * from '{module_path}';\n"));
-            }
-            for ie in &mr.indirect_exports {
-                let module_path = mr
-                    .module_requests
-                    .get(ie.module_request_idx as usize)
-                    .map(|s| s.as_str())
-                    .unwrap_or("?");
-                if ie.export_name == ie.import_name {
-                    class_output.push_str(&format!(
-                        "export {{ {} }} from '{module_path}';\n",
-                        ie.import_name
-                    ));
-                } else {
-                    class_output.push_str(&format!(
-                        "export {{ {} as {} }} from '{module_path}';\n",
-                        ie.import_name, ie.export_name
-                    ));
+        });
+        let rendered = match cached {
+            Some(js) => js,
+            None => {
+                let js = render_class_body(
+                    abc,
+                    &class,
+                    module_record.as_ref(),
+                    debug.as_ref(),
+                    store,
+                    functions,
+                    &mut progress,
+                );
+                if let Some(Err(e)) = store.map(|store| store.put_decompiled(class_off.0, &js)) {
+                    status::error(format_args!("// Error caching class at {class_off}: {e}"));
                 }
+                js
             }
-            if !mr.regular_imports.is_empty()
-                || !mr.namespace_imports.is_empty()
-                || !mr.star_exports.is_empty()
-                || !mr.indirect_exports.is_empty()
-            {
-                class_output.push('\n');
-            }
+        };
+        // Notes and coverage are added after caching so that editing them
+        // never invalidates the database.
+        let mut body = class_notes(abc, &class, class_off, &class_name, notes);
+        if let Some(coverage) = coverage {
+            body.push_str(&class_coverage(coverage, &class, class_off, &class_name));
         }
+        body.push_str(&rendered);
+
+        let source = class_source(
+            &class_name,
+            module_record.as_ref(),
+            &rel_path,
+            package,
+            body,
+        );
+        sources.add(rel_path, source);
+    }
+    drop(progress);
 
-        for method_off in class.method_offsets() {
-            decompile_method_to_string(&abc, &resolver, method_off, &mut class_output);
-        }
-
-        // Generate local export statements
-        if let Some(ref mr) = module_record {
-            if !mr.local_exports.is_empty() {
-                let exports: Vec<String> = mr
-                    .local_exports
-                    .iter()
-                    .map(|e| {
-                        if e.local_name == e.export_name {
-                            e.export_name.clone()
-                        } else {
-                            format!("{} as {}", e.local_name, e.export_name)
-                        }
-                    })
-                    .collect();
-                class_output.push_str(&format!("export {{ {} }};\n", exports.join(", ")));
-            }
+    for (rel_path, text) in sources.render() {
+        let Some(dir) = output_dir else {
+            print!("{text}");
+            continue;
+        };
+        let out_path = dir.join(rel_path);
+        write_output(&out_path, text.as_bytes()).unwrap_or_else(|e| {
+            status::error(format_args!("Error writing {}: {e}", out_path.display()));
+        });
+    }
+}
+
+/// Notes on a class and its methods as a comment block.
+fn class_notes(
+    abc: &abcd_file::File,
+    class: &abcd_file::class::Class,
+    class_off: EntityId,
+    class_name: &str,
+    notes: &NoteStore,
+) -> String {
+    let mut out = String::new();
+    if let Some(n) = notes.get(class_off) {
+        let _ = writeln!(out, "// {} ({:#x})", one_line(class_name), class_off.0);
+        out.push_str(&note_lines(n, "//   "));
+    }
+    for method_off in class.method_offsets() {
+        let Some(n) = notes.get(method_off) else {
+            continue;
+        };
+        let name = one_line(&abc.method_name_lossy(method_off));
+        let _ = writeln!(out, "// {name} ({:#x})", method_off.0);
+        out.push_str(&note_lines(n, "//   "));
+    }
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    out
+}
+
+/// How much of a class and each of its methods ran, with each method's
+/// source lines marked run or not, as a comment block.
+fn class_coverage(
+    coverage: &Coverage,
+    class: &abcd_file::class::Class,
+    class_off: EntityId,
+    class_name: &str,
+) -> String {
+    let mut out = String::new();
+    let Some(totals) = coverage.class(class_off) else {
+        return out;
+    };
+    let _ = writeln!(out, "// coverage {class_name}: {}", totals.ratio());
+    for method_off in class.method_offsets() {
+        let Some(m) = coverage.method(method_off) else {
+            continue;
+        };
+        let _ = write!(out, "//   {}: {}", m.map().name, m.ratio());
+        let lines = m.lines();
+        if !lines.is_empty() {
+            let _ = write!(out, ", lines {}", coverage::line_marks(&lines));
         }
+        out.push('\n');
+    }
+    out.push('\n');
+    out
+}
 
-        // Replace __module_N and __export_N placeholders with actual names
-        if let Some(ref mr) = module_record {
-            for (i, imp) in mr.regular_imports.iter().enumerate() {
-                let placeholder = format!("__module_{i}");
-                class_output = class_output.replace(&placeholder, &imp.local_name);
-            }
-            let ns_offset = mr.regular_imports.len();
-            for (i, imp) in mr.namespace_imports.iter().enumerate() {
-                let placeholder = format!("__module_{}", ns_offset + i);
-                class_output = class_output.replace(&placeholder, &imp.local_name);
-            }
-            for (i, exp) in mr.local_exports.iter().enumerate() {
-                let placeholder = format!("__local_module_{i}");
-                class_output = class_output.replace(&placeholder, &exp.local_name);
+/// Version of what `decompile --db` caches per class. Bump it whenever
+/// [`render_class_body`] changes what it leaves out or adds: 2 dropped the
+/// imports and exports, which [`sources`] now merges per file.
+const CACHED_BODY_VERSION: u32 = 2;
+
+/// What cached class bodies depend on besides the file itself: any other
+/// build may decompile differently, `--stable-names` names differently and
+/// a different budget resolves more or fewer references.
+fn cache_options(stable_names: bool, budget: abcd_decompiler::Budget) -> String {
+    let names = if stable_names { "stable" } else { "positional" };
+    format!(
+        "abcd-{}/body-{CACHED_BODY_VERSION}/names-{names}/refs-{}x{}",
+        env!("CARGO_PKG_VERSION"),
+        budget.max_depth,
+        budget.max_nodes
+    )
+}
+
+/// Open the analysis database at `db_path` and the results stored for the
+/// file at `path`, recording the file's summary.
+fn open_cache(
+    db_path: &std::path::Path,
+    path: &std::path::Path,
+    abc: &abcd_file::File,
+    stable_names: bool,
+) -> Result<(abcd_db::Database, abcd_db::FileStore), String> {
+    let failed =
+        |e: abcd_db::Error| format!("Error opening analysis database {}: {e}", db_path.display());
+    let db = abcd_db::Database::open(db_path).map_err(failed)?;
+    // The bytes already parsed, which for a bundle are the `.abc` inside.
+    let bytes = abc.raw_data();
+    let store = db
+        .file(
+            abcd_db::Digest::of(bytes),
+            &cache_options(stable_names, budget()),
+        )
+        .map_err(failed)?;
+    let summary = abcd_db::FileSummary {
+        path: path.display().to_string(),
+        size: bytes.len() as u64,
+        version: abc.version().to_string(),
+        num_classes: abc.num_classes(),
+    };
+    if let Err(e) = store.set_summary(&summary) {
+        status::error(format_args!("Error updating analysis database: {e}"));
+    }
+    Ok((db, store))
+}
+
+/// Decompile one class into a module's source: imports, every method, then
+/// exports.
+fn render_class(
+    abc: &abcd_file::File,
+    class: &abcd_file::class::Class,
+    class_name: &str,
+    rel_path: &std::path::Path,
+    package: Option<&package::PackageLayout>,
+    debug: Option<&abcd_file::debug::DebugInfo>,
+    store: Option<&abcd_db::FileStore>,
+) -> ClassSource {
+    let module_record = class_module_record(abc, class);
+    let body = render_class_body(
+        abc,
+        class,
+        module_record.as_ref(),
+        debug,
+        store,
+        None,
+        &mut (),
+    );
+    class_source(class_name, module_record.as_ref(), rel_path, package, body)
+}
+
+/// The module record of `class`, if it has one.
+fn class_module_record(
+    abc: &abcd_file::File,
+    class: &abcd_file::class::Class,
+) -> Option<ResolvedModuleRecord> {
+    find_module_record_offset(abc, class)
+        .and_then(|off| abc.module(off).ok())
+        .map(|m| resolve_module_record(abc, &m))
+}
+
+/// `body` with the imports and exports of `module_record` around it.
+fn class_source(
+    class_name: &str,
+    module_record: Option<&ResolvedModuleRecord>,
+    rel_path: &std::path::Path,
+    package: Option<&package::PackageLayout>,
+    body: String,
+) -> ClassSource {
+    let Some(mr) = module_record else {
+        return ClassSource {
+            name: class_name.to_string(),
+            imports: Vec::new(),
+            body,
+            exports: Vec::new(),
+        };
+    };
+    let specifier = |idx: u32| -> String {
+        let request = mr
+            .module_requests
+            .get(idx as usize)
+            .map(|s| s.as_str())
+            .unwrap_or("?");
+        package
+            .as_ref()
+            .and_then(|p| p.resolve_specifier(rel_path, request))
+            .unwrap_or_else(|| request.to_string())
+    };
+
+    let mut imports = Vec::new();
+    for imp in &mr.regular_imports {
+        let module_path = specifier(imp.module_request_idx);
+        if imp.import_name == "default" {
+            imports.push(format!("import {} from '{module_path}';", imp.local_name));
+        } else if imp.local_name == imp.import_name {
+            imports.push(format!(
+                "import {{ {} }} from '{module_path}';",
+                imp.import_name
+            ));
+        } else {
+            imports.push(format!(
+                "import {{ {} as {} }} from '{module_path}';",
+                imp.import_name, imp.local_name
+            ));
+        }
+    }
+    for imp in &mr.namespace_imports {
+        let module_path = specifier(imp.module_request_idx);
+        imports.push(format!(
+            "import * as {} from '{module_path}';",
+            imp.local_name
+        ));
+    }
+    for se in &mr.star_exports {
+        let module_path = specifier(se.module_request_idx);
+        imports.push(format!("export * from '{module_path}';"));
+    }
+    for ie in &mr.indirect_exports {
+        let module_path = specifier(ie.module_request_idx);
+        if ie.export_name == ie.import_name {
+            imports.push(format!(
+                "export {{ {} }} from '{module_path}';",
+                ie.import_name
+            ));
+        } else {
+            imports.push(format!(
+                "export {{ {} as {} }} from '{module_path}';",
+                ie.import_name, ie.export_name
+            ));
+        }
+    }
+
+    let exports = mr
+        .local_exports
+        .iter()
+        .map(|e| {
+            if e.local_name == e.export_name {
+                e.export_name.clone()
+            } else {
+                format!("{} as {}", e.local_name, e.export_name)
             }
-            for (i, exp) in mr.local_exports.iter().enumerate() {
-                let placeholder = format!("__export_{i}");
-                class_output = class_output.replace(&placeholder, &exp.export_name);
+        })
+        .collect();
+
+    ClassSource {
+        name: class_name.to_string(),
+        imports,
+        body,
+        exports,
+    }
+}
+
+/// Decompile every method of `class` in declaration order, with the
+/// module record's placeholders replaced by the names they stand for.
+fn render_class_body(
+    abc: &abcd_file::File,
+    class: &abcd_file::class::Class,
+    module_record: Option<&ResolvedModuleRecord>,
+    debug: Option<&abcd_file::debug::DebugInfo>,
+    store: Option<&abcd_db::FileStore>,
+    functions: Option<&FunctionNames>,
+    observer: &mut dyn AnalysisObserver,
+) -> String {
+    let mut class_output = String::new();
+
+    let arkui = arkui_build(abc, class, debug);
+    let methods = abcd_decompiler::member_order::declaration_order_with(
+        abc,
+        &class.method_offsets(),
+        budget(),
+    );
+    for method_off in methods {
+        if let Some(build) = &arkui {
+            let folded = build.closures.contains(&method_off);
+            if method_off == build.initial_render || folded {
+                // Still decompiled when caching, for the cross-references;
+                // only the build() text is shown, so its errors are not.
+                if store.is_some() {
+                    let _ = decompile_method_to_string(
+                        abc,
+                        debug,
+                        store,
+                        method_off,
+                        functions,
+                        &mut String::new(),
+                    );
+                }
+                if !folded {
+                    class_output.push_str("// initialRender(), rebuilt as ArkUI build()\n");
+                    class_output.push_str(&build.text);
+                    class_output.push('\n');
+                }
+                observer.on_method_done(method_off, None);
+                continue;
             }
         }
+        let start = class_output.len();
+        let decompiled =
+            decompile_method_to_string(abc, debug, store, method_off, functions, &mut class_output);
+        if let Err(e) = decompiled {
+            observer.on_warning(Some(method_off), &e);
+        }
+        observer.on_method_done(method_off, Some(&class_output[start..]));
+    }
 
-        if let Some(dir) = output_dir {
-            let rel_path = class_name_to_path(&source_file);
-            let out_path = dir.join(&rel_path);
-            if let Some(parent) = out_path.parent() {
-                fs::create_dir_all(parent).unwrap_or_else(|e| {
-                    eprintln!("Error creating directory {}: {e}", parent.display());
-                });
-            }
-            let mut existing = fs::read_to_string(&out_path).unwrap_or_default();
-            existing.push_str(&class_output);
-            fs::write(&out_path, existing).unwrap_or_else(|e| {
-                eprintln!("Error writing {}: {e}", out_path.display());
-            });
-        } else {
-            print!("{class_output}");
+    // Replace __module_N and __export_N placeholders with actual names
+    if let Some(mr) = module_record {
+        for (i, imp) in mr.regular_imports.iter().enumerate() {
+            let placeholder = format!("__module_{i}");
+            class_output = class_output.replace(&placeholder, &imp.local_name);
         }
+        let ns_offset = mr.regular_imports.len();
+        for (i, imp) in mr.namespace_imports.iter().enumerate() {
+            let placeholder = format!("__module_{}", ns_offset + i);
+            class_output = class_output.replace(&placeholder, &imp.local_name);
+        }
+        for (i, exp) in mr.local_exports.iter().enumerate() {
+            let placeholder = format!("__local_module_{i}");
+            class_output = class_output.replace(&placeholder, &exp.local_name);
+        }
+        for (i, exp) in mr.local_exports.iter().enumerate() {
+            let placeholder = format!("__export_{i}");
+            class_output = class_output.replace(&placeholder, &exp.export_name);
+        }
+    }
+
+    class_output
+}
+
+/// An ArkUI component's `initialRender` reprinted as `build()`.
+struct ArkuiBuild {
+    initial_render: EntityId,
+    text: String,
+    /// Creation closures folded into `text`.
+    closures: HashSet<EntityId>,
+}
+
+/// Recognize an ArkUI component among `class`'s methods; see
+/// [`abcd_decompiler::arkui`].
+fn arkui_build(
+    abc: &abcd_file::File,
+    class: &abcd_file::class::Class,
+    debug: Option<&abcd_file::debug::DebugInfo>,
+) -> Option<ArkuiBuild> {
+    let initial_render = class
+        .method_offsets()
+        .into_iter()
+        .find(|&m| clean_method_name(&abc.method_name_lossy(m)) == "initialRender")?;
+    let stmts = method_stmts(abc, debug, initial_render)?;
+    if !abcd_decompiler::arkui::is_initial_render(&stmts) {
+        return None;
     }
+    let build = abcd_decompiler::arkui::render_build(&stmts, &mut |method| {
+        method_stmts(abc, debug, method)
+    })?;
+    Some(ArkuiBuild {
+        initial_render,
+        text: build.text,
+        closures: build.closures.into_iter().collect(),
+    })
+}
+
+/// Recovered statements of a method, before printing.
+fn method_stmts(
+    abc: &abcd_file::File,
+    debug: Option<&abcd_file::debug::DebugInfo>,
+    method_off: EntityId,
+) -> Option<Vec<abcd_ir::stmt::Stmt>> {
+    let code = abc.code(abc.method(method_off).ok()?.code_off()?).ok()?;
+    let resolver = AbcResolver::new(abc, debug);
+    abcd_decompiler::decompile_method_stmts(
+        code.instructions(),
+        &abcd_decompiler::try_blocks(&code),
+        &resolver,
+        method_off,
+        code.num_vregs(),
+        code.num_args(),
+        abcd_decompiler::UnknownOpcodePolicy::Comment,
+        abcd_decompiler::SyntheticNames::Positional,
+        budget(),
+    )
+    .ok()
 }
 
+/// Decompile the method at `method_off` into `output`, which also gets a
+/// comment saying what went wrong when the method cannot be read or
+/// decoded; the `Err` repeats it for the caller to report.
 fn decompile_method_to_string(
     abc: &abcd_file::File,
-    resolver: &AbcResolver,
+    debug: Option<&abcd_file::debug::DebugInfo>,
+    store: Option<&abcd_db::FileStore>,
     method_off: EntityId,
+    functions: Option<&FunctionNames>,
     output: &mut String,
-) {
+) -> Result<(), String> {
     let method = match abc.method(method_off) {
         Ok(m) => m,
         Err(e) => {
-            output.push_str(&format!("// Error parsing method at {method_off}: {e}\n"));
-            return;
+            let msg = format!("Error parsing method at {method_off}: {e}");
+            output.push_str(&format!("// {msg}\n"));
+            return Err(msg);
         }
     };
 
+    let method_name = abc.get_string_lossy(method.name_off()).into_owned();
+
+    // Native, abstract and external methods have no body, but their proto
+    // still says how many arguments callers should pass.
     let Some(code_off) = method.code_off() else {
-        return; // Skip native/abstract methods
+        if let Some(signature) = bodiless_signature(&method, &method_name) {
+            output.push_str(&format!("// no code: {signature};\n\n"));
+        }
+        return Ok(());
     };
 
     let code = match abc.code(code_off) {
         Ok(c) => c,
         Err(e) => {
-            output.push_str(&format!("// Error parsing code at {code_off}: {e}\n"));
-            return;
+            let msg = format!("Error parsing code at {code_off}: {e}");
+            output.push_str(&format!("// {msg}\n"));
+            return Err(msg);
         }
     };
 
-    let method_name = abc
-        .get_string(method.name_off())
-        .unwrap_or_else(|_| format!("<{method_off}>"));
-
     let instructions = code.instructions();
+    if let Err(e) = abcd_decompiler::decode_method_in(abc, instructions) {
+        let msg = format!("Error decoding code at {code_off}: {e}");
+        output.push_str(&format!("// {msg}\n"));
+        return Err(msg);
+    }
 
-    // Convert try blocks to IR try blocks
-    let try_blocks: Vec<abcd_ir::instruction::TryBlockInfo> = code
-        .try_blocks()
-        .iter()
-        .map(|tb| abcd_ir::instruction::TryBlockInfo {
-            start_pc: tb.start_pc,
-            length: tb.length,
-            catch_blocks: tb
-                .catches
-                .iter()
-                .map(|cb| abcd_ir::instruction::CatchBlockInfo {
-                    type_idx: cb.type_idx,
-                    handler_pc: cb.handler_pc,
-                    code_size: cb.code_size,
-                })
-                .collect(),
-        })
-        .collect();
+    let try_blocks = abcd_decompiler::try_blocks(&code);
 
-    let js = abcd_decompiler::decompile_method(
+    let mut resolver = AbcResolver::new(abc, debug);
+    let names = match functions {
+        Some(functions) => {
+            resolver.functions = Some(functions);
+            abcd_decompiler::SyntheticNames::ContentHash
+        }
+        None => abcd_decompiler::SyntheticNames::Positional,
+    };
+    let stmts = abcd_decompiler::decompile_method_stmts(
         instructions,
         &try_blocks,
-        resolver,
+        &resolver,
         method_off,
         code.num_vregs(),
         code.num_args(),
-    );
+        abcd_decompiler::UnknownOpcodePolicy::Comment,
+        names,
+        budget(),
+    )
+    .expect("only UnknownOpcodePolicy::Error rejects a method");
+    let js = abcd_decompiler::js_emitter::emit_js(&stmts);
+
+    if let Some(store) = store {
+        let record = abcd_db::MethodRecord {
+            code_digest: abcd_db::Digest::of(instructions),
+            xrefs: resolver.xrefs.into_inner().into_iter().collect(),
+        };
+        if let Err(e) = store.put_method(method_off.0, &record) {
+            status::error(format_args!("// Error caching method at {method_off}: {e}"));
+        }
+    }
 
     // Detect rest parameters by scanning for copyrestargs instruction
     let decoded = abcd_decompiler::decode_method(instructions);
@@ -629,25 +2598,92 @@ fn decompile_method_to_string(
     } else {
         0
     };
+    // Types the compiler recorded for TypeScript sources; plain JS has none.
+    let profile = method.profile();
     let user_params = (1..=user_param_count)
         .map(|i| {
-            if rest_param_idx == Some(i - 1) {
+            let name = if rest_param_idx == Some(i - 1) {
                 format!("...p{i}")
             } else {
                 format!("p{i}")
+            };
+            match profile.param_type(i - 1) {
+                Some(ty) => format!("{name} /* {ty} */"),
+                None => name,
             }
         })
         .collect::<Vec<_>>()
         .join(", ");
 
-    output.push_str(&format!(
-        "function {}({user_params}) {{\n",
-        clean_method_name(&method_name)
-    ));
+    let name = match functions.and_then(|f| f.get(&method_off)) {
+        Some(name) => name.clone(),
+        None => clean_method_name(&method_name),
+    };
+    output.push_str(&call_notes(abc, &stmts));
+    output.push_str(&format!("function {name}({user_params}) {{\n"));
     for line in js.lines() {
         output.push_str(&format!("    {line}\n"));
     }
     output.push_str("}\n\n");
+    Ok(())
+}
+
+/// `function name(p1, p2)` for a method without code, from the arity its
+/// proto declares.
+fn bodiless_signature(method: &abcd_file::method::Method, name: &str) -> Option<String> {
+    let params = (1..=method.expected_arity()?)
+        .map(|i| format!("p{i}"))
+        .collect::<Vec<_>>();
+    Some(format!(
+        "function {}({})",
+        clean_method_name(name),
+        params.join(", ")
+    ))
+}
+
+/// Comments on what the calls in `stmts` reach outside the method: the
+/// signature of each function defined in another file, and every call
+/// passing a different number of arguments than its callee declares.
+fn call_notes(abc: &abcd_file::File, stmts: &[abcd_ir::stmt::Stmt]) -> String {
+    use abcd_ir::expr::Expr;
+    use abcd_ir::visit::{self, ExprVisitor};
+
+    struct Externals<'a>(&'a abcd_file::File, BTreeSet<EntityId>);
+    impl ExprVisitor for Externals<'_> {
+        fn visit_expr(&mut self, expr: &Expr) {
+            if let Expr::Function {
+                method: Some(off), ..
+            } = expr
+            {
+                if self.0.is_external(*off) {
+                    self.1.insert(*off);
+                }
+            }
+            visit::walk_expr(self, expr);
+        }
+    }
+
+    let mut out = String::new();
+    let mut externals = Externals(abc, BTreeSet::new());
+    externals.visit_stmts(stmts);
+    for off in externals.1 {
+        let Ok(method) = abc.method(off) else {
+            continue;
+        };
+        let name = abc.get_string_lossy(method.name_off());
+        if let Some(signature) = bodiless_signature(&method, &name) {
+            let _ = writeln!(out, "// external: {signature};");
+        }
+    }
+    let arity = &mut |off| abc.method(off).ok()?.expected_arity();
+    for call in abcd_decompiler::arity::check_calls(stmts, arity) {
+        let _ = writeln!(
+            out,
+            "// arity: {}() declares {} parameters, a call here passes {}",
+            call.callee, call.declared, call.passed
+        );
+    }
+    out
 }
 
 /// Parse ABC internal method names into readable names.
@@ -710,8 +2746,9 @@ fn sanitize_js_ident(s: &str) -> String {
         .collect()
 }
 
+/// `name` as a single path component.
 fn sanitize_filename(name: &str) -> String {
-    name.replace(['\\', ':', '*', '?', '"', '<', '>', '|'], "_")
+    name.replace(['/', '\\', ':', '*', '?', '"', '<', '>', '|', '\0'], "_")
         .replace("..", "_")
         .trim_matches('_')
         .to_string()
@@ -719,22 +2756,259 @@ fn sanitize_filename(name: &str) -> String {
 
 /// Convert a class name like `Lcom.huawei.hmos.photos/phone_photos/ets/Application/AbilityStage;`
 /// into a relative path like `com.huawei.hmos.photos/phone_photos/ets/Application/AbilityStage.js`.
+/// Merged names (`bundle&module/path&version`) go under `bundle/module/path/`.
 fn class_name_to_path(name: &str) -> PathBuf {
-    let stripped = name
-        .strip_prefix('L')
-        .unwrap_or(name)
-        .strip_suffix(';')
-        .unwrap_or(name.strip_prefix('L').unwrap_or(name));
-
-    let parts: Vec<&str> = stripped.split('/').collect();
-    if parts.len() <= 1 {
-        PathBuf::from(format!("{}.js", sanitize_filename(stripped)))
-    } else {
-        let mut path = PathBuf::new();
-        for &dir in &parts[..parts.len() - 1] {
-            path.push(sanitize_filename(dir));
+    let qualified = QualifiedName::parse(name);
+    let mut segments: Vec<&str> = qualified.segments().collect();
+    let name = segments.pop().unwrap_or_default();
+    let mut path: PathBuf = segments.iter().map(|dir| sanitize_filename(dir)).collect();
+    path.push(format!("{}.js", sanitize_filename(name)));
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use abcd_file::manifest::{Change, MethodDiff};
+
+    fn diff(class: &str, name: &str, offset: u32) -> MethodDiff {
+        MethodDiff {
+            class: class.to_string(),
+            name: name.to_string(),
+            change: Change::Modified,
+            old_offset: Some(offset),
+            new_offset: Some(offset + 4),
+        }
+    }
+
+    #[test]
+    fn asm_lists_each_instruction_with_its_bytes() {
+        let program =
+            abcd_isa::asm::assemble("top:\n  ldtrue\n  jnez top\n  returnundefined\n").unwrap();
+        let (code, offsets) = program.emitter.build().unwrap();
+        let mut out = Vec::new();
+        write_asm_listing(program.emitter.instructions(), &code, &offsets, &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 3, "{text}");
+        assert!(lines[0].starts_with("0000: "), "{text}");
+        assert!(lines[2].ends_with("returnundefined"), "{text}");
+        // Every byte of the code is listed once.
+        let listed: String = lines
+            .iter()
+            .map(|line| line[6..].split_whitespace().next().unwrap())
+            .collect();
+        assert_eq!(listed, hex(&code));
+    }
+
+    #[test]
+    fn diff_files_stay_in_their_class_directory() {
+        let stems = diff_file_stems(&[diff("Lcom/example/A;", "../../../etc/passwd", 0x10)]);
+        assert_eq!(stems.len(), 1);
+        assert!(
+            stems[0]
+                .components()
+                .all(|c| matches!(c, std::path::Component::Normal(_))),
+            "{:?}",
+            stems[0]
+        );
+        assert_eq!(
+            stems[0].parent(),
+            Some(std::path::Path::new("com/example/A"))
+        );
+    }
+
+    #[test]
+    fn overloads_get_a_file_each() {
+        let stems = diff_file_stems(&[
+            diff("LA;", "get", 0x10),
+            diff("LA;", "get", 0x20),
+            diff("LA;", "set", 0x30),
+        ]);
+        assert_eq!(stems[0], PathBuf::from("A/get@0x10"));
+        assert_eq!(stems[1], PathBuf::from("A/get@0x20"));
+        assert_eq!(stems[2], PathBuf::from("A/set"));
+    }
+
+    #[test]
+    fn allowlist_skips_comments_and_trailing_columns() {
+        let text = "# trusted build\n\
+                    00112233  0x10  LA;.f\n\
+                    \n\
+                    AABBCCDD\n";
+        let allowed = read_allowlist(text, DigestAlgo::Crc32).unwrap();
+        assert_eq!(
+            allowed,
+            HashSet::from(["00112233".to_string(), "aabbccdd".to_string()])
+        );
+    }
+
+    #[test]
+    fn allowlist_of_the_other_algorithm_is_refused() {
+        let sha = "ab".repeat(32);
+        let err = read_allowlist(&format!("{sha}  0x10  LA;.f\n"), DigestAlgo::Crc32).unwrap_err();
+        assert!(err.starts_with("line 1:"), "{err}");
+        assert!(err.contains("crc32"), "{err}");
+        assert!(read_allowlist("00112233\n", DigestAlgo::Sha256).is_err());
+        assert!(read_allowlist("0011223g\n", DigestAlgo::Crc32).is_err());
+    }
+
+    #[cfg(feature = "selftest")]
+    #[test]
+    fn verify_flags_only_methods_off_the_allowlist() {
+        let abc = abcd_file::File::open(selftest::build_fixture().unwrap()).unwrap();
+        for algo in [DigestAlgo::Crc32, DigestAlgo::Sha256] {
+            let digests = hex_digests(&abc, algo, &mut ());
+            assert_eq!(digests.len(), 1);
+            // The output of a run without an allowlist, as is.
+            let listed: String = digests
+                .iter()
+                .map(|(off, name, digest)| format!("{digest}  {:#x}  {name}\n", off.0))
+                .collect();
+            let allowed = read_allowlist(&listed, algo).unwrap();
+            assert_eq!(untrusted(digests.clone(), Some(&allowed)).count(), 0);
+
+            let flagged: Vec<_> = untrusted(digests.clone(), Some(&HashSet::new())).collect();
+            assert_eq!(flagged, digests);
+            assert!(flagged[0].1.ends_with("selftest"), "{}", flagged[0].1);
+            assert_eq!(untrusted(digests.clone(), None).count(), 1);
         }
-        path.push(format!("{}.js", sanitize_filename(parts[parts.len() - 1])));
-        path
+    }
+
+    #[cfg(feature = "selftest")]
+    #[test]
+    fn verify_sarif_lists_untrusted_methods_with_or_without_an_allowlist() {
+        let abc = abcd_file::File::open(selftest::build_fixture().unwrap()).unwrap();
+        let untrusted = |report: &ValidationReport| -> Vec<Level> {
+            report
+                .findings
+                .iter()
+                .filter(|f| f.rule == validation::UNTRUSTED_METHOD)
+                .map(|f| f.level)
+                .collect()
+        };
+
+        let (report, checked) =
+            verify_report(&abc, "a.abc".into(), DigestAlgo::Crc32, None, &mut ());
+        assert_eq!(checked, 1);
+        assert_eq!(untrusted(&report), [Level::Note]);
+
+        let none = HashSet::new();
+        let (report, _) = verify_report(
+            &abc,
+            "a.abc".into(),
+            DigestAlgo::Crc32,
+            Some(&none),
+            &mut (),
+        );
+        assert_eq!(untrusted(&report), [Level::Error]);
+        let sarif = report.to_sarif();
+        let result = &sarif["runs"][0]["results"][0];
+        assert_eq!(result["ruleId"], validation::UNTRUSTED_METHOD);
+        assert_eq!(result["level"], "error");
+
+        let digests = hex_digests(&abc, DigestAlgo::Crc32, &mut ());
+        let all = digests.into_iter().map(|(_, _, digest)| digest).collect();
+        let (report, _) =
+            verify_report(&abc, "a.abc".into(), DigestAlgo::Crc32, Some(&all), &mut ());
+        assert!(untrusted(&report).is_empty());
+        // The fixture's code is sound, so nothing else is reported.
+        assert!(report.is_empty(), "{:?}", report.findings);
+    }
+
+    #[test]
+    fn notes_cannot_end_their_comment() {
+        let notes = EntityNotes {
+            notes: vec!["decrypts\nalert(1)\r\u{2028}x".to_string()],
+            tags: ["a\nb".to_string()].into(),
+        };
+        let text = note_lines(&notes, "// ");
+        assert_eq!(
+            text,
+            "// note: decrypts\n\
+             //       alert(1)\n\
+             //       \n\
+             //       x\n\
+             // tags: a\\nb\n"
+        );
+        assert!(text.lines().all(|line| line.starts_with("// ")));
+    }
+
+    #[test]
+    fn stable_names_are_cached_apart() {
+        let budget = abcd_decompiler::Budget::default();
+        assert_ne!(cache_options(true, budget), cache_options(false, budget));
+    }
+
+    #[test]
+    fn budgets_are_cached_apart() {
+        let budget = abcd_decompiler::Budget::default();
+        let shallow = abcd_decompiler::Budget {
+            max_depth: 1,
+            ..budget
+        };
+        assert_ne!(cache_options(false, budget), cache_options(false, shallow));
+    }
+
+    #[test]
+    fn budget_flags_default_to_the_library_budget() {
+        let cli = Cli::try_parse_from(["abcd", "isa"]).unwrap();
+        let budget = abcd_decompiler::Budget::default();
+        assert_eq!(cli.max_ref_depth, budget.max_depth);
+        assert_eq!(cli.max_ref_count, budget.max_nodes);
+        let cli = Cli::try_parse_from(["abcd", "isa", "--max-ref-depth", "3"]).unwrap();
+        assert_eq!(cli.max_ref_depth, 3);
+    }
+
+    /// A dynamic file whose `pair` declares two parameters and has no code.
+    #[cfg(feature = "selftest")]
+    fn file_with_pair() -> (abcd_file::File, EntityId) {
+        use abcd_file::{ACC_PUBLIC, TypeId};
+
+        let mut b = abcd_file::builder::Builder::new().unwrap();
+        b.set_api(12, "").unwrap();
+        let class = b.add_class("L_GLOBAL;").unwrap();
+        let proto = b.create_proto(TypeId::Tagged, &[TypeId::Tagged; 5]);
+        b.class_add_method_with_proto(class, "pair", proto, ACC_PUBLIC, &[], 0, 0)
+            .unwrap();
+        let abc = abcd_file::File::open(b.finalize().unwrap()).unwrap();
+        let class = abc.class_id_by_name("L_GLOBAL;").unwrap().unwrap();
+        let pair = abc.class(class).unwrap().method_offsets()[0];
+        (abc, pair)
+    }
+
+    #[cfg(feature = "selftest")]
+    #[test]
+    fn methods_without_code_show_their_arity() {
+        let (abc, pair) = file_with_pair();
+        let mut out = String::new();
+        decompile_method_to_string(&abc, None, None, pair, None, &mut out).unwrap();
+        assert_eq!(out, "// no code: function pair(p1, p2);\n\n");
+    }
+
+    #[cfg(feature = "selftest")]
+    #[test]
+    fn calls_with_the_wrong_arity_are_noted() {
+        use abcd_ir::expr::{Expr, FunctionKind};
+        use abcd_ir::stmt::Stmt;
+
+        let (abc, pair) = file_with_pair();
+        let function = Expr::Function {
+            kind: FunctionKind::Func,
+            name: "pair".into(),
+            method: Some(pair),
+        };
+        let stmts = vec![
+            Stmt::Let {
+                name: "f".into(),
+                init: Some(function.clone()),
+            },
+            Stmt::Expr(Expr::call(Expr::var("f"), vec![Expr::num(1.0)])),
+            Stmt::Expr(Expr::call(function, vec![Expr::num(1.0), Expr::num(2.0)])),
+        ];
+        assert_eq!(
+            call_notes(&abc, &stmts),
+            "// arity: f() declares 2 parameters, a call here passes 1\n"
+        );
     }
 }
@@ -0,0 +1,470 @@
+//! `decompile --as-package`: lay out decompiled modules as an npm-style project.
+//!
+//! Every record with a module record becomes a file under the output directory
+//! (same layout as plain `decompile -o`), import specifiers that point at other
+//! records in the same file are rewritten to relative paths, and a
+//! `package.json` ties everything together.
+//!
+//! Shared packages given with `--shared` are laid out the same way under
+//! `oh_modules/<name>/`, and requests from the app into their modules resolve
+//! to relative paths as well.
+
+use abcd_file::EntityId;
+use abcd_file::names::QualifiedName;
+use serde_json::{Map, Value, json};
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+use crate::{class_name_to_path, find_module_record_offset};
+
+/// Field name prefix es2abc uses to tag a record with its owning package.
+const PKG_NAME_FIELD_PREFIX: &str = "pkgName@";
+/// Field holding the raw text of a JSON record.
+const JSON_CONTENT_FIELD: &str = "jsonFileContent";
+
+/// Module table and package metadata collected from an ABC file.
+#[derive(Default)]
+pub(crate) struct PackageLayout {
+    /// Record name -> emitted path (relative to the output directory).
+    records: BTreeMap<String, PathBuf>,
+    /// Normalized module key -> emitted path, for resolving import requests.
+    by_key: BTreeMap<String, PathBuf>,
+    /// Package names tagged on records via `pkgName@<name>` fields.
+    pkg_names: Vec<String>,
+    /// Raw `oh-package.json5` content, if the bundle carries it.
+    oh_package: Option<String>,
+    /// Shared packages the modules may import from.
+    shared: Vec<SharedPackage>,
+}
+
+/// A shared package (HSP or HAR) laid out next to the app.
+pub(crate) struct SharedPackage {
+    /// Names the package is imported by.
+    names: Vec<String>,
+    /// Output directory of the package, relative to the app's.
+    pub(crate) root: PathBuf,
+    pub(crate) layout: PackageLayout,
+}
+
+impl PackageLayout {
+    /// Scan all local records of `abc`.
+    pub(crate) fn collect(abc: &abcd_file::File) -> Self {
+        let mut layout = PackageLayout::default();
+
+        for class_off in abc.class_offsets() {
+            if abc.is_external(class_off) {
+                continue;
+            }
+            let Ok(class) = abc.class(class_off) else {
+                continue;
+            };
+            let record = abc.get_string_lossy(class_off).into_owned();
+
+            for field_off in class.field_offsets() {
+                let Some((name, value)) = field_name_and_string(abc, field_off) else {
+                    continue;
+                };
+                if let Some(pkg) = name.strip_prefix(PKG_NAME_FIELD_PREFIX) {
+                    if !pkg.is_empty() && !layout.pkg_names.iter().any(|p| p == pkg) {
+                        layout.pkg_names.push(pkg.to_string());
+                    }
+                } else if name == JSON_CONTENT_FIELD
+                    && module_key(&record).ends_with("oh-package.json5")
+                    && layout.oh_package.is_none()
+                {
+                    layout.oh_package = value;
+                }
+            }
+
+            if find_module_record_offset(abc, &class).is_none() {
+                continue;
+            }
+            let source_file = class
+                .source_file_off()
+                .map(|off| abc.get_string_lossy(off).into_owned())
+                .unwrap_or_else(|| record.clone());
+            let path = class_name_to_path(&source_file);
+            layout.by_key.insert(module_key(&record), path.clone());
+            layout.records.insert(record, path);
+        }
+
+        layout
+    }
+
+    /// Make the modules of a shared package importable from this one. The
+    /// first of `names` names its output directory.
+    ///
+    /// The names come from the package's own manifest, so one that is not
+    /// a plain package name (`name` or `@scope/name`) is refused rather than
+    /// joined onto the output path.
+    pub(crate) fn add_shared(
+        &mut self,
+        names: Vec<String>,
+        layout: PackageLayout,
+    ) -> Result<(), String> {
+        if let Some(bad) = names.iter().find(|n| !is_package_name(n)) {
+            return Err(format!("{bad:?} is not a package name"));
+        }
+        let Some(first) = names.first() else {
+            return Err("the package has no name".to_string());
+        };
+        let root = Path::new("oh_modules").join(first);
+        self.shared.push(SharedPackage {
+            names,
+            root,
+            layout,
+        });
+        Ok(())
+    }
+
+    pub(crate) fn shared(&self) -> &[SharedPackage] {
+        &self.shared
+    }
+
+    /// Rewrite an import request made from the module at `from` into a
+    /// relative specifier, if it names a module emitted in this package or
+    /// one of its shared packages.
+    ///
+    /// A request for a bare package name resolves to that package's entry
+    /// module.
+    pub(crate) fn resolve_specifier(&self, from: &Path, request: &str) -> Option<String> {
+        if request.starts_with("./") || request.starts_with("../") {
+            return None;
+        }
+        let key = module_key(request);
+        if let Some(target) = self.by_key.get(&key) {
+            return Some(relative_specifier(from, target));
+        }
+        let target = self.shared.iter().find_map(|pkg| {
+            let target = match pkg.layout.by_key.get(&key) {
+                Some(target) => target,
+                None if pkg.names.contains(&key) => pkg.layout.entry()?,
+                None => return None,
+            };
+            Some(pkg.root.join(target))
+        })?;
+        Some(relative_specifier(from, &target))
+    }
+
+    /// Write `package.json` (and the recovered `oh-package.json5`) into `dir`.
+    pub(crate) fn write_manifest(&self, dir: &Path, input: &Path) -> std::io::Result<()> {
+        let oh = self.oh_package.as_deref();
+        let fallback_name = input
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "decompiled".to_string());
+        let name = oh
+            .and_then(|c| json5_string_field(c, "name"))
+            .or_else(|| self.pkg_names.first().cloned())
+            .unwrap_or(fallback_name);
+        let version = oh
+            .and_then(|c| json5_string_field(c, "version"))
+            .unwrap_or_else(|| "0.0.0".to_string());
+
+        let mut manifest = Map::new();
+        manifest.insert("name".into(), json!(name));
+        manifest.insert("version".into(), json!(version));
+        for key in ["description", "author", "license"] {
+            if let Some(v) = oh.and_then(|c| json5_string_field(c, key)) {
+                manifest.insert(key.into(), json!(v));
+            }
+        }
+        manifest.insert("private".into(), json!(true));
+        manifest.insert("type".into(), json!("module"));
+        if let Some(entry) = self.entry() {
+            manifest.insert("main".into(), json!(to_specifier(entry)));
+        }
+        if !self.shared.is_empty() {
+            let deps: Map<String, Value> = self
+                .shared
+                .iter()
+                .map(|pkg| {
+                    let spec = format!("file:./{}", to_specifier(&pkg.root));
+                    (pkg.names[0].clone(), json!(spec))
+                })
+                .collect();
+            manifest.insert("dependencies".into(), Value::Object(deps));
+        }
+        let modules: Map<String, Value> = self
+            .records
+            .iter()
+            .map(|(record, path)| (record.clone(), json!(to_specifier(path))))
+            .collect();
+        manifest.insert(
+            "abcd".into(),
+            json!({
+                "source": input.file_name().map(|n| n.to_string_lossy().into_owned()),
+                "packages": self.pkg_names,
+                "modules": modules,
+            }),
+        );
+
+        let text = serde_json::to_string_pretty(&Value::Object(manifest))?;
+        crate::write_output(&dir.join("package.json"), (text + "\n").as_bytes())?;
+        if let Some(content) = oh {
+            crate::write_output(&dir.join("oh-package.json5"), content.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Best guess at the package entry module.
+    ///
+    /// Prefers an ability stage / entry ability, then an `index` module,
+    /// then the first module in record order.
+    fn entry(&self) -> Option<&PathBuf> {
+        let stem_is = |p: &&PathBuf, names: &[&str]| {
+            p.file_stem()
+                .and_then(|s| s.to_str())
+                .is_some_and(|s| names.iter().any(|n| s.eq_ignore_ascii_case(n)))
+        };
+        let paths = || self.records.values();
+        paths()
+            .find(|p| stem_is(p, &["EntryAbility", "AbilityStage"]))
+            .or_else(|| {
+                paths()
+                    .filter(|p| stem_is(p, &["index"]))
+                    .min_by_key(|p| p.components().count())
+            })
+            .or_else(|| paths().next())
+    }
+}
+
+/// Read a field's name and, if it holds one, its string value.
+fn field_name_and_string(
+    abc: &abcd_file::File,
+    field_off: EntityId,
+) -> Option<(String, Option<String>)> {
+    let field = abc.field(field_off).ok()?;
+    let name = abc.get_string(field.name_off()).ok()?;
+    let value = field
+        .value_i32()
+        .and_then(|v| abc.get_string(EntityId(v as u32)).ok());
+    Some((name, value))
+}
+
+/// Reduce a record name or import request to a comparable module key.
+///
+/// Handles `Lpath;` descriptors, `@bundle:`/`@normalized:` requests and the
+/// `&`-separated merged-abc record form, keeping the path component.
+pub(crate) fn module_key(name: &str) -> String {
+    let name = name
+        .strip_prefix("@normalized:")
+        .or_else(|| name.strip_prefix("@bundle:"))
+        .unwrap_or(name);
+    QualifiedName::parse(name)
+        .module_path()
+        .trim_end_matches(".js")
+        .trim_end_matches(".ets")
+        .trim_end_matches(".ts")
+        .to_string()
+}
+
+/// Relative import specifier from the module at `from` to the one at `to`.
+fn relative_specifier(from: &Path, to: &Path) -> String {
+    let from_dir: Vec<Component> = from
+        .parent()
+        .map(|p| p.components().collect())
+        .unwrap_or_default();
+    let to_parts: Vec<Component> = to.components().collect();
+    let common = from_dir
+        .iter()
+        .zip(&to_parts)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut rel = PathBuf::new();
+    for _ in common..from_dir.len() {
+        rel.push("..");
+    }
+    for part in &to_parts[common..] {
+        rel.push(part);
+    }
+    let rel = to_specifier(&rel);
+    if rel.starts_with("../") {
+        rel
+    } else {
+        format!("./{rel}")
+    }
+}
+
+/// Whether `name` is `name` or `@scope/name`, each part a single path
+/// component that is neither `.` nor `..`.
+fn is_package_name(name: &str) -> bool {
+    let part =
+        |p: &str| !p.is_empty() && p != "." && p != ".." && !p.contains(['/', '\\', ':', '\0']);
+    match name.split_once('/') {
+        Some((scope, rest)) => {
+            scope.len() > 1 && scope.starts_with('@') && part(scope) && part(rest)
+        }
+        None => !name.starts_with('@') && part(name),
+    }
+}
+
+/// Path rendered with forward slashes, as used in JS specifiers.
+fn to_specifier(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Pull a top-level string field out of JSON5 text without a full parser.
+///
+/// Accepts quoted or bare keys and single- or double-quoted values, which
+/// covers what ohpm writes. Keys of nested objects, and fields whose value
+/// is not a string, are passed over.
+pub(crate) fn json5_string_field(content: &str, key: &str) -> Option<String> {
+    let tokens = json5_tokens(content);
+    let mut depth = 0usize;
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            Json5Token::Punct('{' | '[') => depth += 1,
+            Json5Token::Punct('}' | ']') => depth = depth.saturating_sub(1),
+            Json5Token::Punct(':') if depth == 1 && i > 0 => {
+                let name = match &tokens[i - 1] {
+                    Json5Token::Str(name) | Json5Token::Word(name) => name,
+                    Json5Token::Punct(_) => continue,
+                };
+                match tokens.get(i + 1) {
+                    Some(Json5Token::Str(value)) if name == key => return Some(value.clone()),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+enum Json5Token {
+    /// A quoted string, unescaped.
+    Str(String),
+    /// A bare key, number or literal.
+    Word(String),
+    Punct(char),
+}
+
+/// `content` split into tokens, without whitespace and comments.
+fn json5_tokens(content: &str) -> Vec<Json5Token> {
+    let is_word = |c: char| c.is_alphanumeric() || matches!(c, '_' | '$' | '-' | '+' | '.');
+    let mut tokens = Vec::new();
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            quote @ ('"' | '\'') => {
+                let mut s = String::new();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => match chars.next() {
+                            Some('n') => s.push('\n'),
+                            Some('t') => s.push('\t'),
+                            Some('\n') | None => {}
+                            Some(other) => s.push(other),
+                        },
+                        _ if c == quote => break,
+                        _ => s.push(c),
+                    }
+                }
+                tokens.push(Json5Token::Str(s));
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                chars.by_ref().find(|&c| c == '\n');
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = '\0';
+                for c in chars.by_ref() {
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+            }
+            c if c.is_whitespace() => {}
+            c if is_word(c) => {
+                let mut word = c.to_string();
+                while let Some(c) = chars.next_if(|&c| is_word(c)) {
+                    word.push(c);
+                }
+                tokens.push(Json5Token::Word(word));
+            }
+            c => tokens.push(Json5Token::Punct(c)),
+        }
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json5_fields_are_read_at_the_top_level_only() {
+        let content = r#"{
+          // "name": "commented",
+          /* name: 'also commented' */
+          dependencies: { "name": "nested", "@ohos/lib": "file:../lib" },
+          'description': "the name: field",
+          "version": 2,
+          version: '1.0.0',
+          name: 'app\'s',
+        }"#;
+        assert_eq!(
+            json5_string_field(content, "name").as_deref(),
+            Some("app's")
+        );
+        assert_eq!(
+            json5_string_field(content, "version").as_deref(),
+            Some("1.0.0")
+        );
+        assert_eq!(
+            json5_string_field(content, "description").as_deref(),
+            Some("the name: field")
+        );
+        assert_eq!(json5_string_field(content, "@ohos/lib"), None);
+        assert_eq!(json5_string_field(content, "main"), None);
+        assert_eq!(json5_string_field("{ name: [\"x\"] }", "name"), None);
+    }
+
+    #[test]
+    fn hostile_shared_package_names_are_refused() {
+        for name in [
+            "../../etc",
+            "..",
+            "/tmp/pwned",
+            "a/b",
+            "@scope/../x",
+            "@/x",
+            "a\\b",
+            "C:evil",
+            "",
+        ] {
+            let mut layout = PackageLayout::default();
+            let result = layout.add_shared(vec![name.to_string()], PackageLayout::default());
+            assert!(result.is_err(), "{name:?} was accepted");
+            assert!(layout.shared().is_empty());
+        }
+        // A hostile alias is refused too, even behind a good first name.
+        let mut layout = PackageLayout::default();
+        let names = vec!["lib".to_string(), "../x".to_string()];
+        assert!(layout.add_shared(names, PackageLayout::default()).is_err());
+    }
+
+    #[test]
+    fn plain_and_scoped_names_are_laid_out_under_oh_modules() {
+        let mut layout = PackageLayout::default();
+        for name in ["library", "@ohos/hypium"] {
+            layout
+                .add_shared(vec![name.to_string()], PackageLayout::default())
+                .unwrap();
+        }
+        let roots: Vec<_> = layout.shared().iter().map(|p| p.root.clone()).collect();
+        assert_eq!(
+            roots,
+            [
+                Path::new("oh_modules/library"),
+                Path::new("oh_modules/@ohos/hypium")
+            ]
+        );
+    }
+}
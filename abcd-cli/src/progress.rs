@@ -0,0 +1,54 @@
+//! The progress line `decompile` and `verify` keep on stderr.
+
+use std::io::{IsTerminal, Write};
+
+use abcd_file::{AnalysisObserver, EntityId};
+
+use crate::status;
+
+/// Shows `[done/total] class` on one stderr line, rewritten in place, while
+/// stderr is a terminal. Warnings go through [`status::warn`], which, like
+/// every [`status`] message, erases the line before printing.
+pub struct Progress {
+    total: usize,
+    done: usize,
+    shown: bool,
+}
+
+impl Progress {
+    /// A progress line for a pass over `total` classes.
+    pub fn new(total: usize) -> Self {
+        Self {
+            total,
+            done: 0,
+            shown: std::io::stderr().is_terminal(),
+        }
+    }
+
+    /// Erase the line, so whatever is printed next starts on a clean one.
+    /// [`status`] messages do this themselves.
+    pub fn clear(&self) {
+        status::clear_progress();
+    }
+}
+
+impl AnalysisObserver for Progress {
+    fn on_class_start(&mut self, _class: EntityId, name: &str) {
+        self.done += 1;
+        if self.shown {
+            eprint!("\r\x1b[2K[{}/{}] {name}", self.done, self.total);
+            let _ = std::io::stderr().flush();
+            status::set_progress_shown(true);
+        }
+    }
+
+    fn on_warning(&mut self, _method: Option<EntityId>, message: &str) {
+        status::warn(message);
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
@@ -0,0 +1,849 @@
+//! `report`: a static HTML site describing a whole app.
+//!
+//! The site is a handful of plain files that open from disk without a
+//! server: an index with summary statistics and findings, a class tree
+//! linking to each class's decompiled source, the module import graph as
+//! SVG, and a string search page backed by a generated index. Everything
+//! is rendered here from the library APIs; the only script is the few
+//! lines that filter the string index. The findings are also written as a
+//! SARIF log, `findings.sarif`, for dashboards.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use abcd_file::EntityId;
+use abcd_file::notes::NoteStore;
+use abcd_file::validation::{self, Level, ValidationReport};
+use abcd_isa::{CostClass, CostEstimate, OpcodeCategory, TypedEntityRef};
+
+use crate::package::module_key;
+
+/// Methods shorter than this are left out of the clone findings.
+const CLONE_MIN_LEN: usize = 20;
+
+/// Rule of the findings on entities tagged with `notes --tag`.
+const TAGGED: &str = "tagged";
+/// Rule of the findings on groups of identical methods.
+const IDENTICAL_METHODS: &str = "identical-methods";
+
+/// Radius per node of the module graph's circle.
+const NODE_SPACING: f64 = 14.0;
+
+const STYLE: &str = "\
+body { font-family: sans-serif; margin: 0 2em 2em; color: #222; }
+nav { padding: 1em 0; border-bottom: 1px solid #ccc; margin-bottom: 1em; }
+nav a { margin-right: 1.5em; }
+table { border-collapse: collapse; }
+td, th { padding: 2px 12px 2px 0; text-align: left; vertical-align: top; }
+td.n { text-align: right; }
+ul.tree { list-style: none; padding-left: 1.2em; }
+pre { background: #f7f7f7; padding: 1em; overflow-x: auto; }
+.kw { color: #0033b3; font-weight: bold; }
+.str { color: #067d17; }
+.num { color: #1750eb; }
+.com { color: #8c8c8c; font-style: italic; }
+.error { color: #b00020; }
+.warning { color: #9a6700; }
+.note { color: #555; }
+#results li { font-family: monospace; margin: 2px 0; }
+";
+
+const JS_KEYWORDS: &[&str] = &[
+    "async",
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "from",
+    "function",
+    "if",
+    "import",
+    "in",
+    "instanceof",
+    "let",
+    "new",
+    "null",
+    "of",
+    "return",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "undefined",
+    "var",
+    "void",
+    "while",
+    "yield",
+];
+
+/// One local class and what the report shows about it.
+struct ClassPage {
+    off: EntityId,
+    name: String,
+    /// Output path of the class's module, as `decompile -o` would write it.
+    rel_path: PathBuf,
+    source: String,
+    /// `(offset, name, instruction count)`.
+    methods: Vec<(EntityId, String, usize)>,
+    module: Option<ModuleNode>,
+}
+
+impl ClassPage {
+    fn href(&self, index: usize) -> String {
+        format!("classes/{index}.html")
+    }
+}
+
+/// A class's place in the module graph.
+struct ModuleNode {
+    key: String,
+    requests: Vec<String>,
+}
+
+struct Finding {
+    rule: String,
+    level: Level,
+    text: String,
+    /// Index of the class page the finding is about.
+    class: Option<usize>,
+    /// The method the finding is about, if it is about one.
+    method: Option<EntityId>,
+}
+
+/// Write the report for `abc`, read from `input`, into `dir`, covering the
+/// classes `filter` keeps.
+pub(crate) fn write(
+    abc: &abcd_file::File,
+    input: &Path,
+    dir: &Path,
+    filter: &crate::RecordFilter,
+) -> io::Result<()> {
+    let notes = crate::load_notes(input);
+    let debug = abc.debug_info().ok();
+
+    // String text -> pages of the classes using it.
+    let mut strings: BTreeMap<String, BTreeSet<usize>> = BTreeMap::new();
+    let mut findings = Vec::new();
+    let mut categories = [0u32; OpcodeCategory::ALL.len()];
+    let mut cost = CostEstimate::default();
+    let mut pages = Vec::new();
+    let mut filtered_out = false;
+
+    for class_off in abc.class_offsets() {
+        if abc.is_external(class_off) {
+            continue;
+        }
+        let name = abc.get_string_lossy(class_off).into_owned();
+        if !filter.keeps(&name) {
+            filtered_out = true;
+            continue;
+        }
+        let Ok(class) = abc.class(class_off) else {
+            continue;
+        };
+        let index = pages.len();
+        let source_file = class
+            .source_file_off()
+            .map(|off| abc.get_string_lossy(off).into_owned())
+            .unwrap_or_else(|| name.clone());
+        let rel_path = crate::class_name_to_path(&source_file);
+
+        let mut methods = Vec::new();
+        for method_off in class.method_offsets() {
+            let method_name = abc.method_name_lossy(method_off).into_owned();
+            let code = abc
+                .method(method_off)
+                .ok()
+                .and_then(|m| m.code_off())
+                .and_then(|off| abc.code(off).ok());
+            let Some(code) = code else {
+                methods.push((method_off, method_name, 0));
+                continue;
+            };
+            let bytes = code.instructions();
+            let decoded = abcd_decompiler::decode_method(bytes);
+            if let Some(unknown) = abcd_decompiler::expr_recovery::find_unknown(&decoded, bytes) {
+                findings.push(Finding {
+                    rule: validation::UNKNOWN_OPCODES.into(),
+                    level: Level::Warning,
+                    text: format!("{name}.{method_name}: {unknown}"),
+                    class: Some(index),
+                    method: Some(method_off),
+                });
+            }
+            for insn in &decoded {
+                categories[insn.opcode.category() as usize] += 1;
+                cost.add(&insn.opcode);
+                let (_, _, n) = insn.opcode.emit_args();
+                for idx in 0..n {
+                    let Some(TypedEntityRef::String(id)) = insn.opcode.typed_id(idx) else {
+                        continue;
+                    };
+                    let text = abc
+                        .resolve_offset_by_index(method_off, id.0 as u16)
+                        .map(|off| abc.get_string_lossy(off).into_owned());
+                    if let Some(text) = text {
+                        strings.entry(text).or_default().insert(index);
+                    }
+                }
+            }
+            methods.push((method_off, method_name, decoded.len()));
+        }
+
+        let module = crate::find_module_record_offset(abc, &class)
+            .and_then(|off| abc.module(off).ok())
+            .map(|m| ModuleNode {
+                key: module_key(&name),
+                requests: crate::resolve_module_record(abc, &m).module_requests,
+            });
+        let mut source = crate::class_notes(abc, &class, class_off, &name, &notes);
+        source.push_str(
+            &crate::render_class(abc, &class, &name, &rel_path, None, debug.as_ref(), None).text(),
+        );
+        pages.push(ClassPage {
+            off: class_off,
+            name,
+            rel_path,
+            source,
+            methods,
+            module,
+        });
+    }
+
+    // Code that does not decode or names nothing, as `verify` checks it.
+    let mut checked = ValidationReport::default();
+    checked.check_code(abc, &|name| filter.keeps(name));
+    for f in checked.findings {
+        let class = f.method.and_then(|m| {
+            pages
+                .iter()
+                .position(|p| p.methods.iter().any(|(off, _, _)| *off == m))
+        });
+        findings.push(Finding {
+            rule: f.rule,
+            level: f.level,
+            text: f.message,
+            class,
+            method: f.method,
+        });
+    }
+    findings.extend(note_findings(abc, &notes, &pages, !filtered_out));
+    for group in abcd_analysis::clones::find(abc, CLONE_MIN_LEN) {
+        if group.kind != abcd_analysis::clones::CloneKind::Identical {
+            continue;
+        }
+        let names: Vec<&str> = group.methods.iter().map(|m| m.name.as_str()).collect();
+        findings.push(Finding {
+            rule: IDENTICAL_METHODS.into(),
+            level: Level::Note,
+            text: format!(
+                "{} identical methods of {} instructions: {}",
+                names.len(),
+                group.methods[0].len,
+                names.join(", ")
+            ),
+            class: None,
+            method: Some(group.methods[0].method_off),
+        });
+    }
+
+    put(dir, "style.css", STYLE)?;
+    put(
+        dir,
+        "index.html",
+        index_page(
+            abc,
+            input,
+            &pages,
+            &findings,
+            &categories,
+            &cost,
+            strings.len(),
+        ),
+    )?;
+    put(dir, "classes.html", class_tree_page(&pages))?;
+    for (index, page) in pages.iter().enumerate() {
+        put(dir, page.href(index), class_page(page))?;
+    }
+    put(dir, "modules.svg", module_graph(&pages))?;
+    put(
+        dir,
+        "modules.html",
+        layout(
+            "Modules",
+            "",
+            "<h1>Module graph</h1>\n<p>Modules of this file are filled, \
+             imported modules from elsewhere are outlined.</p>\n\
+             <object data=\"modules.svg\" type=\"image/svg+xml\"></object>\n",
+        ),
+    )?;
+    put(dir, "strings.js", string_index(&strings, &pages))?;
+    put(dir, "strings.html", strings_page())?;
+    let sarif = sarif(input, &findings).to_sarif();
+    put(dir, "findings.sarif", format!("{sarif:#}\n"))?;
+    Ok(())
+}
+
+/// The findings as a [`ValidationReport`] about `input`.
+fn sarif(input: &Path, findings: &[Finding]) -> ValidationReport {
+    let mut report = ValidationReport::new(input.display().to_string());
+    for f in findings {
+        report.push(validation::Finding {
+            rule: f.rule.clone(),
+            level: f.level,
+            message: f.text.clone(),
+            method: f.method,
+            location: None,
+        });
+    }
+    report
+}
+
+/// Write `text` to `name` under `dir`.
+fn put(dir: &Path, name: impl AsRef<Path>, text: impl AsRef<[u8]>) -> io::Result<()> {
+    crate::write_output(&dir.join(name), text.as_ref())
+}
+
+/// Tagged entities, as recorded with `abcd-rs notes --tag`. Entities on
+/// no page are only listed if `all_classes` is set, that is if no class
+/// was left out of the report.
+fn note_findings(
+    abc: &abcd_file::File,
+    notes: &NoteStore,
+    pages: &[ClassPage],
+    all_classes: bool,
+) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for (entity, n) in notes.iter() {
+        if n.tags.is_empty() {
+            continue;
+        }
+        let class = pages
+            .iter()
+            .position(|p| p.off == entity || p.methods.iter().any(|(m, _, _)| *m == entity));
+        if class.is_none() && !all_classes {
+            continue;
+        }
+        let what = match class {
+            Some(i) if pages[i].off == entity => pages[i].name.clone(),
+            _ => abc.method_name_lossy(entity).into_owned(),
+        };
+        let tags: Vec<&str> = n.tags.iter().map(String::as_str).collect();
+        let is_method = class.is_some_and(|i| pages[i].off != entity);
+        findings.push(Finding {
+            rule: TAGGED.into(),
+            level: Level::Note,
+            text: format!("{what}: tagged {}", tags.join(", ")),
+            class,
+            method: is_method.then_some(entity),
+        });
+    }
+    findings
+}
+
+/// A page with the shared header. `up` leads from the page back to the
+/// site root.
+fn layout(title: &str, up: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\">\
+         <title>{title}</title><link rel=\"stylesheet\" href=\"{up}style.css\"></head>\n\
+         <body><nav><a href=\"{up}index.html\">Summary</a><a href=\"{up}classes.html\">Classes</a>\
+         <a href=\"{up}modules.html\">Modules</a><a href=\"{up}strings.html\">Strings</a></nav>\n\
+         {body}</body></html>\n",
+        title = escape(title)
+    )
+}
+
+fn index_page(
+    abc: &abcd_file::File,
+    input: &Path,
+    pages: &[ClassPage],
+    findings: &[Finding],
+    categories: &[u32],
+    cost: &CostEstimate,
+    num_strings: usize,
+) -> String {
+    let num_methods: usize = pages.iter().map(|p| p.methods.len()).sum();
+    let num_insns: usize = pages
+        .iter()
+        .flat_map(|p| &p.methods)
+        .map(|(_, _, n)| n)
+        .sum();
+    let mut body = String::new();
+    let _ = writeln!(
+        body,
+        "<h1>{}</h1>\n<table>",
+        escape(&input.display().to_string())
+    );
+    let rows = [
+        ("Version", abc.version().to_string()),
+        ("Size", format!("{} bytes", abc.raw_data().len())),
+        ("Classes", pages.len().to_string()),
+        (
+            "Modules",
+            pages
+                .iter()
+                .filter(|p| p.module.is_some())
+                .count()
+                .to_string(),
+        ),
+        ("Methods", num_methods.to_string()),
+        ("Instructions", num_insns.to_string()),
+        (
+            "Literal arrays",
+            abc.literal_array_offsets().len().to_string(),
+        ),
+        ("Strings used by code", num_strings.to_string()),
+        ("Estimated cost", cost.total().to_string()),
+    ];
+    for (label, value) in rows {
+        let _ = writeln!(body, "<tr><th>{label}</th><td>{}</td></tr>", escape(&value));
+    }
+    body.push_str("</table>\n<h2>Instruction mix</h2>\n<table>\n");
+    for category in OpcodeCategory::ALL {
+        let _ = writeln!(
+            body,
+            "<tr><td>{category}</td><td class=\"n\">{}</td></tr>",
+            categories[category as usize]
+        );
+    }
+    body.push_str("</table>\n<h2>Cost classes</h2>\n<table>\n");
+    for class in CostClass::ALL {
+        let _ = writeln!(
+            body,
+            "<tr><td>{class}</td><td class=\"n\">{}</td></tr>",
+            cost.count(class)
+        );
+    }
+    let _ = writeln!(body, "</table>\n<h2>Findings ({})</h2>", findings.len());
+    if findings.is_empty() {
+        body.push_str("<p>None.</p>\n");
+    } else {
+        body.push_str("<ul>\n");
+        for f in findings {
+            let text = escape(&f.text);
+            let text = match f.class {
+                Some(i) => format!("<a href=\"{}\">{text}</a>", pages[i].href(i)),
+                None => text,
+            };
+            let level = f.level.as_str();
+            let _ = writeln!(body, "<li class=\"{level}\">[{level}] {text}</li>");
+        }
+        body.push_str(
+            "</ul>\n<p>Also as a SARIF log: <a href=\"findings.sarif\">findings.sarif</a></p>\n",
+        );
+    }
+    layout("Summary", "", &body)
+}
+
+/// Classes nested by the directories of their module paths.
+#[derive(Default)]
+struct Tree<'p> {
+    dirs: BTreeMap<String, Tree<'p>>,
+    classes: Vec<(usize, &'p ClassPage)>,
+}
+
+impl Tree<'_> {
+    fn render(&self, out: &mut String) {
+        out.push_str("<ul class=\"tree\">\n");
+        for (name, sub) in &self.dirs {
+            let _ = writeln!(
+                out,
+                "<li><details open><summary>{}/</summary>",
+                escape(name)
+            );
+            sub.render(out);
+            out.push_str("</details></li>\n");
+        }
+        for (index, page) in &self.classes {
+            let _ = writeln!(
+                out,
+                "<li><a href=\"{}\">{}</a> ({} methods)</li>",
+                page.href(*index),
+                escape(&page.name),
+                page.methods.len()
+            );
+        }
+        out.push_str("</ul>\n");
+    }
+}
+
+fn class_tree_page(pages: &[ClassPage]) -> String {
+    let mut root = Tree::default();
+    for (index, page) in pages.iter().enumerate() {
+        let mut node = &mut root;
+        if let Some(parent) = page.rel_path.parent() {
+            for dir in parent.components() {
+                let dir = dir.as_os_str().to_string_lossy().into_owned();
+                node = node.dirs.entry(dir).or_default();
+            }
+        }
+        node.classes.push((index, page));
+    }
+    let mut body = format!("<h1>Classes ({})</h1>\n", pages.len());
+    root.render(&mut body);
+    layout("Classes", "", &body)
+}
+
+fn class_page(page: &ClassPage) -> String {
+    let mut body = format!(
+        "<h1>{}</h1>\n<p>Offset {:#x}, module <code>{}</code></p>\n<table>\n",
+        escape(&page.name),
+        page.off.0,
+        escape(&page.rel_path.display().to_string())
+    );
+    body.push_str("<tr><th>Offset</th><th>Instructions</th><th>Method</th></tr>\n");
+    for (off, name, len) in &page.methods {
+        let _ = writeln!(
+            body,
+            "<tr><td>{:#x}</td><td class=\"n\">{len}</td><td>{}</td></tr>",
+            off.0,
+            escape(name)
+        );
+    }
+    body.push_str("</table>\n<pre><code>");
+    body.push_str(&highlight_js(&page.source));
+    body.push_str("</code></pre>\n");
+    layout(&page.name, "../", &body)
+}
+
+/// The import graph of the file's modules as SVG, nodes on a circle.
+fn module_graph(pages: &[ClassPage]) -> String {
+    let local: BTreeSet<&str> = pages
+        .iter()
+        .filter_map(|p| p.module.as_ref())
+        .map(|m| m.key.as_str())
+        .collect();
+    let mut edges = BTreeSet::new();
+    let mut nodes: BTreeSet<String> = local.iter().map(|k| k.to_string()).collect();
+    for module in pages.iter().filter_map(|p| p.module.as_ref()) {
+        for request in &module.requests {
+            let target = request_key(&module.key, request);
+            nodes.insert(target.clone());
+            edges.insert((module.key.clone(), target));
+        }
+    }
+    let nodes: Vec<String> = nodes.into_iter().collect();
+    let radius = (nodes.len() as f64 * NODE_SPACING).max(120.0);
+    let margin = 260.0;
+    let size = 2.0 * (radius + margin);
+    let position = |i: usize| {
+        let angle = i as f64 / nodes.len().max(1) as f64 * std::f64::consts::TAU;
+        (
+            radius + margin + radius * angle.cos(),
+            radius + margin + radius * angle.sin(),
+        )
+    };
+    let index: BTreeMap<&str, usize> = nodes
+        .iter()
+        .enumerate()
+        .map(|(i, n)| (n.as_str(), i))
+        .collect();
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{size:.0}\" height=\"{size:.0}\" \
+         font-family=\"sans-serif\" font-size=\"11\">\n\
+         <defs><marker id=\"arrow\" viewBox=\"0 0 10 10\" refX=\"14\" refY=\"5\" \
+         markerWidth=\"6\" markerHeight=\"6\" orient=\"auto\">\
+         <path d=\"M0,0 L10,5 L0,10 z\" fill=\"#888\"/></marker></defs>\n"
+    );
+    for (from, to) in &edges {
+        let (x1, y1) = position(index[from.as_str()]);
+        let (x2, y2) = position(index[to.as_str()]);
+        let _ = writeln!(
+            svg,
+            "<line x1=\"{x1:.1}\" y1=\"{y1:.1}\" x2=\"{x2:.1}\" y2=\"{y2:.1}\" \
+             stroke=\"#bbb\" marker-end=\"url(#arrow)\"/>"
+        );
+    }
+    for (i, node) in nodes.iter().enumerate() {
+        let (x, y) = position(i);
+        let fill = if local.contains(node.as_str()) {
+            "#4a7bd0"
+        } else {
+            "#fff"
+        };
+        // Labels point away from the circle so they do not cross it.
+        let anchor = if x < radius + margin { "end" } else { "start" };
+        let dx = if anchor == "end" { -8.0 } else { 8.0 };
+        let _ = writeln!(
+            svg,
+            "<circle cx=\"{x:.1}\" cy=\"{y:.1}\" r=\"5\" fill=\"{fill}\" stroke=\"#4a7bd0\"/>\
+             <text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"{anchor}\">{}</text>",
+            x + dx,
+            y + 4.0,
+            escape(node)
+        );
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Module key of `request` made from the module `from`.
+fn request_key(from: &str, request: &str) -> String {
+    if !(request.starts_with("./") || request.starts_with("../")) {
+        return module_key(request);
+    }
+    let mut parts: Vec<&str> = from.split('/').collect();
+    parts.pop();
+    for part in request.split('/') {
+        match part {
+            "." => {}
+            ".." => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+    module_key(&parts.join("/"))
+}
+
+/// `strings.js`: every string code uses, with the classes using it.
+fn string_index(strings: &BTreeMap<String, BTreeSet<usize>>, pages: &[ClassPage]) -> String {
+    let entries: Vec<serde_json::Value> = strings
+        .iter()
+        .map(|(text, classes)| {
+            let classes: Vec<serde_json::Value> = classes
+                .iter()
+                .map(|&i| serde_json::json!([pages[i].name, pages[i].href(i)]))
+                .collect();
+            serde_json::json!([text, classes])
+        })
+        .collect();
+    format!("const STRINGS = {};\n", serde_json::Value::Array(entries))
+}
+
+fn strings_page() -> String {
+    let body = "<h1>Strings</h1>\n\
+        <input id=\"q\" type=\"search\" placeholder=\"Search strings\" size=\"60\" autofocus>\n\
+        <p id=\"count\"></p>\n<ul id=\"results\"></ul>\n\
+        <script src=\"strings.js\"></script>\n<script>\n\
+        const LIMIT = 500;\n\
+        function esc(s) { return s.replace(/[&<>\"]/g, c => ({'&':'&amp;','<':'&lt;','>':'&gt;','\"':'&quot;'})[c]); }\n\
+        function show() {\n\
+          const q = document.getElementById('q').value.toLowerCase();\n\
+          const hits = STRINGS.filter(([s]) => s.toLowerCase().includes(q));\n\
+          document.getElementById('count').textContent = hits.length + ' of ' + STRINGS.length + ' strings';\n\
+          document.getElementById('results').innerHTML = hits.slice(0, LIMIT).map(([s, cs]) =>\n\
+            '<li>' + esc(JSON.stringify(s)) + ' &mdash; ' +\n\
+            cs.map(([n, h]) => '<a href=\"' + h + '\">' + esc(n) + '</a>').join(', ') + '</li>').join('');\n\
+        }\n\
+        document.getElementById('q').addEventListener('input', show);\n\
+        show();\n\
+        </script>\n";
+    layout("Strings", "", body)
+}
+
+/// `source` as HTML, with keywords, strings, numbers and comments marked.
+fn highlight_js(source: &str) -> String {
+    let chars: Vec<char> = source.chars().collect();
+    let mut out = String::with_capacity(source.len() * 2);
+    let mut i = 0;
+    let span = |out: &mut String, class: &str, text: &[char]| {
+        let text: String = text.iter().collect();
+        let _ = write!(out, "<span class=\"{class}\">{}</span>", escape(&text));
+    };
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        if c == '/' && chars.get(i + 1) == Some(&'/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            span(&mut out, "com", &chars[start..i]);
+        } else if c == '/' && chars.get(i + 1) == Some(&'*') {
+            // The `*/` closing the comment starts after the `/*` opening
+            // it, so `/*/` is still open.
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                i += 1;
+            }
+            i = (i + 2).min(chars.len());
+            span(&mut out, "com", &chars[start..i]);
+        } else if c == '"' || c == '\'' || c == '`' {
+            i += 1;
+            while i < chars.len() && chars[i] != c {
+                if chars[i] == '\\' {
+                    i += 1;
+                }
+                i += 1;
+            }
+            i = (i + 1).min(chars.len());
+            span(&mut out, "str", &chars[start..i]);
+        } else if c.is_ascii_digit() {
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '.') {
+                i += 1;
+            }
+            span(&mut out, "num", &chars[start..i]);
+        } else if c.is_alphabetic() || c == '_' || c == '$' {
+            while i < chars.len()
+                && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '$')
+            {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            if JS_KEYWORDS.contains(&word.as_str()) {
+                span(&mut out, "kw", &chars[start..i]);
+            } else {
+                out.push_str(&escape(&word));
+            }
+        } else {
+            let mut buf = [0; 4];
+            out.push_str(&escape(c.encode_utf8(&mut buf)));
+            i += 1;
+        }
+    }
+    out
+}
+
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(key: &str, requests: &[&str]) -> ClassPage {
+        ClassPage {
+            off: EntityId(0),
+            name: key.to_string(),
+            rel_path: PathBuf::from(key),
+            source: String::new(),
+            methods: Vec::new(),
+            module: Some(ModuleNode {
+                key: key.to_string(),
+                requests: requests.iter().map(|r| r.to_string()).collect(),
+            }),
+        }
+    }
+
+    #[test]
+    fn escape_covers_markup_and_attributes() {
+        assert_eq!(
+            escape("a<b && c>\"d\""),
+            "a&lt;b &amp;&amp; c&gt;&quot;d&quot;"
+        );
+        assert_eq!(escape("plain"), "plain");
+    }
+
+    #[test]
+    fn highlight_marks_each_token_kind() {
+        assert_eq!(
+            highlight_js("return x < 1;"),
+            "<span class=\"kw\">return</span> x &lt; <span class=\"num\">1</span>;"
+        );
+        assert_eq!(
+            highlight_js("f('a\\'b') // c"),
+            "f(<span class=\"str\">'a\\'b'</span>) <span class=\"com\">// c</span>"
+        );
+    }
+
+    #[test]
+    fn block_comments_close_only_after_they_open() {
+        assert_eq!(
+            highlight_js("/*/ x */y"),
+            "<span class=\"com\">/*/ x */</span>y"
+        );
+        assert_eq!(
+            highlight_js("/**/1"),
+            "<span class=\"com\">/**/</span><span class=\"num\">1</span>"
+        );
+        // Unclosed, the comment runs to the end.
+        assert_eq!(highlight_js("/* x"), "<span class=\"com\">/* x</span>");
+    }
+
+    #[test]
+    fn relative_requests_resolve_against_the_importing_module() {
+        assert_eq!(
+            request_key("entry/pages/Index", "./Util"),
+            "entry/pages/Util"
+        );
+        assert_eq!(
+            request_key("entry/pages/Index", "../common/Log.js"),
+            "entry/common/Log"
+        );
+        assert_eq!(
+            request_key("entry/pages/Index", "@ohos.router"),
+            "@ohos.router"
+        );
+    }
+
+    #[test]
+    fn module_graph_fills_local_modules_and_draws_each_import() {
+        let pages = [
+            page("entry/Index", &["./Util", "@ohos.router"]),
+            page("entry/Util", &[]),
+        ];
+        let svg = module_graph(&pages);
+        assert_eq!(svg.matches("<circle").count(), 3, "{svg}");
+        assert_eq!(svg.matches("<line").count(), 2, "{svg}");
+        assert_eq!(svg.matches("fill=\"#4a7bd0\"").count(), 2, "{svg}");
+        assert!(svg.contains(">@ohos.router</text>"), "{svg}");
+    }
+
+    #[test]
+    fn findings_keep_their_rule_level_and_method_in_sarif() {
+        let findings = [
+            Finding {
+                rule: validation::UNKNOWN_OPCODES.into(),
+                level: Level::Warning,
+                text: "LA;.f: no translation".into(),
+                class: Some(0),
+                method: Some(EntityId(0x40)),
+            },
+            Finding {
+                rule: TAGGED.into(),
+                level: Level::Note,
+                text: "LA;: tagged suspicious".into(),
+                class: Some(0),
+                method: None,
+            },
+        ];
+        let sarif = sarif(Path::new("app.abc"), &findings).to_sarif();
+        let run = &sarif["runs"][0];
+        assert_eq!(run["artifacts"][0]["location"]["uri"], "app.abc");
+        let rules: Vec<_> = run["tool"]["driver"]["rules"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["id"].as_str().unwrap())
+            .collect();
+        assert_eq!(rules, [validation::UNKNOWN_OPCODES, TAGGED]);
+        let results = run["results"].as_array().unwrap();
+        assert_eq!(results[0]["level"], "warning");
+        assert_eq!(
+            results[0]["locations"][0]["physicalLocation"]["region"]["byteOffset"],
+            0x40
+        );
+        assert_eq!(results[1]["ruleIndex"], 1);
+        assert_eq!(results[1]["message"]["text"], "LA;: tagged suspicious");
+    }
+}
@@ -0,0 +1,350 @@
+//! `selftest`: build a small file in memory and take it through every
+//! stage a real input goes through, to check a local build (and the C++
+//! runtime vendored into it) after a vendor sync.
+//!
+//! The fixture is one class with one method, `try { v0 = 1 } catch
+//! { v0 = 0 } return v0`, assembled with an `Emitter`. Each stage is timed
+//! and its result checked against what the fixture is known to contain;
+//! the stages after a failed one need its result and are skipped. A stage
+//! that panics fails like one that returns an error, so a crash in the
+//! vendored code is reported with the stage it happened in.
+
+use std::any::Any;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::time::{Duration, Instant};
+
+use abcd_file::builder::{Builder, CatchBlockDef};
+use abcd_file::notes::NoteStore;
+use abcd_file::{ACC_PUBLIC, BackendInfo, EntityId, File, TypeId};
+use abcd_isa::{Emitter, Imm, Reg, Version, insn};
+
+use crate::RecordFilter;
+
+/// Every stage, in the order they run.
+const STAGES: [&str; 6] = ["build", "parse", "disasm", "decompile", "rewrite", "reopen"];
+
+const CLASS: &str = "L_GLOBAL;";
+const METHOD: &str = "selftest";
+const RENAMED: &str = "selftest_renamed";
+
+/// What a run found: the capabilities of this build and how each stage
+/// went.
+pub struct Report {
+    pub backend: BackendInfo,
+    pub stages: Vec<Stage>,
+}
+
+pub struct Stage {
+    pub name: &'static str,
+    pub elapsed: Duration,
+    pub outcome: Outcome,
+}
+
+pub enum Outcome {
+    /// Passed, with a short description of what came out.
+    Passed(String),
+    Failed(String),
+    /// Not run, because an earlier stage failed.
+    Skipped,
+}
+
+impl Report {
+    pub fn failures(&self) -> impl Iterator<Item = (&'static str, &str)> {
+        self.stages.iter().filter_map(|s| match &s.outcome {
+            Outcome::Failed(e) => Some((s.name, e.as_str())),
+            _ => None,
+        })
+    }
+
+    pub fn write_text(&self, mut out: impl io::Write) -> io::Result<()> {
+        let backend = [
+            (self.backend.builder, "builder"),
+            (self.backend.debug_info, "debug-info"),
+            (self.backend.module, "module"),
+        ]
+        .iter()
+        .filter(|(on, _)| *on)
+        .map(|(_, name)| *name)
+        .collect::<Vec<_>>()
+        .join(", ");
+        writeln!(out, "abcd-rs {}", env!("CARGO_PKG_VERSION"))?;
+        writeln!(
+            out,
+            "ISA:      {} opcodes, file versions {} to {}",
+            abcd_isa::opcode_table().len(),
+            Version::min_supported(),
+            Version::current()
+        )?;
+        writeln!(out, "Backend:  {backend}")?;
+        writeln!(out)?;
+        for stage in &self.stages {
+            let (status, detail) = match &stage.outcome {
+                Outcome::Passed(detail) => ("ok", detail.as_str()),
+                Outcome::Failed(e) => ("FAILED", e.as_str()),
+                Outcome::Skipped => ("skipped", ""),
+            };
+            writeln!(
+                out,
+                "{:<10} {status:<8} {:>9.3} ms  {detail}",
+                stage.name,
+                stage.elapsed.as_secs_f64() * 1e3
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let stages = self
+            .stages
+            .iter()
+            .map(|stage| {
+                let (status, detail) = match &stage.outcome {
+                    Outcome::Passed(detail) => ("ok", Some(detail)),
+                    Outcome::Failed(e) => ("failed", Some(e)),
+                    Outcome::Skipped => ("skipped", None),
+                };
+                serde_json::json!({
+                    "name": stage.name,
+                    "status": status,
+                    "ms": stage.elapsed.as_secs_f64() * 1e3,
+                    "detail": detail,
+                })
+            })
+            .collect::<Vec<_>>();
+        serde_json::json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "isa": {
+                "opcodes": abcd_isa::opcode_table().len(),
+                "min_version": Version::min_supported().to_string(),
+                "max_version": Version::current().to_string(),
+            },
+            "backend": {
+                "builder": self.backend.builder,
+                "debug_info": self.backend.debug_info,
+                "module": self.backend.module,
+            },
+            "stages": stages,
+        })
+    }
+}
+
+/// Run every stage.
+pub fn run() -> Report {
+    let mut stages = Vec::new();
+    pipeline(&mut stages);
+    for &name in &STAGES[stages.len()..] {
+        stages.push(Stage {
+            name,
+            elapsed: Duration::ZERO,
+            outcome: Outcome::Skipped,
+        });
+    }
+    Report {
+        backend: abcd_file::backend_info(),
+        stages,
+    }
+}
+
+/// Run the stages in order, stopping at the first that fails.
+fn pipeline(stages: &mut Vec<Stage>) -> Option<()> {
+    let mut stage = |f: &mut dyn FnMut() -> Result<String, String>| {
+        let stage = run_stage(STAGES[stages.len()], f);
+        let passed = !matches!(stage.outcome, Outcome::Failed(_));
+        stages.push(stage);
+        passed.then_some(())
+    };
+
+    let mut bytes = Vec::new();
+    stage(&mut || {
+        bytes = build_fixture().map_err(|e| e.to_string())?;
+        Ok(format!("{} bytes", bytes.len()))
+    })?;
+
+    let mut abc = None;
+    stage(&mut || {
+        let file = File::open(std::mem::take(&mut bytes)).map_err(|e| e.to_string())?;
+        file.check_version().map_err(|e| e.to_string())?;
+        let detail = format!(
+            "version {}, {} class(es)",
+            file.version(),
+            file.num_classes()
+        );
+        abc = Some(file);
+        Ok(detail)
+    })?;
+    let abc = abc?;
+
+    stage(&mut || {
+        let mut out = Vec::new();
+        crate::write_disasm(
+            &abc,
+            &NoteStore::default(),
+            None,
+            &RecordFilter::default(),
+            &mut out,
+        )
+        .map_err(|e| e.to_string())?;
+        let text = String::from_utf8_lossy(&out);
+        expect(&text, &[METHOD, "ldai", "jmp", "return"])?;
+        Ok(format!("{} lines", text.lines().count()))
+    })?;
+
+    stage(&mut || {
+        let class = abc.class(class_off(&abc)?).map_err(|e| e.to_string())?;
+        let debug = abc.debug_info().ok();
+        let body =
+            crate::render_class_body(&abc, &class, None, debug.as_ref(), None, None, &mut ());
+        expect(&body, &[METHOD, "try", "catch", "return"])?;
+        Ok(format!("{} lines", body.lines().count()))
+    })?;
+
+    let mut rewritten = Vec::new();
+    stage(&mut || {
+        let name_off = abc.method_name_off(method_off(&abc, METHOD)?);
+        rewritten =
+            abcd_file::edit::replace_string(&abc, name_off, RENAMED).map_err(|e| e.to_string())?;
+        Ok(format!("{} bytes", rewritten.len()))
+    })?;
+
+    stage(&mut || {
+        let file = File::open(std::mem::take(&mut rewritten)).map_err(|e| e.to_string())?;
+        if method_off(&file, METHOD).is_ok() {
+            return Err(format!("{METHOD} is still there"));
+        }
+        let before = method_code(&abc, METHOD)?;
+        let after = method_code(&file, RENAMED)?;
+        if before != after {
+            return Err(format!("{RENAMED} has other instructions than {METHOD}"));
+        }
+        let insns = abcd_isa::decode(&after).map_err(|e| e.to_string())?;
+        Ok(format!(
+            "renamed {METHOD} to {RENAMED}, {} instructions unchanged",
+            insns.len()
+        ))
+    })
+}
+
+/// Run `f` as the stage `name` and time it. A panic fails the stage.
+fn run_stage(name: &'static str, f: &mut dyn FnMut() -> Result<String, String>) -> Stage {
+    let start = Instant::now();
+    let result = panic::catch_unwind(AssertUnwindSafe(f))
+        .unwrap_or_else(|payload| Err(format!("panicked: {}", panic_message(&*payload))));
+    Stage {
+        name,
+        elapsed: start.elapsed(),
+        outcome: match result {
+            Ok(detail) => Outcome::Passed(detail),
+            Err(e) => Outcome::Failed(e),
+        },
+    }
+}
+
+/// `try { v0 = 1 } catch { v0 = 0 } return v0` in [`CLASS`].
+pub(crate) fn build_fixture() -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut e = Emitter::new();
+    let done = e.create_label();
+    let try_start = e.len();
+    e.emit(insn::Ldai::new(Imm(1)));
+    e.emit(insn::Sta::new(Reg(0)));
+    e.emit(insn::Jmp::new(done));
+    let handler = e.len();
+    e.emit(insn::Ldai::new(Imm(0)));
+    e.emit(insn::Sta::new(Reg(0)));
+    let handler_end = e.len();
+    e.bind(done);
+    e.emit(insn::Lda::new(Reg(0)));
+    e.emit(insn::Return::new());
+    let (bytes, pc) = e.build()?;
+
+    let mut b = Builder::new()?;
+    b.set_api(12, "")?;
+    let class = b.add_class(CLASS)?;
+    let proto = b.create_proto(TypeId::Tagged, &[]);
+    let method = b.class_add_method_with_proto(class, METHOD, proto, ACC_PUBLIC, &[], 0, 0)?;
+    let code = b.create_code(1, 3, &bytes);
+    let catch_all = CatchBlockDef {
+        type_class: None,
+        handler_pc: pc[handler],
+        code_size: pc[handler_end] - pc[handler],
+    };
+    b.code_add_try_block(
+        code,
+        pc[try_start],
+        pc[handler] - pc[try_start],
+        &[catch_all],
+    );
+    b.method_set_code(method, code);
+    Ok(b.finalize()?)
+}
+
+fn class_off(abc: &File) -> Result<EntityId, String> {
+    abc.class_id_by_name(CLASS)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("no class {CLASS}"))
+}
+
+fn method_off(abc: &File, name: &str) -> Result<EntityId, String> {
+    let class = abc.class(class_off(abc)?).map_err(|e| e.to_string())?;
+    class
+        .method_offsets()
+        .into_iter()
+        .find(|&m| abc.method_name(m).is_ok_and(|n| n == name))
+        .ok_or_else(|| format!("no method {name}"))
+}
+
+/// The instruction bytes of the method `name` of [`CLASS`].
+fn method_code(abc: &File, name: &str) -> Result<Vec<u8>, String> {
+    let method = abc
+        .method(method_off(abc, name)?)
+        .map_err(|e| e.to_string())?;
+    let code_off = method
+        .code_off()
+        .ok_or_else(|| format!("{name} has no code"))?;
+    let code = abc.code(code_off).map_err(|e| e.to_string())?;
+    Ok(code.instructions().to_vec())
+}
+
+/// The message a panic was raised with, if it is a string.
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    match payload.downcast_ref::<&str>() {
+        Some(msg) => msg,
+        None => payload
+            .downcast_ref::<String>()
+            .map_or("non-string payload", String::as_str),
+    }
+}
+
+/// Fail unless `text` contains each of `words`.
+fn expect(text: &str, words: &[&str]) -> Result<(), String> {
+    match words.iter().find(|w| !text.contains(*w)) {
+        Some(missing) => Err(format!("output has no `{missing}`")),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_stage_passes() {
+        let report = run();
+        let failures = report.failures().collect::<Vec<_>>();
+        assert!(failures.is_empty(), "{failures:?}");
+        assert_eq!(report.stages.len(), STAGES.len());
+        match &report.stages[5].outcome {
+            Outcome::Passed(detail) => assert!(detail.ends_with("instructions unchanged")),
+            _ => panic!("reopen did not pass"),
+        }
+    }
+
+    #[test]
+    fn a_panicking_stage_fails() {
+        let stage = run_stage("decompile", &mut || panic!("index out of bounds"));
+        match stage.outcome {
+            Outcome::Failed(e) => assert_eq!(e, "panicked: index out of bounds"),
+            _ => panic!("the stage did not fail"),
+        }
+    }
+}
@@ -0,0 +1,161 @@
+//! Decompiled classes gathered per source file.
+//!
+//! A source file can compile to several classes: a module and the partial
+//! records split off it all name the same `source_file`. Each class is
+//! decompiled on its own, with its own module record, so writing them out
+//! one after another repeats imports and leaves the order to wherever the
+//! compiler happened to put each class. [`SourceFiles`] collects every class
+//! of a file first and renders the file once: the union of the imports and
+//! re-exports without repeats, the classes' code ordered by record name, and
+//! a single `export { ... }` list at the end.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// One class's share of its source file.
+pub(crate) struct ClassSource {
+    /// Record name, which orders the classes of a file.
+    pub(crate) name: String,
+    /// `import` and `export ... from` statements, one per entry.
+    pub(crate) imports: Vec<String>,
+    /// Everything between the imports and the local exports.
+    pub(crate) body: String,
+    /// Local export specifiers: `name` or `local as name`.
+    pub(crate) exports: Vec<String>,
+}
+
+impl ClassSource {
+    /// The class as a file of its own.
+    pub(crate) fn text(&self) -> String {
+        render(&[self])
+    }
+}
+
+/// Classes grouped by output path, files in the order first seen.
+#[derive(Default)]
+pub(crate) struct SourceFiles {
+    files: Vec<(PathBuf, Vec<ClassSource>)>,
+    index: HashMap<PathBuf, usize>,
+}
+
+impl SourceFiles {
+    pub(crate) fn add(&mut self, path: PathBuf, class: ClassSource) {
+        let i = *self.index.entry(path.clone()).or_insert_with(|| {
+            self.files.push((path, Vec::new()));
+            self.files.len() - 1
+        });
+        self.files[i].1.push(class);
+    }
+
+    /// Every file with its merged source.
+    pub(crate) fn render(&self) -> impl Iterator<Item = (&Path, String)> {
+        self.files.iter().map(|(path, classes)| {
+            let mut classes: Vec<&ClassSource> = classes.iter().collect();
+            classes.sort_by(|a, b| a.name.cmp(&b.name));
+            (path.as_path(), render(&classes))
+        })
+    }
+}
+
+fn render(classes: &[&ClassSource]) -> String {
+    let mut out = String::new();
+    let statements = |reexport: bool| {
+        classes
+            .iter()
+            .flat_map(|c| &c.imports)
+            .filter(move |s| s.starts_with("export") == reexport)
+    };
+    // Imports before re-exports, each statement once.
+    let mut header: Vec<&String> = Vec::new();
+    for s in statements(false).chain(statements(true)) {
+        if !header.contains(&s) {
+            header.push(s);
+        }
+    }
+    for s in &header {
+        out.push_str(s);
+        out.push('\n');
+    }
+    if !header.is_empty() {
+        out.push('\n');
+    }
+
+    for class in classes {
+        out.push_str(&class.body);
+    }
+
+    let mut exports: Vec<&str> = Vec::new();
+    for e in classes.iter().flat_map(|c| &c.exports) {
+        if !exports.contains(&e.as_str()) {
+            exports.push(e);
+        }
+    }
+    if !exports.is_empty() {
+        out.push_str(&format!("export {{ {} }};\n", exports.join(", ")));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn class(name: &str, imports: &[&str], body: &str, exports: &[&str]) -> ClassSource {
+        ClassSource {
+            name: name.to_string(),
+            imports: imports.iter().map(|s| s.to_string()).collect(),
+            body: body.to_string(),
+            exports: exports.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn classes_of_a_file_are_merged() {
+        let mut files = SourceFiles::default();
+        files.add(
+            "a.js".into(),
+            class(
+                "&a&2",
+                &["export * from './c';", "import { x } from './b';"],
+                "class Two {}\n",
+                &["Two"],
+            ),
+        );
+        files.add(
+            "a.js".into(),
+            class(
+                "&a&1",
+                &["import { x } from './b';"],
+                "class One {}\n",
+                &["One", "Two"],
+            ),
+        );
+        let rendered: Vec<_> = files.render().collect();
+        assert_eq!(rendered.len(), 1);
+        assert_eq!(
+            rendered[0].1,
+            "import { x } from './b';\n\
+             export * from './c';\n\
+             \n\
+             class One {}\n\
+             class Two {}\n\
+             export { One, Two };\n"
+        );
+    }
+
+    #[test]
+    fn files_keep_the_order_first_seen() {
+        let mut files = SourceFiles::default();
+        files.add("b.js".into(), class("b", &[], "b\n", &[]));
+        files.add("a.js".into(), class("a", &[], "a\n", &[]));
+        files.add("b.js".into(), class("b2", &[], "b2\n", &[]));
+        let paths: Vec<_> = files.render().map(|(p, _)| p.to_path_buf()).collect();
+        assert_eq!(paths, [PathBuf::from("b.js"), PathBuf::from("a.js")]);
+    }
+
+    #[test]
+    fn a_class_without_a_module_record_is_just_its_body() {
+        let source = class("L_GLOBAL;", &[], "function f() {}\n", &[]);
+        assert_eq!(source.text(), "function f() {}\n");
+    }
+}
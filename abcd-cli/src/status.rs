@@ -0,0 +1,220 @@
+//! Exit status and `--deny`.
+//!
+//! Every subcommand exits with
+//!
+//! - [`CLEAN`] (0) when it ran and found nothing to gate on,
+//! - [`FINDINGS`] (1) when it found something: a method `verify` does not
+//!   trust, or anything of a kind named by `--deny`,
+//! - [`ERROR`] (2) when it could not do all it was asked: unreadable input,
+//!   a class that does not parse, an output that could not be written.
+//!   clap exits with 2 on bad arguments as well.
+//!
+//! Errors win over findings, so a script can tell "the build has problems"
+//! from "the tool could not check the build".
+//!
+//! Warnings the libraries log through `log` count as warnings too, whether
+//! or not `RUST_LOG` lets them through to stderr; see [`init_logger`].
+
+use std::fmt;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+use abcd_file::validation;
+use clap::ValueEnum;
+use log::Log as _;
+
+pub const CLEAN: i32 = 0;
+pub const FINDINGS: i32 = 1;
+pub const ERROR: i32 = 2;
+
+/// Kinds of findings `--deny` turns into exit status [`FINDINGS`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Deny {
+    /// Anything reported as `Warning:`
+    Warnings,
+    /// Code that does not decode, or that has instructions the decompiler
+    /// has no translation for
+    UnknownOpcodes,
+    /// ID operands that name nothing in the file
+    UnresolvedEntities,
+}
+
+impl Deny {
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+
+    /// The name `--deny` takes, also the rule id of the finding in SARIF.
+    pub fn name(self) -> &'static str {
+        match self {
+            Deny::Warnings => "warnings",
+            Deny::UnknownOpcodes => validation::UNKNOWN_OPCODES,
+            Deny::UnresolvedEntities => validation::UNRESOLVED_ENTITIES,
+        }
+    }
+}
+
+static DENIED: AtomicU8 = AtomicU8::new(0);
+/// Whether a progress line is on screen; see [`crate::progress`].
+static PROGRESS: AtomicBool = AtomicBool::new(false);
+static FOUND: AtomicU8 = AtomicU8::new(0);
+static FLAGGED: AtomicBool = AtomicBool::new(false);
+static FAILED: AtomicBool = AtomicBool::new(false);
+
+/// Gate the exit status on `kinds`.
+pub fn deny(kinds: &[Deny]) {
+    let bits = kinds.iter().fold(0, |bits, kind| bits | kind.bit());
+    DENIED.fetch_or(bits, Ordering::Relaxed);
+}
+
+/// Whether `--deny` names `kind`; checks that only feed the exit status
+/// are skipped otherwise.
+pub fn is_denied(kind: Deny) -> bool {
+    DENIED.load(Ordering::Relaxed) & kind.bit() != 0
+}
+
+/// Note that a progress line is, or is no longer, on stderr.
+pub fn set_progress_shown(shown: bool) {
+    PROGRESS.store(shown, Ordering::Relaxed);
+}
+
+/// Erase the progress line, if one is shown, so a message printed next
+/// starts on a clean line. The line comes back with the next update.
+pub fn clear_progress() {
+    if PROGRESS.swap(false, Ordering::Relaxed) {
+        eprint!("\r\x1b[2K");
+        let _ = std::io::stderr().flush();
+    }
+}
+
+/// Print a warning and remember that there was one.
+pub fn warn(msg: impl fmt::Display) {
+    clear_progress();
+    eprintln!("Warning: {msg}");
+    FOUND.fetch_or(Deny::Warnings.bit(), Ordering::Relaxed);
+}
+
+/// Report a finding of `kind` if it is denied; otherwise the listing
+/// already shows it and there is nothing to add.
+pub fn finding(kind: Deny, msg: impl fmt::Display) {
+    if is_denied(kind) {
+        clear_progress();
+        eprintln!("Denied ({}): {msg}", kind.name());
+        FOUND.fetch_or(kind.bit(), Ordering::Relaxed);
+    }
+}
+
+/// Remember findings a subcommand gates on by itself, such as methods off
+/// the `verify` allowlist.
+pub fn flag() {
+    FLAGGED.store(true, Ordering::Relaxed);
+}
+
+/// Print an error the command recovers from, and remember that there was
+/// one.
+pub fn error(msg: impl fmt::Display) {
+    clear_progress();
+    eprintln!("{msg}");
+    FAILED.store(true, Ordering::Relaxed);
+}
+
+/// Forget everything reported so far, for `--watch`, whose exit status is
+/// that of the last round.
+pub fn reset() {
+    FOUND.store(0, Ordering::Relaxed);
+    FLAGGED.store(false, Ordering::Relaxed);
+    FAILED.store(false, Ordering::Relaxed);
+}
+
+/// The exit status for everything reported so far.
+pub fn code() -> i32 {
+    exit_code(
+        FAILED.load(Ordering::Relaxed),
+        FLAGGED.load(Ordering::Relaxed),
+        FOUND.load(Ordering::Relaxed),
+        DENIED.load(Ordering::Relaxed),
+    )
+}
+
+/// The exit status given whether there was an error, whether a subcommand
+/// flagged something, and the kinds found and denied as [`Deny::bit`] sets.
+fn exit_code(failed: bool, flagged: bool, found: u8, denied: u8) -> i32 {
+    if failed {
+        ERROR
+    } else if found & denied != 0 || flagged {
+        FINDINGS
+    } else {
+        CLEAN
+    }
+}
+
+/// `env_logger`, filtered by `RUST_LOG` as usual, that also counts every
+/// record at warning level or above as a warning for `--deny warnings`.
+struct Logger(env_logger::Logger);
+
+impl log::Log for Logger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::Level::Warn || self.0.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        if record.level() <= log::Level::Warn {
+            FOUND.fetch_or(Deny::Warnings.bit(), Ordering::Relaxed);
+        }
+        if self.0.matches(record) {
+            clear_progress();
+            self.0.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.0.flush();
+    }
+}
+
+/// Install the logger. Warnings are always passed to it, so a library
+/// warning gates `--deny warnings` the same with or without `RUST_LOG`.
+pub fn init_logger() {
+    let logger = env_logger::Builder::from_default_env().build();
+    let level = logger.filter().max(log::LevelFilter::Warn);
+    if log::set_logger(Box::leak(Box::new(Logger(logger)))).is_ok() {
+        log::set_max_level(level);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_win_over_findings() {
+        let all = Deny::Warnings.bit() | Deny::UnknownOpcodes.bit();
+        assert_eq!(exit_code(true, true, all, all), ERROR);
+        assert_eq!(exit_code(true, false, 0, 0), ERROR);
+    }
+
+    #[test]
+    fn only_denied_kinds_are_findings() {
+        let warnings = Deny::Warnings.bit();
+        let opcodes = Deny::UnknownOpcodes.bit();
+        assert_eq!(exit_code(false, false, warnings, 0), CLEAN);
+        assert_eq!(exit_code(false, false, warnings, opcodes), CLEAN);
+        assert_eq!(
+            exit_code(false, false, warnings | opcodes, opcodes),
+            FINDINGS
+        );
+        assert_eq!(exit_code(false, true, 0, 0), FINDINGS);
+        assert_eq!(exit_code(false, false, 0, 0), CLEAN);
+    }
+
+    #[test]
+    fn kinds_have_distinct_bits_and_their_clap_names() {
+        let kinds = Deny::value_variants();
+        let bits = kinds.iter().fold(0, |bits, kind| bits | kind.bit());
+        assert_eq!(bits.count_ones() as usize, kinds.len());
+        for kind in kinds {
+            let value = kind.to_possible_value().unwrap();
+            assert_eq!(value.get_name(), kind.name());
+        }
+    }
+}
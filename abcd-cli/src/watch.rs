@@ -0,0 +1,254 @@
+//! `--watch`: redo a command whenever its inputs change.
+//!
+//! Inputs are polled rather than watched through the OS, which behaves the
+//! same on every platform and on network mounts. A change is acted on once
+//! the file has stopped growing, so a bundle still being pushed to the
+//! device is not read half-written, and only if its content differs from
+//! the last round: touching a file or pushing the same build again does
+//! nothing.
+//!
+//! Ctrl-C ends the watch after the round in progress, and the command exits
+//! with the status of the last round; a second Ctrl-C stops it at once.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use abcd_db::Digest;
+
+use crate::status;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Cheap change check: size and modification time, `None` if missing.
+type Stamp = Option<(u64, Option<SystemTime>)>;
+
+fn stamps(paths: &[PathBuf]) -> Vec<Stamp> {
+    paths
+        .iter()
+        .map(|p| {
+            let meta = fs::metadata(p).ok()?;
+            Some((meta.len(), meta.modified().ok()))
+        })
+        .collect()
+}
+
+fn digests(paths: &[PathBuf]) -> Vec<Option<Digest>> {
+    paths
+        .iter()
+        .map(|p| fs::read(p).ok().map(|data| Digest::of(&data)))
+        .collect()
+}
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(unix)]
+extern "C" fn on_interrupt(_: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::Relaxed);
+    // Only the first Ctrl-C waits for the round to finish.
+    unsafe { libc::signal(libc::SIGINT, libc::SIG_DFL) };
+}
+
+/// Catch Ctrl-C so [`run`] can return; elsewhere it kills the process.
+fn catch_interrupt() {
+    #[cfg(unix)]
+    unsafe {
+        libc::signal(
+            libc::SIGINT,
+            on_interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t,
+        );
+    }
+}
+
+/// Sleep for a poll interval; `false` if Ctrl-C came in meanwhile.
+fn wait() -> bool {
+    thread::sleep(POLL_INTERVAL);
+    !INTERRUPTED.load(Ordering::Relaxed)
+}
+
+/// Run `round` now and again after every change to `paths`, until Ctrl-C.
+/// Paths that do not exist yet are watched for their creation. The exit
+/// status reflects only the last round.
+pub(crate) fn run(paths: &[PathBuf], mut round: impl FnMut()) {
+    catch_interrupt();
+    let mut seen = stamps(paths);
+    let mut content = digests(paths);
+    round();
+    eprintln!("watching {} for changes (Ctrl-C to stop)", describe(paths));
+    loop {
+        if !wait() {
+            return;
+        }
+        let now = stamps(paths);
+        if now == seen {
+            continue;
+        }
+        // Wait for writers to finish.
+        seen = now;
+        loop {
+            if !wait() {
+                return;
+            }
+            let now = stamps(paths);
+            if now == seen {
+                break;
+            }
+            seen = now;
+        }
+        let now = digests(paths);
+        if now == content {
+            continue;
+        }
+        content = now;
+        eprintln!("change detected, regenerating");
+        status::reset();
+        round();
+    }
+}
+
+fn describe(paths: &[PathBuf]) -> String {
+    let names: Vec<String> = paths.iter().map(|p| p.display().to_string()).collect();
+    names.join(", ")
+}
+
+/// Files written to an output directory by earlier rounds.
+#[derive(Default)]
+pub(crate) struct Synced {
+    files: BTreeSet<PathBuf>,
+}
+
+/// What one [`Synced::sync`] did.
+pub(crate) struct SyncStats {
+    pub(crate) written: usize,
+    pub(crate) removed: usize,
+    pub(crate) unchanged: usize,
+}
+
+impl Synced {
+    /// Make `dest` hold what `staging` holds: files whose content changed
+    /// are rewritten, unchanged ones are left alone so their timestamps
+    /// survive, and files an earlier round wrote but this one did not are
+    /// removed. Files in `dest` that no round wrote are never touched.
+    pub(crate) fn sync(&mut self, staging: &Path, dest: &Path) -> io::Result<SyncStats> {
+        let mut produced = BTreeSet::new();
+        collect_files(staging, Path::new(""), &mut produced)?;
+        let mut stats = SyncStats {
+            written: 0,
+            removed: 0,
+            unchanged: 0,
+        };
+        for rel in &produced {
+            let data = fs::read(staging.join(rel))?;
+            let target = dest.join(rel);
+            if fs::read(&target).is_ok_and(|old| old == data) {
+                stats.unchanged += 1;
+                continue;
+            }
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&target, data)?;
+            stats.written += 1;
+        }
+        for stale in self.files.difference(&produced) {
+            if fs::remove_file(dest.join(stale)).is_ok() {
+                stats.removed += 1;
+            }
+        }
+        self.files = produced;
+        Ok(stats)
+    }
+}
+
+/// Paths of every file under `dir`, relative to the walk's root.
+fn collect_files(dir: &Path, rel: &Path, out: &mut BTreeSet<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let rel = rel.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            collect_files(&entry.path(), &rel, out)?;
+        } else {
+            out.insert(rel);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory under the system temp dir, named for `test`.
+    fn scratch(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("abcd-watch-{test}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn put(dir: &Path, rel: &str, text: &str) {
+        let path = dir.join(rel);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, text).unwrap();
+    }
+
+    fn round(
+        synced: &mut Synced,
+        staging: &Path,
+        dest: &Path,
+        files: &[(&str, &str)],
+    ) -> SyncStats {
+        let _ = fs::remove_dir_all(staging);
+        fs::create_dir_all(staging).unwrap();
+        for (rel, text) in files {
+            put(staging, rel, text);
+        }
+        synced.sync(staging, dest).unwrap()
+    }
+
+    #[test]
+    fn only_changed_files_are_written_and_dropped_ones_removed() {
+        let dir = scratch("sync");
+        let (staging, dest) = (dir.join("staging"), dir.join("out"));
+        let mut synced = Synced::default();
+
+        let stats = round(
+            &mut synced,
+            &staging,
+            &dest,
+            &[("a.js", "a"), ("lib/b.js", "b")],
+        );
+        assert_eq!((stats.written, stats.removed, stats.unchanged), (2, 0, 0));
+
+        let stats = round(
+            &mut synced,
+            &staging,
+            &dest,
+            &[("a.js", "a2"), ("lib/b.js", "b")],
+        );
+        assert_eq!((stats.written, stats.removed, stats.unchanged), (1, 0, 1));
+        assert_eq!(fs::read_to_string(dest.join("a.js")).unwrap(), "a2");
+
+        let stats = round(&mut synced, &staging, &dest, &[("a.js", "a2")]);
+        assert_eq!((stats.written, stats.removed, stats.unchanged), (0, 1, 1));
+        assert!(!dest.join("lib/b.js").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn files_no_round_wrote_are_left_alone() {
+        let dir = scratch("foreign");
+        let (staging, dest) = (dir.join("staging"), dir.join("out"));
+        put(&dest, "notes.txt", "mine");
+        let mut synced = Synced::default();
+        round(&mut synced, &staging, &dest, &[("a.js", "a")]);
+        let stats = round(&mut synced, &staging, &dest, &[]);
+        assert_eq!(stats.removed, 1);
+        assert_eq!(fs::read_to_string(dest.join("notes.txt")).unwrap(), "mine");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,15 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Storage error: {0}")]
+    Storage(#[from] sled::Error),
+
+    #[error("Corrupt record under {0}: {1}")]
+    Corrupt(String, String),
+
+    #[error("Invalid digest: {0}")]
+    InvalidDigest(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,259 @@
+//! Persistent analysis database for `.abc` files.
+//!
+//! Results are keyed by the SHA-256 of the analyzed file, so a bundle that
+//! was analyzed before is recognized no matter where it lives on disk, and
+//! a rebuilt bundle gets a fresh namespace instead of stale results. The
+//! namespace also carries [`FORMAT_VERSION`] and a caller-chosen options
+//! string, so results made by another build, or with settings that change
+//! the output, are never read back.
+//! Everything is written entry by entry: an interrupted run keeps what it
+//! finished, and the next run only computes what is missing.
+//!
+//! ```no_run
+//! use abcd_db::{Database, Digest};
+//!
+//! let db = Database::open("analysis.db").unwrap();
+//! let bytes = std::fs::read("modules.abc").unwrap();
+//! let store = db.file(Digest::of(&bytes), "default").unwrap();
+//! if let Some(js) = store.decompiled(0x1234).unwrap() {
+//!     print!("{js}");
+//! }
+//! ```
+
+mod digest;
+mod error;
+
+pub use digest::Digest;
+pub use error::{Error, Result};
+
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// Name of the tree indexing every analyzed file.
+const FILES_TREE: &str = "files";
+
+/// Version of what is stored per file. Bump it whenever a stored value
+/// changes shape or meaning.
+pub const FORMAT_VERSION: u32 = 1;
+
+// Entries are keyed by one of these prefixes and an offset, and markers
+// start with `#`, so no two kinds of entry share keys or show up in each
+// other's scans.
+const PREFIX_METHOD: u8 = b'm';
+const PREFIX_CLASS: u8 = b'c';
+const KEY_COMPLETE: &[u8] = b"#complete";
+
+/// File-level facts recorded when analysis starts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileSummary {
+    /// Path the file was last analyzed from.
+    pub path: String,
+    /// File size in bytes.
+    pub size: u64,
+    /// File format version, e.g. `"13.0.1.0"`.
+    pub version: String,
+    /// Number of classes (external ones included).
+    pub num_classes: u32,
+}
+
+/// Per-method analysis results.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MethodRecord {
+    /// Digest of the method's bytecode.
+    pub code_digest: Digest,
+    /// Offsets of the entities (strings, methods, literal arrays) the
+    /// method's instructions refer to.
+    pub xrefs: Vec<u32>,
+}
+
+/// An open analysis database.
+pub struct Database {
+    db: sled::Db,
+    files: sled::Tree,
+}
+
+impl Database {
+    /// Open or create the database at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_db(sled::open(path)?)
+    }
+
+    /// A database that lives in memory and is discarded on drop.
+    pub fn temporary() -> Result<Self> {
+        Self::from_db(sled::Config::new().temporary(true).open()?)
+    }
+
+    fn from_db(db: sled::Db) -> Result<Self> {
+        let files = db.open_tree(FILES_TREE)?;
+        Ok(Database { db, files })
+    }
+
+    /// Results for the file with the given digest, computed with
+    /// `options` (created empty if new).
+    ///
+    /// `options` names whatever settings the stored results depend on;
+    /// results stored under other options, or by a build with another
+    /// [`FORMAT_VERSION`], are not visible here.
+    pub fn file(&self, digest: Digest, options: &str) -> Result<FileStore> {
+        Ok(FileStore {
+            digest,
+            files: self.files.clone(),
+            tree: self.db.open_tree(tree_name(digest, options))?,
+        })
+    }
+
+    /// Every file with a recorded summary.
+    pub fn files(&self) -> Result<Vec<(Digest, FileSummary)>> {
+        self.files
+            .iter()
+            .map(|entry| {
+                let (key, value) = entry?;
+                let digest = Digest(key.as_ref().try_into().map_err(|_| {
+                    Error::InvalidDigest(format!("{} byte key in {FILES_TREE}", key.len()))
+                })?);
+                Ok((digest, decode(&digest.to_string(), &value)?))
+            })
+            .collect()
+    }
+
+    /// Drop everything stored for `digest`, under any options and format
+    /// version. Returns whether anything was stored.
+    pub fn remove_file(&self, digest: Digest) -> Result<bool> {
+        let mut removed = self.files.remove(digest.0)?.is_some();
+        let suffix = format!("/{digest}");
+        for name in self.db.tree_names() {
+            if name.first() == Some(&b'v') && name.ends_with(suffix.as_bytes()) {
+                removed |= self.db.drop_tree(name)?;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Block until all writes have reached disk.
+    pub fn flush(&self) -> Result<()> {
+        self.db.flush()?;
+        Ok(())
+    }
+}
+
+/// Analysis results for a single file.
+pub struct FileStore {
+    digest: Digest,
+    files: sled::Tree,
+    tree: sled::Tree,
+}
+
+impl FileStore {
+    pub fn digest(&self) -> Digest {
+        self.digest
+    }
+
+    pub fn summary(&self) -> Result<Option<FileSummary>> {
+        self.files
+            .get(self.digest.0)?
+            .map(|v| decode(&self.digest.to_string(), &v))
+            .transpose()
+    }
+
+    pub fn set_summary(&self, summary: &FileSummary) -> Result<()> {
+        self.files.insert(self.digest.0, encode(summary))?;
+        Ok(())
+    }
+
+    /// Whether a previous run analyzed the whole file.
+    pub fn is_complete(&self) -> Result<bool> {
+        Ok(self.tree.contains_key(KEY_COMPLETE)?)
+    }
+
+    /// Record that every class and method has been stored.
+    pub fn mark_complete(&self) -> Result<()> {
+        self.tree.insert(KEY_COMPLETE, &[])?;
+        Ok(())
+    }
+
+    pub fn method(&self, method_off: u32) -> Result<Option<MethodRecord>> {
+        let key = entity_key(PREFIX_METHOD, method_off);
+        self.tree
+            .get(key)?
+            .map(|v| decode(&format!("method {method_off:#x}"), &v))
+            .transpose()
+    }
+
+    pub fn put_method(&self, method_off: u32, record: &MethodRecord) -> Result<()> {
+        self.tree
+            .insert(entity_key(PREFIX_METHOD, method_off), encode(record))?;
+        Ok(())
+    }
+
+    /// Every stored method, by offset.
+    pub fn methods(&self) -> Result<Vec<(u32, MethodRecord)>> {
+        self.tree
+            .scan_prefix([PREFIX_METHOD])
+            .map(|entry| {
+                let (key, value) = entry?;
+                let off = entity_off(&key)?;
+                Ok((off, decode(&format!("method {off:#x}"), &value)?))
+            })
+            .collect()
+    }
+
+    /// Methods whose instructions refer to the entity at `target`.
+    pub fn referencing(&self, target: u32) -> Result<Vec<u32>> {
+        Ok(self
+            .methods()?
+            .into_iter()
+            .filter(|(_, m)| m.xrefs.contains(&target))
+            .map(|(off, _)| off)
+            .collect())
+    }
+
+    /// Decompiled JavaScript of the class at `class_off`.
+    pub fn decompiled(&self, class_off: u32) -> Result<Option<String>> {
+        let Some(bytes) = self.tree.get(entity_key(PREFIX_CLASS, class_off))? else {
+            return Ok(None);
+        };
+        String::from_utf8(bytes.to_vec())
+            .map(Some)
+            .map_err(|e| Error::Corrupt(format!("class {class_off:#x}"), e.to_string()))
+    }
+
+    pub fn put_decompiled(&self, class_off: u32, js: &str) -> Result<()> {
+        self.tree
+            .insert(entity_key(PREFIX_CLASS, class_off), js.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// `v{FORMAT_VERSION}/{options}/{digest}`.
+fn tree_name(digest: Digest, options: &str) -> Vec<u8> {
+    format!("v{FORMAT_VERSION}/{options}/{digest}").into_bytes()
+}
+
+/// Big-endian so that keys sort by offset.
+fn entity_key(prefix: u8, off: u32) -> [u8; 5] {
+    let b = off.to_be_bytes();
+    [prefix, b[0], b[1], b[2], b[3]]
+}
+
+fn entity_off(key: &[u8]) -> Result<u32> {
+    let off = key
+        .get(1..5)
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| {
+            Error::Corrupt(
+                format!("key {key:02x?}"),
+                format!("{} bytes, expected 5", key.len()),
+            )
+        })?;
+    Ok(u32::from_be_bytes(off))
+}
+
+fn encode<T: Serialize>(value: &T) -> Vec<u8> {
+    serde_json::to_vec(value).expect("analysis records are always serializable")
+}
+
+fn decode<T: DeserializeOwned>(what: &str, bytes: &[u8]) -> Result<T> {
+    serde_json::from_slice(bytes).map_err(|e| Error::Corrupt(what.to_string(), e.to_string()))
+}
@@ -0,0 +1,141 @@
+use abcd_db::{Database, Digest, FileSummary, MethodRecord};
+
+const OPTIONS: &str = "default";
+
+fn summary() -> FileSummary {
+    FileSummary {
+        path: "modules.abc".into(),
+        size: 4096,
+        version: "13.0.1.0".into(),
+        num_classes: 3,
+    }
+}
+
+#[test]
+fn digest_hex_roundtrip() {
+    let d = Digest::of(b"abc");
+    assert_eq!(
+        d.to_string(),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+    assert_eq!(d.to_string().parse::<Digest>().unwrap(), d);
+    assert!("xyz".parse::<Digest>().is_err());
+}
+
+#[test]
+fn results_are_scoped_by_digest() {
+    let db = Database::temporary().unwrap();
+    let a = db.file(Digest::of(b"a"), OPTIONS).unwrap();
+    let b = db.file(Digest::of(b"b"), OPTIONS).unwrap();
+
+    a.put_decompiled(0x10, "class A {}\n").unwrap();
+    assert_eq!(a.decompiled(0x10).unwrap().as_deref(), Some("class A {}\n"));
+    assert_eq!(b.decompiled(0x10).unwrap(), None);
+}
+
+#[test]
+fn results_are_scoped_by_options() {
+    let db = Database::temporary().unwrap();
+    let digest = Digest::of(b"bundle");
+    let store = db.file(digest, "names=positional").unwrap();
+    store.put_decompiled(0x10, "class A {}\n").unwrap();
+    store.mark_complete().unwrap();
+
+    let other = db.file(digest, "names=hash").unwrap();
+    assert_eq!(other.decompiled(0x10).unwrap(), None);
+    assert!(!other.is_complete().unwrap());
+
+    assert!(db.remove_file(digest).unwrap());
+    let store = db.file(digest, "names=positional").unwrap();
+    assert_eq!(store.decompiled(0x10).unwrap(), None);
+}
+
+#[test]
+fn summary_and_completion() {
+    let db = Database::temporary().unwrap();
+    let digest = Digest::of(b"bundle");
+    let store = db.file(digest, OPTIONS).unwrap();
+    assert_eq!(store.summary().unwrap(), None);
+    assert!(!store.is_complete().unwrap());
+
+    store.set_summary(&summary()).unwrap();
+    store.mark_complete().unwrap();
+
+    let reopened = db.file(digest, OPTIONS).unwrap();
+    assert_eq!(reopened.summary().unwrap(), Some(summary()));
+    assert!(reopened.is_complete().unwrap());
+    assert_eq!(db.files().unwrap(), vec![(digest, summary())]);
+}
+
+#[test]
+fn methods_and_xrefs() {
+    let db = Database::temporary().unwrap();
+    let store = db.file(Digest::of(b"bundle"), OPTIONS).unwrap();
+    let code = Digest::of(&[0x62, 0x64]);
+    store
+        .put_method(
+            0x200,
+            &MethodRecord {
+                code_digest: code,
+                xrefs: vec![0x40, 0x80],
+            },
+        )
+        .unwrap();
+    store
+        .put_method(
+            0x100,
+            &MethodRecord {
+                code_digest: code,
+                xrefs: vec![0x80],
+            },
+        )
+        .unwrap();
+
+    assert_eq!(store.method(0x200).unwrap().unwrap().xrefs, [0x40, 0x80]);
+    assert_eq!(store.method(0x300).unwrap(), None);
+    let offs: Vec<u32> = store.methods().unwrap().iter().map(|(o, _)| *o).collect();
+    assert_eq!(offs, [0x100, 0x200]);
+    assert_eq!(store.referencing(0x80).unwrap(), [0x100, 0x200]);
+    assert_eq!(store.referencing(0x40).unwrap(), [0x200]);
+}
+
+#[test]
+fn remove_file_drops_everything() {
+    let db = Database::temporary().unwrap();
+    let digest = Digest::of(b"bundle");
+    let store = db.file(digest, OPTIONS).unwrap();
+    store.set_summary(&summary()).unwrap();
+    store.put_decompiled(0x10, "x").unwrap();
+
+    assert!(db.remove_file(digest).unwrap());
+    assert!(db.files().unwrap().is_empty());
+    assert_eq!(
+        db.file(digest, OPTIONS).unwrap().decompiled(0x10).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn persists_across_reopen() {
+    let dir = std::env::temp_dir().join(format!("abcd-db-test-{}", std::process::id()));
+    let digest = Digest::of(b"bundle");
+    {
+        let db = Database::open(&dir).unwrap();
+        db.file(digest, OPTIONS)
+            .unwrap()
+            .put_decompiled(1, "kept")
+            .unwrap();
+        db.flush().unwrap();
+    }
+    let db = Database::open(&dir).unwrap();
+    assert_eq!(
+        db.file(digest, OPTIONS)
+            .unwrap()
+            .decompiled(1)
+            .unwrap()
+            .as_deref(),
+        Some("kept")
+    );
+    drop(db);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
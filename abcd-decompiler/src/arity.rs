@@ -0,0 +1,149 @@
+//! Argument counts at call sites against what the callee declares.
+//!
+//! A call is checked when its callee is a function the code defines: the
+//! [`Expr::Function`] itself, or a name every binding of which, anywhere
+//! in the method, is that same function. The callee's arity comes from
+//! the caller, typically `Method::expected_arity` in abcd-file, which reads
+//! the proto and so also covers methods of other files. JavaScript lets a
+//! caller pass fewer or more arguments than declared, so a mismatch is
+//! worth a look rather than an error.
+
+use std::collections::HashMap;
+
+use abcd_ir::expr::Expr;
+use abcd_ir::stmt::Stmt;
+use abcd_ir::visit::{self, ExprVisitor};
+use abcd_isa::EntityId;
+
+/// A call passing a different number of arguments than its callee
+/// declares.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArityMismatch {
+    /// The callee as the call names it.
+    pub callee: String,
+    pub method: EntityId,
+    pub passed: usize,
+    pub declared: u32,
+}
+
+/// Calls in `stmts` whose argument count differs from what `arity` says
+/// the callee declares, in source order. `arity` returns `None` for a
+/// method it cannot tell, whose calls are then not checked, as are calls
+/// that spread an array into their arguments.
+pub fn check_calls(
+    stmts: &[Stmt],
+    arity: &mut dyn FnMut(EntityId) -> Option<u32>,
+) -> Vec<ArityMismatch> {
+    let mut bindings = Bindings::default();
+    bindings.visit_stmts(stmts);
+    let mut calls = Calls {
+        bound: bindings
+            .names
+            .into_iter()
+            .filter_map(|(name, method)| Some((name, method?)))
+            .collect(),
+        arity,
+        found: Vec::new(),
+    };
+    calls.visit_stmts(stmts);
+    calls.found
+}
+
+/// The method each name is bound to, `None` once it is bound to anything
+/// else as well.
+#[derive(Default)]
+struct Bindings {
+    names: HashMap<String, Option<EntityId>>,
+}
+
+impl Bindings {
+    fn bind(&mut self, name: &str, value: Option<&Expr>) {
+        let method = match value {
+            Some(Expr::Function { method, .. }) => *method,
+            _ => None,
+        };
+        self.names
+            .entry(name.to_string())
+            .and_modify(|bound| {
+                if *bound != method {
+                    *bound = None;
+                }
+            })
+            .or_insert(method);
+    }
+}
+
+impl ExprVisitor for Bindings {
+    fn visit_expr(&mut self, expr: &Expr) {
+        if let Expr::Assign { target, value } = expr {
+            if let Expr::Var(name) = target.as_ref() {
+                self.bind(name, Some(value));
+            }
+        }
+        visit::walk_expr(self, expr);
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Let { name, init } => self.bind(name, init.as_ref()),
+            Stmt::Const { name, init } => self.bind(name, Some(init)),
+            Stmt::Assign {
+                target: Expr::Var(name),
+                value,
+            } => self.bind(name, Some(value)),
+            Stmt::ForIn { binding, .. } | Stmt::ForOf { binding, .. } => self.bind(binding, None),
+            Stmt::TryCatch {
+                catch_binding: Some(name),
+                ..
+            } => self.bind(name, None),
+            _ => {}
+        }
+        visit::walk_stmt(self, stmt);
+    }
+}
+
+struct Calls<'a> {
+    bound: HashMap<String, EntityId>,
+    arity: &'a mut dyn FnMut(EntityId) -> Option<u32>,
+    found: Vec<ArityMismatch>,
+}
+
+impl Calls<'_> {
+    fn check(&mut self, callee: &Expr, args: &[Expr]) {
+        if args.iter().any(|arg| matches!(arg, Expr::Spread(_))) {
+            return;
+        }
+        let (name, method) = match callee {
+            Expr::Function {
+                name,
+                method: Some(method),
+                ..
+            } => (name, *method),
+            Expr::Var(name) => match self.bound.get(name) {
+                Some(&method) => (name, method),
+                None => return,
+            },
+            _ => return,
+        };
+        let Some(declared) = (self.arity)(method) else {
+            return;
+        };
+        if declared as usize != args.len() {
+            self.found.push(ArityMismatch {
+                callee: name.clone(),
+                method,
+                passed: args.len(),
+                declared,
+            });
+        }
+    }
+}
+
+impl ExprVisitor for Calls<'_> {
+    fn visit_expr(&mut self, expr: &Expr) {
+        if let Expr::Call { callee, args } = expr {
+            self.check(callee, args);
+        }
+        visit::walk_expr(self, expr);
+    }
+}
@@ -0,0 +1,322 @@
+//! ArkUI `build()` reconstruction.
+//!
+//! ArkTS compiles a component's `build()` into `initialRender()`, where each
+//! UI element turns into a creation closure registered with the view:
+//!
+//! ```text
+//! this.observeComponentCreation2((elmtId, isInitialRender) => {
+//!     Column.create();
+//!     Column.width("100%");
+//! }, Column);
+//! ...children...
+//! Column.pop();
+//! ```
+//!
+//! The closures are separate functions in the file, so the plain
+//! decompilation is a list of `/* func ... */` placeholders
+//! ([`Expr::Function`]). [`render_build`] looks the closures up by method
+//! offset and prints the declarative form the developer wrote:
+//!
+//! ```text
+//! build() {
+//!     Column() {
+//!         ...children...
+//!     }
+//!     .width("100%")
+//! }
+//! ```
+//!
+//! Statements that do not fit the pattern (`ForEach`, `If` and state
+//! updates) are kept as they decompile, in place. Components are only
+//! created inside a creation closure; `initialRender` itself only pops
+//! them, so a call such as `Object.create(...)` there stays a statement.
+
+use abcd_ir::expr::{Expr, FunctionKind, UnOp};
+use abcd_ir::stmt::Stmt;
+use abcd_isa::EntityId;
+
+use crate::js_emitter::{emit_expr, emit_js};
+
+/// Result of [`render_build`].
+#[derive(Debug, Clone)]
+pub struct Build {
+    /// `build() { ... }`, unindented, ending in a newline.
+    pub text: String,
+    /// Offsets of the creation closures folded into `text`.
+    pub closures: Vec<EntityId>,
+}
+
+enum Node {
+    Component {
+        name: String,
+        args: Vec<Expr>,
+        attrs: Vec<(String, Vec<Expr>)>,
+        children: Vec<Node>,
+    },
+    Raw(Vec<Stmt>),
+}
+
+/// Whether `stmts`, the body of `initialRender`, registers ArkUI
+/// creation closures.
+pub fn is_initial_render(stmts: &[Stmt]) -> bool {
+    stmts.iter().any(|s| observed_closure(s).is_some())
+}
+
+/// Rebuild the `build()` method from the statements of `initialRender`.
+/// `closure` maps a closure's method offset to its decompiled statements.
+/// `None` if no component was recognized.
+pub fn render_build(
+    stmts: &[Stmt],
+    closure: &mut dyn FnMut(EntityId) -> Option<Vec<Stmt>>,
+) -> Option<Build> {
+    let mut tree = Tree::default();
+    let mut closures = Vec::new();
+    for stmt in stmts {
+        if let Some(method) = observed_closure(stmt) {
+            let body = closure(method).unwrap_or_default();
+            if tree.creation(&body) {
+                closures.push(method);
+                continue;
+            }
+        }
+        tree.statement(stmt);
+    }
+    if !tree.found {
+        return None;
+    }
+    let nodes = tree.finish();
+    let mut text = String::from("build() {\n");
+    for node in &nodes {
+        render(&mut text, node, 1);
+    }
+    text.push_str("}\n");
+    Some(Build { text, closures })
+}
+
+#[derive(Default)]
+struct Tree {
+    /// Top-level nodes closed so far.
+    root: Vec<Node>,
+    /// Components whose `pop()` has not been seen yet, innermost last.
+    open: Vec<Node>,
+    found: bool,
+}
+
+impl Tree {
+    /// Apply one creation closure; `false` if it creates no component.
+    fn creation(&mut self, body: &[Stmt]) -> bool {
+        let mut before = Vec::new();
+        let mut component: Option<Node> = None;
+        for stmt in body {
+            if is_bookkeeping(stmt) {
+                continue;
+            }
+            match (component_call(stmt), &mut component) {
+                (Some((name, "create", args)), None) => {
+                    component = Some(Node::Component {
+                        name: name.to_string(),
+                        args: args.to_vec(),
+                        attrs: Vec::new(),
+                        children: Vec::new(),
+                    });
+                }
+                (Some((name, method, args)), Some(Node::Component { name: n, attrs, .. }))
+                    if name == n.as_str() =>
+                {
+                    attrs.push((method.to_string(), args.to_vec()));
+                }
+                _ => before.push(stmt.clone()),
+            }
+        }
+        let Some(node) = component else {
+            return false;
+        };
+        if !before.is_empty() {
+            self.push(Node::Raw(before));
+        }
+        self.found = true;
+        self.open.push(node);
+        true
+    }
+
+    fn statement(&mut self, stmt: &Stmt) {
+        match component_call(stmt) {
+            Some((name, "pop", [])) if self.is_open(name) => self.close(name),
+            Some((name, method, args)) if self.top_is(name) => {
+                if let Some(Node::Component { attrs, .. }) = self.open.last_mut() {
+                    attrs.push((method.to_string(), args.to_vec()));
+                }
+            }
+            _ => self.push(Node::Raw(vec![stmt.clone()])),
+        }
+    }
+
+    fn push(&mut self, node: Node) {
+        match self.open.last_mut() {
+            Some(Node::Component { children, .. }) => children.push(node),
+            _ => self.root.push(node),
+        }
+    }
+
+    fn is_open(&self, name: &str) -> bool {
+        self.open
+            .iter()
+            .any(|n| matches!(n, Node::Component { name: n, .. } if n == name))
+    }
+
+    fn top_is(&self, name: &str) -> bool {
+        matches!(self.open.last(), Some(Node::Component { name: n, .. }) if n == name)
+    }
+
+    /// Close the innermost open `name`. Components opened after it were
+    /// never popped, which is how leaves such as `Image` compile: what
+    /// was collected as their children are really their siblings.
+    fn close(&mut self, name: &str) {
+        while let Some(node) = self.open.pop() {
+            let done = matches!(&node, Node::Component { name: n, .. } if n == name);
+            if done {
+                self.push(node);
+                return;
+            }
+            self.push_leaf(node);
+        }
+    }
+
+    fn push_leaf(&mut self, node: Node) {
+        match node {
+            Node::Component {
+                name,
+                args,
+                attrs,
+                children,
+            } => {
+                self.push(Node::Component {
+                    name,
+                    args,
+                    attrs,
+                    children: Vec::new(),
+                });
+                for child in children {
+                    self.push(child);
+                }
+            }
+            raw => self.push(raw),
+        }
+    }
+
+    fn finish(mut self) -> Vec<Node> {
+        while let Some(node) = self.open.pop() {
+            self.push_leaf(node);
+        }
+        self.root
+    }
+}
+
+/// The closure passed to `this.observeComponentCreation2(closure, ...)`
+/// or `this.observeComponentCreation(closure)`, when its ID resolved.
+fn observed_closure(stmt: &Stmt) -> Option<EntityId> {
+    let Stmt::Expr(Expr::Call { callee, args }) = stmt else {
+        return None;
+    };
+    let Expr::MemberAccess { object, property } = callee.as_ref() else {
+        return None;
+    };
+    if !matches!(object.as_ref(), Expr::This)
+        || !matches!(
+            property.as_str(),
+            "observeComponentCreation" | "observeComponentCreation2"
+        )
+    {
+        return None;
+    }
+    match args.first() {
+        Some(Expr::Function {
+            kind: FunctionKind::Func,
+            method,
+            ..
+        }) => *method,
+        _ => None,
+    }
+}
+
+/// `(X, method, args)` of a statement `X.method(args)` whose receiver is
+/// a global starting with an uppercase letter, as components do.
+fn component_call(stmt: &Stmt) -> Option<(&str, &str, &[Expr])> {
+    let Stmt::Expr(Expr::Call { callee, args }) = stmt else {
+        return None;
+    };
+    let Expr::MemberAccess { object, property } = callee.as_ref() else {
+        return None;
+    };
+    let Expr::Var(name) = object.as_ref() else {
+        return None;
+    };
+    if !name.starts_with(|c: char| c.is_ascii_uppercase()) || name == "ViewStackProcessor" {
+        return None;
+    }
+    Some((name, property, args))
+}
+
+/// Framework calls the older `observeComponentCreation` form wraps
+/// around each element: access recording, and the `pop()` that undoes
+/// creation on re-render.
+fn is_bookkeeping(stmt: &Stmt) -> bool {
+    match stmt {
+        Stmt::Expr(Expr::Call { callee, .. }) => matches!(
+            callee.as_ref(),
+            Expr::MemberAccess { object, .. }
+                if matches!(object.as_ref(), Expr::Var(v) if v == "ViewStackProcessor")
+        ),
+        Stmt::If {
+            cond: Expr::UnaryOp { op: UnOp::Not, .. },
+            then_body,
+            else_body,
+        } => {
+            else_body.is_empty()
+                && !then_body.is_empty()
+                && then_body
+                    .iter()
+                    .all(|s| matches!(component_call(s), Some((_, "pop", []))))
+        }
+        _ => false,
+    }
+}
+
+fn render(out: &mut String, node: &Node, indent: usize) {
+    let pad = "    ".repeat(indent);
+    match node {
+        Node::Component {
+            name,
+            args,
+            attrs,
+            children,
+        } => {
+            out.push_str(&format!("{pad}{name}({})", join(args)));
+            // Containers put attributes after the closing brace, leaves
+            // indent them under the component.
+            let attr_pad = if children.is_empty() {
+                out.push('\n');
+                "    ".repeat(indent + 1)
+            } else {
+                out.push_str(" {\n");
+                for child in children {
+                    render(out, child, indent + 1);
+                }
+                out.push_str(&format!("{pad}}}\n"));
+                pad.clone()
+            };
+            for (attr, args) in attrs {
+                out.push_str(&format!("{attr_pad}.{attr}({})\n", join(args)));
+            }
+        }
+        Node::Raw(stmts) => {
+            for line in emit_js(stmts).lines() {
+                out.push_str(&format!("{pad}{line}\n"));
+            }
+        }
+    }
+}
+
+fn join(args: &[Expr]) -> String {
+    args.iter().map(emit_expr).collect::<Vec<_>>().join(", ")
+}
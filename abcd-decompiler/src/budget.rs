@@ -0,0 +1,87 @@
+//! Limits on walking entity graphs from the file.
+//!
+//! Literal arrays can reference other literal arrays, and methods define
+//! other methods, directly or through the literal array of a class. Nothing
+//! in the format stops a crafted file from making these references cyclic
+//! or from fanning them out exponentially. Every walk that follows them,
+//! nested literal arrays in [`crate::expr_recovery`] and class members in
+//! [`crate::member_order`], takes a [`BudgetTracker`] and stops with a
+//! [`BudgetExceeded`] once it nests too deep or visits too many entities.
+
+use std::fmt;
+
+/// How deep and how wide one resolution may go.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Budget {
+    /// Nesting levels below the entity the walk starts at; 0 follows no
+    /// reference at all.
+    pub max_depth: u32,
+    /// Entities visited in total below the one the walk starts at.
+    pub max_nodes: u32,
+}
+
+impl Default for Budget {
+    fn default() -> Self {
+        Budget {
+            max_depth: 32,
+            max_nodes: 10_000,
+        }
+    }
+}
+
+impl Budget {
+    /// Start a walk.
+    pub fn tracker(self) -> BudgetTracker {
+        BudgetTracker {
+            budget: self,
+            depth: 0,
+            nodes: 0,
+        }
+    }
+}
+
+/// The part of a [`Budget`] one walk has used so far.
+#[derive(Debug)]
+pub struct BudgetTracker {
+    budget: Budget,
+    depth: u32,
+    nodes: u32,
+}
+
+impl BudgetTracker {
+    /// Step into an entity. Every successful `enter` must be paired with a
+    /// [`leave`](Self::leave).
+    pub fn enter(&mut self) -> Result<(), BudgetExceeded> {
+        if self.depth >= self.budget.max_depth {
+            return Err(BudgetExceeded::Depth(self.budget.max_depth));
+        }
+        if self.nodes >= self.budget.max_nodes {
+            return Err(BudgetExceeded::Nodes(self.budget.max_nodes));
+        }
+        self.depth += 1;
+        self.nodes += 1;
+        Ok(())
+    }
+
+    pub fn leave(&mut self) {
+        self.depth = self.depth.saturating_sub(1);
+    }
+}
+
+/// Which limit a walk ran into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BudgetExceeded {
+    Depth(u32),
+    Nodes(u32),
+}
+
+impl fmt::Display for BudgetExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BudgetExceeded::Depth(n) => write!(f, "nested more than {n} levels deep"),
+            BudgetExceeded::Nodes(n) => write!(f, "references more than {n} entities"),
+        }
+    }
+}
+
+impl std::error::Error for BudgetExceeded {}
@@ -1,32 +1,65 @@
-use abcd_ir::instruction::Instruction;
+use abcd_file::File;
+use abcd_file::code::Code;
+use abcd_ir::instruction::{CatchBlockInfo, Instruction, TryBlockInfo};
+use abcd_isa::{DecodeError, DecodedInst};
 
 /// Decode a raw bytecode byte slice into a list of instructions.
+///
+/// Goes through [`abcd_isa::decode_all`], which reads sizes and operands
+/// from the opcode table in the same pass instead of asking the bridge per
+/// instruction.
 pub fn decode_method(code: &[u8]) -> Vec<Instruction> {
-    let decoded = match abcd_isa::decode(code) {
+    let decoded = match abcd_isa::decode_all(code) {
         Ok(d) => d,
         Err(e) => {
             log::warn!("decode failed: {e}");
             return Vec::new();
         }
     };
+    decoded.into_iter().map(instruction).collect()
+}
+
+/// [`decode_method`] against the instruction set of `abc`'s version
+/// ([`File::isa_profile`]): an instruction the version predates is an
+/// error, as are files older than the oldest supported version.
+pub fn decode_method_in(abc: &File, code: &[u8]) -> Result<Vec<Instruction>, String> {
+    let profile = abc.isa_profile().map_err(|e| e.to_string())?;
+    let decoded = abcd_isa::decode_all(code).map_err(|e| e.to_string())?;
+    if let Some(inst) = decoded.iter().find(|inst| !profile.has(inst.info)) {
+        let e = DecodeError::InvalidOpcode(inst.offset as usize);
+        return Err(format!(
+            "{e} ({} needs a later file version)",
+            inst.info.mnemonic
+        ));
+    }
+    Ok(decoded.into_iter().map(instruction).collect())
+}
 
-    let total_len = code.len() as u32;
-    let offsets: Vec<u32> = decoded.iter().map(|(_, off)| *off).collect();
+fn instruction(inst: DecodedInst) -> Instruction {
+    Instruction {
+        offset: inst.offset,
+        opcode: inst.bc,
+        size: inst.size,
+    }
+}
 
-    decoded
+/// The try blocks of `code` in the form [`CFG::build`](abcd_ir::cfg::CFG::build)
+/// takes.
+pub fn try_blocks(code: &Code) -> Vec<TryBlockInfo> {
+    code.try_blocks()
         .iter()
-        .enumerate()
-        .map(|(i, (bc, offset))| {
-            let size = if i + 1 < offsets.len() {
-                (offsets[i + 1] - offset) as u8
-            } else {
-                (total_len - offset) as u8
-            };
-            Instruction {
-                offset: *offset,
-                opcode: *bc,
-                size,
-            }
+        .map(|tb| TryBlockInfo {
+            start_pc: tb.start_pc,
+            length: tb.length,
+            catch_blocks: tb
+                .catches
+                .iter()
+                .map(|cb| CatchBlockInfo {
+                    type_idx: cb.type_idx,
+                    handler_pc: cb.handler_pc,
+                    code_size: cb.code_size,
+                })
+                .collect(),
         })
         .collect()
 }
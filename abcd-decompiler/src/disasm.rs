@@ -0,0 +1,223 @@
+//! Streaming disassembly of a whole file.
+//!
+//! [`DisasmStream`] walks classes and methods lazily and yields one
+//! [`MethodListing`] at a time, so memory use is bounded by the largest
+//! method rather than the file. [`DisasmStream::write_ndjson`] serializes the
+//! stream as newline-delimited JSON, one method per line.
+
+use std::io::{self, Write};
+use std::vec;
+
+use abcd_file::{EntityId, File};
+use serde::Serialize;
+
+/// Disassembly of a single method.
+#[derive(Debug, Clone, Serialize)]
+pub struct MethodListing {
+    /// Name of the declaring class.
+    pub class: String,
+    /// Source file recorded for the class, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_file: Option<String>,
+    pub method_off: u32,
+    pub name: String,
+    /// `None` for native or abstract methods.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<CodeListing>,
+    /// Why the method or its code could not be read.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A method's code section.
+#[derive(Debug, Clone, Serialize)]
+pub struct CodeListing {
+    pub num_vregs: u32,
+    pub num_args: u32,
+    pub code_size: u32,
+    pub instructions: Vec<InsnListing>,
+    pub try_blocks: Vec<TryListing>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InsnListing {
+    pub offset: u32,
+    pub mnemonic: &'static str,
+    /// [`abcd_isa::OpcodeCategory`] name (`"call"`), for coloring.
+    pub category: &'static str,
+    /// Full rendering, operands included (`"lda.str id:3"`).
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TryListing {
+    pub start_pc: u32,
+    pub end_pc: u32,
+    pub catches: Vec<CatchListing>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CatchListing {
+    /// `0` for a catch-all handler.
+    pub type_idx: u32,
+    pub handler_pc: u32,
+}
+
+/// The class currently being walked.
+struct ClassCursor {
+    name: String,
+    source_file: Option<String>,
+    methods: vec::IntoIter<EntityId>,
+}
+
+/// Lazily disassembles every method of every local class in a file.
+pub struct DisasmStream<'f> {
+    abc: &'f File,
+    classes: vec::IntoIter<EntityId>,
+    current: Option<ClassCursor>,
+    keep: Option<Box<dyn Fn(&str) -> bool + 'f>>,
+}
+
+impl<'f> DisasmStream<'f> {
+    pub fn new(abc: &'f File) -> Self {
+        DisasmStream {
+            abc,
+            classes: abc.class_offsets().into_iter(),
+            current: None,
+            keep: None,
+        }
+    }
+
+    /// Only walk the classes whose name `keep` accepts.
+    pub fn retain_classes(mut self, keep: impl Fn(&str) -> bool + 'f) -> Self {
+        self.keep = Some(Box::new(keep));
+        self
+    }
+
+    /// Write each remaining method as one JSON object per line.
+    pub fn write_ndjson(self, mut out: impl Write) -> io::Result<()> {
+        for listing in self {
+            serde_json::to_writer(&mut out, &listing)?;
+            out.write_all(b"\n")?;
+        }
+        out.flush()
+    }
+
+    /// Advance to the next local class that opens successfully.
+    fn next_class(&mut self) -> Option<ClassCursor> {
+        for class_off in self.classes.by_ref() {
+            if self.abc.is_external(class_off) {
+                continue;
+            }
+            let name = self.abc.get_string_lossy(class_off).into_owned();
+            if self.keep.as_ref().is_some_and(|keep| !keep(&name)) {
+                continue;
+            }
+            let class = match self.abc.class(class_off) {
+                Ok(c) => c,
+                Err(e) => {
+                    log::warn!("skipping class at {class_off}: {e}");
+                    continue;
+                }
+            };
+            return Some(ClassCursor {
+                name,
+                source_file: class
+                    .source_file_off()
+                    .map(|off| self.abc.get_string_lossy(off).into_owned()),
+                methods: class.method_offsets().into_iter(),
+            });
+        }
+        None
+    }
+
+    fn listing(&self, cursor: &ClassCursor, method_off: EntityId) -> MethodListing {
+        let mut listing = MethodListing {
+            class: cursor.name.clone(),
+            source_file: cursor.source_file.clone(),
+            method_off: method_off.0,
+            name: self.abc.method_name_lossy(method_off).into_owned(),
+            code: None,
+            error: None,
+        };
+
+        let method = match self.abc.method(method_off) {
+            Ok(m) => m,
+            Err(e) => {
+                listing.error = Some(e.to_string());
+                return listing;
+            }
+        };
+        let Some(code_off) = method.code_off() else {
+            return listing;
+        };
+        let code = match self.abc.code(code_off) {
+            Ok(c) => c,
+            Err(e) => {
+                listing.error = Some(e.to_string());
+                return listing;
+            }
+        };
+
+        let bytes = code.instructions();
+        let decoded = match crate::decode::decode_method_in(self.abc, bytes) {
+            Ok(d) => d,
+            Err(e) => {
+                listing.error = Some(e);
+                return listing;
+            }
+        };
+        let instructions = decoded
+            .into_iter()
+            .map(|insn| InsnListing {
+                offset: insn.offset,
+                mnemonic: insn.opcode.mnemonic(),
+                category: insn.opcode.category().name(),
+                text: insn.opcode.to_string(),
+            })
+            .collect();
+        let try_blocks = code
+            .try_blocks()
+            .into_iter()
+            .map(|tb| TryListing {
+                start_pc: tb.start_pc,
+                end_pc: tb.start_pc + tb.length,
+                catches: tb
+                    .catches
+                    .iter()
+                    .map(|cb| CatchListing {
+                        type_idx: cb.type_idx,
+                        handler_pc: cb.handler_pc,
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        listing.code = Some(CodeListing {
+            num_vregs: code.num_vregs(),
+            num_args: code.num_args(),
+            code_size: bytes.len() as u32,
+            instructions,
+            try_blocks,
+        });
+        listing
+    }
+}
+
+impl Iterator for DisasmStream<'_> {
+    type Item = MethodListing;
+
+    fn next(&mut self) -> Option<MethodListing> {
+        loop {
+            let mut cursor = match self.current.take() {
+                Some(c) => c,
+                None => self.next_class()?,
+            };
+            if let Some(method_off) = cursor.methods.next() {
+                let listing = self.listing(&cursor, method_off);
+                self.current = Some(cursor);
+                return Some(listing);
+            }
+        }
+    }
+}
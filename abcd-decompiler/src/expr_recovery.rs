@@ -1,10 +1,15 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::sync::OnceLock;
 
 use abcd_file::literal::{LiteralArray, LiteralTag, LiteralValue};
-use abcd_ir::expr::{BinOp, Expr, PropKey, UnOp};
+use abcd_ir::expr::{BinOp, Expr, FunctionKind, PropKey, UnOp};
 use abcd_ir::instruction::Instruction;
-use abcd_ir::stmt::Stmt;
-use abcd_isa::{Bytecode as B, EntityId};
+use abcd_ir::stmt::{AsmInsn, Stmt};
+use abcd_isa::{Bytecode as B, CallArgs, CallKind, EntityId, Version, opcode_table};
+
+use crate::budget::{Budget, BudgetTracker};
+use crate::session::CancelToken;
 
 /// Resolves entity IDs to strings/names and literal arrays.
 pub trait StringResolver {
@@ -23,6 +28,44 @@ pub trait StringResolver {
     fn resolve_method_name(&self, _method_off: EntityId, _entity_id: EntityId) -> Option<String> {
         None
     }
+    /// The literal array at a file offset, for arrays nested in others.
+    fn resolve_literal_array_at(&self, _offset: EntityId) -> Option<LiteralArray> {
+        None
+    }
+    /// Source-level locals the method's debug info records.
+    fn local_variables(&self, _method_off: EntityId) -> Vec<LocalVariable> {
+        Vec::new()
+    }
+    /// Name of the anonymous function an ID operand names, under
+    /// [`SyntheticNames::ContentHash`]; see `stable_function_names`
+    /// (the `structuring` feature).
+    fn function_name(&self, _method_off: EntityId, _entity_id: EntityId) -> Option<String> {
+        None
+    }
+    /// Version of the file being decompiled, which decides how object
+    /// literals are laid out; `None` reads them as the current version.
+    fn file_version(&self) -> Option<Version> {
+        None
+    }
+}
+
+/// A named local variable held in a register over a range of the code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalVariable {
+    pub reg: u16,
+    pub start_pc: u32,
+    /// Exclusive.
+    pub end_pc: u32,
+    pub name: String,
+}
+
+/// Name a `catch` clause binds the caught exception to.
+pub const CATCH_BINDING: &str = "$err";
+
+/// What acc holds on entry to a catch handler: the thrown value, which the
+/// runtime leaves there before jumping to the handler.
+pub fn caught_exception() -> Expr {
+    Expr::Var(CATCH_BINDING.into())
 }
 
 /// Result of recovering expressions from a basic block.
@@ -32,18 +75,93 @@ pub struct BlockRecovery {
     pub final_regs: HashMap<u16, Expr>,
 }
 
+/// What recovery emits for an instruction it has no translation for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnknownOpcodePolicy {
+    /// A one-line `Stmt::Comment` with the offset, bytes and disassembly.
+    #[default]
+    Comment,
+    /// A `Stmt::Asm` block; consecutive untranslated instructions share one.
+    RawAsmBlock,
+    /// Refuse the method; see [`find_unknown`]. Recovery of individual
+    /// blocks cannot fail and falls back to `Comment`.
+    Error,
+}
+
+/// How the decompiler makes up names the file does not provide.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SyntheticNames {
+    /// Numbered in order of appearance: registers `v1`, `v2`, ...; anonymous
+    /// functions after the index es2abc gave them (`anonymous_0x3`).
+    #[default]
+    Positional,
+    /// Derived from a hash of the code they name: a register after the
+    /// instruction that first defines it and the one before (`v_3fa9c1`),
+    /// an anonymous function after its body (`anonymous_e01b2c`). Entity
+    /// IDs, jump offsets and IC slots are left out of the hash, so code
+    /// that did not change keeps its names when the rest of the file
+    /// moves, and diffs between builds show only real changes.
+    ContentHash,
+}
+
+/// An instruction with no translation, located in the method's code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownOpcode {
+    pub offset: u32,
+    pub bytes: Vec<u8>,
+    pub text: String,
+}
+
+impl fmt::Display for UnknownOpcode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "No translation for `{}` at {:#x} ({} bytes)",
+            self.text,
+            self.offset,
+            self.bytes.len()
+        )
+    }
+}
+
+impl std::error::Error for UnknownOpcode {}
+
+/// Method-wide inputs shared by the recovery of every block.
+#[derive(Clone, Copy)]
+pub struct MethodContext<'a> {
+    pub resolver: &'a dyn StringResolver,
+    pub method_off: EntityId,
+    pub num_vregs: u32,
+    pub num_args: u32,
+    /// The method's whole bytecode; untranslated instructions are quoted
+    /// from it.
+    pub code: &'a [u8],
+    pub unknown_opcodes: UnknownOpcodePolicy,
+    pub synthetic_names: SyntheticNames,
+    /// Register definitions to store into a named variable, by instruction
+    /// offset; other definitions are propagated into their uses.
+    pub stored_defs: Option<&'a HashMap<u32, String>>,
+    /// Limits for following references between literal arrays.
+    pub budget: Budget,
+    /// Once cancelled, recovery stops early and returns what it has.
+    pub cancel: Option<&'a CancelToken>,
+}
+
+impl MethodContext<'_> {
+    pub fn cancelled(&self) -> bool {
+        self.cancel.is_some_and(CancelToken::is_cancelled)
+    }
+}
+
 /// Recover expressions from a sequence of instructions within a basic block.
-pub fn recover_block(
-    instructions: &[Instruction],
-    resolver: &dyn StringResolver,
-    method_off: EntityId,
-    num_vregs: u32,
-    num_args: u32,
-) -> BlockRecovery {
-    let mut state = ExprState::new(num_vregs, num_args);
+pub fn recover_block(instructions: &[Instruction], ctx: &MethodContext) -> BlockRecovery {
+    let mut state = ExprState::new(ctx.num_vregs, ctx.num_args);
     let mut stmts = Vec::new();
     for insn in instructions {
-        process_insn(insn, &mut state, &mut stmts, resolver, method_off);
+        if ctx.cancelled() {
+            break;
+        }
+        process_insn(insn, &mut state, &mut stmts, ctx);
     }
     BlockRecovery {
         stmts,
@@ -54,6 +172,8 @@ pub fn recover_block(
 
 struct ExprState {
     acc: Expr,
+    /// The accumulator was just stored to a register, which keeps its value.
+    acc_stored: bool,
     regs: HashMap<u16, Expr>,
     num_vregs: u32,
     num_args: u32,
@@ -63,6 +183,7 @@ impl ExprState {
     fn new(num_vregs: u32, num_args: u32) -> Self {
         ExprState {
             acc: Expr::Undefined,
+            acc_stored: false,
             regs: HashMap::new(),
             num_vregs,
             num_args,
@@ -71,6 +192,7 @@ impl ExprState {
     fn with_state(num_vregs: u32, num_args: u32, acc: Expr, regs: HashMap<u16, Expr>) -> Self {
         ExprState {
             acc,
+            acc_stored: false,
             regs,
             num_vregs,
             num_args,
@@ -89,17 +211,17 @@ impl ExprState {
 
 pub fn recover_block_with_state(
     instructions: &[Instruction],
-    resolver: &dyn StringResolver,
-    method_off: EntityId,
-    num_vregs: u32,
-    num_args: u32,
+    ctx: &MethodContext,
     initial_acc: Expr,
     initial_regs: HashMap<u16, Expr>,
 ) -> BlockRecovery {
-    let mut state = ExprState::with_state(num_vregs, num_args, initial_acc, initial_regs);
+    let mut state = ExprState::with_state(ctx.num_vregs, ctx.num_args, initial_acc, initial_regs);
     let mut stmts = Vec::new();
     for insn in instructions {
-        process_insn(insn, &mut state, &mut stmts, resolver, method_off);
+        if ctx.cancelled() {
+            break;
+        }
+        process_insn(insn, &mut state, &mut stmts, ctx);
     }
     BlockRecovery {
         stmts,
@@ -108,6 +230,49 @@ pub fn recover_block_with_state(
     }
 }
 
+/// Mnemonics with a dedicated translation.
+///
+/// Everything else in the ISA falls through to a comment holding the raw
+/// instruction. Derived by running each opcode of [`opcode_table`] through
+/// the recovery, so it cannot drift from the actual dispatch.
+pub fn handled_mnemonics() -> BTreeSet<&'static str> {
+    struct NoNames;
+    impl StringResolver for NoNames {
+        fn resolve_string(&self, _: EntityId, _: EntityId) -> Option<String> {
+            None
+        }
+        fn resolve_offset(&self, _: EntityId, _: EntityId) -> Option<EntityId> {
+            None
+        }
+    }
+
+    opcode_table()
+        .iter()
+        .filter(|info| {
+            let insn = Instruction {
+                offset: 0,
+                opcode: info.template,
+                size: info.size,
+            };
+            let ctx = MethodContext {
+                resolver: &NoNames,
+                method_off: EntityId(0),
+                num_vregs: 0,
+                num_args: 0,
+                code: &[],
+                unknown_opcodes: UnknownOpcodePolicy::Comment,
+                synthetic_names: SyntheticNames::Positional,
+                stored_defs: None,
+                budget: Budget::default(),
+                cancel: None,
+            };
+            let mut state = ExprState::new(0, 0);
+            process_insn(&insn, &mut state, &mut Vec::new(), &ctx)
+        })
+        .map(|info| info.mnemonic)
+        .collect()
+}
+
 fn arg_or_var(r: u16, num_vregs: u32, _num_args: u32) -> Expr {
     let r32 = r as u32;
     if r32 < num_vregs {
@@ -129,6 +294,21 @@ fn resolve_str(resolver: &dyn StringResolver, method_off: EntityId, id: EntityId
         .unwrap_or_else(|| format!("@{:#x}", id.0))
 }
 
+/// Names of the globals `instructions` read or write, which appear in the
+/// output as plain variables; register names must stay clear of them.
+pub(crate) fn global_names(instructions: &[Instruction], ctx: &MethodContext) -> HashSet<String> {
+    instructions
+        .iter()
+        .filter_map(|insn| match insn.opcode {
+            B::Tryldglobalbyname(_, id)
+            | B::Ldglobalvar(_, id)
+            | B::Trystglobalbyname(_, id)
+            | B::Stglobalvar(_, id) => Some(resolve_str(ctx.resolver, ctx.method_off, id)),
+            _ => None,
+        })
+        .collect()
+}
+
 fn resolve_method_or_str(
     resolver: &dyn StringResolver,
     method_off: EntityId,
@@ -172,6 +352,65 @@ fn is_acc_replacing(bc: &B) -> bool {
     )
 }
 
+/// Whether `bc` sets the accumulator to something not derived from its
+/// previous value.
+pub(crate) fn loads_fresh_acc(bc: &B) -> bool {
+    is_acc_replacing(bc)
+        || matches!(
+            bc,
+            B::Ldlexvar(..)
+                | B::WideLdlexvar(..)
+                | B::Tryldglobalbyname(..)
+                | B::Ldglobalvar(..)
+                | B::Ldglobal
+                | B::Ldlocalmodulevar(..)
+                | B::WideLdlocalmodulevar(..)
+                | B::Ldexternalmodulevar(..)
+                | B::WideLdexternalmodulevar(..)
+                | B::Ldthis
+                | B::Ldfunction
+                | B::Ldnewtarget
+                | B::Ldbigint(..)
+                | B::Getunmappedargs
+        )
+}
+
+/// The value a register holds after `insn` writes `value` to it: the
+/// variable it is stored into, if the definition is stored, else `value`.
+fn define_reg(insn: &Instruction, value: Expr, stmts: &mut Vec<Stmt>, ctx: &MethodContext) -> Expr {
+    match ctx.stored_defs.and_then(|defs| defs.get(&insn.offset)) {
+        Some(name) => {
+            if value.as_var() != Some(name) {
+                stmts.push(Stmt::assign(Expr::var(name.clone()), value));
+            }
+            Expr::var(name.clone())
+        }
+        None => value,
+    }
+}
+
+/// Values of a range instruction's argument registers (receiver excluded).
+fn range_arg_exprs(state: &ExprState, bc: &B) -> Vec<Expr> {
+    bc.range_args()
+        .map(|range| range.arg_regs().map(|r| state.get_reg(r.0)).collect())
+        .unwrap_or_default()
+}
+
+/// Arguments of a call instruction, from where [`CallInfo`] says they are.
+/// The receiver is not among them: the callee in acc already carries it.
+///
+/// [`CallInfo`]: abcd_isa::CallInfo
+fn call_arg_exprs(state: &ExprState, bc: &B) -> Vec<Expr> {
+    let (_, operands, _) = bc.emit_args();
+    let reg = |i: usize| state.get_reg(operands[i] as u16);
+    match bc.call_info().map(|info| info.args) {
+        Some(CallArgs::Fixed { first, count }) => (first..first + count).map(reg).collect(),
+        Some(CallArgs::Range { .. }) => range_arg_exprs(state, bc),
+        Some(CallArgs::Spread { operand }) => vec![Expr::Spread(Box::new(reg(operand)))],
+        None => Vec::new(),
+    }
+}
+
 fn binary_op(state: &mut ExprState, reg: u16, op: BinOp) {
     let rhs = state.get_reg(reg);
     state.acc = Expr::BinaryOp {
@@ -188,16 +427,18 @@ fn unary_op(state: &mut ExprState, op: UnOp) {
     };
 }
 
+/// Translate one instruction. Returns `false` if it hit the catch-all.
 fn process_insn(
     insn: &Instruction,
     state: &mut ExprState,
     stmts: &mut Vec<Stmt>,
-    resolver: &dyn StringResolver,
-    method_off: EntityId,
-) {
-    if is_acc_replacing(&insn.opcode) {
+    ctx: &MethodContext,
+) -> bool {
+    let (resolver, method_off) = (ctx.resolver, ctx.method_off);
+    if !state.acc_stored && (loads_fresh_acc(&insn.opcode) || matches!(insn.opcode, B::Lda(..))) {
         flush_acc_side_effects(state, stmts);
     }
+    state.acc_stored = matches!(insn.opcode, B::Sta(..));
 
     match insn.opcode {
         // === Load constants ===
@@ -211,8 +452,15 @@ fn process_insn(
             state.acc = Expr::StringLit(resolve_str(resolver, method_off, id));
         }
         B::Lda(reg) => state.acc = state.get_reg(reg.0),
-        B::Sta(reg) => state.set_reg(reg.0, state.acc.clone()),
-        B::Mov(dst, src) => state.set_reg(dst.0, state.get_reg(src.0)),
+        B::Sta(reg) => {
+            let value = define_reg(insn, state.acc.clone(), stmts, ctx);
+            state.acc = value.clone();
+            state.set_reg(reg.0, value);
+        }
+        B::Mov(dst, src) => {
+            let value = define_reg(insn, state.get_reg(src.0), stmts, ctx);
+            state.set_reg(dst.0, value);
+        }
 
         // === Lexical variables ===
         B::Ldlexvar(level, slot) | B::WideLdlexvar(level, slot) => {
@@ -367,129 +615,19 @@ fn process_insn(
         B::Dec(..) => unary_op(state, UnOp::Dec),
 
         // === Calls ===
-        B::Callarg0(_) => {
-            let callee = state.acc.clone();
-            state.acc = Expr::Call {
-                callee: Box::new(callee),
-                args: vec![],
-            };
-        }
-        B::Callarg1(_, a0) => {
-            let callee = state.acc.clone();
-            state.acc = Expr::Call {
-                callee: Box::new(callee),
-                args: vec![state.get_reg(a0.0)],
-            };
-        }
-        B::Callargs2(_, a0, a1) => {
-            let callee = state.acc.clone();
-            state.acc = Expr::Call {
-                callee: Box::new(callee),
-                args: vec![state.get_reg(a0.0), state.get_reg(a1.0)],
-            };
-        }
-        B::Callargs3(_, a0, a1, a2) => {
-            let callee = state.acc.clone();
-            state.acc = Expr::Call {
-                callee: Box::new(callee),
-                args: vec![
-                    state.get_reg(a0.0),
-                    state.get_reg(a1.0),
-                    state.get_reg(a2.0),
-                ],
-            };
-        }
-        B::Callrange(_, count, start) => {
-            let callee = state.acc.clone();
-            let args: Vec<Expr> = (0..count.0 as u16)
-                .map(|i| state.get_reg(start.0 + i))
-                .collect();
-            state.acc = Expr::Call {
-                callee: Box::new(callee),
-                args,
+        ref bc if bc.call_kind() == Some(CallKind::Super) => {
+            state.acc = Expr::SuperCall {
+                args: call_arg_exprs(state, bc),
             };
         }
-        B::WideCallrange(count, start) => {
+        ref bc if bc.call_kind().is_some() => {
+            let args = call_arg_exprs(state, bc);
             let callee = state.acc.clone();
-            let args: Vec<Expr> = (0..count.0 as u16)
-                .map(|i| state.get_reg(start.0 + i))
-                .collect();
             state.acc = Expr::Call {
                 callee: Box::new(callee),
                 args,
             };
         }
-        B::Callthis0(_, this) => {
-            let _this_val = state.get_reg(this.0);
-            let method = state.acc.clone();
-            state.acc = Expr::Call {
-                callee: Box::new(method),
-                args: vec![],
-            };
-        }
-        B::Callthis1(_, this, a0) => {
-            let _this_val = state.get_reg(this.0);
-            let method = state.acc.clone();
-            state.acc = Expr::Call {
-                callee: Box::new(method),
-                args: vec![state.get_reg(a0.0)],
-            };
-        }
-        B::Callthis2(_, _this, a0, a1) => {
-            let method = state.acc.clone();
-            state.acc = Expr::Call {
-                callee: Box::new(method),
-                args: vec![state.get_reg(a0.0), state.get_reg(a1.0)],
-            };
-        }
-        B::Callthis3(_, _this, a0, a1, a2) => {
-            let method = state.acc.clone();
-            state.acc = Expr::Call {
-                callee: Box::new(method),
-                args: vec![
-                    state.get_reg(a0.0),
-                    state.get_reg(a1.0),
-                    state.get_reg(a2.0),
-                ],
-            };
-        }
-        B::Callthisrange(_, count, start) => {
-            let args: Vec<Expr> = (1..count.0 as u16)
-                .map(|i| state.get_reg(start.0 + i))
-                .collect();
-            let method = state.acc.clone();
-            state.acc = Expr::Call {
-                callee: Box::new(method),
-                args,
-            };
-        }
-        B::WideCallthisrange(count, start) => {
-            let args: Vec<Expr> = (1..count.0 as u16)
-                .map(|i| state.get_reg(start.0 + i))
-                .collect();
-            let method = state.acc.clone();
-            state.acc = Expr::Call {
-                callee: Box::new(method),
-                args,
-            };
-        }
-        B::Supercallarrowrange(_, count, start) | B::Supercallthisrange(_, count, start) => {
-            let args: Vec<Expr> = (0..count.0 as u16)
-                .map(|i| state.get_reg(start.0 + i))
-                .collect();
-            state.acc = Expr::SuperCall { args };
-        }
-        B::WideSupercallarrowrange(count, start) | B::WideSupercallthisrange(count, start) => {
-            let args: Vec<Expr> = (0..count.0 as u16)
-                .map(|i| state.get_reg(start.0 + i))
-                .collect();
-            state.acc = Expr::SuperCall { args };
-        }
-        B::Supercallspread(_, arg) => {
-            state.acc = Expr::SuperCall {
-                args: vec![Expr::Spread(Box::new(state.get_reg(arg.0)))],
-            };
-        }
         B::Apply(_, this_reg, args_reg) => {
             let this_val = state.get_reg(this_reg.0);
             let args_arr = state.get_reg(args_reg.0);
@@ -504,24 +642,13 @@ fn process_insn(
         }
 
         // === New ===
-        B::Newobjrange(_, count, start) => {
-            let ctor = state.get_reg(start.0);
-            let args: Vec<Expr> = (1..count.0 as u16)
-                .map(|i| state.get_reg(start.0 + i))
-                .collect();
-            state.acc = Expr::New {
-                callee: Box::new(ctor),
-                args,
-            };
-        }
-        B::WideNewobjrange(count, start) => {
-            let ctor = state.get_reg(start.0);
-            let args: Vec<Expr> = (1..count.0 as u16)
-                .map(|i| state.get_reg(start.0 + i))
-                .collect();
+        B::Newobjrange(..) | B::WideNewobjrange(..) => {
+            // The constructor occupies the first register of the window.
+            let mut window = range_arg_exprs(state, &insn.opcode).into_iter();
+            let ctor = window.next().unwrap_or(Expr::Undefined);
             state.acc = Expr::New {
                 callee: Box::new(ctor),
-                args,
+                args: window.collect(),
             };
         }
 
@@ -531,10 +658,11 @@ fn process_insn(
         B::Createobjectwithbuffer(_, lit_id) | B::Createarraywithbuffer(_, lit_id) => {
             let is_array = matches!(insn.opcode, B::Createarraywithbuffer(..));
             if let Some(lit_arr) = resolver.resolve_literal_array(method_off, lit_id) {
+                let mut budget = ctx.budget.tracker();
                 state.acc = if is_array {
-                    resolve_array_buffer(&lit_arr, resolver)
+                    resolve_array_buffer(&lit_arr, resolver, &mut budget)
                 } else {
-                    resolve_object_buffer(&lit_arr, resolver)
+                    resolve_object_buffer(&lit_arr, resolver, &mut budget)
                 };
             } else {
                 state.acc = if is_array {
@@ -578,17 +706,25 @@ fn process_insn(
         // === Function/class definition ===
         B::Definefunc(_, id, _) | B::Definemethod(_, id, _) => {
             let name = resolve_method_or_str(resolver, method_off, id);
-            let clean = clean_abc_name(&name);
-            let prefix = if matches!(insn.opcode, B::Definefunc(..)) {
-                "func"
-            } else {
-                "method"
+            let stable = match ctx.synthetic_names {
+                SyntheticNames::ContentHash if is_anonymous_name(&name) => {
+                    resolver.function_name(method_off, id)
+                }
+                _ => None,
+            };
+            state.acc = Expr::Function {
+                kind: if matches!(insn.opcode, B::Definefunc(..)) {
+                    FunctionKind::Func
+                } else {
+                    FunctionKind::Method
+                },
+                name: stable.unwrap_or_else(|| clean_abc_name(&name)),
+                method: resolver.resolve_offset(method_off, id),
             };
-            state.acc = Expr::Var(format!("/* {prefix} {clean} */"));
         }
         B::Defineclasswithbuffer(_, id, _, _, _) => {
             let name = resolve_method_or_str(resolver, method_off, id);
-            state.acc = Expr::Var(format!("/* class */ {}", clean_abc_name(&name)));
+            state.acc = Expr::Unknown(format!("/* class */ {}", clean_abc_name(&name)));
         }
 
         // === Misc ===
@@ -754,22 +890,61 @@ fn process_insn(
 
         // === Catch all ===
         _ => {
-            stmts.push(Stmt::Comment(format!("{}", insn.opcode)));
+            push_unknown(insn, stmts, ctx);
+            return false;
         }
     }
+    true
 }
 
-fn resolve_object_buffer(lit: &LiteralArray, resolver: &dyn StringResolver) -> Expr {
-    let mut props = Vec::new();
-    let entries = &lit.entries;
-    let mut i = 0;
-    while i + 1 < entries.len() {
-        let (key_tag, key_val) = &entries[i];
-        let (val_tag, val_val) = &entries[i + 1];
-        if *key_tag == LiteralTag::MethodAffiliate || *val_tag == LiteralTag::MethodAffiliate {
-            i += 2;
-            continue;
+fn push_unknown(insn: &Instruction, stmts: &mut Vec<Stmt>, ctx: &MethodContext) {
+    let asm = AsmInsn {
+        offset: insn.offset,
+        bytes: insn_bytes(ctx.code, insn).to_vec(),
+        text: insn.opcode.to_string(),
+    };
+    match ctx.unknown_opcodes {
+        UnknownOpcodePolicy::RawAsmBlock => match stmts.last_mut() {
+            Some(Stmt::Asm(block)) => block.push(asm),
+            _ => stmts.push(Stmt::Asm(vec![asm])),
+        },
+        UnknownOpcodePolicy::Comment | UnknownOpcodePolicy::Error => {
+            stmts.push(Stmt::Comment(asm.to_string()));
         }
+    }
+}
+
+/// The first instruction recovery has no translation for, if any.
+pub fn find_unknown(instructions: &[Instruction], code: &[u8]) -> Option<UnknownOpcode> {
+    static HANDLED: OnceLock<BTreeSet<&'static str>> = OnceLock::new();
+    let handled = HANDLED.get_or_init(handled_mnemonics);
+    let insn = instructions
+        .iter()
+        .find(|i| !i.opcode.is_jump() && !handled.contains(i.opcode.mnemonic()))?;
+    Some(UnknownOpcode {
+        offset: insn.offset,
+        bytes: insn_bytes(code, insn).to_vec(),
+        text: insn.opcode.to_string(),
+    })
+}
+
+/// The encoding of `insn` within its method's `code`.
+fn insn_bytes<'c>(code: &'c [u8], insn: &Instruction) -> &'c [u8] {
+    let start = insn.offset as usize;
+    code.get(start..start + insn.size as usize)
+        .unwrap_or_default()
+}
+
+fn resolve_object_buffer(
+    lit: &LiteralArray,
+    resolver: &dyn StringResolver,
+    budget: &mut BudgetTracker,
+) -> Expr {
+    let mut props = Vec::new();
+    let version = resolver.file_version().unwrap_or_else(Version::current);
+    for member in lit.members(version) {
+        let (key_tag, key_val) = &member.key;
+        let (val_tag, val_val) = &member.value;
         let key = match key_val {
             LiteralValue::String(off) => {
                 let s = resolver
@@ -779,22 +954,25 @@ fn resolve_object_buffer(lit: &LiteralArray, resolver: &dyn StringResolver) -> E
             }
             LiteralValue::Integer(n) => PropKey::Computed(Expr::NumberLit(*n as f64)),
             LiteralValue::Double(d) => PropKey::Computed(Expr::NumberLit(*d)),
-            _ => PropKey::Computed(literal_value_to_expr(key_tag, key_val, resolver)),
+            _ => PropKey::Computed(literal_value_to_expr(key_tag, key_val, resolver, budget)),
         };
-        let val = literal_value_to_expr(val_tag, val_val, resolver);
+        let val = literal_value_to_expr(val_tag, val_val, resolver, budget);
         props.push((key, val));
-        i += 2;
     }
     Expr::ObjectLit(props)
 }
 
-fn resolve_array_buffer(lit: &LiteralArray, resolver: &dyn StringResolver) -> Expr {
+fn resolve_array_buffer(
+    lit: &LiteralArray,
+    resolver: &dyn StringResolver,
+    budget: &mut BudgetTracker,
+) -> Expr {
     let mut elems = Vec::new();
     let entries = &lit.entries;
     let mut i = 0;
     while i + 1 < entries.len() {
         let (val_tag, val_val) = &entries[i + 1];
-        elems.push(literal_value_to_expr(val_tag, val_val, resolver));
+        elems.push(literal_value_to_expr(val_tag, val_val, resolver, budget));
         i += 2;
     }
     Expr::ArrayLit(elems)
@@ -804,6 +982,7 @@ fn literal_value_to_expr(
     _tag: &LiteralTag,
     val: &LiteralValue,
     resolver: &dyn StringResolver,
+    budget: &mut BudgetTracker,
 ) -> Expr {
     match val {
         LiteralValue::Bool(b) => Expr::BoolLit(*b),
@@ -816,10 +995,43 @@ fn literal_value_to_expr(
                 .unwrap_or_else(|| format!("@{}", off.0));
             Expr::StringLit(s)
         }
-        LiteralValue::Method(off) => Expr::Var(format!("/* method@{} */", off.0)),
+        LiteralValue::Method(off) => Expr::Unknown(format!("/* method@{} */", off.0)),
         LiteralValue::Null => Expr::Null,
         LiteralValue::MethodAffiliate(_) => Expr::NumberLit(0.0),
         LiteralValue::TagValue(v) => Expr::NumberLit(*v as f64),
+        LiteralValue::Accessor(v) | LiteralValue::BuiltinTypeIndex(v) => Expr::NumberLit(*v as f64),
+        LiteralValue::LiteralBufferIndex(v) => Expr::NumberLit(*v as f64),
+        LiteralValue::LiteralArray(off) => match resolver.resolve_literal_array_at(*off) {
+            Some(nested) => within_budget(budget, *off, |budget| {
+                resolve_array_buffer(&nested, resolver, budget)
+            }),
+            None => Expr::Unknown(format!("/* literal_array@{} */", off.0)),
+        },
+        LiteralValue::EtsImplements(off) => Expr::Unknown(format!("/* implements@{} */", off.0)),
+        LiteralValue::TypedArray(off) => Expr::Unknown(format!("/* typed_array@{} */", off.0)),
+        LiteralValue::Unknown { tag, .. } => {
+            Expr::Unknown(format!("/* unknown literal tag {tag:#04x} */"))
+        }
+    }
+}
+
+/// Resolve the literal array at `off` with `resolve`, or note why the
+/// budget stopped it.
+fn within_budget(
+    budget: &mut BudgetTracker,
+    off: EntityId,
+    resolve: impl FnOnce(&mut BudgetTracker) -> Expr,
+) -> Expr {
+    match budget.enter() {
+        Ok(()) => {
+            let expr = resolve(budget);
+            budget.leave();
+            expr
+        }
+        Err(e) => {
+            log::warn!("not resolving literal array at {off}: {e}");
+            Expr::Unknown(format!("/* literal_array@{}: {e} */", off.0))
+        }
     }
 }
 
@@ -849,6 +1061,23 @@ fn decode_regex_flags(bits: u32) -> String {
     flags
 }
 
+/// Whether `name`, as the file records it, is one es2abc made up for an
+/// anonymous function (`#*#`, `#*#^1`, `#~@0>@3*#`).
+pub fn is_anonymous_name(name: &str) -> bool {
+    let named = name.contains("=#")
+        || name
+            .rfind(">#")
+            .is_some_and(|pos| !name[pos + 2..].is_empty() && !name[pos + 2..].starts_with('@'));
+    if named {
+        return false;
+    }
+    name == "#*#"
+        || name.starts_with("#*#^")
+        || name
+            .rfind('@')
+            .is_some_and(|at| name[at + 1..].contains("*#"))
+}
+
 pub fn clean_abc_name(name: &str) -> String {
     if let Some(pos) = name.rfind("=#") {
         return name[pos + 2..].to_string();
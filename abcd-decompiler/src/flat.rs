@@ -0,0 +1,54 @@
+//! Decompilation without the `structuring` feature.
+//!
+//! Each basic block's statements in code order, headed by a label, with the
+//! branch that ends it left as a comment. No loops or conditionals are
+//! rebuilt, registers keep their positional names and values are not
+//! carried from one block into the next, but every instruction is still
+//! accounted for.
+
+use std::collections::HashMap;
+
+use abcd_ir::cfg::CFG;
+use abcd_ir::expr::Expr;
+use abcd_ir::instruction::Instruction;
+use abcd_ir::stmt::Stmt;
+
+use crate::expr_recovery::{self, MethodContext};
+use crate::js_emitter::emit_expr;
+
+pub(crate) fn flat_method(
+    instructions: &[Instruction],
+    cfg: &CFG,
+    method: &MethodContext,
+) -> Vec<Stmt> {
+    let label = |block: usize| format!("block_{:#x}", cfg.blocks[block].start);
+    let mut stmts = Vec::new();
+    for block in &cfg.blocks {
+        if method.cancelled() {
+            break;
+        }
+        let acc = if block.is_catch_handler {
+            expr_recovery::caught_exception()
+        } else {
+            Expr::Undefined
+        };
+        let recovery = expr_recovery::recover_block_with_state(
+            &instructions[block.first_insn..block.last_insn],
+            method,
+            acc,
+            HashMap::new(),
+        );
+        stmts.push(Stmt::Comment(format!("{}:", label(block.id))));
+        stmts.extend(recovery.stmts);
+        if let [fall_through, target] = block.succs[..] {
+            let mnemonic = instructions[block.last_insn - 1].opcode.mnemonic();
+            stmts.push(Stmt::Comment(format!(
+                "{mnemonic} {} -> {}, else {}",
+                emit_expr(&recovery.final_acc),
+                label(target),
+                label(fall_through)
+            )));
+        }
+    }
+    stmts
+}
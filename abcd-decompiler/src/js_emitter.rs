@@ -1,5 +1,5 @@
-use abcd_ir::expr::{BinOp, Expr, PropKey, UnOp};
-use abcd_ir::stmt::Stmt;
+use abcd_ir::expr::{BinOp, Expr, PropKey};
+use abcd_ir::stmt::{AsmInsn, Stmt};
 use std::fmt::Write;
 
 /// Emit a list of statements as JavaScript source text.
@@ -19,6 +19,10 @@ fn indent_str(level: usize) -> String {
     "    ".repeat(level)
 }
 
+// `Stmt`, `Expr` and `PropKey` are `#[non_exhaustive]`, so each match
+// below needs a wildcard arm; the lint makes clippy name any variant that
+// arm would take, so a new one is spelled out here before it ships.
+#[deny(clippy::wildcard_enum_match_arm)]
 fn emit_stmt(out: &mut String, stmt: &Stmt, indent: usize) {
     let pad = indent_str(indent);
     match stmt {
@@ -141,13 +145,29 @@ fn emit_stmt(out: &mut String, stmt: &Stmt, indent: usize) {
         Stmt::Comment(text) => {
             let _ = writeln!(out, "{pad}// {text}");
         }
+        Stmt::Asm(insns) => {
+            let rows: Vec<(String, &AsmInsn)> = insns.iter().map(|i| (i.hex(), i)).collect();
+            let width = rows.iter().map(|(hex, _)| hex.len()).max().unwrap_or(0);
+            let _ = writeln!(out, "{pad}/* asm");
+            for (hex, insn) in rows {
+                // Keep resolved string operands from closing the comment.
+                let text = insn.text.replace("*/", "* /");
+                let _ = writeln!(out, "{pad} * {:#06x}: {hex:<width$}  {text}", insn.offset);
+            }
+            let _ = writeln!(out, "{pad} */");
+        }
         Stmt::Debugger => {
             let _ = writeln!(out, "{pad}debugger;");
         }
+        // Only variants newer than this crate.
+        other => {
+            let _ = writeln!(out, "{pad}/* unsupported statement: {other:?} */");
+        }
     }
 }
 
-fn emit_expr(expr: &Expr) -> String {
+#[deny(clippy::wildcard_enum_match_arm)]
+pub(crate) fn emit_expr(expr: &Expr) -> String {
     match expr {
         Expr::NumberLit(n) => {
             if *n == n.floor() && n.is_finite() && n.abs() < 1e15 {
@@ -173,10 +193,7 @@ fn emit_expr(expr: &Expr) -> String {
         }
         Expr::UnaryOp { op, expr } => {
             let e = emit_expr_paren(expr, None, false);
-            match op {
-                UnOp::Inc | UnOp::Dec => format!("{op}{e}"),
-                _ => format!("{op}{e}"),
-            }
+            format!("{op}{e}")
         }
         Expr::TypeOf(e) => format!("typeof {}", emit_expr(e)),
         Expr::MemberAccess { object, property } => {
@@ -219,6 +236,7 @@ fn emit_expr(expr: &Expr) -> String {
                     let key = match k {
                         PropKey::Ident(s) => s.clone(),
                         PropKey::Computed(e) => format!("[{}]", emit_expr(e)),
+                        other => format!("/* {other:?} */"),
                     };
                     format!("{key}: {}", emit_expr(v))
                 })
@@ -247,8 +265,11 @@ fn emit_expr(expr: &Expr) -> String {
         Expr::Assign { target, value } => {
             format!("{} = {}", emit_expr(target), emit_expr(value))
         }
+        Expr::Function { kind, name, .. } => format!("/* {kind} {name} */"),
         Expr::Acc => "__acc__".into(),
         Expr::Unknown(s) => s.clone(),
+        // Only variants newer than this crate.
+        other => format!("/* unsupported expression: {other:?} */"),
     }
 }
 
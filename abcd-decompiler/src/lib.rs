@@ -1,15 +1,62 @@
+pub mod arity;
+#[cfg(feature = "arkui")]
+pub mod arkui;
+pub mod budget;
 pub mod decode;
+pub mod disasm;
 pub mod expr_recovery;
+#[cfg(not(feature = "structuring"))]
+mod flat;
 pub mod js_emitter;
+pub mod member_order;
+#[cfg(feature = "structuring")]
+mod naming;
+#[cfg(feature = "structuring")]
+mod scoping;
+pub mod session;
+#[cfg(feature = "structuring")]
 pub mod structuring;
 
-pub use decode::decode_method;
+pub use budget::{Budget, BudgetExceeded};
+pub use decode::{decode_method, decode_method_in, try_blocks};
+pub use expr_recovery::{SyntheticNames, UnknownOpcode, UnknownOpcodePolicy};
+#[cfg(feature = "structuring")]
+pub use naming::stable_function_names;
+pub use session::{AnalysisSession, CancelToken, Cancelled, DecompiledMethod, Task};
 
-use abcd_ir::cfg::CFG;
 use abcd_ir::instruction::TryBlockInfo;
+use abcd_ir::stmt::Stmt;
 use abcd_isa::EntityId;
 
+/// Optional passes compiled into this build.
+///
+/// Each is a crate feature, on by default. A tool that embeds the
+/// decompiler behind its own feature set can check here rather than
+/// mirror the flags it was built with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Features {
+    /// ArkUI `build()` reconstruction, the `arkui` module.
+    pub arkui: bool,
+    /// Control-flow structuring, register naming and declaration scoping,
+    /// the `structuring` module and `stable_function_names`. Without
+    /// it a method decompiles to its basic blocks in code order, each
+    /// headed by a `block_0x…:` comment and ending in a comment naming
+    /// its branch targets.
+    pub structuring: bool,
+}
+
+/// The passes this build has; see [`Features`].
+pub fn features() -> Features {
+    Features {
+        arkui: cfg!(feature = "arkui"),
+        structuring: cfg!(feature = "structuring"),
+    }
+}
+
 /// Decompile a method's bytecode into JavaScript source.
+///
+/// Instructions without a translation become comments; see
+/// [`decompile_method_with`] to choose otherwise.
 pub fn decompile_method(
     code_bytes: &[u8],
     try_blocks: &[TryBlockInfo],
@@ -18,16 +65,71 @@ pub fn decompile_method(
     num_vregs: u32,
     num_args: u32,
 ) -> String {
-    let instructions = decode::decode_method(code_bytes);
-    let cfg = CFG::build(&instructions, try_blocks);
-    let stmts = structuring::structure_method(
-        &instructions,
-        &cfg,
+    decompile_method_with(
+        code_bytes,
         try_blocks,
         resolver,
         method_off,
         num_vregs,
         num_args,
-    );
-    js_emitter::emit_js(&stmts)
+        UnknownOpcodePolicy::Comment,
+        SyntheticNames::Positional,
+        Budget::default(),
+    )
+    .expect("only UnknownOpcodePolicy::Error rejects a method")
+}
+
+/// [`decompile_method`] with an explicit policy for untranslated
+/// instructions, for naming what the file leaves unnamed and for how far
+/// references between literal arrays are followed. Fails only under
+/// [`UnknownOpcodePolicy::Error`].
+#[allow(clippy::too_many_arguments)]
+pub fn decompile_method_with(
+    code_bytes: &[u8],
+    try_blocks: &[TryBlockInfo],
+    resolver: &dyn expr_recovery::StringResolver,
+    method_off: EntityId,
+    num_vregs: u32,
+    num_args: u32,
+    unknown_opcodes: UnknownOpcodePolicy,
+    synthetic_names: SyntheticNames,
+    budget: Budget,
+) -> Result<String, UnknownOpcode> {
+    session(unknown_opcodes, synthetic_names, budget)
+        .decompile_method(
+            code_bytes, try_blocks, resolver, method_off, num_vregs, num_args,
+        )
+        .expect("nothing cancels a session of its own")
+}
+
+/// [`decompile_method_with`], stopping short of printing: the recovered
+/// statements, for passes such as `arkui` that rewrite them.
+#[allow(clippy::too_many_arguments)]
+pub fn decompile_method_stmts(
+    code_bytes: &[u8],
+    try_blocks: &[TryBlockInfo],
+    resolver: &dyn expr_recovery::StringResolver,
+    method_off: EntityId,
+    num_vregs: u32,
+    num_args: u32,
+    unknown_opcodes: UnknownOpcodePolicy,
+    synthetic_names: SyntheticNames,
+    budget: Budget,
+) -> Result<Vec<Stmt>, UnknownOpcode> {
+    session(unknown_opcodes, synthetic_names, budget)
+        .decompile_method_stmts(
+            code_bytes, try_blocks, resolver, method_off, num_vregs, num_args,
+        )
+        .expect("nothing cancels a session of its own")
+}
+
+fn session(
+    unknown_opcodes: UnknownOpcodePolicy,
+    synthetic_names: SyntheticNames,
+    budget: Budget,
+) -> AnalysisSession {
+    AnalysisSession::new()
+        .with_unknown_opcodes(unknown_opcodes)
+        .with_synthetic_names(synthetic_names)
+        .with_budget(budget)
 }
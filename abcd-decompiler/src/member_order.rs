@@ -0,0 +1,135 @@
+//! Source order of a class's methods.
+//!
+//! es2abc does not lay methods out in the file in the order they were
+//! written, so listing a module in method-offset order scatters a class's
+//! members and moves them around between builds. The declaration order
+//! survives in the code, though: `func_main_0` defines the module's
+//! functions and classes in source order, every function defines its
+//! nested functions in order, and the literal array of
+//! `defineclasswithbuffer` lists a class's methods as they were declared.
+//! [`declaration_order`] walks those definitions depth-first from
+//! `func_main_0`, so each function is followed by the ones it defines.
+//!
+//! ```no_run
+//! let abc = abcd_file::File::open_path("modules.abc".as_ref()).unwrap();
+//! let class = abc.class(abc.class_offsets()[0]).unwrap();
+//! for method_off in abcd_decompiler::member_order::declaration_order(&abc, &class.method_offsets()) {
+//!     println!("{}", abc.method_name(method_off).unwrap());
+//! }
+//! ```
+
+use std::collections::HashSet;
+
+use abcd_file::literal::LiteralValue;
+use abcd_file::{EntityId, File};
+use abcd_isa::Bytecode as B;
+
+use crate::budget::{Budget, BudgetTracker};
+use crate::decode::decode_method;
+
+/// The module entry es2abc generates; the walk starts there.
+const MODULE_ENTRY: &str = "func_main_0";
+
+/// `methods` in declaration order: the module entry, then every method in
+/// the order the code defines it. Methods no definition reaches keep their
+/// relative order, each followed by what it defines. Methods outside
+/// `methods` are not followed.
+pub fn declaration_order(abc: &File, methods: &[EntityId]) -> Vec<EntityId> {
+    declaration_order_with(abc, methods, Budget::default())
+}
+
+/// [`declaration_order`] following definitions only as far as `budget`
+/// allows. Methods it cuts off keep their relative order instead, so all
+/// of `methods` are still listed.
+pub fn declaration_order_with(abc: &File, methods: &[EntityId], budget: Budget) -> Vec<EntityId> {
+    let members: HashSet<EntityId> = methods.iter().copied().collect();
+    let is_entry = |m: &EntityId| abc.method_name(*m).is_ok_and(|n| n == MODULE_ENTRY);
+    let roots = methods
+        .iter()
+        .filter(|m| is_entry(m))
+        .chain(methods.iter().filter(|m| !is_entry(m)));
+
+    let mut walk = Walk {
+        abc,
+        members,
+        seen: HashSet::new(),
+        order: Vec::with_capacity(methods.len()),
+        budget: budget.tracker(),
+    };
+    for &root in roots {
+        if walk.seen.insert(root) {
+            walk.follow(root);
+        }
+    }
+    walk.order
+}
+
+struct Walk<'a> {
+    abc: &'a File,
+    members: HashSet<EntityId>,
+    seen: HashSet<EntityId>,
+    order: Vec<EntityId>,
+    budget: BudgetTracker,
+}
+
+impl Walk<'_> {
+    /// List `method`, then what it defines, depth-first.
+    fn follow(&mut self, method: EntityId) {
+        self.order.push(method);
+        for defined in defined_by(self.abc, method) {
+            if !self.members.contains(&defined) || self.seen.contains(&defined) {
+                continue;
+            }
+            if let Err(e) = self.budget.enter() {
+                log::warn!("not following definitions in method at {method}: {e}");
+                return;
+            }
+            self.seen.insert(defined);
+            self.follow(defined);
+            self.budget.leave();
+        }
+    }
+}
+
+/// Methods `method_off` defines, in the order its code defines them.
+fn defined_by(abc: &File, method_off: EntityId) -> Vec<EntityId> {
+    let Some(code) = abc
+        .method(method_off)
+        .ok()
+        .and_then(|m| m.code_off())
+        .and_then(|off| abc.code(off).ok())
+    else {
+        return Vec::new();
+    };
+    let resolve = |id: EntityId| abc.resolve_offset_by_index(method_off, id.0 as u16);
+    let mut out = Vec::new();
+    for insn in decode_method(code.instructions()) {
+        match insn.opcode {
+            B::Definefunc(_, id, _) | B::Definemethod(_, id, _) => out.extend(resolve(id)),
+            B::Defineclasswithbuffer(_, ctor, literals, _, _)
+            | B::CallruntimeDefinesendableclass(_, ctor, literals, _, _) => {
+                out.extend(resolve(ctor));
+                out.extend(class_methods(abc, method_off, literals));
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// The methods a class literal array lists, in its order.
+fn class_methods(abc: &File, method_off: EntityId, literals: EntityId) -> Vec<EntityId> {
+    let Some(vals) = abc
+        .literal_for(method_off)
+        .ok()
+        .and_then(|l| l.enumerate_vals(literals.0 as u16))
+    else {
+        return Vec::new();
+    };
+    vals.iter()
+        .filter_map(|v| match v.resolve_value(abc) {
+            LiteralValue::Method(off) => Some(off),
+            _ => None,
+        })
+        .collect()
+}
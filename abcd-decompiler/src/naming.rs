@@ -0,0 +1,580 @@
+//! Names for register temporaries.
+//!
+//! The compiler reuses registers for unrelated values, so naming a
+//! temporary after its register (`r3`) conflates them. Instead, the
+//! definitions of each local register are grouped into webs: definitions
+//! that reach the same live block entry hold the same variable. Each web
+//! gets a fresh `vN`; two webs share a name only when they are provably the
+//! same variable, because one debug-info local covers both, or one stores a
+//! value just loaded from the same register (`lda v0; inc; sta v0`).
+//!
+//! With [`SyntheticNames::ContentHash`] a web is named after a hash of its
+//! first definition instead of its position, so an edit elsewhere in the
+//! method does not renumber it. Anonymous functions are likewise named
+//! after their body, by [`stable_function_names`].
+//!
+//! Naming takes two passes over the method. The first lets recovery
+//! propagate values into their uses as usual and finds the webs the output
+//! still refers to by name; the second stores exactly those definitions
+//! into their variable.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use abcd_file::{EntityId, File};
+use abcd_ir::cfg::{BlockId, CFG};
+use abcd_ir::expr::Expr;
+use abcd_ir::instruction::{Instruction, TryBlockInfo};
+use abcd_ir::stmt::Stmt;
+use abcd_ir::visit::{self, ExprVisitor};
+use abcd_isa::{Bytecode as B, OperandKind, Reg, lookup_mnemonic};
+
+use crate::decode::decode_method;
+use crate::expr_recovery::{
+    LocalVariable, MethodContext, SyntheticNames, is_anonymous_name, loads_fresh_acc,
+};
+
+/// Index into the definition list. The first `num_vregs` entries are the
+/// values registers hold on method entry, so a register's entry value has
+/// the register's number as its id.
+type DefId = usize;
+
+/// Where a register gets its value.
+struct Def {
+    reg: u16,
+    /// Offset and size of the defining instruction; `None` on method entry.
+    insn: Option<(u32, u8)>,
+}
+
+/// A local register live on entry to a block.
+#[derive(Clone, Copy)]
+struct EntryReg {
+    /// Root definition of the register's web.
+    web: DefId,
+    /// Whether a single definition reaches the entry, so the value a
+    /// predecessor propagates is the one the block sees.
+    single: bool,
+}
+
+/// Variable names for the local registers of one method.
+pub(crate) struct RegisterNames {
+    /// For every block, its live local registers.
+    entry: Vec<HashMap<u16, EntryReg>>,
+    names: HashMap<DefId, String>,
+    /// Web of each definition of a named web, by instruction offset.
+    def_webs: HashMap<u32, DefId>,
+    /// Webs whose definitions are stored into their variable.
+    stored: HashSet<DefId>,
+    /// Declaration order of every name.
+    order: HashMap<String, usize>,
+}
+
+impl RegisterNames {
+    pub(crate) fn compute(
+        instructions: &[Instruction],
+        cfg: &CFG,
+        try_blocks: &[TryBlockInfo],
+        method: &MethodContext,
+        locals: &[LocalVariable],
+        globals: &HashSet<String>,
+    ) -> Self {
+        let synthetic_names = method.synthetic_names;
+        let n = cfg.blocks.len();
+        let num_vregs = method.num_vregs.min(u32::from(u16::MAX) + 1);
+        let is_local = |r: u16| u32::from(r) < num_vregs;
+        let mut defs: Vec<Def> = (0..num_vregs)
+            .map(|r| Def {
+                reg: r as u16,
+                insn: None,
+            })
+            .collect();
+
+        // Last definition of each register per block, registers read before
+        // being defined, and stores of a value reloaded from the same
+        // register (with the definition the load saw, if in the block).
+        let mut gen_defs: Vec<HashMap<u16, DefId>> = vec![HashMap::new(); n];
+        let mut upward: Vec<BTreeSet<u16>> = vec![BTreeSet::new(); n];
+        let mut reloads: Vec<(DefId, BlockId, Option<DefId>)> = Vec::new();
+        for (b, block) in cfg.blocks.iter().enumerate() {
+            let mut acc_src: Option<(u16, Option<DefId>)> = None;
+            for insn in &instructions[block.first_insn..block.last_insn] {
+                for Reg(r) in insn.opcode.reg_reads() {
+                    if is_local(r) && !gen_defs[b].contains_key(&r) {
+                        upward[b].insert(r);
+                    }
+                }
+                match insn.opcode {
+                    B::Lda(r) => acc_src = Some((r.0, gen_defs[b].get(&r.0).copied())),
+                    ref bc if loads_fresh_acc(bc) => acc_src = None,
+                    _ => {}
+                }
+                for Reg(r) in insn.opcode.reg_writes() {
+                    if !is_local(r) {
+                        continue;
+                    }
+                    let id = defs.len();
+                    defs.push(Def {
+                        reg: r,
+                        insn: Some((insn.offset, insn.size)),
+                    });
+                    match (insn.opcode, acc_src) {
+                        (B::Sta(_), Some((src, seen))) if src == r => reloads.push((id, b, seen)),
+                        _ => {}
+                    }
+                    gen_defs[b].insert(r, id);
+                }
+            }
+        }
+
+        // Catch handlers each block may throw to. Handlers have no CFG
+        // predecessors, so values flow into them along these edges.
+        let mut handlers: Vec<Vec<BlockId>> = vec![Vec::new(); n];
+        for tb in try_blocks {
+            let range = tb.start_pc..tb.start_pc + tb.length;
+            for cb in &tb.catch_blocks {
+                let Some(h) = cfg.block_at_offset(cb.handler_pc) else {
+                    continue;
+                };
+                for (b, block) in cfg.blocks.iter().enumerate() {
+                    if range.contains(&block.start) {
+                        handlers[b].push(h);
+                    }
+                }
+            }
+        }
+
+        // The fixpoints below can take many rounds; a cancelled session
+        // gives up on them, and its result is thrown away.
+        let mut live_in = upward;
+        while !method.cancelled() {
+            let mut changed = false;
+            for b in (0..n).rev() {
+                let live_out: BTreeSet<u16> = cfg.blocks[b]
+                    .succs
+                    .iter()
+                    .chain(&handlers[b])
+                    .flat_map(|&s| live_in[s].iter().copied())
+                    .collect();
+                for r in live_out {
+                    if !gen_defs[b].contains_key(&r) {
+                        changed |= live_in[b].insert(r);
+                    }
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        // Reaching definitions, for registers live somewhere.
+        let tracked: BTreeSet<u16> = live_in.iter().flatten().copied().collect();
+        let mut reach: Vec<HashMap<u16, BTreeSet<DefId>>> = vec![HashMap::new(); n];
+        if n > 0 {
+            for &r in &tracked {
+                reach[cfg.entry].entry(r).or_default().insert(r as DefId);
+            }
+        }
+        while !method.cancelled() {
+            let mut changed = false;
+            for b in 0..n {
+                for &r in &tracked {
+                    let reach_in = reach[b].get(&r).cloned().unwrap_or_default();
+                    let reach_out = match gen_defs[b].get(&r) {
+                        Some(&d) => BTreeSet::from([d]),
+                        None => reach_in.clone(),
+                    };
+                    for &s in &cfg.blocks[b].succs {
+                        let set = reach[s].entry(r).or_default();
+                        for &d in &reach_out {
+                            changed |= set.insert(d);
+                        }
+                    }
+                    // A throw anywhere in the block may see the value from
+                    // before or after its definitions.
+                    for &h in &handlers[b] {
+                        let set = reach[h].entry(r).or_default();
+                        for &d in reach_in.iter().chain(&reach_out) {
+                            changed |= set.insert(d);
+                        }
+                    }
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        // What reaches `r` at the entry of `b`; the entry value if nothing
+        // does (an unreachable block).
+        let reaching = |b: BlockId, r: u16| -> Vec<DefId> {
+            match reach[b].get(&r) {
+                Some(set) if !set.is_empty() => set.iter().copied().collect(),
+                _ => vec![r as DefId],
+            }
+        };
+
+        let mut webs = UnionFind::new(defs.len());
+        for (b, live) in live_in.iter().enumerate() {
+            for &r in live {
+                let ds = reaching(b, r);
+                for &d in &ds[1..] {
+                    webs.union(ds[0], d);
+                }
+            }
+        }
+        for &(def, b, seen) in &reloads {
+            let src = seen.unwrap_or_else(|| reaching(b, defs[def].reg)[0]);
+            webs.union(def, src);
+        }
+        let mut debug_names: Vec<(DefId, &str)> = Vec::new();
+        for local in locals {
+            if !is_local(local.reg) || !is_identifier(&local.name) {
+                continue;
+            }
+            let covered: Vec<DefId> = (0..defs.len())
+                .filter(|&d| {
+                    defs[d].reg == local.reg
+                        && defs[d].insn.is_some_and(|(off, size)| {
+                            local.start_pc <= off + u32::from(size) && off < local.end_pc
+                        })
+                })
+                .collect();
+            if let Some(&first) = covered.first() {
+                for &d in &covered[1..] {
+                    webs.union(first, d);
+                }
+                debug_names.push((first, &local.name));
+            }
+        }
+
+        let mut entry: Vec<HashMap<u16, EntryReg>> = vec![HashMap::new(); n];
+        for (b, live) in live_in.iter().enumerate() {
+            for &r in live {
+                let ds = reaching(b, r);
+                let web = webs.find(ds[0]);
+                entry[b].insert(
+                    r,
+                    EntryReg {
+                        web,
+                        single: ds.len() == 1,
+                    },
+                );
+            }
+        }
+
+        // Name the webs live at some block entry, in order of their first
+        // definition; entry values come first.
+        let mut first_def: HashMap<DefId, u64> = HashMap::new();
+        for (d, def) in defs.iter().enumerate() {
+            let key = def.insn.map_or(0, |(off, _)| u64::from(off) + 1);
+            first_def
+                .entry(webs.find(d))
+                .and_modify(|k| *k = (*k).min(key))
+                .or_insert(key);
+        }
+        let mut named: Vec<DefId> = entry
+            .iter()
+            .flat_map(|regs| regs.values().map(|e| e.web))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        named.sort_by_key(|&w| (first_def[&w], w));
+        let mut debug_of: HashMap<DefId, &str> = HashMap::new();
+        for &(d, name) in &debug_names {
+            debug_of.entry(webs.find(d)).or_insert(name);
+        }
+
+        // The earliest definition of each web, for content-derived names.
+        let mut first_of: HashMap<DefId, DefId> = HashMap::new();
+        for d in 0..defs.len() {
+            let key = |d: DefId| defs[d].insn.map_or(0, |(off, _)| off + 1);
+            first_of
+                .entry(webs.find(d))
+                .and_modify(|f| {
+                    if key(d) < key(*f) {
+                        *f = d;
+                    }
+                })
+                .or_insert(d);
+        }
+
+        // Names made up below must not be taken already.
+        let taken: HashSet<&str> = globals
+            .iter()
+            .map(String::as_str)
+            .chain(debug_of.values().copied())
+            .collect();
+        let mut names = HashMap::new();
+        let mut order = HashMap::new();
+        let mut counter = 0;
+        let mut hashed: HashMap<String, usize> = HashMap::new();
+        for w in named {
+            let name = match (debug_of.get(&w), synthetic_names) {
+                (Some(name), _) => name.to_string(),
+                // Made-up names skip the method's globals and debug-info
+                // names: the output would read them as the same variable,
+                // and so would `store_referenced`.
+                (None, SyntheticNames::Positional) => loop {
+                    counter += 1;
+                    let name = format!("v{counter}");
+                    if !taken.contains(name.as_str()) {
+                        break name;
+                    }
+                },
+                (None, SyntheticNames::ContentHash) => {
+                    let def = &defs[first_of[&w]];
+                    let base = format!("v_{:06x}", def_hash(instructions, def) & 0xff_ffff);
+                    // Webs defined alike are told apart by their order.
+                    let seen = hashed.entry(base.clone()).or_insert(0);
+                    loop {
+                        *seen += 1;
+                        let name = match *seen {
+                            1 => base.clone(),
+                            n => format!("{base}_{n}"),
+                        };
+                        if !taken.contains(name.as_str()) {
+                            break name;
+                        }
+                    }
+                }
+            };
+            let next = order.len();
+            order.entry(name.clone()).or_insert(next);
+            names.insert(w, name);
+        }
+
+        let mut def_webs = HashMap::new();
+        for (d, def) in defs.iter().enumerate() {
+            let web = webs.find(d);
+            if let (Some((off, _)), true) = (def.insn, names.contains_key(&web)) {
+                def_webs.insert(off, web);
+            }
+        }
+
+        RegisterNames {
+            entry,
+            names,
+            def_webs,
+            stored: HashSet::new(),
+            order,
+        }
+    }
+
+    /// Register values on entry to `block`, given those its predecessor
+    /// propagated.
+    ///
+    /// A live register reads as its variable unless the propagated value is
+    /// known to be the only one that reaches and is not stored.
+    pub(crate) fn entry_regs(
+        &self,
+        block: BlockId,
+        propagated: &HashMap<u16, Expr>,
+    ) -> HashMap<u16, Expr> {
+        let mut regs = propagated.clone();
+        for (&r, e) in &self.entry[block] {
+            if self.stored.contains(&e.web) || !e.single || !regs.contains_key(&r) {
+                regs.insert(r, Expr::Var(self.names[&e.web].clone()));
+            }
+        }
+        regs
+    }
+
+    /// Store the webs `stmts` refer to by name. Returns whether any of them
+    /// has definitions to store, i.e. whether another pass is needed.
+    ///
+    /// Variables are matched by name, which is exact because made-up names
+    /// avoid every other variable the output can name: the globals and
+    /// debug-info names passed to [`compute`](Self::compute), and the
+    /// decompiler's own names (`r3`, `p1`, `x_1_2`, `__module_0`), which
+    /// never take the `v` forms. Debug-info names are the source's, where a
+    /// local hides any global of the same name.
+    pub(crate) fn store_referenced(&mut self, stmts: &[Stmt]) -> bool {
+        struct Vars(HashSet<String>);
+        impl ExprVisitor for Vars {
+            fn visit_expr(&mut self, expr: &Expr) {
+                if let Expr::Var(name) = expr {
+                    self.0.insert(name.clone());
+                }
+                visit::walk_expr(self, expr);
+            }
+        }
+
+        let mut vars = Vars(HashSet::new());
+        vars.visit_stmts(stmts);
+        let has_defs: HashSet<DefId> = self.def_webs.values().copied().collect();
+        let mut added = false;
+        for (&web, name) in &self.names {
+            if vars.0.contains(name) && has_defs.contains(&web) {
+                added |= self.stored.insert(web);
+            }
+        }
+        added
+    }
+
+    /// Definitions to store into their variable, by instruction offset.
+    pub(crate) fn stored_defs(&self) -> HashMap<u32, String> {
+        self.def_webs
+            .iter()
+            .filter(|(_, web)| self.stored.contains(web))
+            .map(|(&off, web)| (off, self.names[web].clone()))
+            .collect()
+    }
+
+    /// Where `name` goes among the method's declarations, or `None` if it
+    /// is not a temporary. Registers read without a recorded name (`r3`)
+    /// sort after every named web.
+    pub(crate) fn declaration_key(&self, name: &str) -> Option<usize> {
+        if let Some(&k) = self.order.get(name) {
+            return Some(k);
+        }
+        let digits = name.strip_prefix('r')?;
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        digits.parse::<usize>().ok().map(|i| self.order.len() + i)
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+        && name != "this"
+}
+
+/// Content hash of a definition: its instruction and the one before,
+/// which usually computed the value. An entry value hashes its register.
+fn def_hash(instructions: &[Instruction], def: &Def) -> u64 {
+    let mut h = StableHasher::new();
+    match def.insn {
+        None => {
+            h.write(b"entry");
+            h.write_u64(u64::from(def.reg));
+        }
+        Some((off, _)) => {
+            let i = instructions.partition_point(|insn| insn.offset < off);
+            for insn in instructions
+                .get(i.saturating_sub(1)..=i)
+                .unwrap_or_default()
+            {
+                hash_insn(&mut h, &insn.opcode);
+            }
+        }
+    }
+    h.0
+}
+
+/// Content hash of a whole method body.
+pub(crate) fn code_hash(code: &[u8]) -> u64 {
+    let mut h = StableHasher::new();
+    for insn in decode_method(code) {
+        hash_insn(&mut h, &insn.opcode);
+    }
+    h.0
+}
+
+/// Names for the anonymous functions of `abc` under
+/// [`SyntheticNames::ContentHash`]: `anonymous_` and a hash of the body,
+/// numbered `_2`, `_3`, ... in file order when bodies hash alike. Methods
+/// with a name of their own are left out.
+pub fn stable_function_names(abc: &File) -> HashMap<EntityId, String> {
+    let mut names = HashMap::new();
+    let mut hashed: HashMap<String, usize> = HashMap::new();
+    for class_off in abc.class_offsets() {
+        if abc.is_external(class_off) {
+            continue;
+        }
+        let Ok(class) = abc.class(class_off) else {
+            continue;
+        };
+        for method_off in class.method_offsets() {
+            if !is_anonymous_name(&abc.method_name_lossy(method_off)) {
+                continue;
+            }
+            let Some(code) = abc
+                .method(method_off)
+                .ok()
+                .and_then(|m| m.code_off())
+                .and_then(|off| abc.code(off).ok())
+            else {
+                continue;
+            };
+            let base = format!(
+                "anonymous_{:06x}",
+                code_hash(code.instructions()) & 0xff_ffff
+            );
+            let seen = hashed.entry(base.clone()).or_insert(0);
+            *seen += 1;
+            let name = match *seen {
+                1 => base,
+                n => format!("{base}_{n}"),
+            };
+            names.insert(method_off, name);
+        }
+    }
+    names
+}
+
+/// Feed what an instruction does into `h`: mnemonic, registers and
+/// immediates. Entity IDs, jump offsets and IC slots change when code
+/// elsewhere in the file does, so they are left out.
+fn hash_insn(h: &mut StableHasher, bc: &B) {
+    let bc = bc.semantic_form();
+    h.write(bc.mnemonic().as_bytes());
+    let kinds = lookup_mnemonic(bc.mnemonic()).map_or(&[][..], |row| row.operands);
+    let jump = bc.jump_label_arg_index();
+    let (_, args, n) = bc.emit_args();
+    for (i, &value) in args[..n].iter().enumerate() {
+        let moves = jump == Some(i) || kinds.get(i).is_some_and(|op| op.kind == OperandKind::Id);
+        h.write_u64(if moves { 0 } else { value as u64 });
+    }
+}
+
+/// 64-bit FNV-1a. Names derived from it must come out the same from one
+/// release of the tool to the next, which `DefaultHasher` does not promise.
+struct StableHasher(u64);
+
+impl StableHasher {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= u64::from(b);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.write(&value.to_le_bytes());
+    }
+}
+
+struct UnionFind {
+    parent: Vec<usize>,
+}
+
+impl UnionFind {
+    fn new(n: usize) -> Self {
+        UnionFind {
+            parent: (0..n).collect(),
+        }
+    }
+
+    fn find(&mut self, mut x: usize) -> usize {
+        while self.parent[x] != x {
+            self.parent[x] = self.parent[self.parent[x]];
+            x = self.parent[x];
+        }
+        x
+    }
+
+    /// Merge the sets of `a` and `b`, keeping the smaller root so the
+    /// representative of a web is its earliest definition id.
+    fn union(&mut self, a: usize, b: usize) {
+        let (ra, rb) = (self.find(a), self.find(b));
+        let (keep, merge) = if ra < rb { (ra, rb) } else { (rb, ra) };
+        self.parent[merge] = keep;
+    }
+}
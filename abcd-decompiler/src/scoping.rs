@@ -0,0 +1,275 @@
+//! Placement of `let` declarations for register temporaries.
+//!
+//! Register values that are not propagated into their uses surface as
+//! variables named by [`RegisterNames`]. Each one is declared in the lowest
+//! block that dominates every block mentioning it, so the declaration lands
+//! next to its uses instead of at the top of the function. Dominators are
+//! taken over the CFG with its exception edges, and the block found is then
+//! moved out of every try, catch or loop body the temporary outlives: to
+//! the block starting the try, whose declarations the structurer emits
+//! before the `try`, or to the loop header, emitted before the `while`.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use abcd_ir::cfg::{BlockId, CFG};
+use abcd_ir::dominators::Dominators;
+use abcd_ir::expr::Expr;
+use abcd_ir::instruction::TryBlockInfo;
+use abcd_ir::stmt::Stmt;
+use abcd_ir::visit::{self, ExprVisitor};
+
+use crate::expr_recovery::BlockRecovery;
+use crate::naming::RegisterNames;
+
+/// For every block that must declare temporaries, the names to declare,
+/// in the order `names` gives them.
+pub(crate) fn plan_declarations(
+    cfg: &CFG,
+    try_blocks: &[TryBlockInfo],
+    recoveries: &[Option<BlockRecovery>],
+    names: &RegisterNames,
+) -> HashMap<BlockId, Vec<String>> {
+    let doms = Dominators::compute_with_handlers(cfg, try_blocks);
+    let regions = try_regions(cfg, try_blocks);
+    let loops = natural_loops(cfg);
+
+    // Temp -> the blocks mentioning it, each with whether it assigns the
+    // temp before reading it.
+    let mut mentions: BTreeMap<(usize, String), Vec<(BlockId, bool)>> = BTreeMap::new();
+    for (block_id, recovery) in recoveries.iter().enumerate() {
+        let Some(recovery) = recovery else {
+            continue;
+        };
+        let mut vars = Vec::new();
+        for stmt in &recovery.stmts {
+            stmt_vars(stmt, &mut vars);
+        }
+        // The branch condition is emitted in the same scope as the block.
+        if cfg.blocks[block_id].succs.len() == 2 {
+            expr_vars(&recovery.final_acc, &mut vars);
+        }
+        for name in vars {
+            let Some(idx) = names.declaration_key(&name) else {
+                continue;
+            };
+            let kills = assigned_first(&recovery.stmts, &name).is_some();
+            mentions
+                .entry((idx, name))
+                .or_default()
+                .push((block_id, kills));
+        }
+    }
+
+    let mut plan: HashMap<BlockId, Vec<String>> = HashMap::new();
+    for ((_, name), blocks) in mentions {
+        let mut home = blocks[0].0;
+        for &(block, _) in &blocks[1..] {
+            home = doms.common_dominator(home, block).unwrap_or(cfg.entry);
+        }
+        let home = hoist(cfg, home, &blocks, &regions, &loops);
+        plan.entry(home).or_default().push(name);
+    }
+    plan
+}
+
+/// A try body or one of its handlers, as a range of code offsets, and the
+/// block the try starts with.
+struct Region {
+    start: u32,
+    end: u32,
+    try_start: BlockId,
+}
+
+impl Region {
+    fn contains(&self, cfg: &CFG, block: BlockId) -> bool {
+        let start = cfg.blocks[block].start;
+        start >= self.start && start < self.end
+    }
+}
+
+/// A natural loop: its header and every block on a path back to it.
+struct Loop {
+    header: BlockId,
+    body: HashSet<BlockId>,
+}
+
+fn try_regions(cfg: &CFG, try_blocks: &[TryBlockInfo]) -> Vec<Region> {
+    let mut regions = Vec::new();
+    for tb in try_blocks {
+        let Some(try_start) = cfg.block_at_offset(tb.start_pc) else {
+            continue;
+        };
+        regions.push(Region {
+            start: tb.start_pc,
+            end: tb.start_pc + tb.length,
+            try_start,
+        });
+        for cb in &tb.catch_blocks {
+            regions.push(Region {
+                start: cb.handler_pc,
+                end: cb.handler_pc + cb.code_size,
+                try_start,
+            });
+        }
+    }
+    regions
+}
+
+/// One loop per header, taking a back edge to be any edge to a block at or
+/// before its source, as the structurer does.
+fn natural_loops(cfg: &CFG) -> Vec<Loop> {
+    let mut loops: Vec<Loop> = Vec::new();
+    for block in &cfg.blocks {
+        for &header in &block.succs {
+            if header > block.id {
+                continue;
+            }
+            let i = match loops.iter().position(|l| l.header == header) {
+                Some(i) => i,
+                None => {
+                    loops.push(Loop {
+                        header,
+                        body: HashSet::from([header]),
+                    });
+                    loops.len() - 1
+                }
+            };
+            let body = &mut loops[i].body;
+            let mut stack = vec![block.id];
+            while let Some(b) = stack.pop() {
+                if body.insert(b) {
+                    stack.extend(cfg.blocks[b].preds.iter().copied());
+                }
+            }
+        }
+    }
+    loops
+}
+
+/// Move `home` out of every try, catch and loop body that some use of the
+/// temporary lies outside of, and out of loops that carry its value from
+/// one iteration to the next.
+fn hoist(
+    cfg: &CFG,
+    mut home: BlockId,
+    mentions: &[(BlockId, bool)],
+    regions: &[Region],
+    loops: &[Loop],
+) -> BlockId {
+    let mut tried = HashSet::new();
+    while tried.insert(home) {
+        let escaped_try = regions.iter().find(|r| {
+            home != r.try_start
+                && r.contains(cfg, home)
+                && mentions.iter().any(|&(b, _)| !r.contains(cfg, b))
+        });
+        if let Some(region) = escaped_try {
+            home = region.try_start;
+            continue;
+        }
+        let escaped_loop = loops.iter().find(|l| {
+            home != l.header
+                && l.body.contains(&home)
+                && (mentions.iter().any(|(b, _)| !l.body.contains(b)) || carried(cfg, l, mentions))
+        });
+        match escaped_loop {
+            Some(l) => home = l.header,
+            None => return home,
+        }
+    }
+    // Tries and loops that overlap without nesting.
+    cfg.entry
+}
+
+/// Whether the temporary can be read in `l` before the current iteration
+/// assigns it, i.e. its value flows around the back edge.
+fn carried(cfg: &CFG, l: &Loop, mentions: &[(BlockId, bool)]) -> bool {
+    let mut seen = HashSet::new();
+    let mut stack = vec![l.header];
+    while let Some(b) = stack.pop() {
+        if !seen.insert(b) {
+            continue;
+        }
+        match mentions.iter().find(|(m, _)| *m == b) {
+            Some(&(_, true)) => continue,
+            Some(&(_, false)) => return true,
+            None => {}
+        }
+        for &s in &cfg.blocks[b].succs {
+            if s != l.header && l.body.contains(&s) {
+                stack.push(s);
+            }
+        }
+    }
+    false
+}
+
+/// Index of the statement that first mentions `name`, if it is a plain
+/// top-level assignment to it that does not read it.
+fn assigned_first(stmts: &[Stmt], name: &str) -> Option<usize> {
+    let first = stmts.iter().position(|s| {
+        let mut found = Vec::new();
+        stmt_vars(s, &mut found);
+        found.iter().any(|f| f == name)
+    })?;
+    match &stmts[first] {
+        Stmt::Assign {
+            target: Expr::Var(target),
+            value,
+        } if target == name => {
+            let mut in_value = Vec::new();
+            expr_vars(value, &mut in_value);
+            (!in_value.iter().any(|v| v == name)).then_some(first)
+        }
+        _ => None,
+    }
+}
+
+/// Prepend declarations for `names` to a block's statements.
+///
+/// A temporary whose first mention is a plain top-level assignment gets
+/// that assignment turned into its initializer.
+pub(crate) fn declare(stmts: &mut Vec<Stmt>, names: &[String]) {
+    let mut bare = Vec::new();
+    for name in names {
+        match assigned_first(stmts, name) {
+            Some(i) => {
+                if let Stmt::Assign { value, .. } = stmts[i].clone() {
+                    stmts[i] = Stmt::Let {
+                        name: name.clone(),
+                        init: Some(value),
+                    };
+                }
+            }
+            None => bare.push(Stmt::Let {
+                name: name.clone(),
+                init: None,
+            }),
+        }
+    }
+    stmts.splice(0..0, bare);
+}
+
+/// Collects variable names in first-mention order.
+struct Vars<'a>(&'a mut Vec<String>);
+
+impl ExprVisitor for Vars<'_> {
+    fn visit_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Var(name) => {
+                if !self.0.contains(name) {
+                    self.0.push(name.clone());
+                }
+            }
+            _ => visit::walk_expr(self, expr),
+        }
+    }
+}
+
+fn stmt_vars(stmt: &Stmt, out: &mut Vec<String>) {
+    Vars(out).visit_stmt(stmt);
+}
+
+fn expr_vars(expr: &Expr, out: &mut Vec<String>) {
+    Vars(out).visit_expr(expr);
+}
@@ -0,0 +1,445 @@
+//! Cancelling work in progress, for applications that embed the
+//! decompiler.
+//!
+//! A GUI or a server cannot afford to kill its process because one crafted
+//! method sends structuring into a very long walk. An [`AnalysisSession`]
+//! carries a [`CancelToken`] that register naming, structuring and
+//! expression recovery check as they go, block by block and instruction by
+//! instruction; once another thread cancels it, the running call stops at
+//! the next check and returns [`Cancelled`] instead of a partial result.
+//! The session also holds the options of [`crate::decompile_method_with`],
+//! which is a session of its own that nothing cancels.
+//!
+//! [`AnalysisSession::spawn`] runs work on a pool of background threads,
+//! one per core, and returns a [`Task`], which can be waited on or awaited
+//! from any executor.
+//! [`AnalysisSession::decompile_file`] decompiles a whole file, reporting
+//! progress to an [`AnalysisObserver`] as it goes.
+//!
+//! ```ignore
+//! let session = AnalysisSession::new();
+//! let task = session.spawn(move |s| {
+//!     s.decompile_method(&code, &[], &resolver, method_off, num_vregs, num_args)
+//! });
+//! // The user pressed "Stop".
+//! task.cancel();
+//! assert_eq!(task.wait(), Err(Cancelled));
+//! ```
+
+use std::any::Any;
+use std::fmt;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+
+use abcd_file::{AnalysisObserver, File};
+use abcd_ir::cfg::CFG;
+use abcd_ir::instruction::TryBlockInfo;
+use abcd_ir::stmt::Stmt;
+use abcd_isa::EntityId;
+
+use crate::budget::Budget;
+use crate::expr_recovery::{
+    self, MethodContext, StringResolver, SyntheticNames, UnknownOpcode, UnknownOpcodePolicy,
+};
+#[cfg(not(feature = "structuring"))]
+use crate::flat;
+#[cfg(feature = "structuring")]
+use crate::structuring;
+use crate::{decode, js_emitter};
+
+/// One method from [`AnalysisSession::decompile_file`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecompiledMethod {
+    pub class: EntityId,
+    pub method: EntityId,
+    /// The method body as [`AnalysisSession::decompile_method`] prints it.
+    pub source: String,
+}
+
+/// Shared flag asking running work to stop. Clones share the flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask every holder of this token to stop. Cannot be undone.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// `Err(Cancelled)` once cancelled.
+    pub fn check(&self) -> Result<(), Cancelled> {
+        if self.is_cancelled() {
+            Err(Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// The work was cancelled through its [`CancelToken`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("analysis cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Decompilation that can be cancelled from another thread. Clones share
+/// the token, so cancelling one cancels them all.
+#[derive(Debug, Clone, Default)]
+pub struct AnalysisSession {
+    token: CancelToken,
+    budget: Budget,
+    synthetic_names: SyntheticNames,
+    unknown_opcodes: UnknownOpcodePolicy,
+}
+
+impl AnalysisSession {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A session checking `token`, which may be shared with other work.
+    pub fn with_token(token: CancelToken) -> Self {
+        Self {
+            token,
+            ..Self::default()
+        }
+    }
+
+    /// What to do with instructions there is no translation for; see
+    /// [`UnknownOpcodePolicy`].
+    pub fn with_unknown_opcodes(mut self, policy: UnknownOpcodePolicy) -> Self {
+        self.unknown_opcodes = policy;
+        self
+    }
+
+    /// Limits for following references between entities; see [`Budget`].
+    pub fn with_budget(mut self, budget: Budget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// How to name registers and anonymous functions; see
+    /// [`SyntheticNames`].
+    pub fn with_synthetic_names(mut self, names: SyntheticNames) -> Self {
+        self.synthetic_names = names;
+        self
+    }
+
+    /// The session's token, to hand to whoever may cancel it.
+    pub fn token(&self) -> &CancelToken {
+        &self.token
+    }
+
+    pub fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// [`crate::decompile_method_with`] with the session's options,
+    /// stopping early once cancelled. The inner `Err` is the method's own:
+    /// an instruction [`UnknownOpcodePolicy::Error`] refuses.
+    pub fn decompile_method(
+        &self,
+        code_bytes: &[u8],
+        try_blocks: &[TryBlockInfo],
+        resolver: &dyn StringResolver,
+        method_off: EntityId,
+        num_vregs: u32,
+        num_args: u32,
+    ) -> Result<Result<String, UnknownOpcode>, Cancelled> {
+        let stmts = self.decompile_method_stmts(
+            code_bytes, try_blocks, resolver, method_off, num_vregs, num_args,
+        )?;
+        Ok(stmts.map(|stmts| js_emitter::emit_js(&stmts)))
+    }
+
+    /// [`crate::decompile_method_stmts`] with the session's options,
+    /// stopping early once cancelled.
+    pub fn decompile_method_stmts(
+        &self,
+        code_bytes: &[u8],
+        try_blocks: &[TryBlockInfo],
+        resolver: &dyn StringResolver,
+        method_off: EntityId,
+        num_vregs: u32,
+        num_args: u32,
+    ) -> Result<Result<Vec<Stmt>, UnknownOpcode>, Cancelled> {
+        self.token.check()?;
+        let instructions = decode::decode_method(code_bytes);
+        if self.unknown_opcodes == UnknownOpcodePolicy::Error {
+            if let Some(unknown) = expr_recovery::find_unknown(&instructions, code_bytes) {
+                return Ok(Err(unknown));
+            }
+        }
+        self.token.check()?;
+        let cfg = CFG::build(&instructions, try_blocks);
+        self.token.check()?;
+        let method = MethodContext {
+            resolver,
+            method_off,
+            num_vregs,
+            num_args,
+            code: code_bytes,
+            unknown_opcodes: self.unknown_opcodes,
+            synthetic_names: self.synthetic_names,
+            stored_defs: None,
+            budget: self.budget,
+            cancel: Some(&self.token),
+        };
+        #[cfg(feature = "structuring")]
+        let stmts = structuring::structure_method(&instructions, &cfg, try_blocks, &method);
+        #[cfg(not(feature = "structuring"))]
+        let stmts = flat::flat_method(&instructions, &cfg, &method);
+        // Structuring returns whatever it had when it noticed.
+        self.token.check()?;
+        Ok(Ok(stmts))
+    }
+
+    /// Decompile every method with code in `abc`'s local classes, in class
+    /// order. `observer` hears of each class as it starts and each method
+    /// with its source; methods whose code cannot be read, decoded or, under
+    /// [`UnknownOpcodePolicy::Error`], translated are left out with a
+    /// warning.
+    pub fn decompile_file(
+        &self,
+        abc: &File,
+        resolver: &dyn StringResolver,
+        observer: &mut dyn AnalysisObserver,
+    ) -> Result<Vec<DecompiledMethod>, Cancelled> {
+        let mut methods = Vec::new();
+        for class_off in abc.class_offsets() {
+            if abc.is_external(class_off) {
+                continue;
+            }
+            self.token.check()?;
+            let class = match abc.class(class_off) {
+                Ok(c) => c,
+                Err(e) => {
+                    observer.on_warning(None, &format!("class at {class_off}: {e}"));
+                    continue;
+                }
+            };
+            observer.on_class_start(class_off, &abc.get_string_lossy(class_off));
+            for method_off in class.method_offsets() {
+                let code = abc
+                    .method(method_off)
+                    .and_then(|m| m.code_off().map(|off| abc.code(off)).transpose());
+                let code = match code {
+                    Ok(Some(code)) => code,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        observer.on_warning(Some(method_off), &e.to_string());
+                        continue;
+                    }
+                };
+                let bytes = code.instructions();
+                if let Err(e) = decode::decode_method_in(abc, bytes) {
+                    observer.on_warning(Some(method_off), &format!("method at {method_off}: {e}"));
+                    continue;
+                }
+                let source = self.decompile_method(
+                    bytes,
+                    &decode::try_blocks(&code),
+                    resolver,
+                    method_off,
+                    code.num_vregs(),
+                    code.num_args(),
+                )?;
+                let source = match source {
+                    Ok(source) => source,
+                    Err(e) => {
+                        observer
+                            .on_warning(Some(method_off), &format!("method at {method_off}: {e}"));
+                        continue;
+                    }
+                };
+                observer.on_method_done(method_off, Some(&source));
+                methods.push(DecompiledMethod {
+                    class: class_off,
+                    method: method_off,
+                    source,
+                });
+            }
+        }
+        Ok(methods)
+    }
+
+    /// Run `work` with a clone of this session on the shared pool, whose
+    /// threads, one per core, take queued work in order. Work that blocks
+    /// on another task can starve the pool; wait on it from outside.
+    pub fn spawn<T, F>(&self, work: F) -> Task<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&AnalysisSession) -> Result<T, Cancelled> + Send + 'static,
+    {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                outcome: None,
+                waker: None,
+            }),
+            done: Condvar::new(),
+        });
+        let session = self.clone();
+        let worker = Arc::clone(&shared);
+        pool().run(Box::new(move || {
+            let outcome = panic::catch_unwind(AssertUnwindSafe(|| work(&session)));
+            let mut state = worker.state.lock().unwrap_or_else(|e| e.into_inner());
+            state.outcome = Some(outcome);
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+            worker.done.notify_all();
+        }));
+        Task {
+            token: self.token.clone(),
+            shared,
+        }
+    }
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Threads running [`AnalysisSession::spawn`]ed work. Jobs catch their own
+/// panics, so the threads live as long as the process.
+struct Pool {
+    jobs: Mutex<Sender<Job>>,
+}
+
+impl Pool {
+    fn new(threads: usize) -> Self {
+        let (jobs, queue) = mpsc::channel::<Job>();
+        let queue = Arc::new(Mutex::new(queue));
+        for i in 0..threads {
+            let queue = Arc::clone(&queue);
+            thread::Builder::new()
+                .name(format!("abcd-analysis-{i}"))
+                .spawn(move || work_through(&queue))
+                .expect("failed to start an analysis thread");
+        }
+        Pool {
+            jobs: Mutex::new(jobs),
+        }
+    }
+
+    fn run(&self, job: Job) {
+        let jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        jobs.send(job).expect("analysis threads outlive the pool");
+    }
+}
+
+fn work_through(queue: &Mutex<Receiver<Job>>) {
+    loop {
+        // Only waiting for a job holds the lock, not running it.
+        let job = queue.lock().unwrap_or_else(|e| e.into_inner()).recv();
+        match job {
+            Ok(job) => job(),
+            Err(_) => return,
+        }
+    }
+}
+
+fn pool() -> &'static Pool {
+    static POOL: OnceLock<Pool> = OnceLock::new();
+    POOL.get_or_init(|| Pool::new(thread::available_parallelism().map_or(1, |n| n.get())))
+}
+
+type Outcome<T> = thread::Result<Result<T, Cancelled>>;
+
+struct State<T> {
+    outcome: Option<Outcome<T>>,
+    waker: Option<Waker>,
+}
+
+struct Shared<T> {
+    state: Mutex<State<T>>,
+    done: Condvar,
+}
+
+/// Work started by [`AnalysisSession::spawn`]. Block on it with
+/// [`wait`](Self::wait) or `.await` it; a panic in the work resumes in
+/// the waiter. Dropping the task lets the work run to completion
+/// unobserved; cancel it first to stop it.
+pub struct Task<T> {
+    token: CancelToken,
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Task<T> {
+    /// Cancel the session the task runs in.
+    pub fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.lock().outcome.is_some()
+    }
+
+    /// Block until the work returns.
+    pub fn wait(self) -> Result<T, Cancelled> {
+        let mut state = self.lock();
+        loop {
+            if let Some(outcome) = state.outcome.take() {
+                return unwrap_outcome(outcome);
+            }
+            state = self
+                .shared
+                .done
+                .wait(state)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State<T>> {
+        self.shared.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<T> Future for Task<T> {
+    type Output = Result<T, Cancelled>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.lock();
+        match state.outcome.take() {
+            Some(outcome) => Poll::Ready(unwrap_outcome(outcome)),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<T> fmt::Debug for Task<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Task")
+            .field("finished", &self.is_finished())
+            .finish()
+    }
+}
+
+fn unwrap_outcome<T>(outcome: Outcome<T>) -> Result<T, Cancelled> {
+    outcome.unwrap_or_else(|payload: Box<dyn Any + Send>| panic::resume_unwind(payload))
+}
@@ -4,24 +4,49 @@ use abcd_ir::cfg::{BlockId, CFG};
 use abcd_ir::expr::{BinOp, Expr, UnOp};
 use abcd_ir::instruction::{Instruction, TryBlockInfo};
 use abcd_ir::stmt::Stmt;
-use abcd_isa::EntityId;
 
-use crate::expr_recovery::{self, BlockRecovery, StringResolver};
+use crate::expr_recovery::{self, BlockRecovery, MethodContext};
+use crate::naming::RegisterNames;
+use crate::scoping;
 
 /// Decompile a method's instructions into structured JavaScript statements.
 pub fn structure_method(
     instructions: &[Instruction],
     cfg: &CFG,
     try_blocks: &[TryBlockInfo],
-    resolver: &dyn StringResolver,
-    method_off: EntityId,
-    num_vregs: u32,
-    num_args: u32,
+    method: &MethodContext,
 ) -> Vec<Stmt> {
     if cfg.blocks.is_empty() {
         return vec![];
     }
 
+    let locals = method.resolver.local_variables(method.method_off);
+    let globals = expr_recovery::global_names(instructions, method);
+    let mut names =
+        RegisterNames::compute(instructions, cfg, try_blocks, method, &locals, &globals);
+
+    // Values are propagated into their uses wherever possible. A register
+    // the output still reads by name needs its definitions stored, which
+    // takes another pass.
+    let mut result = structure_once(instructions, cfg, try_blocks, method, &names);
+    while !method.cancelled() && names.store_referenced(&result) {
+        let stored = names.stored_defs();
+        let method = MethodContext {
+            stored_defs: Some(&stored),
+            ..*method
+        };
+        result = structure_once(instructions, cfg, try_blocks, &method, &names);
+    }
+    result
+}
+
+fn structure_once(
+    instructions: &[Instruction],
+    cfg: &CFG,
+    try_blocks: &[TryBlockInfo],
+    method: &MethodContext,
+    names: &RegisterNames,
+) -> Vec<Stmt> {
     let loop_headers = find_loop_headers(cfg);
 
     let mut ctx = StructCtx {
@@ -31,17 +56,33 @@ pub fn structure_method(
         try_blocks,
         loop_headers,
         visited: vec![false; cfg.blocks.len()],
-        resolver,
-        method_off,
-        num_vregs,
-        num_args,
+        method: *method,
+        names,
+        decls: HashMap::new(),
+        pending_decls: Vec::new(),
+        acc_live: cfg.acc_live_in(instructions),
     };
 
     // Recover entry block with no predecessor state
     ctx.ensure_recovered(cfg.entry, None, &HashMap::new());
 
+    // The first pass recovers every block the structurer reaches; where
+    // temporaries are used is only known once it is done. Recovery is
+    // memoized, so the second pass produces the same statements and only
+    // adds the declarations.
     let mut result = Vec::new();
     emit_block_range(&mut ctx, &mut result, cfg.entry, None);
+
+    if ctx.method.cancelled() {
+        return result;
+    }
+    ctx.decls = scoping::plan_declarations(cfg, try_blocks, &ctx.recoveries, names);
+    if ctx.decls.is_empty() {
+        return result;
+    }
+    ctx.visited.fill(false);
+    result.clear();
+    emit_block_range(&mut ctx, &mut result, cfg.entry, None);
     result
 }
 
@@ -52,10 +93,16 @@ struct StructCtx<'a> {
     try_blocks: &'a [TryBlockInfo],
     loop_headers: HashSet<BlockId>,
     visited: Vec<bool>,
-    resolver: &'a dyn StringResolver,
-    method_off: EntityId,
-    num_vregs: u32,
-    num_args: u32,
+    method: MethodContext<'a>,
+    names: &'a RegisterNames,
+    /// Temporaries to declare at the start of each block.
+    decls: HashMap<BlockId, Vec<String>>,
+    /// Declarations of blocks folded into a combined condition, to be
+    /// emitted before the `if` that consumes them.
+    pending_decls: Vec<String>,
+    /// Blocks that read the acc they are entered with; a catch binds the
+    /// exception only if its handler does.
+    acc_live: Vec<bool>,
 }
 
 impl<'a> StructCtx<'a> {
@@ -71,25 +118,18 @@ impl<'a> StructCtx<'a> {
         }
         let block = &self.cfg.blocks[block_id];
         let block_insns = &self.instructions[block.first_insn..block.last_insn];
-        let recovery = if let Some(acc) = pred_acc {
-            expr_recovery::recover_block_with_state(
-                block_insns,
-                self.resolver,
-                self.method_off,
-                self.num_vregs,
-                self.num_args,
-                acc.clone(),
-                pred_regs.clone(),
-            )
+        // Handlers are entered by a throw, never by falling into them.
+        let acc = if block.is_catch_handler {
+            expr_recovery::caught_exception()
         } else {
-            expr_recovery::recover_block(
-                block_insns,
-                self.resolver,
-                self.method_off,
-                self.num_vregs,
-                self.num_args,
-            )
+            pred_acc.cloned().unwrap_or(Expr::Undefined)
         };
+        let recovery = expr_recovery::recover_block_with_state(
+            block_insns,
+            &self.method,
+            acc,
+            self.names.entry_regs(block_id, pred_regs),
+        );
         self.recoveries[block_id] = Some(recovery);
     }
 
@@ -107,6 +147,24 @@ impl<'a> StructCtx<'a> {
         }
     }
 
+    /// Append a block's recovered statements, preceded by the temporaries
+    /// it declares.
+    fn emit_stmts(&mut self, result: &mut Vec<Stmt>, block_id: BlockId) {
+        let mut stmts = self.get_recovery(block_id).stmts.clone();
+        if let Some(names) = self.decls.remove(&block_id) {
+            scoping::declare(&mut stmts, &names);
+        }
+        result.extend(stmts);
+    }
+
+    /// Emit bare declarations for everything queued in `pending_decls`.
+    fn flush_pending_decls(&mut self, result: &mut Vec<Stmt>) {
+        let names = std::mem::take(&mut self.pending_decls);
+        for name in names {
+            result.push(Stmt::Let { name, init: None });
+        }
+    }
+
     fn get_recovery(&self, block_id: BlockId) -> &BlockRecovery {
         self.recoveries[block_id]
             .as_ref()
@@ -141,7 +199,7 @@ fn emit_block_range(
     let mut current = start;
 
     loop {
-        if current >= ctx.cfg.blocks.len() || ctx.visited[current] {
+        if current >= ctx.cfg.blocks.len() || ctx.visited[current] || ctx.method.cancelled() {
             break;
         }
         if let Some(stop) = stop_before {
@@ -157,6 +215,11 @@ fn emit_block_range(
             let try_end = tb.start_pc + tb.length;
             let catch_blocks: Vec<_> = tb.catch_blocks.clone();
 
+            // Temporaries homed in the first try block must outlive the try.
+            if let Some(names) = ctx.decls.remove(&current) {
+                result.extend(names.into_iter().map(|name| Stmt::Let { name, init: None }));
+            }
+
             let mut try_body = Vec::new();
             emit_try_body(ctx, &mut try_body, current, try_end);
 
@@ -165,8 +228,8 @@ fn emit_block_range(
             for cb in &catch_blocks {
                 if let Some(catch_block_id) = ctx.cfg.block_at_offset(cb.handler_pc) {
                     if !ctx.visited[catch_block_id] {
-                        if cb.type_idx == 0 {
-                            catch_binding = Some("$err".to_string());
+                        if ctx.acc_live[catch_block_id] {
+                            catch_binding = Some(expr_recovery::CATCH_BINDING.to_string());
                         }
                         ctx.ensure_recovered(catch_block_id, None, &HashMap::new());
                         emit_block_range(ctx, &mut catch_body, catch_block_id, None);
@@ -202,7 +265,7 @@ fn emit_block_range(
         ctx.ensure_recovered(current, None, &HashMap::new());
         ctx.visited[current] = true;
 
-        result.extend(ctx.get_recovery(current).stmts.clone());
+        ctx.emit_stmts(result, current);
 
         if block.first_insn >= block.last_insn {
             break;
@@ -246,6 +309,7 @@ fn emit_block_range(
                     ctx.propagate_and_recover(current, fall_through);
                     let (combined_cond, actual_then_start) =
                         try_combine_conditions(ctx, fall_through, jump_target, cond.clone(), mn);
+                    ctx.flush_pending_decls(result);
 
                     let then_start_block = &ctx.cfg.blocks[actual_then_start];
                     let ft_ends_at_target = then_start_block.succs.len() == 1
@@ -319,7 +383,7 @@ fn emit_loop(ctx: &mut StructCtx, result: &mut Vec<Stmt>, header: BlockId) {
         let mn = last_insn.opcode.mnemonic();
         let acc_expr = ctx.get_recovery(header).final_acc.clone();
 
-        result.extend(ctx.get_recovery(header).stmts.clone());
+        ctx.emit_stmts(result, header);
 
         if jump_target > header && fall_through <= jump_target {
             let cond = make_condition(mn, acc_expr);
@@ -344,7 +408,7 @@ fn emit_loop(ctx: &mut StructCtx, result: &mut Vec<Stmt>, header: BlockId) {
         }
     } else {
         ctx.visited[header] = true;
-        result.extend(ctx.get_recovery(header).stmts.clone());
+        ctx.emit_stmts(result, header);
 
         let mut body = Vec::new();
         if block.succs.len() == 1 && !ctx.visited[block.succs[0]] {
@@ -362,7 +426,7 @@ fn emit_loop(ctx: &mut StructCtx, result: &mut Vec<Stmt>, header: BlockId) {
 fn emit_try_body(ctx: &mut StructCtx, result: &mut Vec<Stmt>, start: BlockId, try_end: u32) {
     let mut current = start;
     loop {
-        if current >= ctx.cfg.blocks.len() || ctx.visited[current] {
+        if current >= ctx.cfg.blocks.len() || ctx.visited[current] || ctx.method.cancelled() {
             break;
         }
         let block = &ctx.cfg.blocks[current];
@@ -372,7 +436,7 @@ fn emit_try_body(ctx: &mut StructCtx, result: &mut Vec<Stmt>, start: BlockId, tr
 
         ctx.ensure_recovered(current, None, &HashMap::new());
         ctx.visited[current] = true;
-        result.extend(ctx.get_recovery(current).stmts.clone());
+        ctx.emit_stmts(result, current);
 
         if block.first_insn >= block.last_insn {
             break;
@@ -466,6 +530,9 @@ fn try_combine_conditions(
         let cond2 = make_condition(ft_mn, ft_acc);
 
         ctx.visited[fall_through] = true;
+        if let Some(names) = ctx.decls.remove(&fall_through) {
+            ctx.pending_decls.extend(names);
+        }
 
         let combined = Expr::BinaryOp {
             op: BinOp::And,
@@ -486,6 +553,9 @@ fn try_combine_conditions(
         let cond2 = make_condition(ft_mn, ft_acc);
 
         ctx.visited[fall_through] = true;
+        if let Some(names) = ctx.decls.remove(&fall_through) {
+            ctx.pending_decls.extend(names);
+        }
 
         let combined = Expr::BinaryOp {
             op: BinOp::Or,
@@ -0,0 +1,132 @@
+//! Call sites checked against the arity their callee declares.
+
+use abcd_decompiler::arity::{ArityMismatch, check_calls};
+use abcd_decompiler::expr_recovery::StringResolver;
+use abcd_decompiler::{Budget, SyntheticNames, UnknownOpcodePolicy, decompile_method_stmts};
+use abcd_ir::expr::{Expr, FunctionKind};
+use abcd_ir::stmt::Stmt;
+use abcd_isa::{EntityId, Imm, Reg, encode, insn};
+
+const PAIR: EntityId = EntityId(0x100);
+const OTHER: EntityId = EntityId(0x200);
+
+fn arity(method: EntityId) -> Option<u32> {
+    (method == PAIR).then_some(2)
+}
+
+fn function(method: EntityId) -> Expr {
+    Expr::Function {
+        kind: FunctionKind::Func,
+        name: format!("f{:x}", method.0),
+        method: Some(method),
+    }
+}
+
+fn call(callee: Expr, n: usize) -> Stmt {
+    Stmt::Expr(Expr::call(callee, vec![Expr::num(0.0); n]))
+}
+
+#[test]
+fn direct_and_named_calls_are_checked() {
+    let stmts = vec![
+        Stmt::Let {
+            name: "pair".into(),
+            init: Some(function(PAIR)),
+        },
+        call(Expr::var("pair"), 2),
+        call(Expr::var("pair"), 3),
+        call(function(PAIR), 1),
+        // Unknown arity, and calls that spread their arguments.
+        call(function(OTHER), 5),
+        Stmt::Expr(Expr::call(
+            Expr::var("pair"),
+            vec![Expr::Spread(Box::new(Expr::var("args")))],
+        )),
+    ];
+    let found = check_calls(&stmts, &mut arity);
+    assert_eq!(
+        found,
+        [
+            ArityMismatch {
+                callee: "pair".into(),
+                method: PAIR,
+                passed: 3,
+                declared: 2,
+            },
+            ArityMismatch {
+                callee: "f100".into(),
+                method: PAIR,
+                passed: 1,
+                declared: 2,
+            },
+        ]
+    );
+}
+
+#[test]
+fn names_bound_to_anything_else_are_not_checked() {
+    let stmts = vec![
+        Stmt::Let {
+            name: "f".into(),
+            init: Some(function(PAIR)),
+        },
+        Stmt::If {
+            cond: Expr::var("c"),
+            then_body: vec![Stmt::Assign {
+                target: Expr::var("f"),
+                value: Expr::var("g"),
+            }],
+            else_body: vec![],
+        },
+        call(Expr::var("f"), 1),
+    ];
+    assert_eq!(check_calls(&stmts, &mut arity), []);
+}
+
+struct Ids;
+
+impl Ids {
+    const PAIR: EntityId = EntityId(1);
+}
+
+impl StringResolver for Ids {
+    fn resolve_string(&self, _: EntityId, _: EntityId) -> Option<String> {
+        None
+    }
+    fn resolve_offset(&self, _: EntityId, id: EntityId) -> Option<EntityId> {
+        (id == Ids::PAIR).then_some(PAIR)
+    }
+    fn resolve_method_name(&self, _: EntityId, id: EntityId) -> Option<String> {
+        (id == Ids::PAIR).then(|| "#*#pair".to_string())
+    }
+}
+
+#[test]
+fn decompiled_call_is_checked() {
+    // const pair = function (a, b) {...}; pair(1);
+    let (code, _) = encode(&[
+        insn::Definefunc::new(Imm(0), Ids::PAIR, Imm(2)),
+        insn::Sta::new(Reg(0)),
+        insn::Ldai::new(Imm(1)),
+        insn::Sta::new(Reg(1)),
+        insn::Lda::new(Reg(0)),
+        insn::Callarg1::new(Imm(1), Reg(1)),
+        insn::Returnundefined::new(),
+    ])
+    .unwrap();
+    let stmts = decompile_method_stmts(
+        &code,
+        &[],
+        &Ids,
+        EntityId(0x10),
+        2,
+        3,
+        UnknownOpcodePolicy::Comment,
+        SyntheticNames::Positional,
+        Budget::default(),
+    )
+    .unwrap();
+    let found = check_calls(&stmts, &mut arity);
+    assert_eq!(found.len(), 1, "{stmts:?}");
+    assert_eq!((found[0].method, found[0].passed), (PAIR, 1));
+}
@@ -0,0 +1,280 @@
+//! `initialRender` creation closures reprinted as ArkUI `build()`.
+
+#![cfg(feature = "arkui")]
+
+use std::collections::HashMap;
+
+use abcd_decompiler::arkui::{is_initial_render, render_build};
+use abcd_decompiler::expr_recovery::StringResolver;
+use abcd_decompiler::{Budget, SyntheticNames, UnknownOpcodePolicy, decompile_method_stmts};
+use abcd_ir::expr::{Expr, FunctionKind, UnOp};
+use abcd_ir::stmt::Stmt;
+use abcd_isa::{EntityId, Imm, Reg, encode, insn};
+
+/// `X.method(args);`
+fn call(object: &str, method: &str, args: Vec<Expr>) -> Stmt {
+    Stmt::Expr(Expr::call(Expr::member(Expr::var(object), method), args))
+}
+
+const COLUMN: EntityId = EntityId(0x100);
+const TEXT: EntityId = EntityId(0x200);
+const IMAGE: EntityId = EntityId(0x300);
+const OLD: EntityId = EntityId(0x400);
+
+/// `this.observeComponentCreation2(/* func NAME */, X);`, the closure
+/// defined at `closure`.
+fn observe(closure: EntityId, component: &str) -> Stmt {
+    Stmt::Expr(Expr::call(
+        Expr::member(Expr::This, "observeComponentCreation2"),
+        vec![
+            Expr::Function {
+                kind: FunctionKind::Func,
+                name: format!("closure{:x}", closure.0),
+                method: Some(closure),
+            },
+            Expr::var(component),
+        ],
+    ))
+}
+
+fn closures() -> HashMap<EntityId, Vec<Stmt>> {
+    HashMap::from([
+        (
+            COLUMN,
+            vec![
+                call("Column", "create", vec![]),
+                call("Column", "width", vec![Expr::string("100%")]),
+            ],
+        ),
+        (
+            TEXT,
+            vec![
+                call("Text", "create", vec![Expr::string("Hello")]),
+                call("Text", "fontSize", vec![Expr::num(50.0)]),
+            ],
+        ),
+        (
+            IMAGE,
+            vec![call("Image", "create", vec![Expr::string("icon.png")])],
+        ),
+    ])
+}
+
+fn render(stmts: &[Stmt]) -> Option<(String, Vec<EntityId>)> {
+    let closures = closures();
+    let build = render_build(stmts, &mut |method| closures.get(&method).cloned())?;
+    Some((build.text, build.closures))
+}
+
+#[test]
+fn nested_components() {
+    let stmts = vec![
+        observe(COLUMN, "Column"),
+        observe(TEXT, "Text"),
+        call("Text", "pop", vec![]),
+        call("Column", "pop", vec![]),
+    ];
+    assert!(is_initial_render(&stmts));
+    let (text, used) = render(&stmts).unwrap();
+    assert_eq!(
+        text,
+        "build() {\n\
+         \x20   Column() {\n\
+         \x20       Text(\"Hello\")\n\
+         \x20           .fontSize(50)\n\
+         \x20   }\n\
+         \x20   .width(\"100%\")\n\
+         }\n"
+    );
+    assert_eq!(used, [COLUMN, TEXT]);
+}
+
+#[test]
+fn unpopped_leaves_stay_siblings() {
+    let stmts = vec![
+        observe(COLUMN, "Column"),
+        observe(IMAGE, "Image"),
+        observe(TEXT, "Text"),
+        call("Text", "pop", vec![]),
+        call("Column", "pop", vec![]),
+    ];
+    let (text, _) = render(&stmts).unwrap();
+    assert!(
+        text.contains("        Image(\"icon.png\")\n        Text(\"Hello\")\n"),
+        "{text}"
+    );
+}
+
+#[test]
+fn bookkeeping_and_unknown_statements() {
+    let mut closures = closures();
+    closures.insert(
+        OLD,
+        vec![
+            call("ViewStackProcessor", "StartGetAccessRecordingFor", vec![]),
+            call("Text", "create", vec![Expr::string("Old")]),
+            Stmt::If {
+                cond: Expr::unary(UnOp::Not, Expr::var("isInitialRender")),
+                then_body: vec![call("Text", "pop", vec![])],
+                else_body: vec![],
+            },
+            call("ViewStackProcessor", "StopGetAccessRecording", vec![]),
+        ],
+    );
+    let stmts = vec![
+        observe(OLD, "Text"),
+        call("Text", "pop", vec![]),
+        call("this", "forEachUpdateFunction", vec![]),
+    ];
+    let build = render_build(&stmts, &mut |method| closures.get(&method).cloned()).unwrap();
+    assert_eq!(
+        build.text,
+        "build() {\n    Text(\"Old\")\n    this.forEachUpdateFunction();\n}\n"
+    );
+}
+
+#[test]
+fn plain_methods_are_left_alone() {
+    let stmts = vec![call("console", "log", vec![Expr::string("hi")])];
+    assert!(!is_initial_render(&stmts));
+    assert!(render(&stmts).is_none());
+}
+
+#[test]
+fn creation_outside_a_closure_is_a_statement() {
+    let stmts = vec![
+        observe(TEXT, "Text"),
+        call("Text", "pop", vec![]),
+        call("Object", "create", vec![Expr::Null]),
+        call("Object", "pop", vec![]),
+    ];
+    let (text, _) = render(&stmts).unwrap();
+    assert_eq!(
+        text,
+        "build() {\n\
+         \x20   Text(\"Hello\")\n\
+         \x20       .fontSize(50)\n\
+         \x20   Object.create(null);\n\
+         \x20   Object.pop();\n\
+         }\n"
+    );
+}
+
+#[test]
+fn unresolved_closures_are_not_followed() {
+    let stmts = vec![Stmt::Expr(Expr::call(
+        Expr::member(Expr::This, "observeComponentCreation2"),
+        vec![
+            Expr::Function {
+                kind: FunctionKind::Func,
+                name: "column".into(),
+                method: None,
+            },
+            Expr::var("Column"),
+        ],
+    ))];
+    assert!(!is_initial_render(&stmts));
+}
+
+/// Strings by ID, and method offsets `0x1000 + ID`.
+struct Names;
+
+impl Names {
+    const OBSERVE: EntityId = EntityId(1);
+    const COLUMN: EntityId = EntityId(2);
+    const POP: EntityId = EntityId(3);
+    const CREATE: EntityId = EntityId(4);
+    const WIDTH: EntityId = EntityId(5);
+    const FULL: EntityId = EntityId(6);
+    const CLOSURE: EntityId = EntityId(7);
+}
+
+impl StringResolver for Names {
+    fn resolve_string(&self, _: EntityId, id: EntityId) -> Option<String> {
+        let s = match id {
+            Names::OBSERVE => "observeComponentCreation2",
+            Names::COLUMN => "Column",
+            Names::POP => "pop",
+            Names::CREATE => "create",
+            Names::WIDTH => "width",
+            Names::FULL => "100%",
+            _ => return None,
+        };
+        Some(s.to_string())
+    }
+    fn resolve_offset(&self, _: EntityId, id: EntityId) -> Option<EntityId> {
+        Some(EntityId(0x1000 + id.0))
+    }
+    fn resolve_method_name(&self, _: EntityId, id: EntityId) -> Option<String> {
+        (id == Names::CLOSURE).then(|| "#~@0>#column".to_string())
+    }
+}
+
+fn decompile(code: &[u8], method_off: EntityId) -> Vec<Stmt> {
+    decompile_method_stmts(
+        code,
+        &[],
+        &Names,
+        method_off,
+        4,
+        3,
+        UnknownOpcodePolicy::Comment,
+        SyntheticNames::Positional,
+        Budget::default(),
+    )
+    .unwrap()
+}
+
+#[cfg(feature = "structuring")]
+#[test]
+fn decompiled_initial_render() {
+    // this.observeComponentCreation2(closure, Column); Column.pop();
+    let (initial_render, _) = encode(&[
+        insn::Definefunc::new(Imm(0), Names::CLOSURE, Imm(2)),
+        insn::Sta::new(Reg(1)),
+        insn::Tryldglobalbyname::new(Imm(1), Names::COLUMN),
+        insn::Sta::new(Reg(2)),
+        insn::Ldthis::new(),
+        insn::Sta::new(Reg(0)),
+        insn::Ldobjbyname::new(Imm(2), Names::OBSERVE),
+        insn::Callthis2::new(Imm(3), Reg(0), Reg(1), Reg(2)),
+        insn::Tryldglobalbyname::new(Imm(4), Names::COLUMN),
+        insn::Sta::new(Reg(3)),
+        insn::Ldobjbyname::new(Imm(5), Names::POP),
+        insn::Callthis0::new(Imm(6), Reg(3)),
+        insn::Returnundefined::new(),
+    ])
+    .unwrap();
+    // Column.create(); Column.width("100%");
+    let (closure, _) = encode(&[
+        insn::Tryldglobalbyname::new(Imm(0), Names::COLUMN),
+        insn::Sta::new(Reg(0)),
+        insn::Ldobjbyname::new(Imm(1), Names::CREATE),
+        insn::Callthis0::new(Imm(2), Reg(0)),
+        insn::Tryldglobalbyname::new(Imm(3), Names::COLUMN),
+        insn::Sta::new(Reg(0)),
+        insn::LdaStr::new(Names::FULL),
+        insn::Sta::new(Reg(1)),
+        insn::Lda::new(Reg(0)),
+        insn::Ldobjbyname::new(Imm(4), Names::WIDTH),
+        insn::Callthis1::new(Imm(5), Reg(0), Reg(1)),
+        insn::Returnundefined::new(),
+    ])
+    .unwrap();
+    let closure_off = EntityId(0x1000 + Names::CLOSURE.0);
+
+    let stmts = decompile(&initial_render, EntityId(0x10));
+    assert!(is_initial_render(&stmts), "{stmts:?}");
+    let build = render_build(&stmts, &mut |method| {
+        (method == closure_off).then(|| decompile(&closure, method))
+    })
+    .unwrap();
+    assert!(
+        build
+            .text
+            .starts_with("build() {\n    Column()\n        .width(\"100%\")\n"),
+        "{}",
+        build.text
+    );
+    assert_eq!(build.closures, [closure_off]);
+}
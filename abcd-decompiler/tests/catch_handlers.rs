@@ -0,0 +1,73 @@
+#![cfg(feature = "structuring")]
+
+use abcd_decompiler::decompile_method;
+use abcd_decompiler::expr_recovery::StringResolver;
+use abcd_ir::instruction::{CatchBlockInfo, TryBlockInfo};
+use abcd_isa::{Bytecode, EntityId, Imm, Label, Reg, encode, insn};
+
+struct NoNames;
+
+impl StringResolver for NoNames {
+    fn resolve_string(&self, _: EntityId, _: EntityId) -> Option<String> {
+        None
+    }
+    fn resolve_offset(&self, _: EntityId, _: EntityId) -> Option<EntityId> {
+        None
+    }
+}
+
+/// `try { return 1 } catch (e) { v0 = e; return v0 }`
+fn decompile() -> String {
+    decompile_handler(&[
+        insn::Sta::new(Reg(0)),
+        insn::Lda::new(Reg(0)),
+        insn::Return::new(),
+    ])
+}
+
+/// `try { return 1 }` with `handler` as its catch-all handler.
+fn decompile_handler(handler: &[Bytecode]) -> String {
+    let mut program = vec![insn::Ldai::new(Imm(1)), insn::Return::new()];
+    program.extend(handler.iter().map(|bc| {
+        let mut bc = *bc;
+        if let Some(idx) = bc.jump_label_arg_index() {
+            let (_, args, _) = bc.emit_args();
+            bc.set_label(Label(args[idx] as u32 + 2));
+        }
+        bc
+    }));
+    let (code, offsets) = encode(&program).unwrap();
+    let try_blocks = [TryBlockInfo {
+        start_pc: 0,
+        length: offsets[2],
+        catch_blocks: vec![CatchBlockInfo {
+            type_idx: 0,
+            handler_pc: offsets[2],
+            code_size: code.len() as u32 - offsets[2],
+        }],
+    }];
+    decompile_method(&code, &try_blocks, &NoNames, EntityId(0), 1, 0)
+}
+
+#[test]
+fn handlers_start_with_the_exception_in_acc() {
+    let js = decompile();
+    assert!(js.contains("catch ($err) {"), "{js}");
+    assert!(js.contains("return $err"), "{js}");
+    assert!(!js.contains("undefined"), "{js}");
+}
+
+#[test]
+fn handlers_that_ignore_the_exception_bind_nothing() {
+    let js = decompile_handler(&[insn::Ldai::new(Imm(0)), insn::Return::new()]);
+    assert!(js.contains("catch {"), "{js}");
+    assert!(!js.contains("$err"), "{js}");
+}
+
+#[test]
+fn exception_read_in_a_later_block_is_bound() {
+    // The handler only jumps; the block it jumps to returns acc.
+    let js = decompile_handler(&[insn::Jmp::new(Label(1)), insn::Return::new()]);
+    assert!(js.contains("catch ($err) {"), "{js}");
+    assert!(js.contains("return $err"), "{js}");
+}
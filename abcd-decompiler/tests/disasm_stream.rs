@@ -0,0 +1,139 @@
+use abcd_decompiler::disasm::DisasmStream;
+use abcd_file::builder::{Builder, CatchBlockDef};
+use abcd_file::{ACC_PUBLIC, File, TypeId};
+use abcd_isa::{encode, insn};
+
+/// Two records of a merged file, `&app/a&` and `&app/b&`, with one method
+/// each.
+fn build() -> File {
+    let mut b = Builder::new().unwrap();
+    b.set_api(12, "").unwrap();
+    let proto = b.create_proto(TypeId::Tagged, &[]);
+    let (body, _) = encode(&[insn::Ldundefined::new(), insn::Return::new()]).unwrap();
+    for (record, method) in [("&app/a&", "f"), ("&app/b&", "g")] {
+        let class = b.add_class(record).unwrap();
+        b.class_add_method_with_proto(class, method, proto, ACC_PUBLIC, &body, 0, 3)
+            .unwrap();
+    }
+    File::open(b.finalize().unwrap()).unwrap()
+}
+
+#[test]
+fn retained_classes_are_the_only_ones_listed() {
+    let abc = build();
+    let all: Vec<String> = DisasmStream::new(&abc).map(|m| m.name).collect();
+    assert_eq!(all.len(), 2, "{all:?}");
+
+    let listed: Vec<(String, String)> = DisasmStream::new(&abc)
+        .retain_classes(|name| name == "&app/b&")
+        .map(|m| (m.class, m.name))
+        .collect();
+    assert_eq!(listed, [("&app/b&".to_string(), "g".to_string())]);
+
+    let mut out = Vec::new();
+    DisasmStream::new(&abc)
+        .retain_classes(|name| name == "&app/a&")
+        .write_ndjson(&mut out)
+        .unwrap();
+    let text = String::from_utf8(out).unwrap();
+    assert_eq!(text.lines().count(), 1, "{text}");
+    assert!(text.contains("\"name\":\"f\""), "{text}");
+}
+
+/// `L_GLOBAL;` with `f`, whose two instructions are covered by a
+/// catch-all try block handled at the second, and `n`, which has no code.
+fn build_global() -> File {
+    let mut b = Builder::new().unwrap();
+    b.set_api(12, "").unwrap();
+    let proto = b.create_proto(TypeId::Tagged, &[]);
+    let class = b.add_class("L_GLOBAL;").unwrap();
+    let source = b.add_string("main.ets").unwrap();
+    b.class_set_source_file(class, source);
+    let (body, pc) = encode(&[insn::Ldundefined::new(), insn::Return::new()]).unwrap();
+    let f = b
+        .class_add_method_with_proto(class, "f", proto, ACC_PUBLIC, &[], 0, 0)
+        .unwrap();
+    let code = b.create_code(2, 3, &body);
+    let catch_all = CatchBlockDef {
+        type_class: None,
+        handler_pc: pc[1],
+        code_size: body.len() as u32 - pc[1],
+    };
+    b.code_add_try_block(code, pc[0], pc[1] - pc[0], &[catch_all]);
+    b.method_set_code(f, code);
+    b.class_add_method_with_proto(class, "n", proto, ACC_PUBLIC, &[], 0, 0)
+        .unwrap();
+    File::open(b.finalize().unwrap()).unwrap()
+}
+
+#[test]
+fn listings_carry_code_and_try_blocks() {
+    let abc = build_global();
+    let mut listings: Vec<_> = DisasmStream::new(&abc)
+        .filter(|m| m.class == "L_GLOBAL;")
+        .collect();
+    listings.sort_by(|a, b| a.name.cmp(&b.name));
+    let [f, n] = &listings[..] else {
+        panic!("{listings:?}");
+    };
+
+    assert_eq!(f.name, "f");
+    assert_eq!(f.source_file.as_deref(), Some("main.ets"));
+    assert_eq!(f.error, None);
+    let code = f.code.as_ref().unwrap();
+    assert_eq!((code.num_vregs, code.num_args, code.code_size), (2, 3, 2));
+    let insns: Vec<_> = code
+        .instructions
+        .iter()
+        .map(|i| (i.offset, i.mnemonic, i.category))
+        .collect();
+    assert_eq!(insns, [(0, "ldundefined", "load"), (1, "return", "jump")]);
+    assert_eq!(code.instructions[1].text, "return");
+    let [tb] = &code.try_blocks[..] else {
+        panic!("{:?}", code.try_blocks);
+    };
+    assert_eq!((tb.start_pc, tb.end_pc), (0, 1));
+    assert_eq!(
+        tb.catches
+            .iter()
+            .map(|c| (c.type_idx, c.handler_pc))
+            .collect::<Vec<_>>(),
+        [(0, 1)]
+    );
+
+    assert_eq!(n.name, "n");
+    assert!(n.code.is_none() && n.error.is_none(), "{n:?}");
+}
+
+#[test]
+fn ndjson_is_one_object_per_method() {
+    let abc = build_global();
+    let mut out = Vec::new();
+    DisasmStream::new(&abc)
+        .retain_classes(|name| name == "L_GLOBAL;")
+        .write_ndjson(&mut out)
+        .unwrap();
+    let text = String::from_utf8(out).unwrap();
+    assert!(text.ends_with('\n'), "{text}");
+
+    let mut lines: Vec<serde_json::Value> = text
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    lines.sort_by_key(|m| m["name"].as_str().unwrap().to_string());
+    let [f, n] = &lines[..] else {
+        panic!("{text}");
+    };
+
+    assert_eq!(f["class"], "L_GLOBAL;");
+    assert_eq!(f["source_file"], "main.ets");
+    assert_eq!(f["code"]["instructions"][1]["mnemonic"], "return");
+    assert_eq!(f["code"]["try_blocks"][0]["catches"][0]["handler_pc"], 1);
+    assert!(f["method_off"].as_u64().unwrap() > 0);
+
+    // Absent fields are left out rather than written as null.
+    let keys: Vec<&str> = n.as_object().unwrap().keys().map(String::as_str).collect();
+    for key in ["code", "error"] {
+        assert!(!keys.contains(&key), "{n}");
+    }
+}
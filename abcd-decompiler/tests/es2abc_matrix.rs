@@ -0,0 +1,286 @@
+//! Compatibility matrix against files built by official es2abc releases.
+//!
+//! Skipped unless `ABCD_ES2ABC_CORPUS` names a directory with one
+//! subdirectory per compiler version, each holding `.abc` files (searched
+//! recursively):
+//!
+//! ```text
+//! corpus/
+//!   4.0.0.0/app.abc
+//!   5.0.2.1/entry/modules.abc
+//! ```
+//!
+//! Every file is opened, disassembled and decompiled method by method. The
+//! test fails if any stage panics or if a version's share of instructions
+//! without a translation exceeds `ABCD_ES2ABC_MAX_UNKNOWN` percent (1 by
+//! default). The matrix is written as Markdown to `ABCD_ES2ABC_MATRIX`, or
+//! to `es2abc-matrix.md` under Cargo's test scratch directory.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+use abcd_decompiler::disasm::DisasmStream;
+use abcd_decompiler::expr_recovery::{StringResolver, handled_mnemonics};
+use abcd_decompiler::{decode_method, decompile_method};
+use abcd_file::{EntityId, File};
+use abcd_ir::instruction::{CatchBlockInfo, TryBlockInfo};
+
+const DEFAULT_MAX_UNKNOWN_PERCENT: f64 = 1.0;
+
+/// Resolves string and offset operands; literal arrays and method names
+/// stay opaque.
+struct FileNames<'a>(&'a File);
+
+impl StringResolver for FileNames<'_> {
+    fn resolve_string(&self, method_off: EntityId, entity_id: EntityId) -> Option<String> {
+        let off = self.resolve_offset(method_off, entity_id)?;
+        self.0.get_string(off).ok()
+    }
+    fn resolve_offset(&self, method_off: EntityId, entity_id: EntityId) -> Option<EntityId> {
+        self.0
+            .resolve_offset_by_index(method_off, entity_id.0 as u16)
+    }
+    fn get_string_at_offset(&self, offset: EntityId) -> Option<String> {
+        self.0.get_string(offset).ok()
+    }
+}
+
+/// Totals for one compiler version.
+#[derive(Default)]
+struct Row {
+    files: usize,
+    unopened: usize,
+    methods: usize,
+    insns: usize,
+    unknown: usize,
+    panics: usize,
+    /// Header versions of the files that opened.
+    abc_versions: BTreeSet<String>,
+    /// Unknown mnemonics with their counts.
+    unknown_mnemonics: BTreeMap<&'static str, usize>,
+    /// `file: what went wrong`, for files that failed to open or panicked.
+    failures: Vec<String>,
+}
+
+#[test]
+fn es2abc_version_matrix() {
+    let Some(corpus) = std::env::var_os("ABCD_ES2ABC_CORPUS") else {
+        eprintln!("ABCD_ES2ABC_CORPUS not set; skipping");
+        return;
+    };
+    let max_unknown = std::env::var("ABCD_ES2ABC_MAX_UNKNOWN")
+        .ok()
+        .map(|v| {
+            v.parse::<f64>()
+                .expect("ABCD_ES2ABC_MAX_UNKNOWN is a number")
+        })
+        .unwrap_or(DEFAULT_MAX_UNKNOWN_PERCENT);
+
+    let mut rows = BTreeMap::new();
+    let mut versions: Vec<PathBuf> = fs::read_dir(&corpus)
+        .expect("ABCD_ES2ABC_CORPUS is a directory")
+        .filter_map(|e| Some(e.ok()?.path()))
+        .filter(|p| p.is_dir())
+        .collect();
+    versions.sort();
+    for dir in versions {
+        let version = dir.file_name().unwrap().to_string_lossy().into_owned();
+        let mut row = Row::default();
+        let mut files = Vec::new();
+        collect_abc_files(&dir, &mut files);
+        files.sort();
+        for path in files {
+            check_file(&path, &dir, &mut row);
+        }
+        rows.insert(version, row);
+    }
+
+    let matrix = render(&rows, max_unknown);
+    let out = std::env::var_os("ABCD_ES2ABC_MATRIX")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| Path::new(env!("CARGO_TARGET_TMPDIR")).join("es2abc-matrix.md"));
+    fs::write(&out, &matrix).expect("matrix is writable");
+    eprintln!("{matrix}\nwritten to {}", out.display());
+
+    for (version, row) in &rows {
+        assert_eq!(row.panics, 0, "{version}: {}", row.failures.join("; "));
+        let rate = unknown_percent(row);
+        assert!(
+            rate <= max_unknown,
+            "{version}: {rate:.2}% unknown instructions, limit {max_unknown}%"
+        );
+    }
+}
+
+fn collect_abc_files(dir: &Path, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_abc_files(&path, out);
+        } else if path.extension().is_some_and(|e| e == "abc") {
+            out.push(path);
+        }
+    }
+}
+
+fn check_file(path: &Path, root: &Path, row: &mut Row) {
+    let name = path
+        .strip_prefix(root)
+        .unwrap_or(path)
+        .display()
+        .to_string();
+    row.files += 1;
+    let data = match fs::read(path) {
+        Ok(d) => d,
+        Err(e) => {
+            row.unopened += 1;
+            row.failures.push(format!("{name}: {e}"));
+            return;
+        }
+    };
+    let abc = match panic::catch_unwind(|| File::open(data)) {
+        Ok(Ok(abc)) => abc,
+        Ok(Err(e)) => {
+            row.unopened += 1;
+            row.failures.push(format!("{name}: {e}"));
+            return;
+        }
+        Err(_) => {
+            row.panics += 1;
+            row.failures.push(format!("{name}: panicked while opening"));
+            return;
+        }
+    };
+    row.abc_versions.insert(abc.version().to_string());
+
+    let disasm = panic::catch_unwind(AssertUnwindSafe(|| DisasmStream::new(&abc).count()));
+    if disasm.is_err() {
+        row.panics += 1;
+        row.failures
+            .push(format!("{name}: panicked while disassembling"));
+    }
+
+    let handled = handled_mnemonics();
+    let resolver = FileNames(&abc);
+    for class_off in abc.class_offsets() {
+        if abc.is_external(class_off) {
+            continue;
+        }
+        let Ok(class) = abc.class(class_off) else {
+            continue;
+        };
+        for method_off in class.method_offsets() {
+            let Some(code) = abc
+                .method(method_off)
+                .ok()
+                .and_then(|m| m.code_off())
+                .and_then(|off| abc.code(off).ok())
+            else {
+                continue;
+            };
+            row.methods += 1;
+            let bytes = code.instructions();
+            for insn in decode_method(bytes) {
+                row.insns += 1;
+                let mnemonic = insn.opcode.mnemonic();
+                if !insn.opcode.is_jump() && !handled.contains(mnemonic) {
+                    row.unknown += 1;
+                    *row.unknown_mnemonics.entry(mnemonic).or_default() += 1;
+                }
+            }
+            let try_blocks = try_blocks(&code);
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                decompile_method(
+                    bytes,
+                    &try_blocks,
+                    &resolver,
+                    method_off,
+                    code.num_vregs(),
+                    code.num_args(),
+                )
+            }));
+            if result.is_err() {
+                row.panics += 1;
+                row.failures.push(format!(
+                    "{name}: panicked while decompiling method at {method_off}"
+                ));
+            }
+        }
+    }
+}
+
+fn try_blocks(code: &abcd_file::code::Code<'_>) -> Vec<TryBlockInfo> {
+    code.try_blocks()
+        .iter()
+        .map(|tb| TryBlockInfo {
+            start_pc: tb.start_pc,
+            length: tb.length,
+            catch_blocks: tb
+                .catches
+                .iter()
+                .map(|cb| CatchBlockInfo {
+                    type_idx: cb.type_idx,
+                    handler_pc: cb.handler_pc,
+                    code_size: cb.code_size,
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+fn unknown_percent(row: &Row) -> f64 {
+    if row.insns == 0 {
+        0.0
+    } else {
+        row.unknown as f64 * 100.0 / row.insns as f64
+    }
+}
+
+fn render(rows: &BTreeMap<String, Row>, max_unknown: f64) -> String {
+    let mut out = String::from("# es2abc compatibility matrix\n\n");
+    let _ = writeln!(out, "Unknown-instruction limit: {max_unknown}%\n");
+    out.push_str(
+        "| es2abc | .abc versions | files | unopened | methods | instructions | unknown | panics | status |\n\
+         |---|---|---:|---:|---:|---:|---:|---:|---|\n",
+    );
+    for (version, row) in rows {
+        let rate = unknown_percent(row);
+        let status = if row.panics == 0 && rate <= max_unknown {
+            "ok"
+        } else {
+            "FAIL"
+        };
+        let abc_versions: Vec<&str> = row.abc_versions.iter().map(String::as_str).collect();
+        let _ = writeln!(
+            out,
+            "| {version} | {} | {} | {} | {} | {} | {rate:.2}% | {} | {status} |",
+            abc_versions.join(", "),
+            row.files,
+            row.unopened,
+            row.methods,
+            row.insns,
+            row.panics,
+        );
+    }
+    for (version, row) in rows {
+        if row.unknown_mnemonics.is_empty() && row.failures.is_empty() {
+            continue;
+        }
+        let _ = writeln!(out, "\n## {version}\n");
+        let mut unknown: Vec<_> = row.unknown_mnemonics.iter().collect();
+        unknown.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        for (mnemonic, count) in unknown {
+            let _ = writeln!(out, "- `{mnemonic}`: {count}");
+        }
+        for failure in &row.failures {
+            let _ = writeln!(out, "- {failure}");
+        }
+    }
+    out
+}
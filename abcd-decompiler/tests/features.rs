@@ -0,0 +1,12 @@
+use abcd_decompiler::{Features, features};
+
+#[test]
+fn reports_the_enabled_features() {
+    assert_eq!(
+        features(),
+        Features {
+            arkui: cfg!(feature = "arkui"),
+            structuring: cfg!(feature = "structuring"),
+        }
+    );
+}
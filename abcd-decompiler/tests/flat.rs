@@ -0,0 +1,39 @@
+//! Without the `structuring` feature each block is listed in code order
+//! under a label, with its branch left as a comment.
+
+#![cfg(not(feature = "structuring"))]
+
+use abcd_decompiler::decompile_method;
+use abcd_decompiler::expr_recovery::StringResolver;
+use abcd_isa::{EntityId, Imm, Label, encode, insn};
+
+struct NoNames;
+
+impl StringResolver for NoNames {
+    fn resolve_string(&self, _: EntityId, _: EntityId) -> Option<String> {
+        None
+    }
+    fn resolve_offset(&self, _: EntityId, _: EntityId) -> Option<EntityId> {
+        None
+    }
+}
+
+#[test]
+fn blocks_are_labelled_and_branches_commented() {
+    // `ldai 1; jeqz 4; ldai 2; return; ldai 3; return`
+    let (code, _) = encode(&[
+        insn::Ldai::new(Imm(1)),
+        insn::Jeqz::new(Label(4)),
+        insn::Ldai::new(Imm(2)),
+        insn::Return::new(),
+        insn::Ldai::new(Imm(3)),
+        insn::Return::new(),
+    ])
+    .unwrap();
+    let js = decompile_method(&code, &[], &NoNames, EntityId(0), 0, 0);
+    assert!(js.contains("// block_0x0:"), "{js}");
+    assert!(js.contains("jeqz 1 -> block_"), "{js}");
+    assert!(js.contains("return 2"), "{js}");
+    assert!(js.contains("return 3"), "{js}");
+    assert!(!js.contains("if ("), "{js}");
+}
@@ -0,0 +1,107 @@
+//! Anonymous functions named after their body under
+//! `SyntheticNames::ContentHash`.
+
+#![cfg(feature = "structuring")]
+
+use std::collections::HashMap;
+
+use abcd_decompiler::expr_recovery::{StringResolver, is_anonymous_name};
+use abcd_decompiler::{AnalysisSession, SyntheticNames, stable_function_names};
+use abcd_file::builder::Builder;
+use abcd_file::{ACC_PUBLIC, File, TypeId};
+use abcd_isa::{EntityId, Imm, encode, insn};
+
+/// Resolves every method ID to `name`, and anonymous functions to `stable`.
+struct Callee {
+    name: &'static str,
+    stable: &'static str,
+}
+
+impl StringResolver for Callee {
+    fn resolve_string(&self, _: EntityId, _: EntityId) -> Option<String> {
+        None
+    }
+    fn resolve_offset(&self, _: EntityId, _: EntityId) -> Option<EntityId> {
+        None
+    }
+    fn resolve_method_name(&self, _: EntityId, _: EntityId) -> Option<String> {
+        Some(self.name.into())
+    }
+    fn function_name(&self, _: EntityId, _: EntityId) -> Option<String> {
+        Some(self.stable.into())
+    }
+}
+
+fn decompile(callee: &Callee, names: SyntheticNames) -> String {
+    let (code, _) = encode(&[
+        insn::Definefunc::new(Imm(0), EntityId(0), Imm(0)),
+        insn::Return::new(),
+    ])
+    .unwrap();
+    AnalysisSession::new()
+        .with_synthetic_names(names)
+        .decompile_method(&code, &[], callee, EntityId(0), 0, 0)
+        .unwrap()
+        .unwrap()
+}
+
+#[test]
+fn only_made_up_names_are_anonymous() {
+    assert!(is_anonymous_name("#*#"));
+    assert!(is_anonymous_name("#*#^1"));
+    assert!(is_anonymous_name("#~@0>@1*#"));
+    assert!(is_anonymous_name("#~@0>@1*#^2"));
+    assert!(!is_anonymous_name("#*#anonymousHelper"));
+    assert!(!is_anonymous_name("#~@0>#anonymous"));
+    assert!(!is_anonymous_name("#~@0=#Point"));
+}
+
+#[test]
+fn content_hash_renames_anonymous_functions_only() {
+    let anonymous = Callee {
+        name: "#*#^1",
+        stable: "anonymous_e01b2c",
+    };
+    let js = decompile(&anonymous, SyntheticNames::ContentHash);
+    assert!(js.contains("anonymous_e01b2c"), "{js}");
+    let js = decompile(&anonymous, SyntheticNames::Positional);
+    assert!(!js.contains("anonymous_e01b2c"), "{js}");
+
+    let named = Callee {
+        name: "#*#anonymousHelper",
+        stable: "anonymous_e01b2c",
+    };
+    let js = decompile(&named, SyntheticNames::ContentHash);
+    assert!(js.contains("anonymousHelper"), "{js}");
+    assert!(!js.contains("anonymous_e01b2c"), "{js}");
+}
+
+#[test]
+fn identical_bodies_are_numbered() {
+    let mut b = Builder::new().unwrap();
+    let class = b.add_class("Lmain;").unwrap();
+    let proto = b.create_proto(TypeId::Tagged, &[]);
+    let (empty, _) = encode(&[insn::Returnundefined::new()]).unwrap();
+    let (other, _) = encode(&[insn::Ldundefined::new(), insn::Return::new()]).unwrap();
+    let methods = [
+        ("#*#^1", &empty),
+        ("#*#^2", &empty),
+        ("#*#anonymousHelper", &empty),
+        ("#*#^3", &other),
+    ];
+    for (name, body) in methods {
+        b.class_add_method_with_proto(class, name, proto, ACC_PUBLIC, body, 0, 3)
+            .unwrap();
+    }
+    let abc = File::open(b.finalize().unwrap()).unwrap();
+
+    let by_name: HashMap<String, String> = stable_function_names(&abc)
+        .into_iter()
+        .map(|(off, name)| (abc.method_name_lossy(off).into_owned(), name))
+        .collect();
+    assert_eq!(by_name.len(), 3, "{by_name:?}");
+    let first = &by_name["#*#^1"];
+    assert!(first.starts_with("anonymous_"), "{first}");
+    assert_eq!(by_name["#*#^2"], format!("{first}_2"));
+    assert!(!by_name["#*#^3"].starts_with(first.as_str()), "{by_name:?}");
+}
@@ -0,0 +1,35 @@
+//! Code is decoded against the instruction set of the file's version.
+
+use abcd_decompiler::{decode_method, decode_method_in};
+use abcd_file::builder::Builder;
+use abcd_file::{ACC_PUBLIC, File, TypeId};
+use abcd_isa::{EntityId, Imm, Reg, encode, insn};
+
+/// One method running `definefieldbyname`, which API 11 introduced.
+fn build(api: u8) -> (File, Vec<u8>) {
+    let mut b = Builder::new().unwrap();
+    b.set_api(api, "").unwrap();
+    let class = b.add_class("Lmain;").unwrap();
+    let proto = b.create_proto(TypeId::Tagged, &[]);
+    let (body, _) = encode(&[
+        insn::Definefieldbyname::new(Imm(0), EntityId(0), Reg(0)),
+        insn::Returnundefined::new(),
+    ])
+    .unwrap();
+    b.class_add_method_with_proto(class, "f", proto, ACC_PUBLIC, &body, 1, 0)
+        .unwrap();
+    (File::open(b.finalize().unwrap()).unwrap(), body)
+}
+
+#[test]
+fn later_instructions_are_refused_in_older_files() {
+    let (api9, body) = build(9);
+    assert_eq!(decode_method(&body).len(), 2);
+    let err = decode_method_in(&api9, &body).unwrap_err();
+    assert!(err.contains("definefieldbyname"), "{err}");
+
+    let (api12, body) = build(12);
+    let decoded = decode_method_in(&api12, &body).unwrap();
+    assert_eq!(decoded.len(), 2);
+    assert_eq!(decoded[0].opcode.mnemonic(), "definefieldbyname");
+}
@@ -0,0 +1,98 @@
+use abcd_decompiler::expr_recovery::StringResolver;
+use abcd_decompiler::{
+    Budget, SyntheticNames, UnknownOpcodePolicy, decompile_method, decompile_method_with,
+};
+use abcd_file::literal::{LiteralArray, LiteralTag, LiteralValue};
+use abcd_isa::{EntityId, Imm, encode, insn};
+
+/// Every literal array holds two references to itself.
+struct SelfReferencing;
+
+impl StringResolver for SelfReferencing {
+    fn resolve_string(&self, _: EntityId, _: EntityId) -> Option<String> {
+        None
+    }
+    fn resolve_offset(&self, _: EntityId, _: EntityId) -> Option<EntityId> {
+        None
+    }
+    fn resolve_literal_array(&self, _: EntityId, _: EntityId) -> Option<LiteralArray> {
+        self.resolve_literal_array_at(EntityId(0x100))
+    }
+    fn resolve_literal_array_at(&self, offset: EntityId) -> Option<LiteralArray> {
+        let entry = (LiteralTag::LiteralArray, LiteralValue::LiteralArray(offset));
+        let tag = (LiteralTag::TagValue, LiteralValue::TagValue(0));
+        Some(LiteralArray {
+            entries: vec![tag.clone(), entry.clone(), tag, entry],
+        })
+    }
+}
+
+/// The literal array at every offset holds the one at the next offset.
+struct Chain;
+
+impl StringResolver for Chain {
+    fn resolve_string(&self, _: EntityId, _: EntityId) -> Option<String> {
+        None
+    }
+    fn resolve_offset(&self, _: EntityId, _: EntityId) -> Option<EntityId> {
+        None
+    }
+    fn resolve_literal_array(&self, _: EntityId, _: EntityId) -> Option<LiteralArray> {
+        self.resolve_literal_array_at(EntityId(0x100))
+    }
+    fn resolve_literal_array_at(&self, offset: EntityId) -> Option<LiteralArray> {
+        let next = LiteralValue::LiteralArray(EntityId(offset.0 + 1));
+        Some(LiteralArray {
+            entries: vec![
+                (LiteralTag::TagValue, LiteralValue::TagValue(0)),
+                (LiteralTag::LiteralArray, next),
+            ],
+        })
+    }
+}
+
+fn create_array() -> Vec<u8> {
+    encode(&[
+        insn::Createarraywithbuffer::new(Imm(0), EntityId(0)),
+        insn::Return::new(),
+    ])
+    .unwrap()
+    .0
+}
+
+#[test]
+fn max_depth_is_the_number_of_levels_followed() {
+    let budget = Budget {
+        max_depth: 2,
+        ..Budget::default()
+    };
+    let js = decompile_method_with(
+        &create_array(),
+        &[],
+        &Chain,
+        EntityId(0),
+        0,
+        0,
+        UnknownOpcodePolicy::Comment,
+        SyntheticNames::Positional,
+        budget,
+    )
+    .unwrap();
+    // 0x100 holds 0x101 and 0x102; 0x103 would be a third level.
+    assert!(
+        js.contains("[[[/* literal_array@259: nested more than 2 levels deep */]]]"),
+        "{js}"
+    );
+}
+
+#[test]
+fn cyclic_literal_arrays_stop_at_the_budget() {
+    let (code, _) = encode(&[
+        insn::Createarraywithbuffer::new(Imm(0), EntityId(0)),
+        insn::Return::new(),
+    ])
+    .unwrap();
+    let js = decompile_method(&code, &[], &SelfReferencing, EntityId(0), 0, 0);
+    assert!(js.contains("literal_array@256: "), "{js}");
+    assert!(js.contains("more than"), "{js}");
+}
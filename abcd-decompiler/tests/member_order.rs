@@ -0,0 +1,94 @@
+use abcd_decompiler::Budget;
+use abcd_decompiler::member_order::{declaration_order, declaration_order_with};
+use abcd_file::builder::{Builder, IndexDep};
+use abcd_file::literal::LiteralTag;
+use abcd_file::{ACC_PUBLIC, EntityId, File, TypeId};
+use abcd_isa::{Imm, Reg, encode, insn};
+
+/// `function a() {} class C { m() {} }` plus an unreferenced `orphan`,
+/// with the methods laid out in neither source nor reverse order.
+fn build() -> File {
+    let mut b = Builder::new().unwrap();
+    b.set_api(12, "").unwrap();
+    let class = b.add_class("Lmain;").unwrap();
+    let proto = b.create_proto(TypeId::Tagged, &[]);
+    let add = |b: &mut Builder, name: &str| {
+        let (body, _) = encode(&[insn::Returnundefined::new()]).unwrap();
+        b.class_add_method_with_proto(class, name, proto, ACC_PUBLIC, &body, 0, 3)
+            .unwrap()
+    };
+    add(&mut b, "orphan");
+    let m = add(&mut b, "m");
+    let ctor = add(&mut b, "C");
+    let a = add(&mut b, "a");
+    let main = b
+        .class_add_method_with_proto(class, "func_main_0", proto, ACC_PUBLIC, &[], 0, 0)
+        .unwrap();
+
+    let layout = b.add_literal_array("C").unwrap();
+    let name = b.add_string("m").unwrap();
+    b.literal_array_add_u8(layout, LiteralTag::String as u8);
+    b.literal_array_add_string(layout, name);
+    b.literal_array_add_u8(layout, LiteralTag::Method as u8);
+    b.literal_array_add_method(layout, m);
+    b.literal_array_add_u8(layout, LiteralTag::Integer as u8);
+    b.literal_array_add_u32(layout, 1);
+
+    let deps = [
+        IndexDep::Method(a),
+        IndexDep::Method(ctor),
+        IndexDep::LiteralArray(layout),
+    ];
+    for dep in deps {
+        b.method_add_index_dependency(main, dep);
+    }
+    let body = |ids: [u16; 3]| {
+        let id = |i: usize| EntityId(ids[i].into());
+        encode(&[
+            insn::Definefunc::new(Imm(0), id(0), Imm(0)),
+            insn::Defineclasswithbuffer::new(Imm(1), id(1), id(2), Imm(0), Reg(0)),
+            insn::Returnundefined::new(),
+        ])
+        .unwrap()
+        .0
+    };
+    let code = b.create_code(1, 3, &body([0; 3]));
+    b.method_set_code(main, code);
+    b.finalize().unwrap();
+    let ids = deps.map(|dep| b.method_index_of(main, dep).unwrap());
+    b.code_set_instructions(code, &body(ids));
+    File::open(b.finalize().unwrap()).unwrap()
+}
+
+#[test]
+fn definitions_give_source_order() {
+    let abc = build();
+    let class = abc
+        .class(abc.class_id_by_name("Lmain;").unwrap().unwrap())
+        .unwrap();
+    let names: Vec<String> = declaration_order(&abc, &class.method_offsets())
+        .into_iter()
+        .map(|m| abc.method_name(m).unwrap())
+        .collect();
+    assert_eq!(names, ["func_main_0", "a", "C", "m", "orphan"]);
+}
+
+#[test]
+fn the_budget_stops_following_definitions() {
+    let abc = build();
+    let class = abc
+        .class(abc.class_id_by_name("Lmain;").unwrap().unwrap())
+        .unwrap();
+    let budget = Budget {
+        max_depth: 1,
+        ..Budget::default()
+    };
+    let mut names: Vec<String> = declaration_order_with(&abc, &class.method_offsets(), budget)
+        .into_iter()
+        .map(|m| abc.method_name(m).unwrap())
+        .collect();
+    // `m` is defined two levels down, by the class `func_main_0` defines.
+    assert_eq!(names[..3], ["func_main_0", "a", "C"]);
+    names.sort();
+    assert_eq!(names, ["C", "a", "func_main_0", "m", "orphan"]);
+}
@@ -0,0 +1,121 @@
+//! Every opcode in the ISA table is either translated by expression recovery
+//! or listed here as a known gap. A vendor sync that adds instructions fails
+//! this test until each new mnemonic is handled or explicitly acknowledged.
+
+use std::collections::BTreeSet;
+
+use abcd_decompiler::expr_recovery::handled_mnemonics;
+use abcd_isa::opcode_table;
+
+/// Mnemonics that currently fall through to the catch-all comment.
+const KNOWN_UNHANDLED: &[&str] = &[
+    "callruntime.callinit",
+    "callruntime.createprivateproperty",
+    "callruntime.defineprivateproperty",
+    "callruntime.ldsendablelocalmodulevar",
+    "callruntime.supercallforwardallargs",
+    "callruntime.topropertykey",
+    "callruntime.wideldlazymodulevar",
+    "callruntime.wideldlazysendablemodulevar",
+    "callruntime.wideldsendableexternalmodulevar",
+    "callruntime.wideldsendablelocalmodulevar",
+    "callruntime.widenewsendableenv",
+    "createasyncgeneratorobj",
+    "creategeneratorobj",
+    "createiterresultobj",
+    "definefieldbyname",
+    "deprecated.asyncfunctionawaituncaught",
+    "deprecated.asyncfunctionreject",
+    "deprecated.asyncfunctionresolve",
+    "deprecated.asyncgeneratorreject",
+    "deprecated.callarg0",
+    "deprecated.callarg1",
+    "deprecated.callargs2",
+    "deprecated.callargs3",
+    "deprecated.callrange",
+    "deprecated.callspread",
+    "deprecated.callthisrange",
+    "deprecated.copydataproperties",
+    "deprecated.createarraywithbuffer",
+    "deprecated.createobjecthavingmethod",
+    "deprecated.createobjectwithbuffer",
+    "deprecated.dec",
+    "deprecated.defineclasswithbuffer",
+    "deprecated.delobjprop",
+    "deprecated.dynamicimport",
+    "deprecated.getiteratornext",
+    "deprecated.getmodulenamespace",
+    "deprecated.getresumemode",
+    "deprecated.gettemplateobject",
+    "deprecated.inc",
+    "deprecated.ldhomeobject",
+    "deprecated.ldlexenv",
+    "deprecated.ldmodulevar",
+    "deprecated.ldobjbyindex",
+    "deprecated.ldobjbyname",
+    "deprecated.ldobjbyvalue",
+    "deprecated.ldsuperbyname",
+    "deprecated.ldsuperbyvalue",
+    "deprecated.neg",
+    "deprecated.not",
+    "deprecated.poplexenv",
+    "deprecated.resumegenerator",
+    "deprecated.setobjectwithproto",
+    "deprecated.stclasstoglobalrecord",
+    "deprecated.stconsttoglobalrecord",
+    "deprecated.stlettoglobalrecord",
+    "deprecated.stlexvar",
+    "deprecated.stmodulevar",
+    "deprecated.suspendgenerator",
+    "deprecated.tonumber",
+    "deprecated.tonumeric",
+    "getasynciterator",
+    "gettemplateobject",
+    "isfalse",
+    "istrue",
+    "ldbigint",
+    "ldprivateproperty",
+    "ldthisbyname",
+    "ldthisbyvalue",
+    "newobjapply",
+    "setgeneratorstate",
+    "setobjectwithproto",
+    "stconsttoglobalrecord",
+    "stownbyvalue",
+    "stprivateproperty",
+    "stsuperbyvalue",
+    "stthisbyname",
+    "stthisbyvalue",
+    "sttoglobalrecord",
+    "testin",
+    "wide.copyrestargs",
+    "wide.ldobjbyindex",
+    "wide.ldpatchvar",
+    "wide.stobjbyindex",
+    "wide.stownbyindex",
+    "wide.stpatchvar",
+];
+
+#[test]
+fn every_opcode_is_handled_or_acknowledged() {
+    let handled = handled_mnemonics();
+    let known: BTreeSet<&str> = KNOWN_UNHANDLED.iter().copied().collect();
+    let unhandled: BTreeSet<&str> = opcode_table()
+        .iter()
+        .map(|info| info.mnemonic)
+        .filter(|mn| !handled.contains(mn))
+        .collect();
+
+    let new: Vec<_> = unhandled.difference(&known).collect();
+    assert!(
+        new.is_empty(),
+        "opcodes without decompiler support: {new:?}\n\
+         handle them in expr_recovery or add them to KNOWN_UNHANDLED"
+    );
+
+    let stale: Vec<_> = known.difference(&unhandled).collect();
+    assert!(
+        stale.is_empty(),
+        "now handled or removed from the ISA, drop from KNOWN_UNHANDLED: {stale:?}"
+    );
+}
@@ -0,0 +1,119 @@
+//! References the decompiler cannot resolve become `Expr::Unknown`
+//! placeholders, never variables.
+
+use abcd_decompiler::expr_recovery::StringResolver;
+use abcd_decompiler::{Budget, SyntheticNames, UnknownOpcodePolicy, decompile_method_stmts};
+use abcd_file::literal::{LiteralArray, LiteralTag, LiteralValue};
+use abcd_ir::expr::Expr;
+use abcd_ir::stmt::Stmt;
+use abcd_ir::visit::{self, ExprVisitor};
+use abcd_isa::{EntityId, Imm, Reg, encode, insn};
+
+/// Names `Point` for every ID; the array at ID 0 holds a method, a nested
+/// array that cannot be read and an `implements` record.
+struct Unresolved;
+
+impl StringResolver for Unresolved {
+    fn resolve_string(&self, _: EntityId, _: EntityId) -> Option<String> {
+        None
+    }
+    fn resolve_offset(&self, _: EntityId, _: EntityId) -> Option<EntityId> {
+        None
+    }
+    fn resolve_method_name(&self, _: EntityId, _: EntityId) -> Option<String> {
+        Some("Point".into())
+    }
+    fn resolve_literal_array(&self, _: EntityId, _: EntityId) -> Option<LiteralArray> {
+        let tag = (LiteralTag::TagValue, LiteralValue::TagValue(0));
+        Some(LiteralArray {
+            entries: vec![
+                tag.clone(),
+                (LiteralTag::Method, LiteralValue::Method(EntityId(0x10))),
+                tag.clone(),
+                (
+                    LiteralTag::LiteralArray,
+                    LiteralValue::LiteralArray(EntityId(0x20)),
+                ),
+                tag,
+                (
+                    LiteralTag::EtsImplements,
+                    LiteralValue::EtsImplements(EntityId(0x30)),
+                ),
+            ],
+        })
+    }
+}
+
+/// Variable names and `Unknown` texts, in visiting order.
+#[derive(Default)]
+struct Leaves {
+    vars: Vec<String>,
+    unknown: Vec<String>,
+}
+
+impl ExprVisitor for Leaves {
+    fn visit_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Var(name) => self.vars.push(name.clone()),
+            Expr::Unknown(text) => self.unknown.push(text.clone()),
+            _ => visit::walk_expr(self, expr),
+        }
+    }
+}
+
+fn leaves(code: &[u8]) -> Leaves {
+    let stmts: Vec<Stmt> = decompile_method_stmts(
+        code,
+        &[],
+        &Unresolved,
+        EntityId(0),
+        1,
+        0,
+        UnknownOpcodePolicy::Comment,
+        SyntheticNames::Positional,
+        Budget::default(),
+    )
+    .unwrap();
+    let mut leaves = Leaves::default();
+    leaves.visit_stmts(&stmts);
+    leaves
+}
+
+#[test]
+fn unresolved_literals_are_placeholders() {
+    let (code, _) = encode(&[
+        insn::Createarraywithbuffer::new(Imm(0), EntityId(0)),
+        insn::Return::new(),
+    ])
+    .unwrap();
+    let found = leaves(&code);
+    assert!(
+        !found.vars.iter().any(|v| v.starts_with("/*")),
+        "{:?}",
+        found.vars
+    );
+    assert_eq!(
+        found.unknown,
+        [
+            "/* method@16 */",
+            "/* literal_array@32 */",
+            "/* implements@48 */"
+        ]
+    );
+}
+
+#[test]
+fn class_definitions_are_placeholders() {
+    let (code, _) = encode(&[
+        insn::Defineclasswithbuffer::new(Imm(0), EntityId(1), EntityId(2), Imm(0), Reg(0)),
+        insn::Return::new(),
+    ])
+    .unwrap();
+    let found = leaves(&code);
+    assert!(
+        !found.vars.iter().any(|v| v.starts_with("/*")),
+        "{:?}",
+        found.vars
+    );
+    assert_eq!(found.unknown, ["/* class */ Point"]);
+}
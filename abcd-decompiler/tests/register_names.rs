@@ -0,0 +1,206 @@
+#![cfg(feature = "structuring")]
+
+use abcd_decompiler::expr_recovery::{LocalVariable, StringResolver};
+use abcd_decompiler::{AnalysisSession, SyntheticNames, decompile_method};
+use abcd_ir::instruction::{CatchBlockInfo, TryBlockInfo};
+use abcd_isa::{EntityId, Imm, Label, Reg, encode, insn};
+
+struct Locals(Vec<LocalVariable>);
+
+impl StringResolver for Locals {
+    fn resolve_string(&self, _: EntityId, _: EntityId) -> Option<String> {
+        None
+    }
+    fn resolve_offset(&self, _: EntityId, _: EntityId) -> Option<EntityId> {
+        None
+    }
+    fn local_variables(&self, _: EntityId) -> Vec<LocalVariable> {
+        self.0.clone()
+    }
+}
+
+/// `v0 = 1; if (!true) v0 = 2; return v0`: both stores reach the return.
+fn code() -> Vec<u8> {
+    let (bytes, _) = encode(&[
+        insn::Ldai::new(Imm(1)),
+        insn::Sta::new(Reg(0)),
+        insn::Ldtrue::new(),
+        insn::Jnez::new(Label(6)),
+        insn::Ldai::new(Imm(2)),
+        insn::Sta::new(Reg(0)),
+        insn::Lda::new(Reg(0)),
+        insn::Return::new(),
+    ])
+    .unwrap();
+    bytes
+}
+
+#[test]
+fn merged_definitions_share_a_fresh_name() {
+    let code = code();
+    let js = decompile_method(&code, &[], &Locals(Vec::new()), EntityId(0), 1, 0);
+    assert!(js.contains("v1 = 2"), "{js}");
+    assert!(js.contains("return v1"), "{js}");
+    assert!(!js.contains("r1"), "{js}");
+}
+
+#[test]
+fn debug_info_names_the_variable() {
+    let code = code();
+    let count = LocalVariable {
+        reg: 0,
+        start_pc: 0,
+        end_pc: code.len() as u32,
+        name: "count".into(),
+    };
+    let js = decompile_method(&code, &[], &Locals(vec![count]), EntityId(0), 1, 0);
+    assert!(js.contains("count = 2"), "{js}");
+    assert!(js.contains("return count"), "{js}");
+    assert!(!js.contains("v1"), "{js}");
+}
+
+#[test]
+fn content_hash_names_survive_moved_code() {
+    let session = AnalysisSession::new().with_synthetic_names(SyntheticNames::ContentHash);
+    let decompile = |code: &[u8]| {
+        session
+            .decompile_method(code, &[], &Locals(Vec::new()), EntityId(0), 1, 0)
+            .unwrap()
+            .unwrap()
+    };
+    let js = decompile(&code());
+    let name = js
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .find(|w| w.starts_with("v_"))
+        .unwrap_or_else(|| panic!("{js}"));
+    assert!(js.contains(&format!("return {name}")), "{js}");
+
+    // The same code behind an extra instruction, every offset shifted.
+    let (shifted, _) = encode(&[
+        insn::Ldundefined::new(),
+        insn::Ldai::new(Imm(1)),
+        insn::Sta::new(Reg(0)),
+        insn::Ldtrue::new(),
+        insn::Jnez::new(Label(7)),
+        insn::Ldai::new(Imm(2)),
+        insn::Sta::new(Reg(0)),
+        insn::Lda::new(Reg(0)),
+        insn::Return::new(),
+    ])
+    .unwrap();
+    assert!(decompile(&shifted).contains(&format!("return {name}")));
+}
+
+/// Every string ID names the global `v1`.
+struct GlobalV1;
+
+impl StringResolver for GlobalV1 {
+    fn resolve_string(&self, _: EntityId, _: EntityId) -> Option<String> {
+        Some("v1".into())
+    }
+    fn resolve_offset(&self, _: EntityId, _: EntityId) -> Option<EntityId> {
+        None
+    }
+}
+
+#[test]
+fn separate_webs_get_separate_names() {
+    // v0 and v1 each merge two stores before `return v0 + v1`.
+    let (code, _) = encode(&[
+        insn::Ldai::new(Imm(1)),
+        insn::Sta::new(Reg(0)),
+        insn::Ldai::new(Imm(3)),
+        insn::Sta::new(Reg(1)),
+        insn::Ldtrue::new(),
+        insn::Jnez::new(Label(10)),
+        insn::Ldai::new(Imm(2)),
+        insn::Sta::new(Reg(0)),
+        insn::Ldai::new(Imm(4)),
+        insn::Sta::new(Reg(1)),
+        insn::Lda::new(Reg(0)),
+        insn::Add2::new(Imm(0), Reg(1)),
+        insn::Return::new(),
+    ])
+    .unwrap();
+    let js = decompile_method(&code, &[], &Locals(Vec::new()), EntityId(0), 2, 0);
+    assert!(js.contains("v1 = 2"), "{js}");
+    assert!(js.contains("v2 = 4"), "{js}");
+    let ret = js.lines().find(|l| l.contains("return")).unwrap();
+    assert!(ret.contains("v1") && ret.contains("v2"), "{js}");
+}
+
+#[test]
+fn made_up_names_avoid_globals() {
+    // `v0 = 1; if (!true) v0 = 2; return v1(v0)`, `v1` a global.
+    let (code, _) = encode(&[
+        insn::Ldai::new(Imm(1)),
+        insn::Sta::new(Reg(0)),
+        insn::Ldtrue::new(),
+        insn::Jnez::new(Label(6)),
+        insn::Ldai::new(Imm(2)),
+        insn::Sta::new(Reg(0)),
+        insn::Tryldglobalbyname::new(Imm(0), EntityId(0)),
+        insn::Callarg1::new(Imm(1), Reg(0)),
+        insn::Return::new(),
+    ])
+    .unwrap();
+    let js = decompile_method(&code, &[], &GlobalV1, EntityId(0), 1, 0);
+    assert!(js.contains("v2 = 2"), "{js}");
+    assert!(js.contains("v1(v2)"), "{js}");
+    assert!(!js.contains("v1 = "), "{js}");
+}
+
+#[test]
+fn reloaded_values_keep_their_name() {
+    // `v0 = 1 or 2; v0 = v0 + 1; v0 = that or 5; return v0`: the
+    // increment stores what it loaded from v0, so one variable throughout.
+    let (code, _) = encode(&[
+        insn::Ldai::new(Imm(1)),
+        insn::Sta::new(Reg(0)),
+        insn::Ldtrue::new(),
+        insn::Jnez::new(Label(6)),
+        insn::Ldai::new(Imm(2)),
+        insn::Sta::new(Reg(0)),
+        insn::Lda::new(Reg(0)),
+        insn::Inc::new(Imm(0)),
+        insn::Sta::new(Reg(0)),
+        insn::Ldtrue::new(),
+        insn::Jnez::new(Label(13)),
+        insn::Ldai::new(Imm(5)),
+        insn::Sta::new(Reg(0)),
+        insn::Lda::new(Reg(0)),
+        insn::Return::new(),
+    ])
+    .unwrap();
+    let js = decompile_method(&code, &[], &Locals(Vec::new()), EntityId(0), 1, 0);
+    assert!(js.contains("return v1"), "{js}");
+    assert!(!js.contains("v2"), "{js}");
+}
+
+#[test]
+fn values_reaching_a_handler_are_named() {
+    // `v0 = 1; try { v0 = 2; throw undefined } catch { return v0 }`
+    let (code, offsets) = encode(&[
+        insn::Ldai::new(Imm(1)),
+        insn::Sta::new(Reg(0)),
+        insn::Ldai::new(Imm(2)),
+        insn::Sta::new(Reg(0)),
+        insn::Ldundefined::new(),
+        insn::Throw::new(),
+        insn::Lda::new(Reg(0)),
+        insn::Return::new(),
+    ])
+    .unwrap();
+    let try_blocks = [TryBlockInfo {
+        start_pc: offsets[2],
+        length: offsets[6] - offsets[2],
+        catch_blocks: vec![CatchBlockInfo {
+            type_idx: 0,
+            handler_pc: offsets[6],
+            code_size: code.len() as u32 - offsets[6],
+        }],
+    }];
+    let js = decompile_method(&code, &try_blocks, &Locals(Vec::new()), EntityId(0), 1, 0);
+    assert!(js.contains("v1 = 2"), "{js}");
+    assert!(js.contains("return v1"), "{js}");
+}
@@ -0,0 +1,132 @@
+//! Temporaries are declared outside the try, catch and loop bodies they
+//! outlive.
+
+#![cfg(feature = "structuring")]
+
+use abcd_decompiler::decompile_method;
+use abcd_decompiler::expr_recovery::StringResolver;
+use abcd_ir::instruction::{CatchBlockInfo, TryBlockInfo};
+use abcd_isa::{Bytecode, EntityId, Imm, Label, Reg, encode, insn};
+
+struct NoNames;
+
+impl StringResolver for NoNames {
+    fn resolve_string(&self, _: EntityId, _: EntityId) -> Option<String> {
+        None
+    }
+    fn resolve_offset(&self, _: EntityId, _: EntityId) -> Option<EntityId> {
+        None
+    }
+}
+
+/// Decompile `insns`, with one try covering `try_range` and handled at
+/// `handler`, both instruction index ranges (the end may be `insns.len()`).
+fn decompile(
+    insns: &[Bytecode],
+    try_range: Option<(usize, usize)>,
+    handler: (usize, usize),
+) -> String {
+    let (code, offsets) = encode(insns).unwrap();
+    let at = |i: usize| offsets.get(i).copied().unwrap_or(code.len() as u32);
+    let try_blocks: Vec<TryBlockInfo> = try_range
+        .map(|(start, end)| TryBlockInfo {
+            start_pc: at(start),
+            length: at(end) - at(start),
+            catch_blocks: vec![CatchBlockInfo {
+                type_idx: 0,
+                handler_pc: at(handler.0),
+                code_size: at(handler.1) - at(handler.0),
+            }],
+        })
+        .into_iter()
+        .collect();
+    decompile_method(&code, &try_blocks, &NoNames, EntityId(0), 1, 0)
+}
+
+/// Where the first `let` is, and where `marker` is.
+fn positions(js: &str, marker: &str) -> (usize, usize) {
+    let decl = js
+        .find("let ")
+        .unwrap_or_else(|| panic!("no declaration:\n{js}"));
+    let at = js
+        .find(marker)
+        .unwrap_or_else(|| panic!("no `{marker}`:\n{js}"));
+    (decl, at)
+}
+
+#[test]
+fn a_temp_read_after_the_try_is_declared_before_it() {
+    // try { if (!true) {}; if (true) v0 = 1 else v0 = 2 } catch { return }
+    // return v0
+    let js = decompile(
+        &[
+            insn::Ldtrue::new(),
+            insn::Jeqz::new(Label(3)),
+            insn::Ldai::new(Imm(5)),
+            insn::Ldtrue::new(),
+            insn::Jnez::new(Label(8)),
+            insn::Ldai::new(Imm(2)),
+            insn::Sta::new(Reg(0)),
+            insn::Jmp::new(Label(12)),
+            insn::Ldai::new(Imm(1)),
+            insn::Sta::new(Reg(0)),
+            insn::Jmp::new(Label(12)),
+            insn::Returnundefined::new(),
+            insn::Lda::new(Reg(0)),
+            insn::Return::new(),
+        ],
+        Some((0, 11)),
+        (11, 12),
+    );
+    let (decl, try_) = positions(&js, "try {");
+    assert!(decl < try_, "{js}");
+}
+
+#[test]
+fn a_temp_read_in_the_catch_is_declared_before_the_try() {
+    // try { if (!true) {}; if (true) v0 = 1 else v0 = 2 } catch { return v0 }
+    let js = decompile(
+        &[
+            insn::Ldtrue::new(),
+            insn::Jeqz::new(Label(3)),
+            insn::Ldai::new(Imm(5)),
+            insn::Ldtrue::new(),
+            insn::Jnez::new(Label(8)),
+            insn::Ldai::new(Imm(2)),
+            insn::Sta::new(Reg(0)),
+            insn::Jmp::new(Label(10)),
+            insn::Ldai::new(Imm(1)),
+            insn::Sta::new(Reg(0)),
+            insn::Returnundefined::new(),
+            insn::Lda::new(Reg(0)),
+            insn::Return::new(),
+        ],
+        Some((0, 10)),
+        (11, 13),
+    );
+    let (decl, try_) = positions(&js, "try {");
+    assert!(decl < try_, "{js}");
+}
+
+#[test]
+fn a_loop_carried_temp_is_declared_before_the_loop() {
+    // while (true) { if (!true) return v0; v0 = 1 }
+    let js = decompile(
+        &[
+            insn::Ldtrue::new(),
+            insn::Jeqz::new(Label(9)),
+            insn::Ldtrue::new(),
+            insn::Jnez::new(Label(6)),
+            insn::Lda::new(Reg(0)),
+            insn::Return::new(),
+            insn::Ldai::new(Imm(1)),
+            insn::Sta::new(Reg(0)),
+            insn::Jmp::new(Label(0)),
+            insn::Returnundefined::new(),
+        ],
+        None,
+        (0, 0),
+    );
+    let (decl, while_) = positions(&js, "while");
+    assert!(decl < while_, "{js}");
+}
@@ -0,0 +1,253 @@
+use std::cell::Cell;
+use std::future::Future;
+use std::pin::pin;
+use std::sync::Arc;
+use std::sync::mpsc;
+use std::task::{Context, Poll, Wake};
+use std::thread::{self, Thread};
+
+use abcd_decompiler::expr_recovery::StringResolver;
+use abcd_decompiler::{
+    AnalysisSession, CancelToken, Cancelled, UnknownOpcodePolicy, decompile_method,
+};
+use abcd_file::builder::Builder;
+use abcd_file::{ACC_PUBLIC, AnalysisObserver, File, TypeId};
+use abcd_isa::{EntityId, Imm, encode, insn};
+
+struct NoNames;
+
+impl StringResolver for NoNames {
+    fn resolve_string(&self, _: EntityId, _: EntityId) -> Option<String> {
+        None
+    }
+    fn resolve_offset(&self, _: EntityId, _: EntityId) -> Option<EntityId> {
+        None
+    }
+}
+
+/// `ldai 1; return`
+fn code() -> Vec<u8> {
+    let (bytes, _) = encode(&[insn::Ldai::new(Imm(1)), insn::Return::new()]).unwrap();
+    bytes
+}
+
+#[test]
+fn uncancelled_session_decompiles_as_usual() {
+    let code = code();
+    let session = AnalysisSession::new();
+    assert_eq!(
+        session.decompile_method(&code, &[], &NoNames, EntityId(0), 0, 0),
+        Ok(Ok(decompile_method(
+            &code,
+            &[],
+            &NoNames,
+            EntityId(0),
+            0,
+            0
+        )))
+    );
+}
+
+#[test]
+fn cancelled_session_refuses_work() {
+    let session = AnalysisSession::new();
+    session.cancel();
+    assert_eq!(
+        session.decompile_method(&code(), &[], &NoNames, EntityId(0), 0, 0),
+        Err(Cancelled)
+    );
+}
+
+/// Cancels `token` at the first string it is asked for, counting the
+/// requests.
+struct CancelAtFirstString {
+    token: CancelToken,
+    requests: Cell<usize>,
+}
+
+impl StringResolver for CancelAtFirstString {
+    fn resolve_string(&self, _: EntityId, _: EntityId) -> Option<String> {
+        self.token.cancel();
+        self.requests.set(self.requests.get() + 1);
+        None
+    }
+    fn resolve_offset(&self, _: EntityId, _: EntityId) -> Option<EntityId> {
+        None
+    }
+}
+
+#[test]
+fn cancelling_partway_stops_the_method() {
+    let mut insns = vec![insn::LdaStr::new(EntityId(0)); 1000];
+    insns.push(insn::Return::new());
+    let (code, _) = encode(&insns).unwrap();
+    let session = AnalysisSession::new();
+    let resolver = CancelAtFirstString {
+        token: session.token().clone(),
+        requests: Cell::new(0),
+    };
+    assert_eq!(
+        session.decompile_method(&code, &[], &resolver, EntityId(0), 0, 0),
+        Err(Cancelled)
+    );
+    // Recovery stopped at the next instruction, not at the end.
+    assert!(resolver.requests.get() < 10, "{}", resolver.requests.get());
+}
+
+#[test]
+fn sessions_take_the_unknown_opcode_policy() {
+    let (code, _) = encode(&[
+        insn::CallruntimeTopropertykey::new(),
+        insn::Returnundefined::new(),
+    ])
+    .unwrap();
+    let session = AnalysisSession::new().with_unknown_opcodes(UnknownOpcodePolicy::Error);
+    let refused = session
+        .decompile_method(&code, &[], &NoNames, EntityId(0), 0, 0)
+        .unwrap()
+        .unwrap_err();
+    assert_eq!(refused.offset, 0);
+}
+
+#[test]
+fn shared_token_cancels_every_session() {
+    let token = CancelToken::new();
+    let a = AnalysisSession::with_token(token.clone());
+    let b = AnalysisSession::with_token(token.clone());
+    token.cancel();
+    assert!(a.is_cancelled() && b.is_cancelled());
+    assert_eq!(a.token().check(), Err(Cancelled));
+}
+
+#[test]
+fn spawned_task_returns_its_result() {
+    let session = AnalysisSession::new();
+    let task = session.spawn(|_| Ok(41 + 1));
+    assert_eq!(task.wait(), Ok(42));
+}
+
+#[test]
+fn spawned_task_sees_cancellation() {
+    let session = AnalysisSession::new();
+    let (tx, rx) = mpsc::channel::<()>();
+    let task = session.spawn(move |s| {
+        // Wait until the test has cancelled.
+        rx.recv().unwrap();
+        s.token().check()?;
+        Ok(())
+    });
+    task.cancel();
+    tx.send(()).unwrap();
+    assert_eq!(task.wait(), Err(Cancelled));
+    assert!(session.is_cancelled());
+}
+
+#[test]
+fn more_tasks_than_threads_all_finish() {
+    let session = AnalysisSession::new();
+    let n = 4 * thread::available_parallelism().map_or(1, |n| n.get());
+    let tasks: Vec<_> = (0..n).map(|i| session.spawn(move |_| Ok(i))).collect();
+    let results: Vec<usize> = tasks.into_iter().map(|t| t.wait().unwrap()).collect();
+    assert_eq!(results, (0..n).collect::<Vec<_>>());
+}
+
+struct Unpark(Thread);
+
+impl Wake for Unpark {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    let waker = Arc::new(Unpark(thread::current())).into();
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(out) => return out,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+#[test]
+fn task_can_be_awaited() {
+    let session = AnalysisSession::new();
+    let task = session.spawn(|_| Ok("done"));
+    assert_eq!(block_on(task), Ok("done"));
+}
+
+#[test]
+#[should_panic(expected = "boom")]
+fn panics_resume_in_the_waiter() {
+    let session = AnalysisSession::new();
+    let task = session.spawn(|_| -> Result<(), Cancelled> { panic!("boom") });
+    let _ = task.wait();
+}
+
+/// Every event, in order.
+#[derive(Default)]
+struct Recorder(Vec<String>);
+
+impl AnalysisObserver for Recorder {
+    fn on_class_start(&mut self, _class: EntityId, name: &str) {
+        self.0.push(format!("class {name}"));
+    }
+
+    fn on_method_done(&mut self, _method: EntityId, output: Option<&str>) {
+        self.0.push(format!("method {}", output.unwrap().trim()));
+    }
+
+    fn on_warning(&mut self, _method: Option<EntityId>, message: &str) {
+        self.0.push(format!("warning {message}"));
+    }
+}
+
+/// `Lmain;` with `f` returning 1, and `g` using an instruction API 9
+/// files cannot have.
+fn file() -> File {
+    let mut b = Builder::new().unwrap();
+    b.set_api(9, "").unwrap();
+    let class = b.add_class("Lmain;").unwrap();
+    let proto = b.create_proto(TypeId::Tagged, &[]);
+    let (newer, _) = encode(&[
+        insn::CallruntimeTopropertykey::new(),
+        insn::Returnundefined::new(),
+    ])
+    .unwrap();
+    b.class_add_method_with_proto(class, "f", proto, ACC_PUBLIC, &code(), 0, 3)
+        .unwrap();
+    b.class_add_method_with_proto(class, "g", proto, ACC_PUBLIC, &newer, 0, 3)
+        .unwrap();
+    File::open(b.finalize().unwrap()).unwrap()
+}
+
+#[test]
+fn whole_files_report_to_the_observer() {
+    let abc = file();
+    let mut recorder = Recorder::default();
+    let methods = AnalysisSession::new()
+        .decompile_file(&abc, &NoNames, &mut recorder)
+        .unwrap();
+
+    assert_eq!(methods.len(), 1);
+    assert!(methods[0].source.contains("return 1"), "{methods:?}");
+    let events = &recorder.0;
+    let start = events.iter().position(|e| e == "class Lmain;").unwrap();
+    assert!(events[start + 1].contains("return 1"), "{events:?}");
+    assert!(
+        events[start + 2].starts_with("warning") && events[start + 2].contains("topropertykey"),
+        "{events:?}"
+    );
+}
+
+#[test]
+fn cancelled_sessions_stop_before_the_file() {
+    let session = AnalysisSession::new();
+    session.cancel();
+    assert_eq!(
+        session.decompile_file(&file(), &NoNames, &mut ()),
+        Err(Cancelled)
+    );
+}
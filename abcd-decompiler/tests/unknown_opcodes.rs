@@ -0,0 +1,82 @@
+use abcd_decompiler::expr_recovery::StringResolver;
+use abcd_decompiler::{Budget, SyntheticNames, UnknownOpcodePolicy, decompile_method_with};
+use abcd_isa::{EntityId, encode, insn};
+
+struct NoNames;
+
+impl StringResolver for NoNames {
+    fn resolve_string(&self, _: EntityId, _: EntityId) -> Option<String> {
+        None
+    }
+    fn resolve_offset(&self, _: EntityId, _: EntityId) -> Option<EntityId> {
+        None
+    }
+}
+
+/// `ldundefined; callruntime.topropertykey; callruntime.topropertykey; returnundefined`
+fn code() -> Vec<u8> {
+    let (bytes, _) = encode(&[
+        insn::Ldundefined::new(),
+        insn::CallruntimeTopropertykey::new(),
+        insn::CallruntimeTopropertykey::new(),
+        insn::Returnundefined::new(),
+    ])
+    .unwrap();
+    bytes
+}
+
+fn decompile(code: &[u8], policy: UnknownOpcodePolicy) -> Result<String, String> {
+    decompile_method_with(
+        code,
+        &[],
+        &NoNames,
+        EntityId(0),
+        0,
+        0,
+        policy,
+        SyntheticNames::Positional,
+        Budget::default(),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Hex of the instruction at `offset`, as the markers print it.
+fn hex_at(code: &[u8], offset: usize, len: usize) -> String {
+    code[offset..offset + len]
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[test]
+fn comment_marker_has_offset_and_bytes() {
+    let code = code();
+    let js = decompile(&code, UnknownOpcodePolicy::Comment).unwrap();
+    let hex = hex_at(&code, 1, 2);
+    assert!(
+        js.contains(&format!("// 0x0001 [{hex}] callruntime.topropertykey")),
+        "{js}"
+    );
+    assert!(
+        js.contains(&format!("// 0x0003 [{hex}] callruntime.topropertykey")),
+        "{js}"
+    );
+}
+
+#[test]
+fn raw_asm_block_merges_consecutive_instructions() {
+    let code = code();
+    let js = decompile(&code, UnknownOpcodePolicy::RawAsmBlock).unwrap();
+    assert_eq!(js.matches("/* asm").count(), 1, "{js}");
+    assert!(js.contains(" * 0x0001: "), "{js}");
+    assert!(js.contains(" * 0x0003: "), "{js}");
+}
+
+#[test]
+fn error_policy_reports_first_unknown() {
+    let code = code();
+    let err = decompile(&code, UnknownOpcodePolicy::Error).unwrap_err();
+    assert!(err.contains("callruntime.topropertykey"), "{err}");
+    assert!(err.contains("0x1"), "{err}");
+}
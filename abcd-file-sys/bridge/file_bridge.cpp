@@ -23,6 +23,8 @@
 
 #include <cstring>
 #include <new>
+#include <string>
+#include <utility>
 #include <vector>
 #include <iostream>
 #include <stdexcept>
@@ -64,6 +66,7 @@ using SourceLang = panda::panda_file::SourceLang;
 using FunctionKind = panda::panda_file::FunctionKind;
 using BaseClassItem = panda::panda_file::BaseClassItem;
 using TypeItem = panda::panda_file::TypeItem;
+using IndexedItem = panda::panda_file::IndexedItem;
 
 /* ========== File method implementations (merged from file_impl.cpp) ========== */
 namespace panda::panda_file {
@@ -264,17 +267,97 @@ struct AbcIndexAccessor {
     AbcIndexAccessor(const File &f, File::EntityId id) : accessor(f, id) {}
 };
 
+/* ========== Error reporting ========== */
+
+namespace {
+
+thread_local std::string g_last_error;
+
+void set_last_error(std::string msg) {
+    g_last_error = std::move(msg);
+}
+
+void clear_last_error() {
+    g_last_error.clear();
+}
+
+// Allocate a bridge wrapper, recording the reason on failure. Accessor
+// constructors parse the entity header and throw FileAccessException
+// (SUPPORT_KNOWN_EXCEPTION) on out-of-range or malformed data.
+template <typename T, typename... Args>
+T *bridge_new(const char *what, Args &&...args) {
+    clear_last_error();
+    try {
+        T *p = new (std::nothrow) T(std::forward<Args>(args)...);
+        if (p == nullptr) {
+            set_last_error(std::string("allocation failed for ") + what);
+        }
+        return p;
+    } catch (const std::exception &e) {
+        set_last_error(std::string(what) + ": " + e.what());
+        return nullptr;
+    }
+}
+
+}  // namespace
+
 extern "C" {
 
+/* ========== Last error ========== */
+
+const char *abc_last_error(void) {
+    return g_last_error.empty() ? nullptr : g_last_error.c_str();
+}
+
+/* ========== Capabilities ========== */
+
+uint32_t abc_capabilities(void) {
+    uint32_t caps = 0;
+#ifndef ABC_BRIDGE_NO_BUILDER
+    caps |= ABC_CAP_BUILDER;
+#endif
+#ifndef ABC_BRIDGE_NO_DEBUG_INFO
+    caps |= ABC_CAP_DEBUG_INFO;
+#endif
+#ifndef ABC_BRIDGE_NO_MODULE
+    caps |= ABC_CAP_MODULE;
+#endif
+    return caps;
+}
+
 /* ========== File handle ========== */
 
 AbcFileHandle *abc_file_open(const uint8_t *data, size_t len) {
-    if (!data || len < sizeof(File::Header)) return nullptr;
+    clear_last_error();
+    if (!data) {
+        set_last_error("null data pointer");
+        return nullptr;
+    }
+    if (len < sizeof(File::Header)) {
+        set_last_error("file too small: " + std::to_string(len) + " bytes, header needs " +
+                       std::to_string(sizeof(File::Header)));
+        return nullptr;
+    }
+    if (std::memcmp(data, File::MAGIC.data(), File::MAGIC_SIZE) != 0) {
+        set_last_error("invalid magic (expected \"PANDA\\0\\0\\0\")");
+        return nullptr;
+    }
+    auto *header = reinterpret_cast<const File::Header *>(data);
+    if (!panda::panda_file::IsVersionLessOrEqual(panda::panda_file::minVersion, header->version)) {
+        const auto &v = header->version;
+        set_last_error("unsupported version " + std::to_string(v[0]) + "." + std::to_string(v[1]) + "." +
+                       std::to_string(v[2]) + "." + std::to_string(v[3]) + " (older than minimum " +
+                       panda::panda_file::GetVersion(panda::panda_file::minVersion) + ")");
+        return nullptr;
+    }
     auto *bytes = reinterpret_cast<std::byte *>(const_cast<uint8_t *>(data));
     panda::os::mem::ConstBytePtr ptr(bytes, len, nullptr);
     auto file = File::OpenFromMemory(std::move(ptr));
-    if (!file) return nullptr;
-    return new (std::nothrow) AbcFileHandle(std::move(file));
+    if (!file) {
+        set_last_error("allocation failed for panda file");
+        return nullptr;
+    }
+    return bridge_new<AbcFileHandle>("file handle", std::move(file));
 }
 
 void abc_file_close(AbcFileHandle *f) {
@@ -477,7 +560,7 @@ int abc_contains_literal_array_in_header(const uint8_t ver[4]) {
 /* ========== Proto Data Accessor ========== */
 
 AbcProtoAccessor *abc_proto_open(const AbcFileHandle *f, uint32_t proto_off) {
-    return new (std::nothrow) AbcProtoAccessor(*f->file, File::EntityId(proto_off));
+    return bridge_new<AbcProtoAccessor>("proto", *f->file, File::EntityId(proto_off));
 }
 
 void abc_proto_close(AbcProtoAccessor *a) {
@@ -531,7 +614,7 @@ uint32_t abc_proto_get_proto_id(const AbcProtoAccessor *a) {
 /* ========== Class Data Accessor ========== */
 
 AbcClassAccessor *abc_class_open(const AbcFileHandle *f, uint32_t offset) {
-    return new (std::nothrow) AbcClassAccessor(*f->file, File::EntityId(offset));
+    return bridge_new<AbcClassAccessor>("class", *f->file, File::EntityId(offset));
 }
 
 void abc_class_close(AbcClassAccessor *a) {
@@ -652,7 +735,7 @@ size_t abc_class_get_name(const AbcClassAccessor *a, char *buf, size_t buf_len)
 /* ========== Method Data Accessor ========== */
 
 AbcMethodAccessor *abc_method_open(const AbcFileHandle *f, uint32_t offset) {
-    return new (std::nothrow) AbcMethodAccessor(*f->file, File::EntityId(offset));
+    return bridge_new<AbcMethodAccessor>("method", *f->file, File::EntityId(offset));
 }
 
 void abc_method_close(AbcMethodAccessor *a) {
@@ -821,7 +904,7 @@ size_t abc_method_get_name_static(const AbcFileHandle *f, uint32_t method_off,
 /* ========== Code Data Accessor ========== */
 
 AbcCodeAccessor *abc_code_open(const AbcFileHandle *f, uint32_t offset) {
-    return new (std::nothrow) AbcCodeAccessor(*f->file, File::EntityId(offset));
+    return bridge_new<AbcCodeAccessor>("code", *f->file, File::EntityId(offset));
 }
 
 void abc_code_close(AbcCodeAccessor *a) {
@@ -890,7 +973,7 @@ const uint8_t *abc_code_get_instructions_static(const AbcFileHandle *f, uint32_t
 /* ========== Field Data Accessor ========== */
 
 AbcFieldAccessor *abc_field_open(const AbcFileHandle *f, uint32_t offset) {
-    return new (std::nothrow) AbcFieldAccessor(*f->file, File::EntityId(offset));
+    return bridge_new<AbcFieldAccessor>("field", *f->file, File::EntityId(offset));
 }
 
 void abc_field_close(AbcFieldAccessor *a) {
@@ -1004,7 +1087,7 @@ uint32_t abc_field_get_type_static(const AbcFileHandle *f, uint32_t field_off) {
 /* ========== Literal Data Accessor ========== */
 
 AbcLiteralAccessor *abc_literal_open(const AbcFileHandle *f, uint32_t literal_data_off) {
-    return new (std::nothrow) AbcLiteralAccessor(*f->file, File::EntityId(literal_data_off));
+    return bridge_new<AbcLiteralAccessor>("literal", *f->file, File::EntityId(literal_data_off));
 }
 
 void abc_literal_close(AbcLiteralAccessor *a) {
@@ -1081,10 +1164,12 @@ uint32_t abc_literal_get_data_id(const AbcLiteralAccessor *a) {
     return a->accessor.GetLiteralDataId().GetOffset();
 }
 
+#ifndef ABC_BRIDGE_NO_MODULE
+
 /* ========== Module Data Accessor ========== */
 
 AbcModuleAccessor *abc_module_open(const AbcFileHandle *f, uint32_t offset) {
-    return new (std::nothrow) AbcModuleAccessor(*f->file, File::EntityId(offset));
+    return bridge_new<AbcModuleAccessor>("module", *f->file, File::EntityId(offset));
 }
 
 void abc_module_close(AbcModuleAccessor *a) {
@@ -1114,10 +1199,12 @@ uint32_t abc_module_get_data_id(const AbcModuleAccessor *a) {
     return a->accessor.GetModuleDataId().GetOffset();
 }
 
+#endif /* ABC_BRIDGE_NO_MODULE */
+
 /* ========== Annotation Data Accessor ========== */
 
 AbcAnnotationAccessor *abc_annotation_open(const AbcFileHandle *f, uint32_t offset) {
-    return new (std::nothrow) AbcAnnotationAccessor(*f->file, File::EntityId(offset));
+    return bridge_new<AbcAnnotationAccessor>("annotation", *f->file, File::EntityId(offset));
 }
 
 void abc_annotation_close(AbcAnnotationAccessor *a) {
@@ -1161,10 +1248,12 @@ uint32_t abc_annotation_get_annotation_id(const AbcAnnotationAccessor *a) {
     return a->accessor.GetAnnotationId().GetOffset();
 }
 
+#ifndef ABC_BRIDGE_NO_DEBUG_INFO
+
 /* ========== Debug Info Extractor ========== */
 
 AbcDebugInfo *abc_debug_info_open(const AbcFileHandle *f) {
-    return new (std::nothrow) AbcDebugInfo(f->file.get());
+    return bridge_new<AbcDebugInfo>("debug info", f->file.get());
 }
 
 void abc_debug_info_close(AbcDebugInfo *d) {
@@ -1234,10 +1323,12 @@ void abc_debug_get_method_list(const AbcDebugInfo *d, AbcEntityIdCb cb, void *ct
     }
 }
 
+#endif /* ABC_BRIDGE_NO_DEBUG_INFO */
+
 /* ========== Index Accessor ========== */
 
 AbcIndexAccessor *abc_index_open(const AbcFileHandle *f, uint32_t method_off) {
-    return new (std::nothrow) AbcIndexAccessor(*f->file, File::EntityId(method_off));
+    return bridge_new<AbcIndexAccessor>("index", *f->file, File::EntityId(method_off));
 }
 
 void abc_index_close(AbcIndexAccessor *a) {
@@ -1260,6 +1351,8 @@ uint32_t abc_index_get_num_headers(const AbcIndexAccessor *a) {
     return a->accessor.GetNumHeaders();
 }
 
+#ifndef ABC_BRIDGE_NO_BUILDER
+
 /* ========== ABC Builder ========== */
 
 struct AbcBuilder {
@@ -1295,7 +1388,7 @@ struct AbcBuilder {
 };
 
 AbcBuilder *abc_builder_new(void) {
-    return new (std::nothrow) AbcBuilder();
+    return bridge_new<AbcBuilder>("builder");
 }
 
 void abc_builder_free(AbcBuilder *b) {
@@ -1570,6 +1663,15 @@ void abc_builder_field_set_value_f64(AbcBuilder *b, uint32_t field_handle, doubl
     b->fields[field_handle]->SetValue(val);
 }
 
+void abc_builder_field_set_value_literal_array(AbcBuilder *b, uint32_t field_handle,
+                                               uint32_t lit_handle) {
+    if (field_handle >= b->fields.size()) return;
+    if (lit_handle >= b->literal_arrays.size()) return;
+    auto *lit = static_cast<panda::panda_file::BaseItem *>(b->literal_arrays[lit_handle]);
+    auto *val = b->container.CreateItem<ScalarValueItem>(lit);
+    b->fields[field_handle]->SetValue(val);
+}
+
 /* --- 3.5 Try-Catch blocks --- */
 
 uint32_t abc_builder_create_code(AbcBuilder *b, uint32_t num_vregs, uint32_t num_args,
@@ -1611,6 +1713,50 @@ void abc_builder_method_set_code(AbcBuilder *b, uint32_t method_handle, uint32_t
     b->methods[method_handle]->SetCode(b->code_items[code_handle]);
 }
 
+void abc_builder_code_set_instructions(AbcBuilder *b, uint32_t code_handle,
+                                       const uint8_t *instructions, uint32_t code_size) {
+    if (code_handle >= b->code_items.size()) return;
+    auto *insns = b->code_items[code_handle]->GetInstructions();
+    if (instructions && code_size > 0) {
+        insns->assign(instructions, instructions + code_size);
+    } else {
+        insns->clear();
+    }
+}
+
+/* --- 3.5b Index dependencies --- */
+
+static IndexedItem *resolve_index_dep(AbcBuilder *b, uint8_t kind, uint32_t handle) {
+    switch (kind) {
+        case ABC_INDEX_DEP_STRING:
+            return handle < b->strings.size() ? b->strings[handle] : nullptr;
+        case ABC_INDEX_DEP_METHOD:
+            return handle < b->methods.size() ? b->methods[handle] : nullptr;
+        case ABC_INDEX_DEP_LITERAL_ARRAY:
+            return handle < b->literal_arrays.size() ? b->literal_arrays[handle] : nullptr;
+        default:
+            return nullptr;
+    }
+}
+
+void abc_builder_method_add_index_dependency(AbcBuilder *b, uint32_t method_handle,
+                                             uint8_t kind, uint32_t handle) {
+    if (method_handle >= b->methods.size()) return;
+    auto *dep = resolve_index_dep(b, kind, handle);
+    if (!dep) return;
+    b->methods[method_handle]->AddIndexDependency(dep);
+}
+
+uint32_t abc_builder_method_get_index(AbcBuilder *b, uint32_t method_handle,
+                                      uint8_t kind, uint32_t handle) {
+    if (method_handle >= b->methods.size()) return UINT32_MAX;
+    auto *method = b->methods[method_handle];
+    auto *dep = resolve_index_dep(b, kind, handle);
+    // Order indexes are assigned by the layout pass in finalize.
+    if (!dep || !method->HasOrderIndex() || !dep->HasIndex(method)) return UINT32_MAX;
+    return dep->GetIndex(method);
+}
+
 /* --- 3.6 Debug Info --- */
 
 uint32_t abc_builder_create_lnp(AbcBuilder *b) {
@@ -1694,6 +1840,80 @@ void abc_builder_debug_add_param(AbcBuilder *b, uint32_t debug_handle, uint32_t
     b->debug_infos[debug_handle]->AddParameter(b->strings[name_string_handle]);
 }
 
+/* Debug info copied byte for byte from another file. Everything up to the
+ * line number program index is written as given; the index is the one this
+ * file's layout assigns. */
+class RawDebugInfoItem : public DebugInfoItem {
+public:
+    RawDebugInfoItem(LineNumberProgramItem *program, std::vector<uint8_t> head)
+        : DebugInfoItem(program), head_(std::move(head)) {}
+
+    size_t CalculateSize() const override {
+        return head_.size() +
+               panda::leb128::UnsignedEncodingSize(GetLineNumberProgram()->GetIndex(this));
+    }
+
+    bool Write(panda::panda_file::Writer *writer) override {
+        return writer->WriteBytes(head_) &&
+               writer->WriteUleb128(GetLineNumberProgram()->GetIndex(this));
+    }
+
+private:
+    std::vector<uint8_t> head_;
+};
+
+static bool read_uleb128(const uint8_t *data, uint32_t len, uint32_t *pos, uint32_t *out) {
+    uint32_t value = 0;
+    for (uint32_t shift = 0; shift < 35; shift += 7) {
+        if (*pos >= len) return false;
+        uint8_t byte = data[(*pos)++];
+        value |= static_cast<uint32_t>(byte & 0x7f) << shift;
+        if ((byte & 0x80) == 0) {
+            *out = value;
+            return true;
+        }
+    }
+    return false;
+}
+
+/* Length of a debug info item before its program index, or 0 if `data` is
+ * not exactly one item. */
+static uint32_t raw_debug_head_len(const uint8_t *data, uint32_t len) {
+    uint32_t pos = 0;
+    uint32_t value = 0;
+    if (!read_uleb128(data, len, &pos, &value)) return 0;  // line_start
+    uint32_t num_params = 0;
+    if (!read_uleb128(data, len, &pos, &num_params)) return 0;
+    for (uint32_t i = 0; i < num_params; i++) {
+        if (!read_uleb128(data, len, &pos, &value)) return 0;
+    }
+    uint32_t pool_size = 0;
+    if (!read_uleb128(data, len, &pos, &pool_size)) return 0;
+    if (pool_size > len - pos) return 0;
+    pos += pool_size;
+    uint32_t head_len = pos;
+    if (!read_uleb128(data, len, &pos, &value) || pos != len) return 0;
+    return head_len;
+}
+
+uint32_t abc_builder_method_set_raw_debug_info(AbcBuilder *b, uint32_t method_handle,
+                                               const uint8_t *lnp, uint32_t lnp_len,
+                                               const uint8_t *debug, uint32_t debug_len) {
+    if (method_handle >= b->methods.size()) return UINT32_MAX;
+    if (!lnp || lnp_len == 0 || !debug) return UINT32_MAX;
+    uint32_t head_len = raw_debug_head_len(debug, debug_len);
+    if (head_len == 0) return UINT32_MAX;
+    auto *program = b->container.CreateLineNumberProgramItem();
+    program->SetData(std::vector<uint8_t>(lnp, lnp + lnp_len));
+    auto *item = b->container.CreateItem<RawDebugInfoItem>(
+        program, std::vector<uint8_t>(debug, debug + head_len));
+    b->lnps.push_back(program);
+    uint32_t idx = static_cast<uint32_t>(b->debug_infos.size());
+    b->debug_infos.push_back(item);
+    b->methods[method_handle]->SetDebugInfo(item);
+    return idx;
+}
+
 /* --- 3.7 Annotations --- */
 
 uint32_t abc_builder_create_annotation(AbcBuilder *b, uint32_t class_handle,
@@ -1719,6 +1939,18 @@ uint32_t abc_builder_create_annotation(AbcBuilder *b, uint32_t class_handle,
     return idx;
 }
 
+int abc_builder_annotation_set_scalar(AbcBuilder *b, uint32_t ann_handle, uint32_t index,
+                                      uint32_t value) {
+    if (ann_handle >= b->annotations.size()) return 0;
+    auto *elems = b->annotations[ann_handle]->GetElements();
+    if (index >= elems->size()) return 0;
+    auto &elem = (*elems)[index];
+    // A fresh Elem, so the new value is marked as stored inline like the old.
+    auto *val = b->container.CreateItem<ScalarValueItem>(value);
+    elem = AnnotationItem::Elem(const_cast<StringItem *>(elem.GetName()), val);
+    return 1;
+}
+
 uint32_t abc_builder_create_annotation_ex(AbcBuilder *b, uint32_t class_handle,
     const struct AbcAnnotationElemDefEx *elements, uint32_t num_elements) {
     auto *cls = b->ResolveClassHandle(class_handle);
@@ -1867,6 +2099,7 @@ void abc_builder_deduplicate_annotations(AbcBuilder *b) {
 }
 
 const uint8_t *abc_builder_finalize(AbcBuilder *b, uint32_t *out_len) {
+    clear_last_error();
     try {
         // Flush staged literal items to their LiteralArrayItems
         for (size_t i = 0; i < b->literal_items_staging.size(); i++) {
@@ -1877,14 +2110,21 @@ const uint8_t *abc_builder_finalize(AbcBuilder *b, uint32_t *out_len) {
         b->container.ComputeLayout();
         MemoryWriter writer;
         if (!b->container.Write(&writer)) {
+            set_last_error("ItemContainer::Write failed");
             return nullptr;
         }
         b->output = writer.GetData();
         *out_len = static_cast<uint32_t>(b->output.size());
         return b->output.data();
+    } catch (const std::exception &e) {
+        set_last_error(std::string("finalize: ") + e.what());
+        return nullptr;
     } catch (...) {
+        set_last_error("finalize: unknown exception");
         return nullptr;
     }
 }
 
+#endif /* ABC_BRIDGE_NO_BUILDER */
+
 } /* extern "C" */
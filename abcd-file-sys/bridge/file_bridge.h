@@ -132,6 +132,26 @@ extern "C" {
 typedef int (*AbcAnnotationCb)(uint32_t annotation_off, void *ctx);
 typedef int (*AbcEntityIdCb)(uint32_t entity_off, void *ctx);
 
+/* ========== Error reporting ========== */
+
+/* Reason for the most recent failed call on this thread, or NULL.
+ * Set by the *_open functions, abc_builder_new and abc_builder_finalize;
+ * cleared when one of them is called again. Valid until the next such call. */
+const char *abc_last_error(void);
+
+/* ========== Capabilities ========== */
+
+/* Optional parts of the bridge. Compiling with ABC_BRIDGE_NO_BUILDER,
+ * ABC_BRIDGE_NO_DEBUG_INFO or ABC_BRIDGE_NO_MODULE leaves the matching part
+ * out, declarations included, so the upstream code only it uses is not
+ * linked either. */
+#define ABC_CAP_BUILDER    0x1
+#define ABC_CAP_DEBUG_INFO 0x2
+#define ABC_CAP_MODULE     0x4
+
+/* ABC_CAP_* bits of the parts this build has. */
+uint32_t abc_capabilities(void);
+
 /* ========== File handle ========== */
 
 typedef struct AbcFileHandle AbcFileHandle;
@@ -459,6 +479,8 @@ uint32_t abc_literal_resolve_index(const AbcLiteralAccessor *a, uint32_t entity_
 /* Literal data entity ID */
 uint32_t abc_literal_get_data_id(const AbcLiteralAccessor *a);
 
+#ifndef ABC_BRIDGE_NO_MODULE
+
 /* ========== Module Data Accessor ========== */
 
 typedef struct AbcModuleAccessor AbcModuleAccessor;
@@ -480,6 +502,8 @@ void abc_module_enumerate_records(AbcModuleAccessor *a, AbcModuleRecordCb cb, vo
 /* Module data entity ID */
 uint32_t abc_module_get_data_id(const AbcModuleAccessor *a);
 
+#endif /* ABC_BRIDGE_NO_MODULE */
+
 /* ========== Annotation Data Accessor ========== */
 
 typedef struct AbcAnnotationAccessor AbcAnnotationAccessor;
@@ -510,6 +534,8 @@ int abc_annotation_get_array_element(const AbcAnnotationAccessor *a, uint32_t id
 /* Annotation entity ID */
 uint32_t abc_annotation_get_annotation_id(const AbcAnnotationAccessor *a);
 
+#ifndef ABC_BRIDGE_NO_DEBUG_INFO
+
 /* ========== Debug Info Extractor ========== */
 
 typedef struct AbcDebugInfo AbcDebugInfo;
@@ -564,6 +590,8 @@ void abc_debug_get_parameter_info(const AbcDebugInfo *d, uint32_t method_off,
 /* List of all methods with debug info */
 void abc_debug_get_method_list(const AbcDebugInfo *d, AbcEntityIdCb cb, void *ctx);
 
+#endif /* ABC_BRIDGE_NO_DEBUG_INFO */
+
 /* ========== Index Accessor ========== */
 
 typedef struct AbcIndexAccessor AbcIndexAccessor;
@@ -577,6 +605,8 @@ uint8_t abc_index_get_function_kind(const AbcIndexAccessor *a);
 uint16_t abc_index_get_header_index(const AbcIndexAccessor *a);
 uint32_t abc_index_get_num_headers(const AbcIndexAccessor *a);
 
+#ifndef ABC_BRIDGE_NO_BUILDER
+
 /* ========== ABC Builder (ItemContainer + MemoryWriter) ========== */
 
 typedef struct AbcBuilder AbcBuilder;
@@ -657,6 +687,9 @@ void abc_builder_field_set_value_i32(AbcBuilder *b, uint32_t field_handle, int32
 void abc_builder_field_set_value_i64(AbcBuilder *b, uint32_t field_handle, int64_t value);
 void abc_builder_field_set_value_f32(AbcBuilder *b, uint32_t field_handle, float value);
 void abc_builder_field_set_value_f64(AbcBuilder *b, uint32_t field_handle, double value);
+/* Offset of a literal array, as es2abc stores in `moduleRecordIdx` */
+void abc_builder_field_set_value_literal_array(AbcBuilder *b, uint32_t field_handle,
+                                               uint32_t lit_handle);
 
 /* --- Try-Catch blocks --- */
 struct AbcCatchBlockDef {
@@ -670,6 +703,24 @@ void abc_builder_code_add_try_block(AbcBuilder *b, uint32_t code_handle,
     uint32_t start_pc, uint32_t length,
     const struct AbcCatchBlockDef *catches, uint32_t num_catches);
 void abc_builder_method_set_code(AbcBuilder *b, uint32_t method_handle, uint32_t code_handle);
+/* Replace a code item's instructions. Keep the size unchanged between two
+ * finalize calls, or indexes read back in between no longer apply. */
+void abc_builder_code_set_instructions(AbcBuilder *b, uint32_t code_handle,
+                                       const uint8_t *instructions, uint32_t code_size);
+
+/* --- Index dependencies ---
+ * Instructions name strings, methods and literal arrays by a 16-bit index
+ * into the index of the method's region. Register every entity a method's
+ * code refers to, finalize, read the indexes back, patch the code with
+ * abc_builder_code_set_instructions and finalize again. */
+#define ABC_INDEX_DEP_STRING        0
+#define ABC_INDEX_DEP_METHOD        1
+#define ABC_INDEX_DEP_LITERAL_ARRAY 2
+void abc_builder_method_add_index_dependency(AbcBuilder *b, uint32_t method_handle,
+                                             uint8_t kind, uint32_t handle);
+/* Index assigned by the last finalize, or UINT32_MAX if there is none */
+uint32_t abc_builder_method_get_index(AbcBuilder *b, uint32_t method_handle,
+                                      uint8_t kind, uint32_t handle);
 
 /* --- Debug Info --- */
 uint32_t abc_builder_create_lnp(AbcBuilder *b);
@@ -689,6 +740,15 @@ void abc_builder_lnp_emit_set_source_code(AbcBuilder *b, uint32_t lnp_handle,
                                            uint32_t debug_handle, uint32_t source_code_handle);
 uint32_t abc_builder_create_debug_info(AbcBuilder *b, uint32_t lnp_handle, uint32_t line_number);
 void abc_builder_debug_add_param(AbcBuilder *b, uint32_t debug_handle, uint32_t name_string_handle);
+/* Give a method debug info copied verbatim from another file: `lnp` is its
+ * line number program through END_SEQUENCE, `debug` its debug info item
+ * through the program index, which is replaced by the index this file
+ * assigns. String offsets inside both are kept as they are, so they stay
+ * valid only if strings keep their offsets. Returns the debug handle, or
+ * UINT32_MAX if `debug` is not exactly one debug info item. */
+uint32_t abc_builder_method_set_raw_debug_info(AbcBuilder *b, uint32_t method_handle,
+                                               const uint8_t *lnp, uint32_t lnp_len,
+                                               const uint8_t *debug, uint32_t debug_len);
 
 /* --- Annotations --- */
 struct AbcAnnotationElemDef {
@@ -698,6 +758,10 @@ struct AbcAnnotationElemDef {
 };
 uint32_t abc_builder_create_annotation(AbcBuilder *b, uint32_t class_handle,
     const struct AbcAnnotationElemDef *elements, uint32_t num_elements);
+/* Replace the value of scalar element `index` of an annotation made by
+ * abc_builder_create_annotation. Returns 0 if there is no such element. */
+int abc_builder_annotation_set_scalar(AbcBuilder *b, uint32_t ann_handle, uint32_t index,
+                                      uint32_t value);
 
 /* Extended annotation element definition supporting array values */
 struct AbcAnnotationElemDefEx {
@@ -734,6 +798,8 @@ void abc_builder_deduplicate(AbcBuilder *b);
 void abc_builder_deduplicate_code_and_debug_info(AbcBuilder *b);
 void abc_builder_deduplicate_annotations(AbcBuilder *b);
 
+#endif /* ABC_BRIDGE_NO_BUILDER */
+
 #ifdef __cplusplus
 }
 #endif
@@ -88,6 +88,21 @@ fn main() {
         build.flag("-include").flag(&fixups);
     }
 
+    // Optional bridge parts left out by disabled features; bindgen sees the
+    // same macros below, so their functions are not declared either.
+    let omitted: Vec<String> = [
+        ("BUILDER", "ABC_BRIDGE_NO_BUILDER"),
+        ("DEBUG_INFO", "ABC_BRIDGE_NO_DEBUG_INFO"),
+        ("MODULE", "ABC_BRIDGE_NO_MODULE"),
+    ]
+    .iter()
+    .filter(|(feature, _)| env::var(format!("CARGO_FEATURE_{feature}")).is_err())
+    .map(|(_, define)| define.to_string())
+    .collect();
+    for define in &omitted {
+        build.define(define, None);
+    }
+
     // Coverage: instrument C++ when running under cargo-llvm-cov
     if env::var("CARGO_LLVM_COV").is_ok() {
         build
@@ -111,6 +126,7 @@ fn main() {
         .allowlist_function("abc_.*")
         .allowlist_type("Abc.*")
         .allowlist_var("ABC_.*")
+        .clang_args(omitted.iter().map(|define| format!("-D{define}")))
         .generate()
         .expect("bindgen failed");
 
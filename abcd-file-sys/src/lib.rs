@@ -5,13 +5,30 @@
     dead_code
 )]
 
+// libpandafile maps `.abc` structures straight onto the file bytes and reads
+// multi-byte fields in host order. On a big-endian host every offset and
+// count would come out byte-swapped, so refuse to build rather than parse
+// files wrongly.
+#[cfg(target_endian = "big")]
+compile_error!(
+    "abcd-file-sys reads .abc files in host byte order and supports little-endian targets only"
+);
+
 include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
 
+/// `ABC_CAP_*` bits of the optional bridge parts this build has, one per
+/// crate feature: `builder`, `debug-info` and `module`.
+pub fn capabilities() -> u32 {
+    // SAFETY: pure query, no preconditions.
+    unsafe { abc_capabilities() }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
+    #[cfg(feature = "builder")]
     fn builder_roundtrip() {
         unsafe {
             let b = abc_builder_new();
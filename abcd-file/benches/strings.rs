@@ -0,0 +1,71 @@
+//! String reads: `get_string`, which copies UTF-8 straight out of the file
+//! buffer, against the C++ reader it falls back to.
+//!
+//! Run with `cargo bench -p abcd-file --bench strings`. It only reports
+//! the timings: they vary too much between runs, and CI machines, to fail
+//! on. CI runs it so that it keeps building and running.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use abcd_file::{EntityId, File};
+use abcd_testgen::files::{GlobalClass, RETURN_UNDEFINED};
+
+const METHODS: usize = 5_000;
+const ROUNDS: usize = 20;
+
+/// A file whose method names are a mix of ASCII and CJK identifiers.
+fn fixture() -> File {
+    let mut global = GlobalClass::new();
+    for i in 0..METHODS {
+        let name = if i % 4 == 0 {
+            format!("处理事件_{i}")
+        } else {
+            format!("onRenderComponent{i}")
+        };
+        global.method(&name, &RETURN_UNDEFINED);
+    }
+    global.open()
+}
+
+fn time(name: &str, f: impl Fn() -> usize) -> Duration {
+    // One untimed pass to warm caches and the allocator.
+    black_box(f());
+    let start = Instant::now();
+    let bytes = black_box(f());
+    let elapsed = start.elapsed();
+    println!(
+        "{name:>16}: {:>8.1} ns/string ({bytes} bytes)",
+        elapsed.as_nanos() as f64 / (METHODS * ROUNDS) as f64
+    );
+    elapsed
+}
+
+fn main() {
+    let abc = fixture();
+    let class = abc.class_id_by_name("L_GLOBAL;").unwrap().unwrap();
+    let names: Vec<EntityId> = abc
+        .class(class)
+        .unwrap()
+        .method_offsets()
+        .into_iter()
+        .map(|m| abc.method_name_off(m))
+        .collect();
+
+    let read = |get: &dyn Fn(EntityId) -> String| {
+        (0..ROUNDS)
+            .flat_map(|_| &names)
+            .map(|&off| black_box(get(off)).len())
+            .sum()
+    };
+    let bridge = time("get_string_ffi", || {
+        read(&|off| abc.get_string_ffi(off).unwrap())
+    });
+    let fast = time("get_string", || read(&|off| abc.get_string(off).unwrap()));
+
+    println!(
+        "{:>16}: {:.2}x",
+        "speedup",
+        bridge.as_secs_f64() / fast.as_secs_f64()
+    );
+}
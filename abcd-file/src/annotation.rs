@@ -179,7 +179,7 @@ impl<'f> Annotation<'f> {
     pub(crate) fn open(file: &'f File, offset: EntityId) -> Result<Self, Error> {
         let handle = unsafe { abcd_file_sys::abc_annotation_open(file.handle(), offset.0) };
         if handle.is_null() {
-            return Err(Error::Ffi(format!(
+            return Err(crate::ffi_error(format!(
                 "abc_annotation_open failed at offset {offset:?}"
             )));
         }
@@ -1,8 +1,155 @@
 //! ABC file builder (writer).
+//!
+//! Handles returned by [`Builder`] name entities that exist only until the
+//! builder is dropped; [`Builder::finalize`] lays everything out and
+//! returns the bytes, which [`File::open`](crate::File::open) reads back.
+//!
+//! # Example
+//!
+//! A complete file: a record class with two methods, one naming a literal
+//! array and carrying a line table, the other assembled with an
+//! [`Emitter`](abcd_isa::Emitter) around a try block, and the class's module
+//! record. Then the file is reopened and each piece checked.
+//!
+//! ```
+//! use abcd_file::builder::{Builder, CatchBlockDef, IndexDep};
+//! use abcd_file::literal::LiteralTag;
+//! use abcd_file::{ACC_PUBLIC, EntityId, File, ModuleTag, TypeId};
+//! use abcd_isa::{Emitter, Imm, Reg, decode, encode, insn};
+//!
+//! let mut b = Builder::new()?;
+//! b.set_api(12, "")?;
+//! let proto = b.create_proto(TypeId::Tagged, &[]);
+//!
+//! // A record class, as es2abc emits for `main.js`.
+//! let class = b.add_class("Lmain;")?;
+//! let source_file = b.add_string("main.js")?;
+//! b.class_set_source_file(class, source_file);
+//!
+//! // `function primes() { return [2, 3]; }`: the array comes from a tagged
+//! // literal array, which the code names through the method's index region.
+//! let primes = b.class_add_method_with_proto(class, "primes", proto, ACC_PUBLIC, &[], 0, 0)?;
+//! let array = b.add_literal_array("primes")?;
+//! for value in [2, 3] {
+//!     b.literal_array_add_u8(array, LiteralTag::Integer as u8);
+//!     b.literal_array_add_u32(array, value);
+//! }
+//! b.method_add_index_dependency(primes, IndexDep::LiteralArray(array));
+//! // Indexes are assigned at layout, so encode with a placeholder for now.
+//! let primes_body = |id: u16| {
+//!     encode(&[
+//!         insn::Createarraywithbuffer::new(Imm(0), EntityId(id.into())),
+//!         insn::Return::new(),
+//!     ])
+//!     .map(|(bytes, _)| bytes)
+//! };
+//! let primes_code = b.create_code(0, 3, &primes_body(0)?);
+//! b.method_set_code(primes, primes_code);
+//!
+//! // Line 2 for the whole body.
+//! let lnp = b.create_lnp();
+//! let debug = b.create_debug_info(lnp, 2);
+//! b.lnp_emit_end(lnp);
+//! b.method_set_debug_info(primes, debug);
+//!
+//! // `function main() { try { return 1; } catch { return 0; } }`, assembled
+//! // with labels; the try block is given in byte offsets from `build`.
+//! let main = b.class_add_method_with_proto(class, "main", proto, ACC_PUBLIC, &[], 0, 0)?;
+//! let mut e = Emitter::new();
+//! let done = e.create_label();
+//! let try_start = e.len();
+//! e.emit(insn::Ldai::new(Imm(1)));
+//! e.emit(insn::Sta::new(Reg(0)));
+//! e.emit(insn::Jmp::new(done));
+//! let handler = e.len();
+//! e.emit(insn::Ldai::new(Imm(0)));
+//! e.emit(insn::Sta::new(Reg(0)));
+//! let handler_end = e.len();
+//! e.bind(done);
+//! e.emit(insn::Lda::new(Reg(0)));
+//! e.emit(insn::Return::new());
+//! let (main_bytes, pc) = e.build()?;
+//! let main_code = b.create_code(1, 3, &main_bytes);
+//! let catch_all = CatchBlockDef {
+//!     type_class: None,
+//!     handler_pc: pc[handler],
+//!     code_size: pc[handler_end] - pc[handler],
+//! };
+//! b.code_add_try_block(
+//!     main_code,
+//!     pc[try_start],
+//!     pc[handler] - pc[try_start],
+//!     &[catch_all],
+//! );
+//! b.method_set_code(main, main_code);
+//!
+//! // Module record: no requests or imports, `export { primes }`.
+//! let module = b.add_literal_array("main")?;
+//! let export = b.add_string("primes")?;
+//! b.literal_array_add_u32(module, 0); // requests
+//! b.literal_array_add_u32(module, 0); // regular imports
+//! b.literal_array_add_u32(module, 0); // namespace imports
+//! b.literal_array_add_u32(module, 1); // local exports
+//! b.literal_array_add_string(module, export);
+//! b.literal_array_add_string(module, export);
+//! b.literal_array_add_u32(module, 0); // indirect exports
+//! b.literal_array_add_u32(module, 0); // star exports
+//! let record = b.class_add_field(class, "moduleRecordIdx", TypeId::U32, ACC_PUBLIC)?;
+//! b.field_set_value_literal_array(record, module);
+//!
+//! // Lay out once to learn the index, patch it in, lay out again.
+//! b.finalize()?;
+//! let id = b
+//!     .method_index_of(primes, IndexDep::LiteralArray(array))
+//!     .expect("finalized");
+//! b.code_set_instructions(primes_code, &primes_body(id)?);
+//! let abc = File::open(b.finalize()?)?;
+//!
+//! // Read it all back.
+//! let class_off = abc.class_id_by_name("Lmain;")?.expect("class");
+//! let class = abc.class(class_off)?;
+//! assert_eq!(abc.get_string(class.source_file_off().unwrap())?, "main.js");
+//! let method = |name: &str| {
+//!     class
+//!         .method_offsets()
+//!         .into_iter()
+//!         .find(|&m| abc.method_name(m).is_ok_and(|n| n == name))
+//!         .expect("method")
+//! };
+//! let code = |m: EntityId| abc.code(abc.method(m)?.code_off().expect("code"));
+//!
+//! let primes = method("primes");
+//! let insns = decode(code(primes)?.instructions())?;
+//! let (_, args, _) = insns[0].0.emit_args();
+//! let vals = abc
+//!     .literal_for(primes)?
+//!     .enumerate_vals(args[1] as u16)
+//!     .expect("array");
+//! let values: Vec<u32> = vals.iter().map(|v| v.as_u32()).collect();
+//! assert_eq!(values, [2, 3]);
+//! assert_eq!(abc.debug_info()?.line_table(primes)[0].line, 2);
+//!
+//! let tries = code(method("main"))?.try_blocks();
+//! assert_eq!(tries.len(), 1);
+//! assert_eq!(tries[0].start_pc, pc[try_start]);
+//! assert_eq!(tries[0].catches[0].handler_pc, pc[handler]);
+//!
+//! let field = abc.field(class.field_offsets()[0])?; // the only field
+//! let records = abc
+//!     .module_record(field.value_i32().expect("offset") as u32)?
+//!     .records();
+//! assert_eq!(records.len(), 1);
+//! assert_eq!(records[0].tag, ModuleTag::LocalExport);
+//! assert_eq!(abc.get_string(records[0].export_name_off)?, "primes");
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
 
 use crate::annotation::AnnotationTag;
 use crate::error::Error;
+use crate::literal::LiteralTag;
+use crate::profile::{SLOT_NUMBER_ANNOTATION, SLOT_NUMBER_ELEMENT};
 use crate::types::{FunctionKind, SourceLang, TypeId};
+use std::collections::HashMap;
 use std::ffi::CString;
 
 /// Opaque handle for a class being built.
@@ -82,6 +229,25 @@ pub struct DebugHandle(pub(crate) u32);
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct AnnotationHandle(pub(crate) u32);
 
+/// An entity that method code refers to by 16-bit index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IndexDep {
+    String(StringHandle),
+    Method(MethodHandle),
+    LiteralArray(LiteralArrayHandle),
+}
+
+impl IndexDep {
+    /// `(ABC_INDEX_DEP_* kind, handle)` for the C bridge.
+    fn raw(self) -> (u8, u32) {
+        match self {
+            IndexDep::String(h) => (abcd_file_sys::ABC_INDEX_DEP_STRING as u8, h.0),
+            IndexDep::Method(h) => (abcd_file_sys::ABC_INDEX_DEP_METHOD as u8, h.0),
+            IndexDep::LiteralArray(h) => (abcd_file_sys::ABC_INDEX_DEP_LITERAL_ARRAY as u8, h.0),
+        }
+    }
+}
+
 /// A catch block definition for try-catch building.
 #[derive(Debug, Clone)]
 pub struct CatchBlockDef {
@@ -124,6 +290,9 @@ pub struct ProtoParam {
 /// ABC file builder.
 pub struct Builder {
     inner: *mut abcd_file_sys::AbcBuilder,
+    /// The slot count annotation given to each method, to update rather
+    /// than add another.
+    slot_counts: HashMap<MethodHandle, AnnotationHandle>,
 }
 
 /// Convert a `&str` to `CString`, mapping null-byte errors to `Error::Ffi`.
@@ -140,9 +309,12 @@ impl Builder {
     pub fn new() -> Result<Self, Error> {
         let inner = unsafe { abcd_file_sys::abc_builder_new() };
         if inner.is_null() {
-            return Err(Error::Ffi("abc_builder_new returned null".into()));
+            return Err(crate::ffi_error("abc_builder_new failed"));
         }
-        Ok(Self { inner })
+        Ok(Self {
+            inner,
+            slot_counts: HashMap::new(),
+        })
     }
 
     // --- API version ---
@@ -414,6 +586,67 @@ impl Builder {
         unsafe { abcd_file_sys::abc_builder_method_set_code(self.inner, method.0, code.0) };
     }
 
+    /// Record that `method`'s code names `dep`, giving `dep` an entry in
+    /// the index of the method's region.
+    ///
+    /// Indexes are only known once the file is laid out: call
+    /// [`finalize`](Self::finalize), read them with
+    /// [`method_index_of`](Self::method_index_of), patch the code with
+    /// [`code_set_instructions`](Self::code_set_instructions) and finalize
+    /// again.
+    pub fn method_add_index_dependency(&mut self, method: MethodHandle, dep: IndexDep) {
+        let (kind, handle) = dep.raw();
+        unsafe {
+            abcd_file_sys::abc_builder_method_add_index_dependency(
+                self.inner, method.0, kind, handle,
+            )
+        };
+    }
+
+    /// The index `method`'s code uses for `dep`, as of the last
+    /// [`finalize`](Self::finalize). `None` before the first finalize or if
+    /// the dependency was never added.
+    pub fn method_index_of(&self, method: MethodHandle, dep: IndexDep) -> Option<u16> {
+        let (kind, handle) = dep.raw();
+        let idx = unsafe {
+            abcd_file_sys::abc_builder_method_get_index(self.inner, method.0, kind, handle)
+        };
+        u16::try_from(idx).ok()
+    }
+
+    /// Record that `method` uses `count` inline-cache slots, as the
+    /// `L_ESSlotNumberAnnotation;` es2abc writes and
+    /// [`Method::profile`](crate::method::Method::profile) reads. Calling it
+    /// again for the same method replaces the count.
+    ///
+    /// The runtime sizes the method's IC array from this, so code that
+    /// gains slotted instructions needs the count raised to match (see
+    /// `abcd_isa::ic_slot_count`).
+    pub fn method_set_slot_count(&mut self, method: MethodHandle, count: u32) -> Result<(), Error> {
+        if let Some(&ann) = self.slot_counts.get(&method) {
+            let ok = unsafe {
+                abcd_file_sys::abc_builder_annotation_set_scalar(self.inner, ann.0, 0, count)
+            };
+            return match ok {
+                0 => Err(crate::ffi_error("abc_builder_annotation_set_scalar failed")),
+                _ => Ok(()),
+            };
+        }
+        let class = self.add_class(SLOT_NUMBER_ANNOTATION)?;
+        let name = self.add_string(SLOT_NUMBER_ELEMENT)?;
+        let ann = self.create_annotation(
+            class,
+            &[AnnotationElemDef {
+                name,
+                tag: AnnotationTag::U32,
+                value: count,
+            }],
+        );
+        self.method_add_annotation(method, ann);
+        self.slot_counts.insert(method, ann);
+        Ok(())
+    }
+
     // --- Field configuration ---
 
     pub fn field_set_value_i32(&mut self, field: FieldHandle, value: i32) {
@@ -432,6 +665,14 @@ impl Builder {
         unsafe { abcd_file_sys::abc_builder_field_set_value_f64(self.inner, field.0, value) };
     }
 
+    /// Store the offset of `lit`, the way `moduleRecordIdx` points at a
+    /// record's module literal.
+    pub fn field_set_value_literal_array(&mut self, field: FieldHandle, lit: LiteralArrayHandle) {
+        unsafe {
+            abcd_file_sys::abc_builder_field_set_value_literal_array(self.inner, field.0, lit.0)
+        };
+    }
+
     // --- Code ---
 
     pub fn create_code(
@@ -456,6 +697,24 @@ impl Builder {
         })
     }
 
+    /// Replace the instructions of `code`. Between two finalize calls the
+    /// length must stay the same, or indexes read in between go stale.
+    pub fn code_set_instructions(&mut self, code: CodeHandle, instructions: &[u8]) {
+        let ptr = if instructions.is_empty() {
+            std::ptr::null()
+        } else {
+            instructions.as_ptr()
+        };
+        unsafe {
+            abcd_file_sys::abc_builder_code_set_instructions(
+                self.inner,
+                code.0,
+                ptr,
+                to_u32(instructions.len()),
+            )
+        };
+    }
+
     pub fn code_add_try_block(
         &mut self,
         code: CodeHandle,
@@ -583,6 +842,38 @@ impl Builder {
         unsafe { abcd_file_sys::abc_builder_debug_add_param(self.inner, debug.0, name.0) };
     }
 
+    /// Give `method` debug info copied verbatim from another file, for
+    /// repacking without re-emitting line tables.
+    ///
+    /// `lnp_bytes` is the line number program through its `END_SEQUENCE`
+    /// opcode; `debug_bytes` is the debug info item from its line start
+    /// through its program index, which is the one part rewritten. String
+    /// offsets in both are copied as they are, so they only stay valid if
+    /// the strings land at the same offsets in the new file.
+    pub fn method_set_raw_debug_info(
+        &mut self,
+        method: MethodHandle,
+        lnp_bytes: &[u8],
+        debug_bytes: &[u8],
+    ) -> Result<DebugHandle, Error> {
+        let handle = unsafe {
+            abcd_file_sys::abc_builder_method_set_raw_debug_info(
+                self.inner,
+                method.0,
+                lnp_bytes.as_ptr(),
+                to_u32(lnp_bytes.len()),
+                debug_bytes.as_ptr(),
+                to_u32(debug_bytes.len()),
+            )
+        };
+        if handle == u32::MAX {
+            return Err(Error::Ffi(
+                "bad method handle or malformed debug info item".into(),
+            ));
+        }
+        Ok(DebugHandle(handle))
+    }
+
     // --- Annotations ---
 
     pub fn create_annotation(
@@ -793,7 +1084,7 @@ impl Builder {
         let mut out_len = 0u32;
         let ptr = unsafe { abcd_file_sys::abc_builder_finalize(self.inner, &mut out_len) };
         if ptr.is_null() {
-            return Err(Error::Ffi("abc_builder_finalize failed".into()));
+            return Err(crate::ffi_error("abc_builder_finalize failed"));
         }
         let slice = unsafe { std::slice::from_raw_parts(ptr, out_len as usize) };
         Ok(slice.to_vec())
@@ -819,3 +1110,130 @@ impl std::fmt::Debug for Builder {
         f.debug_struct("Builder").finish_non_exhaustive()
     }
 }
+
+/// A literal array described in the JSON that
+/// [`LiteralArray::to_json`](crate::literal::LiteralArray::to_json) writes,
+/// ready to add to a [`Builder`].
+///
+/// Values the JSON gives as file offsets (`EtsImplements`, typed arrays,
+/// unexpanded nested arrays) and unknown tags cannot be rebuilt and are
+/// rejected by [`from_json`](Self::from_json).
+#[derive(Debug, Clone, PartialEq)]
+pub struct LiteralArrayBuilder {
+    entries: Vec<(LiteralTag, LiteralItem)>,
+}
+
+/// One entry's payload, in the width the tag stores it.
+#[derive(Debug, Clone, PartialEq)]
+enum LiteralItem {
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    Bool(bool),
+    F32(f32),
+    F64(f64),
+    String(String),
+    Method(String),
+    Array(LiteralArrayBuilder),
+}
+
+impl LiteralArrayBuilder {
+    /// Parse an array in the format documented on
+    /// [`LiteralArray::to_json`](crate::literal::LiteralArray::to_json).
+    pub fn from_json(json: &serde_json::Value) -> Result<Self, Error> {
+        let invalid = |msg: String| Error::InvalidLiteralJson(msg);
+        let items = json
+            .as_array()
+            .ok_or_else(|| invalid("expected an array of entries".into()))?;
+        let mut entries = Vec::with_capacity(items.len());
+        for (i, item) in items.iter().enumerate() {
+            let name = item.get("tag").and_then(|t| t.as_str());
+            let tag = name
+                .and_then(|name| LiteralTag::ALL.into_iter().find(|t| t.to_string() == name))
+                .ok_or_else(|| invalid(format!("entry {i}: unknown or missing tag")))?;
+            let value = item.get("value").unwrap_or(&serde_json::Value::Null);
+            let payload = if tag == LiteralTag::LiteralArray && value.is_array() {
+                let nested = Self::from_json(value).map_err(|e| match e {
+                    Error::InvalidLiteralJson(msg) => invalid(format!("entry {i}: {msg}")),
+                    other => other,
+                })?;
+                LiteralItem::Array(nested)
+            } else {
+                literal_item(tag, value)
+                    .ok_or_else(|| invalid(format!("entry {i}: bad value for {tag}: {value}")))?
+            };
+            entries.push((tag, payload));
+        }
+        Ok(Self { entries })
+    }
+
+    /// Add the array to `b` under `id`, nested arrays under `id.0`, `id.1`
+    /// and so on by entry index. `method` maps the `Class.method` names of
+    /// method entries to methods already added to `b`.
+    pub fn build(
+        &self,
+        b: &mut Builder,
+        id: &str,
+        method: &impl Fn(&str) -> Option<MethodHandle>,
+    ) -> Result<LiteralArrayHandle, Error> {
+        let lit = b.add_literal_array(id)?;
+        for (i, (tag, item)) in self.entries.iter().enumerate() {
+            b.literal_array_add_u8(lit, *tag as u8);
+            match item {
+                LiteralItem::U8(v) => b.literal_array_add_u8(lit, *v),
+                LiteralItem::U16(v) => b.literal_array_add_u16(lit, *v),
+                LiteralItem::U32(v) => b.literal_array_add_u32(lit, *v),
+                LiteralItem::Bool(v) => b.literal_array_add_bool(lit, *v),
+                LiteralItem::F32(v) => b.literal_array_add_f32(lit, *v),
+                LiteralItem::F64(v) => b.literal_array_add_f64(lit, *v),
+                LiteralItem::String(s) => {
+                    let s = b.add_string(s)?;
+                    b.literal_array_add_string(lit, s);
+                }
+                LiteralItem::Method(name) => {
+                    let m = method(name).ok_or_else(|| {
+                        Error::InvalidLiteralJson(format!("entry {i}: unknown method {name}"))
+                    })?;
+                    b.literal_array_add_method(lit, m);
+                }
+                LiteralItem::Array(nested) => {
+                    let nested = nested.build(b, &format!("{id}.{i}"), method)?;
+                    b.literal_array_add_literalarray(lit, nested);
+                }
+            }
+        }
+        Ok(lit)
+    }
+}
+
+/// The payload of a `tag` entry whose JSON value is `value`; nested arrays
+/// are handled by [`LiteralArrayBuilder::from_json`] itself.
+fn literal_item(tag: LiteralTag, value: &serde_json::Value) -> Option<LiteralItem> {
+    let float = || match value.as_str() {
+        Some("NaN") => Some(f64::NAN),
+        Some("Infinity") => Some(f64::INFINITY),
+        Some("-Infinity") => Some(f64::NEG_INFINITY),
+        Some(_) => None,
+        None => value.as_f64(),
+    };
+    let uint = || value.as_u64();
+    Some(match tag {
+        LiteralTag::TagValue | LiteralTag::Accessor | LiteralTag::BuiltinTypeIndex => {
+            LiteralItem::U8(u8::try_from(uint()?).ok()?)
+        }
+        LiteralTag::NullValue if value.is_null() => LiteralItem::U8(0),
+        LiteralTag::Bool => LiteralItem::Bool(value.as_bool()?),
+        LiteralTag::Integer => LiteralItem::U32(i32::try_from(value.as_i64()?).ok()? as u32),
+        LiteralTag::Float => LiteralItem::F32(float()? as f32),
+        LiteralTag::Double => LiteralItem::F64(float()?),
+        LiteralTag::String => LiteralItem::String(value.as_str()?.to_owned()),
+        LiteralTag::Method
+        | LiteralTag::GeneratorMethod
+        | LiteralTag::AsyncGeneratorMethod
+        | LiteralTag::Getter
+        | LiteralTag::Setter => LiteralItem::Method(value.as_str()?.to_owned()),
+        LiteralTag::MethodAffiliate => LiteralItem::U16(u16::try_from(uint()?).ok()?),
+        LiteralTag::LiteralBufferIndex => LiteralItem::U32(u32::try_from(uint()?).ok()?),
+        _ => return None,
+    })
+}
@@ -14,7 +14,7 @@ impl<'f> Class<'f> {
     pub(crate) fn open(file: &'f File, offset: EntityId) -> Result<Self, Error> {
         let handle = unsafe { abcd_file_sys::abc_class_open(file.handle(), offset.0) };
         if handle.is_null() {
-            return Err(Error::Ffi(format!(
+            return Err(crate::ffi_error(format!(
                 "abc_class_open failed at offset {offset:?}"
             )));
         }
@@ -73,21 +73,9 @@ impl<'f> Class<'f> {
     }
 
     pub fn name(&self) -> Result<String, Error> {
-        let len =
-            unsafe { abcd_file_sys::abc_class_get_name(self.handle, std::ptr::null_mut(), 0) };
-        if len == 0 {
-            return Ok(String::new());
-        }
-        let mut buf = vec![0u8; len];
-        let written = unsafe {
-            abcd_file_sys::abc_class_get_name(
-                self.handle,
-                buf.as_mut_ptr() as *mut std::ffi::c_char,
-                buf.len(),
-            )
-        };
-        buf.truncate(written);
-        String::from_utf8(buf).map_err(|e| Error::Ffi(e.to_string()))
+        crate::ffi_string(|buf, len| unsafe {
+            abcd_file_sys::abc_class_get_name(self.handle, buf, len)
+        })
     }
 
     pub fn descriptor(&self) -> &[u8] {
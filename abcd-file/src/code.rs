@@ -1,5 +1,6 @@
 //! Code data accessor.
 
+use crate::digest::CodeDigest;
 use crate::{EntityId, File, error::Error};
 
 /// Try block info.
@@ -30,7 +31,7 @@ impl<'f> Code<'f> {
     pub(crate) fn open(file: &'f File, offset: EntityId) -> Result<Self, Error> {
         let handle = unsafe { abcd_file_sys::abc_code_open(file.handle(), offset.0) };
         if handle.is_null() {
-            return Err(Error::Ffi(format!(
+            return Err(crate::ffi_error(format!(
                 "abc_code_open failed at offset {offset:?}"
             )));
         }
@@ -113,6 +114,32 @@ impl<'f> Code<'f> {
         unsafe { abcd_file_sys::abc_code_get_size(self.handle) }
     }
 
+    /// The code item as stored in the file, `size()` bytes from
+    /// [`offset`](Self::offset); empty if that runs past the end.
+    pub fn raw_bytes(&self) -> &'f [u8] {
+        let start = self.off.0 as usize;
+        self.file
+            .raw_data()
+            .get(start..start + self.size() as usize)
+            .unwrap_or(&[])
+    }
+
+    /// Digest of [`raw_bytes`](Self::raw_bytes) under `algo`; see
+    /// [`crate::digest`]. With the `sha2` feature, `digest::Sha256` gives
+    /// a 32-byte digest the same way.
+    ///
+    /// ```no_run
+    /// # fn f(code: &abcd_file::code::Code) {
+    /// use abcd_file::digest::Crc32;
+    ///
+    /// let crc: [u8; 4] = code.digest(Crc32::new());
+    /// # }
+    /// ```
+    pub fn digest<const N: usize>(&self, mut algo: impl CodeDigest<N>) -> [u8; N] {
+        algo.update(self.raw_bytes());
+        algo.finish()
+    }
+
     pub fn code_id(&self) -> EntityId {
         EntityId(unsafe { abcd_file_sys::abc_code_get_code_id(self.handle) })
     }
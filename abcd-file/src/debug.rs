@@ -45,7 +45,7 @@ impl<'f> DebugInfo<'f> {
     pub(crate) fn open(file: &'f File) -> Result<Self, Error> {
         let handle = unsafe { abcd_file_sys::abc_debug_info_open(file.handle()) };
         if handle.is_null() {
-            return Err(Error::Ffi("abc_debug_info_open failed".into()));
+            return Err(crate::ffi_error("abc_debug_info_open failed"));
         }
         Ok(Self { handle, file })
     }
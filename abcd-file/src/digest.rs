@@ -0,0 +1,139 @@
+//! Digests of method code items, for integrity checks.
+//!
+//! [`Code::digest`](crate::code::Code::digest) feeds a code item's bytes —
+//! register and argument counts, instructions and try blocks, exactly as
+//! stored — to any [`CodeDigest`]. [`Crc32`] is cheap enough to run over
+//! every method of an app on a device; SHA-256 (`Sha256`, with the `sha2`
+//! feature) suits allowlists that must resist forgery. Because the bytes
+//! are hashed as stored, IDs embedded in instructions are part of the
+//! digest: an allowlist is tied to one build of a file.
+//!
+//! [`method_digests`] digests every method of a file, reporting progress
+//! to an [`AnalysisObserver`].
+
+#[cfg(feature = "sha2")]
+pub use sha2::Sha256;
+
+use crate::{AnalysisObserver, EntityId, File};
+
+/// A hash function producing an `N`-byte digest.
+pub trait CodeDigest<const N: usize> {
+    fn update(&mut self, bytes: &[u8]);
+    fn finish(self) -> [u8; N];
+}
+
+#[cfg(feature = "sha2")]
+impl CodeDigest<32> for Sha256 {
+    fn update(&mut self, bytes: &[u8]) {
+        sha2::Digest::update(self, bytes);
+    }
+
+    fn finish(self) -> [u8; 32] {
+        sha2::Digest::finalize(self).into()
+    }
+}
+
+/// The digest of one method's code item, from [`method_digests`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MethodDigest<const N: usize> {
+    pub method: EntityId,
+    /// `class.method`, as verification reports name it.
+    pub name: String,
+    pub digest: [u8; N],
+}
+
+/// Digest the code item of every method in `abc`'s local classes, each
+/// with a fresh digest from `new`, in class order. `observer` hears of
+/// each class and method; classes and code items that do not parse are
+/// left out with a warning.
+pub fn method_digests<const N: usize, D: CodeDigest<N>>(
+    abc: &File,
+    mut new: impl FnMut() -> D,
+    observer: &mut dyn AnalysisObserver,
+) -> Vec<MethodDigest<N>> {
+    let mut digests = Vec::new();
+    for class_off in abc.class_offsets() {
+        if abc.is_external(class_off) {
+            continue;
+        }
+        let class = match abc.class(class_off) {
+            Ok(c) => c,
+            Err(e) => {
+                observer.on_warning(None, &format!("class at {class_off}: {e}"));
+                continue;
+            }
+        };
+        let class_name = abc.get_string_lossy(class_off);
+        observer.on_class_start(class_off, &class_name);
+        for method_off in class.method_offsets() {
+            let code = abc
+                .method(method_off)
+                .and_then(|m| m.code_off().map(|off| abc.code(off)).transpose());
+            let code = match code {
+                Ok(Some(code)) => code,
+                Ok(None) => continue,
+                Err(e) => {
+                    observer.on_warning(Some(method_off), &format!("method at {method_off}: {e}"));
+                    continue;
+                }
+            };
+            digests.push(MethodDigest {
+                method: method_off,
+                name: format!("{class_name}.{}", abc.method_name_lossy(method_off)),
+                digest: code.digest(new()),
+            });
+            observer.on_method_done(method_off, None);
+        }
+    }
+    digests
+}
+
+/// CRC-32 (IEEE 802.3, as in zlib), digest in big-endian byte order.
+#[derive(Debug, Clone, Copy)]
+pub struct Crc32(u32);
+
+impl Crc32 {
+    pub fn new() -> Self {
+        Self(!0)
+    }
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CodeDigest<4> for Crc32 {
+    fn update(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = CRC_TABLE[((self.0 ^ b as u32) & 0xff) as usize] ^ (self.0 >> 8);
+        }
+    }
+
+    fn finish(self) -> [u8; 4] {
+        (!self.0).to_be_bytes()
+    }
+}
+
+static CRC_TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 {
+                0xedb8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+}
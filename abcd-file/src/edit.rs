@@ -0,0 +1,343 @@
+//! Instruction-level edits of a method, with everything that points into
+//! its code kept on target.
+//!
+//! [`abcd_isa::Patcher`] rewrites a method body and keeps its jumps on
+//! target, but a method's code is also addressed from outside the
+//! instructions: try blocks and catch handlers in the code item, and the
+//! line number table in the debug info. [`CodeEditor`] takes edits by pc
+//! and, on [`commit`](CodeEditor::commit), moves all of them along with
+//! the code. Writing the result into a file (with `builder::Builder`, or
+//! by relocating the code item) is up to the caller, except for the
+//! method's inline-cache slot count, which
+//! [`EditedCode::write_slot_count`] updates in place.
+//!
+//! Edits follow [`Patcher`]'s rules: code inserted before an instruction
+//! is run by every jump to it, and so is inside a try block or handler
+//! that starts there.
+//!
+//! ```no_run
+//! use abcd_file::EntityId;
+//! use abcd_file::edit::CodeEditor;
+//! use abcd_isa::insn;
+//!
+//! let abc = abcd_file::File::open_path("modules.abc".as_ref()).unwrap();
+//! let mut editor = CodeEditor::new(&abc, EntityId(0x1a4)).unwrap();
+//! editor.insert_before(0, &[insn::Debugger::new()]).unwrap();
+//! let edited = editor.commit().unwrap();
+//! println!("{} bytes, entry moved to {:?}", edited.bytes.len(), edited.map_pc(0));
+//! ```
+//!
+//! [`replace_string`] works on a whole file instead: it points every
+//! reference to one string at another text, for giving obfuscated names
+//! and strings back their meaning without rebuilding the file.
+
+use abcd_isa::{Bytecode, Patcher};
+
+use crate::code::{CatchBlock, TryBlock};
+#[cfg(feature = "debug-info")]
+use crate::debug::LineEntry;
+use crate::literal::LiteralTag;
+use crate::migrate::{read_u32, value_size, write_checksum};
+use crate::util::leb128::encode_uleb128;
+use crate::util::mutf8::encode_mutf8;
+use crate::{EntityId, Error, File, Result};
+
+/// Header layout: magic[8], checksum u32, version[4], file_size u32.
+const FILE_SIZE_OFFSET: usize = 16;
+
+/// Pending edits of one method's code.
+#[derive(Clone, Debug)]
+pub struct CodeEditor {
+    method_off: EntityId,
+    patcher: Patcher,
+    code_len: u32,
+    try_blocks: Vec<TryBlock>,
+    #[cfg(feature = "debug-info")]
+    lines: Vec<LineEntry>,
+    /// The method's recorded slot count and where the file stores it.
+    slot_count: Option<(u32, usize)>,
+}
+
+/// The result of [`CodeEditor::commit`].
+#[derive(Clone, Debug)]
+pub struct EditedCode {
+    /// The new instructions.
+    pub bytes: Vec<u8>,
+    /// The method's try blocks over the new instructions, in their
+    /// original order.
+    pub try_blocks: Vec<TryBlock>,
+    /// The method's line number table over the new instructions. Entries
+    /// that did not start an instruction are dropped.
+    #[cfg(feature = "debug-info")]
+    pub line_table: Vec<LineEntry>,
+    /// Inline-cache slots to record for the new code: what it needs (see
+    /// [`abcd_isa::ic_slot_count`]), or the method's recorded count if
+    /// that is larger.
+    pub ic_slots: u32,
+    /// Where the file stores the method's slot count, if it records one;
+    /// [`write_slot_count`](Self::write_slot_count) stores `ic_slots`
+    /// there.
+    pub slot_count_off: Option<usize>,
+    /// `(old, new)` pc of every original instruction and of the end of the
+    /// code, ascending. A deleted instruction maps to whatever took its
+    /// place.
+    pub pc_map: Vec<(u32, u32)>,
+}
+
+impl EditedCode {
+    /// Where the original instruction at `pc` went; `None` if no
+    /// instruction started there.
+    pub fn map_pc(&self, pc: u32) -> Option<u32> {
+        let i = self
+            .pc_map
+            .binary_search_by_key(&pc, |&(old, _)| old)
+            .ok()?;
+        Some(self.pc_map[i].1)
+    }
+
+    /// Store [`ic_slots`](Self::ic_slots) as the method's slot count in
+    /// `data`, the bytes of the file the editor read, and update the
+    /// checksum. A method that records no count is left as it is; a
+    /// rebuilt file gives it one with `Builder::method_set_slot_count`.
+    pub fn write_slot_count(&self, data: &mut [u8]) -> Result<()> {
+        let Some(at) = self.slot_count_off else {
+            return Ok(());
+        };
+        let len = data.len();
+        data.get_mut(at..at + 4)
+            .ok_or(Error::OffsetOutOfBounds(at, len))?
+            .copy_from_slice(&self.ic_slots.to_le_bytes());
+        write_checksum(data);
+        Ok(())
+    }
+}
+
+impl CodeEditor {
+    /// Decode the code of the method at `method_off`, with its try blocks
+    /// and line table.
+    pub fn new(file: &File, method_off: EntityId) -> Result<Self> {
+        let method = file.method(method_off)?;
+        let code_off = method.code_off().ok_or(Error::NoCode(method_off))?;
+        let code = file.code(code_off)?;
+        let slot_count = method.profile().ic_slots.zip(method.slot_count_off());
+        Ok(Self {
+            method_off,
+            patcher: Patcher::new(code.instructions()).map_err(Error::Edit)?,
+            code_len: code.code_size(),
+            try_blocks: code.try_blocks(),
+            #[cfg(feature = "debug-info")]
+            lines: file
+                .debug_info()
+                .map(|d| d.line_table(method_off))
+                .unwrap_or_default(),
+            slot_count,
+        })
+    }
+
+    pub fn method_off(&self) -> EntityId {
+        self.method_off
+    }
+
+    /// Insert `insts` ahead of the instruction at `pc`.
+    pub fn insert_before(&mut self, pc: u32, insts: &[Bytecode]) -> Result<()> {
+        let idx = self.index(pc)?;
+        self.patcher.insert_before(idx, insts).map_err(Error::Edit)
+    }
+
+    /// Insert `insts` right after the instruction at `pc`.
+    pub fn insert_after(&mut self, pc: u32, insts: &[Bytecode]) -> Result<()> {
+        let idx = self.index(pc)?;
+        self.patcher.insert_after(idx, insts).map_err(Error::Edit)
+    }
+
+    /// Replace the instruction at `pc` with `insts`, which may be empty.
+    pub fn replace(&mut self, pc: u32, insts: &[Bytecode]) -> Result<()> {
+        let idx = self.index(pc)?;
+        self.patcher.replace(idx, insts).map_err(Error::Edit)
+    }
+
+    /// Remove the instruction at `pc`.
+    pub fn delete(&mut self, pc: u32) -> Result<()> {
+        self.replace(pc, &[])
+    }
+
+    /// Apply every edit: re-encode the code, widening jumps that no longer
+    /// reach, and move try blocks, handlers and line entries with it.
+    pub fn commit(&self) -> Result<EditedCode> {
+        let patched = self.patcher.finish().map_err(Error::Edit)?;
+        let map = |pc: u32| patched.map_offset(pc).ok_or(Error::InvalidPc(pc));
+        // A range's end is the start of the instruction after it, or the
+        // end of the code, and moves as one.
+        let range = |start: u32, len: u32| -> Result<(u32, u32)> {
+            let new = map(start)?;
+            Ok((new, map(start + len)? - new))
+        };
+
+        let mut try_blocks = Vec::with_capacity(self.try_blocks.len());
+        for block in &self.try_blocks {
+            let (start_pc, length) = range(block.start_pc, block.length)?;
+            let mut catches = Vec::with_capacity(block.catches.len());
+            for catch in &block.catches {
+                let (handler_pc, code_size) = range(catch.handler_pc, catch.code_size)?;
+                catches.push(CatchBlock {
+                    type_idx: catch.type_idx,
+                    handler_pc,
+                    code_size,
+                });
+            }
+            try_blocks.push(TryBlock {
+                start_pc,
+                length,
+                num_catches: block.num_catches,
+                catches,
+            });
+        }
+
+        let mut pc_map = patched.moves().to_vec();
+        pc_map.push((self.code_len, patched.bytes.len() as u32));
+        Ok(EditedCode {
+            #[cfg(feature = "debug-info")]
+            line_table: self
+                .lines
+                .iter()
+                .filter_map(|e| {
+                    Some(LineEntry {
+                        offset: patched.map_offset(e.offset)?,
+                        line: e.line,
+                    })
+                })
+                .collect(),
+            ic_slots: match self.slot_count {
+                Some((recorded, _)) => recorded.max(patched.ic_slots),
+                None => patched.ic_slots,
+            },
+            slot_count_off: self.slot_count.map(|(_, at)| at),
+            try_blocks,
+            pc_map,
+            bytes: patched.bytes,
+        })
+    }
+
+    fn index(&self, pc: u32) -> Result<usize> {
+        self.patcher.index_at(pc).ok_or(Error::InvalidPc(pc))
+    }
+}
+
+/// Point every reference to the string at `old` at a string holding
+/// `new_text`, and return the rewritten file.
+///
+/// References are the entries of each region's index (through which
+/// `lda.str` and the named property instructions refer to strings),
+/// `String` values in literal arrays, and the names of local methods and
+/// fields. If one of those already refers to a string holding `new_text`,
+/// that string is reused; otherwise a new one is appended to the file.
+/// The string at `old` stays where it was, as do references to it from
+/// debug info, annotations and the elements of a typed `ArrayString`
+/// literal, whose untagged payload this does not read.
+///
+/// Fails if `old` is a class, whose name is part of the class item, or if
+/// nothing above refers to it.
+pub fn replace_string(file: &File, old: EntityId, new_text: &str) -> Result<Vec<u8>> {
+    if file.class_offsets().contains(&old) {
+        return Err(Error::ReplaceString(old, "it is a class name"));
+    }
+    let mut data = file.raw_data().to_vec();
+    let (refs, strings) = string_refs(file, &data, old)?;
+    if refs.is_empty() {
+        return Err(Error::ReplaceString(old, "nothing refers to it"));
+    }
+    let text = encode_mutf8(new_text);
+    let holds_text = |s: EntityId| file.string_bytes(s).is_ok_and(|bytes| bytes == text);
+    if holds_text(old) {
+        return Ok(data);
+    }
+
+    let new = match strings.into_iter().find(|&s| holds_text(s)) {
+        Some(existing) => existing,
+        None => {
+            let new = EntityId(data.len() as u32);
+            // The prefix holds the UTF-16 length and whether every byte
+            // is ASCII.
+            let utf16_len = new_text.encode_utf16().count() as u64;
+            let is_ascii = text.iter().all(|&b| b < 0x80);
+            encode_uleb128(utf16_len << 1 | u64::from(is_ascii), &mut data);
+            data.extend_from_slice(&text);
+            data.push(0);
+            let size = data.len() as u32;
+            data[FILE_SIZE_OFFSET..FILE_SIZE_OFFSET + 4].copy_from_slice(&size.to_le_bytes());
+            new
+        }
+    };
+    for pos in refs {
+        data[pos..pos + 4].copy_from_slice(&new.0.to_le_bytes());
+    }
+    write_checksum(&mut data);
+    Ok(data)
+}
+
+/// Where `data` stores the offset `old` as a string reference, and the
+/// other strings referred to from the same places.
+fn string_refs(file: &File, data: &[u8], old: EntityId) -> Result<(Vec<usize>, Vec<EntityId>)> {
+    let mut refs = Vec::new();
+    let mut strings = Vec::new();
+    let mut check = |pos: usize, is_string: bool| -> Result<()> {
+        let id = read_u32(data, pos)?;
+        if id == old.0 {
+            refs.push(pos);
+        } else if is_string {
+            strings.push(EntityId(id));
+        }
+        Ok(())
+    };
+
+    // Region indexes also hold methods and literal arrays, so an entry
+    // is only known to be a string if it is `old`.
+    for idx in 0..file.num_index_headers() {
+        let Some(header) = file.index_header(idx) else {
+            continue;
+        };
+        for i in 0..header.method_idx_size as usize {
+            check(header.method_idx_off as usize + 4 * i, false)?;
+        }
+    }
+
+    for array_off in file.literal_array_offsets() {
+        let mut pos = array_off.0 as usize;
+        let count = read_u32(data, pos)?;
+        pos += 4;
+        for _ in 0..count / 2 {
+            let tag = *data
+                .get(pos)
+                .ok_or(Error::OffsetOutOfBounds(pos, data.len()))?;
+            pos += 1;
+            // A typed array (`ArrayString` included) is the last value and
+            // its payload carries no tags, so its strings are not found.
+            let Some(size) = LiteralTag::from_u8(tag).and_then(value_size) else {
+                break;
+            };
+            if tag == LiteralTag::String as u8 {
+                check(pos, true)?;
+            }
+            pos += size;
+        }
+    }
+
+    for class_off in file.class_offsets() {
+        if file.is_external(class_off) {
+            continue;
+        }
+        let Ok(class) = file.class(class_off) else {
+            continue;
+        };
+        // Methods and fields both start with class_idx u16, then
+        // proto_idx or type_idx u16, then name_off u32.
+        for item in class
+            .method_offsets()
+            .into_iter()
+            .chain(class.field_offsets())
+        {
+            check(item.0 as usize + 4, true)?;
+        }
+    }
+    Ok((refs, strings))
+}
@@ -11,6 +11,15 @@ pub enum Error {
     #[error("Unsupported version: {0}")]
     UnsupportedVersion(abcd_isa::Version),
 
+    #[error(
+        "File version {file} is outside the supported range {supported_min} to {supported_max}"
+    )]
+    VersionMismatch {
+        file: abcd_isa::Version,
+        supported_min: abcd_isa::Version,
+        supported_max: abcd_isa::Version,
+    },
+
     #[error("Offset {0:#x} out of bounds (file size: {1:#x})")]
     OffsetOutOfBounds(usize, usize),
 
@@ -23,11 +32,35 @@ pub enum Error {
     #[error("Invalid tagged value tag {0:#x} at offset {1:#x}")]
     InvalidTag(u8, usize),
 
+    #[error("Literal array index {0} out of range ({1} arrays in header)")]
+    LiteralIndexOutOfRange(u32, u32),
+
+    #[error("Cannot migrate: {0}")]
+    Migration(String),
+
     #[error("FFI call failed: {0}")]
     Ffi(String),
 
     #[error("I/O error: {0}")]
     Io(String),
+
+    #[error("Invalid notes file: {0}")]
+    InvalidNotes(String),
+
+    #[error("Invalid literal array JSON: {0}")]
+    InvalidLiteralJson(String),
+
+    #[error("Method {0} has no code")]
+    NoCode(crate::EntityId),
+
+    #[error("No instruction starts at pc {0:#x}")]
+    InvalidPc(u32),
+
+    #[error("Cannot edit code: {0}")]
+    Edit(abcd_isa::PatchError),
+
+    #[error("Cannot replace string {0}: {1}")]
+    ReplaceString(crate::EntityId, &'static str),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -1,6 +1,6 @@
 //! Field data accessor.
 
-use crate::{EntityId, File, collect_entity_ids, error::Error};
+use crate::{EntityId, File, collect_entity_ids, error::Error, types::ValueType};
 
 /// A field data accessor. Borrows from a [`File`].
 pub struct Field<'f> {
@@ -13,7 +13,7 @@ impl<'f> Field<'f> {
     pub(crate) fn open(file: &'f File, offset: EntityId) -> Result<Self, Error> {
         let handle = unsafe { abcd_file_sys::abc_field_open(file.handle(), offset.0) };
         if handle.is_null() {
-            return Err(Error::Ffi(format!(
+            return Err(crate::ffi_error(format!(
                 "abc_field_open failed at offset {offset:?}"
             )));
         }
@@ -37,6 +37,11 @@ impl<'f> Field<'f> {
         unsafe { abcd_file_sys::abc_field_type(self.handle) }
     }
 
+    /// The field's declared type, decoded from [`type_id`](Self::type_id).
+    pub fn value_type(&self) -> ValueType {
+        ValueType::from_field_encoding(self.type_id())
+    }
+
     pub fn access_flags(&self) -> u32 {
         unsafe { abcd_file_sys::abc_field_access_flags(self.handle) }
     }
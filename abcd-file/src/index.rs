@@ -13,7 +13,7 @@ impl<'f> Index<'f> {
     pub(crate) fn open(file: &'f File, method_off: EntityId) -> Result<Self, Error> {
         let handle = unsafe { abcd_file_sys::abc_index_open(file.handle(), method_off.0) };
         if handle.is_null() {
-            return Err(Error::Ffi(format!(
+            return Err(crate::ffi_error(format!(
                 "abc_index_open failed for method at {method_off:?}"
             )));
         }
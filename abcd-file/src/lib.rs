@@ -1,34 +1,104 @@
 //! ABC binary file format reader, writer, and utilities for ArkCompiler bytecode files.
 
 pub mod annotation;
+#[cfg(feature = "builder")]
 pub mod builder;
 pub mod class;
 pub mod code;
+#[cfg(feature = "debug-info")]
 pub mod debug;
+pub mod digest;
+pub mod edit;
 pub mod error;
 pub mod field;
 pub mod index;
 pub mod literal;
+#[cfg(feature = "sha2")]
+pub mod manifest;
 pub mod method;
+pub mod migrate;
+#[cfg(feature = "module")]
 pub mod module;
+pub mod names;
+pub mod notes;
+pub mod observer;
+pub mod profile;
 pub mod proto;
+#[cfg(unix)]
+pub mod sandbox;
+pub mod static_ir;
 pub mod types;
 pub mod util;
+pub mod validation;
 pub mod version;
 
 pub use abcd_isa::EntityId;
 pub use error::{Error, Result};
+pub use migrate::{downgrade, migrate};
+pub use observer::AnalysisObserver;
 pub use types::*;
+pub use validation::ValidationReport;
 
 // Backward-compat aliases for downstream crates in this workspace.
 pub type AbcFile = File;
+#[cfg(feature = "module")]
 pub use module as module_record;
 
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::ffi::CString;
+use std::marker::PhantomData;
 use std::path::Path;
+use std::sync::OnceLock;
+
+/// Optional parts of the C++ backend, as compiled into this build.
+///
+/// Each is a crate feature, on by default; a slim build that turns one off
+/// also loses the matching API (`builder`, `File::debug_info`,
+/// `File::module`). Code that only links against whatever build it is
+/// given can check here to report what is missing instead of failing
+/// later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackendInfo {
+    /// Writing files with `builder::Builder`.
+    pub builder: bool,
+    /// Line, column and local variable tables from `File::debug_info`.
+    pub debug_info: bool,
+    /// Module records from `File::module` and `File::module_record`.
+    pub module: bool,
+}
+
+/// What the linked backend supports; see [`BackendInfo`].
+pub fn backend_info() -> BackendInfo {
+    let caps = abcd_file_sys::capabilities();
+    BackendInfo {
+        builder: caps & abcd_file_sys::ABC_CAP_BUILDER != 0,
+        debug_info: caps & abcd_file_sys::ABC_CAP_DEBUG_INFO != 0,
+        module: caps & abcd_file_sys::ABC_CAP_MODULE != 0,
+    }
+}
 
 // ---- pub(crate) helpers ----
 
+/// Whether UTF-8 `bytes` mean the same read as MUTF-8: they hold no 4-byte
+/// sequence, which MUTF-8 spells as a surrogate pair instead.
+fn is_mutf8_compatible(bytes: &[u8]) -> bool {
+    !bytes.iter().any(|&b| b >= 0xf0)
+}
+
+/// `bytes` as UTF-8, else as MUTF-8, else with invalid sequences replaced.
+fn lossy_name(bytes: &[u8]) -> Cow<'_, str> {
+    if let Ok(s) = std::str::from_utf8(bytes) {
+        return Cow::Borrowed(s);
+    }
+    let mut terminated = bytes.to_vec();
+    terminated.push(0);
+    match util::mutf8::decode_mutf8(&terminated, 0) {
+        Ok(s) => Cow::Owned(s),
+        Err(_) => String::from_utf8_lossy(bytes),
+    }
+}
+
 /// Callback for collecting entity IDs from C++ enumerate functions.
 pub(crate) unsafe extern "C" fn entity_id_cb(id: u32, ctx: *mut std::ffi::c_void) -> i32 {
     unsafe {
@@ -51,6 +121,36 @@ where
     ids
 }
 
+/// Read a string through a bridge function taking `(buf, buf_len)`, which
+/// returns the full length when `buf` is null and otherwise copies what
+/// fits and NUL-terminates it.
+pub(crate) fn ffi_string(read: impl Fn(*mut std::ffi::c_char, usize) -> usize) -> Result<String> {
+    let len = read(std::ptr::null_mut(), 0);
+    if len == 0 {
+        return Ok(String::new());
+    }
+    // One more byte for the NUL, which would otherwise take the place of
+    // the last character.
+    let mut buf = vec![0u8; len + 1];
+    let written = read(buf.as_mut_ptr() as *mut std::ffi::c_char, buf.len());
+    buf.truncate(written);
+    String::from_utf8(buf).map_err(|e| Error::Ffi(e.to_string()))
+}
+
+/// Build an [`Error::Ffi`] for a failed bridge call, appending the reason the
+/// C++ side recorded in `abc_last_error()` (if any).
+pub(crate) fn ffi_error(context: impl std::fmt::Display) -> Error {
+    // SAFETY: abc_last_error returns null or a NUL-terminated thread-local
+    // string that stays valid until the next bridge call on this thread.
+    let reason = unsafe { abcd_file_sys::abc_last_error() };
+    if reason.is_null() {
+        return Error::Ffi(context.to_string());
+    }
+    // SAFETY: reason is non-null and NUL-terminated (checked above).
+    let reason = unsafe { std::ffi::CStr::from_ptr(reason) }.to_string_lossy();
+    Error::Ffi(format!("{context}: {reason}"))
+}
+
 // ---- FileType ----
 
 /// ABC file type classification.
@@ -93,26 +193,98 @@ pub struct IndexHeader {
 
 // ---- File ----
 
+/// The bytes a [`File`] was opened from. The C++ runtime reads them in
+/// place, so they must not move or change while the handle is open.
+enum Data {
+    Owned(Vec<u8>),
+    #[cfg(feature = "mmap")]
+    Mapped(memmap2::Mmap),
+    /// The caller's bytes; only ever inside a [`FileRef`], whose lifetime
+    /// keeps them borrowed.
+    Borrowed(*const u8, usize),
+}
+
+impl std::ops::Deref for Data {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Data::Owned(data) => data,
+            #[cfg(feature = "mmap")]
+            Data::Mapped(map) => map,
+            // SAFETY: `FileRef` borrows the bytes for as long as the file
+            // that holds them is reachable.
+            Data::Borrowed(ptr, len) => unsafe { std::slice::from_raw_parts(*ptr, *len) },
+        }
+    }
+}
+
 /// An opened ABC file backed by the C++ runtime.
 pub struct File {
     handle: *mut abcd_file_sys::AbcFileHandle,
-    data: Vec<u8>,
+    data: Data,
+    /// Local methods by function kind, built on first use.
+    methods_by_kind: OnceLock<HashMap<FunctionKind, Vec<EntityId>>>,
+    /// Dynamic or static, worked out on first use.
+    kind: OnceLock<FileType>,
 }
 
-// SAFETY: The C++ AbcFileHandle is read-only after construction.
+// SAFETY: The C++ AbcFileHandle is read-only after construction, and so
+// are the bytes it reads.
 unsafe impl Send for File {}
 unsafe impl Sync for File {}
 
+/// A [`File`] parsed from bytes the caller keeps, from
+/// [`File::open_ref`]. Dereferences to the file.
+#[derive(Debug)]
+pub struct FileRef<'a> {
+    file: File,
+    bytes: PhantomData<&'a [u8]>,
+}
+
+impl std::ops::Deref for FileRef<'_> {
+    type Target = File;
+
+    fn deref(&self) -> &File {
+        &self.file
+    }
+}
+
 impl File {
     /// Open an ABC file from owned bytes.
     pub fn open(data: Vec<u8>) -> Result<Self> {
+        Self::from_data(Data::Owned(data))
+    }
+
+    /// Open an ABC file from bytes the caller owns, without copying them.
+    ///
+    /// For bytes that are already in memory, such as entries of an archive
+    /// read whole: [`open`](Self::open) would need its own copy of each.
+    /// The file borrows `data` for as long as it is open.
+    ///
+    /// ```no_run
+    /// let bytes = std::fs::read("modules.abc").unwrap();
+    /// let abc = abcd_file::File::open_ref(&bytes).unwrap();
+    /// println!("{} classes", abc.num_classes());
+    /// ```
+    pub fn open_ref(data: &[u8]) -> Result<FileRef<'_>> {
+        Ok(FileRef {
+            file: Self::from_data(Data::Borrowed(data.as_ptr(), data.len()))?,
+            bytes: PhantomData,
+        })
+    }
+
+    fn from_data(data: Data) -> Result<Self> {
         let handle = unsafe { abcd_file_sys::abc_file_open(data.as_ptr(), data.len()) };
         if handle.is_null() {
-            return Err(Error::Ffi(
-                "abc_file_open failed (invalid magic, corrupt data, or allocation failure)".into(),
-            ));
+            return Err(ffi_error("abc_file_open failed"));
         }
-        Ok(Self { handle, data })
+        Ok(Self {
+            handle,
+            data,
+            methods_by_kind: OnceLock::new(),
+            kind: OnceLock::new(),
+        })
     }
 
     /// Open an ABC file from a filesystem path.
@@ -121,6 +293,57 @@ impl File {
         Self::open(data)
     }
 
+    /// Open an ABC file by mapping it into memory instead of reading it.
+    ///
+    /// The parser reads the mapping in place, so the file is never copied:
+    /// pages are loaded as they are touched and, being backed by the file,
+    /// can be dropped again under memory pressure. This keeps resident
+    /// memory low for files of hundreds of megabytes.
+    ///
+    /// The file must not be modified or truncated while it is open. Writes
+    /// by another process show through the mapping, and reading past a
+    /// truncated end kills the process with `SIGBUS`.
+    #[cfg(feature = "mmap")]
+    pub fn open_mmap(path: &Path) -> Result<Self> {
+        let file = std::fs::File::open(path).map_err(|e| Error::Io(e.to_string()))?;
+        // SAFETY: the mapping is read-only; that the file stays unchanged
+        // while it is mapped is the caller's part, as documented above.
+        let map = unsafe { memmap2::Mmap::map(&file) }.map_err(|e| Error::Io(e.to_string()))?;
+        Self::from_data(Data::Mapped(map))
+    }
+
+    /// Like [`open`](Self::open), but refuse a file whose version this
+    /// build's instruction set does not cover; see
+    /// [`check_version`](Self::check_version).
+    pub fn open_checked(data: Vec<u8>) -> Result<Self> {
+        let file = Self::open(data)?;
+        file.check_version()?;
+        Ok(file)
+    }
+
+    /// Whether the linked ISA decodes this file's version.
+    ///
+    /// [`open`](Self::open) accepts any file it can parse, so that headers,
+    /// strings and classes of files from newer or older toolchains can
+    /// still be read. Their code may use opcodes that mean something else
+    /// here, though. Fails with [`Error::VersionMismatch`] outside the
+    /// supported range, and with [`Error::UnsupportedVersion`] for versions
+    /// the runtime lists as incompatible.
+    pub fn check_version(&self) -> Result<()> {
+        let file = self.version();
+        if !file.is_in_supported_range() {
+            return Err(Error::VersionMismatch {
+                file,
+                supported_min: abcd_isa::Version::min_supported(),
+                supported_max: abcd_isa::Version::current(),
+            });
+        }
+        if file.is_blocked() {
+            return Err(Error::UnsupportedVersion(file));
+        }
+        Ok(())
+    }
+
     /// Internal handle accessor for sub-modules.
     pub(crate) fn handle(&self) -> *mut abcd_file_sys::AbcFileHandle {
         self.handle
@@ -134,6 +357,15 @@ impl File {
         abcd_isa::Version::from(out)
     }
 
+    /// The instruction set this file's version allows; decode its code with
+    /// [`IsaProfile::decode`](abcd_isa::IsaProfile::decode) to reject
+    /// instructions the version predates. Fails with
+    /// [`Error::UnsupportedVersion`] for versions below the minimum.
+    pub fn isa_profile(&self) -> Result<abcd_isa::IsaProfile> {
+        let version = self.version();
+        abcd_isa::IsaProfile::for_version(version).ok_or(Error::UnsupportedVersion(version))
+    }
+
     pub fn file_size(&self) -> u32 {
         unsafe { abcd_file_sys::abc_file_size(self.handle) }
     }
@@ -186,31 +418,64 @@ impl File {
 
     /// Get the string at the given offset.
     ///
+    /// MUTF-8 only differs from UTF-8 for NUL and characters outside the
+    /// BMP, which are rare in real files, so the stored bytes are usually
+    /// valid UTF-8 and are copied straight out of the file buffer. Strings
+    /// that are not, or that hold 4-byte sequences (valid UTF-8, but not
+    /// MUTF-8), go through the C++ reader, as they always did.
+    ///
     /// Note: the C++ runtime returns length 0 for both empty strings and
     /// missing/invalid offsets, so this method cannot distinguish the two
     /// cases — both return `Ok(String::new())`.
     pub fn get_string(&self, offset: EntityId) -> Result<String> {
-        // First call with NULL buf to query the required length.
-        let len = unsafe {
-            abcd_file_sys::abc_file_get_string(self.handle, offset.0, std::ptr::null_mut(), 0)
-        };
-        // len == 0 is a valid empty string; the C++ side returns 0 for both
-        // empty strings and missing entries, but offset validity is checked
-        // upstream so we treat 0 as empty.
-        if len == 0 {
-            return Ok(String::new());
+        match self.string_bytes(offset) {
+            Ok(bytes) => match std::str::from_utf8(bytes) {
+                Ok(s) if is_mutf8_compatible(bytes) => Ok(s.to_owned()),
+                _ => self.get_string_ffi(offset),
+            },
+            Err(_) => self.get_string_ffi(offset),
         }
-        let mut buf = vec![0u8; len];
-        let written = unsafe {
-            abcd_file_sys::abc_file_get_string(
-                self.handle,
-                offset.0,
-                buf.as_mut_ptr() as *mut std::ffi::c_char,
-                buf.len(),
-            )
+    }
+
+    /// [`get_string`](Self::get_string) through the C++ reader only, for
+    /// benchmarking and checking the Rust path against it.
+    #[doc(hidden)]
+    pub fn get_string_ffi(&self, offset: EntityId) -> Result<String> {
+        // The C++ side returns length 0 for both empty strings and missing
+        // entries; both read as empty.
+        ffi_string(|buf, len| unsafe {
+            abcd_file_sys::abc_file_get_string(self.handle, offset.0, buf, len)
+        })
+    }
+
+    /// The string at `offset` as stored: MUTF-8 bytes without the length
+    /// prefix or the terminating NUL.
+    pub fn string_bytes(&self, offset: EntityId) -> Result<&[u8]> {
+        let start = offset.0 as usize;
+        let Some(rest) = self.data.get(start..).filter(|rest| !rest.is_empty()) else {
+            return Err(Error::OffsetOutOfBounds(start, self.data.len()));
         };
-        buf.truncate(written);
-        String::from_utf8(buf).map_err(|e| Error::Ffi(e.to_string()))
+        // The ULEB128 prefix holds the UTF-16 length and an ASCII flag.
+        let (_, prefix) =
+            util::leb128::decode_uleb128(rest, 0).map_err(|_| Error::InvalidLeb128(start))?;
+        let bytes = &rest[prefix..];
+        let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        Ok(&bytes[..end])
+    }
+
+    /// The string at `offset`, never failing on its encoding.
+    ///
+    /// Obfuscated bundles name things with byte sequences that are neither
+    /// UTF-8 nor MUTF-8, which [`get_string`](Self::get_string) rejects.
+    /// Here valid UTF-8 is borrowed from the file, MUTF-8 (surrogate pairs,
+    /// `C0 80` for NUL) is decoded, and anything else has its invalid
+    /// sequences replaced by U+FFFD. An offset with no string at all reads
+    /// as `<offset>`, so listings show where the name was missing.
+    pub fn get_string_lossy(&self, offset: EntityId) -> Cow<'_, str> {
+        match self.string_bytes(offset) {
+            Ok(bytes) => lossy_name(bytes),
+            Err(_) => Cow::Owned(format!("<{offset}>")),
+        }
     }
 
     pub fn string_utf16_len(&self, offset: EntityId) -> u32 {
@@ -299,7 +564,11 @@ impl File {
         &self.data
     }
 
-    /// Determine file type from raw bytes.
+    /// Determine file type from the header in raw bytes.
+    ///
+    /// Dynamic and static files share a header layout, and the bundled
+    /// runtime reports every well-formed one as dynamic; [`File::kind`]
+    /// also looks at what the file contains.
     pub fn file_type(data: &[u8]) -> FileType {
         // Clamp to i32::MAX — safe because abc_file_get_type only inspects the
         // first few header bytes (magic + version), well within i32 range.
@@ -312,6 +581,38 @@ impl File {
         }
     }
 
+    /// Whether this is a dynamic (EcmaScript/ArkTS) or static
+    /// (PandaAssembly) file.
+    ///
+    /// Besides the header, this checks the languages the local classes
+    /// record: static files record PandaAssembly, dynamic ones a script
+    /// language or nothing. The file is static when more classes record
+    /// PandaAssembly than anything else, so one odd class, first or not,
+    /// does not decide it.
+    pub fn kind(&self) -> FileType {
+        *self.kind.get_or_init(|| {
+            if Self::file_type(&self.data) == FileType::Static {
+                return FileType::Static;
+            }
+            let (mut panda, mut other) = (0usize, 0usize);
+            for off in self.class_offsets() {
+                if self.is_external(off) {
+                    continue;
+                }
+                match self.class(off).ok().and_then(|c| c.source_lang()) {
+                    Some(SourceLang::PandaAssembly) => panda += 1,
+                    Some(_) => other += 1,
+                    None => {}
+                }
+            }
+            if panda > other {
+                FileType::Static
+            } else {
+                FileType::Dynamic
+            }
+        })
+    }
+
     // --- Index headers ---
 
     pub fn num_index_headers(&self) -> u32 {
@@ -377,6 +678,21 @@ impl File {
             .collect()
     }
 
+    /// Resolve a literal array reference as stored in a class field or literal.
+    ///
+    /// Legacy files (see [`version::uses_literal_array_index`]) store an index
+    /// into the header table; newer files store the offset itself.
+    pub fn resolve_literal_array_id(&self, raw: u32) -> Result<EntityId> {
+        if !version::uses_literal_array_index(&self.version()) {
+            return Ok(EntityId(raw));
+        }
+        self.literal_array_offset(raw)
+            .ok_or(Error::LiteralIndexOutOfRange(
+                raw,
+                self.num_literal_arrays(),
+            ))
+    }
+
     /// Get the offset of a single literal array by index. Returns `None` if out of bounds.
     pub fn literal_array_offset(&self, idx: u32) -> Option<EntityId> {
         if idx >= self.num_literal_arrays() {
@@ -387,6 +703,40 @@ impl File {
         }))
     }
 
+    /// Methods of local classes whose access flags carry `kind`, in file
+    /// order.
+    ///
+    /// The first call reads the access flags of every method once and keeps
+    /// the result, so asking for each kind in turn costs a single pass.
+    pub fn methods_by_kind(&self, kind: FunctionKind) -> Vec<EntityId> {
+        self.methods_by_kind
+            .get_or_init(|| self.index_methods_by_kind())
+            .get(&kind)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn index_methods_by_kind(&self) -> HashMap<FunctionKind, Vec<EntityId>> {
+        let mut by_kind: HashMap<FunctionKind, Vec<EntityId>> = HashMap::new();
+        for class_off in self.class_offsets() {
+            if self.is_external(class_off) {
+                continue;
+            }
+            let Ok(class) = self.class(class_off) else {
+                continue;
+            };
+            for method_off in class.method_offsets() {
+                if let Ok(method) = self.method(method_off) {
+                    by_kind
+                        .entry(method.function_kind())
+                        .or_default()
+                        .push(method_off);
+                }
+            }
+        }
+        by_kind
+    }
+
     // --- Accessor factory methods ---
 
     pub fn class(&self, offset: EntityId) -> Result<class::Class<'_>> {
@@ -417,10 +767,36 @@ impl File {
         literal::Literal::open(self, literal_data_off)
     }
 
+    /// Literal arrays as the instructions of the method at `method_off`
+    /// name them.
+    ///
+    /// An ID such as `createarraywithbuffer`'s indexes the method's own
+    /// index region, and merged files carry one region per input, so the
+    /// same ID names different arrays in different methods. Looking it up
+    /// in the header's literal array table instead gives the wrong array.
+    /// The region is the one [`File::index`] opens for the method.
+    pub fn literal_for(&self, method_off: EntityId) -> Result<literal::MethodLiterals<'_>> {
+        // Arrays are only ever read by offset, so the accessor is opened
+        // at the method rather than at the header's table.
+        let literal = self.literal(method_off)?;
+        Ok(literal::MethodLiterals::new(
+            literal,
+            self.index(method_off)?,
+        ))
+    }
+
+    #[cfg(feature = "module")]
     pub fn module(&self, offset: EntityId) -> Result<module::Module<'_>> {
         module::Module::open(self, offset)
     }
 
+    /// Open a module from the raw value of a record's `moduleRecordIdx` field.
+    #[cfg(feature = "module")]
+    pub fn module_record(&self, module_record_idx: u32) -> Result<module::Module<'_>> {
+        module::Module::open_record(self, module_record_idx)
+    }
+
+    #[cfg(feature = "debug-info")]
     pub fn debug_info(&self) -> Result<debug::DebugInfo<'_>> {
         debug::DebugInfo::open(self)
     }
@@ -440,29 +816,24 @@ impl File {
 
     /// Get a method's name as a string without opening a Method accessor.
     pub fn method_name(&self, method_off: EntityId) -> Result<String> {
-        // Query length first.
-        let len = unsafe {
-            abcd_file_sys::abc_method_get_name_static(
-                self.handle,
-                method_off.0,
-                std::ptr::null_mut(),
-                0,
-            )
-        };
-        if len == 0 {
-            return Ok(String::new());
+        ffi_string(|buf, len| unsafe {
+            abcd_file_sys::abc_method_get_name_static(self.handle, method_off.0, buf, len)
+        })
+    }
+
+    /// A method's name as stored; see [`string_bytes`](Self::string_bytes).
+    pub fn method_name_bytes(&self, method_off: EntityId) -> Result<&[u8]> {
+        self.string_bytes(self.method_name_off(method_off))
+    }
+
+    /// A method's name, never failing on its encoding; see
+    /// [`get_string_lossy`](Self::get_string_lossy). A method whose name
+    /// cannot be found reads as `<method_off>`.
+    pub fn method_name_lossy(&self, method_off: EntityId) -> Cow<'_, str> {
+        match self.method_name_bytes(method_off) {
+            Ok(bytes) => lossy_name(bytes),
+            Err(_) => Cow::Owned(format!("<{method_off}>")),
         }
-        let mut buf = vec![0u8; len];
-        let written = unsafe {
-            abcd_file_sys::abc_method_get_name_static(
-                self.handle,
-                method_off.0,
-                buf.as_mut_ptr() as *mut std::ffi::c_char,
-                buf.len(),
-            )
-        };
-        buf.truncate(written);
-        String::from_utf8(buf).map_err(|e| Error::Ffi(e.to_string()))
     }
 
     /// Get a method's class ID without opening a Method accessor.
@@ -1,11 +1,13 @@
 //! Literal data accessor.
 
 use crate::{EntityId, File, error::Error};
+use abcd_isa::Version;
+use std::collections::BTreeMap;
 use std::ffi::CStr;
 use std::fmt;
 
 /// Literal tag values from the ABC file format.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 #[repr(u8)]
 pub enum LiteralTag {
     TagValue = 0x00,
@@ -41,6 +43,46 @@ pub enum LiteralTag {
 }
 
 impl LiteralTag {
+    /// Every tag known to the current ABC version, in tag-byte order.
+    pub const ALL: [LiteralTag; 30] = [
+        Self::TagValue,
+        Self::Bool,
+        Self::Integer,
+        Self::Float,
+        Self::Double,
+        Self::String,
+        Self::Method,
+        Self::GeneratorMethod,
+        Self::Accessor,
+        Self::MethodAffiliate,
+        Self::ArrayU1,
+        Self::ArrayU8,
+        Self::ArrayI8,
+        Self::ArrayU16,
+        Self::ArrayI16,
+        Self::ArrayU32,
+        Self::ArrayI32,
+        Self::ArrayU64,
+        Self::ArrayI64,
+        Self::ArrayF32,
+        Self::ArrayF64,
+        Self::ArrayString,
+        Self::AsyncGeneratorMethod,
+        Self::LiteralBufferIndex,
+        Self::LiteralArray,
+        Self::BuiltinTypeIndex,
+        Self::Getter,
+        Self::Setter,
+        Self::EtsImplements,
+        Self::NullValue,
+    ];
+
+    /// True for the `ARRAY_*` tags used by statically-typed literal arrays,
+    /// where a single tag covers the whole (untagged) payload.
+    pub fn is_typed_array(self) -> bool {
+        (Self::ArrayU1 as u8..=Self::ArrayString as u8).contains(&(self as u8))
+    }
+
     pub fn from_u8(val: u8) -> Option<Self> {
         match val {
             0x00 => Some(Self::TagValue),
@@ -87,9 +129,13 @@ impl fmt::Display for LiteralTag {
 /// A literal value from a literal array.
 #[derive(Debug, Clone)]
 pub struct LiteralVal {
+    /// Decoded tag, or `None` if `raw_tag` is not known to this crate.
     pub tag: Option<LiteralTag>,
+    /// The tag byte exactly as stored in the file.
+    pub raw_tag: u8,
     pub u64_val: u64,
-    /// String data (decoded from MUTF-8). Present when tag is STRING or ARRAY_STRING.
+    /// String data, decoded from MUTF-8 as [`File::get_string_lossy`]
+    /// decodes it. Present when the C++ reader hands the string over inline.
     pub str_data: Option<String>,
     /// UTF-16 length of the string. 0 for non-string tags.
     pub str_utf16_len: u32,
@@ -153,28 +199,69 @@ impl LiteralVal {
         i64::try_from(self.u64_val).ok()
     }
 
+    /// Like [`to_value`](Self::to_value), but resolves literal array
+    /// references against `file`.
+    ///
+    /// In legacy files [`LiteralValue::LiteralArray`] holds a header index
+    /// rather than an offset, and nested buffers are referenced through
+    /// [`LiteralValue::LiteralBufferIndex`]; both come back as
+    /// `LiteralArray(offset)`. References that do not resolve are returned
+    /// unchanged.
+    pub fn resolve_value(&self, file: &File) -> LiteralValue {
+        match self.to_value() {
+            LiteralValue::LiteralArray(id) => file
+                .resolve_literal_array_id(id.0)
+                .map_or(LiteralValue::LiteralArray(id), LiteralValue::LiteralArray),
+            LiteralValue::LiteralBufferIndex(idx) => file.literal_array_offset(idx).map_or(
+                LiteralValue::LiteralBufferIndex(idx),
+                LiteralValue::LiteralArray,
+            ),
+            other => other,
+        }
+    }
+
     /// Convert to a typed `LiteralValue` using the tag.
+    ///
+    /// Tags this crate does not know are returned as [`LiteralValue::Unknown`]
+    /// with the raw tag byte, never silently folded into another variant.
     pub fn to_value(&self) -> LiteralValue {
-        match self.tag {
-            Some(LiteralTag::Bool) => LiteralValue::Bool(self.as_bool()),
-            Some(LiteralTag::Integer) => LiteralValue::Integer(self.u64_val as i64),
-            Some(LiteralTag::Float) => LiteralValue::Float(self.as_f32()),
-            Some(LiteralTag::Double) => LiteralValue::Double(self.as_f64()),
-            Some(LiteralTag::String | LiteralTag::ArrayString) => {
-                LiteralValue::String(EntityId(self.as_u32()))
-            }
-            Some(
-                LiteralTag::Method
-                | LiteralTag::GeneratorMethod
-                | LiteralTag::AsyncGeneratorMethod
-                | LiteralTag::Getter
-                | LiteralTag::Setter
-                | LiteralTag::Accessor,
-            ) => LiteralValue::Method(EntityId(self.as_u32())),
-            Some(LiteralTag::MethodAffiliate) => LiteralValue::MethodAffiliate(self.as_u16()),
-            Some(LiteralTag::NullValue) => LiteralValue::Null,
-            Some(LiteralTag::TagValue) => LiteralValue::TagValue(self.as_u32()),
-            _ => LiteralValue::TagValue(self.as_u32()),
+        let Some(tag) = self.tag else {
+            return LiteralValue::Unknown {
+                tag: self.raw_tag,
+                raw: self.u64_val,
+            };
+        };
+        match tag {
+            LiteralTag::TagValue => LiteralValue::TagValue(self.as_u32()),
+            LiteralTag::Bool => LiteralValue::Bool(self.as_bool()),
+            LiteralTag::Integer => LiteralValue::Integer(self.u64_val as i64),
+            LiteralTag::Float => LiteralValue::Float(self.as_f32()),
+            LiteralTag::Double => LiteralValue::Double(self.as_f64()),
+            LiteralTag::String => LiteralValue::String(EntityId(self.as_u32())),
+            LiteralTag::Method
+            | LiteralTag::GeneratorMethod
+            | LiteralTag::AsyncGeneratorMethod
+            | LiteralTag::Getter
+            | LiteralTag::Setter => LiteralValue::Method(EntityId(self.as_u32())),
+            LiteralTag::Accessor => LiteralValue::Accessor(self.as_u8()),
+            LiteralTag::MethodAffiliate => LiteralValue::MethodAffiliate(self.as_u16()),
+            LiteralTag::LiteralBufferIndex => LiteralValue::LiteralBufferIndex(self.as_u32()),
+            LiteralTag::LiteralArray => LiteralValue::LiteralArray(EntityId(self.as_u32())),
+            LiteralTag::BuiltinTypeIndex => LiteralValue::BuiltinTypeIndex(self.as_u8()),
+            LiteralTag::EtsImplements => LiteralValue::EtsImplements(EntityId(self.as_u32())),
+            LiteralTag::NullValue => LiteralValue::Null,
+            LiteralTag::ArrayU1
+            | LiteralTag::ArrayU8
+            | LiteralTag::ArrayI8
+            | LiteralTag::ArrayU16
+            | LiteralTag::ArrayI16
+            | LiteralTag::ArrayU32
+            | LiteralTag::ArrayI32
+            | LiteralTag::ArrayU64
+            | LiteralTag::ArrayI64
+            | LiteralTag::ArrayF32
+            | LiteralTag::ArrayF64
+            | LiteralTag::ArrayString => LiteralValue::TypedArray(EntityId(self.as_u32())),
         }
     }
 }
@@ -187,10 +274,59 @@ pub enum LiteralValue {
     Float(f32),
     Double(f64),
     String(EntityId),
+    /// Method, generator, async generator, getter or setter.
     Method(EntityId),
+    /// Accessor kind byte.
+    Accessor(u8),
     Null,
     MethodAffiliate(u16),
     TagValue(u32),
+    /// Index of another literal buffer (sendable class layouts).
+    LiteralBufferIndex(u32),
+    /// Offset of a nested literal array (TS type literals).
+    LiteralArray(EntityId),
+    /// Index into the builtin type table.
+    BuiltinTypeIndex(u8),
+    /// Offset of an ETS `implements` record.
+    EtsImplements(EntityId),
+    /// Offset of the untagged payload of an `ARRAY_*` literal.
+    TypedArray(EntityId),
+    /// A tag this crate does not know about, with the raw payload bits.
+    Unknown {
+        tag: u8,
+        raw: u64,
+    },
+}
+
+/// Histogram of literal tags seen while walking a file's literal arrays.
+///
+/// Produced by [`Literal::tag_coverage`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TagCoverage {
+    /// Number of values seen for each raw tag byte.
+    pub counts: BTreeMap<u8, usize>,
+}
+
+impl TagCoverage {
+    /// Known tags and their counts.
+    pub fn known(&self) -> impl Iterator<Item = (LiteralTag, usize)> + '_ {
+        self.counts
+            .iter()
+            .filter_map(|(&t, &n)| LiteralTag::from_u8(t).map(|tag| (tag, n)))
+    }
+
+    /// Raw tag bytes not known to this crate and their counts.
+    pub fn unknown(&self) -> impl Iterator<Item = (u8, usize)> + '_ {
+        self.counts
+            .iter()
+            .filter(|&(&t, _)| LiteralTag::from_u8(t).is_none())
+            .map(|(&t, &n)| (t, n))
+    }
+
+    /// True if every tag seen is known to this crate.
+    pub fn is_complete(&self) -> bool {
+        self.unknown().next().is_none()
+    }
 }
 
 /// A parsed literal array (collection of tag-value pairs).
@@ -199,6 +335,148 @@ pub struct LiteralArray {
     pub entries: Vec<(LiteralTag, LiteralValue)>,
 }
 
+/// One key/value pair of an object or class literal; see
+/// [`LiteralArray::members`].
+#[derive(Debug, Clone)]
+pub struct LiteralMember {
+    pub key: (LiteralTag, LiteralValue),
+    pub value: (LiteralTag, LiteralValue),
+    /// Parameter count of a method value, from the `MethodAffiliate`
+    /// after it.
+    pub affiliate: Option<u16>,
+}
+
+impl LiteralArray {
+    /// Read the tagged literal array at `array_off`, with references to
+    /// nested arrays resolved as by [`LiteralVal::resolve_value`].
+    pub fn read(file: &File, array_off: EntityId) -> Result<LiteralArray, Error> {
+        // Reading by offset never touches the header's table, which files
+        // from API 12 on leave empty.
+        let literal = file.literal(array_off)?;
+        let entries = literal
+            .enumerate_vals(array_off)
+            .iter()
+            .map(|v| (v.tag.unwrap_or(LiteralTag::TagValue), v.resolve_value(file)))
+            .collect();
+        Ok(LiteralArray { entries })
+    }
+
+    /// The entries as the key/value pairs of an object or class literal
+    /// in a file of `version`.
+    ///
+    /// From API 11 on (see [`crate::version::has_method_affiliates`]),
+    /// each method value is followed by a `MethodAffiliate` that is not a
+    /// pair of its own; it is read into [`LiteralMember::affiliate`]. Older
+    /// files pair every entry. A last entry without a partner, such as a
+    /// class literal's static method count, is left out.
+    pub fn members(&self, version: Version) -> Vec<LiteralMember> {
+        let affiliates = crate::version::has_method_affiliates(&version);
+        let mut members = Vec::new();
+        let mut i = 0;
+        while i + 1 < self.entries.len() {
+            let mut member = LiteralMember {
+                key: self.entries[i].clone(),
+                value: self.entries[i + 1].clone(),
+                affiliate: None,
+            };
+            i += 2;
+            match self.entries.get(i) {
+                Some(&(LiteralTag::MethodAffiliate, LiteralValue::MethodAffiliate(n)))
+                    if affiliates =>
+                {
+                    member.affiliate = Some(n);
+                    i += 1;
+                }
+                _ => {}
+            }
+            members.push(member);
+        }
+        members
+    }
+
+    /// The array as JSON, for reviewing object literals and authoring
+    /// fixtures; `builder::LiteralArrayBuilder::from_json`, with the
+    /// `builder` feature, reads it back.
+    ///
+    /// The array is a JSON array with one `{"tag": ..., "value": ...}`
+    /// object per entry. `tag` is the [`LiteralTag`] name (`"Integer"`,
+    /// `"MethodAffiliate"`, ...), or the raw byte as a number for tags this
+    /// crate does not know. `value` depends on the tag:
+    ///
+    /// | tag | value |
+    /// |-----|-------|
+    /// | `Bool` | `true` / `false` |
+    /// | `Integer` | signed 32-bit integer |
+    /// | `Float`, `Double` | number; `"NaN"`, `"Infinity"` or `"-Infinity"` when not finite |
+    /// | `String` | the string itself |
+    /// | `Method`, `GeneratorMethod`, `AsyncGeneratorMethod`, `Getter`, `Setter` | `"Class.method"` |
+    /// | `LiteralArray` | the nested array, in this same format |
+    /// | `NullValue` | `null` |
+    /// | `TagValue`, `Accessor`, `MethodAffiliate`, `BuiltinTypeIndex`, `LiteralBufferIndex` | the number stored |
+    /// | `EtsImplements`, `Array*`, and nested arrays that do not resolve or refer back to an enclosing one | file offset, not expanded |
+    /// | unknown | the raw payload bits |
+    pub fn to_json(&self, file: &File) -> serde_json::Value {
+        self.to_json_within(file, &mut Vec::new())
+    }
+
+    /// [`to_json`](Self::to_json), with `open` the arrays being expanded
+    /// around this one.
+    fn to_json_within(&self, file: &File, open: &mut Vec<EntityId>) -> serde_json::Value {
+        use serde_json::{Value, json};
+
+        let entries = self.entries.iter().map(|(tag, value)| {
+            let value = match value {
+                LiteralValue::Unknown { tag, raw } => return json!({ "tag": tag, "value": raw }),
+                LiteralValue::Bool(b) => json!(b),
+                LiteralValue::Integer(v) => json!(*v as i32),
+                LiteralValue::Float(f) => float_json(f64::from(*f)),
+                LiteralValue::Double(f) => float_json(*f),
+                LiteralValue::String(off) => json!(file.get_string_lossy(*off)),
+                LiteralValue::Method(off) => json!(qualified_method_name(file, *off)),
+                LiteralValue::Accessor(v) | LiteralValue::BuiltinTypeIndex(v) => json!(v),
+                LiteralValue::MethodAffiliate(v) => json!(v),
+                LiteralValue::TagValue(v) | LiteralValue::LiteralBufferIndex(v) => json!(v),
+                LiteralValue::Null => Value::Null,
+                LiteralValue::LiteralArray(off) => match LiteralArray::read(file, *off) {
+                    Ok(nested) if !open.contains(off) => {
+                        open.push(*off);
+                        let value = nested.to_json_within(file, open);
+                        open.pop();
+                        value
+                    }
+                    _ => json!(off.0),
+                },
+                LiteralValue::EtsImplements(off) | LiteralValue::TypedArray(off) => json!(off.0),
+            };
+            json!({ "tag": tag.to_string(), "value": value })
+        });
+        Value::Array(entries.collect())
+    }
+}
+
+/// A float as JSON, which has no spelling for NaN and the infinities.
+fn float_json(f: f64) -> serde_json::Value {
+    if f.is_nan() {
+        "NaN".into()
+    } else if f.is_infinite() {
+        if f > 0.0 { "Infinity" } else { "-Infinity" }.into()
+    } else {
+        f.into()
+    }
+}
+
+/// `Class.method` for the method at `off`.
+fn qualified_method_name(file: &File, off: EntityId) -> String {
+    match file.method(off) {
+        Ok(method) => format!(
+            "{}.{}",
+            file.get_string_lossy(method.class_id()),
+            file.get_string_lossy(method.name_off())
+        ),
+        Err(_) => format!("<{off}>"),
+    }
+}
+
 /// A literal data accessor. Borrows from a [`File`].
 pub struct Literal<'f> {
     handle: *mut abcd_file_sys::AbcLiteralAccessor,
@@ -216,13 +494,15 @@ unsafe extern "C" fn literal_val_cb(
         let str_data = if !lv.str_data.is_null() {
             // SAFETY: The C++ side returns a null-terminated MUTF-8 string.
             let cstr = CStr::from_ptr(lv.str_data as *const std::ffi::c_char);
-            let bytes = cstr.to_bytes();
-            String::from_utf8_lossy(bytes).into_owned().into()
+            // Decoded like `File::get_string_lossy`, so surrogate pairs
+            // come out as the characters they encode.
+            Some(crate::lossy_name(cstr.to_bytes()).into_owned())
         } else {
             None
         };
         v.push(LiteralVal {
             tag: LiteralTag::from_u8(lv.tag),
+            raw_tag: lv.tag,
             u64_val: lv.__bindgen_anon_1.u64_val,
             str_data,
             str_utf16_len: lv.str_utf16_len,
@@ -234,7 +514,7 @@ impl<'f> Literal<'f> {
     pub(crate) fn open(file: &'f File, literal_data_off: EntityId) -> Result<Self, Error> {
         let handle = unsafe { abcd_file_sys::abc_literal_open(file.handle(), literal_data_off.0) };
         if handle.is_null() {
-            return Err(Error::Ffi(format!(
+            return Err(crate::ffi_error(format!(
                 "abc_literal_open failed at offset {literal_data_off:?}"
             )));
         }
@@ -297,6 +577,20 @@ impl<'f> Literal<'f> {
         if idx == u32::MAX { None } else { Some(idx) }
     }
 
+    /// Walk every literal array and count the tags used.
+    ///
+    /// Useful for spotting files produced by a newer toolchain: any tag this
+    /// crate cannot interpret shows up in [`TagCoverage::unknown`].
+    pub fn tag_coverage(&self) -> TagCoverage {
+        let mut coverage = TagCoverage::default();
+        for index in 0..self.count() {
+            for val in self.enumerate_vals_by_index(index) {
+                *coverage.counts.entry(val.raw_tag).or_default() += 1;
+            }
+        }
+        coverage
+    }
+
     pub fn data_id(&self) -> EntityId {
         EntityId(unsafe { abcd_file_sys::abc_literal_get_data_id(self.handle) })
     }
@@ -306,6 +600,45 @@ impl<'f> Literal<'f> {
     }
 }
 
+/// The literal arrays one method's instructions name, from
+/// [`File::literal_for`].
+#[derive(Debug)]
+pub struct MethodLiterals<'f> {
+    literal: Literal<'f>,
+    index: crate::index::Index<'f>,
+}
+
+impl<'f> MethodLiterals<'f> {
+    pub(crate) fn new(literal: Literal<'f>, index: crate::index::Index<'f>) -> Self {
+        Self { literal, index }
+    }
+
+    /// The method whose region IDs are looked up in.
+    pub fn method_off(&self) -> EntityId {
+        self.index.offset()
+    }
+
+    /// Offset of the array an instruction ID names, or `None` if the ID is
+    /// outside the method's region.
+    ///
+    /// The region is the index header the method's access flags name, as
+    /// the runtime picks it, not whichever header's range holds the method.
+    pub fn array_id(&self, id: u16) -> Option<EntityId> {
+        self.index.offset_by_id(id)
+    }
+
+    /// Values of the array an instruction ID names.
+    pub fn enumerate_vals(&self, id: u16) -> Option<Vec<LiteralVal>> {
+        Some(self.literal.enumerate_vals(self.array_id(id)?))
+    }
+
+    /// The accessor the arrays are read with, for arrays already known by
+    /// offset, such as nested ones.
+    pub fn literal(&self) -> &Literal<'f> {
+        &self.literal
+    }
+}
+
 impl Drop for Literal<'_> {
     fn drop(&mut self) {
         if !self.handle.is_null() {
@@ -321,3 +654,70 @@ impl std::fmt::Debug for Literal<'_> {
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn val(raw_tag: u8, u64_val: u64) -> LiteralVal {
+        LiteralVal {
+            tag: LiteralTag::from_u8(raw_tag),
+            raw_tag,
+            u64_val,
+            str_data: None,
+            str_utf16_len: 0,
+        }
+    }
+
+    #[test]
+    fn all_tags_roundtrip() {
+        for tag in LiteralTag::ALL {
+            assert_eq!(LiteralTag::from_u8(tag as u8), Some(tag));
+        }
+        let known = (0..=u8::MAX).filter_map(LiteralTag::from_u8).count();
+        assert_eq!(known, LiteralTag::ALL.len());
+    }
+
+    #[test]
+    fn newer_tags_are_typed() {
+        assert!(matches!(
+            val(0x17, 3).to_value(),
+            LiteralValue::LiteralBufferIndex(3)
+        ));
+        assert!(matches!(
+            val(0x18, 0x40).to_value(),
+            LiteralValue::LiteralArray(EntityId(0x40))
+        ));
+        assert!(matches!(
+            val(0x19, 7).to_value(),
+            LiteralValue::BuiltinTypeIndex(7)
+        ));
+        assert!(matches!(
+            val(0x1c, 0x80).to_value(),
+            LiteralValue::EtsImplements(EntityId(0x80))
+        ));
+        assert!(matches!(val(0x08, 1).to_value(), LiteralValue::Accessor(1)));
+        assert!(matches!(
+            val(0x15, 0x100).to_value(),
+            LiteralValue::TypedArray(EntityId(0x100))
+        ));
+    }
+
+    #[test]
+    fn unknown_tag_is_surfaced() {
+        assert!(matches!(
+            val(0x42, 9).to_value(),
+            LiteralValue::Unknown { tag: 0x42, raw: 9 }
+        ));
+
+        let mut coverage = TagCoverage::default();
+        coverage.counts.insert(0x05, 2);
+        coverage.counts.insert(0x42, 1);
+        assert_eq!(
+            coverage.known().collect::<Vec<_>>(),
+            [(LiteralTag::String, 2)]
+        );
+        assert_eq!(coverage.unknown().collect::<Vec<_>>(), [(0x42, 1)]);
+        assert!(!coverage.is_complete());
+    }
+}
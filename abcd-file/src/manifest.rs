@@ -0,0 +1,436 @@
+//! Per-entity content hashes, for telling which parts of a file changed.
+//!
+//! A [`Manifest`] holds a SHA-256 for every class, method and literal
+//! array of a file. Hashes cover what an entity means rather than where it
+//! sits: an instruction that names a string is hashed with the string's
+//! text, not with the index it happens to use, and a literal naming a
+//! method is hashed with the method's qualified name. Adding a method to
+//! one class therefore leaves the hashes of every other method alone, and
+//! comparing the manifests of two builds points at exactly the entities
+//! that were edited.
+//!
+//! ```no_run
+//! let abc = abcd_file::File::open_path("modules.abc".as_ref()).unwrap();
+//! let manifest = abc.manifest();
+//! println!("{}", serde_json::to_string_pretty(&manifest).unwrap());
+//! ```
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use abcd_isa::{IdKind, OpcodeInfo, OperandKind, PrefixGroup, opcode_table};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::literal::{Literal, LiteralValue};
+use crate::{EntityId, File};
+
+/// Nested literal arrays deeper than this are hashed by offset.
+const MAX_LITERAL_DEPTH: u32 = 8;
+
+/// Content hashes of one file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// SHA-256 of the file's bytes.
+    pub file: String,
+    pub version: String,
+    /// Hash of every string the classes, code and literal arrays use,
+    /// sorted.
+    pub strings: String,
+    /// Local classes in file order.
+    pub classes: Vec<ClassHash>,
+    /// Literal arrays in header order.
+    pub literal_arrays: Vec<LiteralArrayHash>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClassHash {
+    pub name: String,
+    pub offset: u32,
+    /// Covers the class header, its fields and the hashes of its methods.
+    pub hash: String,
+    pub methods: Vec<MethodHash>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MethodHash {
+    pub name: String,
+    pub offset: u32,
+    /// Covers the name, access flags and code, try blocks included.
+    pub hash: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LiteralArrayHash {
+    /// Position in the header's literal array index.
+    pub index: u32,
+    pub offset: u32,
+    pub hash: String,
+}
+
+/// How a method differs between two builds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Change {
+    Added,
+    Removed,
+    /// In both builds, with a different hash.
+    Modified,
+}
+
+/// One entry of [`Manifest::diff_methods`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MethodDiff {
+    pub class: String,
+    pub name: String,
+    pub change: Change,
+    /// Offset in the old file; `None` for added methods.
+    pub old_offset: Option<u32>,
+    /// Offset in the new file; `None` for removed methods.
+    pub new_offset: Option<u32>,
+}
+
+impl Manifest {
+    /// Every method, as `(class name, method)`, in file order.
+    pub fn methods(&self) -> impl Iterator<Item = (&str, &MethodHash)> {
+        self.classes
+            .iter()
+            .flat_map(|c| c.methods.iter().map(move |m| (c.name.as_str(), m)))
+    }
+
+    /// Methods that differ from `self` to `new`: modified and added ones in
+    /// `new`'s order, then removed ones in `self`'s.
+    ///
+    /// Methods are matched by class and method name. Several methods of
+    /// one class sharing a name are paired in file order.
+    pub fn diff_methods(&self, new: &Manifest) -> Vec<MethodDiff> {
+        let mut old: HashMap<(&str, &str), Vec<&MethodHash>> = HashMap::new();
+        for (class, m) in self.methods() {
+            old.entry((class, m.name.as_str())).or_default().push(m);
+        }
+        for same_name in old.values_mut() {
+            same_name.reverse();
+        }
+
+        let mut diffs = Vec::new();
+        for (class, m) in new.methods() {
+            let (change, old_offset) =
+                match old.get_mut(&(class, m.name.as_str())).and_then(Vec::pop) {
+                    Some(prev) if prev.hash == m.hash => continue,
+                    Some(prev) => (Change::Modified, Some(prev.offset)),
+                    None => (Change::Added, None),
+                };
+            diffs.push(MethodDiff {
+                class: class.to_string(),
+                name: m.name.clone(),
+                change,
+                old_offset,
+                new_offset: Some(m.offset),
+            });
+        }
+        for (class, m) in self.methods() {
+            let unmatched = old
+                .get(&(class, m.name.as_str()))
+                .is_some_and(|left| left.iter().any(|l| l.offset == m.offset));
+            if unmatched {
+                diffs.push(MethodDiff {
+                    class: class.to_string(),
+                    name: m.name.clone(),
+                    change: Change::Removed,
+                    old_offset: Some(m.offset),
+                    new_offset: None,
+                });
+            }
+        }
+        diffs
+    }
+}
+
+impl File {
+    /// Content hashes of this file's entities; see [`crate::manifest`].
+    pub fn manifest(&self) -> Manifest {
+        let mut h = Hashes::new(self);
+        let literal_arrays = self
+            .literal_array_offsets()
+            .into_iter()
+            .enumerate()
+            .map(|(index, off)| LiteralArrayHash {
+                index: index as u32,
+                offset: off.0,
+                hash: hex(&h.literal_array(off, 0)),
+            })
+            .collect();
+        let classes = self
+            .class_offsets()
+            .into_iter()
+            .filter(|&off| !self.is_external(off))
+            .map(|off| h.class(off))
+            .collect();
+
+        let mut strings = Sha256::new();
+        for s in &h.strings {
+            put_str(&mut strings, s);
+        }
+        Manifest {
+            file: hex(&Sha256::digest(self.raw_data()).into()),
+            version: self.version().to_string(),
+            strings: hex(&strings.finalize().into()),
+            classes,
+            literal_arrays,
+        }
+    }
+}
+
+/// State shared while hashing one file.
+struct Hashes<'f> {
+    file: &'f File,
+    literal: Option<Literal<'f>>,
+    literal_offsets: HashSet<u32>,
+    local_methods: HashSet<u32>,
+    opcodes: HashMap<u16, &'static OpcodeInfo>,
+    /// Hashes of literal arrays already visited.
+    literal_hashes: HashMap<u32, [u8; 32]>,
+    strings: BTreeSet<String>,
+}
+
+impl<'f> Hashes<'f> {
+    fn new(file: &'f File) -> Self {
+        let mut local_methods = HashSet::new();
+        for class_off in file.class_offsets() {
+            if file.is_external(class_off) {
+                continue;
+            }
+            if let Ok(class) = file.class(class_off) {
+                local_methods.extend(class.method_offsets().into_iter().map(|m| m.0));
+            }
+        }
+        Self {
+            file,
+            literal: file.literal(EntityId(file.literal_array_idx_off())).ok(),
+            literal_offsets: file
+                .literal_array_offsets()
+                .into_iter()
+                .map(|o| o.0)
+                .collect(),
+            local_methods,
+            opcodes: opcode_table().iter().map(|r| (r.opcode, r)).collect(),
+            literal_hashes: HashMap::new(),
+            strings: BTreeSet::new(),
+        }
+    }
+
+    fn string(&mut self, off: EntityId) -> Option<String> {
+        let s = self.file.get_string(off).ok()?;
+        self.strings.insert(s.clone());
+        Some(s)
+    }
+
+    /// `Class.method`, naming a method independently of its offset.
+    fn method_name(&mut self, off: EntityId) -> String {
+        let class = self
+            .string(self.file.method_class_id(off))
+            .unwrap_or_default();
+        let name = self.file.method_name(off).unwrap_or_default();
+        self.strings.insert(name.clone());
+        format!("{class}.{name}")
+    }
+
+    fn class(&mut self, off: EntityId) -> ClassHash {
+        let name = self.string(off).unwrap_or_else(|| format!("<{off}>"));
+        let mut hasher = Sha256::new();
+        put_str(&mut hasher, &name);
+        let Ok(class) = self.file.class(off) else {
+            return ClassHash {
+                name,
+                offset: off.0,
+                hash: hex(&hasher.finalize().into()),
+                methods: Vec::new(),
+            };
+        };
+        hasher.update(class.access_flags().to_le_bytes());
+        let super_off = class.super_class_off();
+        let super_name = match super_off.0 {
+            0 => String::new(),
+            _ => self.string(super_off).unwrap_or_default(),
+        };
+        put_str(&mut hasher, &super_name);
+        let source_file = class
+            .source_file_off()
+            .and_then(|f| self.string(f))
+            .unwrap_or_default();
+        put_str(&mut hasher, &source_file);
+
+        for field_off in class.field_offsets() {
+            let Ok(field) = self.file.field(field_off) else {
+                continue;
+            };
+            let field_name = self.string(field.name_off()).unwrap_or_default();
+            put_str(&mut hasher, &field_name);
+            hasher.update(field.type_id().to_le_bytes());
+            hasher.update(field.access_flags().to_le_bytes());
+            // Integer fields such as `moduleRecordIdx` hold literal array
+            // offsets, which move between builds.
+            match field.value_i32() {
+                Some(v) if self.literal_offsets.contains(&(v as u32)) => {
+                    hasher.update(self.literal_array(EntityId(v as u32), 0));
+                }
+                Some(v) => hasher.update(v.to_le_bytes()),
+                None => {}
+            }
+            if let Some(v) = field.value_i64() {
+                hasher.update(v.to_le_bytes());
+            }
+        }
+
+        let methods: Vec<MethodHash> = class
+            .method_offsets()
+            .into_iter()
+            .map(|m| self.method(m))
+            .collect();
+        for m in &methods {
+            put_str(&mut hasher, &m.hash);
+        }
+        ClassHash {
+            name,
+            offset: off.0,
+            hash: hex(&hasher.finalize().into()),
+            methods,
+        }
+    }
+
+    fn method(&mut self, off: EntityId) -> MethodHash {
+        let name = self.file.method_name(off).unwrap_or_default();
+        self.strings.insert(name.clone());
+        let mut hasher = Sha256::new();
+        put_str(&mut hasher, &name);
+        let method = self.file.method(off).ok();
+        if let Some(method) = &method {
+            hasher.update(method.access_flags().to_le_bytes());
+        }
+        let code = method
+            .and_then(|m| m.code_off())
+            .and_then(|c| self.file.code(c).ok());
+        if let Some(code) = code {
+            hasher.update(code.num_vregs().to_le_bytes());
+            hasher.update(code.num_args().to_le_bytes());
+            self.instructions(&mut hasher, off, code.instructions());
+            for tb in code.try_blocks() {
+                hasher.update(tb.start_pc.to_le_bytes());
+                hasher.update(tb.length.to_le_bytes());
+                for cb in &tb.catches {
+                    let ty = self
+                        .file
+                        .resolve_class_index(off, cb.type_idx as u16)
+                        .and_then(|c| self.string(c))
+                        .unwrap_or_default();
+                    put_str(&mut hasher, &ty);
+                    hasher.update(cb.handler_pc.to_le_bytes());
+                    hasher.update(cb.code_size.to_le_bytes());
+                }
+            }
+        }
+        MethodHash {
+            name,
+            offset: off.0,
+            hash: hex(&hasher.finalize().into()),
+        }
+    }
+
+    /// Hash `bytes` with every entity id replaced by what it names.
+    fn instructions(&mut self, hasher: &mut Sha256, method: EntityId, bytes: &[u8]) {
+        let mut pc = 0;
+        while pc < bytes.len() {
+            let opcode = match PrefixGroup::from_prefix_byte(bytes[pc]) {
+                Some(_) if pc + 1 < bytes.len() => u16::from_le_bytes([bytes[pc], bytes[pc + 1]]),
+                _ => u16::from(bytes[pc]),
+            };
+            let Some(&row) = self.opcodes.get(&opcode) else {
+                // Not an instruction; hash the rest as it is.
+                hasher.update(&bytes[pc..]);
+                return;
+            };
+            let Some(insn) = bytes.get(pc..pc + usize::from(row.size)) else {
+                hasher.update(&bytes[pc..]);
+                return;
+            };
+            let mut masked = insn.to_vec();
+            let ids = row.operands.iter().filter(|d| d.kind == OperandKind::Id);
+            for (k, desc) in ids.enumerate() {
+                let idx = desc.extract(insn) as u16;
+                let at = usize::from(desc.byte_offset);
+                masked[at..at + usize::from(desc.width / 8)].fill(0);
+                let target = self.file.resolve_offset_by_index(method, idx);
+                let named = target.map(|t| self.entity(row.id_kind(k), t));
+                put_str(hasher, &named.unwrap_or_else(|| format!("#{idx}")));
+            }
+            hasher.update(&masked);
+            pc += insn.len();
+        }
+    }
+
+    /// What an id operand names, as text. Operands of unknown kind are
+    /// told apart by what sits at `off`.
+    fn entity(&mut self, kind: Option<IdKind>, off: EntityId) -> String {
+        let kind = kind.or_else(|| {
+            if self.literal_offsets.contains(&off.0) {
+                Some(IdKind::LiteralArray)
+            } else if self.local_methods.contains(&off.0) {
+                Some(IdKind::Method)
+            } else {
+                None
+            }
+        });
+        match kind {
+            Some(IdKind::LiteralArray) => format!("literals:{}", hex(&self.literal_array(off, 0))),
+            Some(IdKind::Method) => format!("method:{}", self.method_name(off)),
+            _ => match self.string(off) {
+                Some(s) => format!("string:{s}"),
+                None => format!("@{off}"),
+            },
+        }
+    }
+
+    fn literal_array(&mut self, off: EntityId, depth: u32) -> [u8; 32] {
+        if let Some(&hash) = self.literal_hashes.get(&off.0) {
+            return hash;
+        }
+        let mut hasher = Sha256::new();
+        let vals = match &self.literal {
+            Some(literal) => literal.enumerate_vals(off),
+            None => Vec::new(),
+        };
+        for val in vals {
+            hasher.update([val.raw_tag]);
+            if let Some(s) = &val.str_data {
+                self.strings.insert(s.clone());
+                put_str(&mut hasher, s);
+                continue;
+            }
+            match val.resolve_value(self.file) {
+                LiteralValue::Method(m) => {
+                    let name = self.method_name(m);
+                    put_str(&mut hasher, &name);
+                }
+                LiteralValue::LiteralArray(nested) if depth < MAX_LITERAL_DEPTH => {
+                    hasher.update(self.literal_array(nested, depth + 1));
+                }
+                _ => hasher.update(val.u64_val.to_le_bytes()),
+            }
+        }
+        let hash = hasher.finalize().into();
+        self.literal_hashes.insert(off.0, hash);
+        hash
+    }
+}
+
+/// Length-prefixed, so that adjacent strings cannot run together.
+fn put_str(hasher: &mut Sha256, s: &str) {
+    hasher.update((s.len() as u64).to_le_bytes());
+    hasher.update(s.as_bytes());
+}
+
+fn hex(digest: &[u8; 32]) -> String {
+    let mut out = String::with_capacity(64);
+    for b in digest {
+        out.push_str(&format!("{b:02x}"));
+    }
+    out
+}
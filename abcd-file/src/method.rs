@@ -1,6 +1,19 @@
 //! Method data accessor.
 
-use crate::{EntityId, File, collect_entity_ids, error::Error, types::SourceLang};
+use crate::{
+    EntityId, File, FileType, collect_entity_ids,
+    error::Error,
+    types::{ACC_STATIC, FunctionKind, SourceLang, TypeId, ValueType},
+};
+
+/// A method's typed signature, read from its proto.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    pub ret: ValueType,
+    /// Arguments in calling order. An instance method's first argument is
+    /// `this`, typed as its declaring class.
+    pub params: Vec<ValueType>,
+}
 
 /// A method data accessor. Borrows from a [`File`].
 pub struct Method<'f> {
@@ -13,7 +26,7 @@ impl<'f> Method<'f> {
     pub(crate) fn open(file: &'f File, offset: EntityId) -> Result<Self, Error> {
         let handle = unsafe { abcd_file_sys::abc_method_open(file.handle(), offset.0) };
         if handle.is_null() {
-            return Err(Error::Ffi(format!(
+            return Err(crate::ffi_error(format!(
                 "abc_method_open failed at offset {offset:?}"
             )));
         }
@@ -45,6 +58,46 @@ impl<'f> Method<'f> {
         unsafe { abcd_file_sys::abc_method_access_flags(self.handle) }
     }
 
+    /// Function kind encoded in the access flags (async, generator, ...).
+    pub fn function_kind(&self) -> FunctionKind {
+        FunctionKind::from_access_flags(self.access_flags())
+    }
+
+    /// Whether the method is declared `static`, taking no `this`.
+    pub fn is_static(&self) -> bool {
+        self.access_flags() & ACC_STATIC != 0
+    }
+
+    /// Arguments the calling convention passes ahead of the declared
+    /// parameters: the function object, `new.target` and `this` in dynamic
+    /// files, just `this` for a static file's instance methods. The code
+    /// item's `num_args` counts these too.
+    pub fn implicit_args(&self) -> u32 {
+        match self.file.kind() {
+            FileType::Static => u32::from(!self.is_static()),
+            FileType::Dynamic | FileType::Invalid => 3,
+        }
+    }
+
+    /// Parameters the method declares, not counting the
+    /// [`implicit_args`](Self::implicit_args), read from its proto rather
+    /// than the code item so abstract, native and external methods have
+    /// one too. `None` without a valid proto, or if the proto of a dynamic
+    /// method does not even cover the implicit arguments.
+    pub fn expected_arity(&self) -> Option<u32> {
+        if !self.has_valid_proto() {
+            return None;
+        }
+        let declared = self.file.proto(self.proto_id()).ok()?.num_args();
+        match self.file.kind() {
+            // Static protos leave `this` out.
+            FileType::Static => Some(declared),
+            // es2abc lists the implicit arguments in the proto, as in the
+            // code item's `num_args`.
+            FileType::Dynamic | FileType::Invalid => declared.checked_sub(self.implicit_args()),
+        }
+    }
+
     pub fn code_off(&self) -> Option<EntityId> {
         let off = unsafe { abcd_file_sys::abc_method_code_off(self.handle) };
         if off == u32::MAX {
@@ -93,21 +146,9 @@ impl<'f> Method<'f> {
     }
 
     pub fn name(&self) -> Result<String, Error> {
-        let len =
-            unsafe { abcd_file_sys::abc_method_get_name(self.handle, std::ptr::null_mut(), 0) };
-        if len == 0 {
-            return Ok(String::new());
-        }
-        let mut buf = vec![0u8; len];
-        let written = unsafe {
-            abcd_file_sys::abc_method_get_name(
-                self.handle,
-                buf.as_mut_ptr() as *mut std::ffi::c_char,
-                buf.len(),
-            )
-        };
-        buf.truncate(written);
-        String::from_utf8(buf).map_err(|e| Error::Ffi(e.to_string()))
+        crate::ffi_string(|buf, len| unsafe {
+            abcd_file_sys::abc_method_get_name(self.handle, buf, len)
+        })
     }
 
     pub fn has_valid_proto(&self) -> bool {
@@ -143,6 +184,27 @@ impl<'f> Method<'f> {
         types
     }
 
+    /// The typed signature: [`proto_types`](Self::proto_types) with
+    /// references resolved to their classes.
+    pub fn signature(&self) -> Signature {
+        let mut types =
+            self.proto_types()
+                .into_iter()
+                .map(|(id, class)| match (TypeId::from_u8(id), class) {
+                    (Some(TypeId::Reference), Some(class)) if class.0 != 0 => {
+                        ValueType::Class(class)
+                    }
+                    (id, _) => ValueType::Primitive(id.unwrap_or(TypeId::Invalid)),
+                });
+        let ret = types
+            .next()
+            .unwrap_or(ValueType::Primitive(TypeId::Invalid));
+        Signature {
+            ret,
+            params: types.collect(),
+        }
+    }
+
     pub fn annotations(&self) -> Vec<EntityId> {
         collect_entity_ids(|cb, ctx| unsafe {
             abcd_file_sys::abc_method_enumerate_annotations(self.handle, Some(cb), ctx);
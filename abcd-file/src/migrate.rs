@@ -0,0 +1,562 @@
+//! Rewriting a file for a different runtime version.
+//!
+//! [`migrate`] takes a file built for an older runtime and produces one a
+//! newer runtime accepts:
+//!
+//! - `deprecated.*` instructions that have a modern counterpart are
+//!   replaced by it. The modern forms take one operand in the accumulator,
+//!   so each becomes `lda vN` followed by the new opcode, padded with `nop`
+//!   to the old size. Every instruction keeps its offset, so jumps, try
+//!   blocks and line tables stay valid.
+//! - Literal array references stored as header indexes (files before
+//!   [`FIRST_OFFSET_LITERAL_ID_VERSION`](crate::version::FIRST_OFFSET_LITERAL_ID_VERSION))
+//!   are replaced by offsets.
+//! - The header version is set to the target and the checksum recomputed.
+//!
+//! [`downgrade`] goes the other way, for running an app on an older
+//! runtime. Instructions the target predates are lowered to older ones
+//! where an equivalent of the same size or smaller exists
+//! (`definepropertybyname` becomes `stownbyname`); the rest are left in
+//! place and listed in [`Downgrade::unlowered`], since the methods using
+//! them will not run there.
+//!
+//! Nothing is moved, so some things are left as they are: deprecated
+//! instructions without a same-size modern form (newer runtimes still
+//! execute them), the header's literal array table, which runtimes
+//! after 12.0.6.0 ignore, and the `MethodAffiliate` entries API 11 adds
+//! after methods in object literals, which readers treat as optional.
+//!
+//! [`migrate_with`] and [`downgrade_with`] report each class and method to
+//! an [`AnalysisObserver`] as they go, and each instruction left unlowered
+//! as a warning.
+
+use std::collections::{HashMap, HashSet};
+
+use abcd_isa::{IsaProfile, OpcodeInfo, OperandDesc, PrefixGroup, Version, opcode_table, opcodes};
+
+use crate::literal::LiteralTag;
+use crate::util::leb128::{decode_sleb128, decode_uleb128};
+use crate::version::{has_literal_array_in_header, uses_literal_array_index};
+use crate::{AnalysisObserver, EntityId, Error, File, Result};
+
+/// Header layout: magic[8], checksum u32, version[4].
+const CHECKSUM_OFFSET: usize = 8;
+const VERSION_OFFSET: usize = 12;
+
+/// Field tag of an integer value stored inline as SLEB128.
+const FIELD_INT_VALUE: u8 = 0x01;
+
+/// An instruction and what replaces it: a sequence of encodings, each
+/// with its operands taken from the listed operands of the original.
+type Rule = (&'static str, &'static [(&'static str, &'static [usize])]);
+
+/// Deprecated instructions and their modern counterparts. The last
+/// register of the deprecated form moves to the accumulator.
+const UPGRADES: [Rule; 10] = [
+    (
+        "deprecated.resumegenerator",
+        &[("lda", &[0]), ("resumegenerator", &[])],
+    ),
+    (
+        "deprecated.getresumemode",
+        &[("lda", &[0]), ("getresumemode", &[])],
+    ),
+    (
+        "deprecated.dynamicimport",
+        &[("lda", &[0]), ("dynamicimport", &[])],
+    ),
+    (
+        "deprecated.delobjprop",
+        &[("lda", &[1]), ("delobjprop", &[0])],
+    ),
+    (
+        "deprecated.suspendgenerator",
+        &[("lda", &[1]), ("suspendgenerator", &[0])],
+    ),
+    (
+        "deprecated.asyncfunctionawaituncaught",
+        &[("lda", &[1]), ("asyncfunctionawaituncaught", &[0])],
+    ),
+    (
+        "deprecated.copydataproperties",
+        &[("lda", &[1]), ("copydataproperties", &[0])],
+    ),
+    (
+        "deprecated.asyncgeneratorreject",
+        &[("lda", &[1]), ("asyncgeneratorreject", &[0])],
+    ),
+    // The middle register is unused.
+    (
+        "deprecated.asyncfunctionresolve",
+        &[("lda", &[2]), ("asyncfunctionresolve", &[0])],
+    ),
+    (
+        "deprecated.asyncfunctionreject",
+        &[("lda", &[2]), ("asyncfunctionreject", &[0])],
+    ),
+];
+
+/// Older equivalents of instructions added after API 9 (see
+/// [`abcd_isa::introduced_in`]).
+const LOWERINGS: [Rule; 8] = [
+    ("definefieldbyname", &[("stownbyname", &[0, 1, 2])]),
+    ("definepropertybyname", &[("stownbyname", &[0, 1, 2])]),
+    // (slot, key, object) to (slot, object, key).
+    (
+        "callruntime.definefieldbyvalue",
+        &[("stownbyvalue", &[0, 2, 1])],
+    ),
+    // (slot, index, object) to (slot, object, index).
+    (
+        "callruntime.definefieldbyindex",
+        &[("stownbyindex", &[0, 2, 1])],
+    ),
+    ("callruntime.istrue", &[("istrue", &[])]),
+    ("callruntime.isfalse", &[("isfalse", &[])]),
+    (
+        "callruntime.ldlazymodulevar",
+        &[("ldexternalmodulevar", &[0])],
+    ),
+    (
+        "callruntime.wideldlazymodulevar",
+        &[("wide.ldexternalmodulevar", &[0])],
+    ),
+];
+
+/// Rewrite `file` for runtimes that expect version `target`.
+///
+/// Fails with [`Error::UnsupportedVersion`] if `target` is older than the
+/// file or outside the supported range, and with [`Error::Migration`] if
+/// a literal array reference cannot be rewritten in place.
+pub fn migrate(file: &File, target: Version) -> Result<Vec<u8>> {
+    migrate_with(file, target, &mut ())
+}
+
+/// [`migrate`], reporting each class and method to `observer` as it is
+/// rewritten.
+pub fn migrate_with(
+    file: &File,
+    target: Version,
+    observer: &mut dyn AnalysisObserver,
+) -> Result<Vec<u8>> {
+    let from = file.version();
+    if target < from || !target.is_in_supported_range() {
+        return Err(Error::UnsupportedVersion(target));
+    }
+    let mut data = file.raw_data().to_vec();
+    let rewriter = Rewriter::new(&UPGRADES, HashSet::new());
+    rewrite_methods(file, &mut data, &rewriter, observer)?;
+    if uses_literal_array_index(&from) && !uses_literal_array_index(&target) {
+        rewrite_literal_ids(file, &mut data, |idx| {
+            file.resolve_literal_array_id(idx).map(|off| off.0)
+        })?;
+    }
+    finish(file, &mut data, target);
+    Ok(data)
+}
+
+/// Result of [`downgrade`].
+#[derive(Debug, Clone)]
+pub struct Downgrade {
+    /// The rewritten file.
+    pub data: Vec<u8>,
+    /// Instructions the target predates that have no older equivalent,
+    /// in file order. They are left as they are.
+    pub unlowered: Vec<Unlowered>,
+}
+
+impl Downgrade {
+    /// Whether every method will run on the target.
+    pub fn is_complete(&self) -> bool {
+        self.unlowered.is_empty()
+    }
+
+    /// Methods that still use instructions the target lacks, each once.
+    pub fn methods(&self) -> Vec<EntityId> {
+        let mut seen = HashSet::new();
+        self.unlowered
+            .iter()
+            .filter(|u| seen.insert(u.method.0))
+            .map(|u| u.method)
+            .collect()
+    }
+}
+
+/// An instruction [`downgrade`] could not lower.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Unlowered {
+    pub method: EntityId,
+    /// Offset of the instruction within the method's code.
+    pub pc: u32,
+    pub mnemonic: &'static str,
+}
+
+/// Rewrite `file` for runtimes that expect the older version `target`,
+/// lowering what can be lowered.
+///
+/// Fails with [`Error::UnsupportedVersion`] if `target` is newer than the
+/// file or outside the supported range, and with [`Error::Migration`] if
+/// the file has no header literal array table and `target` needs one.
+pub fn downgrade(file: &File, target: Version) -> Result<Downgrade> {
+    downgrade_with(file, target, &mut ())
+}
+
+/// [`downgrade`], reporting each class and method to `observer` as it is
+/// rewritten, and each instruction it cannot lower as a warning.
+pub fn downgrade_with(
+    file: &File,
+    target: Version,
+    observer: &mut dyn AnalysisObserver,
+) -> Result<Downgrade> {
+    let from = file.version();
+    if target > from || !target.is_in_supported_range() {
+        return Err(Error::UnsupportedVersion(target));
+    }
+    let mut data = file.raw_data().to_vec();
+    if has_literal_array_in_header(&target)
+        && !has_literal_array_in_header(&from)
+        && file.literal_array_idx_off() as usize >= data.len()
+    {
+        return Err(Error::Migration(format!(
+            "{target} reads literal arrays from the header, which this file leaves out"
+        )));
+    }
+
+    let profile = IsaProfile::for_version(target).ok_or(Error::UnsupportedVersion(target))?;
+    let flagged: HashSet<&str> = opcode_table()
+        .iter()
+        .filter(|row| !profile.has(row))
+        .map(|row| row.mnemonic)
+        .collect();
+    let rules: Vec<Rule> = LOWERINGS
+        .into_iter()
+        .filter(|(mnemonic, _)| flagged.contains(mnemonic))
+        .collect();
+    let rewriter = Rewriter::new(&rules, flagged);
+    let unlowered = rewrite_methods(file, &mut data, &rewriter, observer)?;
+
+    if !uses_literal_array_index(&from) && uses_literal_array_index(&target) {
+        let offsets = file.literal_array_offsets();
+        rewrite_literal_ids(file, &mut data, |off| {
+            offsets
+                .iter()
+                .position(|o| o.0 == off)
+                .map(|idx| idx as u32)
+                .ok_or_else(|| Error::Migration(format!("no header literal array at {off:#x}")))
+        })?;
+    }
+    finish(file, &mut data, target);
+    Ok(Downgrade { data, unlowered })
+}
+
+/// Apply `rewriter` to the code of every local method, returning the
+/// flagged instructions it left in place.
+fn rewrite_methods(
+    file: &File,
+    data: &mut [u8],
+    rewriter: &Rewriter,
+    observer: &mut dyn AnalysisObserver,
+) -> Result<Vec<Unlowered>> {
+    let mut unlowered = Vec::new();
+    let mut seen = HashSet::new();
+    for class_off in file.class_offsets() {
+        if file.is_external(class_off) {
+            continue;
+        }
+        let Ok(class) = file.class(class_off) else {
+            continue;
+        };
+        observer.on_class_start(class_off, &file.get_string_lossy(class_off));
+        for method in class.method_offsets() {
+            let Some(code_off) = file.method(method).ok().and_then(|m| m.code_off()) else {
+                continue;
+            };
+            // Methods sharing code are rewritten with the first of them.
+            if seen.insert(code_off.0) {
+                let (start, len) = code_range(data, code_off)?;
+                let data_len = data.len();
+                let Some(code) = data.get_mut(start..start + len) else {
+                    return Err(Error::OffsetOutOfBounds(start + len, data_len));
+                };
+                rewriter.rewrite(code, &mut |pc, mnemonic| {
+                    observer.on_warning(
+                        Some(method),
+                        &format!("{mnemonic} at pc {pc:#x} has no equivalent on the target"),
+                    );
+                    unlowered.push(Unlowered {
+                        method,
+                        pc,
+                        mnemonic,
+                    })
+                });
+            }
+            observer.on_method_done(method, None);
+        }
+    }
+    Ok(unlowered)
+}
+
+/// Set the header version to `target` and recompute the checksum.
+fn finish(file: &File, data: &mut [u8], target: Version) {
+    data[VERSION_OFFSET..VERSION_OFFSET + 4].copy_from_slice(target.as_bytes());
+    let end = (file.file_size() as usize).min(data.len());
+    write_checksum(&mut data[..end]);
+}
+
+/// Recompute the header checksum over everything after it.
+pub(crate) fn write_checksum(data: &mut [u8]) {
+    let checksum = adler32(&data[VERSION_OFFSET..]);
+    data[CHECKSUM_OFFSET..CHECKSUM_OFFSET + 4].copy_from_slice(&checksum.to_le_bytes());
+}
+
+/// Replaces instructions in a method's code according to a list of rules.
+struct Rewriter {
+    opcodes: HashMap<u16, &'static OpcodeInfo>,
+    rules: HashMap<u16, Vec<(&'static OpcodeInfo, &'static [usize])>>,
+    /// Opcodes reported when their rule cannot be applied or they have
+    /// none.
+    flagged: HashSet<u16>,
+}
+
+impl Rewriter {
+    /// `flagged` names the instructions to report when no rule replaces
+    /// them.
+    fn new(rules: &[Rule], flagged: HashSet<&str>) -> Self {
+        let flagged = opcode_table()
+            .iter()
+            .filter(|r| flagged.contains(r.mnemonic))
+            .map(|r| r.opcode)
+            .collect();
+        let rules = rules
+            .iter()
+            .flat_map(|&(old, seq)| {
+                let seq: Vec<_> = seq
+                    .iter()
+                    .map(|&(new, from)| (narrowest(new), from))
+                    .collect();
+                opcode_table()
+                    .iter()
+                    .filter(move |r| r.mnemonic == old)
+                    .map(move |r| (r.opcode, seq.clone()))
+            })
+            .collect();
+        Self {
+            opcodes: opcode_table().iter().map(|r| (r.opcode, r)).collect(),
+            rules,
+            flagged,
+        }
+    }
+
+    /// Rewrite `code` in place, calling `unlowered` for each flagged
+    /// instruction left as it is. Stops at the first byte that does not
+    /// start an instruction.
+    fn rewrite(&self, code: &mut [u8], unlowered: &mut dyn FnMut(u32, &'static str)) {
+        let mut pc = 0;
+        while pc < code.len() {
+            let opcode = match PrefixGroup::from_prefix_byte(code[pc]) {
+                Some(_) if pc + 1 < code.len() => u16::from_le_bytes([code[pc], code[pc + 1]]),
+                _ => u16::from(code[pc]),
+            };
+            let Some(row) = self.opcodes.get(&opcode) else {
+                return;
+            };
+            let end = pc + usize::from(row.size);
+            let Some(insn) = code.get_mut(pc..end) else {
+                return;
+            };
+            let replacement = self
+                .rules
+                .get(&opcode)
+                .and_then(|seq| self.replacement(row, insn, seq));
+            match replacement {
+                Some(bytes) => insn.copy_from_slice(&bytes),
+                None if self.flagged.contains(&opcode) => unlowered(pc as u32, row.mnemonic),
+                None => {}
+            }
+            pc = end;
+        }
+    }
+
+    /// `insn` re-encoded as `seq` and padded with `nop`, or `None` if
+    /// that is longer or an operand does not fit.
+    fn replacement(
+        &self,
+        row: &OpcodeInfo,
+        insn: &[u8],
+        seq: &[(&'static OpcodeInfo, &'static [usize])],
+    ) -> Option<Vec<u8>> {
+        let values: Vec<u64> = row.operands.iter().map(|d| d.extract(insn)).collect();
+        let mut out = Vec::with_capacity(insn.len());
+        for &(new, from) in seq {
+            let mut bytes = vec![0; usize::from(new.size)];
+            let opcode = new.opcode.to_le_bytes();
+            let opcode_len = match PrefixGroup::from_prefix_byte(opcode[0]) {
+                Some(_) => 2,
+                None => 1,
+            };
+            bytes[..opcode_len].copy_from_slice(&opcode[..opcode_len]);
+            for (desc, &k) in new.operands.iter().zip(from) {
+                put(desc, &mut bytes, values[k])?;
+            }
+            out.extend_from_slice(&bytes);
+        }
+        if out.len() > insn.len() {
+            return None;
+        }
+        out.resize(insn.len(), opcodes::NOP as u8);
+        Some(out)
+    }
+}
+
+/// The shortest encoding of `mnemonic`.
+fn narrowest(mnemonic: &str) -> &'static OpcodeInfo {
+    opcode_table()
+        .iter()
+        .filter(|r| r.mnemonic == mnemonic)
+        .min_by_key(|r| r.size)
+        .unwrap_or_else(|| panic!("`{mnemonic}` missing from the opcode table"))
+}
+
+/// Write operand `value` where `desc` says; `None` if it does not fit.
+fn put(desc: &OperandDesc, bytes: &mut [u8], value: u64) -> Option<()> {
+    if desc.width < 64 && value >> desc.width != 0 {
+        return None;
+    }
+    desc.insert(bytes, value);
+    Some(())
+}
+
+/// Start and length of the instructions of the code item at `code_off`.
+fn code_range(data: &[u8], code_off: EntityId) -> Result<(usize, usize)> {
+    let mut pos = code_off.0 as usize;
+    let mut code_size = 0;
+    // num_vregs, num_args, code_size, tries_size
+    for field in 0..4 {
+        let (value, len) = decode_uleb128(data, pos)?;
+        if field == 2 {
+            code_size = value as usize;
+        }
+        pos += len;
+    }
+    Ok((pos, code_size))
+}
+
+/// Replace the literal array references in `moduleRecordIdx` fields and
+/// [`LiteralTag::LiteralArray`] values with `map(reference)`, between
+/// header indexes and offsets.
+fn rewrite_literal_ids(
+    file: &File,
+    data: &mut [u8],
+    map: impl Fn(u32) -> Result<u32>,
+) -> Result<()> {
+    for array_off in file.literal_array_offsets() {
+        let mut pos = array_off.0 as usize;
+        let count = read_u32(data, pos)?;
+        pos += 4;
+        for _ in 0..count / 2 {
+            let tag = *data
+                .get(pos)
+                .ok_or(Error::OffsetOutOfBounds(pos, data.len()))?;
+            pos += 1;
+            let Some(size) = LiteralTag::from_u8(tag).and_then(value_size) else {
+                // Typed arrays end the array; unknown tags cannot be
+                // stepped over.
+                break;
+            };
+            if tag == LiteralTag::LiteralArray as u8 {
+                let id = map(read_u32(data, pos)?)?;
+                data[pos..pos + 4].copy_from_slice(&id.to_le_bytes());
+            }
+            pos += size;
+        }
+    }
+
+    for class_off in file.class_offsets() {
+        if file.is_external(class_off) {
+            continue;
+        }
+        let Ok(class) = file.class(class_off) else {
+            continue;
+        };
+        for field_off in class.field_offsets() {
+            let is_module_record = file
+                .field(field_off)
+                .ok()
+                .and_then(|f| file.get_string(f.name_off()).ok())
+                .is_some_and(|name| name == "moduleRecordIdx");
+            if !is_module_record {
+                continue;
+            }
+            // class_idx u16, type_idx u16, name_off u32, access_flags uleb128
+            let pos = field_off.0 as usize + 8;
+            let pos = pos + decode_uleb128(data, pos)?.1;
+            if data.get(pos) != Some(&FIELD_INT_VALUE) {
+                continue;
+            }
+            let (old, len) = decode_sleb128(data, pos + 1)?;
+            let id = map(old as u32)?;
+            let Some(bytes) = sleb128_padded(i64::from(id as i32), len) else {
+                let class = file.get_string(class_off).unwrap_or_default();
+                return Err(Error::Migration(format!(
+                    "moduleRecordIdx of {class} has {len} byte(s), {id:#x} needs more"
+                )));
+            };
+            data[pos + 1..pos + 1 + len].copy_from_slice(&bytes);
+        }
+    }
+    Ok(())
+}
+
+/// Size of the value following `tag` in a literal array; `None` for
+/// typed arrays, whose payload runs to the end.
+pub(crate) fn value_size(tag: LiteralTag) -> Option<usize> {
+    use LiteralTag::*;
+    match tag {
+        Bool | Accessor | BuiltinTypeIndex | NullValue => Some(1),
+        MethodAffiliate => Some(2),
+        Integer | Float | String | Method | GeneratorMethod | AsyncGeneratorMethod
+        | LiteralBufferIndex | LiteralArray | Getter | Setter | EtsImplements => Some(4),
+        Double => Some(8),
+        _ => None,
+    }
+}
+
+pub(crate) fn read_u32(data: &[u8], pos: usize) -> Result<u32> {
+    data.get(pos..pos + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or(Error::OffsetOutOfBounds(pos, data.len()))
+}
+
+/// `value` as SLEB128 in exactly `len` bytes, using redundant
+/// continuation bytes if it is shorter. `None` if it does not fit.
+fn sleb128_padded(mut value: i64, len: usize) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(len);
+    for i in 0..len {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if i + 1 == len {
+            let done = (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0);
+            return done.then(|| {
+                out.push(byte);
+                out
+            });
+        }
+        out.push(byte | 0x80);
+    }
+    None
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+    // 5552 bytes is the most that can be summed before `b` overflows.
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += u32::from(byte);
+            b += a;
+        }
+        a %= MOD;
+        b %= MOD;
+    }
+    (b << 16) | a
+}
@@ -23,7 +23,7 @@ impl<'f> Module<'f> {
     pub(crate) fn open(file: &'f File, offset: EntityId) -> Result<Self, Error> {
         let handle = unsafe { abcd_file_sys::abc_module_open(file.handle(), offset.0) };
         if handle.is_null() {
-            return Err(Error::Ffi(format!(
+            return Err(crate::ffi_error(format!(
                 "abc_module_open failed at offset {offset:?}"
             )));
         }
@@ -34,6 +34,15 @@ impl<'f> Module<'f> {
         })
     }
 
+    /// Open the module literal named by a record's `moduleRecordIdx` field.
+    ///
+    /// The field holds a header literal array index in legacy files and an
+    /// offset otherwise; see [`File::resolve_literal_array_id`].
+    pub(crate) fn open_record(file: &'f File, module_record_idx: u32) -> Result<Self, Error> {
+        let offset = file.resolve_literal_array_id(module_record_idx)?;
+        Self::open(file, offset)
+    }
+
     /// The offset in the ABC file where this module was opened.
     pub fn offset(&self) -> EntityId {
         self.off
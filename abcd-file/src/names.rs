@@ -0,0 +1,167 @@
+//! Record names split into bundle, module, path and name.
+//!
+//! Files merged from several modules name their records
+//! `bundle&module/path/name&version`, for example
+//! `com.example.app&entry/src/main/ets/pages/Index&` or
+//! `&@ohos/lib/src/Util&1.0.0`; normalized import requests
+//! (`@normalized:N&entry&com.example.app&entry/src/main/ets/pages/Index&`)
+//! carry the same fields behind two more. [`QualifiedName::parse`] reads
+//! all of these, as well as plain `Lpath/name;` descriptors, which have no
+//! bundle, module or version.
+
+use std::fmt;
+
+/// A record or module name split into its parts. Empty parts are `None`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct QualifiedName {
+    pub bundle: Option<String>,
+    /// The package the path starts with; scoped packages keep their scope,
+    /// as in `@ohos/lib`.
+    pub module: Option<String>,
+    /// Directories between the module and the name, `/`-separated.
+    pub path: String,
+    pub name: String,
+    pub version: Option<String>,
+}
+
+impl QualifiedName {
+    pub fn parse(raw: &str) -> Self {
+        let raw = raw
+            .strip_prefix('L')
+            .and_then(|s| s.strip_suffix(';'))
+            .unwrap_or(raw);
+        if !raw.contains('&') {
+            let (path, name) = split_last(raw);
+            return QualifiedName {
+                path: path.to_string(),
+                name: name.to_string(),
+                ..QualifiedName::default()
+            };
+        }
+
+        // The import path is the last part with a directory in it; the
+        // bundle comes right before it and the version right after.
+        let parts: Vec<&str> = raw.split('&').collect();
+        let at = parts
+            .iter()
+            .rposition(|p| p.contains('/'))
+            .unwrap_or_else(|| {
+                let longest = parts.iter().map(|p| p.len()).max().unwrap_or(0);
+                parts.iter().position(|p| p.len() == longest).unwrap_or(0)
+            });
+        let part = |i: Option<usize>| {
+            i.and_then(|i| parts.get(i))
+                .filter(|p| !p.is_empty())
+                .map(|p| p.to_string())
+        };
+
+        let import = parts[at];
+        let module_len = match import.starts_with('@') {
+            true => 2,
+            false => 1,
+        };
+        let segments: Vec<&str> = import.split('/').collect();
+        let (module, rest) = match segments.len() > module_len {
+            true => (
+                Some(segments[..module_len].join("/")),
+                segments[module_len..].join("/"),
+            ),
+            false => (None, import.to_string()),
+        };
+        let (path, name) = split_last(&rest);
+        QualifiedName {
+            bundle: part(at.checked_sub(1)),
+            module,
+            path: path.to_string(),
+            name: name.to_string(),
+            version: part(Some(at + 1)),
+        }
+    }
+
+    /// Bundle, module, path directories and name, skipping empty ones.
+    pub fn segments(&self) -> impl Iterator<Item = &str> {
+        let dirs = self.module.iter().chain([&self.path]);
+        self.bundle
+            .as_deref()
+            .into_iter()
+            .chain(dirs.flat_map(|d| d.split('/')))
+            .chain([self.name.as_str()])
+            .filter(|s| !s.is_empty())
+    }
+
+    /// Module, path and name joined with `/`: where the record sits within
+    /// its bundle.
+    pub fn module_path(&self) -> String {
+        let mut out = self.module.clone().unwrap_or_default();
+        for part in [&self.path, &self.name] {
+            if part.is_empty() {
+                continue;
+            }
+            if !out.is_empty() {
+                out.push('/');
+            }
+            out.push_str(part);
+        }
+        out
+    }
+}
+
+/// The merged form when there is a bundle, module or version, otherwise
+/// the path.
+impl fmt::Display for QualifiedName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let merged = self.bundle.is_some() || self.module.is_some() || self.version.is_some();
+        if !merged {
+            return f.write_str(&self.module_path());
+        }
+        write!(
+            f,
+            "{}&{}&{}",
+            self.bundle.as_deref().unwrap_or_default(),
+            self.module_path(),
+            self.version.as_deref().unwrap_or_default()
+        )
+    }
+}
+
+/// The `pandasm` spelling of a static file's type descriptor:
+/// `Lstd/core/Object;` is `std.core.Object` and `[I` is `i32[]`.
+/// Unrecognized descriptors come back unchanged.
+pub fn pandasm_name(descriptor: &str) -> String {
+    let elem = descriptor.trim_start_matches('[');
+    let dims = descriptor.len() - elem.len();
+    let base = match elem.strip_prefix('L').and_then(|s| s.strip_suffix(';')) {
+        Some(path) => path.replace('/', "."),
+        None => primitive_name(elem).unwrap_or(elem).to_string(),
+    };
+    base + &"[]".repeat(dims)
+}
+
+/// Primitive type descriptors, as the runtime's `Type::GetSignature`
+/// writes them.
+fn primitive_name(descriptor: &str) -> Option<&'static str> {
+    Some(match descriptor {
+        "V" => "void",
+        "Z" => "u1",
+        "B" => "i8",
+        "H" => "u8",
+        "S" => "i16",
+        "C" => "u16",
+        "I" => "i32",
+        "U" => "u32",
+        "F" => "f32",
+        "D" => "f64",
+        "J" => "i64",
+        "Q" => "u64",
+        "A" => "any",
+        _ => return None,
+    })
+}
+
+/// `dir/sub/name` as `("dir/sub", "name")`.
+fn split_last(path: &str) -> (&str, &str) {
+    match path.rsplit_once('/') {
+        Some((dirs, name)) => (dirs, name),
+        None => ("", path),
+    }
+}
@@ -0,0 +1,159 @@
+//! Analyst notes kept next to a file.
+//!
+//! A [`NoteStore`] maps entity offsets (classes, methods, strings, literal
+//! arrays) to free-text notes and tags. It lives in a JSON sidecar beside
+//! the file it describes, `app.abc.notes.json` for `app.abc`, so it can be
+//! shared and merged like any other text file:
+//!
+//! ```json
+//! {
+//!   "entities": {
+//!     "0x1a2c": { "notes": ["derives the session key"], "tags": ["crypto"] }
+//!   }
+//! }
+//! ```
+//!
+//! Offsets are file offsets, so notes stay valid only for the exact file
+//! they were written against.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{EntityId, Error, Result};
+
+/// Notes and tags attached to one entity.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntityNotes {
+    /// In the order they were added.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub tags: BTreeSet<String>,
+}
+
+impl EntityNotes {
+    pub fn is_empty(&self) -> bool {
+        self.notes.is_empty() && self.tags.is_empty()
+    }
+}
+
+/// On-disk form: offsets as `0x`-prefixed hex keys.
+#[derive(Serialize, Deserialize)]
+struct Sidecar {
+    entities: BTreeMap<String, EntityNotes>,
+}
+
+/// Notes for the entities of one file, ordered by offset.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NoteStore {
+    entities: BTreeMap<u32, EntityNotes>,
+}
+
+impl NoteStore {
+    /// Where the notes for the file at `path` are kept.
+    pub fn sidecar_path(path: &Path) -> PathBuf {
+        let mut name = path.as_os_str().to_owned();
+        name.push(".notes.json");
+        PathBuf::from(name)
+    }
+
+    /// Read a sidecar. A missing file gives an empty store.
+    pub fn load(sidecar: &Path) -> Result<Self> {
+        let text = match std::fs::read_to_string(sidecar) {
+            Ok(text) => text,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(Error::Io(e.to_string())),
+        };
+        let parsed: Sidecar =
+            serde_json::from_str(&text).map_err(|e| Error::InvalidNotes(e.to_string()))?;
+        let mut entities = BTreeMap::new();
+        for (key, notes) in parsed.entities {
+            let offset = parse_offset(&key)
+                .ok_or_else(|| Error::InvalidNotes(format!("bad entity offset {key:?}")))?;
+            entities.insert(offset.0, notes);
+        }
+        Ok(Self { entities })
+    }
+
+    /// The notes for the file at `path`, from its sidecar.
+    pub fn load_for(path: &Path) -> Result<Self> {
+        Self::load(&Self::sidecar_path(path))
+    }
+
+    /// Write the store as pretty-printed JSON.
+    pub fn save(&self, sidecar: &Path) -> Result<()> {
+        let on_disk = Sidecar {
+            entities: self
+                .entities
+                .iter()
+                .map(|(&off, notes)| (format!("{off:#x}"), notes.clone()))
+                .collect(),
+        };
+        let mut text = serde_json::to_string_pretty(&on_disk)
+            .map_err(|e| Error::InvalidNotes(e.to_string()))?;
+        text.push('\n');
+        #[cfg(unix)]
+        let written = crate::sandbox::write_output(sidecar, text.as_bytes());
+        #[cfg(not(unix))]
+        let written = std::fs::write(sidecar, text);
+        written.map_err(|e| Error::Io(e.to_string()))
+    }
+
+    pub fn add_note(&mut self, entity: EntityId, text: &str) {
+        self.entities
+            .entry(entity.0)
+            .or_default()
+            .notes
+            .push(text.to_string());
+    }
+
+    /// Tag `entity`; returns `false` if it already had the tag.
+    pub fn add_tag(&mut self, entity: EntityId, tag: &str) -> bool {
+        self.entities
+            .entry(entity.0)
+            .or_default()
+            .tags
+            .insert(tag.to_string())
+    }
+
+    /// Drop everything attached to `entity`.
+    pub fn remove(&mut self, entity: EntityId) -> Option<EntityNotes> {
+        self.entities.remove(&entity.0)
+    }
+
+    pub fn get(&self, entity: EntityId) -> Option<&EntityNotes> {
+        self.entities.get(&entity.0).filter(|n| !n.is_empty())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.values().all(EntityNotes::is_empty)
+    }
+
+    /// Annotated entities in offset order.
+    pub fn iter(&self) -> impl Iterator<Item = (EntityId, &EntityNotes)> {
+        self.entities
+            .iter()
+            .filter(|(_, n)| !n.is_empty())
+            .map(|(&off, n)| (EntityId(off), n))
+    }
+
+    /// Entities carrying `tag`, in offset order.
+    pub fn tagged<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = EntityId> + 'a {
+        self.iter()
+            .filter(move |(_, n)| n.tags.contains(tag))
+            .map(|(id, _)| id)
+    }
+}
+
+/// An entity offset written as `0x`-prefixed hex or decimal, as in
+/// sidecar keys.
+pub fn parse_offset(s: &str) -> Option<EntityId> {
+    let off = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+        None => s.parse().ok()?,
+    };
+    Some(EntityId(off))
+}
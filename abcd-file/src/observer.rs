@@ -0,0 +1,36 @@
+//! Progress events from passes over a whole file.
+//!
+//! Rewriting, verifying or decompiling every class of a large app takes a
+//! while. A GUI that embeds one of these passes implements
+//! [`AnalysisObserver`] to show progress and partial results as they come,
+//! instead of scraping the log. Every method has a no-op default, so an
+//! observer implements only the events it cares about; `()` ignores them
+//! all.
+//!
+//! Passes that take an observer: [`migrate::migrate_with`],
+//! [`migrate::downgrade_with`] and [`digest::method_digests`] here,
+//! `AnalysisSession::decompile_file` in `abcd-decompiler`, and the CLI's
+//! `decompile` and `verify`.
+//!
+//! [`migrate::migrate_with`]: crate::migrate::migrate_with
+//! [`migrate::downgrade_with`]: crate::migrate::downgrade_with
+//! [`digest::method_digests`]: crate::digest::method_digests
+
+use crate::EntityId;
+
+/// Receives progress from a pass over a file's local classes.
+pub trait AnalysisObserver {
+    /// The pass is starting on the class at `class`, named `name`.
+    fn on_class_start(&mut self, _class: EntityId, _name: &str) {}
+
+    /// The pass is done with `method`. `output` is what it produced for
+    /// the method if it produces text, as decompilation does; passes that
+    /// rewrite or check code pass `None`.
+    fn on_method_done(&mut self, _method: EntityId, _output: Option<&str>) {}
+
+    /// Something the pass noticed and carried on past, with the method it
+    /// concerns if there is one.
+    fn on_warning(&mut self, _method: Option<EntityId>, _message: &str) {}
+}
+
+impl AnalysisObserver for () {}
@@ -0,0 +1,231 @@
+//! Per-method data es2abc leaves for the runtime's optimizing tiers.
+//!
+//! Besides code, the compiler annotates methods with the number of
+//! inline-cache slots their instructions use (`L_ESSlotNumberAnnotation;`,
+//! or `icSize` in `L_ESAnnotation;` in older files) and, for TypeScript
+//! built with type extraction, a literal array pairing instruction orders
+//! with type indexes (`_TypeOfInstruction` in `L_ESTypeAnnotation;`).
+//!
+//! Everything here is optional. A method without these annotations has an
+//! empty [`MethodProfile`]; malformed entries are skipped.
+
+use std::fmt;
+
+use crate::annotation::AnnotationValue;
+use crate::literal::LiteralValue;
+use crate::method::Method;
+use crate::{EntityId, File};
+
+pub(crate) const SLOT_NUMBER_ANNOTATION: &str = "L_ESSlotNumberAnnotation;";
+pub(crate) const SLOT_NUMBER_ELEMENT: &str = "SlotNumber";
+const ES_ANNOTATION: &str = "L_ESAnnotation;";
+const TYPE_ANNOTATION: &str = "L_ESTypeAnnotation;";
+
+/// Profile data recorded for one method.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MethodProfile {
+    /// Inline-cache slots the method's instructions index into.
+    pub ic_slots: Option<u32>,
+    /// Recorded types, in file order.
+    pub types: Vec<TypeHint>,
+}
+
+impl MethodProfile {
+    /// Type recorded for declared parameter `index` (0 is the first
+    /// parameter after the implicit function, new.target and this).
+    pub fn param_type(&self, index: u32) -> Option<TypeRef> {
+        self.types
+            .iter()
+            .find(|h| h.param() == Some(index))
+            .map(|h| h.ty)
+    }
+
+    /// Type recorded for the result of the instruction at `order`.
+    pub fn insn_type(&self, order: u32) -> Option<TypeRef> {
+        self.types
+            .iter()
+            .find(|h| h.order == order as i32)
+            .map(|h| h.ty)
+    }
+}
+
+/// One entry of `_TypeOfInstruction`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TypeHint {
+    /// Instruction index within the method for values `>= 0`; parameter
+    /// `n` is recorded as `-(n + 1)`.
+    pub order: i32,
+    pub ty: TypeRef,
+}
+
+impl TypeHint {
+    /// The parameter this hint describes, if it describes one.
+    pub fn param(&self) -> Option<u32> {
+        (self.order < 0).then(|| (-(self.order + 1)) as u32)
+    }
+}
+
+/// A type index from `_TypeOfInstruction`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TypeRef {
+    Primitive(PrimitiveType),
+    /// A user-defined or builtin type, described by the file's type
+    /// literals; not decoded here.
+    Other(u32),
+}
+
+impl fmt::Display for TypeRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeRef::Primitive(p) => f.write_str(p.ts_name()),
+            TypeRef::Other(idx) => write!(f, "type#{idx}"),
+        }
+    }
+}
+
+/// Type indexes below 100 that name TypeScript primitives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PrimitiveType {
+    Any,
+    Number,
+    Boolean,
+    Void,
+    String,
+    Symbol,
+    Null,
+    Undefined,
+    Int,
+}
+
+impl PrimitiveType {
+    pub fn from_index(idx: u32) -> Option<Self> {
+        Some(match idx {
+            0 => Self::Any,
+            1 => Self::Number,
+            2 => Self::Boolean,
+            3 => Self::Void,
+            4 => Self::String,
+            5 => Self::Symbol,
+            6 => Self::Null,
+            7 => Self::Undefined,
+            8 => Self::Int,
+            _ => return None,
+        })
+    }
+
+    /// The TypeScript spelling; `Int` has none of its own and reads as
+    /// `number`.
+    pub fn ts_name(self) -> &'static str {
+        match self {
+            Self::Any => "any",
+            Self::Number | Self::Int => "number",
+            Self::Boolean => "boolean",
+            Self::Void => "void",
+            Self::String => "string",
+            Self::Symbol => "symbol",
+            Self::Null => "null",
+            Self::Undefined => "undefined",
+        }
+    }
+}
+
+impl TypeRef {
+    fn from_index(idx: u32) -> Self {
+        PrimitiveType::from_index(idx).map_or(TypeRef::Other(idx), TypeRef::Primitive)
+    }
+}
+
+impl Method<'_> {
+    /// Slot count and type hints from the method's annotations.
+    pub fn profile(&self) -> MethodProfile {
+        let file = self.file();
+        let mut profile = MethodProfile::default();
+        self.elements(|class, name, value, _| match (class, name, value) {
+            (SLOT_NUMBER_ANNOTATION, SLOT_NUMBER_ELEMENT, AnnotationValue::Scalar(n)) => {
+                profile.ic_slots = Some(n);
+            }
+            // The dedicated annotation wins when both are present.
+            (ES_ANNOTATION, "icSize", AnnotationValue::Scalar(n)) => {
+                profile.ic_slots.get_or_insert(n);
+            }
+            (TYPE_ANNOTATION, "_TypeOfInstruction", value) => {
+                let raw = match value {
+                    AnnotationValue::Scalar(v) => v,
+                    AnnotationValue::EntityRef(id) => id.0,
+                };
+                profile.types = type_hints(file, raw);
+            }
+            _ => {}
+        });
+        profile
+    }
+
+    /// File offset of the value [`MethodProfile::ic_slots`] is read from,
+    /// for storing a new count in place.
+    pub(crate) fn slot_count_off(&self) -> Option<usize> {
+        let (mut dedicated, mut legacy) = (None, None);
+        self.elements(|class, name, value, at| match (class, name, value) {
+            (SLOT_NUMBER_ANNOTATION, SLOT_NUMBER_ELEMENT, AnnotationValue::Scalar(_)) => {
+                dedicated = Some(at);
+            }
+            (ES_ANNOTATION, "icSize", AnnotationValue::Scalar(_)) => {
+                legacy.get_or_insert(at);
+            }
+            _ => {}
+        });
+        dedicated.or(legacy)
+    }
+
+    /// Call `f` with the class name, element name, value and value offset
+    /// of every annotation element on the method.
+    fn elements(&self, mut f: impl FnMut(&str, &str, AnnotationValue, usize)) {
+        let file = self.file();
+        for ann_off in self.annotations() {
+            let Ok(ann) = file.annotation(ann_off) else {
+                continue;
+            };
+            let Ok(class) = file.get_string(ann.class_off()) else {
+                continue;
+            };
+            for i in 0..ann.count() {
+                let Some(elem) = ann.element(i) else {
+                    continue;
+                };
+                let Ok(name) = file.get_string(elem.name_off) else {
+                    continue;
+                };
+                // class_idx u16 and count u16, then a name and a value
+                // u32 per element.
+                let at = ann_off.0 as usize + 4 + 8 * i as usize + 4;
+                f(&class, &name, elem.value, at);
+            }
+        }
+    }
+}
+
+/// Decode the `(order, type)` pairs of a `_TypeOfInstruction` array.
+fn type_hints(file: &File, raw: u32) -> Vec<TypeHint> {
+    let Ok(array_off) = file.resolve_literal_array_id(raw) else {
+        return Vec::new();
+    };
+    let Ok(literal) = file.literal(EntityId(file.literal_array_idx_off())) else {
+        return Vec::new();
+    };
+    let ints: Vec<i32> = literal
+        .enumerate_vals(array_off)
+        .iter()
+        .filter_map(|v| match v.to_value() {
+            LiteralValue::Integer(_) => Some(v.as_i32()),
+            _ => None,
+        })
+        .collect();
+    ints.chunks_exact(2)
+        .filter_map(|pair| {
+            let ty = u32::try_from(pair[1]).ok()?;
+            Some(TypeHint {
+                order: pair[0],
+                ty: TypeRef::from_index(ty),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,682 @@
+//! Parsing untrusted files in a child process.
+//!
+//! The parser is C++ that was never hardened against hostile input: a
+//! crafted file that crashes it, loops forever or allocates without bound
+//! takes the whole process down with it. [`SandboxedFile`] forks first and
+//! parses in the child, under [`Limits`] on memory, CPU and wall-clock
+//! time and, on Linux x86-64 and AArch64, the syscall allowlist described
+//! below. Whatever the child writes to stdout streams back through a pipe;
+//! files it writes with [`write_output`] stream back through another and
+//! are written by the parent, and only under the directories the caller
+//! named. If the child dies, the caller gets a [`SandboxError`] instead of
+//! dying too.
+//!
+//! [`isolate`] is the same thing for any closure, which is how the CLI
+//! runs a whole subcommand under `--sandbox`.
+//!
+//! Only the forking thread lives on in the child. Start a sandbox before
+//! spawning threads, or at least while no other thread can be holding a
+//! lock the child needs, such as stdout's.
+//!
+//! # The syscall filter
+//!
+//! The child may make only these syscalls; any other fails with `EPERM`,
+//! and one made through another ABI (32-bit or x32) kills it:
+//!
+//! - reading and writing descriptors it already has, closing them, and
+//!   seeking;
+//! - `openat` without `O_WRONLY`, `O_RDWR`, `O_CREAT`, `O_TRUNC` or
+//!   `O_APPEND`, `stat` and friends, `getdents64`, `readlinkat`, `getcwd`
+//!   and `faccessat`. Subcommands open their own inputs, so reading stays
+//!   possible, including `/proc/<pid>/mem` of a process the kernel's
+//!   ptrace checks let the caller's user read;
+//! - memory management, futexes, and `clone` with `CLONE_THREAD`, so the
+//!   decompiler can start threads but the child cannot fork (`clone3`
+//!   fails with `ENOSYS`, which makes the C library fall back to `clone`);
+//! - signal handling on itself, and `tgkill` addressed to its own process,
+//!   which is how `abort` raises `SIGABRT`;
+//! - clocks, sleeping, `getrandom`, `uname`, its own ids and resource
+//!   limits, and exiting.
+//!
+//! Everything else is refused: starting programs, sockets, `kill` and
+//! `tgkill` to other processes, `ptrace`, `process_vm_*`, `io_uring_*`,
+//! `ioctl`, creating or removing files and directories.
+//!
+//! ```no_run
+//! use std::io::Write;
+//!
+//! use abcd_file::sandbox::SandboxedFile;
+//!
+//! let file = SandboxedFile::open_path("untrusted.abc".as_ref()).unwrap();
+//! let mut summary = Vec::new();
+//! file.run(&mut summary, |abc, out| {
+//!     writeln!(out, "{} classes", abc.num_classes())
+//! })
+//! .unwrap();
+//! ```
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::os::fd::FromRawFd;
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use thiserror::Error;
+
+use crate::{Error, File, Result};
+
+/// Exit status of a child that could not be confined; it exits before
+/// running anything. Like `env` and `timeout`, which use 125 for their own
+/// failures.
+pub const CONFINE_FAILED: i32 = 125;
+
+/// Exit status of a child whose closure panicked, as for an uncaught panic
+/// in a normal process.
+pub const PANICKED: i32 = 101;
+
+/// Resource limits for the child; `None` leaves a limit as inherited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Address space, in bytes. Allocations past it fail, which aborts.
+    pub memory: Option<u64>,
+    /// CPU time, in seconds. The child gets `SIGXCPU` when it runs out.
+    pub cpu_seconds: Option<u64>,
+    /// Wall-clock time, in seconds, for a child that blocks or sleeps
+    /// instead of spinning. The parent kills it when it runs out.
+    pub wall_seconds: Option<u64>,
+}
+
+impl Default for Limits {
+    /// 4 GiB, ten minutes of CPU and twenty in all: far more than the
+    /// largest real apps need.
+    fn default() -> Self {
+        Limits {
+            memory: Some(4 << 30),
+            cpu_seconds: Some(600),
+            wall_seconds: Some(1200),
+        }
+    }
+}
+
+/// Why a sandboxed run did not finish.
+#[derive(Debug, Error)]
+pub enum SandboxError {
+    #[error("Cannot start sandbox: {0}")]
+    Spawn(io::Error),
+
+    #[error("Cannot copy sandbox output: {0}")]
+    Output(io::Error),
+
+    #[error("Sandboxed child exited with status {0}")]
+    Exited(i32),
+
+    #[error("Sandboxed child was killed by signal {0}")]
+    Killed(i32),
+
+    #[error("Sandboxed child ran for more than {0} seconds")]
+    TimedOut(u64),
+}
+
+/// The write end of the pipe [`write_output`] sends files through, in a
+/// child started by [`isolate_with_outputs`].
+static OUTPUT_PIPE: Mutex<Option<fs::File>> = Mutex::new(None);
+
+/// Write `data` to `path`, creating its parent directories.
+///
+/// In a sandboxed child, which cannot create files, `path` and `data` go
+/// to the parent instead, which writes them if `path` is under one of the
+/// directories given to [`isolate_with_outputs`] and fails the run if not.
+pub fn write_output(path: &Path, data: &[u8]) -> io::Result<()> {
+    let mut guard = OUTPUT_PIPE.lock().unwrap_or_else(|e| e.into_inner());
+    match guard.as_mut() {
+        Some(pipe) => {
+            let path = path.as_os_str().as_bytes();
+            let mut frame = Vec::with_capacity(12 + path.len() + data.len());
+            frame.extend_from_slice(&(path.len() as u32).to_le_bytes());
+            frame.extend_from_slice(path);
+            frame.extend_from_slice(&(data.len() as u64).to_le_bytes());
+            frame.extend_from_slice(data);
+            pipe.write_all(&frame)
+        }
+        None => {
+            drop(guard);
+            write_file(path, data)
+        }
+    }
+}
+
+fn write_file(path: &Path, data: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, data)
+}
+
+/// Run `child` in a forked child process under `limits`, copying its
+/// stdout into `out` as it comes. Returns the child's exit status, which
+/// is what `child` returned unless it panicked ([`PANICKED`]) or could not
+/// be confined ([`CONFINE_FAILED`]). The child's stderr is the caller's.
+pub fn isolate(
+    limits: &Limits,
+    out: &mut dyn Write,
+    child: impl FnOnce() -> i32,
+) -> std::result::Result<i32, SandboxError> {
+    isolate_with_outputs(limits, &[], out, child)
+}
+
+/// [`isolate`], letting the child write files under `dirs` through
+/// [`write_output`]. The directories are created up front.
+pub fn isolate_with_outputs(
+    limits: &Limits,
+    dirs: &[PathBuf],
+    out: &mut dyn Write,
+    child: impl FnOnce() -> i32,
+) -> std::result::Result<i32, SandboxError> {
+    for dir in dirs {
+        fs::create_dir_all(dir).map_err(SandboxError::Output)?;
+    }
+    // Anything still buffered would be written by both processes.
+    io::stdout().flush().map_err(SandboxError::Output)?;
+
+    let [stdout_read, stdout_write] = pipe().map_err(SandboxError::Spawn)?;
+    let [files_read, files_write] = match pipe() {
+        Ok(fds) => fds,
+        Err(e) => {
+            close(&[stdout_read, stdout_write]);
+            return Err(SandboxError::Spawn(e));
+        }
+    };
+
+    // SAFETY: the child only runs `child` and exits; see the module docs
+    // for what the caller must not have going on in other threads.
+    let pid = unsafe { libc::fork() };
+    if pid < 0 {
+        let err = io::Error::last_os_error();
+        close(&[stdout_read, stdout_write, files_read, files_write]);
+        return Err(SandboxError::Spawn(err));
+    }
+    if pid == 0 {
+        close(&[stdout_read, files_read]);
+        // SAFETY: `stdout_write` is open in the child; stdout becomes the
+        // pipe's write end.
+        unsafe {
+            libc::dup2(stdout_write, libc::STDOUT_FILENO);
+            libc::close(stdout_write);
+        }
+        // SAFETY: `files_write` is open and nothing else owns it.
+        let files = unsafe { fs::File::from_raw_fd(files_write) };
+        *OUTPUT_PIPE.lock().unwrap_or_else(|e| e.into_inner()) = Some(files);
+        let status = match confine(limits) {
+            Ok(()) => panic::catch_unwind(AssertUnwindSafe(child)).unwrap_or(PANICKED),
+            Err(e) => {
+                eprintln!("Error: cannot confine sandboxed child: {e}");
+                CONFINE_FAILED
+            }
+        };
+        let _ = io::stdout().flush();
+        // SAFETY: leaves without unwinding back into the parent's frames.
+        unsafe { libc::_exit(status) }
+    }
+
+    close(&[stdout_write, files_write]);
+    // SAFETY: the read ends are ours and owned by these files from here on.
+    let (mut stdout, files) = unsafe {
+        (
+            fs::File::from_raw_fd(stdout_read),
+            fs::File::from_raw_fd(files_read),
+        )
+    };
+    let dirs = dirs.to_vec();
+    let writer = thread::spawn(move || receive_outputs(files, &dirs));
+    let (stop, stopped) = mpsc::channel::<()>();
+    let watchdog = limits.wall_seconds.map(|seconds| {
+        thread::spawn(move || {
+            let expired = matches!(
+                stopped.recv_timeout(Duration::from_secs(seconds)),
+                Err(RecvTimeoutError::Timeout)
+            );
+            if expired {
+                // SAFETY: the parent only reaps `pid` after this thread is
+                // joined, so it still names our child.
+                unsafe { libc::kill(pid, libc::SIGKILL) };
+            }
+            expired
+        })
+    });
+
+    let copied = io::copy(&mut stdout, out);
+    drop(stdout);
+    if copied.is_err() {
+        // It would block on a full pipe nobody reads.
+        // SAFETY: `pid` is our child and not yet reaped.
+        unsafe { libc::kill(pid, libc::SIGKILL) };
+    }
+    let exited = wait_exited(pid);
+    drop(stop);
+    let timed_out = watchdog.is_some_and(|w| w.join().unwrap_or(false));
+    let status = wait(pid).map_err(SandboxError::Spawn);
+    let written = writer
+        .join()
+        .unwrap_or_else(|_| Err(io::Error::other("output writer panicked")));
+    exited.map_err(SandboxError::Spawn)?;
+    let status = status?;
+    if timed_out {
+        return Err(SandboxError::TimedOut(limits.wall_seconds.unwrap_or(0)));
+    }
+    copied.map_err(SandboxError::Output)?;
+    written.map_err(SandboxError::Output)?;
+    if libc::WIFSIGNALED(status) {
+        Err(SandboxError::Killed(libc::WTERMSIG(status)))
+    } else {
+        Ok(libc::WEXITSTATUS(status))
+    }
+}
+
+fn pipe() -> io::Result<[libc::c_int; 2]> {
+    let mut fds = [0; 2];
+    // SAFETY: `fds` has room for the two descriptors pipe() fills in.
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(fds)
+}
+
+fn close(fds: &[libc::c_int]) {
+    for &fd in fds {
+        // SAFETY: each descriptor is ours, open, and not used again.
+        unsafe { libc::close(fd) };
+    }
+}
+
+/// Write the files a child sends until it closes the pipe. A file outside
+/// `dirs` is not written, and fails the run once the pipe is drained.
+fn receive_outputs(mut pipe: fs::File, dirs: &[PathBuf]) -> io::Result<()> {
+    let mut result = Ok(());
+    loop {
+        let mut len = [0; 4];
+        match pipe.read_exact(&mut len) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return result,
+            Err(e) => return Err(e),
+        }
+        let mut path = vec![0; u32::from_le_bytes(len) as usize];
+        pipe.read_exact(&mut path)?;
+        let path = PathBuf::from(std::ffi::OsString::from_vec(path));
+        let mut len = [0; 8];
+        pipe.read_exact(&mut len)?;
+        let len = u64::from_le_bytes(len);
+        if !is_under(&path, dirs) {
+            io::copy(&mut (&mut pipe).take(len), &mut io::sink())?;
+            if result.is_ok() {
+                result = Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    format!(
+                        "refused to write {} outside the output directories",
+                        path.display()
+                    ),
+                ));
+            }
+            continue;
+        }
+        let mut data = Vec::new();
+        (&mut pipe).take(len).read_to_end(&mut data)?;
+        if let Err(e) = write_file(&path, &data) {
+            if result.is_ok() {
+                result = Err(e);
+            }
+        }
+    }
+}
+
+/// Whether `path` is inside one of `dirs`, judged on the names alone.
+fn is_under(path: &Path, dirs: &[PathBuf]) -> bool {
+    let plain = path.components().all(|c| {
+        matches!(
+            c,
+            Component::Normal(_) | Component::CurDir | Component::RootDir
+        )
+    });
+    plain && dirs.iter().any(|dir| path.starts_with(dir) && path != dir)
+}
+
+/// Block until `pid` has exited, leaving it to be reaped.
+fn wait_exited(pid: libc::pid_t) -> io::Result<()> {
+    loop {
+        // SAFETY: `info` is a valid out pointer for waitid.
+        let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+        // SAFETY: `pid` is our child.
+        let r = unsafe {
+            libc::waitid(
+                libc::P_PID,
+                pid as libc::id_t,
+                &mut info,
+                libc::WEXITED | libc::WNOWAIT,
+            )
+        };
+        if r == 0 {
+            return Ok(());
+        }
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+}
+
+/// Reap `pid`, returning its wait status.
+fn wait(pid: libc::pid_t) -> io::Result<libc::c_int> {
+    let mut status = 0;
+    loop {
+        // SAFETY: `pid` is our child and `status` is a valid out pointer.
+        if unsafe { libc::waitpid(pid, &mut status, 0) } >= 0 {
+            return Ok(status);
+        }
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+}
+
+/// The bytes of a file to parse in a sandbox. Reading happens in the
+/// caller's process; parsing, and everything done with the parsed file,
+/// in the child.
+pub struct SandboxedFile {
+    data: Vec<u8>,
+    limits: Limits,
+}
+
+impl SandboxedFile {
+    /// Take `data` for parsing later, under the default [`Limits`].
+    pub fn new(data: Vec<u8>) -> Self {
+        SandboxedFile {
+            data,
+            limits: Limits::default(),
+        }
+    }
+
+    /// Read the file at `path`; nothing is parsed yet.
+    pub fn open_path(path: &Path) -> Result<Self> {
+        let data = fs::read(path).map_err(|e| Error::Io(e.to_string()))?;
+        Ok(Self::new(data))
+    }
+
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Parse the file in a child and call `f` on it there. What `f` writes
+    /// comes back into `out`, up to the point the child failed if it did.
+    /// A file that does not parse, or an `f` that fails, is reported on
+    /// the child's stderr and comes back as [`SandboxError::Exited`].
+    pub fn run<F>(&self, out: &mut dyn Write, f: F) -> std::result::Result<(), SandboxError>
+    where
+        F: FnOnce(&File, &mut dyn Write) -> io::Result<()>,
+    {
+        let status = isolate(&self.limits, out, || {
+            let abc = match File::open(self.data.clone()) {
+                Ok(abc) => abc,
+                Err(e) => {
+                    eprintln!("Error: {e}");
+                    return 1;
+                }
+            };
+            match f(&abc, &mut io::stdout().lock()) {
+                Ok(()) => 0,
+                Err(e) => {
+                    eprintln!("Error: {e}");
+                    1
+                }
+            }
+        })?;
+        match status {
+            0 => Ok(()),
+            status => Err(SandboxError::Exited(status)),
+        }
+    }
+}
+
+/// Apply `limits` and the syscall filter to the calling process.
+fn confine(limits: &Limits) -> io::Result<()> {
+    // A crash should not leave a core dump of a hostile file around.
+    set_limit(libc::RLIMIT_CORE, 0)?;
+    if let Some(bytes) = limits.memory {
+        set_limit(libc::RLIMIT_AS, bytes)?;
+    }
+    if let Some(seconds) = limits.cpu_seconds {
+        set_limit(libc::RLIMIT_CPU, seconds)?;
+    }
+    #[cfg(all(
+        target_os = "linux",
+        any(target_arch = "x86_64", target_arch = "aarch64")
+    ))]
+    seccomp::install()?;
+    Ok(())
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+type Resource = libc::__rlimit_resource_t;
+#[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+type Resource = libc::c_int;
+
+fn set_limit(resource: Resource, value: u64) -> io::Result<()> {
+    let limit = libc::rlimit {
+        rlim_cur: value as libc::rlim_t,
+        rlim_max: value as libc::rlim_t,
+    };
+    // SAFETY: `limit` is a valid rlimit.
+    if unsafe { libc::setrlimit(resource, &limit) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// The syscall allowlist described in the module docs.
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+mod seccomp {
+    use std::io;
+
+    // Classic BPF, as in <linux/filter.h>.
+    const BPF_LD_W_ABS: u16 = 0x20;
+    const BPF_JEQ_K: u16 = 0x15;
+    const BPF_JSET_K: u16 = 0x45;
+    #[cfg(target_arch = "x86_64")]
+    const BPF_JGE_K: u16 = 0x35;
+    const BPF_RET_K: u16 = 0x06;
+
+    // <linux/seccomp.h>
+    const SECCOMP_MODE_FILTER: libc::c_ulong = 2;
+    const SECCOMP_RET_KILL_PROCESS: u32 = 0x8000_0000;
+    const SECCOMP_RET_ERRNO: u32 = 0x0005_0000;
+    const SECCOMP_RET_ALLOW: u32 = 0x7fff_0000;
+
+    // Offsets into `struct seccomp_data`; an argument's low half, on
+    // these little-endian targets.
+    const NR: u32 = 0;
+    const ARCH: u32 = 4;
+    const fn arg(i: u32) -> u32 {
+        16 + 8 * i
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    const AUDIT_ARCH: u32 = 0xc000_003e;
+    #[cfg(target_arch = "aarch64")]
+    const AUDIT_ARCH: u32 = 0xc000_00b7;
+
+    /// Syscall numbers at and above this are the x32 ABI.
+    #[cfg(target_arch = "x86_64")]
+    const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+
+    const WRITE_FLAGS: libc::c_int =
+        libc::O_WRONLY | libc::O_RDWR | libc::O_CREAT | libc::O_TRUNC | libc::O_APPEND;
+
+    enum Rule {
+        Allow(libc::c_long),
+        /// Fail with the given errno, rather than `EPERM`.
+        Fail(libc::c_long, libc::c_int),
+        /// Allow if none of the bits in the mask are set in an argument.
+        AllowUnless(libc::c_long, u32, u32),
+        /// Allow if all of the bits in the mask are set in an argument.
+        AllowIf(libc::c_long, u32, u32),
+        /// Allow if an argument equals a value.
+        AllowEq(libc::c_long, u32, u32),
+    }
+
+    fn rules() -> Vec<Rule> {
+        use Rule::*;
+
+        // SAFETY: getpid has no preconditions.
+        let pid = unsafe { libc::getpid() } as u32;
+        vec![
+            Allow(libc::SYS_read),
+            Allow(libc::SYS_readv),
+            Allow(libc::SYS_pread64),
+            Allow(libc::SYS_write),
+            Allow(libc::SYS_writev),
+            Allow(libc::SYS_close),
+            Allow(libc::SYS_lseek),
+            AllowUnless(libc::SYS_openat, 2, WRITE_FLAGS as u32),
+            Allow(libc::SYS_fstat),
+            Allow(libc::SYS_newfstatat),
+            Allow(libc::SYS_statx),
+            #[cfg(target_arch = "x86_64")]
+            Allow(libc::SYS_stat),
+            #[cfg(target_arch = "x86_64")]
+            Allow(libc::SYS_lstat),
+            Allow(libc::SYS_getdents64),
+            Allow(libc::SYS_readlinkat),
+            Allow(libc::SYS_getcwd),
+            Allow(libc::SYS_faccessat),
+            Allow(libc::SYS_brk),
+            Allow(libc::SYS_mmap),
+            Allow(libc::SYS_munmap),
+            Allow(libc::SYS_mremap),
+            Allow(libc::SYS_mprotect),
+            Allow(libc::SYS_madvise),
+            Allow(libc::SYS_futex),
+            AllowIf(libc::SYS_clone, 0, libc::CLONE_THREAD as u32),
+            Fail(libc::SYS_clone3, libc::ENOSYS),
+            Allow(libc::SYS_set_robust_list),
+            Allow(libc::SYS_rseq),
+            Allow(libc::SYS_sched_yield),
+            Allow(libc::SYS_sched_getaffinity),
+            Allow(libc::SYS_rt_sigaction),
+            Allow(libc::SYS_rt_sigprocmask),
+            Allow(libc::SYS_rt_sigreturn),
+            Allow(libc::SYS_sigaltstack),
+            AllowEq(libc::SYS_tgkill, 0, pid),
+            Allow(libc::SYS_getpid),
+            Allow(libc::SYS_gettid),
+            AllowEq(libc::SYS_prlimit64, 0, 0),
+            Allow(libc::SYS_clock_gettime),
+            Allow(libc::SYS_clock_nanosleep),
+            Allow(libc::SYS_nanosleep),
+            Allow(libc::SYS_getrandom),
+            Allow(libc::SYS_uname),
+            Allow(libc::SYS_exit),
+            Allow(libc::SYS_exit_group),
+        ]
+    }
+
+    fn stmt(code: u16, k: u32) -> libc::sock_filter {
+        libc::sock_filter {
+            code,
+            jt: 0,
+            jf: 0,
+            k,
+        }
+    }
+
+    fn jump(code: u16, k: u32, jt: u8, jf: u8) -> libc::sock_filter {
+        libc::sock_filter { code, jt, jf, k }
+    }
+
+    const fn errno(e: libc::c_int) -> u32 {
+        SECCOMP_RET_ERRNO | e as u32
+    }
+
+    fn program() -> Vec<libc::sock_filter> {
+        let mut program = vec![
+            stmt(BPF_LD_W_ABS, ARCH),
+            jump(BPF_JEQ_K, AUDIT_ARCH, 1, 0),
+            stmt(BPF_RET_K, SECCOMP_RET_KILL_PROCESS),
+            stmt(BPF_LD_W_ABS, NR),
+        ];
+        #[cfg(target_arch = "x86_64")]
+        program.extend([
+            jump(BPF_JGE_K, X32_SYSCALL_BIT, 0, 1),
+            stmt(BPF_RET_K, SECCOMP_RET_KILL_PROCESS),
+        ]);
+        let deny = errno(libc::EPERM);
+        // Each rule checks the syscall number, still in the accumulator,
+        // and skips itself if it does not match; a match always returns.
+        for rule in rules() {
+            match rule {
+                Rule::Allow(nr) => program.extend([
+                    jump(BPF_JEQ_K, nr as u32, 0, 1),
+                    stmt(BPF_RET_K, SECCOMP_RET_ALLOW),
+                ]),
+                Rule::Fail(nr, e) => {
+                    program.extend([jump(BPF_JEQ_K, nr as u32, 0, 1), stmt(BPF_RET_K, errno(e))])
+                }
+                Rule::AllowUnless(nr, i, mask) => program.extend([
+                    jump(BPF_JEQ_K, nr as u32, 0, 4),
+                    stmt(BPF_LD_W_ABS, arg(i)),
+                    jump(BPF_JSET_K, mask, 0, 1),
+                    stmt(BPF_RET_K, deny),
+                    stmt(BPF_RET_K, SECCOMP_RET_ALLOW),
+                ]),
+                Rule::AllowIf(nr, i, mask) => program.extend([
+                    jump(BPF_JEQ_K, nr as u32, 0, 4),
+                    stmt(BPF_LD_W_ABS, arg(i)),
+                    jump(BPF_JSET_K, mask, 0, 1),
+                    stmt(BPF_RET_K, SECCOMP_RET_ALLOW),
+                    stmt(BPF_RET_K, deny),
+                ]),
+                Rule::AllowEq(nr, i, value) => program.extend([
+                    jump(BPF_JEQ_K, nr as u32, 0, 4),
+                    stmt(BPF_LD_W_ABS, arg(i)),
+                    jump(BPF_JEQ_K, value, 0, 1),
+                    stmt(BPF_RET_K, SECCOMP_RET_ALLOW),
+                    stmt(BPF_RET_K, deny),
+                ]),
+            }
+        }
+        program.push(stmt(BPF_RET_K, deny));
+        program
+    }
+
+    pub(super) fn install() -> io::Result<()> {
+        let mut program = program();
+        let fprog = libc::sock_fprog {
+            len: program.len() as libc::c_ushort,
+            filter: program.as_mut_ptr(),
+        };
+        // SAFETY: prctl with these options reads only its integer
+        // arguments and `fprog`, which points at `program`.
+        unsafe {
+            let (on, off): (libc::c_ulong, libc::c_ulong) = (1, 0);
+            if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, on, off, off, off) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            if libc::prctl(
+                libc::PR_SET_SECCOMP,
+                SECCOMP_MODE_FILTER,
+                &fprog as *const libc::sock_fprog,
+            ) != 0
+            {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+}
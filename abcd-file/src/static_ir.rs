@@ -0,0 +1,281 @@
+//! Text listing of static (PandaAssembly) files in `pandasm` syntax.
+//!
+//! Unlike dynamic files, static ones carry real types, so the listing is
+//! mostly declarations: records with typed fields and functions with typed
+//! signatures. abcd-isa decodes the dynamic instruction set only, and the
+//! static one assigns its opcodes differently, so instructions come from a
+//! [`StaticDecoder`] passed to [`write_with`]; what it does not decode is
+//! dumped as hex. Either way the code is split at the places the file
+//! itself marks: try ranges and handlers become pandasm labels with
+//! `.catch` directives, and, with the `debug-info` feature, each source
+//! line starts with a `# line` comment.
+//!
+//! ```text
+//! .record Point {
+//!     i32 x
+//!     i32 y
+//! }
+//!
+//! .function i32 Point.sum(Point a0) {
+//!     # vregs: 1, args: 1, code_size: 4
+//! try_begin_0:
+//!     0x0000  ...
+//! try_end_0:
+//! handler_0_0:
+//!     0x0002  ...
+//!     .catchall try_begin_0, try_end_0, handler_0_0
+//! }
+//! ```
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use crate::method::Method;
+use crate::names::pandasm_name;
+use crate::types::{ACC_ABSTRACT, ACC_NATIVE, ACC_STATIC, TypeId, ValueType};
+use crate::{EntityId, File};
+
+/// Bytes per line of a hex dump.
+const HEX_ROW: usize = 16;
+
+/// Decodes static instructions for [`write_with`].
+pub trait StaticDecoder {
+    /// The instruction at the start of `code`, as text, and its size in
+    /// bytes; `None` if it is not one this decoder knows.
+    fn decode(&self, code: &[u8]) -> Option<(String, usize)>;
+}
+
+/// The `pandasm` name of `ty`: primitives as `i32`, `u1` and so on,
+/// classes by their dotted descriptor.
+pub fn type_name(file: &File, ty: ValueType) -> String {
+    match ty {
+        ValueType::Primitive(TypeId::Tagged) => "any".to_string(),
+        ValueType::Primitive(id) => id.to_string(),
+        ValueType::Class(off) => pandasm_name(&file.get_string_lossy(off)),
+    }
+}
+
+/// Write every record and function of `file`, with instructions as hex.
+/// External records are listed as `<external>` declarations, the way
+/// `pandasm` input declares them.
+pub fn write(file: &File, out: impl Write) -> io::Result<()> {
+    write_with(file, None, out)
+}
+
+/// [`write`], with instructions decoded by `decoder` where it can.
+pub fn write_with(
+    file: &File,
+    decoder: Option<&dyn StaticDecoder>,
+    mut out: impl Write,
+) -> io::Result<()> {
+    writeln!(out, "# Static ABC (PandaAssembly)")?;
+    writeln!(out, "# Version: {}", file.version())?;
+    writeln!(out, "# Classes: {}", file.num_classes())?;
+    writeln!(out)?;
+
+    for class_off in file.class_offsets() {
+        let name = pandasm_name(&file.get_string_lossy(class_off));
+        if file.is_external(class_off) {
+            writeln!(out, ".record {name} <external>\n")?;
+            continue;
+        }
+        let class = match file.class(class_off) {
+            Ok(c) => c,
+            Err(e) => {
+                writeln!(out, "# Error parsing class at {class_off}: {e}\n")?;
+                continue;
+            }
+        };
+
+        writeln!(out, ".record {name} {{")?;
+        for field_off in class.field_offsets() {
+            let field = match file.field(field_off) {
+                Ok(f) => f,
+                Err(e) => {
+                    writeln!(out, "    # Error parsing field at {field_off}: {e}")?;
+                    continue;
+                }
+            };
+            let attrs = match field.access_flags() & ACC_STATIC != 0 {
+                true => " <static>",
+                false => "",
+            };
+            writeln!(
+                out,
+                "    {} {}{attrs}",
+                type_name(file, field.value_type()),
+                file.get_string_lossy(field.name_off())
+            )?;
+        }
+        writeln!(out, "}}\n")?;
+
+        for method_off in class.method_offsets() {
+            match file.method(method_off) {
+                Ok(method) => write_function(file, &name, &method, decoder, &mut out)?,
+                Err(e) => writeln!(out, "# Error parsing method at {method_off}: {e}\n")?,
+            }
+        }
+    }
+    out.flush()
+}
+
+fn write_function(
+    file: &File,
+    class_name: &str,
+    method: &Method<'_>,
+    decoder: Option<&dyn StaticDecoder>,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let sig = method.signature();
+    let params: Vec<String> = sig
+        .params
+        .iter()
+        .enumerate()
+        .map(|(i, &ty)| format!("{} a{i}", type_name(file, ty)))
+        .collect();
+    write!(
+        out,
+        ".function {} {class_name}.{}({})",
+        type_name(file, sig.ret),
+        file.get_string_lossy(method.name_off()),
+        params.join(", ")
+    )?;
+
+    let flags = method.access_flags();
+    let attrs: Vec<&str> = [
+        (ACC_STATIC, "static"),
+        (ACC_NATIVE, "native"),
+        (ACC_ABSTRACT, "noimpl"),
+    ]
+    .into_iter()
+    .filter(|&(bit, _)| flags & bit != 0)
+    .map(|(_, attr)| attr)
+    .collect();
+    if !attrs.is_empty() {
+        write!(out, " <{}>", attrs.join(", "))?;
+    }
+
+    let Some(code_off) = method.code_off() else {
+        return writeln!(out, "\n");
+    };
+    writeln!(out, " {{")?;
+    match file.code(code_off) {
+        Ok(code) => {
+            let insns = code.instructions();
+            writeln!(
+                out,
+                "    # vregs: {}, args: {}, code_size: {}",
+                code.num_vregs(),
+                code.num_args(),
+                insns.len()
+            )?;
+            let try_blocks = code.try_blocks();
+            let mut marks = line_marks(file, method.offset());
+            let mut catches = Vec::new();
+            for (i, tb) in try_blocks.iter().enumerate() {
+                let (begin, end) = (format!("try_begin_{i}"), format!("try_end_{i}"));
+                for (j, cb) in tb.catches.iter().enumerate() {
+                    let handler = format!("handler_{i}_{j}");
+                    catches.push(match cb.type_idx {
+                        u32::MAX => format!("    .catchall {begin}, {end}, {handler}"),
+                        idx => format!(
+                            "    .catch {}, {begin}, {end}, {handler}",
+                            catch_type(file, method.offset(), idx)
+                        ),
+                    });
+                    mark(&mut marks, cb.handler_pc, format!("{handler}:"));
+                }
+                mark(&mut marks, tb.start_pc, format!("{begin}:"));
+                mark(&mut marks, tb.start_pc + tb.length, format!("{end}:"));
+            }
+            write_code(insns, &marks, decoder, out)?;
+            for line in catches {
+                writeln!(out, "{line}")?;
+            }
+        }
+        Err(e) => writeln!(out, "    # Error parsing code at {code_off}: {e}")?,
+    }
+    writeln!(out, "}}\n")
+}
+
+/// Lines to write before the instruction at each pc.
+type Marks = BTreeMap<u32, Vec<String>>;
+
+fn mark(marks: &mut Marks, pc: u32, line: String) {
+    marks.entry(pc).or_default().push(line);
+}
+
+/// A `# line` comment where each source line of the method starts.
+#[cfg(feature = "debug-info")]
+fn line_marks(file: &File, method_off: EntityId) -> Marks {
+    let mut marks = Marks::new();
+    if let Ok(debug) = file.debug_info() {
+        for entry in debug.line_table(method_off) {
+            mark(
+                &mut marks,
+                entry.offset,
+                format!("    # line {}", entry.line),
+            );
+        }
+    }
+    marks
+}
+
+#[cfg(not(feature = "debug-info"))]
+fn line_marks(_file: &File, _method_off: EntityId) -> Marks {
+    Marks::new()
+}
+
+/// A catch block's exception class, a class index in the method's region.
+/// The accessor reads catch-all blocks as index `u32::MAX`.
+fn catch_type(file: &File, method_off: EntityId, type_idx: u32) -> String {
+    let class = u16::try_from(type_idx)
+        .ok()
+        .and_then(|idx| file.resolve_class_index(method_off, idx));
+    match class {
+        Some(off) => pandasm_name(&file.get_string_lossy(off)),
+        None => format!("type={type_idx}"),
+    }
+}
+
+/// Write `code` one instruction per line where `decoder` knows it, else
+/// as hex rows that stop at the next mark, with each mark's lines before
+/// the instruction at its pc. Marks past the end come last.
+fn write_code(
+    code: &[u8],
+    marks: &Marks,
+    decoder: Option<&dyn StaticDecoder>,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let mut marks = marks.iter().peekable();
+    let mut pc = 0;
+    while pc < code.len() {
+        while let Some((_, lines)) = marks.next_if(|&(&at, _)| at as usize <= pc) {
+            for line in lines {
+                writeln!(out, "{line}")?;
+            }
+        }
+        let decoded = decoder
+            .and_then(|d| d.decode(&code[pc..]))
+            .filter(|&(_, size)| size > 0 && pc + size <= code.len());
+        if let Some((text, size)) = decoded {
+            writeln!(out, "    {pc:#06x}  {text}")?;
+            pc += size;
+            continue;
+        }
+        let next_mark = marks.peek().map_or(code.len(), |&(&at, _)| at as usize);
+        let end = next_mark.min(pc + HEX_ROW).min(code.len());
+        write!(out, "    {pc:#06x} ")?;
+        for b in &code[pc..end] {
+            write!(out, " {b:02x}")?;
+        }
+        writeln!(out)?;
+        pc = end;
+    }
+    for (_, lines) in marks {
+        for line in lines {
+            writeln!(out, "{line}")?;
+        }
+    }
+    Ok(())
+}
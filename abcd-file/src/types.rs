@@ -53,6 +53,29 @@ impl From<TypeId> for u8 {
     }
 }
 
+/// The type of a field, argument or return value: a primitive, or a
+/// reference to the class at an offset.
+///
+/// Dynamic files type everything `any`, so this is only informative for
+/// static (PandaAssembly) files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValueType {
+    Primitive(TypeId),
+    Class(crate::EntityId),
+}
+
+impl ValueType {
+    /// Decode a field's type word, which holds a [`TypeId`] for primitives
+    /// and the class offset for references. Class offsets lie past the
+    /// header, so they never collide with a type ID.
+    pub fn from_field_encoding(v: u32) -> Self {
+        match u8::try_from(v).ok().and_then(TypeId::from_u8) {
+            Some(id) => Self::Primitive(id),
+            None => Self::Class(crate::EntityId(v)),
+        }
+    }
+}
+
 /// Source language.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(u8)]
@@ -100,6 +123,12 @@ pub enum FunctionKind {
 }
 
 impl FunctionKind {
+    /// The kind stored in bits 8..16 of a method's access flags. Unknown
+    /// values read as [`FunctionKind::None`].
+    pub fn from_access_flags(flags: u32) -> Self {
+        Self::from_u8((flags >> 8) as u8).unwrap_or(Self::None)
+    }
+
     pub fn from_u8(v: u8) -> Option<Self> {
         match v {
             0x0 => Some(Self::None),
@@ -70,6 +70,19 @@ pub fn decode_sleb128(data: &[u8], offset: usize) -> Result<(i64, usize), ParseE
     Ok((result, pos - offset))
 }
 
+/// Append `value` to `out` as unsigned LEB128.
+pub fn encode_uleb128(mut value: u64, out: &mut Vec<u8>) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     //! Tests migrated from arkcompiler runtime_core/libpandabase/tests/leb128_test.cpp
@@ -299,4 +312,16 @@ mod tests {
         let data = [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x03];
         assert!(decode_sleb128(&data, 0).is_err());
     }
+
+    #[test]
+    fn uleb128_encode_round_trips() {
+        for value in [0, 0x7f, 0x80, 0x2d7f, 0xffff, u64::from(u32::MAX), u64::MAX] {
+            let mut out = Vec::new();
+            encode_uleb128(value, &mut out);
+            assert_eq!(decode_uleb128(&out, 0).unwrap(), (value, out.len()));
+        }
+        let mut out = Vec::new();
+        encode_uleb128(0x2d7f, &mut out);
+        assert_eq!(out, [0xff, 0x5a]);
+    }
 }
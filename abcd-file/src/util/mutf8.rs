@@ -86,6 +86,28 @@ pub fn decode_mutf8(data: &[u8], offset: usize) -> Result<String, ParseError> {
     Ok(result)
 }
 
+/// Encode `s` as Modified UTF-8, without the terminating NUL: U+0000 as
+/// 0xC0 0x80, and supplementary characters as two 3-byte surrogates.
+pub fn encode_mutf8(s: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(s.len());
+    for unit in s.encode_utf16() {
+        let unit = u32::from(unit);
+        match unit {
+            0x01..=0x7f => out.push(unit as u8),
+            0x00 | 0x80..=0x7ff => {
+                out.push(0xc0 | (unit >> 6) as u8);
+                out.push(0x80 | (unit & 0x3f) as u8);
+            }
+            _ => {
+                out.push(0xe0 | (unit >> 12) as u8);
+                out.push(0x80 | ((unit >> 6) & 0x3f) as u8);
+                out.push(0x80 | (unit & 0x3f) as u8);
+            }
+        }
+    }
+    out
+}
+
 #[cfg(test)]
 mod tests {
     //! Tests migrated from arkcompiler runtime_core/libpandabase/tests/utf_test.cpp
@@ -234,4 +256,18 @@ mod tests {
         // Lone high surrogate → replacement char
         assert_eq!(result, "\u{FFFD}");
     }
+
+    #[test]
+    fn mutf8_encode_round_trips() {
+        assert_eq!(encode_mutf8("\0"), [0xc0, 0x80]);
+        assert_eq!(
+            encode_mutf8("\u{10437}"),
+            [0xed, 0xa0, 0x81, 0xed, 0xb0, 0xb7]
+        );
+        for s in ["", "hello", "§3", "\u{ffc3}", "a\0b", "👳 \u{10437}"] {
+            let mut data = encode_mutf8(s);
+            data.push(0);
+            assert_eq!(decode_mutf8(&data, 0).unwrap(), s);
+        }
+    }
 }
@@ -0,0 +1,267 @@
+//! Findings of checks over a file, and their SARIF form.
+//!
+//! Checks that look at a whole file collect what they find into a
+//! [`ValidationReport`]: each [`Finding`] names the rule that produced
+//! it, how serious it is, and the method it is about. The report runs two
+//! kinds of check itself: [`check_code`](ValidationReport::check_code) for
+//! code that does not decode or names nothing, and
+//! [`check_digests`](ValidationReport::check_digests) for methods off a
+//! build's allowlist. Other tools add their own findings with
+//! [`push`](ValidationReport::push).
+//! [`ValidationReport::to_sarif`] writes the report as a SARIF 2.1.0 log,
+//! which code-review tools and security dashboards read directly.
+//!
+//! ```no_run
+//! use abcd_file::validation::{Finding, Level, ValidationReport};
+//!
+//! let mut report = ValidationReport::new("modules.abc");
+//! report.push(Finding {
+//!     rule: "untrusted-method".into(),
+//!     level: Level::Error,
+//!     message: "digest not on the allowlist".into(),
+//!     method: Some(abcd_file::EntityId(0x1234)),
+//!     location: Some("Lentry/Index;.render".into()),
+//! });
+//! println!("{}", serde_json::to_string_pretty(&report.to_sarif()).unwrap());
+//! ```
+
+use std::fmt::Write as _;
+
+use abcd_isa::IdKind;
+use serde_json::json;
+
+use crate::EntityId;
+use crate::File;
+use crate::digest::MethodDigest;
+
+/// Rule of findings about code that does not decode for the file's
+/// version.
+pub const UNKNOWN_OPCODES: &str = "unknown-opcodes";
+/// Rule of findings about ID operands that name no entity in the file.
+pub const UNRESOLVED_ENTITIES: &str = "unresolved-entities";
+/// Rule of findings about methods whose digest is not on the allowlist.
+pub const UNTRUSTED_METHOD: &str = "untrusted-method";
+
+/// SARIF version [`ValidationReport::to_sarif`] writes.
+pub const SARIF_VERSION: &str = "2.1.0";
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// How serious a finding is; SARIF's `level`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Level {
+    /// The SARIF name of the level.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warning => "warning",
+            Level::Note => "note",
+        }
+    }
+}
+
+/// One thing a check found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    /// Stable identifier of the check, such as `untrusted-method`.
+    /// Dashboards group and suppress findings by it.
+    pub rule: String,
+    pub level: Level,
+    pub message: String,
+    /// Offset of the method the finding is about, if it is about one.
+    pub method: Option<EntityId>,
+    /// Human-readable name of what the finding is about, usually
+    /// `class.method`.
+    pub location: Option<String>,
+}
+
+/// Findings about one file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// Path or URI of the checked file, as the SARIF artifact location.
+    pub artifact: String,
+    pub findings: Vec<Finding>,
+}
+
+impl ValidationReport {
+    /// An empty report about `artifact`.
+    pub fn new(artifact: impl Into<String>) -> Self {
+        Self {
+            artifact: artifact.into(),
+            findings: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, finding: Finding) {
+        self.findings.push(finding);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.findings.is_empty()
+    }
+
+    /// Check the code of the methods in `file`'s local classes whose class
+    /// name `keep` accepts. Code that does not decode, or has instructions
+    /// the file's version predates, is an [`UNKNOWN_OPCODES`] error; an ID
+    /// operand that resolves to nothing, an [`UNRESOLVED_ENTITIES`] error.
+    /// Findings are located at the method and named `class.method`.
+    pub fn check_code(&mut self, file: &File, keep: &dyn Fn(&str) -> bool) {
+        let profile = file.isa_profile();
+        for class_off in file.class_offsets() {
+            if file.is_external(class_off) {
+                continue;
+            }
+            let Ok(class) = file.class(class_off) else {
+                continue;
+            };
+            let class_name = file.get_string_lossy(class_off);
+            if !keep(&class_name) {
+                continue;
+            }
+            for method_off in class.method_offsets() {
+                let Some(code) = file
+                    .method(method_off)
+                    .ok()
+                    .and_then(|m| m.code_off())
+                    .and_then(|off| file.code(off).ok())
+                else {
+                    continue;
+                };
+                let name = format!("{class_name}.{}", file.method_name_lossy(method_off));
+                let mut found = |rule: &str, message: String| {
+                    self.push(Finding {
+                        rule: rule.into(),
+                        level: Level::Error,
+                        message: format!("{name}: {message}"),
+                        method: Some(method_off),
+                        location: Some(name.clone()),
+                    });
+                };
+                let decoded = match &profile {
+                    Ok(profile) => profile
+                        .decode(code.instructions())
+                        .map_err(|e| e.to_string()),
+                    Err(e) => Err(e.to_string()),
+                };
+                let decoded = match decoded {
+                    Ok(decoded) => decoded,
+                    Err(e) => {
+                        found(UNKNOWN_OPCODES, format!("undecodable code: {e}"));
+                        continue;
+                    }
+                };
+                for (bc, offset) in &decoded {
+                    let (_, _, num_args) = bc.emit_args();
+                    for id in (0..num_args).filter_map(|i| bc.typed_id(i)) {
+                        let index = id.id().0 as u16;
+                        let resolved = match id.kind() {
+                            IdKind::LiteralArray => file
+                                .literal_for(method_off)
+                                .ok()
+                                .and_then(|l| l.array_id(index)),
+                            _ => file.resolve_offset_by_index(method_off, index),
+                        };
+                        if resolved.is_none() {
+                            let message = format!("`{bc}` at {offset:#x} names no entity");
+                            found(UNRESOLVED_ENTITIES, message);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Check method digests, as [`method_digests`](crate::digest::method_digests)
+    /// gives them, against a trusted build: each method `trusted` refuses
+    /// is an [`UNTRUSTED_METHOD`] error. Without an allowlist there is
+    /// nothing to compare against, and every method is an
+    /// [`UNTRUSTED_METHOD`] note with its digest, so the log still lists
+    /// what a later run will be checked against.
+    pub fn check_digests<const N: usize>(
+        &mut self,
+        digests: &[MethodDigest<N>],
+        trusted: Option<&dyn Fn(&[u8; N]) -> bool>,
+    ) {
+        for d in digests {
+            let hex = d.digest.iter().fold(String::new(), |mut hex, b| {
+                let _ = write!(hex, "{b:02x}");
+                hex
+            });
+            let (level, message) = match trusted {
+                Some(trusted) if trusted(&d.digest) => continue,
+                Some(_) => (
+                    Level::Error,
+                    format!("{}: digest {hex} is not on the allowlist", d.name),
+                ),
+                None => (
+                    Level::Note,
+                    format!("{}: digest {hex}, no allowlist to check against", d.name),
+                ),
+            };
+            self.push(Finding {
+                rule: UNTRUSTED_METHOD.into(),
+                level,
+                message,
+                method: Some(d.method),
+                location: Some(d.name.clone()),
+            });
+        }
+    }
+
+    /// The report as a SARIF 2.1.0 log with a single run.
+    ///
+    /// Rules are listed in the order they first appear. A finding about a
+    /// method points into the file at the method's offset (`byteOffset`)
+    /// and names it as a logical location.
+    pub fn to_sarif(&self) -> serde_json::Value {
+        let mut rules: Vec<&str> = Vec::new();
+        for finding in &self.findings {
+            if !rules.contains(&finding.rule.as_str()) {
+                rules.push(&finding.rule);
+            }
+        }
+        let results: Vec<_> = self
+            .findings
+            .iter()
+            .map(|f| {
+                let mut physical = json!({ "artifactLocation": { "uri": self.artifact } });
+                if let Some(method) = f.method {
+                    physical["region"] = json!({ "byteOffset": method.0 });
+                }
+                let mut location = json!({ "physicalLocation": physical });
+                if let Some(name) = &f.location {
+                    location["logicalLocations"] =
+                        json!([{ "fullyQualifiedName": name, "kind": "function" }]);
+                }
+                json!({
+                    "ruleId": f.rule,
+                    "ruleIndex": rules.iter().position(|r| *r == f.rule),
+                    "level": f.level.as_str(),
+                    "message": { "text": f.message },
+                    "locations": [location],
+                })
+            })
+            .collect();
+        json!({
+            "$schema": SARIF_SCHEMA,
+            "version": SARIF_VERSION,
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": "abcd",
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "rules": rules.iter().map(|id| json!({ "id": id })).collect::<Vec<_>>(),
+                    }
+                },
+                "artifacts": [{ "location": { "uri": self.artifact } }],
+                "results": results,
+            }]
+        })
+    }
+}
@@ -6,3 +6,26 @@ use abcd_isa::Version;
 pub fn has_literal_array_in_header(ver: &Version) -> bool {
     unsafe { abcd_file_sys::abc_contains_literal_array_in_header(ver.as_bytes().as_ptr()) != 0 }
 }
+
+/// First version of API 11, whose class fields and literals reference
+/// literal arrays by file offset.
+///
+/// Files for API 9 and 10 (`9.0.0.0`) and the older `0.0.0.x` line store an
+/// index into the header's literal array table instead: `moduleRecordIdx`
+/// fields and
+/// [`LiteralTag::LiteralArray`](crate::literal::LiteralTag::LiteralArray)
+/// values must be resolved through that table. They also carry no
+/// [`LiteralTag::MethodAffiliate`](crate::literal::LiteralTag::MethodAffiliate)
+/// after method literals.
+pub const FIRST_OFFSET_LITERAL_ID_VERSION: Version = Version::new(11, 0, 0, 0);
+
+/// Check if files of the given version reference literal arrays by header index.
+pub fn uses_literal_array_index(ver: &Version) -> bool {
+    *ver < FIRST_OFFSET_LITERAL_ID_VERSION
+}
+
+/// Check if files of the given version follow each method in an object or
+/// class literal with a `MethodAffiliate` entry holding its parameter count.
+pub fn has_method_affiliates(ver: &Version) -> bool {
+    *ver >= FIRST_OFFSET_LITERAL_ID_VERSION
+}
@@ -0,0 +1,24 @@
+use abcd_file::{BackendInfo, backend_info};
+
+#[test]
+fn reports_the_enabled_features() {
+    assert_eq!(
+        backend_info(),
+        BackendInfo {
+            builder: cfg!(feature = "builder"),
+            debug_info: cfg!(feature = "debug-info"),
+            module: cfg!(feature = "module"),
+        }
+    );
+}
+
+#[test]
+fn capability_bits_are_distinct() {
+    let bits = [
+        abcd_file_sys::ABC_CAP_BUILDER,
+        abcd_file_sys::ABC_CAP_DEBUG_INFO,
+        abcd_file_sys::ABC_CAP_MODULE,
+    ];
+    assert_eq!(bits.iter().fold(0, |acc, b| acc | b).count_ones(), 3);
+    assert_eq!(abcd_file_sys::capabilities() & !bits.iter().sum::<u32>(), 0);
+}
@@ -0,0 +1,125 @@
+//! Edits through `CodeEditor` keep jumps, try blocks and lines on target.
+#![cfg(feature = "builder")]
+
+use abcd_file::builder::CatchBlockDef;
+use abcd_file::edit::CodeEditor;
+use abcd_file::{EntityId, Error, File};
+use abcd_isa::{Bytecode, Emitter, Imm, Reg, decode, insn};
+use abcd_testgen::files::GlobalClass;
+
+/// `try { v0 = 1 } catch { v0 = 0 } return v0`, with line 2 for the whole
+/// body and one inline-cache slot recorded. Returns the file and the pc of every instruction.
+fn build_fixture() -> (File, Vec<u32>) {
+    let mut e = Emitter::new();
+    let done = e.create_label();
+    e.emit(insn::Ldai::new(Imm(1)));
+    e.emit(insn::Sta::new(Reg(0)));
+    e.emit(insn::Jmp::new(done));
+    e.emit(insn::Ldai::new(Imm(0)));
+    e.emit(insn::Sta::new(Reg(0)));
+    e.bind(done);
+    e.emit(insn::Lda::new(Reg(0)));
+    e.emit(insn::Return::new());
+    let (bytes, pc) = e.build().unwrap();
+
+    let mut global = GlobalClass::new();
+    let main = global.method_without_code("main");
+    let b = &mut global.builder;
+    let code = b.create_code(1, 3, &bytes);
+    let catch_all = CatchBlockDef {
+        type_class: None,
+        handler_pc: pc[3],
+        code_size: pc[5] - pc[3],
+    };
+    b.code_add_try_block(code, pc[0], pc[3] - pc[0], &[catch_all]);
+    b.method_set_code(main, code);
+    b.method_set_slot_count(main, 1).unwrap();
+    let lnp = b.create_lnp();
+    let debug = b.create_debug_info(lnp, 2);
+    b.lnp_emit_end(lnp);
+    b.method_set_debug_info(main, debug);
+    (global.open(), pc)
+}
+
+fn main_method(abc: &File) -> EntityId {
+    let class = abc.class_id_by_name("L_GLOBAL;").unwrap().unwrap();
+    abc.class(class).unwrap().method_offsets()[0]
+}
+
+#[test]
+fn commit_moves_jumps_try_blocks_and_handlers() {
+    let (abc, pc) = build_fixture();
+    let mut editor = CodeEditor::new(&abc, main_method(&abc)).unwrap();
+    // One byte into the try block, two bytes out of the handler.
+    editor.insert_before(pc[1], &[insn::Ldnull::new()]).unwrap();
+    editor.delete(pc[4]).unwrap();
+    let edited = editor.commit().unwrap();
+
+    let insns: Vec<Bytecode> = decode(&edited.bytes)
+        .unwrap()
+        .into_iter()
+        .map(|(bc, _)| bc)
+        .collect();
+    let mnemonics: Vec<_> = insns.iter().map(Bytecode::mnemonic).collect();
+    assert_eq!(
+        mnemonics,
+        ["ldai", "ldnull", "sta", "jmp", "ldai", "lda", "return"]
+    );
+    // The jump still lands on `lda v0`.
+    assert!(insns[3].semantic_eq(&insn::Jmp::new(abcd_isa::Label(5))));
+
+    let end = pc[6] + 1;
+    assert_eq!(edited.map_pc(pc[1]), Some(pc[1]));
+    assert_eq!(edited.map_pc(pc[2]), Some(pc[2] + 1));
+    assert_eq!(edited.map_pc(pc[4]), edited.map_pc(pc[5]));
+    assert_eq!(edited.map_pc(end), Some(edited.bytes.len() as u32));
+    assert_eq!(edited.map_pc(pc[1] + 1), None);
+
+    let block = &edited.try_blocks[0];
+    assert_eq!((block.start_pc, block.length), (0, pc[3] + 1));
+    let handler = &block.catches[0];
+    assert_eq!(handler.handler_pc, pc[3] + 1);
+    assert_eq!(handler.code_size, pc[5] - pc[3] - 2);
+}
+
+#[test]
+#[cfg(feature = "debug-info")]
+fn commit_moves_the_line_table() {
+    let (abc, pc) = build_fixture();
+    let mut editor = CodeEditor::new(&abc, main_method(&abc)).unwrap();
+    editor
+        .replace(pc[0], &[insn::Ldai::new(Imm(1)), insn::Ldai::new(Imm(2))])
+        .unwrap();
+    let edited = editor.commit().unwrap();
+    let first = edited.line_table[0];
+    assert_eq!((first.offset, first.line), (0, 2));
+}
+
+#[test]
+fn pcs_inside_an_instruction_are_rejected() {
+    let (abc, pc) = build_fixture();
+    let mut editor = CodeEditor::new(&abc, main_method(&abc)).unwrap();
+    assert!(matches!(
+        editor.delete(pc[0] + 1),
+        Err(Error::InvalidPc(p)) if p == pc[0] + 1
+    ));
+}
+
+#[test]
+fn the_slot_count_is_written_back() {
+    let (abc, pc) = build_fixture();
+    let mut editor = CodeEditor::new(&abc, main_method(&abc)).unwrap();
+    // `add2` at slot 2 needs three slots, and one is recorded.
+    editor
+        .insert_before(pc[6], &[insn::Add2::new(Imm(2), Reg(0))])
+        .unwrap();
+    let edited = editor.commit().unwrap();
+    assert_eq!(edited.ic_slots, 3);
+
+    let mut data = abc.raw_data().to_vec();
+    edited.write_slot_count(&mut data).unwrap();
+    let written = File::open(data).unwrap();
+    assert!(written.validate_checksum());
+    let method = written.method(main_method(&written)).unwrap();
+    assert_eq!(method.profile().ic_slots, Some(3));
+}
@@ -0,0 +1,104 @@
+#![cfg(feature = "builder")]
+
+use abcd_file::digest::{CodeDigest, Crc32, method_digests};
+use abcd_file::{AnalysisObserver, EntityId, File};
+use abcd_testgen::files::GlobalClass;
+#[cfg(feature = "sha2")]
+use sha2::{Digest, Sha256};
+
+/// `ldundefined; returnundefined`
+const BODY: [u8; 2] = [0x00, 0x65];
+
+/// `L_GLOBAL;` with one method whose code is `body`.
+fn with_body(body: &[u8]) -> File {
+    let mut global = GlobalClass::new();
+    global.method("main", body);
+    global.open()
+}
+
+fn crc32(bytes: &[u8]) -> [u8; 4] {
+    let mut crc = Crc32::new();
+    crc.update(bytes);
+    crc.finish()
+}
+
+#[test]
+fn crc32_check_value() {
+    assert_eq!(crc32(b""), [0, 0, 0, 0]);
+    assert_eq!(crc32(b"123456789"), 0xcbf4_3926u32.to_be_bytes());
+    let mut split = Crc32::new();
+    split.update(b"1234");
+    split.update(b"56789");
+    assert_eq!(split.finish(), crc32(b"123456789"));
+}
+
+#[test]
+fn digest_covers_the_stored_code_item() {
+    let abc = with_body(&BODY);
+    let class = abc.class_id_by_name("L_GLOBAL;").unwrap().unwrap();
+    let method = abc
+        .method(abc.class(class).unwrap().method_offsets()[0])
+        .unwrap();
+    let code = abc.code(method.code_off().unwrap()).unwrap();
+
+    let raw = code.raw_bytes();
+    assert_eq!(raw.len(), code.size() as usize);
+    assert!(raw.windows(BODY.len()).any(|w| w == BODY));
+
+    assert_eq!(code.digest(Crc32::new()), crc32(raw));
+    #[cfg(feature = "sha2")]
+    {
+        let sha: [u8; 32] = Sha256::digest(raw).into();
+        assert_eq!(code.digest(Sha256::new()), sha);
+    }
+}
+
+#[test]
+fn digest_changes_with_the_code() {
+    let digest = |body: &[u8]| {
+        let abc = with_body(body);
+        let class = abc.class_id_by_name("L_GLOBAL;").unwrap().unwrap();
+        let method = abc
+            .method(abc.class(class).unwrap().method_offsets()[0])
+            .unwrap();
+        let code = abc.code(method.code_off().unwrap()).unwrap();
+        code.digest(Crc32::new())
+    };
+    assert_eq!(digest(&BODY), digest(&BODY));
+    // `ldnull; returnundefined`
+    assert_ne!(digest(&BODY), digest(&[0x01, 0x65]));
+}
+
+/// Classes started and methods done.
+#[derive(Default)]
+struct Counter {
+    classes: Vec<String>,
+    methods: Vec<EntityId>,
+}
+
+impl AnalysisObserver for Counter {
+    fn on_class_start(&mut self, _class: EntityId, name: &str) {
+        self.classes.push(name.to_string());
+    }
+
+    fn on_method_done(&mut self, method: EntityId, output: Option<&str>) {
+        assert_eq!(output, None);
+        self.methods.push(method);
+    }
+}
+
+#[test]
+fn every_method_is_digested_and_observed() {
+    let abc = with_body(&BODY);
+    let mut counter = Counter::default();
+    let digests = method_digests(&abc, Crc32::new, &mut counter);
+
+    let main = digests.iter().find(|d| d.name == "L_GLOBAL;.main").unwrap();
+    let code = abc
+        .code(abc.method(main.method).unwrap().code_off().unwrap())
+        .unwrap();
+    assert_eq!(main.digest, code.digest(Crc32::new()));
+    assert!(counter.classes.iter().any(|c| c == "L_GLOBAL;"));
+    let digested: Vec<_> = digests.iter().map(|d| d.method).collect();
+    assert_eq!(counter.methods, digested);
+}
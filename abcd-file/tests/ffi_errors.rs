@@ -0,0 +1,46 @@
+//! The reasons the C++ bridge records for a failed call reach `Error::Ffi`.
+#![cfg(feature = "builder")]
+
+use abcd_file::{Error, File};
+use abcd_isa::Version;
+use abcd_testgen::files::{GlobalClass, stamp_version};
+
+fn open_error(data: Vec<u8>) -> String {
+    match File::open(data) {
+        Err(Error::Ffi(reason)) => reason,
+        other => panic!("expected an FFI error, got {:?}", other.err()),
+    }
+}
+
+#[test]
+fn short_files_say_how_short() {
+    let reason = open_error(vec![0; 4]);
+    assert!(reason.starts_with("abc_file_open failed: "), "{reason}");
+    assert!(reason.contains("file too small: 4 bytes"), "{reason}");
+}
+
+#[test]
+fn bad_magic_is_named() {
+    let mut data = GlobalClass::new().finish();
+    data[0] = b'X';
+    let reason = open_error(data);
+    assert!(reason.contains("invalid magic"), "{reason}");
+}
+
+#[test]
+fn versions_below_the_minimum_are_named() {
+    let mut data = GlobalClass::new().finish();
+    stamp_version(&mut data, Version::new(0, 0, 0, 0));
+    let reason = open_error(data);
+    assert!(reason.contains("unsupported version 0.0.0.0"), "{reason}");
+}
+
+#[test]
+fn each_failure_reports_its_own_reason() {
+    let mut data = GlobalClass::new().finish();
+    data[0] = b'X';
+    open_error(vec![0; 4]);
+    let reason = open_error(data);
+    assert!(!reason.contains("too small"), "{reason}");
+    assert!(reason.contains("invalid magic"), "{reason}");
+}
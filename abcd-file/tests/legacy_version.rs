@@ -0,0 +1,138 @@
+//! Legacy files, before API 11, reference literal arrays by header index.
+//!
+//! The fixture is built with the API 9 layout (literal array table in the
+//! header), which is legacy as it is. It is also stamped with version
+//! 0.0.0.2, the oldest version the runtime accepts, and with 11.0.2.0,
+//! which keeps the header table but references arrays by offset.
+#![cfg(feature = "builder")]
+
+use abcd_file::literal::{LiteralArray, LiteralTag, LiteralValue};
+use abcd_file::version::{has_method_affiliates, uses_literal_array_index};
+use abcd_file::{ACC_PUBLIC, EntityId, Error, File, TypeId};
+use abcd_isa::Version;
+use abcd_testgen::files::{builder, stamp_version};
+
+const LEGACY_VERSION: [u8; 4] = [0, 0, 0, 2];
+const API11_VERSION: Version = Version::new(11, 0, 2, 0);
+
+/// One record whose `moduleRecordIdx` is 0 and a single, empty module literal.
+fn build_fixture() -> Vec<u8> {
+    let mut b = builder(9);
+
+    let module = b.add_literal_array("0").unwrap();
+    // num_requests, then regular/namespace imports, local/indirect/star exports.
+    for _ in 0..6 {
+        b.literal_array_add_u32(module, 0);
+    }
+
+    let class = b.add_class("Lentry;").unwrap();
+    let field = b
+        .class_add_field(class, "moduleRecordIdx", TypeId::I32, ACC_PUBLIC)
+        .unwrap();
+    b.field_set_value_i32(field, 0);
+
+    b.finalize().unwrap()
+}
+
+fn stamped_fixture(version: Version) -> Vec<u8> {
+    let mut data = build_fixture();
+    stamp_version(&mut data, version);
+    data
+}
+
+fn legacy_fixture() -> Vec<u8> {
+    stamped_fixture(Version::from(LEGACY_VERSION))
+}
+
+#[test]
+fn version_boundary() {
+    for legacy in [Version::from(LEGACY_VERSION), Version::new(9, 0, 0, 0)] {
+        assert!(uses_literal_array_index(&legacy), "{legacy}");
+        assert!(!has_method_affiliates(&legacy), "{legacy}");
+    }
+    for offset in [Version::new(11, 0, 0, 0), API11_VERSION, Version::current()] {
+        assert!(!uses_literal_array_index(&offset), "{offset}");
+        assert!(has_method_affiliates(&offset), "{offset}");
+    }
+}
+
+#[test]
+fn api9_files_resolve_through_header() {
+    let abc = File::open(build_fixture()).unwrap();
+    assert_eq!(abc.version(), Version::new(9, 0, 0, 0));
+    let module_off = abc.literal_array_offset(0).unwrap();
+    assert_eq!(abc.resolve_literal_array_id(0).unwrap(), module_off);
+}
+
+#[test]
+#[cfg(feature = "module")]
+fn legacy_module_record_resolves_through_header() {
+    let abc = File::open(legacy_fixture()).unwrap();
+    assert_eq!(abc.version(), Version::from(LEGACY_VERSION));
+    assert_eq!(abc.num_literal_arrays(), 1);
+
+    let module_off = abc.literal_array_offset(0).unwrap();
+    assert_eq!(abc.resolve_literal_array_id(0).unwrap(), module_off);
+
+    let module = abc.module_record(0).unwrap();
+    assert_eq!(module.offset(), module_off);
+    assert_eq!(module.num_requests(), 0);
+    assert!(module.records().is_empty());
+}
+
+#[test]
+fn legacy_index_out_of_range() {
+    let abc = File::open(legacy_fixture()).unwrap();
+    assert!(matches!(
+        abc.resolve_literal_array_id(5),
+        Err(Error::LiteralIndexOutOfRange(5, 1))
+    ));
+}
+
+#[test]
+fn offset_versions_pass_ids_through() {
+    let abc = File::open(stamped_fixture(API11_VERSION)).unwrap();
+    assert_eq!(abc.version(), API11_VERSION);
+    assert_eq!(abc.resolve_literal_array_id(0).unwrap(), EntityId(0));
+
+    let module_off = abc.literal_array_offset(0).unwrap();
+    assert_eq!(
+        abc.resolve_literal_array_id(module_off.0).unwrap(),
+        module_off
+    );
+}
+
+/// `{ a: 1, m() {} }` as an object literal lays it out, `m` followed by
+/// its parameter count when `affiliate` is set.
+fn object_literal(affiliate: bool) -> LiteralArray {
+    let mut entries = vec![
+        (LiteralTag::String, LiteralValue::String(EntityId(0x10))),
+        (LiteralTag::Integer, LiteralValue::Integer(1)),
+        (LiteralTag::String, LiteralValue::String(EntityId(0x20))),
+        (LiteralTag::Method, LiteralValue::Method(EntityId(0x30))),
+    ];
+    if affiliate {
+        entries.push((
+            LiteralTag::MethodAffiliate,
+            LiteralValue::MethodAffiliate(0),
+        ));
+    }
+    LiteralArray { entries }
+}
+
+#[test]
+fn method_affiliates_are_read_from_api_11() {
+    let members = object_literal(true).members(API11_VERSION);
+    assert_eq!(members.len(), 2);
+    assert_eq!(members[0].affiliate, None);
+    assert!(matches!(
+        members[1].value.1,
+        LiteralValue::Method(EntityId(0x30))
+    ));
+    assert_eq!(members[1].affiliate, Some(0));
+
+    // Legacy files carry no affiliates and pair every entry.
+    let members = object_literal(false).members(Version::new(9, 0, 0, 0));
+    assert_eq!(members.len(), 2);
+    assert!(members.iter().all(|m| m.affiliate.is_none()));
+}
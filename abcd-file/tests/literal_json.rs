@@ -0,0 +1,86 @@
+//! Literal arrays written as JSON and built back from it.
+#![cfg(feature = "builder")]
+
+use abcd_file::File;
+use abcd_file::builder::{Builder, LiteralArrayBuilder};
+use abcd_file::literal::LiteralArray;
+use abcd_testgen::files::{GlobalClass, RETURN_UNDEFINED};
+use serde_json::json;
+
+fn sample() -> serde_json::Value {
+    json!([
+        { "tag": "Integer", "value": -5 },
+        { "tag": "Double", "value": 1.5 },
+        { "tag": "Double", "value": "NaN" },
+        { "tag": "String", "value": "hi" },
+        { "tag": "Method", "value": "L_GLOBAL;.foo" },
+        { "tag": "MethodAffiliate", "value": 2 },
+        { "tag": "LiteralArray", "value": [
+            { "tag": "Bool", "value": true },
+            { "tag": "NullValue", "value": null },
+        ] },
+    ])
+}
+
+/// A file holding `json` as a literal array, and that array read back.
+fn round_trip(json: &serde_json::Value) -> (File, LiteralArray) {
+    let lit = LiteralArrayBuilder::from_json(json).unwrap();
+    let mut global = GlobalClass::new();
+    let method = global.method("foo", &RETURN_UNDEFINED);
+    lit.build(&mut global.builder, "sample", &|name| {
+        (name == "L_GLOBAL;.foo").then_some(method)
+    })
+    .unwrap();
+    let abc = global.open();
+    let array = abc
+        .literal_array_offsets()
+        .into_iter()
+        .map(|off| LiteralArray::read(&abc, off).unwrap())
+        .max_by_key(|a| a.entries.len())
+        .unwrap();
+    (abc, array)
+}
+
+#[test]
+fn json_survives_a_round_trip() {
+    let (abc, array) = round_trip(&sample());
+    assert_eq!(array.to_json(&abc), sample());
+}
+
+#[test]
+fn unknown_methods_are_rejected_when_building() {
+    let lit =
+        LiteralArrayBuilder::from_json(&json!([{ "tag": "Getter", "value": "L_GLOBAL;.bar" }]))
+            .unwrap();
+    let mut b = Builder::new().unwrap();
+    let err = lit.build(&mut b, "getter", &|_| None).unwrap_err();
+    assert!(
+        err.to_string().contains("unknown method L_GLOBAL;.bar"),
+        "{err}"
+    );
+}
+
+#[test]
+fn malformed_entries_name_their_position() {
+    let bad = [
+        (json!({ "tag": "Integer" }), "expected an array"),
+        (json!([{ "value": 1 }]), "entry 0: unknown or missing tag"),
+        (
+            json!([{ "tag": "Integer", "value": 1 }, { "tag": "Integer", "value": 1u64 << 40 }]),
+            "entry 1: bad value for Integer",
+        ),
+        (
+            json!([{ "tag": "LiteralArray", "value": [{ "tag": "Bool", "value": 3 }] }]),
+            "entry 0: entry 0: bad value for Bool",
+        ),
+        // Offsets name data in some other file.
+        (
+            json!([{ "tag": "ArrayI32", "value": 4096 }]),
+            "bad value for ArrayI32",
+        ),
+    ];
+    for (json, expected) in bad {
+        let err = LiteralArrayBuilder::from_json(&json).unwrap_err();
+        assert!(err.to_string().contains(expected), "{json}: {err}");
+    }
+}
@@ -0,0 +1,117 @@
+//! Names that are not valid UTF-8 still come back from the lossy accessors,
+//! and `get_string` reads the same strings as the C++ reader.
+#![cfg(feature = "builder")]
+
+use std::borrow::Cow;
+
+use abcd_file::{EntityId, Error, File};
+use abcd_testgen::files::{GlobalClass, RETURN_UNDEFINED};
+
+/// `L_GLOBAL;` with one method, `name`.
+fn with_method(name: &str) -> Vec<u8> {
+    let mut global = GlobalClass::new();
+    global.method(name, &RETURN_UNDEFINED);
+    global.finish()
+}
+
+fn only_method(abc: &File) -> EntityId {
+    let class = abc.class_id_by_name("L_GLOBAL;").unwrap().unwrap();
+    abc.class(class).unwrap().method_offsets()[0]
+}
+
+/// Overwrite the first occurrence of `from` in `data` with `to`.
+fn patch(data: &mut [u8], from: &[u8], to: &[u8]) {
+    let at = data
+        .windows(from.len())
+        .position(|w| w == from)
+        .expect("pattern in file");
+    data[at..at + to.len()].copy_from_slice(to);
+}
+
+#[test]
+fn valid_names_are_borrowed() {
+    let abc = File::open(with_method("render")).unwrap();
+    let m = only_method(&abc);
+    assert_eq!(abc.method_name_bytes(m).unwrap(), b"render");
+    assert!(matches!(abc.method_name_lossy(m), Cow::Borrowed("render")));
+    assert_eq!(abc.method_name(m).unwrap(), "render");
+    assert_eq!(
+        abc.get_string_lossy(abc.method_name_off(m)),
+        abc.get_string(abc.method_name_off(m)).unwrap()
+    );
+}
+
+#[test]
+fn names_read_through_the_bridge_keep_their_last_character() {
+    let abc = File::open(with_method("render")).unwrap();
+    let m = only_method(&abc);
+    let class = abc.class_id_by_name("L_GLOBAL;").unwrap().unwrap();
+    assert_eq!(abc.class(class).unwrap().name().unwrap(), "L_GLOBAL;");
+    assert_eq!(abc.method(m).unwrap().name().unwrap(), "render");
+    assert_eq!(abc.method_name(m).unwrap(), "render");
+    assert_eq!(
+        abc.get_string_ffi(abc.method_name_off(m)).unwrap(),
+        "render"
+    );
+}
+
+#[test]
+fn invalid_bytes_are_replaced() {
+    let mut data = with_method("obfusc");
+    patch(&mut data, b"obfusc", b"ob\xffus");
+    let abc = File::open(data).unwrap();
+    let m = only_method(&abc);
+
+    assert_eq!(abc.method_name_bytes(m).unwrap(), b"ob\xffusc");
+    assert_eq!(abc.method_name_lossy(m), "ob\u{fffd}usc");
+    assert!(abc.get_string(abc.method_name_off(m)).is_err());
+}
+
+#[test]
+fn mutf8_is_decoded() {
+    // U+1F600 as a MUTF-8 surrogate pair: not valid UTF-8.
+    let mut data = with_method("smile_");
+    patch(&mut data, b"smile_", b"\xed\xa0\xbd\xed\xb8\x80");
+    let abc = File::open(data).unwrap();
+    assert_eq!(abc.method_name_lossy(only_method(&abc)), "\u{1f600}");
+}
+
+#[test]
+fn offsets_outside_the_file_show_where_they_point() {
+    let abc = File::open(with_method("f")).unwrap();
+    let outside = EntityId(u32::MAX);
+    assert!(matches!(
+        abc.string_bytes(outside),
+        Err(Error::OffsetOutOfBounds(..))
+    ));
+    assert_eq!(abc.get_string_lossy(outside), format!("<{outside}>"));
+    let end = EntityId(abc.raw_data().len() as u32);
+    assert!(abc.string_bytes(end).is_err());
+    assert_eq!(abc.get_string_lossy(end), format!("<{end}>"));
+}
+
+#[test]
+fn get_string_matches_the_cpp_reader() {
+    let mut files = vec![with_method("render"), with_method("处理事件")];
+    let mut invalid = with_method("obfusc");
+    patch(&mut invalid, b"obfusc", b"ob\xffus");
+    files.push(invalid);
+    let mut surrogates = with_method("smile_");
+    patch(&mut surrogates, b"smile_", b"\xed\xa0\xbd\xed\xb8\x80");
+    files.push(surrogates);
+    // U+1F600 as plain UTF-8: valid UTF-8, but not MUTF-8.
+    let mut four_byte = with_method("smil");
+    patch(&mut four_byte, b"smil", b"\xf0\x9f\x98\x80");
+    files.push(four_byte);
+
+    for data in files {
+        let abc = File::open(data).unwrap();
+        let off = abc.method_name_off(only_method(&abc));
+        assert_eq!(
+            abc.get_string(off).ok(),
+            abc.get_string_ffi(off).ok(),
+            "{:?}",
+            abc.string_bytes(off)
+        );
+    }
+}
@@ -0,0 +1,167 @@
+//! `File::manifest` hashes stay put when only offsets move.
+#![cfg(all(feature = "builder", feature = "sha2"))]
+
+use abcd_file::builder::IndexDep;
+use abcd_file::manifest::Manifest;
+use abcd_file::{ACC_PUBLIC, File, TypeId};
+use abcd_isa::opcode_table;
+use abcd_testgen::files::{RETURN_UNDEFINED, builder};
+
+/// `ldai 1; return`
+const RETURN_ONE: [u8; 6] = [0x62, 0x01, 0x00, 0x00, 0x00, 0x64];
+
+/// `L_GLOBAL;` with `a`, which loads `text`, and `b`, whose code is
+/// `b_code`. With `padding`, an unrelated class comes first and pushes
+/// every other entity to a new offset.
+fn build(padding: bool, text: &str, b_code: &[u8]) -> Vec<u8> {
+    let mut b = builder(12);
+    let proto = b.create_proto(TypeId::Tagged, &[]);
+    if padding {
+        let pad = b.add_class("LPadding;").unwrap();
+        b.add_string("some padding string").unwrap();
+        b.class_add_method_with_proto(pad, "pad", proto, ACC_PUBLIC, &RETURN_UNDEFINED, 1, 3)
+            .unwrap();
+    }
+    let class = b.add_class("L_GLOBAL;").unwrap();
+    let lda_str = opcode_table()
+        .iter()
+        .find(|row| row.mnemonic == "lda.str")
+        .unwrap()
+        .opcode as u8;
+    let mut code = vec![lda_str, 0, 0, 0x64];
+    let a = b
+        .class_add_method_with_proto(class, "a", proto, ACC_PUBLIC, &[], 0, 0)
+        .unwrap();
+    let a_code = b.create_code(1, 3, &code);
+    b.method_set_code(a, a_code);
+    b.class_add_method_with_proto(class, "b", proto, ACC_PUBLIC, b_code, 1, 3)
+        .unwrap();
+    let string = b.add_string(text).unwrap();
+    b.method_add_index_dependency(a, IndexDep::String(string));
+    b.finalize().unwrap();
+
+    let index = b.method_index_of(a, IndexDep::String(string)).unwrap();
+    code[1..3].copy_from_slice(&index.to_le_bytes());
+    b.code_set_instructions(a_code, &code);
+    b.finalize().unwrap()
+}
+
+fn method_hash<'m>(manifest: &'m Manifest, name: &str) -> &'m str {
+    manifest
+        .methods()
+        .find(|(class, m)| *class == "L_GLOBAL;" && m.name == name)
+        .map(|(_, m)| m.hash.as_str())
+        .unwrap()
+}
+
+fn manifest(data: Vec<u8>) -> Manifest {
+    File::open(data).unwrap().manifest()
+}
+
+#[test]
+fn same_file_same_manifest() {
+    let data = build(false, "hello", &RETURN_UNDEFINED);
+    assert_eq!(manifest(data.clone()), manifest(data));
+}
+
+#[test]
+fn moved_methods_keep_their_hashes() {
+    let plain = manifest(build(false, "hello", &RETURN_UNDEFINED));
+    let padded = manifest(build(true, "hello", &RETURN_UNDEFINED));
+    assert_ne!(plain.file, padded.file);
+    for name in ["a", "b"] {
+        assert_eq!(method_hash(&plain, name), method_hash(&padded, name));
+    }
+    assert!(padded.classes.iter().any(|c| c.name == "LPadding;"));
+}
+
+#[test]
+fn only_the_edited_method_changes() {
+    let before = manifest(build(false, "hello", &RETURN_UNDEFINED));
+    let after = manifest(build(true, "hello", &RETURN_ONE));
+    assert_eq!(method_hash(&before, "a"), method_hash(&after, "a"));
+    assert_ne!(method_hash(&before, "b"), method_hash(&after, "b"));
+}
+
+#[test]
+fn string_operands_hash_by_content() {
+    let hello = manifest(build(false, "hello", &RETURN_UNDEFINED));
+    let world = manifest(build(false, "world", &RETURN_UNDEFINED));
+    assert_ne!(method_hash(&hello, "a"), method_hash(&world, "a"));
+    assert_eq!(method_hash(&hello, "b"), method_hash(&world, "b"));
+    assert_ne!(hello.strings, world.strings);
+}
+
+/// `(name, offset, hash)` of one method.
+type MethodRow<'a> = (&'a str, u32, &'a str);
+
+fn listing(classes: &[(&str, &[MethodRow])]) -> Manifest {
+    use abcd_file::manifest::{ClassHash, MethodHash};
+    Manifest {
+        file: String::new(),
+        version: String::new(),
+        strings: String::new(),
+        classes: classes
+            .iter()
+            .map(|(name, methods)| ClassHash {
+                name: name.to_string(),
+                offset: 0,
+                hash: String::new(),
+                methods: methods
+                    .iter()
+                    .map(|&(name, offset, hash)| MethodHash {
+                        name: name.to_string(),
+                        offset,
+                        hash: hash.to_string(),
+                    })
+                    .collect(),
+            })
+            .collect(),
+        literal_arrays: Vec::new(),
+    }
+}
+
+#[test]
+fn diff_methods_reports_what_changed() {
+    use abcd_file::manifest::Change;
+    let old = listing(&[
+        (
+            "LA;",
+            &[
+                ("keep", 0x10, "k"),
+                ("edit", 0x20, "e1"),
+                ("drop", 0x30, "d"),
+            ],
+        ),
+        ("LB;", &[("f", 0x40, "f1"), ("f", 0x50, "f2")]),
+    ]);
+    let new = listing(&[
+        (
+            "LA;",
+            &[
+                ("keep", 0x110, "k"),
+                ("edit", 0x120, "e2"),
+                ("add", 0x130, "a"),
+            ],
+        ),
+        ("LB;", &[("f", 0x140, "f1"), ("f", 0x150, "f3")]),
+    ]);
+    let diffs: Vec<_> = old
+        .diff_methods(&new)
+        .into_iter()
+        .map(|d| (d.class, d.name, d.change, d.old_offset, d.new_offset))
+        .collect();
+    let entry = |class: &str, name: &str, change, old, new| {
+        (class.to_string(), name.to_string(), change, old, new)
+    };
+    assert_eq!(
+        diffs,
+        [
+            entry("LA;", "edit", Change::Modified, Some(0x20), Some(0x120)),
+            entry("LA;", "add", Change::Added, None, Some(0x130)),
+            entry("LB;", "f", Change::Modified, Some(0x50), Some(0x150)),
+            entry("LA;", "drop", Change::Removed, Some(0x30), None),
+        ]
+    );
+    assert!(new.diff_methods(&new).is_empty());
+}
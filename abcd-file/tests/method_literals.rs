@@ -0,0 +1,116 @@
+//! `File::literal_for` looks instruction IDs up in the method's index
+//! region.
+#![cfg(feature = "builder")]
+
+use abcd_file::builder::IndexDep;
+use abcd_file::literal::LiteralTag;
+use abcd_file::{EntityId, File};
+use abcd_testgen::files::{GlobalClass, RETURN_UNDEFINED};
+
+/// `L_GLOBAL;` with methods `a` and `b`, each naming a one-integer literal
+/// array: 1 for `a`, 2 for `b`. Returns the file and each method's ID for
+/// its array.
+fn build() -> (File, [u16; 2]) {
+    let mut global = GlobalClass::new();
+    let mut deps = Vec::new();
+    for (name, value) in [("a", 1), ("b", 2)] {
+        let method = global.method(name, &RETURN_UNDEFINED);
+        let b = &mut global.builder;
+        let lit = b.add_literal_array(name).unwrap();
+        b.literal_array_add_u8(lit, LiteralTag::Integer as u8);
+        b.literal_array_add_u32(lit, value);
+        b.method_add_index_dependency(method, IndexDep::LiteralArray(lit));
+        deps.push((method, lit));
+    }
+    global.builder.finalize().unwrap();
+    let ids = [0, 1].map(|i| {
+        let (method, lit) = deps[i];
+        global
+            .builder
+            .method_index_of(method, IndexDep::LiteralArray(lit))
+            .unwrap()
+    });
+    (global.open(), ids)
+}
+
+fn method(abc: &File, name: &str) -> EntityId {
+    let class = abc.class_id_by_name("L_GLOBAL;").unwrap().unwrap();
+    abc.class(class)
+        .unwrap()
+        .method_offsets()
+        .into_iter()
+        .find(|&m| abc.method_name(m).unwrap() == name)
+        .unwrap()
+}
+
+#[test]
+fn ids_resolve_to_the_methods_own_arrays() {
+    let (abc, ids) = build();
+    for (name, id, value) in [("a", ids[0], 1), ("b", ids[1], 2)] {
+        let literals = abc.literal_for(method(&abc, name)).unwrap();
+        let vals = literals.enumerate_vals(id).unwrap();
+        assert_eq!(vals.len(), 1);
+        assert_eq!(vals[0].tag, Some(LiteralTag::Integer));
+        assert_eq!(vals[0].as_u32(), value);
+        assert_eq!(
+            literals
+                .literal()
+                .enumerate_vals(literals.array_id(id).unwrap())[0]
+                .as_u32(),
+            value
+        );
+    }
+}
+
+fn u32_at(data: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(data[at..at + 4].try_into().unwrap())
+}
+
+/// [`build`]'s file with a second index header put first. It covers no
+/// offsets, but header 0 is the one both methods' access flags name, and
+/// its method table has the two IDs swapped. The original region becomes
+/// header 1, which a lookup by offset range would find instead.
+fn two_regions() -> (File, [u16; 2]) {
+    let (abc, ids) = build();
+    let mut data = abc.raw_data().to_vec();
+    assert_eq!(u32_at(&data, 52), 1);
+    let section = u32_at(&data, 56) as usize;
+    let original: [u8; 40] = data[section..section + 40].try_into().unwrap();
+    let (size, off) = (u32_at(&original, 16), u32_at(&original, 20));
+    let mut table = data[off as usize..(off + 4 * size) as usize].to_vec();
+    for k in 0..4 {
+        table.swap(4 * ids[0] as usize + k, 4 * ids[1] as usize + k);
+    }
+
+    data.resize(data.len().next_multiple_of(4), 0);
+    let table_off = data.len() as u32;
+    data.extend_from_slice(&table);
+    let mut swapped = original;
+    swapped[0..8].fill(0);
+    swapped[20..24].copy_from_slice(&table_off.to_le_bytes());
+    let section_off = data.len() as u32;
+    data.extend_from_slice(&swapped);
+    data.extend_from_slice(&original);
+    let file_size = data.len() as u32;
+    data[16..20].copy_from_slice(&file_size.to_le_bytes());
+    data[52..56].copy_from_slice(&2u32.to_le_bytes());
+    data[56..60].copy_from_slice(&section_off.to_le_bytes());
+    (File::open(data).unwrap(), ids)
+}
+
+#[test]
+fn ids_resolve_through_the_header_the_method_names() {
+    let (abc, ids) = two_regions();
+    assert_eq!(abc.index(method(&abc, "a")).unwrap().header_index(), 0);
+    // `a`'s ID now names `b`'s array and the other way round, though the
+    // range of header 1 still holds both methods.
+    for (name, id, value) in [("a", ids[0], 2), ("b", ids[1], 1)] {
+        let method = method(&abc, name);
+        let literals = abc.literal_for(method).unwrap();
+        assert_eq!(literals.enumerate_vals(id).unwrap()[0].as_u32(), value);
+        assert_ne!(
+            literals.array_id(id),
+            abc.resolve_offset_by_index(method, id)
+        );
+    }
+}
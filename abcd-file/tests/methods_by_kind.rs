@@ -0,0 +1,50 @@
+//! `File::methods_by_kind` against methods whose kind the builder set.
+#![cfg(feature = "builder")]
+
+use abcd_file::{File, FunctionKind};
+use abcd_testgen::files::{GlobalClass, RETURN_UNDEFINED};
+
+fn build_fixture() -> Vec<u8> {
+    let mut global = GlobalClass::new();
+    for (name, kind) in [
+        ("func_main_0", FunctionKind::Function),
+        ("fetch", FunctionKind::AsyncFunction),
+        ("items", FunctionKind::GeneratorFunction),
+        ("load", FunctionKind::AsyncFunction),
+        ("worker", FunctionKind::ConcurrentFunction),
+    ] {
+        let m = global.method(name, &RETURN_UNDEFINED);
+        global.builder.method_set_function_kind(m, kind);
+    }
+    global.finish()
+}
+
+fn names(abc: &File, kind: FunctionKind) -> Vec<String> {
+    abc.methods_by_kind(kind)
+        .into_iter()
+        .map(|m| abc.method_name(m).unwrap())
+        .collect()
+}
+
+#[test]
+fn groups_methods_by_access_flag_kind() {
+    let abc = File::open(build_fixture()).unwrap();
+    assert_eq!(names(&abc, FunctionKind::AsyncFunction), ["fetch", "load"]);
+    assert_eq!(names(&abc, FunctionKind::GeneratorFunction), ["items"]);
+    assert_eq!(names(&abc, FunctionKind::ConcurrentFunction), ["worker"]);
+    assert!(
+        abc.methods_by_kind(FunctionKind::SendableFunction)
+            .is_empty()
+    );
+}
+
+#[test]
+fn matches_the_method_accessor() {
+    let abc = File::open(build_fixture()).unwrap();
+    for kind in [FunctionKind::Function, FunctionKind::AsyncFunction] {
+        for m in abc.methods_by_kind(kind) {
+            assert_eq!(abc.method(m).unwrap().function_kind(), kind);
+            assert_eq!(abc.index(m).unwrap().function_kind(), kind);
+        }
+    }
+}
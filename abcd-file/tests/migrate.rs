@@ -0,0 +1,266 @@
+//! `migrate` and `downgrade` rewrite files in place for other runtimes.
+#![cfg(feature = "builder")]
+
+use abcd_file::builder::Builder;
+use abcd_file::literal::LiteralTag;
+use abcd_file::migrate::{Unlowered, downgrade, downgrade_with};
+use abcd_file::{ACC_PUBLIC, AnalysisObserver, EntityId, Error, File, TypeId, migrate};
+use abcd_isa::{Version, opcode_table};
+use abcd_testgen::files::{GlobalClass, builder, stamp_version};
+
+const LEGACY_VERSION: [u8; 4] = [0, 0, 0, 2];
+
+fn opcode(mnemonic: &str) -> Vec<u8> {
+    let row = opcode_table()
+        .iter()
+        .find(|r| r.mnemonic == mnemonic)
+        .unwrap();
+    match row.opcode > 0xff {
+        true => row.opcode.to_le_bytes().to_vec(),
+        false => vec![row.opcode as u8],
+    }
+}
+
+/// `insn` followed by its register operands.
+fn insn(mnemonic: &str, regs: &[u8]) -> Vec<u8> {
+    let mut bytes = opcode(mnemonic);
+    bytes.extend_from_slice(regs);
+    bytes
+}
+
+/// The file `b` builds, stamped with the legacy version.
+fn open_legacy(mut b: Builder) -> File {
+    let mut data = b.finalize().unwrap();
+    stamp_version(&mut data, Version::from(LEGACY_VERSION));
+    File::open(data).unwrap()
+}
+
+/// `L_GLOBAL;` with one method `f` whose code is `code`, for API `api`.
+fn with_code_for(api: u8, code: &[u8]) -> File {
+    let mut global = GlobalClass::with_api(api);
+    global
+        .builder
+        .class_add_method_with_proto(global.class, "f", global.proto, ACC_PUBLIC, code, 3, 3)
+        .unwrap();
+    global.open()
+}
+
+fn with_code(code: &[u8]) -> File {
+    with_code_for(9, code)
+}
+
+fn f(abc: &File) -> EntityId {
+    let class = abc.class_id_by_name("L_GLOBAL;").unwrap().unwrap();
+    abc.class(class).unwrap().method_offsets()[0]
+}
+
+fn code_of_f(abc: &File) -> Vec<u8> {
+    let code = abc.method(f(abc)).unwrap().code_off().unwrap();
+    abc.code(code).unwrap().instructions().to_vec()
+}
+
+#[test]
+fn deprecated_instructions_become_modern() {
+    let code = [
+        insn("deprecated.delobjprop", &[1, 2]),
+        insn("deprecated.asyncfunctionresolve", &[0, 1, 2]),
+        insn("deprecated.getresumemode", &[0]),
+        insn("returnundefined", &[]),
+    ]
+    .concat();
+    let abc = with_code(&code);
+
+    let migrated = File::open(migrate(&abc, Version::current()).unwrap()).unwrap();
+    assert_eq!(migrated.version(), Version::current());
+    assert!(migrated.validate_checksum());
+
+    let expected = [
+        insn("lda", &[2]),
+        insn("delobjprop", &[1]),
+        insn("lda", &[2]),
+        insn("asyncfunctionresolve", &[0]),
+        insn("nop", &[]),
+        insn("lda", &[0]),
+        insn("getresumemode", &[]),
+        insn("returnundefined", &[]),
+    ]
+    .concat();
+    assert_eq!(expected.len(), code.len());
+    assert_eq!(code_of_f(&migrated), expected);
+}
+
+#[test]
+fn deprecated_forms_without_modern_counterpart_stay() {
+    let code = [
+        insn("deprecated.poplexenv", &[]),
+        insn("returnundefined", &[]),
+    ]
+    .concat();
+    let migrated = File::open(migrate(&with_code(&code), Version::current()).unwrap()).unwrap();
+    assert_eq!(code_of_f(&migrated), code);
+}
+
+#[test]
+fn older_target_is_refused() {
+    let abc = with_code(&insn("returnundefined", &[]));
+    assert!(matches!(
+        migrate(&abc, Version::from(LEGACY_VERSION)),
+        Err(Error::UnsupportedVersion(_))
+    ));
+}
+
+#[test]
+fn legacy_literal_array_indexes_become_offsets() {
+    let mut b = builder(9);
+    let inner = b.add_literal_array("0").unwrap();
+    b.literal_array_add_u8(inner, LiteralTag::Integer as u8);
+    b.literal_array_add_u32(inner, 7);
+    let outer = b.add_literal_array("1").unwrap();
+    b.literal_array_add_u8(outer, LiteralTag::LiteralArray as u8);
+    b.literal_array_add_u32(outer, 0);
+    b.add_class("Lentry;").unwrap();
+    let legacy = open_legacy(b);
+
+    let target = Version::new(11, 0, 2, 0);
+    let migrated = File::open(migrate(&legacy, target).unwrap()).unwrap();
+    let literal = migrated
+        .literal(EntityId(migrated.literal_array_idx_off()))
+        .unwrap();
+    let inner_off = migrated.literal_array_offset(0).unwrap();
+    let outer_off = migrated.literal_array_offset(1).unwrap();
+    let vals = literal.enumerate_vals(outer_off);
+    assert_eq!(vals[0].tag, Some(LiteralTag::LiteralArray));
+    assert_eq!(vals[0].u64_val as u32, inner_off.0);
+}
+
+#[test]
+fn module_record_index_too_short_for_an_offset() {
+    let mut b = builder(9);
+    let module = b.add_literal_array("0").unwrap();
+    for _ in 0..6 {
+        b.literal_array_add_u32(module, 0);
+    }
+    let class = b.add_class("Lentry;").unwrap();
+    let field = b
+        .class_add_field(class, "moduleRecordIdx", TypeId::I32, ACC_PUBLIC)
+        .unwrap();
+    b.field_set_value_i32(field, 0);
+    let legacy = open_legacy(b);
+
+    // Index 0 takes one SLEB128 byte; every offset past the header needs
+    // at least two.
+    assert!(matches!(
+        migrate(&legacy, Version::new(11, 0, 2, 0)),
+        Err(Error::Migration(_))
+    ));
+}
+
+#[test]
+fn newer_instructions_are_lowered_or_reported() {
+    let code = [
+        insn("definepropertybyname", &[0, 0, 0, 1]),
+        insn("callruntime.topropertykey", &[]),
+        insn("callruntime.istrue", &[0]),
+        insn("returnundefined", &[]),
+    ]
+    .concat();
+    let abc = with_code_for(12, &code);
+
+    let target = Version::new(9, 0, 0, 0);
+    let result = downgrade(&abc, target).unwrap();
+    assert_eq!(
+        result.unlowered,
+        [Unlowered {
+            method: f(&abc),
+            pc: 5,
+            mnemonic: "callruntime.topropertykey",
+        }]
+    );
+    assert_eq!(result.methods(), [f(&abc)]);
+    assert!(!result.is_complete());
+
+    let downgraded = File::open(result.data).unwrap();
+    assert_eq!(downgraded.version(), target);
+    assert!(downgraded.validate_checksum());
+    let expected = [
+        insn("stownbyname", &[0, 0, 0, 1]),
+        insn("callruntime.topropertykey", &[]),
+        insn("istrue", &[]),
+        insn("nop", &[]),
+        insn("nop", &[]),
+        insn("returnundefined", &[]),
+    ]
+    .concat();
+    assert_eq!(code_of_f(&downgraded), expected);
+}
+
+/// Every event, in order.
+#[derive(Default)]
+struct Recorder(Vec<String>);
+
+impl AnalysisObserver for Recorder {
+    fn on_class_start(&mut self, _class: EntityId, name: &str) {
+        self.0.push(format!("class {name}"));
+    }
+
+    fn on_method_done(&mut self, method: EntityId, output: Option<&str>) {
+        assert_eq!(output, None);
+        self.0.push(format!("method {:#x}", method.0));
+    }
+
+    fn on_warning(&mut self, method: Option<EntityId>, message: &str) {
+        self.0
+            .push(format!("warning {:#x} {message}", method.unwrap().0));
+    }
+}
+
+#[test]
+fn observer_sees_classes_methods_and_what_was_not_lowered() {
+    let code = [
+        insn("callruntime.topropertykey", &[]),
+        insn("returnundefined", &[]),
+    ]
+    .concat();
+    let abc = with_code_for(12, &code);
+    let method = f(&abc).0;
+
+    let mut recorder = Recorder::default();
+    downgrade_with(&abc, Version::new(9, 0, 0, 0), &mut recorder).unwrap();
+    // The builder may add classes of its own around L_GLOBAL;.
+    let start = recorder
+        .0
+        .iter()
+        .position(|e| e == "class L_GLOBAL;")
+        .unwrap();
+    assert_eq!(
+        recorder.0[start + 1..start + 3],
+        [
+            format!(
+                "warning {method:#x} callruntime.topropertykey at pc 0x0 has no equivalent on the target"
+            ),
+            format!("method {method:#x}"),
+        ]
+    );
+}
+
+#[test]
+fn instructions_the_target_has_are_kept() {
+    let code = [
+        insn("callruntime.istrue", &[0]),
+        insn("returnundefined", &[]),
+    ]
+    .concat();
+    let abc = with_code_for(12, &code);
+    let result = downgrade(&abc, Version::new(12, 0, 6, 0)).unwrap();
+    assert!(result.is_complete());
+    assert_eq!(code_of_f(&File::open(result.data).unwrap()), code);
+}
+
+#[test]
+fn newer_target_is_refused() {
+    let abc = with_code(&insn("returnundefined", &[]));
+    assert!(matches!(
+        downgrade(&abc, Version::current()),
+        Err(Error::UnsupportedVersion(_))
+    ));
+}
@@ -0,0 +1,28 @@
+//! `File::open_mmap` reads the same file `open` does, without copying it.
+#![cfg(all(feature = "builder", feature = "mmap"))]
+
+use abcd_file::{Error, File};
+use abcd_testgen::files::two_classes;
+
+#[test]
+fn mapped_file_reads_like_an_owned_one() {
+    let data = two_classes();
+    let path = std::env::temp_dir().join(format!("abcd-mmap-test-{}.abc", std::process::id()));
+    std::fs::write(&path, &data).unwrap();
+
+    let mapped = File::open_mmap(&path).unwrap();
+    let owned = File::open(data).unwrap();
+    assert_eq!(mapped.raw_data(), owned.raw_data());
+    assert_eq!(mapped.version(), owned.version());
+    assert_eq!(mapped.num_classes(), 2);
+    let class = mapped.class_id_by_name("Lcom/example/A;").unwrap();
+    assert_eq!(class, owned.class_id_by_name("Lcom/example/A;").unwrap());
+    drop(mapped);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn missing_file_is_an_io_error() {
+    let path = std::env::temp_dir().join("abcd-mmap-test-missing.abc");
+    assert!(matches!(File::open_mmap(&path), Err(Error::Io(_))));
+}
@@ -0,0 +1,87 @@
+use abcd_file::names::QualifiedName;
+
+fn qualified(bundle: &str, module: &str, path: &str, name: &str, version: &str) -> QualifiedName {
+    let some = |s: &str| (!s.is_empty()).then(|| s.to_string());
+    QualifiedName {
+        bundle: some(bundle),
+        module: some(module),
+        path: path.to_string(),
+        name: name.to_string(),
+        version: some(version),
+    }
+}
+
+#[test]
+fn merged_record_names_split_into_parts() {
+    let name = QualifiedName::parse("com.example.app&entry/src/main/ets/pages/Index&");
+    assert_eq!(
+        name,
+        qualified(
+            "com.example.app",
+            "entry",
+            "src/main/ets/pages",
+            "Index",
+            ""
+        )
+    );
+    assert_eq!(name.module_path(), "entry/src/main/ets/pages/Index");
+    assert_eq!(
+        name.to_string(),
+        "com.example.app&entry/src/main/ets/pages/Index&"
+    );
+}
+
+#[test]
+fn scoped_modules_keep_their_scope() {
+    assert_eq!(
+        QualifiedName::parse("L&@ohos/lib/src/Util&1.0.0;"),
+        qualified("", "@ohos/lib", "src", "Util", "1.0.0")
+    );
+}
+
+#[test]
+fn normalized_requests_name_the_bundle_before_the_path() {
+    assert_eq!(
+        QualifiedName::parse("N&entry&com.example.app&entry/src/main/ets/pages/Index&"),
+        qualified(
+            "com.example.app",
+            "entry",
+            "src/main/ets/pages",
+            "Index",
+            ""
+        )
+    );
+}
+
+#[test]
+fn plain_descriptors_have_only_a_path() {
+    let name = QualifiedName::parse("Lcom.example/entry/ets/Index;");
+    assert_eq!(
+        name,
+        qualified("", "", "com.example/entry/ets", "Index", "")
+    );
+    assert_eq!(
+        name.segments().collect::<Vec<_>>(),
+        ["com.example", "entry", "ets", "Index"]
+    );
+    assert_eq!(name.to_string(), "com.example/entry/ets/Index");
+}
+
+#[test]
+fn segments_run_from_bundle_to_name() {
+    let name = QualifiedName::parse("com.example.app&@ohos/lib/Util&");
+    assert_eq!(
+        name.segments().collect::<Vec<_>>(),
+        ["com.example.app", "@ohos", "lib", "Util"]
+    );
+}
+
+#[test]
+fn pandasm_names_dot_class_paths() {
+    use abcd_file::names::pandasm_name;
+    assert_eq!(pandasm_name("Lstd/core/Object;"), "std.core.Object");
+    assert_eq!(pandasm_name("[I"), "i32[]");
+    assert_eq!(pandasm_name("[[Lgeom/Point;"), "geom.Point[][]");
+    assert_eq!(pandasm_name("Z"), "u1");
+    assert_eq!(pandasm_name("not a descriptor"), "not a descriptor");
+}
@@ -0,0 +1,77 @@
+//! Sidecar notes round-trip through JSON keyed by hex offsets.
+
+use std::path::Path;
+
+use abcd_file::EntityId;
+use abcd_file::notes::{NoteStore, parse_offset};
+
+fn scratch(name: &str) -> std::path::PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("notes");
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.join(name)
+}
+
+#[test]
+fn sidecar_sits_next_to_the_file() {
+    assert_eq!(
+        NoteStore::sidecar_path(Path::new("out/app.abc")),
+        Path::new("out/app.abc.notes.json")
+    );
+}
+
+#[test]
+fn missing_sidecar_is_empty() {
+    let store = NoteStore::load(&scratch("absent.notes.json")).unwrap();
+    assert!(store.is_empty());
+}
+
+#[test]
+fn notes_and_tags_round_trip() {
+    let mut store = NoteStore::default();
+    store.add_note(EntityId(0x1a2c), "derives the session key");
+    store.add_note(EntityId(0x1a2c), "called from login only");
+    assert!(store.add_tag(EntityId(0x1a2c), "crypto"));
+    assert!(!store.add_tag(EntityId(0x1a2c), "crypto"));
+    store.add_tag(EntityId(0x40), "entry");
+
+    let path = scratch("round_trip.notes.json");
+    store.save(&path).unwrap();
+    let text = std::fs::read_to_string(&path).unwrap();
+    assert!(text.contains("\"0x1a2c\""), "{text}");
+
+    let loaded = NoteStore::load(&path).unwrap();
+    assert_eq!(loaded, store);
+    let ids: Vec<EntityId> = loaded.iter().map(|(id, _)| id).collect();
+    assert_eq!(ids, [EntityId(0x40), EntityId(0x1a2c)]);
+    assert_eq!(
+        loaded.get(EntityId(0x1a2c)).unwrap().notes,
+        ["derives the session key", "called from login only"]
+    );
+    assert_eq!(
+        loaded.tagged("crypto").collect::<Vec<_>>(),
+        [EntityId(0x1a2c)]
+    );
+}
+
+#[test]
+fn removed_entities_disappear() {
+    let mut store = NoteStore::default();
+    store.add_note(EntityId(8), "stale");
+    assert!(store.remove(EntityId(8)).is_some());
+    assert!(store.get(EntityId(8)).is_none());
+    assert!(store.is_empty());
+}
+
+#[test]
+fn bad_keys_are_rejected() {
+    let path = scratch("bad_key.notes.json");
+    std::fs::write(&path, r#"{"entities": {"main": {"notes": ["x"]}}}"#).unwrap();
+    assert!(NoteStore::load(&path).is_err());
+}
+
+#[test]
+fn offsets_parse_as_hex_or_decimal() {
+    assert_eq!(parse_offset("0x1A2c"), Some(EntityId(0x1a2c)));
+    assert_eq!(parse_offset("64"), Some(EntityId(64)));
+    assert_eq!(parse_offset("0xzz"), None);
+}
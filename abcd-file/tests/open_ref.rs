@@ -0,0 +1,34 @@
+//! `File::open_ref` parses borrowed bytes in place.
+#![cfg(feature = "builder")]
+
+use abcd_file::{File, FileRef};
+use abcd_testgen::files::two_classes;
+
+#[test]
+fn borrowed_bytes_are_read_in_place() {
+    let data = two_classes();
+    let abc = File::open_ref(&data).unwrap();
+    assert_eq!(abc.raw_data().as_ptr(), data.as_ptr());
+    assert_eq!(abc.num_classes(), 2);
+    let class = abc.class_id_by_name("Lcom/example/A;").unwrap().unwrap();
+    assert_eq!(abc.get_string(class).unwrap(), "Lcom/example/A;");
+}
+
+#[test]
+fn files_share_one_buffer() {
+    // Two files back to back, as in an archive read whole.
+    let one = two_classes();
+    let mut archive = one.clone();
+    archive.extend_from_slice(&one);
+    let files: Vec<FileRef<'_>> = archive
+        .chunks(one.len())
+        .map(|bytes| File::open_ref(bytes).unwrap())
+        .collect();
+    assert_eq!(files[1].raw_data().as_ptr(), archive[one.len()..].as_ptr());
+    assert!(files.iter().all(|f| f.num_classes() == 2));
+}
+
+#[test]
+fn garbage_is_refused() {
+    assert!(File::open_ref(b"not an abc file").is_err());
+}
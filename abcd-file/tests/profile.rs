@@ -0,0 +1,175 @@
+//! Inline-cache slot counts and type hints read back from method
+//! annotations.
+#![cfg(feature = "builder")]
+
+use abcd_file::annotation::AnnotationTag;
+use abcd_file::builder::AnnotationElemDef;
+use abcd_file::literal::LiteralTag;
+use abcd_file::profile::{MethodProfile, PrimitiveType, TypeHint, TypeRef};
+use abcd_file::{EntityId, File};
+use abcd_testgen::files::{GlobalClass, RETURN_UNDEFINED};
+
+/// `plain` has no annotations; `slots` records 7 IC slots in both the
+/// dedicated annotation and the legacy `icSize` element.
+fn build_fixture() -> Vec<u8> {
+    let mut global = GlobalClass::new();
+    global.method("plain", &RETURN_UNDEFINED);
+    let slots = global.method("slots", &RETURN_UNDEFINED);
+    let b = &mut global.builder;
+
+    let slot_class = b.add_class("L_ESSlotNumberAnnotation;").unwrap();
+    let slot_name = b.add_string("SlotNumber").unwrap();
+    let ann = b.create_annotation(
+        slot_class,
+        &[AnnotationElemDef {
+            name: slot_name,
+            tag: AnnotationTag::U32,
+            value: 7,
+        }],
+    );
+    b.method_add_annotation(slots, ann);
+
+    let es_class = b.add_class("L_ESAnnotation;").unwrap();
+    let ic_size = b.add_string("icSize").unwrap();
+    let legacy = b.create_annotation(
+        es_class,
+        &[AnnotationElemDef {
+            name: ic_size,
+            tag: AnnotationTag::U32,
+            value: 3,
+        }],
+    );
+    b.method_add_annotation(slots, legacy);
+
+    global.finish()
+}
+
+fn method_named(abc: &File, name: &str) -> EntityId {
+    let class = abc.class_id_by_name("L_GLOBAL;").unwrap().unwrap();
+    abc.class(class)
+        .unwrap()
+        .method_offsets()
+        .into_iter()
+        .find(|&m| abc.method_name(m).unwrap() == name)
+        .unwrap()
+}
+
+#[test]
+fn missing_annotations_give_an_empty_profile() {
+    let abc = File::open(build_fixture()).unwrap();
+    let plain = abc.method(method_named(&abc, "plain")).unwrap();
+    assert_eq!(plain.profile(), MethodProfile::default());
+}
+
+#[test]
+fn slot_number_annotation_wins_over_ic_size() {
+    let abc = File::open(build_fixture()).unwrap();
+    let slots = abc.method(method_named(&abc, "slots")).unwrap();
+    let profile = slots.profile();
+    assert_eq!(profile.ic_slots, Some(7));
+    assert!(profile.types.is_empty());
+}
+
+#[test]
+fn negative_orders_name_parameters() {
+    let profile = MethodProfile {
+        ic_slots: None,
+        types: vec![
+            TypeHint {
+                order: -1,
+                ty: TypeRef::Primitive(PrimitiveType::Number),
+            },
+            TypeHint {
+                order: -2,
+                ty: TypeRef::Other(120),
+            },
+            TypeHint {
+                order: 4,
+                ty: TypeRef::Primitive(PrimitiveType::String),
+            },
+        ],
+    };
+    assert_eq!(profile.param_type(0).unwrap().to_string(), "number");
+    assert_eq!(profile.param_type(1), Some(TypeRef::Other(120)));
+    assert_eq!(profile.param_type(2), None);
+    assert_eq!(
+        profile.insn_type(4),
+        Some(TypeRef::Primitive(PrimitiveType::String))
+    );
+}
+
+#[test]
+fn builder_records_the_slot_count() {
+    let mut global = GlobalClass::new();
+    let slots = global.method("slots", &RETURN_UNDEFINED);
+    global.builder.method_set_slot_count(slots, 10).unwrap();
+    global.builder.method_set_slot_count(slots, 12).unwrap();
+    global.method("plain", &RETURN_UNDEFINED);
+    let abc = global.open();
+
+    let slots = abc.method(method_named(&abc, "slots")).unwrap();
+    assert_eq!(slots.profile().ic_slots, Some(12));
+    // The second call updated the first annotation instead of adding one.
+    assert_eq!(slots.annotations().len(), 1);
+    let plain = abc.method(method_named(&abc, "plain")).unwrap();
+    assert_eq!(plain.profile().ic_slots, None);
+}
+
+/// An API 9 file whose method `typed` records `pairs` in
+/// `_TypeOfInstruction`, referencing the array by its header index.
+fn build_typed(pairs: &[(i32, i32)]) -> File {
+    let mut global = GlobalClass::with_api(9);
+    let typed = global.method("typed", &RETURN_UNDEFINED);
+    let b = &mut global.builder;
+
+    let types = b.add_literal_array("0").unwrap();
+    for &(order, ty) in pairs {
+        for v in [order, ty] {
+            b.literal_array_add_u8(types, LiteralTag::Integer as u8);
+            b.literal_array_add_u32(types, v as u32);
+        }
+    }
+    // A trailing order without a type is dropped.
+    b.literal_array_add_u8(types, LiteralTag::Integer as u8);
+    b.literal_array_add_u32(types, 9);
+
+    let type_class = b.add_class("L_ESTypeAnnotation;").unwrap();
+    let name = b.add_string("_TypeOfInstruction").unwrap();
+    let ann = b.create_annotation(
+        type_class,
+        &[AnnotationElemDef {
+            name,
+            tag: AnnotationTag::U32,
+            value: 0,
+        }],
+    );
+    b.method_add_annotation(typed, ann);
+    global.open()
+}
+
+#[test]
+fn type_of_instruction_is_read_in_pairs() {
+    let abc = build_typed(&[(-1, 1), (3, 120), (5, 4), (6, -1)]);
+    let profile = abc.method(method_named(&abc, "typed")).unwrap().profile();
+    // The pair with a negative type index is skipped.
+    assert_eq!(
+        profile.types,
+        [
+            TypeHint {
+                order: -1,
+                ty: TypeRef::Primitive(PrimitiveType::Number),
+            },
+            TypeHint {
+                order: 3,
+                ty: TypeRef::Other(120),
+            },
+            TypeHint {
+                order: 5,
+                ty: TypeRef::Primitive(PrimitiveType::String),
+            },
+        ]
+    );
+    assert_eq!(profile.param_type(0).unwrap().to_string(), "number");
+    assert_eq!(profile.insn_type(3).unwrap().to_string(), "type#120");
+    assert_eq!(profile.ic_slots, None);
+}
@@ -0,0 +1,120 @@
+//! `Builder::method_set_raw_debug_info` against debug info the LNP emitters
+//! produced.
+#![cfg(all(feature = "builder", feature = "debug-info"))]
+
+use abcd_file::{EntityId, File};
+use abcd_testgen::files::GlobalClass;
+
+/// `ldundefined; returnundefined`
+const CODE: [u8; 2] = [0x00, 0x65];
+
+/// Build one method, with debug info from the emitters or, given
+/// `(lnp, debug)`, copied from another file.
+fn build(raw: Option<(&[u8], &[u8])>) -> Vec<u8> {
+    let mut global = GlobalClass::new();
+    let method = global.method("func_main_0", &CODE);
+    let b = &mut global.builder;
+    match raw {
+        Some((lnp, debug)) => {
+            b.method_set_raw_debug_info(method, lnp, debug).unwrap();
+        }
+        None => {
+            let lnp = b.create_lnp();
+            let debug = b.create_debug_info(lnp, 10);
+            b.lnp_emit_column(lnp, debug, 0, 4);
+            b.lnp_emit_advance_pc(lnp, debug, 1);
+            b.lnp_emit_advance_line(lnp, debug, 2);
+            b.lnp_emit_column(lnp, debug, 0, 8);
+            b.lnp_emit_end(lnp);
+            b.method_set_debug_info(method, debug);
+        }
+    }
+    global.finish()
+}
+
+fn main_method(abc: &File) -> EntityId {
+    let class = abc.class_id_by_name("L_GLOBAL;").unwrap().unwrap();
+    abc.class(class).unwrap().method_offsets()[0]
+}
+
+fn read_uleb(data: &[u8], pos: &mut usize) -> u32 {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = data[*pos];
+        *pos += 1;
+        value |= u32::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return value;
+        }
+        shift += 7;
+    }
+}
+
+/// The `(lnp, debug)` bytes of `method`. Programs from the fixture carry
+/// their operands in the constant pool, so each opcode is one byte and the
+/// program ends at the first `END_SEQUENCE`.
+fn raw_debug_info(abc: &File, method: EntityId) -> (Vec<u8>, Vec<u8>) {
+    let data = abc.raw_data();
+    let start = abc.method(method).unwrap().debug_info_off().unwrap().0 as usize;
+    let mut pos = start;
+    read_uleb(data, &mut pos);
+    let num_params = read_uleb(data, &mut pos);
+    for _ in 0..num_params {
+        read_uleb(data, &mut pos);
+    }
+    pos += read_uleb(data, &mut pos) as usize;
+    let lnp_idx = read_uleb(data, &mut pos);
+    let debug = data[start..pos].to_vec();
+
+    let lnp_start = abc.resolve_lnp_index(lnp_idx).unwrap().0 as usize;
+    let len = data[lnp_start..].iter().position(|&op| op == 0).unwrap() + 1;
+    (data[lnp_start..lnp_start + len].to_vec(), debug)
+}
+
+fn lines(abc: &File, method: EntityId) -> Vec<(u32, u32)> {
+    abc.debug_info()
+        .unwrap()
+        .line_table(method)
+        .iter()
+        .map(|e| (e.offset, e.line))
+        .collect()
+}
+
+#[test]
+fn copied_debug_info_is_byte_identical() {
+    let original = File::open(build(None)).unwrap();
+    let (lnp, debug) = raw_debug_info(&original, main_method(&original));
+
+    let copy = File::open(build(Some((&lnp, &debug)))).unwrap();
+    let method = main_method(&copy);
+    assert_eq!(raw_debug_info(&copy, method), (lnp, debug));
+    assert_eq!(
+        lines(&copy, method),
+        lines(&original, main_method(&original))
+    );
+}
+
+#[test]
+fn malformed_debug_items_are_rejected() {
+    let mut global = GlobalClass::new();
+    let method = global.method("f", &CODE);
+    let b = &mut global.builder;
+    let end = [0x00];
+    // Truncated ULEB128.
+    assert!(b.method_set_raw_debug_info(method, &end, &[0x80]).is_err());
+    // Constant pool longer than the item.
+    assert!(
+        b.method_set_raw_debug_info(method, &end, &[1, 0, 4, 0])
+            .is_err()
+    );
+    // Bytes after the program index.
+    assert!(
+        b.method_set_raw_debug_info(method, &end, &[1, 0, 0, 0, 0])
+            .is_err()
+    );
+    assert!(
+        b.method_set_raw_debug_info(method, &end, &[1, 0, 0, 0])
+            .is_ok()
+    );
+}
@@ -0,0 +1,119 @@
+//! `edit::replace_string` moves instruction, literal and name references.
+#![cfg(feature = "builder")]
+
+use abcd_file::builder::IndexDep;
+use abcd_file::edit::replace_string;
+use abcd_file::literal::{LiteralArray, LiteralTag, LiteralValue};
+use abcd_file::{EntityId, Error, File};
+use abcd_isa::opcode_table;
+use abcd_testgen::files::{GlobalClass, RETURN_UNDEFINED};
+
+/// `L_GLOBAL;` with `a`, which loads `"_0x1f"`, and `b`; a literal array
+/// holds `"_0x1f"` too.
+fn build() -> File {
+    let mut global = GlobalClass::new();
+    let lda_str = opcode_table()
+        .iter()
+        .find(|row| row.mnemonic == "lda.str")
+        .unwrap()
+        .opcode as u8;
+    let mut code = vec![lda_str, 0, 0, 0x64];
+    let a = global.method_without_code("a");
+    global.method("b", &RETURN_UNDEFINED);
+    let b = &mut global.builder;
+    let a_code = b.create_code(1, 3, &code);
+    b.method_set_code(a, a_code);
+    let string = b.add_string("_0x1f").unwrap();
+    b.method_add_index_dependency(a, IndexDep::String(string));
+    let lit = b.add_literal_array("lit").unwrap();
+    b.literal_array_add_u8(lit, LiteralTag::String as u8);
+    b.literal_array_add_string(lit, string);
+    b.finalize().unwrap();
+
+    let index = b.method_index_of(a, IndexDep::String(string)).unwrap();
+    code[1..3].copy_from_slice(&index.to_le_bytes());
+    b.code_set_instructions(a_code, &code);
+    global.open()
+}
+
+fn method(abc: &File, name: &str) -> EntityId {
+    let class = abc.class_id_by_name("L_GLOBAL;").unwrap().unwrap();
+    abc.class(class)
+        .unwrap()
+        .method_offsets()
+        .into_iter()
+        .find(|&m| abc.get_string(abc.method_name_off(m)).unwrap() == name)
+        .unwrap()
+}
+
+/// The offset of the string `a` loads.
+fn loaded(abc: &File) -> EntityId {
+    let a = method(abc, "a");
+    let code = abc
+        .code(abc.method(a).unwrap().code_off().unwrap())
+        .unwrap();
+    let ins = code.instructions();
+    abc.resolve_offset_by_index(a, u16::from_le_bytes([ins[1], ins[2]]))
+        .unwrap()
+}
+
+/// The string `a` loads and the one in the literal array.
+fn strings(abc: &File) -> (String, String) {
+    let loaded = loaded(abc);
+    let array = LiteralArray::read(abc, abc.literal_array_offsets()[0]).unwrap();
+    let LiteralValue::String(literal) = array.entries[0].1 else {
+        panic!("{:?}", array.entries[0]);
+    };
+    (
+        abc.get_string(loaded).unwrap(),
+        abc.get_string(literal).unwrap(),
+    )
+}
+
+#[test]
+fn every_reference_moves_to_a_new_string() {
+    let abc = build();
+    let old = loaded(&abc);
+    let data = replace_string(&abc, old, "apiKey").unwrap();
+    assert!(data.len() > abc.raw_data().len());
+
+    let edited = File::open(data).unwrap();
+    assert_eq!(edited.file_size() as usize, edited.raw_data().len());
+    let (loaded, literal) = strings(&edited);
+    assert_eq!(loaded, "apiKey");
+    assert_eq!(literal, "apiKey");
+    // The old string is still there, just unused.
+    assert_eq!(edited.get_string(old).unwrap(), "_0x1f");
+}
+
+#[test]
+fn an_existing_string_is_reused() {
+    let abc = build();
+    let old = loaded(&abc);
+    let data = replace_string(&abc, old, "b").unwrap();
+    assert_eq!(data.len(), abc.raw_data().len());
+
+    let edited = File::open(data).unwrap();
+    let b_name = edited.method_name_off(method(&edited, "b"));
+    assert_eq!(loaded(&edited), b_name);
+    assert_eq!(strings(&edited).1, "b");
+}
+
+#[test]
+fn method_names_are_renamed() {
+    let abc = build();
+    let name = abc.method_name_off(method(&abc, "b"));
+    let edited = File::open(replace_string(&abc, name, "decrypt").unwrap()).unwrap();
+    method(&edited, "decrypt");
+    assert_eq!(strings(&edited).0, "_0x1f");
+}
+
+#[test]
+fn class_names_are_refused() {
+    let abc = build();
+    let class = abc.class_id_by_name("L_GLOBAL;").unwrap().unwrap();
+    assert!(matches!(
+        replace_string(&abc, class, "LMain;"),
+        Err(Error::ReplaceString(..))
+    ));
+}
@@ -0,0 +1,215 @@
+//! Parsing in a sandboxed child: output comes back, crashes do not spread.
+//!
+//! Forking from the multithreaded libtest harness can leave the child
+//! holding a lock another test thread had, so this runs without it, one
+//! test at a time.
+
+#[cfg(unix)]
+mod unix {
+    use std::fs;
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    use abcd_file::sandbox::{self, Limits, SandboxError, SandboxedFile};
+    use abcd_testgen::files::two_classes;
+
+    fn output_streams_back() {
+        let file = SandboxedFile::new(two_classes());
+        let mut out = Vec::new();
+        file.run(&mut out, |abc, w| {
+            writeln!(w, "{} classes", abc.num_classes())
+        })
+        .unwrap();
+        assert_eq!(out, b"2 classes\n");
+    }
+
+    fn garbage_fails_in_the_child() {
+        let file = SandboxedFile::new(b"not an abc file".to_vec());
+        let mut out = Vec::new();
+        let err = file.run(&mut out, |_, _| Ok(())).unwrap_err();
+        assert!(matches!(err, SandboxError::Exited(1)), "{err}");
+    }
+
+    fn exit_status_and_panics_come_back() {
+        let mut out = Vec::new();
+        assert_eq!(
+            sandbox::isolate(&Limits::default(), &mut out, || 7).unwrap(),
+            7
+        );
+        let status = sandbox::isolate(&Limits::default(), &mut out, || panic!("boom")).unwrap();
+        assert_eq!(status, sandbox::PANICKED);
+    }
+
+    fn a_crash_comes_back_as_an_error() {
+        let mut out = Vec::new();
+        let err = sandbox::isolate(&Limits::default(), &mut out, || {
+            std::io::stdout().write_all(b"partial").unwrap();
+            std::io::stdout().flush().unwrap();
+            std::process::abort()
+        })
+        .unwrap_err();
+        assert!(matches!(err, SandboxError::Killed(libc::SIGABRT)), "{err}");
+        assert_eq!(out, b"partial");
+    }
+
+    fn memory_is_limited() {
+        let limits = Limits {
+            memory: Some(256 << 20),
+            cpu_seconds: None,
+            wall_seconds: None,
+        };
+        let mut out = Vec::new();
+        let result = sandbox::isolate(&limits, &mut out, || {
+            let big = std::hint::black_box(vec![1u8; 1 << 30]);
+            big.len() as i32
+        });
+        assert!(matches!(result, Err(SandboxError::Killed(_))), "{result:?}");
+    }
+
+    fn a_child_that_blocks_is_timed_out() {
+        let limits = Limits {
+            wall_seconds: Some(1),
+            ..Limits::default()
+        };
+        let mut out = Vec::new();
+        let result = sandbox::isolate(&limits, &mut out, || {
+            std::thread::sleep(std::time::Duration::from_secs(60));
+            0
+        });
+        assert!(
+            matches!(result, Err(SandboxError::TimedOut(1))),
+            "{result:?}"
+        );
+    }
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("abcd-sandbox-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn files_are_written_by_the_parent() {
+        let dir = scratch("outputs");
+        let target = dir.join("classes/A.js");
+        let mut out = Vec::new();
+        let status = sandbox::isolate_with_outputs(
+            &Limits::default(),
+            std::slice::from_ref(&dir),
+            &mut out,
+            || match sandbox::write_output(&target, b"class A {}\n") {
+                Ok(()) => 0,
+                Err(_) => 1,
+            },
+        )
+        .unwrap();
+        assert_eq!(status, 0);
+        assert_eq!(fs::read(&target).unwrap(), b"class A {}\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn files_outside_the_output_directories_are_refused() {
+        let dir = scratch("refused");
+        let escape = dir.join("../abcd-sandbox-escape.js");
+        let mut out = Vec::new();
+        let result = sandbox::isolate_with_outputs(
+            &Limits::default(),
+            std::slice::from_ref(&dir),
+            &mut out,
+            || {
+                let _ = sandbox::write_output(&escape, b"pwned");
+                0
+            },
+        );
+        assert!(matches!(result, Err(SandboxError::Output(_))), "{result:?}");
+        assert!(!escape.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(all(
+        target_os = "linux",
+        any(target_arch = "x86_64", target_arch = "aarch64")
+    ))]
+    fn the_filter_refuses_what_is_not_allowed() {
+        let dir = scratch("filter");
+        fs::create_dir_all(&dir).unwrap();
+        let created = dir.join("created");
+        let parent = std::process::id() as libc::pid_t;
+        let mut out = Vec::new();
+        let status = sandbox::isolate(&Limits::default(), &mut out, || {
+            let errno = || std::io::Error::last_os_error().raw_os_error();
+            let denied = [
+                // SAFETY: fork has no preconditions; it is refused here.
+                (unsafe { libc::fork() }, errno()),
+                // SAFETY: signal 0 only checks that the target exists.
+                (unsafe { libc::kill(parent, 0) }, errno()),
+                // SAFETY: as above.
+                (
+                    unsafe { libc::syscall(libc::SYS_tgkill, parent, parent, 0) } as i32,
+                    errno(),
+                ),
+                // SAFETY: socket has no preconditions.
+                (
+                    unsafe { libc::socket(libc::AF_INET, libc::SOCK_STREAM, 0) },
+                    errno(),
+                ),
+            ];
+            let refused = denied
+                .iter()
+                .all(|&(r, e)| r == -1 && e == Some(libc::EPERM));
+            let write_refused = fs::File::create(&created).is_err();
+            let read_allowed = fs::read("/proc/self/status").is_ok();
+            (refused && write_refused && read_allowed) as i32
+        })
+        .unwrap();
+        assert_eq!(status, 1);
+        assert!(!created.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    pub fn run() {
+        let mut tests: Vec<(&str, fn())> = vec![
+            ("output_streams_back", output_streams_back),
+            ("garbage_fails_in_the_child", garbage_fails_in_the_child),
+            (
+                "exit_status_and_panics_come_back",
+                exit_status_and_panics_come_back,
+            ),
+            (
+                "a_crash_comes_back_as_an_error",
+                a_crash_comes_back_as_an_error,
+            ),
+            ("memory_is_limited", memory_is_limited),
+            (
+                "a_child_that_blocks_is_timed_out",
+                a_child_that_blocks_is_timed_out,
+            ),
+            (
+                "files_are_written_by_the_parent",
+                files_are_written_by_the_parent,
+            ),
+            (
+                "files_outside_the_output_directories_are_refused",
+                files_outside_the_output_directories_are_refused,
+            ),
+        ];
+        #[cfg(all(
+            target_os = "linux",
+            any(target_arch = "x86_64", target_arch = "aarch64")
+        ))]
+        tests.push((
+            "the_filter_refuses_what_is_not_allowed",
+            the_filter_refuses_what_is_not_allowed,
+        ));
+        for (name, test) in tests {
+            print!("test {name} ... ");
+            std::io::stdout().flush().unwrap();
+            test();
+            println!("ok");
+        }
+    }
+}
+
+fn main() {
+    #[cfg(unix)]
+    unix::run();
+}
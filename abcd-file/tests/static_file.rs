@@ -0,0 +1,280 @@
+//! Static (PandaAssembly) files: detection, typed signatures and fields,
+//! and the `static_ir` listing.
+#![cfg(feature = "builder")]
+
+use abcd_file::builder::{CatchBlockDef, ProtoParam};
+use abcd_file::static_ir::StaticDecoder;
+use abcd_file::{ACC_PUBLIC, ACC_STATIC, File, FileType, SourceLang, TypeId, ValueType, static_ir};
+use abcd_testgen::files::builder;
+
+/// Opaque instruction bytes; nothing here decodes static code.
+const CODE: [u8; 4] = [0x01, 0x02, 0x03, 0x04];
+
+fn build_fixture(lang: SourceLang) -> Vec<u8> {
+    let mut b = builder(12);
+    let string = b.add_foreign_class("Lstd/core/String;").unwrap();
+    let point = b.add_class("Lgeom/Point;").unwrap();
+    b.class_set_source_lang(point, lang);
+    b.class_add_field(point, "x", TypeId::I32, ACC_PUBLIC)
+        .unwrap();
+    b.class_add_field_ex(
+        point,
+        "label",
+        TypeId::Reference,
+        string.into(),
+        ACC_PUBLIC | ACC_STATIC,
+    )
+    .unwrap();
+
+    // i32 sum(this) and static String describe(Point, f64)
+    let sum = b.create_proto(TypeId::I32, &[]);
+    b.class_add_method_with_proto(point, "sum", sum, ACC_PUBLIC, &CODE, 1, 1)
+        .unwrap();
+    let describe = b.create_proto_ex(
+        TypeId::Reference,
+        string.into(),
+        &[
+            ProtoParam {
+                type_id: TypeId::Reference,
+                class_handle: point.into(),
+            },
+            ProtoParam {
+                type_id: TypeId::F64,
+                class_handle: point.into(),
+            },
+        ],
+    );
+    b.class_add_method_with_proto(
+        point,
+        "describe",
+        describe,
+        ACC_PUBLIC | ACC_STATIC,
+        &CODE,
+        2,
+        2,
+    )
+    .unwrap();
+    b.finalize().unwrap()
+}
+
+fn class_off(abc: &File, name: &str) -> abcd_file::EntityId {
+    abc.class_id_by_name(name).unwrap().unwrap()
+}
+
+fn method(abc: &File, name: &str) -> abcd_file::EntityId {
+    let class = abc.class(class_off(abc, "Lgeom/Point;")).unwrap();
+    class
+        .method_offsets()
+        .into_iter()
+        .find(|&m| abc.method_name(m).unwrap() == name)
+        .unwrap()
+}
+
+#[test]
+fn kind_follows_the_recorded_language() {
+    let abc = File::open(build_fixture(SourceLang::PandaAssembly)).unwrap();
+    assert_eq!(abc.kind(), FileType::Static);
+    let abc = File::open(build_fixture(SourceLang::EcmaScript)).unwrap();
+    assert_eq!(abc.kind(), FileType::Dynamic);
+}
+
+#[test]
+fn kind_follows_most_classes() {
+    let file = |langs: &[SourceLang]| {
+        let mut b = builder(12);
+        for (i, &lang) in langs.iter().enumerate() {
+            let class = b.add_class(&format!("LC{i};")).unwrap();
+            b.class_set_source_lang(class, lang);
+        }
+        File::open(b.finalize().unwrap()).unwrap()
+    };
+    let (panda, script) = (SourceLang::PandaAssembly, SourceLang::EcmaScript);
+    assert_eq!(file(&[script, panda, panda]).kind(), FileType::Static);
+    assert_eq!(file(&[panda, script, script]).kind(), FileType::Dynamic);
+}
+
+#[test]
+fn signatures_resolve_reference_types() {
+    let abc = File::open(build_fixture(SourceLang::PandaAssembly)).unwrap();
+    let point = ValueType::Class(class_off(&abc, "Lgeom/Point;"));
+    let string = ValueType::Class(class_off(&abc, "Lstd/core/String;"));
+
+    let sum = abc.method(method(&abc, "sum")).unwrap();
+    assert!(!sum.is_static());
+    assert_eq!(sum.implicit_args(), 1);
+    let sig = sum.signature();
+    assert_eq!(sig.ret, ValueType::Primitive(TypeId::I32));
+    assert_eq!(sig.params, [point]);
+
+    let describe = abc.method(method(&abc, "describe")).unwrap();
+    assert!(describe.is_static());
+    assert_eq!(describe.implicit_args(), 0);
+    let sig = describe.signature();
+    assert_eq!(sig.ret, string);
+    assert_eq!(sig.params, [point, ValueType::Primitive(TypeId::F64)]);
+}
+
+#[test]
+fn dynamic_methods_keep_three_implicit_args() {
+    let abc = File::open(build_fixture(SourceLang::EcmaScript)).unwrap();
+    let sum = abc.method(method(&abc, "sum")).unwrap();
+    assert_eq!(sum.implicit_args(), 3);
+}
+
+#[test]
+fn expected_arity_comes_from_the_proto() {
+    let abc = File::open(build_fixture(SourceLang::PandaAssembly)).unwrap();
+    let sum = abc.method(method(&abc, "sum")).unwrap();
+    assert_eq!(sum.expected_arity(), Some(0));
+    let describe = abc.method(method(&abc, "describe")).unwrap();
+    assert_eq!(describe.expected_arity(), Some(2));
+
+    // A dynamic proto also lists the function object, new.target and this;
+    // one that does not cannot give an arity.
+    let abc = File::open(build_fixture(SourceLang::EcmaScript)).unwrap();
+    let sum = abc.method(method(&abc, "sum")).unwrap();
+    assert_eq!(sum.expected_arity(), None);
+
+    let mut b = builder(12);
+    let class = b.add_class("L_GLOBAL;").unwrap();
+    let proto = b.create_proto(TypeId::Tagged, &[TypeId::Tagged; 5]);
+    b.class_add_method_with_proto(class, "pair", proto, ACC_PUBLIC, &CODE, 5, 5)
+        .unwrap();
+    let abc = File::open(b.finalize().unwrap()).unwrap();
+    let global = abc.class(class_off(&abc, "L_GLOBAL;")).unwrap();
+    let pair = abc.method(global.method_offsets()[0]).unwrap();
+    assert_eq!(pair.expected_arity(), Some(2));
+}
+
+#[test]
+fn field_types_decode_classes_and_primitives() {
+    let abc = File::open(build_fixture(SourceLang::PandaAssembly)).unwrap();
+    let class = abc.class(class_off(&abc, "Lgeom/Point;")).unwrap();
+    let types: Vec<ValueType> = class
+        .field_offsets()
+        .into_iter()
+        .map(|f| abc.field(f).unwrap().value_type())
+        .collect();
+    assert_eq!(
+        types,
+        [
+            ValueType::Primitive(TypeId::I32),
+            ValueType::Class(class_off(&abc, "Lstd/core/String;")),
+        ]
+    );
+}
+
+#[test]
+fn field_encoding_splits_at_the_type_ids() {
+    assert_eq!(
+        ValueType::from_field_encoding(0x0a),
+        ValueType::Primitive(TypeId::F64)
+    );
+    assert_eq!(
+        ValueType::from_field_encoding(0x1234),
+        ValueType::Class(abcd_file::EntityId(0x1234))
+    );
+}
+
+#[test]
+fn listing_uses_pandasm_syntax() {
+    let abc = File::open(build_fixture(SourceLang::PandaAssembly)).unwrap();
+    let mut out = Vec::new();
+    static_ir::write(&abc, &mut out).unwrap();
+    let text = String::from_utf8(out).unwrap();
+
+    assert!(
+        text.contains(".record geom.Point {\n    i32 x\n    std.core.String label <static>\n}"),
+        "{text}"
+    );
+    assert!(
+        text.contains(".function i32 geom.Point.sum(geom.Point a0) {"),
+        "{text}"
+    );
+    assert!(
+        text.contains(
+            ".function std.core.String geom.Point.describe(geom.Point a0, f64 a1) <static> {"
+        ),
+        "{text}"
+    );
+    assert!(text.contains("    0x0000  01 02 03 04\n"), "{text}");
+}
+
+/// Knows `0x01` (one byte) and `0x02 imm8`.
+struct Toy;
+
+impl StaticDecoder for Toy {
+    fn decode(&self, code: &[u8]) -> Option<(String, usize)> {
+        match code {
+            [0x01, ..] => Some(("op1".to_string(), 1)),
+            [0x02, imm, ..] => Some((format!("op2 {imm:#x}"), 2)),
+            _ => None,
+        }
+    }
+}
+
+/// `Lgeom/Point;` with `sum` as `CODE`, its first byte in a try block whose
+/// catch-all handler is the last byte.
+fn with_try_block() -> File {
+    let mut b = builder(12);
+    let point = b.add_class("Lgeom/Point;").unwrap();
+    b.class_set_source_lang(point, SourceLang::PandaAssembly);
+    let proto = b.create_proto(TypeId::I32, &[]);
+    let sum = b
+        .class_add_method_with_proto(point, "sum", proto, ACC_PUBLIC, &[], 0, 0)
+        .unwrap();
+    let code = b.create_code(1, 1, &CODE);
+    let catch_all = CatchBlockDef {
+        type_class: None,
+        handler_pc: 3,
+        code_size: 1,
+    };
+    b.code_add_try_block(code, 0, 1, &[catch_all]);
+    b.method_set_code(sum, code);
+    File::open(b.finalize().unwrap()).unwrap()
+}
+
+fn listing(abc: &File, decoder: Option<&dyn StaticDecoder>) -> String {
+    let mut out = Vec::new();
+    static_ir::write_with(abc, decoder, &mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn decoder_lists_what_it_knows() {
+    let abc = File::open(build_fixture(SourceLang::PandaAssembly)).unwrap();
+    let text = listing(&abc, Some(&Toy));
+    assert!(
+        text.contains("    0x0000  op1\n    0x0001  op2 0x3\n    0x0003  04\n"),
+        "{text}"
+    );
+}
+
+#[test]
+fn try_blocks_become_labels_and_catch_directives() {
+    let abc = with_try_block();
+    let text = listing(&abc, Some(&Toy));
+    assert!(
+        text.contains(
+            "try_begin_0:\n\
+             \x20   0x0000  op1\n\
+             try_end_0:\n\
+             \x20   0x0001  op2 0x3\n\
+             handler_0_0:\n\
+             \x20   0x0003  04\n\
+             \x20   .catchall try_begin_0, try_end_0, handler_0_0\n}"
+        ),
+        "{text}"
+    );
+
+    // Without a decoder the hex rows still stop at each label.
+    let text = listing(&abc, None);
+    assert!(
+        text.contains(
+            "try_end_0:\n\
+             \x20   0x0001  02 03\n\
+             handler_0_0:\n"
+        ),
+        "{text}"
+    );
+}
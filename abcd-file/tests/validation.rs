@@ -0,0 +1,169 @@
+//! SARIF output of validation reports.
+
+use abcd_file::EntityId;
+use abcd_file::validation::{Finding, Level, SARIF_VERSION, ValidationReport};
+
+fn finding(rule: &str, level: Level, method: Option<u32>) -> Finding {
+    Finding {
+        rule: rule.into(),
+        level,
+        message: format!("{rule} here"),
+        method: method.map(EntityId),
+        location: method.map(|m| format!("Lentry/Index;.m{m:x}")),
+    }
+}
+
+#[test]
+fn sarif_lists_rules_once_and_points_at_methods() {
+    let mut report = ValidationReport::new("entry/modules.abc");
+    report.push(finding("untrusted-method", Level::Error, Some(0x120)));
+    report.push(finding("unresolved-entities", Level::Warning, Some(0x340)));
+    report.push(finding("untrusted-method", Level::Error, None));
+    let sarif = report.to_sarif();
+
+    assert_eq!(sarif["version"], SARIF_VERSION);
+    let run = &sarif["runs"][0];
+    assert_eq!(run["tool"]["driver"]["name"], "abcd");
+    let rules: Vec<&str> = run["tool"]["driver"]["rules"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| r["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(rules, ["untrusted-method", "unresolved-entities"]);
+
+    let results = run["results"].as_array().unwrap();
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[1]["ruleId"], "unresolved-entities");
+    assert_eq!(results[1]["ruleIndex"], 1);
+    assert_eq!(results[1]["level"], "warning");
+    assert_eq!(results[1]["message"]["text"], "unresolved-entities here");
+    let location = &results[1]["locations"][0];
+    assert_eq!(
+        location["physicalLocation"]["artifactLocation"]["uri"],
+        "entry/modules.abc"
+    );
+    assert_eq!(location["physicalLocation"]["region"]["byteOffset"], 0x340);
+    assert_eq!(
+        location["logicalLocations"][0]["fullyQualifiedName"],
+        "Lentry/Index;.m340"
+    );
+
+    // Not about a method: the file is the whole location.
+    let location = &results[2]["locations"][0];
+    assert!(location["physicalLocation"].get("region").is_none());
+    assert!(location.get("logicalLocations").is_none());
+}
+
+#[test]
+fn empty_report_is_a_valid_run() {
+    let report = ValidationReport::new("a.abc");
+    assert!(report.is_empty());
+    let sarif = report.to_sarif();
+    assert_eq!(sarif["runs"][0]["results"], serde_json::json!([]));
+    assert_eq!(
+        sarif["runs"][0]["tool"]["driver"]["rules"],
+        serde_json::json!([])
+    );
+}
+
+#[cfg(feature = "builder")]
+mod checks {
+    use abcd_file::digest::{Crc32, method_digests};
+    use abcd_file::validation::{
+        Level, UNKNOWN_OPCODES, UNRESOLVED_ENTITIES, UNTRUSTED_METHOD, ValidationReport,
+    };
+    use abcd_file::{ACC_PUBLIC, File};
+    use abcd_isa::{EntityId, encode, insn, opcode_table};
+    use abcd_testgen::files::{GlobalClass, RETURN_UNDEFINED};
+
+    /// `L_GLOBAL;` of API `api` with a method per `(name, code)`.
+    fn file(api: u8, methods: &[(&str, &[u8])]) -> File {
+        let mut global = GlobalClass::with_api(api);
+        for &(name, code) in methods {
+            global
+                .builder
+                .class_add_method_with_proto(
+                    global.class,
+                    name,
+                    global.proto,
+                    ACC_PUBLIC,
+                    code,
+                    1,
+                    3,
+                )
+                .unwrap();
+        }
+        global.open()
+    }
+
+    #[test]
+    fn code_checks_find_what_does_not_decode_or_resolve() {
+        let (dangling, _) = encode(&[
+            insn::LdaStr::new(EntityId(0x50)),
+            insn::Returnundefined::new(),
+        ])
+        .unwrap();
+        // API 9 predates `definefieldbyname`.
+        let row = opcode_table()
+            .iter()
+            .find(|r| r.mnemonic == "definefieldbyname")
+            .unwrap();
+        let newer = [row.opcode as u8, 0, 0, 0, 0];
+        let abc = file(
+            9,
+            &[
+                ("dangling", &dangling[..]),
+                ("newer", &newer[..]),
+                ("fine", &RETURN_UNDEFINED[..]),
+            ],
+        );
+
+        let mut report = ValidationReport::default();
+        report.check_code(&abc, &|_| true);
+        let found: Vec<_> = report
+            .findings
+            .iter()
+            .map(|f| (f.rule.as_str(), f.level, f.location.as_deref().unwrap()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (UNRESOLVED_ENTITIES, Level::Error, "L_GLOBAL;.dangling"),
+                (UNKNOWN_OPCODES, Level::Error, "L_GLOBAL;.newer"),
+            ]
+        );
+        assert!(report.findings[1].message.contains("undecodable"));
+
+        let mut report = ValidationReport::default();
+        report.check_code(&abc, &|class| class != "L_GLOBAL;");
+        assert!(report.is_empty());
+    }
+
+    #[test]
+    fn digests_off_the_allowlist_are_errors_and_all_are_notes_without_one() {
+        let abc = file(
+            12,
+            &[("f", &RETURN_UNDEFINED[..]), ("g", &[0x65, 0x65][..])],
+        );
+        let digests = method_digests(&abc, Crc32::new, &mut ());
+        assert_eq!(digests.len(), 2);
+        let f = digests[0].digest;
+
+        let mut report = ValidationReport::default();
+        report.check_digests(&digests, Some(&|d: &[u8; 4]| *d == f));
+        assert_eq!(report.findings.len(), 1);
+        let finding = &report.findings[0];
+        assert_eq!(finding.rule, UNTRUSTED_METHOD);
+        assert_eq!(finding.level, Level::Error);
+        assert_eq!(finding.method, Some(digests[1].method));
+        assert!(finding.message.ends_with("is not on the allowlist"));
+
+        let mut report = ValidationReport::default();
+        report.check_digests(&digests, None);
+        assert_eq!(report.findings.len(), 2);
+        assert!(report.findings.iter().all(|f| f.level == Level::Note));
+        let hex: String = f.iter().map(|b| format!("{b:02x}")).collect();
+        assert!(report.findings[0].message.contains(&hex));
+    }
+}
@@ -0,0 +1,60 @@
+//! `open_checked` refuses versions the linked ISA does not decode.
+#![cfg(feature = "builder")]
+
+use abcd_file::{Error, File};
+use abcd_isa::Version;
+use abcd_testgen::files::{GlobalClass, stamp_version};
+
+fn with_version(version: Version) -> Vec<u8> {
+    let mut data = GlobalClass::new().finish();
+    stamp_version(&mut data, version);
+    data
+}
+
+#[test]
+fn supported_versions_open() {
+    let data = with_version(Version::current());
+    assert!(File::open_checked(data).is_ok());
+}
+
+#[test]
+fn newer_versions_are_a_mismatch() {
+    let future = Version::new(Version::current().major() + 1, 0, 0, 0);
+    let data = with_version(future);
+    // Plain `open` still reads the file.
+    let abc = File::open(data.clone()).unwrap();
+    assert_eq!(abc.version(), future);
+    assert!(abc.check_version().is_err());
+
+    match File::open_checked(data) {
+        Err(Error::VersionMismatch {
+            file,
+            supported_min,
+            supported_max,
+        }) => {
+            assert_eq!(file, future);
+            assert_eq!(supported_min, Version::min_supported());
+            assert_eq!(supported_max, Version::current());
+        }
+        other => panic!("expected a version mismatch, got {:?}", other.err()),
+    }
+}
+
+#[test]
+fn blocked_versions_are_unsupported() {
+    let Some(&blocked) = Version::incompatible_versions().first() else {
+        return;
+    };
+    let data = with_version(blocked);
+    if !blocked.is_in_supported_range() {
+        assert!(matches!(
+            File::open_checked(data),
+            Err(Error::VersionMismatch { .. })
+        ));
+        return;
+    }
+    assert!(matches!(
+        File::open_checked(data),
+        Err(Error::UnsupportedVersion(v)) if v == blocked
+    ));
+}
@@ -236,4 +236,43 @@ impl CFG {
             offset_to_block,
         }
     }
+
+    /// For every block, whether the accumulator it is entered with is read
+    /// before being overwritten, in the block or along its successors. A
+    /// catch handler is entered with the thrown value in acc, so this
+    /// tells whether the handler uses it.
+    pub fn acc_live_in(&self, instructions: &[crate::instruction::Instruction]) -> Vec<bool> {
+        // Per block: whether it reads acc first, and whether it overwrites
+        // acc before any read.
+        let (mut live, kills): (Vec<bool>, Vec<bool>) = self
+            .blocks
+            .iter()
+            .map(|block| {
+                instructions[block.first_insn..block.last_insn]
+                    .iter()
+                    .find_map(|insn| {
+                        if insn.opcode.reads_acc() {
+                            Some((true, false))
+                        } else if insn.opcode.writes_acc() {
+                            Some((false, true))
+                        } else {
+                            None
+                        }
+                    })
+                    .unwrap_or((false, false))
+            })
+            .unzip();
+        loop {
+            let mut changed = false;
+            for b in (0..self.blocks.len()).rev() {
+                if !live[b] && !kills[b] && self.blocks[b].succs.iter().any(|&s| live[s]) {
+                    live[b] = true;
+                    changed = true;
+                }
+            }
+            if !changed {
+                return live;
+            }
+        }
+    }
 }
@@ -0,0 +1,179 @@
+use crate::cfg::{BlockId, CFG};
+use crate::instruction::TryBlockInfo;
+
+/// Dominator tree of a [`CFG`].
+///
+/// Computed with the iterative algorithm of Cooper, Harvey and Kennedy
+/// ("A Simple, Fast Dominance Algorithm"). [`Dominators::compute`] follows
+/// normal successor edges only, so catch handlers, which the CFG reaches
+/// through exception edges it does not model, have no immediate dominator;
+/// [`Dominators::compute_with_handlers`] adds those edges.
+#[derive(Debug, Clone)]
+pub struct Dominators {
+    /// Immediate dominator per block. The entry maps to itself.
+    idom: Vec<Option<BlockId>>,
+    /// Reverse-postorder number per block (`usize::MAX` if unreachable).
+    rpo_index: Vec<usize>,
+}
+
+impl Dominators {
+    /// Compute the dominator tree of `cfg`.
+    pub fn compute(cfg: &CFG) -> Self {
+        let succs: Vec<Vec<BlockId>> = cfg.blocks.iter().map(|b| b.succs.clone()).collect();
+        Self::compute_over(cfg.entry, &succs)
+    }
+
+    /// Compute the dominator tree of `cfg` with an edge from every block
+    /// inside a try range to each of its handlers, so that handlers, and
+    /// the code they fall into, are dominated by the try they belong to.
+    pub fn compute_with_handlers(cfg: &CFG, try_blocks: &[TryBlockInfo]) -> Self {
+        let mut succs: Vec<Vec<BlockId>> = cfg.blocks.iter().map(|b| b.succs.clone()).collect();
+        for tb in try_blocks {
+            let end = tb.start_pc + tb.length;
+            let handlers: Vec<BlockId> = tb
+                .catch_blocks
+                .iter()
+                .filter_map(|cb| cfg.block_at_offset(cb.handler_pc))
+                .collect();
+            for block in &cfg.blocks {
+                if block.start >= tb.start_pc && block.start < end {
+                    for &h in &handlers {
+                        if !succs[block.id].contains(&h) {
+                            succs[block.id].push(h);
+                        }
+                    }
+                }
+            }
+        }
+        Self::compute_over(cfg.entry, &succs)
+    }
+
+    fn compute_over(entry: BlockId, succs: &[Vec<BlockId>]) -> Self {
+        let n = succs.len();
+        let mut idom = vec![None; n];
+        let mut rpo_index = vec![usize::MAX; n];
+        if n == 0 {
+            return Dominators { idom, rpo_index };
+        }
+        let mut preds = vec![Vec::new(); n];
+        for (b, ss) in succs.iter().enumerate() {
+            for &s in ss {
+                preds[s].push(b);
+            }
+        }
+
+        let rpo = reverse_postorder(entry, succs);
+        for (i, &b) in rpo.iter().enumerate() {
+            rpo_index[b] = i;
+        }
+
+        idom[entry] = Some(entry);
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &b in rpo.iter().skip(1) {
+                let mut new_idom: Option<BlockId> = None;
+                for &p in &preds[b] {
+                    if idom[p].is_none() {
+                        continue;
+                    }
+                    new_idom = Some(match new_idom {
+                        None => p,
+                        Some(cur) => intersect(&idom, &rpo_index, p, cur),
+                    });
+                }
+                if new_idom.is_some() && idom[b] != new_idom {
+                    idom[b] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+
+        Dominators { idom, rpo_index }
+    }
+
+    /// Immediate dominator of `block`. `None` for the entry and for blocks
+    /// not reachable from it.
+    pub fn idom(&self, block: BlockId) -> Option<BlockId> {
+        match self.idom.get(block).copied().flatten() {
+            Some(d) if d != block => Some(d),
+            _ => None,
+        }
+    }
+
+    /// Whether `block` is reachable from the entry.
+    pub fn is_reachable(&self, block: BlockId) -> bool {
+        self.idom.get(block).is_some_and(Option::is_some)
+    }
+
+    /// Whether `a` dominates `b` (every block dominates itself).
+    pub fn dominates(&self, a: BlockId, b: BlockId) -> bool {
+        if !self.is_reachable(a) || !self.is_reachable(b) {
+            return a == b;
+        }
+        let mut cur = b;
+        loop {
+            if cur == a {
+                return true;
+            }
+            match self.idom(cur) {
+                Some(d) => cur = d,
+                None => return false,
+            }
+        }
+    }
+
+    /// Lowest block dominating both `a` and `b`, if both are reachable.
+    pub fn common_dominator(&self, a: BlockId, b: BlockId) -> Option<BlockId> {
+        if a == b {
+            return Some(a);
+        }
+        if !self.is_reachable(a) || !self.is_reachable(b) {
+            return None;
+        }
+        Some(intersect(&self.idom, &self.rpo_index, a, b))
+    }
+}
+
+/// Walk both fingers up the tree until they meet.
+fn intersect(
+    idom: &[Option<BlockId>],
+    rpo_index: &[usize],
+    mut a: BlockId,
+    mut b: BlockId,
+) -> BlockId {
+    while a != b {
+        while rpo_index[a] > rpo_index[b] {
+            a = idom[a].expect("processed block has an idom");
+        }
+        while rpo_index[b] > rpo_index[a] {
+            b = idom[b].expect("processed block has an idom");
+        }
+    }
+    a
+}
+
+/// Blocks reachable from `entry`, in reverse postorder.
+fn reverse_postorder(entry: BlockId, succs: &[Vec<BlockId>]) -> Vec<BlockId> {
+    let mut visited = vec![false; succs.len()];
+    let mut postorder = Vec::with_capacity(succs.len());
+    // Iterative DFS: (block, next successor index to visit).
+    let mut stack = vec![(entry, 0usize)];
+    visited[entry] = true;
+    while let Some((b, i)) = stack.last_mut() {
+        let succs = &succs[*b];
+        if *i < succs.len() {
+            let s = succs[*i];
+            *i += 1;
+            if !visited[s] {
+                visited[s] = true;
+                stack.push((s, 0));
+            }
+        } else {
+            postorder.push(*b);
+            stack.pop();
+        }
+    }
+    postorder.reverse();
+    postorder
+}
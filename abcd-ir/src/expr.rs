@@ -1,5 +1,8 @@
+use abcd_isa::EntityId;
+
 /// Expression tree nodes for decompiled code.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum Expr {
     /// Numeric literal (integer).
     NumberLit(f64),
@@ -57,14 +60,150 @@ pub enum Expr {
     Yield(Box<Expr>),
     /// Assignment: `lhs = rhs`
     Assign { target: Box<Expr>, value: Box<Expr> },
+    /// A function or method the code defines, printed as a
+    /// `/* func NAME */` placeholder. `method` is its offset, when the ID
+    /// operand resolved.
+    Function {
+        kind: FunctionKind,
+        name: String,
+        method: Option<EntityId>,
+    },
     /// Unresolved accumulator reference (internal, should be eliminated).
     Acc,
-    /// Raw opcode we couldn't decompile.
+    /// Text emitted as is for what could not be decompiled or resolved: a
+    /// raw opcode, or a `/* ... */` placeholder for an unresolved reference.
     Unknown(String),
 }
 
+impl Expr {
+    pub fn num(n: f64) -> Self {
+        Expr::NumberLit(n)
+    }
+
+    pub fn string(s: impl Into<String>) -> Self {
+        Expr::StringLit(s.into())
+    }
+
+    pub fn var(name: impl Into<String>) -> Self {
+        Expr::Var(name.into())
+    }
+
+    pub fn binary(op: BinOp, lhs: Expr, rhs: Expr) -> Self {
+        Expr::BinaryOp {
+            op,
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+        }
+    }
+
+    pub fn unary(op: UnOp, expr: Expr) -> Self {
+        Expr::UnaryOp {
+            op,
+            expr: Box::new(expr),
+        }
+    }
+
+    pub fn type_of(expr: Expr) -> Self {
+        Expr::TypeOf(Box::new(expr))
+    }
+
+    /// `object.property`
+    pub fn member(object: Expr, property: impl Into<String>) -> Self {
+        Expr::MemberAccess {
+            object: Box::new(object),
+            property: property.into(),
+        }
+    }
+
+    /// `object[index]`
+    pub fn computed(object: Expr, index: Expr) -> Self {
+        Expr::ComputedAccess {
+            object: Box::new(object),
+            index: Box::new(index),
+        }
+    }
+
+    pub fn call(callee: Expr, args: Vec<Expr>) -> Self {
+        Expr::Call {
+            callee: Box::new(callee),
+            args,
+        }
+    }
+
+    /// `new callee(args...)`
+    pub fn construct(callee: Expr, args: Vec<Expr>) -> Self {
+        Expr::New {
+            callee: Box::new(callee),
+            args,
+        }
+    }
+
+    pub fn conditional(cond: Expr, then_expr: Expr, else_expr: Expr) -> Self {
+        Expr::Conditional {
+            cond: Box::new(cond),
+            then_expr: Box::new(then_expr),
+            else_expr: Box::new(else_expr),
+        }
+    }
+
+    pub fn assign(target: Expr, value: Expr) -> Self {
+        Expr::Assign {
+            target: Box::new(target),
+            value: Box::new(value),
+        }
+    }
+
+    pub fn spread(expr: Expr) -> Self {
+        Expr::Spread(Box::new(expr))
+    }
+
+    pub fn await_(expr: Expr) -> Self {
+        Expr::Await(Box::new(expr))
+    }
+
+    pub fn yield_(expr: Expr) -> Self {
+        Expr::Yield(Box::new(expr))
+    }
+
+    /// The variable name, if this is a [`Expr::Var`].
+    pub fn as_var(&self) -> Option<&str> {
+        match self {
+            Expr::Var(name) => Some(name),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            Expr::NumberLit(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Expr::StringLit(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Whether this is a number, string, boolean, `null` or `undefined`
+    /// literal.
+    pub fn is_literal(&self) -> bool {
+        matches!(
+            self,
+            Expr::NumberLit(_)
+                | Expr::StringLit(_)
+                | Expr::BoolLit(_)
+                | Expr::Null
+                | Expr::Undefined
+        )
+    }
+}
+
 /// Object property key.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum PropKey {
     Ident(String),
     Computed(Expr),
@@ -113,6 +252,24 @@ pub enum UnOp {
     Dec,
 }
 
+/// What defined an [`Expr::Function`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FunctionKind {
+    /// `definefunc`
+    Func,
+    /// `definemethod`
+    Method,
+}
+
+impl std::fmt::Display for FunctionKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            FunctionKind::Func => "func",
+            FunctionKind::Method => "method",
+        })
+    }
+}
+
 impl std::fmt::Display for BinOp {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let s = match self {
@@ -1,4 +1,19 @@
+//! Intermediate representation shared by the decompiler passes: the control
+//! flow graph, dominator tree, and the JavaScript-like AST.
+//!
+//! # Stability
+//!
+//! [`expr::Expr`], [`expr::PropKey`] and [`stmt::Stmt`] are
+//! `#[non_exhaustive]`: new node kinds are added in minor releases, so code
+//! outside this crate must keep a wildcard arm when matching on them.
+//! Existing variants and their fields are only changed in a major release.
+//! Build nodes through the constructor helpers (`Expr::call`,
+//! `Stmt::if_`, ...) and traverse them with the [`visit`] traits, which
+//! learn about new variants together with the enums.
+
 pub mod cfg;
+pub mod dominators;
 pub mod expr;
 pub mod instruction;
 pub mod stmt;
+pub mod visit;
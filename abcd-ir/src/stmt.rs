@@ -1,7 +1,10 @@
+use std::fmt::{self, Write};
+
 use crate::expr::Expr;
 
 /// Statement nodes for decompiled code.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum Stmt {
     /// Expression statement: `expr;`
     Expr(Expr),
@@ -56,13 +59,126 @@ pub enum Stmt {
     Block(Vec<Stmt>),
     /// A comment (for undecompilable regions).
     Comment(String),
+    /// Instructions kept verbatim because they have no translation.
+    Asm(Vec<AsmInsn>),
     /// Debugger statement.
     Debugger,
 }
 
+impl Stmt {
+    pub fn expr(expr: Expr) -> Self {
+        Stmt::Expr(expr)
+    }
+
+    pub fn let_(name: impl Into<String>, init: Option<Expr>) -> Self {
+        Stmt::Let {
+            name: name.into(),
+            init,
+        }
+    }
+
+    pub fn assign(target: Expr, value: Expr) -> Self {
+        Stmt::Assign { target, value }
+    }
+
+    pub fn ret(value: Option<Expr>) -> Self {
+        Stmt::Return(value)
+    }
+
+    pub fn if_(cond: Expr, then_body: Vec<Stmt>, else_body: Vec<Stmt>) -> Self {
+        Stmt::If {
+            cond,
+            then_body,
+            else_body,
+        }
+    }
+
+    pub fn while_(cond: Expr, body: Vec<Stmt>) -> Self {
+        Stmt::While { cond, body }
+    }
+
+    pub fn comment(text: impl Into<String>) -> Self {
+        Stmt::Comment(text.into())
+    }
+
+    /// Whether control never falls through to the next statement.
+    pub fn is_terminator(&self) -> bool {
+        matches!(
+            self,
+            Stmt::Return(_) | Stmt::Throw(_) | Stmt::Break | Stmt::Continue
+        )
+    }
+
+    /// The statement lists nested directly inside this statement, in
+    /// source order (switch cases before `default`).
+    pub fn bodies(&self) -> Vec<&[Stmt]> {
+        match self {
+            Stmt::If {
+                then_body,
+                else_body,
+                ..
+            } => vec![then_body, else_body],
+            Stmt::While { body, .. }
+            | Stmt::ForIn { body, .. }
+            | Stmt::ForOf { body, .. }
+            | Stmt::Block(body) => vec![body],
+            Stmt::TryCatch {
+                try_body,
+                catch_body,
+                finally_body,
+                ..
+            } => vec![try_body, catch_body, finally_body],
+            Stmt::Switch { cases, default, .. } => cases
+                .iter()
+                .map(|c| c.body.as_slice())
+                .chain([default.as_slice()])
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
 /// A single case in a switch statement.
 #[derive(Debug, Clone)]
 pub struct SwitchCase {
     pub test: Expr,
     pub body: Vec<Stmt>,
 }
+
+impl SwitchCase {
+    pub fn new(test: Expr, body: Vec<Stmt>) -> Self {
+        SwitchCase { test, body }
+    }
+}
+
+/// An instruction quoted in a [`Stmt::Asm`] block.
+#[derive(Debug, Clone)]
+pub struct AsmInsn {
+    /// Byte offset within the method's code.
+    pub offset: u32,
+    /// The instruction's encoding, exactly as it appears in the file.
+    pub bytes: Vec<u8>,
+    /// Disassembly, e.g. `callruntime.topropertykey`.
+    pub text: String,
+}
+
+impl AsmInsn {
+    /// The encoding as space-separated hex, e.g. `fb 03 00`.
+    pub fn hex(&self) -> String {
+        let mut out = String::with_capacity(self.bytes.len() * 3);
+        for (i, b) in self.bytes.iter().enumerate() {
+            if i > 0 {
+                out.push(' ');
+            }
+            let _ = write!(out, "{b:02x}");
+        }
+        out
+    }
+}
+
+/// `0x001a [fb 03 00] callruntime.topropertykey`
+impl fmt::Display for AsmInsn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#06x} [{}] {}", self.offset, self.hex(), self.text)
+    }
+}
@@ -0,0 +1,324 @@
+//! Traversal of expression and statement trees.
+//!
+//! [`ExprVisitor`] walks a tree by reference, [`ExprRewriter`] by mutable
+//! reference. Every trait method defaults to the matching `walk_*` function,
+//! which visits the node's children in source order. A pass overrides only
+//! the nodes it cares about and calls `walk_*` itself to keep descending:
+//!
+//! ```
+//! use abcd_ir::expr::Expr;
+//! use abcd_ir::visit::{self, ExprVisitor};
+//!
+//! struct Vars(Vec<String>);
+//!
+//! impl ExprVisitor for Vars {
+//!     fn visit_expr(&mut self, expr: &Expr) {
+//!         if let Expr::Var(name) = expr {
+//!             self.0.push(name.clone());
+//!         }
+//!         visit::walk_expr(self, expr);
+//!     }
+//! }
+//!
+//! let e = Expr::call(Expr::var("f"), vec![Expr::var("x"), Expr::num(1.0)]);
+//! let mut vars = Vars(Vec::new());
+//! vars.visit_expr(&e);
+//! assert_eq!(vars.0, ["f", "x"]);
+//! ```
+
+use crate::expr::{Expr, PropKey};
+use crate::stmt::{Stmt, SwitchCase};
+
+/// Read-only traversal of [`Expr`] and [`Stmt`] trees.
+pub trait ExprVisitor {
+    fn visit_expr(&mut self, expr: &Expr) {
+        walk_expr(self, expr);
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        walk_stmt(self, stmt);
+    }
+
+    fn visit_stmts(&mut self, stmts: &[Stmt]) {
+        for stmt in stmts {
+            self.visit_stmt(stmt);
+        }
+    }
+}
+
+/// In-place rewriting of [`Expr`] and [`Stmt`] trees.
+///
+/// To replace a node, assign through the reference, e.g.
+/// `*expr = Expr::Undefined`. Children are visited only if the override
+/// calls [`walk_expr_mut`] / [`walk_stmt_mut`], before or after replacing.
+pub trait ExprRewriter {
+    fn rewrite_expr(&mut self, expr: &mut Expr) {
+        walk_expr_mut(self, expr);
+    }
+
+    fn rewrite_stmt(&mut self, stmt: &mut Stmt) {
+        walk_stmt_mut(self, stmt);
+    }
+
+    /// Rewrite a statement list. Overriding this is the place to insert or
+    /// remove statements.
+    fn rewrite_stmts(&mut self, stmts: &mut Vec<Stmt>) {
+        for stmt in stmts {
+            self.rewrite_stmt(stmt);
+        }
+    }
+}
+
+/// Visit the direct children of `expr`.
+pub fn walk_expr<V: ExprVisitor + ?Sized>(v: &mut V, expr: &Expr) {
+    match expr {
+        Expr::BinaryOp { lhs, rhs, .. } => {
+            v.visit_expr(lhs);
+            v.visit_expr(rhs);
+        }
+        Expr::UnaryOp { expr: e, .. }
+        | Expr::TypeOf(e)
+        | Expr::Spread(e)
+        | Expr::Await(e)
+        | Expr::Yield(e)
+        | Expr::MemberAccess { object: e, .. } => v.visit_expr(e),
+        Expr::ComputedAccess { object, index } => {
+            v.visit_expr(object);
+            v.visit_expr(index);
+        }
+        Expr::Call { callee, args } | Expr::New { callee, args } => {
+            v.visit_expr(callee);
+            args.iter().for_each(|a| v.visit_expr(a));
+        }
+        Expr::SuperCall { args: items } | Expr::ArrayLit(items) | Expr::TemplateLit(items) => {
+            items.iter().for_each(|a| v.visit_expr(a));
+        }
+        Expr::ObjectLit(props) => {
+            for (key, value) in props {
+                if let PropKey::Computed(k) = key {
+                    v.visit_expr(k);
+                }
+                v.visit_expr(value);
+            }
+        }
+        Expr::Conditional {
+            cond,
+            then_expr,
+            else_expr,
+        } => {
+            v.visit_expr(cond);
+            v.visit_expr(then_expr);
+            v.visit_expr(else_expr);
+        }
+        Expr::Assign { target, value } => {
+            v.visit_expr(target);
+            v.visit_expr(value);
+        }
+        Expr::NumberLit(_)
+        | Expr::StringLit(_)
+        | Expr::BoolLit(_)
+        | Expr::Null
+        | Expr::Undefined
+        | Expr::Var(_)
+        | Expr::Function { .. }
+        | Expr::This
+        | Expr::NewTarget
+        | Expr::Acc
+        | Expr::Unknown(_) => {}
+    }
+}
+
+/// Visit the expressions and nested statements of `stmt`.
+pub fn walk_stmt<V: ExprVisitor + ?Sized>(v: &mut V, stmt: &Stmt) {
+    match stmt {
+        Stmt::Expr(e) | Stmt::Throw(e) | Stmt::Return(Some(e)) => v.visit_expr(e),
+        Stmt::Let { init, .. } => {
+            if let Some(e) = init {
+                v.visit_expr(e);
+            }
+        }
+        Stmt::Const { init, .. } => v.visit_expr(init),
+        Stmt::Assign { target, value } => {
+            v.visit_expr(target);
+            v.visit_expr(value);
+        }
+        Stmt::If {
+            cond,
+            then_body,
+            else_body,
+        } => {
+            v.visit_expr(cond);
+            v.visit_stmts(then_body);
+            v.visit_stmts(else_body);
+        }
+        Stmt::While { cond, body } => {
+            v.visit_expr(cond);
+            v.visit_stmts(body);
+        }
+        Stmt::ForIn {
+            object: e, body, ..
+        }
+        | Stmt::ForOf {
+            iterable: e, body, ..
+        } => {
+            v.visit_expr(e);
+            v.visit_stmts(body);
+        }
+        Stmt::TryCatch {
+            try_body,
+            catch_body,
+            finally_body,
+            ..
+        } => {
+            v.visit_stmts(try_body);
+            v.visit_stmts(catch_body);
+            v.visit_stmts(finally_body);
+        }
+        Stmt::Switch {
+            discriminant,
+            cases,
+            default,
+        } => {
+            v.visit_expr(discriminant);
+            for SwitchCase { test, body } in cases {
+                v.visit_expr(test);
+                v.visit_stmts(body);
+            }
+            v.visit_stmts(default);
+        }
+        Stmt::Block(body) => v.visit_stmts(body),
+        Stmt::Return(None)
+        | Stmt::Break
+        | Stmt::Continue
+        | Stmt::Comment(_)
+        | Stmt::Asm(_)
+        | Stmt::Debugger => {}
+    }
+}
+
+/// Rewrite the direct children of `expr`.
+pub fn walk_expr_mut<R: ExprRewriter + ?Sized>(r: &mut R, expr: &mut Expr) {
+    match expr {
+        Expr::BinaryOp { lhs, rhs, .. } => {
+            r.rewrite_expr(lhs);
+            r.rewrite_expr(rhs);
+        }
+        Expr::UnaryOp { expr: e, .. }
+        | Expr::TypeOf(e)
+        | Expr::Spread(e)
+        | Expr::Await(e)
+        | Expr::Yield(e)
+        | Expr::MemberAccess { object: e, .. } => r.rewrite_expr(e),
+        Expr::ComputedAccess { object, index } => {
+            r.rewrite_expr(object);
+            r.rewrite_expr(index);
+        }
+        Expr::Call { callee, args } | Expr::New { callee, args } => {
+            r.rewrite_expr(callee);
+            args.iter_mut().for_each(|a| r.rewrite_expr(a));
+        }
+        Expr::SuperCall { args: items } | Expr::ArrayLit(items) | Expr::TemplateLit(items) => {
+            items.iter_mut().for_each(|a| r.rewrite_expr(a));
+        }
+        Expr::ObjectLit(props) => {
+            for (key, value) in props {
+                if let PropKey::Computed(k) = key {
+                    r.rewrite_expr(k);
+                }
+                r.rewrite_expr(value);
+            }
+        }
+        Expr::Conditional {
+            cond,
+            then_expr,
+            else_expr,
+        } => {
+            r.rewrite_expr(cond);
+            r.rewrite_expr(then_expr);
+            r.rewrite_expr(else_expr);
+        }
+        Expr::Assign { target, value } => {
+            r.rewrite_expr(target);
+            r.rewrite_expr(value);
+        }
+        Expr::NumberLit(_)
+        | Expr::StringLit(_)
+        | Expr::BoolLit(_)
+        | Expr::Null
+        | Expr::Undefined
+        | Expr::Var(_)
+        | Expr::Function { .. }
+        | Expr::This
+        | Expr::NewTarget
+        | Expr::Acc
+        | Expr::Unknown(_) => {}
+    }
+}
+
+/// Rewrite the expressions and nested statements of `stmt`.
+pub fn walk_stmt_mut<R: ExprRewriter + ?Sized>(r: &mut R, stmt: &mut Stmt) {
+    match stmt {
+        Stmt::Expr(e) | Stmt::Throw(e) | Stmt::Return(Some(e)) => r.rewrite_expr(e),
+        Stmt::Let { init, .. } => {
+            if let Some(e) = init {
+                r.rewrite_expr(e);
+            }
+        }
+        Stmt::Const { init, .. } => r.rewrite_expr(init),
+        Stmt::Assign { target, value } => {
+            r.rewrite_expr(target);
+            r.rewrite_expr(value);
+        }
+        Stmt::If {
+            cond,
+            then_body,
+            else_body,
+        } => {
+            r.rewrite_expr(cond);
+            r.rewrite_stmts(then_body);
+            r.rewrite_stmts(else_body);
+        }
+        Stmt::While { cond, body } => {
+            r.rewrite_expr(cond);
+            r.rewrite_stmts(body);
+        }
+        Stmt::ForIn {
+            object: e, body, ..
+        }
+        | Stmt::ForOf {
+            iterable: e, body, ..
+        } => {
+            r.rewrite_expr(e);
+            r.rewrite_stmts(body);
+        }
+        Stmt::TryCatch {
+            try_body,
+            catch_body,
+            finally_body,
+            ..
+        } => {
+            r.rewrite_stmts(try_body);
+            r.rewrite_stmts(catch_body);
+            r.rewrite_stmts(finally_body);
+        }
+        Stmt::Switch {
+            discriminant,
+            cases,
+            default,
+        } => {
+            r.rewrite_expr(discriminant);
+            for SwitchCase { test, body } in cases {
+                r.rewrite_expr(test);
+                r.rewrite_stmts(body);
+            }
+            r.rewrite_stmts(default);
+        }
+        Stmt::Block(body) => r.rewrite_stmts(body),
+        Stmt::Return(None)
+        | Stmt::Break
+        | Stmt::Continue
+        | Stmt::Comment(_)
+        | Stmt::Asm(_)
+        | Stmt::Debugger => {}
+    }
+}
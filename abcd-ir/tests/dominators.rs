@@ -0,0 +1,84 @@
+//! `Dominators` with and without the CFG's exception edges.
+
+use abcd_ir::cfg::{BlockId, CFG};
+use abcd_ir::dominators::Dominators;
+use abcd_ir::instruction::{CatchBlockInfo, Instruction, TryBlockInfo};
+use abcd_isa::{Imm, Label, Reg, decode_all, encode, insn};
+
+/// `try { if (!true) {} v0 = 1 } catch { v0 = 0 } return v0`, with the
+/// block of each instruction index.
+fn build() -> (CFG, Vec<TryBlockInfo>, impl Fn(usize) -> BlockId) {
+    let (code, offsets) = encode(&[
+        insn::Ldtrue::new(),
+        insn::Jnez::new(Label(3)),
+        insn::Ldai::new(Imm(2)),
+        insn::Ldai::new(Imm(1)),
+        insn::Sta::new(Reg(0)),
+        insn::Jmp::new(Label(8)),
+        insn::Ldai::new(Imm(0)),
+        insn::Sta::new(Reg(0)),
+        insn::Lda::new(Reg(0)),
+        insn::Return::new(),
+    ])
+    .unwrap();
+    let instructions: Vec<Instruction> = decode_all(&code)
+        .unwrap()
+        .into_iter()
+        .map(|d| Instruction {
+            offset: d.offset,
+            opcode: d.bc,
+            size: d.size,
+        })
+        .collect();
+    let try_blocks = vec![TryBlockInfo {
+        start_pc: 0,
+        length: offsets[6],
+        catch_blocks: vec![CatchBlockInfo {
+            type_idx: 0,
+            handler_pc: offsets[6],
+            code_size: offsets[8] - offsets[6],
+        }],
+    }];
+    let cfg = CFG::build(&instructions, &try_blocks);
+    let blocks: Vec<BlockId> = offsets
+        .iter()
+        .map(|&off| cfg.block_at_offset(off).unwrap())
+        .collect();
+    (cfg, try_blocks, move |i| blocks[i])
+}
+
+#[test]
+fn normal_edges_leave_handlers_undominated() {
+    let (cfg, _, block) = build();
+    let doms = Dominators::compute(&cfg);
+    assert_eq!(doms.idom(cfg.entry), None);
+    assert_eq!(doms.idom(block(2)), Some(block(0)));
+    assert_eq!(doms.idom(block(3)), Some(block(0)));
+    assert!(!doms.is_reachable(block(6)));
+    // The join after the try is only reached from the try body.
+    assert!(doms.dominates(block(3), block(8)));
+    assert_eq!(doms.common_dominator(block(3), block(6)), None);
+}
+
+#[test]
+fn handler_edges_make_the_try_dominate_its_handler() {
+    let (cfg, try_blocks, block) = build();
+    let doms = Dominators::compute_with_handlers(&cfg, &try_blocks);
+    assert!(doms.is_reachable(block(6)));
+    assert_eq!(doms.idom(block(6)), Some(block(0)));
+    // The handler falls into the join, so the try body no longer
+    // dominates it.
+    assert!(!doms.dominates(block(3), block(8)));
+    assert_eq!(doms.idom(block(8)), Some(block(0)));
+    assert_eq!(doms.common_dominator(block(3), block(6)), Some(block(0)));
+}
+
+#[test]
+fn every_block_dominates_itself() {
+    let (cfg, try_blocks, _) = build();
+    let doms = Dominators::compute_with_handlers(&cfg, &try_blocks);
+    for b in 0..cfg.blocks.len() {
+        assert!(doms.dominates(b, b));
+        assert!(doms.dominates(cfg.entry, b));
+    }
+}
@@ -0,0 +1,172 @@
+//! `ExprVisitor` and `ExprRewriter` reach every nested expression and
+//! statement list.
+
+use abcd_ir::expr::{Expr, PropKey};
+use abcd_ir::stmt::{Stmt, SwitchCase};
+use abcd_ir::visit::{self, ExprRewriter, ExprVisitor};
+
+/// Variables named `a` to `u`, one in each place an expression or
+/// statement list can nest, in source order.
+fn tree() -> Vec<Stmt> {
+    let v = Expr::var;
+    vec![
+        Stmt::let_("x", Some(Expr::conditional(v("a"), v("b"), v("c")))),
+        Stmt::if_(
+            v("d"),
+            vec![Stmt::expr(Expr::call(v("e"), vec![Expr::spread(v("f"))]))],
+            vec![Stmt::ret(Some(v("g")))],
+        ),
+        Stmt::while_(
+            v("h"),
+            vec![Stmt::ForOf {
+                binding: "y".into(),
+                iterable: v("i"),
+                body: vec![Stmt::Throw(v("j"))],
+            }],
+        ),
+        Stmt::TryCatch {
+            try_body: vec![Stmt::expr(Expr::ObjectLit(vec![(
+                PropKey::Computed(v("k")),
+                v("l"),
+            )]))],
+            catch_binding: Some("err".into()),
+            catch_body: vec![Stmt::assign(v("m"), v("n"))],
+            finally_body: vec![Stmt::Block(vec![Stmt::expr(v("o"))])],
+        },
+        Stmt::Switch {
+            discriminant: v("p"),
+            cases: vec![SwitchCase {
+                test: v("q"),
+                body: vec![Stmt::expr(Expr::member(v("r"), "length"))],
+            }],
+            default: vec![Stmt::Const {
+                name: "z".into(),
+                init: Expr::computed(v("s"), Expr::assign(v("t"), v("u"))),
+            }],
+        },
+    ]
+}
+
+/// Every variable read, in visiting order.
+#[derive(Default)]
+struct Vars(Vec<String>);
+
+impl ExprVisitor for Vars {
+    fn visit_expr(&mut self, expr: &Expr) {
+        if let Expr::Var(name) = expr {
+            self.0.push(name.clone());
+        }
+        visit::walk_expr(self, expr);
+    }
+}
+
+fn vars(stmts: &[Stmt]) -> Vec<String> {
+    let mut vars = Vars::default();
+    vars.visit_stmts(stmts);
+    vars.0
+}
+
+fn names(range: std::ops::RangeInclusive<char>) -> Vec<String> {
+    range.map(String::from).collect()
+}
+
+#[test]
+fn visitor_walks_in_source_order() {
+    assert_eq!(vars(&tree()), names('a'..='u'));
+}
+
+/// Renames every variable to upper case.
+struct Upper;
+
+impl ExprRewriter for Upper {
+    fn rewrite_expr(&mut self, expr: &mut Expr) {
+        if let Expr::Var(name) = expr {
+            *name = name.to_uppercase();
+        }
+        visit::walk_expr_mut(self, expr);
+    }
+}
+
+#[test]
+fn rewriter_reaches_every_nested_expression() {
+    let mut stmts = tree();
+    Upper.rewrite_stmts(&mut stmts);
+    assert_eq!(vars(&stmts), names('A'..='U'));
+}
+
+/// Replaces calls with `undefined` without walking them, and counts the
+/// variables it walks past.
+#[derive(Default)]
+struct DropCalls(usize);
+
+impl ExprRewriter for DropCalls {
+    fn rewrite_expr(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Call { .. } => *expr = Expr::Undefined,
+            Expr::Var(_) => self.0 += 1,
+            _ => visit::walk_expr_mut(self, expr),
+        }
+    }
+}
+
+#[test]
+fn replaced_nodes_are_walked_only_on_request() {
+    let mut stmts = tree();
+    let mut rewriter = DropCalls::default();
+    rewriter.rewrite_stmts(&mut stmts);
+    // `e` and `f` were inside the call.
+    assert_eq!(rewriter.0, 19);
+    assert!(!vars(&stmts).iter().any(|v| v == "e" || v == "f"));
+    let Stmt::If { then_body, .. } = &stmts[1] else {
+        panic!("{:?}", stmts[1]);
+    };
+    assert!(matches!(then_body[..], [Stmt::Expr(Expr::Undefined)]));
+}
+
+/// Removes comments from every statement list.
+struct StripComments;
+
+impl ExprRewriter for StripComments {
+    fn rewrite_stmts(&mut self, stmts: &mut Vec<Stmt>) {
+        stmts.retain(|s| !matches!(s, Stmt::Comment(_)));
+        for stmt in stmts {
+            self.rewrite_stmt(stmt);
+        }
+    }
+}
+
+fn comments(stmts: &[Stmt]) -> usize {
+    stmts
+        .iter()
+        .map(|s| {
+            let nested: usize = s.bodies().into_iter().map(comments).sum();
+            usize::from(matches!(s, Stmt::Comment(_))) + nested
+        })
+        .sum()
+}
+
+#[test]
+fn statement_lists_can_be_edited_at_every_depth() {
+    let note = || Stmt::comment("note");
+    let mut stmts = vec![
+        note(),
+        Stmt::if_(Expr::var("c"), vec![note()], vec![Stmt::Break, note()]),
+        Stmt::Switch {
+            discriminant: Expr::var("d"),
+            cases: vec![SwitchCase {
+                test: Expr::num(1.0),
+                body: vec![note(), Stmt::Block(vec![note(), Stmt::Continue])],
+            }],
+            default: vec![note()],
+        },
+    ];
+    StripComments.rewrite_stmts(&mut stmts);
+
+    assert_eq!(comments(&stmts), 0);
+    assert_eq!(stmts.len(), 2);
+    let Stmt::Switch { cases, default, .. } = &stmts[1] else {
+        panic!("{:?}", stmts[1]);
+    };
+    assert!(matches!(cases[0].body[..], [Stmt::Block(ref inner)] if inner.len() == 1));
+    assert!(default.is_empty());
+}
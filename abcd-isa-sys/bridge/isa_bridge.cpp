@@ -3,7 +3,9 @@
 #include "bytecode_emitter.h"
 #include <file_format_version.h>
 #include <cstring>
+#include <new>
 #include <sstream>
+#include <string>
 #include <vector>
 
 using Inst = panda::BytecodeInst<panda::BytecodeInstMode::FAST>;
@@ -18,8 +20,20 @@ struct IsaEmitter {
     std::vector<panda::Label> labels;
 };
 
+/* Reason for the last failed emitter call on this thread (see isa_last_error). */
+static thread_local std::string g_last_error;
+
+static int emit_dispatch(IsaEmitter* e, uint16_t opcode,
+                         const int64_t* args, size_t num_args) {
+#include <isa_bridge_emit_dispatch.h>
+}
+
 extern "C" {
 
+const char* isa_last_error(void) {
+    return g_last_error.empty() ? nullptr : g_last_error.c_str();
+}
+
 uint8_t isa_get_format(uint16_t opcode) {
     auto fmt = Inst::GetFormat(static_cast<Inst::Opcode>(opcode));
     return static_cast<uint8_t>(fmt);
@@ -239,6 +253,12 @@ void isa_update_id(uint8_t* bytes, uint32_t new_id, uint32_t idx) {
     // is mutable. UpdateId writes through the stored pointer, which is safe
     // because the underlying memory was allocated as non-const by the caller.
     using InstMut = panda::BytecodeInst<panda::BytecodeInstMode::FAST>;
+#if defined(__BYTE_ORDER__) && __BYTE_ORDER__ == __ORDER_BIG_ENDIAN__
+    // UpdateId memcpy's the leading `width` bytes of the host-order value.
+    // Swapping first makes those the low bytes in little-endian order,
+    // which is what the bytecode expects.
+    new_id = __builtin_bswap32(new_id);
+#endif
     InstMut inst(const_cast<const uint8_t*>(bytes));
     const_cast<InstMut&>(inst).UpdateId(panda::BytecodeId(new_id), idx);
 }
@@ -327,7 +347,10 @@ int isa_get_version_by_api_sub(uint8_t api_level, const char* sub_api, uint8_t o
 /* === Emitter API === */
 
 IsaEmitter* isa_emitter_create(void) {
-    return new IsaEmitter();
+    g_last_error.clear();
+    auto* e = new (std::nothrow) IsaEmitter();
+    if (e == nullptr) g_last_error = "allocation failed for emitter";
+    return e;
 }
 
 void isa_emitter_destroy(IsaEmitter* e) {
@@ -347,6 +370,7 @@ int isa_emitter_bind(IsaEmitter* e, uint32_t label_id) {
 }
 
 int isa_emitter_build(IsaEmitter* e, uint8_t** out_buf, size_t* out_len) {
+    g_last_error.clear();
     std::vector<uint8_t> output;
     auto rc = e->emitter.Build(&output);
     if (rc != panda::BytecodeEmitter::ErrorCode::SUCCESS) {
@@ -354,8 +378,10 @@ int isa_emitter_build(IsaEmitter* e, uint8_t** out_buf, size_t* out_len) {
         *out_len = 0;
         switch (rc) {
             case panda::BytecodeEmitter::ErrorCode::UNBOUND_LABELS:
+                g_last_error = "build: unbound labels";
                 return ISA_BUILD_UNBOUND_LABELS;
             default:
+                g_last_error = "build: BytecodeEmitter error code " + std::to_string(static_cast<int>(rc));
                 return ISA_BUILD_INTERNAL_ERROR;
         }
     }
@@ -371,7 +397,26 @@ void isa_emitter_free_buf(uint8_t* buf) {
 
 int isa_emitter_emit(IsaEmitter* e, uint16_t opcode,
                      const int64_t* args, size_t num_args) {
-#include <isa_bridge_emit_dispatch.h>
+    g_last_error.clear();
+    int rc = emit_dispatch(e, opcode, args, num_args);
+    switch (rc) {
+        case ISA_EMIT_OK:
+            break;
+        case ISA_EMIT_INVALID_LABEL:
+            g_last_error = "emit opcode " + std::to_string(opcode) + ": invalid label id";
+            break;
+        case ISA_EMIT_TOO_FEW_ARGS:
+            g_last_error = "emit opcode " + std::to_string(opcode) + ": too few operands (" +
+                           std::to_string(num_args) + " given)";
+            break;
+        case ISA_EMIT_UNKNOWN_OPCODE:
+            g_last_error = "emit: unknown opcode " + std::to_string(opcode);
+            break;
+        default:
+            g_last_error = "emit opcode " + std::to_string(opcode) + ": error " + std::to_string(rc);
+            break;
+    }
+    return rc;
 }
 
 } /* extern "C" */
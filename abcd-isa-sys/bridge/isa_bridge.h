@@ -160,6 +160,10 @@ int isa_get_version_by_api_sub(uint8_t api_level, const char* sub_api, uint8_t o
 /* === Emitter (stateful) === */
 typedef struct IsaEmitter IsaEmitter;
 
+/* Reason for the last failed create/emit/build call on this thread, or NULL.
+ * Valid until the next emitter call on the same thread. */
+const char* isa_last_error(void);
+
 IsaEmitter* isa_emitter_create(void);
 void isa_emitter_destroy(IsaEmitter* e);
 
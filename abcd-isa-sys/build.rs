@@ -86,6 +86,16 @@ fn main() {
         &format!("{out_dir}/bytecode.rs"),
     );
 
+    // Generate conformance.rs (one encoded instruction per isa.yaml format,
+    // checked against the decoder and Display by abcd-isa's tests)
+    run_ruby(
+        &gen_rb,
+        &isa_yaml,
+        &requires,
+        &format!("{manifest}/templates/conformance.rs.erb"),
+        &format!("{out_dir}/conformance.rs"),
+    );
+
     // Phase 2: Compile C++ bridge
     let mut cc_build = cc::Build::new();
     cc_build
@@ -136,6 +146,7 @@ fn main() {
     let bindings = bindgen::Builder::default()
         .header(&wrapper_h)
         .parse_callbacks(Box::new(bindgen::CargoCallbacks::new()))
+        .use_core()
         .allowlist_function("isa_.*")
         .allowlist_type("Isa.*")
         .allowlist_var("ISA_.*")
@@ -0,0 +1,117 @@
+//! Calling conventions of the call instructions.
+//!
+//! Every call takes the callee from the accumulator and leaves the result
+//! there; the instructions differ in where they keep the receiver and the
+//! arguments. [`CallInfo`] gives those places as operand indices, the same
+//! for every encoding of a mnemonic, so an analysis can find a call's
+//! arguments without a match arm per instruction:
+//!
+//! ```
+//! use abcd_isa_sys::call::{CallArgs, CallKind};
+//! use abcd_isa_sys::{Imm, Reg, insn};
+//!
+//! let info = insn::Callthis1::new(Imm(0), Reg(2), Reg(3)).call_info().unwrap();
+//! assert_eq!(info.kind, CallKind::This);
+//! assert_eq!(info.this_operand, Some(1));
+//! assert_eq!(info.args, CallArgs::Fixed { first: 2, count: 1 });
+//! ```
+//!
+//! The `deprecated.` calls take the callee from a register instead and are
+//! not described, nor are `apply`, `newobjrange` and the other instructions
+//! that call something without being calls in the source.
+
+use crate::{Bytecode, OpcodeInfo};
+
+/// Family of a call instruction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CallKind {
+    /// `callarg0` to `callargs3`: no receiver, arguments in registers.
+    Arg,
+    /// `callthis0` to `callthis3`: receiver and arguments in registers.
+    This,
+    /// `callrange` and `callthisrange`: arguments in a register window,
+    /// after the receiver for `callthisrange`.
+    Range,
+    /// `supercallthisrange`, `supercallarrowrange` and `supercallspread`:
+    /// `super(...)` in a derived class constructor.
+    Super,
+}
+
+/// Where a call's arguments are.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CallArgs {
+    /// `count` register operands from operand `first` on, in order.
+    Fixed { first: usize, count: usize },
+    /// A register window starting at register operand `start`, with its
+    /// length in immediate operand `count`; see
+    /// [`Bytecode::range_args`] for the window itself.
+    Range { count: usize, start: usize },
+    /// One register operand holding an array spread over the arguments.
+    Spread { operand: usize },
+}
+
+/// Calling convention of a call instruction; see [`crate::call`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CallInfo {
+    pub kind: CallKind,
+    pub args: CallArgs,
+    /// Register operand holding the receiver. For `callthisrange` that is
+    /// the window start: the receiver is the window's first register.
+    pub this_operand: Option<usize>,
+}
+
+impl Bytecode {
+    /// Calling convention of this instruction, or `None` if it is not a
+    /// call; see [`crate::call`].
+    pub fn call_info(&self) -> Option<CallInfo> {
+        use CallArgs::{Fixed, Range, Spread};
+
+        let fixed = |first, count| Fixed { first, count };
+        let (kind, args, this_operand) = match self {
+            Bytecode::Callarg0(..) => (CallKind::Arg, fixed(1, 0), None),
+            Bytecode::Callarg1(..) => (CallKind::Arg, fixed(1, 1), None),
+            Bytecode::Callargs2(..) => (CallKind::Arg, fixed(1, 2), None),
+            Bytecode::Callargs3(..) => (CallKind::Arg, fixed(1, 3), None),
+            Bytecode::Callthis0(..) => (CallKind::This, fixed(2, 0), Some(1)),
+            Bytecode::Callthis1(..) => (CallKind::This, fixed(2, 1), Some(1)),
+            Bytecode::Callthis2(..) => (CallKind::This, fixed(2, 2), Some(1)),
+            Bytecode::Callthis3(..) => (CallKind::This, fixed(2, 3), Some(1)),
+            Bytecode::Callrange(..) => (CallKind::Range, Range { count: 1, start: 2 }, None),
+            Bytecode::WideCallrange(..) => (CallKind::Range, Range { count: 0, start: 1 }, None),
+            Bytecode::Callthisrange(..) => (CallKind::Range, Range { count: 1, start: 2 }, Some(2)),
+            Bytecode::WideCallthisrange(..) => {
+                (CallKind::Range, Range { count: 0, start: 1 }, Some(1))
+            }
+            Bytecode::Supercallthisrange(..) | Bytecode::Supercallarrowrange(..) => {
+                (CallKind::Super, Range { count: 1, start: 2 }, None)
+            }
+            Bytecode::WideSupercallthisrange(..) | Bytecode::WideSupercallarrowrange(..) => {
+                (CallKind::Super, Range { count: 0, start: 1 }, None)
+            }
+            Bytecode::Supercallspread(..) => (CallKind::Super, Spread { operand: 1 }, None),
+            _ => return None,
+        };
+        Some(CallInfo {
+            kind,
+            args,
+            this_operand,
+        })
+    }
+
+    /// Family of this call instruction, or `None` if it is not a call.
+    pub fn call_kind(&self) -> Option<CallKind> {
+        self.call_info().map(|info| info.kind)
+    }
+}
+
+impl OpcodeInfo {
+    /// Calling convention shared by every encoding of this instruction.
+    pub fn call_info(&self) -> Option<CallInfo> {
+        self.template.call_info()
+    }
+
+    /// Family of this call instruction, or `None` if it is not a call.
+    pub fn call_kind(&self) -> Option<CallKind> {
+        self.template.call_kind()
+    }
+}
@@ -0,0 +1,255 @@
+//! Semantic categories of instructions.
+//!
+//! `isa.yaml` groups instructions by how they were added rather than by
+//! what they do (its "object visitors" group holds property access, module
+//! variables and generator resumption alike). [`OpcodeCategory`] sorts every
+//! instruction into one of twelve categories by what it does, for coloring
+//! disassembly and for counting instruction mixes per method or file.
+//!
+//! ```ignore
+//! use abcd_isa_sys::category::OpcodeCategory;
+//!
+//! assert_eq!(insn::Add2::new(Imm(0), Reg(0)).category(), OpcodeCategory::Arithmetic);
+//! for row in opcode_table().iter().filter(|r| r.category() == OpcodeCategory::ModuleOp) {
+//!     println!("{}", row.mnemonic);
+//! }
+//! ```
+
+use crate::cost::{CALL, base_mnemonic};
+use crate::{Bytecode, BytecodeFlag, OpcodeInfo};
+
+/// What an instruction does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum OpcodeCategory {
+    /// Loads a constant, register or variable into the accumulator.
+    Load,
+    /// Stores the accumulator to a register or variable, or moves between
+    /// registers.
+    Store,
+    /// Arithmetic, bitwise and unary operators and numeric conversions.
+    Arithmetic,
+    /// Comparisons and truthiness tests.
+    Compare,
+    /// Jumps and returns.
+    Jump,
+    /// Calls and constructs a function.
+    Call,
+    /// Creates objects, arrays, functions and classes, and accesses their
+    /// properties.
+    ObjectOp,
+    /// Creates or steps iterators.
+    IteratorOp,
+    /// Module variables, namespaces and dynamic import.
+    ModuleOp,
+    /// Throws.
+    ExceptionOp,
+    /// Generators and async functions: creation, suspension and resumption.
+    Async,
+    /// Everything else: environments, `nop`, `debugger`.
+    Misc,
+}
+
+impl OpcodeCategory {
+    /// Every category, in declaration order.
+    pub const ALL: [OpcodeCategory; 12] = [
+        OpcodeCategory::Load,
+        OpcodeCategory::Store,
+        OpcodeCategory::Arithmetic,
+        OpcodeCategory::Compare,
+        OpcodeCategory::Jump,
+        OpcodeCategory::Call,
+        OpcodeCategory::ObjectOp,
+        OpcodeCategory::IteratorOp,
+        OpcodeCategory::ModuleOp,
+        OpcodeCategory::ExceptionOp,
+        OpcodeCategory::Async,
+        OpcodeCategory::Misc,
+    ];
+
+    /// Lowercase name (`"object"`), usable as a CSS class.
+    pub fn name(self) -> &'static str {
+        match self {
+            OpcodeCategory::Load => "load",
+            OpcodeCategory::Store => "store",
+            OpcodeCategory::Arithmetic => "arithmetic",
+            OpcodeCategory::Compare => "compare",
+            OpcodeCategory::Jump => "jump",
+            OpcodeCategory::Call => "call",
+            OpcodeCategory::ObjectOp => "object",
+            OpcodeCategory::IteratorOp => "iterator",
+            OpcodeCategory::ModuleOp => "module",
+            OpcodeCategory::ExceptionOp => "exception",
+            OpcodeCategory::Async => "async",
+            OpcodeCategory::Misc => "misc",
+        }
+    }
+}
+
+impl core::fmt::Display for OpcodeCategory {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+// Flags cover jumps and returns only, so the rest is decided by mnemonic.
+// Entries are base mnemonics, as in `cost`.
+
+const ASYNC: &[&str] = &[
+    "asyncfunctionawaituncaught",
+    "asyncfunctionenter",
+    "asyncfunctionreject",
+    "asyncfunctionresolve",
+    "asyncgeneratorreject",
+    "asyncgeneratorresolve",
+    "createasyncgeneratorobj",
+    "creategeneratorobj",
+    "getresumemode",
+    "resumegenerator",
+    "setgeneratorstate",
+    "suspendgenerator",
+];
+
+const MODULE: &[&str] = &["dynamicimport", "getmodulenamespace"];
+
+const ITERATOR: &[&str] = &[
+    "closeiterator",
+    "createiterresultobj",
+    "getasynciterator",
+    "getiterator",
+    "getiteratornext",
+    "getnextpropname",
+    "getpropiterator",
+];
+
+const COMPARE: &[&str] = &[
+    "eq",
+    "greater",
+    "greatereq",
+    "instanceof",
+    "isfalse",
+    "isin",
+    "istrue",
+    "less",
+    "lesseq",
+    "noteq",
+    "stricteq",
+    "strictnoteq",
+    "testin",
+];
+
+const ARITHMETIC: &[&str] = &[
+    "add2",
+    "and2",
+    "ashr2",
+    "dec",
+    "div2",
+    "exp",
+    "inc",
+    "mod2",
+    "mul2",
+    "neg",
+    "not",
+    "or2",
+    "shl2",
+    "shr2",
+    "sub2",
+    "tonumber",
+    "tonumeric",
+    "topropertykey",
+    "typeof",
+    "xor2",
+];
+
+const OBJECT: &[&str] = &[
+    "copydataproperties",
+    "copyrestargs",
+    "createarraywithbuffer",
+    "createemptyarray",
+    "createemptyobject",
+    "createobjecthavingmethod",
+    "createobjectwithbuffer",
+    "createobjectwithexcludedkeys",
+    "createprivateproperty",
+    "createregexpwithliteral",
+    "defineclasswithbuffer",
+    "definefieldbyindex",
+    "definefieldbyname",
+    "definefieldbyvalue",
+    "definefunc",
+    "definegettersetterbyvalue",
+    "definemethod",
+    "defineprivateproperty",
+    "definepropertybyname",
+    "definesendableclass",
+    "delobjprop",
+    "gettemplateobject",
+    "getunmappedargs",
+    "ldhomeobject",
+    "ldobjbyindex",
+    "ldobjbyname",
+    "ldobjbyvalue",
+    "ldprivateproperty",
+    "ldsuperbyname",
+    "ldsuperbyvalue",
+    "ldthisbyname",
+    "ldthisbyvalue",
+    "setobjectwithproto",
+    "starrayspread",
+    "stobjbyindex",
+    "stobjbyname",
+    "stobjbyvalue",
+    "stownbyindex",
+    "stownbyname",
+    "stownbynamewithnameset",
+    "stownbyvalue",
+    "stownbyvaluewithnameset",
+    "stprivateproperty",
+    "stsuperbyname",
+    "stsuperbyvalue",
+    "stthisbyname",
+    "stthisbyvalue",
+];
+
+impl Bytecode {
+    /// Semantic category of this instruction; see [`crate::category`].
+    pub fn category(&self) -> OpcodeCategory {
+        let base = base_mnemonic(self.mnemonic());
+        // `callruntime.wideldsendablevar` and friends spell their wide form
+        // without the dot.
+        let base = base.strip_prefix("wide").unwrap_or(base);
+        if self.has_flag(BytecodeFlag::JUMP | BytecodeFlag::RETURN) {
+            OpcodeCategory::Jump
+        } else if base.starts_with("throw") {
+            OpcodeCategory::ExceptionOp
+        } else if self.has_flag(BytecodeFlag::SUSPEND) || ASYNC.contains(&base) {
+            OpcodeCategory::Async
+        } else if base.contains("modulevar") || MODULE.contains(&base) {
+            OpcodeCategory::ModuleOp
+        } else if self.has_flag(BytecodeFlag::CALL | BytecodeFlag::CALL_VIRT)
+            || CALL.contains(&base)
+        {
+            OpcodeCategory::Call
+        } else if ITERATOR.contains(&base) {
+            OpcodeCategory::IteratorOp
+        } else if COMPARE.contains(&base) {
+            OpcodeCategory::Compare
+        } else if ARITHMETIC.contains(&base) {
+            OpcodeCategory::Arithmetic
+        } else if OBJECT.contains(&base) {
+            OpcodeCategory::ObjectOp
+        } else if base.starts_with("ld") || base == "fldai" || base == "tryldglobalbyname" {
+            OpcodeCategory::Load
+        } else if base.starts_with("st") || base == "mov" || base == "trystglobalbyname" {
+            OpcodeCategory::Store
+        } else {
+            OpcodeCategory::Misc
+        }
+    }
+}
+
+impl OpcodeInfo {
+    /// Category shared by every encoding of this instruction.
+    pub fn category(&self) -> OpcodeCategory {
+        self.template.category()
+    }
+}
@@ -0,0 +1,160 @@
+//! Where generators and async functions suspend, and what resumes them.
+//!
+//! es2abc lowers every `yield` and `await` to `suspendgenerator`, which
+//! returns to the caller; the caller comes back to the next instruction,
+//! `resumegenerator`, which loads the value sent in, and `getresumemode`
+//! then tells whether it was sent, thrown or returned. An `await` is
+//! preceded by `asyncfunctionawaituncaught`, which subscribes the function
+//! to the promise, and a `yield` in an async generator by
+//! `asyncgeneratorresolve`, which settles the pending `next()`:
+//!
+//! ```text
+//! asyncfunctionawaituncaught v0    ; suspend_kind() Await
+//! suspendgenerator v0              ; suspend_kind() Generator
+//! resumegenerator                  ; resume_opcode() of both
+//! getresumemode
+//! ```
+//!
+//! [`OpcodeInfo::suspend_kind`] tells the three instructions apart and
+//! [`OpcodeInfo::resume_opcode`] gives the instruction that picks up after
+//! them. One instruction cannot tell what a given `suspendgenerator` is
+//! for, though, and `asyncgeneratorresolve` also settles the last `next()`
+//! on `return`, with nothing resumed after it. [`suspend_points`] classes
+//! each `suspendgenerator` of a function's code by the instruction that
+//! announces it, so an `await` counts once.
+//!
+//! [`Bytecode::is_suspend`] asks the ISA's `SUSPEND` flag, which is
+//! assigned to no instruction, and is false throughout.
+
+use alloc::vec::Vec;
+
+use crate::cost::base_mnemonic;
+use crate::{Bytecode, OpcodeInfo, opcode_table};
+
+/// What a `suspendgenerator` hands control back to the caller for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SuspendKind {
+    /// A `yield` in a generator.
+    Generator,
+    /// An `await`, in an async function or an async generator.
+    Await,
+    /// A `yield` in an async generator.
+    AsyncGenerator,
+}
+
+impl SuspendKind {
+    /// Lowercase name (`"await"`).
+    pub fn name(self) -> &'static str {
+        match self {
+            SuspendKind::Generator => "generator",
+            SuspendKind::Await => "await",
+            SuspendKind::AsyncGenerator => "async-generator",
+        }
+    }
+}
+
+impl core::fmt::Display for SuspendKind {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A suspend point of a function's code.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SuspendPoint {
+    /// Index of the `suspendgenerator` in the code.
+    pub index: usize,
+    pub kind: SuspendKind,
+}
+
+/// The suspend points of one function's code, in order.
+///
+/// A `suspendgenerator` is an [`SuspendKind::Await`] when an
+/// `asyncfunctionawaituncaught` precedes it, an
+/// [`SuspendKind::AsyncGenerator`] when an `asyncgeneratorresolve` does,
+/// and a [`SuspendKind::Generator`] otherwise. Only instructions since the
+/// previous suspension, return or throw count, so an
+/// `asyncgeneratorresolve` settling a `return` is not taken for a `yield`.
+///
+/// ```
+/// use abcd_isa_sys::coroutine::{SuspendKind, SuspendPoint, suspend_points};
+/// use abcd_isa_sys::{Reg, insn};
+///
+/// let code = [
+///     insn::Asyncfunctionawaituncaught::new(Reg(0)),
+///     insn::Suspendgenerator::new(Reg(0)),
+///     insn::Resumegenerator::new(),
+/// ];
+/// assert_eq!(
+///     suspend_points(&code),
+///     [SuspendPoint { index: 1, kind: SuspendKind::Await }]
+/// );
+/// ```
+pub fn suspend_points(code: &[Bytecode]) -> Vec<SuspendPoint> {
+    let mut points = Vec::new();
+    let mut kind = SuspendKind::Generator;
+    for (index, bc) in code.iter().enumerate() {
+        match base_mnemonic(bc.mnemonic()) {
+            "asyncfunctionawaituncaught" => kind = SuspendKind::Await,
+            "asyncgeneratorresolve" => kind = SuspendKind::AsyncGenerator,
+            "suspendgenerator" => {
+                points.push(SuspendPoint { index, kind });
+                kind = SuspendKind::Generator;
+            }
+            _ if bc.is_return_or_throw() => kind = SuspendKind::Generator,
+            _ => {}
+        }
+    }
+    points
+}
+
+impl OpcodeInfo {
+    /// What this instruction contributes to a suspension, whatever its
+    /// prefix:
+    ///
+    /// - `suspendgenerator`: [`SuspendKind::Generator`], the suspension
+    ///   itself. It is a whole `yield` in a generator and ends an `await`
+    ///   or async `yield` otherwise.
+    /// - `asyncfunctionawaituncaught`: [`SuspendKind::Await`], an `await`
+    ///   in an async function or async generator.
+    /// - `asyncgeneratorresolve`: [`SuspendKind::AsyncGenerator`], a
+    ///   `yield` in an async generator, or its `return`.
+    ///
+    /// `None` for everything else. Use [`suspend_points`] to count the
+    /// suspensions of a function.
+    pub fn suspend_kind(&self) -> Option<SuspendKind> {
+        match base_mnemonic(self.mnemonic) {
+            "suspendgenerator" => Some(SuspendKind::Generator),
+            "asyncfunctionawaituncaught" => Some(SuspendKind::Await),
+            "asyncgeneratorresolve" => Some(SuspendKind::AsyncGenerator),
+            _ => None,
+        }
+    }
+
+    /// The instruction execution resumes with after this one suspends:
+    /// `resumegenerator`, or `deprecated.resumegenerator` for the
+    /// `deprecated.` forms. `None` if it need not suspend, which includes
+    /// `asyncgeneratorresolve`, as it may be settling a `return`.
+    ///
+    /// ```
+    /// use abcd_isa_sys::coroutine::SuspendKind;
+    /// use abcd_isa_sys::opcode_table;
+    ///
+    /// let row = |m: &str| opcode_table().iter().find(|r| r.mnemonic == m).unwrap();
+    /// let await_ = row("deprecated.asyncfunctionawaituncaught");
+    /// assert_eq!(await_.suspend_kind(), Some(SuspendKind::Await));
+    /// assert_eq!(await_.resume_opcode().unwrap().mnemonic, "deprecated.resumegenerator");
+    /// assert!(row("asyncgeneratorresolve").resume_opcode().is_none());
+    /// ```
+    pub fn resume_opcode(&self) -> Option<&'static OpcodeInfo> {
+        if self.suspend_kind()? == SuspendKind::AsyncGenerator {
+            return None;
+        }
+        let resume = if self.mnemonic.starts_with("deprecated.") {
+            "deprecated.resumegenerator"
+        } else {
+            "resumegenerator"
+        };
+        opcode_table().iter().find(|row| row.mnemonic == resume)
+    }
+}
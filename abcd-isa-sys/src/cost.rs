@@ -0,0 +1,233 @@
+//! Static cost estimates for instructions.
+//!
+//! Without running an app there is no profile to go by, but instructions
+//! differ in cost by orders of magnitude: a register move is a few machine
+//! instructions, a call sets up a frame and a generator suspend saves one.
+//! [`CostClass`] sorts every instruction into one of five classes, and
+//! [`CostEstimate`] adds up the classes of a method's instructions into a
+//! single number for ranking methods against each other.
+//!
+//! ```ignore
+//! use abcd_isa_sys::cost::{CostClass, CostEstimate};
+//!
+//! assert_eq!(insn::Lda::new(Reg(0)).cost_class(), CostClass::Cheap);
+//! let estimate = CostEstimate::of(&code);
+//! println!("{} ({} calls)", estimate.total(), estimate.count(CostClass::Call));
+//! ```
+
+use crate::{Bytecode, BytecodeFlag, OpcodeInfo};
+
+/// Rough cost of executing an instruction once.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CostClass {
+    /// Accumulator and register traffic, constants, jumps and returns.
+    Cheap,
+    /// Operations with dynamic type checks or runtime lookups: arithmetic,
+    /// comparisons, property and variable access.
+    Moderate,
+    /// Allocates an object, array, closure, environment or iterator.
+    Alloc,
+    /// Calls or constructs a function.
+    Call,
+    /// Suspends a generator or async function.
+    Suspend,
+}
+
+impl CostClass {
+    /// Every class, cheapest first.
+    pub const ALL: [CostClass; 5] = [
+        CostClass::Cheap,
+        CostClass::Moderate,
+        CostClass::Alloc,
+        CostClass::Call,
+        CostClass::Suspend,
+    ];
+
+    /// Relative weight of one instruction of this class.
+    pub fn weight(self) -> u32 {
+        match self {
+            CostClass::Cheap => 1,
+            CostClass::Moderate => 4,
+            CostClass::Alloc => 12,
+            CostClass::Call => 20,
+            CostClass::Suspend => 30,
+        }
+    }
+
+    /// Lowercase name (`"alloc"`).
+    pub fn name(self) -> &'static str {
+        match self {
+            CostClass::Cheap => "cheap",
+            CostClass::Moderate => "moderate",
+            CostClass::Alloc => "alloc",
+            CostClass::Call => "call",
+            CostClass::Suspend => "suspend",
+        }
+    }
+}
+
+impl core::fmt::Display for CostClass {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+// `isa.yaml` flags calls, suspends and side-effect-free loads for only a
+// handful of instructions, so the flags are backed by tables of mnemonics.
+// Entries omit the `wide.`, `deprecated.` and `callruntime.` prefixes.
+
+pub(crate) const CALL: &[&str] = &[
+    "apply",
+    "callarg0",
+    "callarg1",
+    "callargs2",
+    "callargs3",
+    "callinit",
+    "callrange",
+    "callspread",
+    "callthis0",
+    "callthis1",
+    "callthis2",
+    "callthis3",
+    "callthisrange",
+    "dynamicimport",
+    "newobjapply",
+    "newobjrange",
+    "supercallarrowrange",
+    "supercallforwardallargs",
+    "supercallspread",
+    "supercallthisrange",
+];
+
+const SUSPEND: &[&str] = &[
+    "asyncfunctionawaituncaught",
+    "asyncgeneratorresolve",
+    "suspendgenerator",
+];
+
+const ALLOC: &[&str] = &[
+    "asyncfunctionenter",
+    "copydataproperties",
+    "copyrestargs",
+    "createarraywithbuffer",
+    "createasyncgeneratorobj",
+    "createemptyarray",
+    "createemptyobject",
+    "creategeneratorobj",
+    "createiterresultobj",
+    "createobjecthavingmethod",
+    "createobjectwithbuffer",
+    "createobjectwithexcludedkeys",
+    "createregexpwithliteral",
+    "defineclasswithbuffer",
+    "definefunc",
+    "definemethod",
+    "definesendableclass",
+    "getasynciterator",
+    "getiterator",
+    "getpropiterator",
+    "gettemplateobject",
+    "getunmappedargs",
+    "newlexenv",
+    "newlexenvwithname",
+    "newsendableenv",
+    "widenewsendableenv",
+];
+
+const CHEAP: &[&str] = &[
+    "debugger",
+    "fldai",
+    "lda",
+    "lda.str",
+    "ldai",
+    "ldfunction",
+    "ldlexenv",
+    "ldlexvar",
+    "ldnewtarget",
+    "ldthis",
+    "mov",
+    "mov.64",
+    "nop",
+    "poplexenv",
+    "sta",
+    "stlexvar",
+];
+
+/// `mnemonic` without its instruction-group prefix.
+pub(crate) fn base_mnemonic(mnemonic: &str) -> &str {
+    ["wide.", "deprecated.", "callruntime."]
+        .iter()
+        .find_map(|prefix| mnemonic.strip_prefix(prefix))
+        .unwrap_or(mnemonic)
+}
+
+impl Bytecode {
+    /// Static cost class of this instruction; see [`crate::cost`].
+    pub fn cost_class(&self) -> CostClass {
+        let base = base_mnemonic(self.mnemonic());
+        if self.has_flag(BytecodeFlag::SUSPEND) || SUSPEND.contains(&base) {
+            CostClass::Suspend
+        } else if self.has_flag(BytecodeFlag::CALL | BytecodeFlag::CALL_VIRT)
+            || CALL.contains(&base)
+        {
+            CostClass::Call
+        } else if ALLOC.contains(&base) {
+            CostClass::Alloc
+        } else if self
+            .has_flag(BytecodeFlag::NO_SIDE_EFFECT | BytecodeFlag::JUMP | BytecodeFlag::RETURN)
+            || CHEAP.contains(&base)
+        {
+            CostClass::Cheap
+        } else {
+            CostClass::Moderate
+        }
+    }
+}
+
+impl OpcodeInfo {
+    /// Cost class shared by every encoding of this instruction.
+    pub fn cost_class(&self) -> CostClass {
+        self.template.cost_class()
+    }
+}
+
+/// Instruction counts per [`CostClass`] over a stretch of code.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CostEstimate {
+    counts: [u32; 5],
+}
+
+impl CostEstimate {
+    /// Estimate for a sequence of instructions.
+    pub fn of<'a>(code: impl IntoIterator<Item = &'a Bytecode>) -> Self {
+        let mut estimate = CostEstimate::default();
+        for bc in code {
+            estimate.add(bc);
+        }
+        estimate
+    }
+
+    pub fn add(&mut self, bc: &Bytecode) {
+        self.counts[bc.cost_class() as usize] += 1;
+    }
+
+    /// Add the counts of `other`, e.g. to total a file's methods.
+    pub fn merge(&mut self, other: &CostEstimate) {
+        for (n, m) in self.counts.iter_mut().zip(other.counts) {
+            *n += m;
+        }
+    }
+
+    /// Number of instructions of class `class`.
+    pub fn count(&self, class: CostClass) -> u32 {
+        self.counts[class as usize]
+    }
+
+    /// Sum of the weights of every instruction.
+    pub fn total(&self) -> u64 {
+        CostClass::ALL
+            .iter()
+            .map(|&c| u64::from(self.count(c)) * u64::from(c.weight()))
+            .sum()
+    }
+}
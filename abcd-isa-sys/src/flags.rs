@@ -0,0 +1,74 @@
+//! Names for the flag sets generated from `isa.yaml`.
+//!
+//! [`BytecodeFlag`] and [`ExceptionType`] print as the `isa.yaml` names of
+//! their set bits, so metadata can be read without looking up the
+//! generated constants:
+//!
+//! ```
+//! use abcd_isa_sys::BytecodeFlag;
+//!
+//! let flags = BytecodeFlag::JUMP | BytecodeFlag::CONDITIONAL;
+//! assert_eq!(flags.to_string(), "JUMP|CONDITIONAL");
+//! ```
+
+use core::fmt;
+
+use crate::{BytecodeFlag, ExceptionType};
+
+impl BytecodeFlag {
+    /// Each set flag with its name, in bit order.
+    pub fn iter_set(self) -> impl Iterator<Item = (BytecodeFlag, &'static str)> {
+        self.iter_names().map(|(name, flag)| (flag, name))
+    }
+}
+
+impl ExceptionType {
+    /// Each set exception type with its name, in bit order.
+    pub fn iter_set(self) -> impl Iterator<Item = (ExceptionType, &'static str)> {
+        self.iter_names().map(|(name, flag)| (flag, name))
+    }
+}
+
+/// Names of the set flags joined by `|`, then any bits without a name in
+/// hex; `0` when nothing is set.
+impl fmt::Display for BytecodeFlag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_set(
+            f,
+            self.iter_set().map(|(_, name)| name),
+            self.bits() & !Self::all().bits(),
+        )
+    }
+}
+
+/// Same format as [`BytecodeFlag`]'s: `X_NULL|X_BOUNDS`.
+impl fmt::Display for ExceptionType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_set(
+            f,
+            self.iter_set().map(|(_, name)| name),
+            self.bits() & !Self::all().bits(),
+        )
+    }
+}
+
+fn write_set<'a>(
+    f: &mut fmt::Formatter<'_>,
+    names: impl Iterator<Item = &'a str>,
+    unnamed: u32,
+) -> fmt::Result {
+    let mut first = true;
+    for name in names {
+        if !first {
+            f.write_str("|")?;
+        }
+        f.write_str(name)?;
+        first = false;
+    }
+    match (unnamed, first) {
+        (0, true) => f.write_str("0"),
+        (0, false) => Ok(()),
+        (bits, true) => write!(f, "{bits:#x}"),
+        (bits, false) => write!(f, "|{bits:#x}"),
+    }
+}
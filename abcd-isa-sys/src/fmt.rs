@@ -0,0 +1,384 @@
+//! Optional name resolution for [`Bytecode`](crate::Bytecode)'s `Display`.
+//!
+//! Instructions only carry raw entity IDs. Installing a resolver on the
+//! current thread makes `Display` append the resolved name after every
+//! string, method and literal-array ID it prints:
+//!
+//! ```text
+//! lda.str id:42 ("hello")
+//! definefunc 0 id:108 (foo) 1
+//! ```
+//!
+//! The resolver is thread-local, so installing one for a disassembly pass
+//! does not affect formatting on other threads. Installing one needs the
+//! `std` feature; without it, `Display` prints IDs bare.
+//!
+//! [`Bytecode::write_formatted`] renders the same text into a buffer the
+//! caller owns, for loops that format many instructions.
+//! [`Bytecode::format_with`] takes a resolver per call instead and puts the
+//! names in place of the IDs (`lda.str "hello"`).
+//!
+//! [`Bytecode::id_kind`] and [`Bytecode::typed_id`] tell which kind of
+//! entity an ID operand names, for resolvers that look IDs up themselves.
+//!
+//! [`tokenize`] splits the same text into classified spans, so listings can
+//! be colored without pattern-matching the formatted string.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write as _;
+#[cfg(feature = "std")]
+use std::cell::RefCell;
+#[cfg(feature = "std")]
+use std::sync::Arc;
+
+use crate::operand::OperandKind;
+use crate::{Bytecode, EntityId, OpcodeInfo, opcode_table};
+
+impl Bytecode {
+    /// Write the instruction's `Display` text into `out`.
+    ///
+    /// Unlike `to_string`, this allocates nothing of its own: disassemblers
+    /// can format every instruction into one buffer that they clear between
+    /// lines. Names looked up through an installed [`IdResolver`] are still
+    /// allocated by the resolver.
+    pub fn write_formatted(&self, out: &mut impl core::fmt::Write) -> core::fmt::Result {
+        write!(out, "{self}")
+    }
+
+    /// The instruction's text with ID operands replaced by the names
+    /// `resolver` gives them: strings quoted, other entities as `@name`,
+    /// or `@"name"` when the name has spaces, commas, parentheses, quotes
+    /// or control characters. IDs it does not know stay `id:N`.
+    ///
+    /// ```text
+    /// lda.str "hello\n"
+    /// definefunc 0 @foo 1
+    /// definefunc 0 @"<lambda>(a, b)" 2
+    /// ```
+    ///
+    /// Quoted text escapes `\\`, `\"`, `\n`, `\t`, `\r` and `\0`, and
+    /// other control characters as `\u{..}`.
+    ///
+    /// Unlike a resolver installed for `Display`, this one is passed
+    /// explicitly, so it can borrow per-method state and works without
+    /// `std`. The names are placeholders `abcd_isa::asm::assemble_with`
+    /// reads back.
+    pub fn format_with<'a>(&'a self, resolver: &'a dyn IdResolver) -> FormatWith<'a> {
+        FormatWith {
+            inst: self,
+            resolver,
+        }
+    }
+}
+
+/// An instruction shown with resolved names; see
+/// [`Bytecode::format_with`].
+pub struct FormatWith<'a> {
+    inst: &'a Bytecode,
+    resolver: &'a dyn IdResolver,
+}
+
+impl core::fmt::Display for FormatWith<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let inst = self.inst;
+        f.write_str(inst.mnemonic())?;
+        let (opcode, args, count) = inst.emit_args();
+        let table = opcode_table();
+        let Ok(row) = table.binary_search_by_key(&opcode, |r| r.opcode) else {
+            return Ok(());
+        };
+        let label = inst.jump_label_arg_index();
+        let mut id_index = 0;
+        for (i, (desc, &value)) in table[row].operands.iter().zip(&args[..count]).enumerate() {
+            if label == Some(i) {
+                write!(f, " label_{value}")?;
+                continue;
+            }
+            match desc.kind {
+                OperandKind::Reg => write!(f, " v{value}")?,
+                OperandKind::Imm => write!(f, " {value}")?,
+                OperandKind::Id => {
+                    let kind = inst.id_kind(id_index);
+                    id_index += 1;
+                    let id = EntityId(value as u32);
+                    match kind.and_then(|k| Some((k, self.resolver.resolve(k, id)?))) {
+                        Some((IdKind::String, name)) => {
+                            f.write_char(' ')?;
+                            write_quoted(f, &name)?;
+                        }
+                        Some((_, name)) if needs_quotes(&name) => {
+                            f.write_str(" @")?;
+                            write_quoted(f, &name)?;
+                        }
+                        Some((_, name)) => write!(f, " @{name}")?,
+                        None => write!(f, " id:{value}")?,
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether `@name` would not read back as one word.
+fn needs_quotes(name: &str) -> bool {
+    name.is_empty()
+        || name
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || matches!(c, ',' | '(' | '"' | ';'))
+}
+
+/// `s` as a string literal `abcd_isa::asm` reads back.
+fn write_quoted(f: &mut core::fmt::Formatter<'_>, s: &str) -> core::fmt::Result {
+    f.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\t' => f.write_str("\\t")?,
+            '\r' => f.write_str("\\r")?,
+            '\0' => f.write_str("\\0")?,
+            c if c.is_control() => write!(f, "\\u{{{:x}}}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+/// Kind of entity an ID operand refers to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IdKind {
+    /// `string_id` operand.
+    String,
+    /// `method_id` operand.
+    Method,
+    /// `literalarray_id` operand.
+    LiteralArray,
+}
+
+/// An ID operand with the kind of entity it names, from
+/// [`Bytecode::typed_id`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TypedEntityRef {
+    String(EntityId),
+    Method(EntityId),
+    LiteralArray(EntityId),
+}
+
+impl TypedEntityRef {
+    pub fn kind(self) -> IdKind {
+        match self {
+            TypedEntityRef::String(_) => IdKind::String,
+            TypedEntityRef::Method(_) => IdKind::Method,
+            TypedEntityRef::LiteralArray(_) => IdKind::LiteralArray,
+        }
+    }
+
+    pub fn id(self) -> EntityId {
+        match self {
+            TypedEntityRef::String(id)
+            | TypedEntityRef::Method(id)
+            | TypedEntityRef::LiteralArray(id) => id,
+        }
+    }
+}
+
+impl OpcodeInfo {
+    /// Kind of ID operand `idx` in every encoding of this instruction; see
+    /// [`Bytecode::id_kind`].
+    pub fn id_kind(&self, idx: usize) -> Option<IdKind> {
+        self.template.id_kind(idx)
+    }
+}
+
+/// Byte range of one token within an instruction's `Display` text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn range(self) -> core::ops::Range<usize> {
+        self.start..self.end
+    }
+}
+
+/// What a [`tokenize`] span holds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    /// The instruction's mnemonic.
+    Mnemonic,
+    /// A virtual register, `v3`.
+    Register,
+    /// An immediate, including IC slots and float bits.
+    Immediate,
+    /// A jump target, `label_2`.
+    Label,
+    /// An entity ID, `id:42`.
+    Id,
+    /// The resolved name printed after an ID, parentheses included.
+    Comment,
+}
+
+/// Split the instruction's `Display` text into classified spans.
+///
+/// Spans index into `inst.to_string()` as formatted on this thread, so a
+/// resolver installed with [`set_display_resolver`] contributes
+/// [`TokenKind::Comment`] spans for the names it knows. Spans are in text
+/// order and cover everything but the separating spaces.
+pub fn tokenize(inst: &Bytecode) -> Vec<(Span, TokenKind)> {
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    let mut push = |tokens: &mut Vec<_>, len: usize, kind| {
+        tokens.push((
+            Span {
+                start: pos,
+                end: pos + len,
+            },
+            kind,
+        ));
+        pos += len + 1;
+    };
+    push(&mut tokens, inst.mnemonic().len(), TokenKind::Mnemonic);
+
+    let (opcode, args, count) = inst.emit_args();
+    let table = opcode_table();
+    let Ok(row) = table.binary_search_by_key(&opcode, |r| r.opcode) else {
+        return tokens;
+    };
+    let label = inst.jump_label_arg_index();
+    let mut id_index = 0;
+    for (i, (desc, &value)) in table[row].operands.iter().zip(&args[..count]).enumerate() {
+        if label == Some(i) {
+            push(
+                &mut tokens,
+                text_len(format_args!("label_{value}")),
+                TokenKind::Label,
+            );
+            continue;
+        }
+        match desc.kind {
+            OperandKind::Reg => {
+                push(
+                    &mut tokens,
+                    text_len(format_args!("v{value}")),
+                    TokenKind::Register,
+                );
+            }
+            OperandKind::Imm => {
+                push(
+                    &mut tokens,
+                    text_len(format_args!("{value}")),
+                    TokenKind::Immediate,
+                );
+            }
+            OperandKind::Id => {
+                push(
+                    &mut tokens,
+                    text_len(format_args!("id:{value}")),
+                    TokenKind::Id,
+                );
+                let kind = inst.id_kind(id_index);
+                id_index += 1;
+                let Some(kind) = kind else {
+                    continue;
+                };
+                let name = resolve(kind, EntityId(value as u32));
+                let len = match name {
+                    Some(name) if kind == IdKind::String => text_len(format_args!("({name:?})")),
+                    Some(name) => text_len(format_args!("({name})")),
+                    None => continue,
+                };
+                push(&mut tokens, len, TokenKind::Comment);
+            }
+        }
+    }
+    tokens
+}
+
+/// Length of formatted text, without allocating it.
+fn text_len(args: core::fmt::Arguments<'_>) -> usize {
+    struct Counter(usize);
+    impl core::fmt::Write for Counter {
+        fn write_str(&mut self, s: &str) -> core::fmt::Result {
+            self.0 += s.len();
+            Ok(())
+        }
+    }
+    let mut counter = Counter(0);
+    let _ = core::fmt::write(&mut counter, args);
+    counter.0
+}
+
+/// Maps entity IDs to human-readable names for instruction display.
+pub trait IdResolver {
+    /// Name of the entity, or `None` to print the bare ID.
+    fn resolve(&self, kind: IdKind, id: EntityId) -> Option<String>;
+}
+
+#[cfg(feature = "std")]
+thread_local! {
+    static RESOLVER: RefCell<Option<Arc<dyn IdResolver>>> = const { RefCell::new(None) };
+}
+
+/// Install (or with `None`, remove) the resolver used by `Display` on this
+/// thread. Returns the previously installed resolver.
+#[cfg(feature = "std")]
+pub fn set_display_resolver(resolver: Option<Arc<dyn IdResolver>>) -> Option<Arc<dyn IdResolver>> {
+    RESOLVER.with(|r| r.replace(resolver))
+}
+
+/// Install `resolver` until the returned guard is dropped, then restore
+/// whatever was installed before.
+#[cfg(feature = "std")]
+pub fn scoped_display_resolver(resolver: Arc<dyn IdResolver>) -> DisplayResolverGuard {
+    DisplayResolverGuard {
+        previous: set_display_resolver(Some(resolver)),
+    }
+}
+
+/// Restores the previous display resolver when dropped.
+#[cfg(feature = "std")]
+#[must_use = "the resolver is uninstalled as soon as the guard is dropped"]
+pub struct DisplayResolverGuard {
+    previous: Option<Arc<dyn IdResolver>>,
+}
+
+#[cfg(feature = "std")]
+impl Drop for DisplayResolverGuard {
+    fn drop(&mut self) {
+        set_display_resolver(self.previous.take());
+    }
+}
+
+/// Write an ID operand, followed by its resolved name if a resolver is
+/// installed and knows it.
+pub(crate) fn write_id(
+    f: &mut core::fmt::Formatter<'_>,
+    kind: IdKind,
+    id: EntityId,
+) -> core::fmt::Result {
+    write!(f, " id:{}", id.0)?;
+    match resolve(kind, id) {
+        Some(name) if kind == IdKind::String => write!(f, " ({name:?})"),
+        Some(name) => write!(f, " ({name})"),
+        None => Ok(()),
+    }
+}
+
+/// The installed resolver's name for an ID.
+#[cfg(feature = "std")]
+fn resolve(kind: IdKind, id: EntityId) -> Option<String> {
+    // Clone the handle so a resolver that formats instructions itself does
+    // not hit the RefCell borrow.
+    let resolver = RESOLVER.with(|r| r.borrow().clone());
+    resolver.and_then(|r| r.resolve(kind, id))
+}
+
+#[cfg(not(feature = "std"))]
+fn resolve(_kind: IdKind, _id: EntityId) -> Option<&'static str> {
+    None
+}
@@ -7,12 +7,34 @@
 //! - A safe [`Bytecode`] enum with per-instruction variants and operand accessors
 //! - Per-mnemonic constructor types in the [`insn`] module
 //! - Operand newtypes: [`Reg`], [`Imm`], [`EntityId`], [`Label`]
-//! - Classification flags: [`BytecodeFlag`], [`ExceptionType`]
+//! - Classification flags: [`BytecodeFlag`], [`ExceptionType`], which
+//!   `Display` as the names of their set bits
+//! - The full opcode table via [`opcode_table`], or one namespace of it via
+//!   [`opcode_table_for_namespace`], with operand positions
+//!   described in [`operand`], and every opcode value as a constant in
+//!   [`opcodes`]
+//! - Optional ID name resolution for `Display` in [`fmt`]
+//! - Static per-instruction cost classes in [`cost`]
+//! - Semantic instruction categories in [`category`]
+//! - Prefixed instruction families in [`prefix`]
+//! - Calling conventions of the call instructions in [`call`]
+//! - Generator and async suspend points, and what resumes them, in
+//!   [`coroutine`]
+//! - Narrow and wide encodings of an instruction via
+//!   [`OpcodeInfo::narrow_equivalent`] and [`OpcodeInfo::wide_equivalent`]
+//!
+//! # `no_std`
+//!
+//! The `std` feature, on by default, adds the thread-local ID resolvers of
+//! [`fmt`] and [`Bytecode::semantic_hash`]. Without it the crate needs only
+//! `core` and `alloc`: operand extraction, the opcode table, classification,
+//! `Display` and [`fmt::tokenize`] all work. The C++ bridge is still compiled and linked.
 //!
 //! Most users should depend on
 //! [`abcd-isa`](https://crates.io/crates/abcd-isa) instead, which wraps
 //! this crate in a safe `encode`/`decode` API.
 
+#![cfg_attr(not(feature = "std"), no_std)]
 #![allow(
     non_upper_case_globals,
     non_camel_case_types,
@@ -20,8 +42,27 @@
     dead_code
 )]
 
+extern crate alloc;
+
+pub mod call;
+pub mod category;
+pub mod coroutine;
+pub mod cost;
+mod flags;
+pub mod fmt;
+pub mod operand;
+pub mod prefix;
+mod width;
+
 // Raw FFI bindings (generated by bindgen).
 include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
 
 // Bytecode enum, operand newtypes, insn constructors (generated from bytecode.rs.erb).
 include!(concat!(env!("OUT_DIR"), "/bytecode.rs"));
+
+/// Test vectors built from `isa.yaml` alone, without going through the
+/// generated decoder (generated from conformance.rs.erb).
+#[doc(hidden)]
+pub mod conformance {
+    include!(concat!(env!("OUT_DIR"), "/conformance.rs"));
+}
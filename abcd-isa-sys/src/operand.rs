@@ -0,0 +1,226 @@
+//! Operand positions within an encoded instruction.
+//!
+//! Each [`OpcodeInfo`] row lists where its operands sit, taken from the
+//! format name in `isa.yaml` (`op_v1_4_v2_4`, `pref_op_imm_16`, ...).
+//! Offsets count from the first opcode byte, so prefixed formats start
+//! their operands at byte 2.
+//!
+//! [`OperandDesc::extract`] reads an operand straight from the bytes without
+//! going through the C bridge, which is what [`OpcodeInfo::vreg`] and
+//! `abcd_isa::decode_pure` use. [`OpcodeInfo::operand_values`] reads them
+//! all at once as typed [`OperandValue`]s, and
+//! [`Bytecode::operand_values`] does the same for a decoded instruction.
+
+use crate::{Bytecode, OpcodeInfo, Reg, opcode_table};
+
+/// What an operand encodes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OperandKind {
+    /// Virtual register number.
+    Reg,
+    /// Immediate, including jump offsets and IC slots.
+    Imm,
+    /// Index into the method's constant region.
+    Id,
+}
+
+/// Location of one operand in an encoding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OperandDesc {
+    pub kind: OperandKind,
+    /// First byte holding the operand, counted from the opcode.
+    pub byte_offset: u8,
+    /// Bit within that byte where the operand starts; non-zero only for
+    /// the high half of a packed 4-bit pair.
+    pub bit_offset: u8,
+    /// Width in bits: 4, 8, 16, 32 or 64.
+    pub width: u8,
+    /// Whether the operand is a signed immediate: jump offsets, signed
+    /// integers and floats. Registers, IDs and IC slots are unsigned.
+    pub signed: bool,
+    /// Whether the immediate holds the bits of an IEEE float rather than
+    /// an integer.
+    pub float: bool,
+}
+
+/// An operand read from the bytes, typed by what it encodes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OperandValue {
+    /// Virtual register number.
+    Reg(u16),
+    /// Integer immediate, sign-extended if signed; jump offsets are
+    /// relative to the instruction.
+    Imm(i64),
+    /// Floating-point immediate.
+    FloatImm(f64),
+    /// Index into the method's constant region.
+    Id(u32),
+}
+
+impl OperandDesc {
+    /// The operand's raw bits in `bytes`, which start at the opcode.
+    ///
+    /// Values are little-endian. Immediates come back unextended: a signed
+    /// 8-bit `-1` reads as `0xff`.
+    ///
+    /// # Panics
+    ///
+    /// If `bytes` ends before the operand does.
+    pub fn extract(&self, bytes: &[u8]) -> u64 {
+        let start = usize::from(self.byte_offset);
+        let len = (usize::from(self.bit_offset) + usize::from(self.width)).div_ceil(8);
+        let raw = bytes[start..start + len]
+            .iter()
+            .rev()
+            .fold(0u64, |acc, &b| (acc << 8) | u64::from(b));
+        let value = raw >> self.bit_offset;
+        if self.width >= 64 {
+            value
+        } else {
+            value & ((1 << self.width) - 1)
+        }
+    }
+
+    /// Overwrite the operand in `bytes` with the low [`width`](Self::width)
+    /// bits of `raw`, leaving every other bit alone; the inverse of
+    /// [`extract`](Self::extract).
+    ///
+    /// # Panics
+    ///
+    /// If `bytes` ends before the operand does.
+    pub fn insert(&self, bytes: &mut [u8], raw: u64) {
+        let start = usize::from(self.byte_offset);
+        let len = (usize::from(self.bit_offset) + usize::from(self.width)).div_ceil(8);
+        let span = &mut bytes[start..start + len];
+        let old = span
+            .iter()
+            .rev()
+            .fold(0u64, |acc, &b| (acc << 8) | u64::from(b));
+        let mask = if self.width >= 64 {
+            u64::MAX
+        } else {
+            (1 << self.width) - 1
+        };
+        let new = (old & !(mask << self.bit_offset)) | ((raw & mask) << self.bit_offset);
+        for (i, b) in span.iter_mut().enumerate() {
+            *b = (new >> (8 * i)) as u8;
+        }
+    }
+
+    /// The operand as the bridge reports it: [`extract`](Self::extract),
+    /// sign-extended if the operand is [`signed`](Self::signed).
+    ///
+    /// # Panics
+    ///
+    /// If `bytes` ends before the operand does.
+    pub fn value(&self, bytes: &[u8]) -> i64 {
+        let raw = self.extract(bytes);
+        if !self.signed || self.width >= 64 {
+            return raw as i64;
+        }
+        let shift = 64 - u32::from(self.width);
+        ((raw << shift) as i64) >> shift
+    }
+
+    /// The operand as an [`OperandValue`] of its kind.
+    ///
+    /// # Panics
+    ///
+    /// If `bytes` ends before the operand does.
+    pub fn read(&self, bytes: &[u8]) -> OperandValue {
+        self.typed(self.value(bytes))
+    }
+
+    /// `raw`, as [`value`](Self::value) or `emit_args` gives it, typed by
+    /// this operand's kind.
+    fn typed(&self, raw: i64) -> OperandValue {
+        match self.kind {
+            OperandKind::Reg => OperandValue::Reg(raw as u16),
+            OperandKind::Id => OperandValue::Id(raw as u32),
+            OperandKind::Imm if self.float && self.width == 32 => {
+                OperandValue::FloatImm(f64::from(f32::from_bits(raw as u32)))
+            }
+            OperandKind::Imm if self.float => OperandValue::FloatImm(f64::from_bits(raw as u64)),
+            OperandKind::Imm => OperandValue::Imm(raw),
+        }
+    }
+}
+
+impl OpcodeInfo {
+    /// Register operand `idx` (counting registers only) of the instruction
+    /// at the start of `bytes`, or `None` if it has fewer registers.
+    ///
+    /// Matches `isa_get_vreg` for this encoding without calling into C.
+    ///
+    /// # Panics
+    ///
+    /// If `bytes` is shorter than [`size`](Self::size).
+    pub fn vreg(&self, bytes: &[u8], idx: usize) -> Option<Reg> {
+        let desc = self
+            .operands
+            .iter()
+            .filter(|op| op.kind == OperandKind::Reg)
+            .nth(idx)?;
+        Some(Reg(desc.extract(bytes) as u16))
+    }
+
+    /// Every operand of the instruction at the start of `bytes`, in
+    /// signature order, so callers need not know which index holds which
+    /// kind in a given format.
+    ///
+    /// ```
+    /// use abcd_isa_sys::opcode_table;
+    /// use abcd_isa_sys::operand::OperandValue;
+    ///
+    /// // mov v1, v2 in op_v1_4_v2_4
+    /// let mov = opcode_table().iter().find(|r| r.opcode == 0x44).unwrap();
+    /// let values: Vec<_> = mov.operand_values(&[0x44, 0x21]).collect();
+    /// assert_eq!(values, [OperandValue::Reg(1), OperandValue::Reg(2)]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If `bytes` is shorter than [`size`](Self::size).
+    pub fn operand_values<'a>(&self, bytes: &'a [u8]) -> impl Iterator<Item = OperandValue> + 'a {
+        let _ = &bytes[..usize::from(self.size)];
+        self.operands.iter().map(move |desc| desc.read(bytes))
+    }
+
+    /// Whether this encoding carries an inline-cache slot operand; see
+    /// [`Bytecode::ic_slot`](crate::Bytecode::ic_slot) for which one.
+    pub fn has_ic_slot(&self) -> bool {
+        self.template.ic_slot().is_some()
+    }
+}
+
+impl Bytecode {
+    /// Every operand of this instruction, in signature order, typed by its
+    /// row of the opcode table like [`OpcodeInfo::operand_values`]. Jump
+    /// operands come out as [`OperandValue::Imm`] holding the label or
+    /// offset the instruction carries.
+    ///
+    /// ```
+    /// use abcd_isa_sys::operand::OperandValue;
+    /// use abcd_isa_sys::{Imm, Reg, insn};
+    ///
+    /// let values: Vec<_> = insn::Mov::new(Reg(1), Reg(2)).operand_values().collect();
+    /// assert_eq!(values, [OperandValue::Reg(1), OperandValue::Reg(2)]);
+    ///
+    /// let fldai = insn::Fldai::new(Imm(1.5f64.to_bits() as i64));
+    /// assert_eq!(fldai.operand_values().collect::<Vec<_>>(), [OperandValue::FloatImm(1.5)]);
+    /// ```
+    pub fn operand_values(&self) -> impl Iterator<Item = OperandValue> {
+        let (opcode, args, n) = self.emit_args();
+        let operands = opcode_table()
+            .iter()
+            .find(|row| row.opcode == opcode)
+            .map_or(&[][..], |row| row.operands);
+        operands
+            .iter()
+            .zip(args)
+            .take(n)
+            .map(|(desc, arg)| desc.typed(arg))
+    }
+}
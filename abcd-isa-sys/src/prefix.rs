@@ -0,0 +1,116 @@
+//! The prefixed instruction groups of the ISA.
+//!
+//! Opcodes past the single-byte range start with one of four prefix bytes
+//! (`isa.yaml`'s `prefixes:`), and the prefix says which family the
+//! instruction belongs to: runtime helper calls, instructions kept for old
+//! files, wide-index variants, and throw variants. [`PrefixGroup`] names
+//! those families so coverage and statistics can be split along them
+//! without comparing opcode bytes.
+//!
+//! ```ignore
+//! use abcd_isa_sys::prefix::PrefixGroup;
+//!
+//! for group in PrefixGroup::ALL {
+//!     println!("{group}: {} encodings", group.opcodes().count());
+//! }
+//! ```
+//!
+//! [`opcodes_with_prefix`] lists a group's rows from its prefix byte, and
+//! [`opcode_table_for_namespace`](crate::opcode_table_for_namespace)
+//! splits the table along `isa.yaml`'s other axis, the group namespace.
+
+use crate::{Bytecode, OpcodeInfo, opcode_table};
+
+/// Family of a prefixed instruction, named after its prefix.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PrefixGroup {
+    /// `0xfb`: calls into runtime helpers (`callruntime.*`).
+    CallRuntime,
+    /// `0xfc`: instructions es2abc no longer emits (`deprecated.*`).
+    Deprecated,
+    /// `0xfd`: variants with wider index or count operands (`wide.*`).
+    Wide,
+    /// `0xfe`: throw variants (`throw.*`).
+    Throw,
+}
+
+impl PrefixGroup {
+    /// Every group, in prefix byte order.
+    pub const ALL: [PrefixGroup; 4] = [
+        PrefixGroup::CallRuntime,
+        PrefixGroup::Deprecated,
+        PrefixGroup::Wide,
+        PrefixGroup::Throw,
+    ];
+
+    /// The byte that introduces instructions of this group.
+    pub fn prefix_byte(self) -> u8 {
+        match self {
+            PrefixGroup::CallRuntime => 0xfb,
+            PrefixGroup::Deprecated => 0xfc,
+            PrefixGroup::Wide => 0xfd,
+            PrefixGroup::Throw => 0xfe,
+        }
+    }
+
+    /// The group introduced by `byte`, if it is a prefix.
+    pub fn from_prefix_byte(byte: u8) -> Option<PrefixGroup> {
+        PrefixGroup::ALL
+            .into_iter()
+            .find(|g| g.prefix_byte() == byte)
+    }
+
+    /// Prefix name as in `isa.yaml` (`"callruntime"`).
+    pub fn name(self) -> &'static str {
+        match self {
+            PrefixGroup::CallRuntime => "callruntime",
+            PrefixGroup::Deprecated => "deprecated",
+            PrefixGroup::Wide => "wide",
+            PrefixGroup::Throw => "throw",
+        }
+    }
+
+    /// Rows of [`opcode_table`] in this group, in opcode order.
+    pub fn opcodes(self) -> impl Iterator<Item = &'static OpcodeInfo> {
+        opcode_table()
+            .iter()
+            .filter(move |info| info.prefix_group() == Some(self))
+    }
+}
+
+impl core::fmt::Display for PrefixGroup {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Rows of [`opcode_table`] introduced by `prefix_byte`, in opcode order;
+/// nothing if the byte is not one of the four prefixes. The same rows as
+/// [`PrefixGroup::opcodes`], for callers holding a raw code byte.
+pub fn opcodes_with_prefix(prefix_byte: u8) -> impl Iterator<Item = &'static OpcodeInfo> {
+    PrefixGroup::from_prefix_byte(prefix_byte)
+        .into_iter()
+        .flat_map(PrefixGroup::opcodes)
+}
+
+/// Group of `opcode`. The prefix sits in the low byte, and no single-byte
+/// opcode uses a prefix value (the plain `throw` is `0x00fe`).
+fn group_of(opcode: u16) -> Option<PrefixGroup> {
+    PrefixGroup::from_prefix_byte(opcode as u8)
+}
+
+impl OpcodeInfo {
+    /// Prefix group of this encoding, `None` for single-byte opcodes.
+    pub fn prefix_group(&self) -> Option<PrefixGroup> {
+        group_of(self.opcode)
+    }
+}
+
+impl Bytecode {
+    /// Prefix group of this instruction, `None` if it is not prefixed.
+    /// Every encoding of a mnemonic shares its group.
+    pub fn prefix_group(&self) -> Option<PrefixGroup> {
+        group_of(self.representative_opcode())
+    }
+}
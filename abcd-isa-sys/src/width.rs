@@ -0,0 +1,57 @@
+//! Narrow and wide encodings of the same instruction.
+//!
+//! An instruction can have several encodings that differ only in operand
+//! width: formats of one mnemonic (`mov` as `v4_v4`, `v8_v8`, `v16_v16`),
+//! and a `wide.*` mnemonic next to the plain one (`ldlexvar` and
+//! `wide.ldlexvar`). Where the `wide.*` form has no IC slot, the plain
+//! form's leading slot operand is the only other difference.
+
+use crate::{OpcodeInfo, opcode_table};
+
+impl OpcodeInfo {
+    /// The smallest encoding of this instruction, if it is not this one:
+    /// the narrowest format of the mnemonic, or for a `wide.*` mnemonic
+    /// the narrowest format of the plain one.
+    ///
+    /// ```
+    /// use abcd_isa_sys::opcode_table;
+    ///
+    /// let wide = opcode_table().iter().find(|r| r.mnemonic == "wide.ldlexvar").unwrap();
+    /// assert_eq!(wide.narrow_equivalent().unwrap().format, "op_imm1_4_imm2_4");
+    /// ```
+    pub fn narrow_equivalent(&self) -> Option<&'static OpcodeInfo> {
+        let mnemonic = self.mnemonic.strip_prefix("wide.").unwrap_or(self.mnemonic);
+        rows(mnemonic)
+            .min_by_key(|row| row.size)
+            .filter(|row| row.opcode != self.opcode)
+    }
+
+    /// The largest encoding of this instruction, if it is not this one:
+    /// the widest format of the `wide.*` mnemonic if there is one, else of
+    /// this mnemonic.
+    pub fn wide_equivalent(&self) -> Option<&'static OpcodeInfo> {
+        let plain = self.mnemonic.strip_prefix("wide.").unwrap_or(self.mnemonic);
+        let mut wide = rows_of_wide(plain).peekable();
+        let widest = if wide.peek().is_some() {
+            wide.max_by_key(|row| row.size)
+        } else {
+            rows(self.mnemonic).max_by_key(|row| row.size)
+        };
+        widest.filter(|row| row.opcode != self.opcode)
+    }
+}
+
+fn rows(mnemonic: &str) -> impl Iterator<Item = &'static OpcodeInfo> + '_ {
+    opcode_table()
+        .iter()
+        .filter(move |row| row.mnemonic == mnemonic)
+}
+
+/// Rows of `wide.<mnemonic>`.
+fn rows_of_wide(mnemonic: &str) -> impl Iterator<Item = &'static OpcodeInfo> + '_ {
+    opcode_table().iter().filter(move |row| {
+        row.mnemonic
+            .strip_prefix("wide.")
+            .is_some_and(|m| m == mnemonic)
+    })
+}
@@ -0,0 +1,68 @@
+//! Formatting throughput: `to_string` per instruction against
+//! `write_formatted` into one reused buffer.
+//!
+//! Run with `cargo bench -p abcd-isa --bench format`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use abcd_isa::*;
+
+const INSTRUCTIONS: usize = 1_000_000;
+
+/// A mix of operand shapes, repeated to [`INSTRUCTIONS`].
+fn program() -> Vec<Bytecode> {
+    let mix = [
+        insn::Lda::new(Reg(3)),
+        insn::Sta::new(Reg(12)),
+        insn::Ldai::new(Imm(-42)),
+        insn::Mov::new(Reg(1), Reg(200)),
+        insn::LdaStr::new(EntityId(0x1234)),
+        insn::Stobjbyname::new(Imm(5), EntityId(0x88), Reg(7)),
+        insn::Callthis1::new(Imm(9), Reg(0), Reg(1)),
+        insn::Definefunc::new(Imm(0x100), EntityId(0x4000), Imm(2)),
+        insn::Returnundefined::new(),
+    ];
+    mix.iter().copied().cycle().take(INSTRUCTIONS).collect()
+}
+
+fn time(name: &str, f: impl Fn() -> usize) -> Duration {
+    // One untimed pass to warm caches and the allocator.
+    black_box(f());
+    let start = Instant::now();
+    let bytes = black_box(f());
+    let elapsed = start.elapsed();
+    println!(
+        "{name:>16}: {:>8.1} ns/insn ({bytes} bytes)",
+        elapsed.as_nanos() as f64 / INSTRUCTIONS as f64
+    );
+    elapsed
+}
+
+fn main() {
+    let program = program();
+
+    let allocating = time("to_string", || {
+        program
+            .iter()
+            .map(|bc| black_box(bc.to_string()).len())
+            .sum()
+    });
+    let reusing = time("write_formatted", || {
+        let mut line = String::new();
+        program
+            .iter()
+            .map(|bc| {
+                line.clear();
+                let _ = bc.write_formatted(&mut line);
+                black_box(&line).len()
+            })
+            .sum()
+    });
+
+    println!(
+        "{:>16}: {:.2}x",
+        "speedup",
+        allocating.as_secs_f64() / reusing.as_secs_f64()
+    );
+}
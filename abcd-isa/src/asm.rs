@@ -0,0 +1,431 @@
+//! Text assembler: instruction listings into an [`Emitter`].
+//!
+//! The syntax is the one instruction `Display` prints, so a disassembly
+//! reassembles as is, with a few additions for writing code by hand:
+//!
+//! ```text
+//! # Comments start with `#`, `;` or `//`.
+//! loop:                       // a label, bound to the next instruction
+//!     lda.str "hello"         // string placeholder
+//!     definefunc 0, @f, 1     // method placeholder
+//!     mov v1, v0              // commas between operands are optional
+//!     jnez loop
+//!     fldai 1.5               // float immediates as numbers or raw bits
+//!     returnundefined
+//! ```
+//!
+//! - Registers are `vN`; immediates are decimal or `0x` hex integers.
+//! - Jumps name a label defined as `name:` anywhere in the listing. A
+//!   `label_N` that is never defined means instruction `N`, which is how
+//!   `Display` prints the targets of decoded jumps.
+//! - IDs are `id:N`, or a placeholder: a `"quoted"` string or an `@name`,
+//!   which [`assemble_with`] hands to its resolver with the kind of entity
+//!   the operand names. A name with spaces, commas, parentheses or quotes
+//!   is written `@"quoted"`. Quoted text takes the escapes `\n`, `\t`,
+//!   `\r`, `\0`, `\"`, `\\` and `\u{..}`.
+//! - A parenthesized group after an operand is a comment, like the names a
+//!   display resolver prints after IDs. `#` starts a comment only where a
+//!   token could, so es2abc names such as `@#~@0>#foo` stay whole; this is
+//!   also what [`Bytecode::format_with`] prints.
+//!
+//! Errors carry the line and the [`Span`] of the offending text.
+//!
+//! ```
+//! use abcd_isa::asm;
+//!
+//! let program = asm::assemble("start:\n  ldtrue\n  jnez start\n  returnundefined\n")?;
+//! assert_eq!(program.emitter.len(), 3);
+//! assert!(asm::assemble("  frobnicate v0").is_err());
+//! # Ok::<(), abcd_isa::asm::AsmError>(())
+//! ```
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use abcd_isa_sys::fmt::{IdKind, Span};
+use abcd_isa_sys::operand::OperandKind;
+use abcd_isa_sys::{Bytecode, EntityId, Label};
+
+use crate::emitter::Emitter;
+use crate::lookup::lookup_mnemonic;
+
+/// A parse error, located in the source.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("line {line}: {kind}")]
+pub struct AsmError {
+    /// 1-based line number.
+    pub line: usize,
+    /// Byte range of the offending text within the whole source.
+    pub span: Span,
+    pub kind: AsmErrorKind,
+}
+
+/// What is wrong at an [`AsmError`].
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum AsmErrorKind {
+    #[error("unknown mnemonic `{0}`")]
+    UnknownMnemonic(String),
+    #[error("expected {expected} operands, found {found}")]
+    OperandCount { expected: usize, found: usize },
+    /// The operand is not the kind the instruction takes there.
+    #[error("expected {0}")]
+    Expected(&'static str),
+    #[error("label `{0}` is defined twice")]
+    DuplicateLabel(String),
+    #[error("label `{0}` is never defined")]
+    UndefinedLabel(String),
+    /// The resolver knows no entity for this placeholder.
+    #[error("cannot resolve `{0}`")]
+    UnresolvedId(String),
+    #[error("unterminated string")]
+    UnterminatedString,
+}
+
+/// The result of [`assemble`].
+#[derive(Clone, Debug)]
+pub struct Program {
+    /// The instructions, ready to [`build`](Emitter::build).
+    pub emitter: Emitter,
+    /// Every label of the listing, including `label_N` targets, for
+    /// [`Emitter::label_offset`].
+    pub labels: BTreeMap<String, Label>,
+}
+
+/// Assemble `source`, which must not contain ID placeholders.
+pub fn assemble(source: &str) -> Result<Program, AsmError> {
+    assemble_with(source, |_, _| None)
+}
+
+/// Assemble `source`, asking `resolve` for the entity each ID placeholder
+/// names. It gets the kind of the operand and the placeholder's text
+/// without quotes or `@`.
+pub fn assemble_with(
+    source: &str,
+    mut resolve: impl FnMut(IdKind, &str) -> Option<EntityId>,
+) -> Result<Program, AsmError> {
+    parse(source, &mut resolve).map_err(|(span, kind)| AsmError {
+        line: source[..span.start].matches('\n').count() + 1,
+        span,
+        kind,
+    })
+}
+
+type Failure = (Span, AsmErrorKind);
+
+/// One parsed instruction, jump target still by name.
+struct Pending {
+    opcode: u16,
+    args: Vec<i64>,
+    /// Operand index, label name and where the name is.
+    label: Option<(usize, String, Span)>,
+}
+
+fn parse(
+    source: &str,
+    resolve: &mut dyn FnMut(IdKind, &str) -> Option<EntityId>,
+) -> Result<Program, Failure> {
+    let mut pending: Vec<Pending> = Vec::new();
+    // Label name to the index of the instruction it is bound to.
+    let mut defined: BTreeMap<String, usize> = BTreeMap::new();
+
+    let mut base = 0;
+    for line in source.split('\n') {
+        let mut toks = tokens(line, base)?;
+        base += line.len() + 1;
+
+        let definition = match toks.first() {
+            Some((span, Tok::Word(word))) => word
+                .strip_suffix(':')
+                .filter(|name| !name.is_empty())
+                .map(|name| (*span, name)),
+            _ => None,
+        };
+        if let Some((span, name)) = definition {
+            if defined.insert(name.to_string(), pending.len()).is_some() {
+                return Err((span, AsmErrorKind::DuplicateLabel(name.to_string())));
+            }
+            toks.remove(0);
+        }
+        let Some((mnemonic_span, mnemonic)) = toks.first() else {
+            continue;
+        };
+        let Tok::Word(mnemonic) = mnemonic else {
+            return Err((*mnemonic_span, AsmErrorKind::Expected("a mnemonic")));
+        };
+        let row = lookup_mnemonic(mnemonic).ok_or_else(|| {
+            (
+                *mnemonic_span,
+                AsmErrorKind::UnknownMnemonic(mnemonic.to_string()),
+            )
+        })?;
+        let operands = &toks[1..];
+        if operands.len() != row.operands.len() {
+            let end = toks.last().map_or(mnemonic_span.end, |(s, _)| s.end);
+            return Err((
+                Span {
+                    start: mnemonic_span.start,
+                    end,
+                },
+                AsmErrorKind::OperandCount {
+                    expected: row.operands.len(),
+                    found: operands.len(),
+                },
+            ));
+        }
+
+        let jump = row.template.jump_label_arg_index();
+        let mut args = Vec::with_capacity(operands.len());
+        let mut label = None;
+        let mut id_index = 0;
+        for (i, ((span, tok), desc)) in operands.iter().zip(row.operands).enumerate() {
+            let expected = |what| (*span, AsmErrorKind::Expected(what));
+            let value = if jump == Some(i) {
+                let Tok::Word(name) = tok else {
+                    return Err(expected("a label"));
+                };
+                label = Some((i, name.to_string(), *span));
+                0
+            } else {
+                match desc.kind {
+                    OperandKind::Reg => word(tok)
+                        .and_then(|w| w.strip_prefix('v'))
+                        .and_then(|n| n.parse::<u16>().ok())
+                        .map(i64::from)
+                        .ok_or_else(|| expected("a register"))?,
+                    OperandKind::Imm if desc.float => word(tok)
+                        .and_then(|w| {
+                            parse_int(w)
+                                .or_else(|| w.parse::<f64>().ok().map(|f| f.to_bits() as i64))
+                        })
+                        .ok_or_else(|| expected("a number"))?,
+                    OperandKind::Imm => word(tok)
+                        .and_then(parse_int)
+                        .ok_or_else(|| expected("an integer"))?,
+                    OperandKind::Id => {
+                        let kind = row.id_kind(id_index);
+                        id_index += 1;
+                        id_operand(tok, kind, resolve).map_err(|kind| (*span, kind))?
+                    }
+                }
+            };
+            args.push(value);
+        }
+        pending.push(Pending {
+            opcode: row.opcode,
+            args,
+            label,
+        });
+    }
+
+    // Labels the listing never defines may be `Display`'s `label_N`.
+    for (_, name, span) in pending.iter().filter_map(|p| p.label.as_ref()) {
+        if defined.contains_key(name) {
+            continue;
+        }
+        let index = name
+            .strip_prefix("label_")
+            .and_then(|n| n.parse::<usize>().ok())
+            .filter(|&n| n <= pending.len())
+            .ok_or_else(|| (*span, AsmErrorKind::UndefinedLabel(name.clone())))?;
+        defined.insert(name.clone(), index);
+    }
+
+    let mut emitter = Emitter::new();
+    let mut labels = BTreeMap::new();
+    let mut bind_at: BTreeMap<usize, Vec<Label>> = BTreeMap::new();
+    for (name, &index) in &defined {
+        let label = emitter.create_label();
+        labels.insert(name.clone(), label);
+        bind_at.entry(index).or_default().push(label);
+    }
+    for (i, mut p) in pending.into_iter().enumerate() {
+        for &label in bind_at.get(&i).into_iter().flatten() {
+            emitter.bind(label);
+        }
+        if let Some((idx, name, _)) = &p.label {
+            p.args[*idx] = i64::from(labels[name].0);
+        }
+        let bc = Bytecode::from_args(p.opcode, &p.args)
+            .expect("operand count was checked against the opcode's row");
+        emitter.emit(bc);
+    }
+    for &label in bind_at.get(&emitter.len()).into_iter().flatten() {
+        emitter.bind(label);
+    }
+    Ok(Program { emitter, labels })
+}
+
+/// An ID operand: `id:N` or a placeholder for `resolve`.
+fn id_operand(
+    tok: &Tok<'_>,
+    kind: Option<IdKind>,
+    resolve: &mut dyn FnMut(IdKind, &str) -> Option<EntityId>,
+) -> Result<i64, AsmErrorKind> {
+    let placeholder = match tok {
+        Tok::Word(w) => {
+            if let Some(n) = w.strip_prefix("id:") {
+                return parse_int(n)
+                    .filter(|&v| u32::try_from(v).is_ok())
+                    .ok_or(AsmErrorKind::Expected("an entity ID"));
+            }
+            w.strip_prefix('@')
+                .ok_or(AsmErrorKind::Expected("an entity ID"))?
+        }
+        Tok::Str(s) | Tok::Named(s) => s.as_str(),
+    };
+    let kind = kind.ok_or(AsmErrorKind::Expected("`id:N` for an untyped ID"))?;
+    resolve(kind, placeholder)
+        .map(|id| i64::from(id.0))
+        .ok_or_else(|| AsmErrorKind::UnresolvedId(placeholder.to_string()))
+}
+
+/// A bare word, a quoted string or an `@"quoted"` name.
+enum Tok<'a> {
+    Word(&'a str),
+    Str(String),
+    Named(String),
+}
+
+fn word<'a>(tok: &Tok<'a>) -> Option<&'a str> {
+    match tok {
+        Tok::Word(w) => Some(w),
+        Tok::Str(_) | Tok::Named(_) => None,
+    }
+}
+
+/// Split one line, whose first byte is at `base` in the source, into
+/// tokens, dropping separators, comments and parenthesized groups.
+fn tokens(line: &str, base: usize) -> Result<Vec<(Span, Tok<'_>)>, Failure> {
+    let bytes = line.as_bytes();
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        match bytes[i] {
+            b' ' | b'\t' | b'\r' | b',' => i += 1,
+            b'#' | b';' => break,
+            b'/' if bytes.get(i + 1) == Some(&b'/') => break,
+            b'(' => {
+                let mut depth = 0;
+                while i < bytes.len() {
+                    match bytes[i] {
+                        b'(' => depth += 1,
+                        b')' => depth -= 1,
+                        b'"' => i = string_end(bytes, i).unwrap_or(bytes.len()) - 1,
+                        _ => {}
+                    }
+                    i += 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+            }
+            // A string, or an `@"quoted"` name.
+            b'"' | b'@' if bytes[i] == b'"' || bytes.get(i + 1) == Some(&b'"') => {
+                let quote = i + usize::from(bytes[i] == b'@');
+                let Some(end) = string_end(bytes, quote) else {
+                    let span = Span {
+                        start: base + start,
+                        end: base + bytes.len(),
+                    };
+                    return Err((span, AsmErrorKind::UnterminatedString));
+                };
+                i = end;
+                let span = Span {
+                    start: base + start,
+                    end: base + end,
+                };
+                let text = unescape(&line[quote + 1..end - 1]);
+                let tok = if quote == start {
+                    Tok::Str(text)
+                } else {
+                    Tok::Named(text)
+                };
+                out.push((span, tok));
+            }
+            _ => {
+                while i < bytes.len() && !b" \t\r,(\";".contains(&bytes[i]) {
+                    i += 1;
+                }
+                let span = Span {
+                    start: base + start,
+                    end: base + i,
+                };
+                out.push((span, Tok::Word(&line[start..i])));
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// One past the closing quote of the string opening at `start`.
+fn string_end(bytes: &[u8], start: usize) -> Option<usize> {
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'"' => return Some(i + 1),
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// The text of a string literal: `\n`, `\t`, `\r`, `\0`, `\"`, `\\` and
+/// `\u{..}` escapes, as [`Bytecode::format_with`] writes them.
+fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some('0') => out.push('\0'),
+            Some('u') => {
+                let rest = chars.as_str();
+                let code = rest
+                    .strip_prefix('{')
+                    .and_then(|r| r.split_once('}'))
+                    .and_then(|(hex, _)| u32::from_str_radix(hex, 16).ok())
+                    .and_then(char::from_u32);
+                match code {
+                    Some(c) => {
+                        out.push(c);
+                        let close = rest.find('}').unwrap_or(0);
+                        chars = rest[close + 1..].chars();
+                    }
+                    None => out.push('u'),
+                }
+            }
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// A decimal or `0x` hex integer, optionally negative.
+fn parse_int(s: &str) -> Option<i64> {
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s),
+    };
+    let value = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) => u64::from_str_radix(hex, 16).ok()? as i64,
+        None => digits.parse::<i64>().ok()?,
+    };
+    Some(if negative {
+        value.wrapping_neg()
+    } else {
+        value
+    })
+}
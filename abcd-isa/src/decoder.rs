@@ -1,6 +1,8 @@
-use abcd_isa_sys::{Bytecode, Label};
+use alloc::vec::Vec;
 
-/// Errors from [`decode`].
+use abcd_isa_sys::{Bytecode, Label, OpcodeInfo, opcode_table};
+
+/// Errors from [`decode`] and [`decode_pure`].
 #[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
 pub enum DecodeError {
     /// Invalid or unknown opcode at the given byte offset.
@@ -28,25 +30,9 @@ pub enum DecodeError {
 /// the input slice. Instruction sizes can be derived from consecutive offsets
 /// (or `bytes.len() - offset` for the last instruction).
 pub fn decode(bytes: &[u8]) -> Result<Vec<(Bytecode, u32)>, DecodeError> {
-    let mut instructions: Vec<Bytecode> = Vec::new();
-    let mut byte_offsets: Vec<usize> = Vec::new();
-    // (insn_index, insn_byte_offset, raw_jump_offset)
-    let mut jumps: Vec<(usize, usize, i64)> = Vec::new();
-    let mut offset: usize = 0;
-
-    // Pass 1: decode instructions, record byte offsets.
     // SAFETY: pure query, no preconditions.
     let prefix_min = unsafe { abcd_isa_sys::isa_min_prefix_opcode() };
-    while offset < bytes.len() {
-        // Prefixed opcodes occupy 2 bytes; ensure we don't read past the end.
-        if bytes[offset] >= prefix_min && offset + 1 >= bytes.len() {
-            return Err(DecodeError::Truncated(offset));
-        }
-        let ptr = bytes[offset..].as_ptr();
-        // SAFETY: ptr points into `bytes[offset..]`; at least 1 byte is
-        // readable (loop condition), and 2 bytes for prefixed opcodes
-        // (checked above).
-        let opcode = unsafe { abcd_isa_sys::isa_get_opcode(ptr) };
+    decode_with(bytes, prefix_min, |opcode, offset| {
         // SAFETY: pure query, no preconditions.
         let size = unsafe { abcd_isa_sys::isa_get_size_by_opcode(opcode) };
         if size == 0 {
@@ -57,9 +43,176 @@ pub fn decode(bytes: &[u8]) -> Result<Vec<(Bytecode, u32)>, DecodeError> {
         }
 
         // SAFETY: ptr has at least `size` readable bytes (checked above);
-        // opcode was obtained from `isa_get_opcode(ptr)`.
+        // opcode was read from the same bytes.
+        let ptr = bytes[offset..].as_ptr();
         let (bc, jump_offset) = unsafe { Bytecode::decode_one(ptr, opcode) }
             .ok_or(DecodeError::InvalidOpcode(offset))?;
+        Ok((bc, size, jump_offset))
+    })
+}
+
+/// [`decode`] without the C bridge.
+///
+/// Opcodes are looked up in [`opcode_table`] and operands read with
+/// [`OperandDesc::value`](abcd_isa_sys::operand::OperandDesc::value), so no
+/// call leaves Rust. Results and errors are the same as [`decode`]'s; this
+/// is the path for callers that must not touch the vendored C++ at run
+/// time, and a step towards building without it.
+pub fn decode_pure(bytes: &[u8]) -> Result<Vec<(Bytecode, u32)>, DecodeError> {
+    decode_rows(bytes, |_| true)
+}
+
+/// [`decode_pure`], treating rows `accept` rejects as invalid opcodes.
+pub(crate) fn decode_rows(
+    bytes: &[u8],
+    accept: impl Fn(&OpcodeInfo) -> bool,
+) -> Result<Vec<(Bytecode, u32)>, DecodeError> {
+    let table = opcode_table();
+    let prefix_min = table_prefix_min(table);
+    decode_with(bytes, prefix_min, |opcode, offset| {
+        let row = table_row(table, opcode)
+            .filter(|row| accept(row))
+            .ok_or(DecodeError::InvalidOpcode(offset))?;
+        let operands = read_operands(row, bytes, offset)?;
+        let (bc, jump_offset) = from_operands(row, &operands, offset)?;
+        Ok((bc, usize::from(row.size), jump_offset))
+    })
+}
+
+/// One instruction from [`decode_all`].
+#[derive(Clone, Copy, Debug)]
+pub struct DecodedInst {
+    /// The instruction, with jump targets resolved as [`decode`] does.
+    pub bc: Bytecode,
+    /// Byte offset within the decoded slice.
+    pub offset: u32,
+    /// Encoded size in bytes, opcode included.
+    pub size: u8,
+    /// The encoding the bytes use.
+    pub info: &'static OpcodeInfo,
+    operands: [i64; Bytecode::MAX_OPERANDS],
+}
+
+impl DecodedInst {
+    /// Every operand as encoded, in signature order: registers and IDs
+    /// as numbers, signed immediates sign-extended, float immediates as
+    /// their bits, jumps as the raw offset relative to this instruction.
+    /// The same values [`OperandDesc::value`] reads from the bytes, read
+    /// once during decoding.
+    ///
+    /// [`OperandDesc::value`]: abcd_isa_sys::operand::OperandDesc::value
+    pub fn operands(&self) -> &[i64] {
+        &self.operands[..self.info.operands.len()]
+    }
+
+    /// The instruction after this one, as a byte offset.
+    pub fn next_offset(&self) -> u32 {
+        self.offset + u32::from(self.size)
+    }
+}
+
+/// Decode a whole code region in one pass, keeping for each instruction
+/// its encoding row, size and operands next to the [`Bytecode`].
+///
+/// Decoding goes through [`opcode_table`] as [`decode_pure`] does, so no
+/// call crosses into C++, and callers that need sizes, formats or raw
+/// operands get them without asking the bridge once per instruction or
+/// operand. Results and errors match [`decode`]'s.
+///
+/// ```
+/// // mov v1, v2; jmp +(-2)
+/// let insts = abcd_isa::decode_all(&[0x44, 0x21, 0x4d, 0xfe])?;
+/// assert_eq!(insts[0].operands(), [1, 2]);
+/// assert_eq!(insts[1].info.format, "op_imm_8");
+/// assert_eq!(insts[1].operands(), [-2]);
+/// assert_eq!(insts[1].bc, abcd_isa::insn::Jmp::new(abcd_isa::Label(0)));
+/// # Ok::<(), abcd_isa::DecodeError>(())
+/// ```
+pub fn decode_all(bytes: &[u8]) -> Result<Vec<DecodedInst>, DecodeError> {
+    let table = opcode_table();
+    let prefix_min = table_prefix_min(table);
+    let mut rows = Vec::new();
+    let decoded = decode_with(bytes, prefix_min, |opcode, offset| {
+        let row = table_row(table, opcode).ok_or(DecodeError::InvalidOpcode(offset))?;
+        let operands = read_operands(row, bytes, offset)?;
+        let (bc, jump_offset) = from_operands(row, &operands, offset)?;
+        rows.push((row, operands));
+        Ok((bc, usize::from(row.size), jump_offset))
+    })?;
+    Ok(decoded
+        .into_iter()
+        .zip(rows)
+        .map(|((bc, offset), (info, operands))| DecodedInst {
+            bc,
+            offset,
+            size: info.size,
+            info,
+            operands,
+        })
+        .collect())
+}
+
+/// Operands of the `row` instruction at `offset`, in signature order.
+fn read_operands(
+    row: &OpcodeInfo,
+    bytes: &[u8],
+    offset: usize,
+) -> Result<[i64; Bytecode::MAX_OPERANDS], DecodeError> {
+    let size = usize::from(row.size);
+    if offset + size > bytes.len() {
+        return Err(DecodeError::Truncated(offset));
+    }
+    let insn = &bytes[offset..offset + size];
+    let mut operands = [0; Bytecode::MAX_OPERANDS];
+    for (value, desc) in operands.iter_mut().zip(row.operands) {
+        *value = desc.value(insn);
+    }
+    Ok(operands)
+}
+
+/// The instruction `row` encodes with `operands`, and its raw jump offset
+/// if it is a jump.
+fn from_operands(
+    row: &OpcodeInfo,
+    operands: &[i64; Bytecode::MAX_OPERANDS],
+    offset: usize,
+) -> Result<(Bytecode, Option<i64>), DecodeError> {
+    let mut args = *operands;
+    // The label is resolved in the second pass; until then it is 0, as
+    // `decode_one` leaves it.
+    let jump_offset = row.template.jump_label_arg_index().map(|i| {
+        let raw = args[i];
+        args[i] = 0;
+        raw
+    });
+    let n = row.operands.len();
+    let bc =
+        Bytecode::from_args(row.opcode, &args[..n]).ok_or(DecodeError::InvalidOpcode(offset))?;
+    Ok((bc, jump_offset))
+}
+
+/// Both passes of decoding. `decode_one(opcode, offset)` decodes the
+/// instruction at `offset` into the instruction, its size and, for jumps,
+/// the raw relative offset.
+fn decode_with(
+    bytes: &[u8],
+    prefix_min: u8,
+    mut decode_one: impl FnMut(u16, usize) -> Result<(Bytecode, usize, Option<i64>), DecodeError>,
+) -> Result<Vec<(Bytecode, u32)>, DecodeError> {
+    let mut instructions: Vec<Bytecode> = Vec::new();
+    let mut byte_offsets: Vec<usize> = Vec::new();
+    // (insn_index, insn_byte_offset, raw_jump_offset)
+    let mut jumps: Vec<(usize, usize, i64)> = Vec::new();
+    let mut offset: usize = 0;
+
+    // Pass 1: decode instructions, record byte offsets.
+    while offset < bytes.len() {
+        // Prefixed opcodes occupy 2 bytes; ensure we don't read past the end.
+        if bytes[offset] >= prefix_min && offset + 1 >= bytes.len() {
+            return Err(DecodeError::Truncated(offset));
+        }
+        let opcode = read_opcode(bytes, offset, prefix_min);
+        let (bc, size, jump_offset) = decode_one(opcode, offset)?;
 
         if let Some(raw_imm) = jump_offset {
             jumps.push((instructions.len(), offset, raw_imm));
@@ -98,3 +251,51 @@ pub fn decode(bytes: &[u8]) -> Result<Vec<(Bytecode, u32)>, DecodeError> {
         .zip(byte_offsets.iter().map(|&o| o as u32))
         .collect())
 }
+
+/// The smallest prefix byte in `table`; prefixed rows carry their prefix
+/// in the low byte.
+fn table_prefix_min(table: &[OpcodeInfo]) -> u8 {
+    table
+        .iter()
+        .filter(|row| row.opcode > 0xff)
+        .map(|row| row.opcode as u8)
+        .min()
+        .unwrap_or(u8::MAX)
+}
+
+fn table_row(table: &'static [OpcodeInfo], opcode: u16) -> Option<&'static OpcodeInfo> {
+    table
+        .binary_search_by_key(&opcode, |row| row.opcode)
+        .ok()
+        .map(|i| &table[i])
+}
+
+/// The [`opcode_table`] row of the instruction at the start of `bytes`,
+/// checking that all of it is there.
+pub(crate) fn row_at(bytes: &[u8]) -> Result<&'static OpcodeInfo, DecodeError> {
+    let table = opcode_table();
+    let prefix_min = table_prefix_min(table);
+    match bytes.first() {
+        None => return Err(DecodeError::Truncated(0)),
+        Some(&b) if b >= prefix_min && bytes.len() < 2 => return Err(DecodeError::Truncated(0)),
+        Some(_) => {}
+    }
+    let row =
+        table_row(table, read_opcode(bytes, 0, prefix_min)).ok_or(DecodeError::InvalidOpcode(0))?;
+    if bytes.len() < usize::from(row.size) {
+        return Err(DecodeError::Truncated(0));
+    }
+    Ok(row)
+}
+
+/// The opcode at `bytes[offset]`. A prefixed opcode is the prefix byte
+/// followed by the sub-opcode, read as one little-endian `u16` whatever the
+/// host byte order; the caller has checked that both bytes are there.
+fn read_opcode(bytes: &[u8], offset: usize, prefix_min: u8) -> u16 {
+    let primary = bytes[offset];
+    if primary >= prefix_min {
+        u16::from_le_bytes([primary, bytes[offset + 1]])
+    } else {
+        u16::from(primary)
+    }
+}
@@ -1,7 +1,15 @@
-use std::collections::HashMap;
-use std::ptr;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::OnceCell;
+use core::ptr;
 
-use abcd_isa_sys::Bytecode;
+use abcd_isa_sys::operand::OperandKind;
+use abcd_isa_sys::{Bytecode, BytecodeFlag, Label, Reg, insn, opcode_table};
+
+use crate::decoder::{DecodeError, decode};
+use crate::inst_buf::InstBuf;
 
 // C bridge error codes (from isa_bridge.h).
 const ISA_EMIT_UNKNOWN_OPCODE: i32 = -3;
@@ -9,9 +17,9 @@ const ISA_EMIT_UNKNOWN_OPCODE: i32 = -3;
 /// Errors from [`encode`].
 #[derive(Debug, thiserror::Error)]
 pub enum EncodeError {
-    /// The underlying C++ emitter returned a non-specific error.
-    #[error("internal emitter error")]
-    Internal,
+    /// The underlying C++ emitter failed; carries the bridge's reason.
+    #[error("internal emitter error: {0}")]
+    Internal(String),
     /// The opcode is not recognized by the emitter.
     #[error("emit failed: unknown opcode")]
     UnknownOpcode,
@@ -23,6 +31,22 @@ pub enum EncodeError {
     /// `u32` index space.
     #[error("instruction count {0} exceeds Label index capacity")]
     TooManyInstructions(usize),
+    /// An [`Emitter`] jump targets label `{0}`, which was never bound.
+    #[error("label {0} is never bound")]
+    UnboundLabel(u32),
+    /// Operand `operand` of instruction `index` holds `value`, which does
+    /// not fit the `bits`-bit field of even the widest format (`wide.*`
+    /// included) of `mnemonic`. Encoding it would truncate the value.
+    #[error(
+        "instruction {index} ({mnemonic}): operand {operand} is {value}, which does not fit in {bits} bits"
+    )]
+    OperandOutOfRange {
+        index: usize,
+        mnemonic: &'static str,
+        operand: usize,
+        value: i64,
+        bits: u8,
+    },
 }
 
 /// Encode a sequence of instructions into bytecode bytes.
@@ -34,6 +58,13 @@ pub enum EncodeError {
 /// instruction `i` within `bytes`. This is needed for try-block metadata
 /// which references instructions by byte offset.
 ///
+/// Formats are picked per instruction from the operand values: `mov`
+/// widens from v4 to v8/v16 registers, and instructions whose immediates
+/// overflow every narrow format are emitted in their `wide.*` form (see
+/// [`Bytecode::to_wide`]). Offsets still line up one-to-one with the input.
+/// A register or immediate that no format holds is an
+/// [`EncodeError::OperandOutOfRange`] rather than a truncated field.
+///
 /// ```no_run
 /// use abcd_isa::{encode, insn, Label, Bytecode};
 ///
@@ -51,7 +82,7 @@ pub fn encode(instructions: &[Bytecode]) -> Result<(Vec<u8>, Vec<u32>), EncodeEr
     }
 
     // 1. Collect jump targets and validate label bounds.
-    let mut targets: HashMap<u32, u32> = HashMap::new(); // insn_index → cpp_label_id (filled in step 2)
+    let mut targets: BTreeMap<u32, u32> = BTreeMap::new(); // insn_index → cpp_label_id (filled in step 2)
     for bc in instructions {
         if let Some(idx) = bc.jump_label_arg_index() {
             let (_, args, _) = bc.emit_args();
@@ -67,7 +98,7 @@ pub fn encode(instructions: &[Bytecode]) -> Result<(Vec<u8>, Vec<u32>), EncodeEr
     // SAFETY: no preconditions; returns null on allocation failure (checked below).
     let raw = unsafe { abcd_isa_sys::isa_emitter_create() };
     if raw.is_null() {
-        return Err(EncodeError::Internal);
+        return Err(EncodeError::Internal(last_error()));
     }
 
     // Ensure cleanup on all exit paths.
@@ -93,6 +124,20 @@ pub fn encode(instructions: &[Bytecode]) -> Result<(Vec<u8>, Vec<u32>), EncodeEr
             debug_assert_eq!(rc, 0, "isa_emitter_bind failed for label {cpp_id}");
         }
 
+        // Switch to the `wide.*` form when an immediate overflows the narrow one.
+        let bc = match bc.to_wide() {
+            Some(wide) if !bc.fits_encoding() => wide,
+            _ => *bc,
+        };
+        if let Some((operand, value, bits)) = overflowing_operand(&bc) {
+            return Err(EncodeError::OperandOutOfRange {
+                index: i,
+                mnemonic: bc.mnemonic(),
+                operand,
+                value,
+                bits,
+            });
+        }
         let (opcode, mut args, num_args) = bc.emit_args();
 
         // Replace instruction index with C++ label ID for jump operands.
@@ -107,7 +152,7 @@ pub fn encode(instructions: &[Bytecode]) -> Result<(Vec<u8>, Vec<u32>), EncodeEr
         match rc {
             0 => {}
             ISA_EMIT_UNKNOWN_OPCODE => return Err(EncodeError::UnknownOpcode),
-            _ => return Err(EncodeError::Internal),
+            _ => return Err(EncodeError::Internal(last_error())),
         }
     }
 
@@ -120,7 +165,7 @@ pub fn encode(instructions: &[Bytecode]) -> Result<(Vec<u8>, Vec<u32>), EncodeEr
         0 if !buf.is_null() => {
             // SAFETY: buf is non-null (match guard) and points to `len` bytes
             // allocated by isa_emitter_build.
-            let vec = unsafe { std::slice::from_raw_parts(buf, len) }.to_vec();
+            let vec = unsafe { core::slice::from_raw_parts(buf, len) }.to_vec();
             // SAFETY: buf was allocated by isa_emitter_build.
             unsafe { abcd_isa_sys::isa_emitter_free_buf(buf) };
 
@@ -142,6 +187,479 @@ pub fn encode(instructions: &[Bytecode]) -> Result<(Vec<u8>, Vec<u32>), EncodeEr
 
             Ok((vec, offsets))
         }
-        _ => Err(EncodeError::Internal),
+        _ => Err(EncodeError::Internal(last_error())),
+    }
+}
+
+/// Incremental assembler on top of [`encode`].
+///
+/// Jumps name labels from [`create_label`](Self::create_label) rather than
+/// instruction indices, so code can jump forward before the target exists.
+/// Instructions stay [`Bytecode`] values until [`build`](Self::build): an
+/// emitter is cheap to clone, and a code generator that tries one lowering
+/// can take a [`snapshot`](Self::snapshot) first and
+/// [`restore`](Self::restore) it to back the lowering out.
+///
+/// ```no_run
+/// use abcd_isa::{Emitter, Imm, insn};
+///
+/// let mut e = Emitter::new();
+/// let done = e.create_label();
+/// e.emit(insn::Ldtrue::new());
+/// e.emit(insn::Jnez::new(done));
+/// let before = e.snapshot();
+/// e.emit(insn::Ldai::new(Imm(1 << 40)));
+/// // Changed our mind: roll back and load something else.
+/// e.restore(before);
+/// e.emit(insn::Ldundefined::new());
+/// e.bind(done);
+/// e.emit(insn::Returnundefined::new());
+/// let (bytes, offsets) = e.build()?;
+/// # Ok::<(), abcd_isa::EncodeError>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Emitter {
+    insns: Vec<Bytecode>,
+    /// Instruction index each label is bound to.
+    labels: Vec<Option<u32>>,
+    /// Labels in the order they were bound, so `restore` can unbind them.
+    bound: Vec<u32>,
+    /// [`layout`](Self::layout), until the code or a binding changes.
+    layout: OnceCell<Vec<u32>>,
+}
+
+/// A state of an [`Emitter`] to [`restore`](Emitter::restore) later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint {
+    insns: usize,
+    labels: usize,
+    bound: usize,
+}
+
+impl Emitter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A new, unbound label for jump operands.
+    pub fn create_label(&mut self) -> Label {
+        self.labels.push(None);
+        Label(self.labels.len() as u32 - 1)
+    }
+
+    /// Bind `label` to the next instruction emitted.
+    ///
+    /// # Panics
+    ///
+    /// If `label` does not come from this emitter or is already bound.
+    pub fn bind(&mut self, label: Label) {
+        let slot = self
+            .labels
+            .get_mut(label.0 as usize)
+            .unwrap_or_else(|| panic!("label {} was not created by this emitter", label.0));
+        assert!(slot.is_none(), "label {} is already bound", label.0);
+        *slot = Some(self.insns.len() as u32);
+        self.bound.push(label.0);
+        self.layout.take();
+    }
+
+    /// Append `insn`. Jump operands are labels of this emitter.
+    pub fn emit(&mut self, insn: Bytecode) {
+        self.insns.push(insn);
+        self.layout.take();
+    }
+
+    /// Append `insn` in the form [`build`](Self::build) will encode it:
+    /// its `wide.*` form when an immediate overflows every narrow format,
+    /// as it is otherwise. Returns the instruction recorded.
+    ///
+    /// `build` widens such instructions anyway; widening at emit time keeps
+    /// [`instructions`](Self::instructions) in step with the output, which
+    /// matters for [`ic_slot_count`](crate::ic_slot_count) because wide
+    /// forms of IC instructions have no slot operand.
+    pub fn emit_auto(&mut self, insn: Bytecode) -> Bytecode {
+        let insn = match insn.to_wide() {
+            Some(wide) if !insn.fits_encoding() => wide,
+            _ => insn,
+        };
+        self.emit(insn);
+        insn
+    }
+
+    /// Append `mov vd, vs` in the narrowest of the v4, v8 and v16 formats
+    /// that holds both registers.
+    pub fn mov_auto(&mut self, vd: Reg, vs: Reg) {
+        self.emit(insn::Mov::new(vd, vs));
+    }
+
+    /// Append a `jmp` to `label` with the narrowest of the imm8, imm16 and
+    /// imm32 offsets that reaches it, decided once the code is laid out.
+    pub fn jmp_auto(&mut self, label: Label) {
+        self.emit(insn::Jmp::new(label));
+    }
+
+    /// Append the instructions encoded in `bytes`, for copying code a
+    /// transformation leaves alone. Returns how many were appended.
+    ///
+    /// `bytes` must hold whole instructions, and its jumps must land on
+    /// one of them; they are rewritten to fresh labels bound there, so the
+    /// copy can move. Jumps that leave `bytes` have no label to take and
+    /// are rejected: emit those with [`emit`](Self::emit) and a label of
+    /// this emitter. Nothing is appended on error.
+    ///
+    /// The copy goes through [`build`](Self::build) like any other code,
+    /// so an instruction that was encoded wider than it needs to be comes
+    /// out in its narrowest format.
+    pub fn emit_raw(&mut self, bytes: &[u8]) -> Result<usize, DecodeError> {
+        let decoded = decode(bytes)?;
+        let mut labels: BTreeMap<u32, Label> = BTreeMap::new();
+        for (bc, _) in &decoded {
+            if let Some(idx) = bc.jump_label_arg_index() {
+                let (_, args, _) = bc.emit_args();
+                labels.entry(args[idx] as u32).or_insert(Label(0));
+            }
+        }
+        for label in labels.values_mut() {
+            *label = self.create_label();
+        }
+        for (i, (mut bc, _)) in decoded.iter().copied().enumerate() {
+            if let Some(&label) = labels.get(&(i as u32)) {
+                self.bind(label);
+            }
+            if let Some(idx) = bc.jump_label_arg_index() {
+                let (_, args, _) = bc.emit_args();
+                bc.set_label(labels[&(args[idx] as u32)]);
+            }
+            self.emit(bc);
+        }
+        Ok(decoded.len())
+    }
+
+    /// Append the instruction `inst` holds; see [`emit_raw`](Self::emit_raw).
+    /// Returns the instruction recorded.
+    pub fn emit_inst(&mut self, inst: &InstBuf) -> Result<Bytecode, DecodeError> {
+        self.emit_raw(inst.as_bytes())?;
+        Ok(self.insns[self.insns.len() - 1])
+    }
+
+    /// Instructions emitted so far, jumps still naming labels.
+    pub fn instructions(&self) -> &[Bytecode] {
+        &self.insns
+    }
+
+    /// Number of instructions emitted so far; the index the next one gets.
+    pub fn len(&self) -> usize {
+        self.insns.len()
+    }
+
+    /// Whether nothing has been emitted yet.
+    pub fn is_empty(&self) -> bool {
+        self.insns.is_empty()
+    }
+
+    /// Number of instructions emitted so far, as [`len`](Self::len).
+    pub fn instruction_count(&self) -> usize {
+        self.len()
+    }
+
+    /// Byte offset the next instruction will be built at, for try-block
+    /// starts and lengths.
+    ///
+    /// Offsets are final once every jump emitted so far targets a bound
+    /// label. A jump to a label bound later is laid out as if it jumped to
+    /// the end of the code, and widens if the code it skips outgrows its
+    /// offset; everything after the jump moves with it. The first call
+    /// after a change encodes the code emitted so far, with the same
+    /// format choices as [`build`](Self::build), and fails where it would;
+    /// calls in between reuse that layout.
+    pub fn pc(&self) -> Result<usize, EncodeError> {
+        Ok(self.layout()?[self.insns.len()] as usize)
+    }
+
+    /// Byte offset of the instruction `label` is bound to, or `None` while
+    /// it is unbound. Final under the same conditions as [`pc`](Self::pc).
+    pub fn label_offset(&self, label: Label) -> Result<Option<usize>, EncodeError> {
+        let Some(index) = self.labels.get(label.0 as usize).copied().flatten() else {
+            return Ok(None);
+        };
+        Ok(Some(self.layout()?[index as usize] as usize))
+    }
+
+    /// The current state, for [`restore`](Self::restore).
+    pub fn snapshot(&self) -> Checkpoint {
+        Checkpoint {
+            insns: self.insns.len(),
+            labels: self.labels.len(),
+            bound: self.bound.len(),
+        }
+    }
+
+    /// Go back to `checkpoint`: instructions emitted and labels created
+    /// since are dropped, and labels bound since are unbound again.
+    ///
+    /// # Panics
+    ///
+    /// If the emitter was already restored to a point before `checkpoint`.
+    pub fn restore(&mut self, checkpoint: Checkpoint) {
+        assert!(
+            checkpoint.insns <= self.insns.len()
+                && checkpoint.labels <= self.labels.len()
+                && checkpoint.bound <= self.bound.len(),
+            "checkpoint is newer than the emitter"
+        );
+        for label in self.bound.drain(checkpoint.bound..) {
+            if let Some(slot) = self.labels.get_mut(label as usize) {
+                *slot = None;
+            }
+        }
+        self.labels.truncate(checkpoint.labels);
+        self.insns.truncate(checkpoint.insns);
+        self.layout.take();
+    }
+
+    /// Assemble everything emitted so far; see [`encode`] for the result.
+    /// Operands are range-checked here rather than in [`emit`](Self::emit),
+    /// so an [`EncodeError::OperandOutOfRange`] names the instruction by
+    /// its index in [`instructions`](Self::instructions).
+    pub fn build(&self) -> Result<(Vec<u8>, Vec<u32>), EncodeError> {
+        encode(&self.resolved()?)
+    }
+
+    /// [`build`](Self::build) after a peephole pass over the code, for
+    /// output of decompile-recompile pipelines, which is full of moves and
+    /// jumps that do nothing:
+    ///
+    /// - jumps to a `jmp` go straight to where that one ends up;
+    /// - code no jump reaches after a `jmp`, return or throw is dropped;
+    /// - the `sta v` of `lda v; sta v` and the `lda v` of `sta v; lda v`
+    ///   are dropped.
+    ///
+    /// Bound labels are kept as entry points, whether or not a jump names
+    /// them, since exception handlers are only reached through the try
+    /// blocks. Offsets line up with [`instructions`](Self::instructions)
+    /// rather than with the output: a dropped instruction gets the offset
+    /// of the next one kept, or the length of the code. [`pc`](Self::pc)
+    /// and [`label_offset`](Self::label_offset) describe `build`, not this.
+    pub fn build_optimized(&self) -> Result<(Vec<u8>, Vec<u32>), EncodeError> {
+        let mut program = self.resolved()?;
+        let entries: BTreeSet<usize> = self.labels.iter().flatten().map(|&i| i as usize).collect();
+        let keep = peephole(&mut program, &entries);
+
+        // Instruction index each original instruction maps to after the drop.
+        let mut index = Vec::with_capacity(program.len() + 1);
+        let mut kept = 0u32;
+        for &k in &keep {
+            index.push(kept);
+            kept += u32::from(k);
+        }
+        index.push(kept);
+        let mut optimized = Vec::with_capacity(kept as usize);
+        for (mut bc, _) in program.into_iter().zip(&keep).filter(|(_, k)| **k) {
+            if let Some(target) = jump_target(&bc) {
+                bc.set_label(Label(index[target]));
+            }
+            optimized.push(bc);
+        }
+
+        let (bytes, offsets) = encode(&optimized)?;
+        let offsets = index[..keep.len()]
+            .iter()
+            .map(|&i| {
+                offsets
+                    .get(i as usize)
+                    .copied()
+                    .unwrap_or(bytes.len() as u32)
+            })
+            .collect();
+        Ok((bytes, offsets))
+    }
+
+    /// The code with every jump naming its target instruction index, as
+    /// [`encode`] takes it.
+    fn resolved(&self) -> Result<Vec<Bytecode>, EncodeError> {
+        let mut program = self.insns.clone();
+        for bc in &mut program {
+            let Some(idx) = bc.jump_label_arg_index() else {
+                continue;
+            };
+            let (_, args, _) = bc.emit_args();
+            let label = args[idx] as u32;
+            let target = self
+                .labels
+                .get(label as usize)
+                .copied()
+                .flatten()
+                .ok_or(EncodeError::UnboundLabel(label))?;
+            bc.set_label(Label(target));
+        }
+        Ok(program)
+    }
+
+    /// Byte offset of every instruction, and of the end, as [`encode`]
+    /// lays them out, jumps to unbound labels going to the end of the code.
+    /// The code is encoded with a placeholder after it, so that the end is
+    /// an instruction too; the result is kept until the code or a label
+    /// binding changes.
+    fn layout(&self) -> Result<&[u32], EncodeError> {
+        if let Some(layout) = self.layout.get() {
+            return Ok(layout);
+        }
+        let end = self.insns.len() as u32;
+        let mut program = self.insns.clone();
+        for bc in &mut program {
+            let Some(idx) = bc.jump_label_arg_index() else {
+                continue;
+            };
+            let (_, args, _) = bc.emit_args();
+            let target = self
+                .labels
+                .get(args[idx] as usize)
+                .copied()
+                .flatten()
+                .unwrap_or(end);
+            bc.set_label(Label(target));
+        }
+        program.push(insn::Returnundefined::new().into());
+        let (_, offsets) = encode(&program)?;
+        Ok(self.layout.get_or_init(|| offsets))
+    }
+}
+
+/// Thread jumps through `jmp`s in `program`, whose jumps name instruction
+/// indices, and pick the instructions to keep. `entries` are reached from
+/// outside the code, as is instruction 0.
+fn peephole(program: &mut [Bytecode], entries: &BTreeSet<usize>) -> Vec<bool> {
+    for i in 0..program.len() {
+        let Some(mut target) = jump_target(&program[i]) else {
+            continue;
+        };
+        // A chain longer than the code is a loop of `jmp`s; leave it.
+        for _ in 0..program.len() {
+            match program.get(target) {
+                Some(Bytecode::Jmp(Label(next))) if *next as usize != target => {
+                    target = *next as usize;
+                }
+                _ => break,
+            }
+        }
+        program[i].set_label(Label(target as u32));
+    }
+
+    // Drop code after a jump, return or throw up to the next instruction
+    // something reaches, until dropping code leaves no more jumps out.
+    let mut keep = vec![true; program.len()];
+    loop {
+        let mut targets = entries.clone();
+        targets.insert(0);
+        targets.extend(
+            program
+                .iter()
+                .zip(&keep)
+                .filter(|(_, k)| **k)
+                .filter_map(|(bc, _)| jump_target(bc)),
+        );
+        let mut live = false;
+        let mut next = Vec::with_capacity(program.len());
+        for (i, bc) in program.iter().enumerate() {
+            live |= targets.contains(&i);
+            next.push(live);
+            if live && !falls_through(bc) {
+                live = false;
+            }
+        }
+        if next == keep {
+            break;
+        }
+        keep = next;
+    }
+
+    // Drop an accumulator move that undoes the one before it.
+    let targets: BTreeSet<usize> = program
+        .iter()
+        .zip(&keep)
+        .filter(|(_, k)| **k)
+        .filter_map(|(bc, _)| jump_target(bc))
+        .chain(entries.iter().copied())
+        .collect();
+    let mut prev: Option<Bytecode> = None;
+    for (i, bc) in program.iter().enumerate() {
+        if !keep[i] {
+            continue;
+        }
+        let redundant = match (prev, bc) {
+            (Some(Bytecode::Lda(a)), Bytecode::Sta(b))
+            | (Some(Bytecode::Sta(a)), Bytecode::Lda(b)) => a == *b && !targets.contains(&i),
+            _ => false,
+        };
+        if redundant {
+            keep[i] = false;
+        } else {
+            prev = Some(*bc);
+        }
+    }
+    keep
+}
+
+/// The instruction index a resolved jump targets.
+fn jump_target(bc: &Bytecode) -> Option<usize> {
+    let idx = bc.jump_label_arg_index()?;
+    let (_, args, _) = bc.emit_args();
+    Some(args[idx] as usize)
+}
+
+/// Whether execution can go on to the next instruction after `bc`.
+fn falls_through(bc: &Bytecode) -> bool {
+    if bc.is_jump() {
+        return bc.has_flag(BytecodeFlag::CONDITIONAL);
+    }
+    !bc.is_return_or_throw() || bc.has_flag(BytecodeFlag::CONDITIONAL_THROW)
+}
+
+/// The first register or immediate operand of `bc` that the widest format
+/// of its mnemonic cannot hold, as `(operand index, value, bits)`. Where
+/// [`Bytecode::fits_encoding`] only answers whether there is one, this
+/// finds it for the error.
+fn overflowing_operand(bc: &Bytecode) -> Option<(usize, i64, u8)> {
+    if bc.fits_encoding() {
+        return None;
+    }
+    let (_, args, n) = bc.emit_args();
+    let label = bc.jump_label_arg_index();
+    let mnemonic = bc.mnemonic();
+    let rows: Vec<_> = opcode_table()
+        .iter()
+        .filter(|row| row.mnemonic == mnemonic)
+        .collect();
+    (0..n).filter(|&i| Some(i) != label).find_map(|i| {
+        let desc = rows.first()?.operands.get(i)?;
+        if desc.kind == OperandKind::Id || desc.float {
+            return None;
+        }
+        let bits = rows
+            .iter()
+            .filter_map(|row| row.operands.get(i))
+            .map(|d| d.width)
+            .max()?;
+        let value = args[i];
+        let fits = match (bits, desc.signed) {
+            (64.., _) => true,
+            (_, true) => (-(1i64 << (bits - 1))..1i64 << (bits - 1)).contains(&value),
+            (_, false) => (0..1i64 << bits).contains(&value),
+        };
+        (!fits).then_some((i, value, bits))
+    })
+}
+
+/// Reason recorded by the C++ bridge for the last failed emitter call.
+fn last_error() -> String {
+    // SAFETY: isa_last_error returns null or a NUL-terminated thread-local
+    // string that stays valid until the next emitter call on this thread.
+    let ptr = unsafe { abcd_isa_sys::isa_last_error() };
+    if ptr.is_null() {
+        return "unknown error".to_string();
     }
+    // SAFETY: ptr is non-null and NUL-terminated (checked above).
+    unsafe { core::ffi::CStr::from_ptr(ptr) }
+        .to_string_lossy()
+        .into_owned()
 }
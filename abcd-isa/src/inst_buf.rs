@@ -0,0 +1,238 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use abcd_isa_sys::operand::{OperandDesc, OperandKind, OperandValue};
+use abcd_isa_sys::{EntityId, Imm, OpcodeInfo, Reg, opcode_table};
+
+use crate::decoder::{DecodeError, row_at};
+
+/// Errors from the [`InstBuf`] setters and the `update_*` functions.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum OperandError {
+    /// The bytes do not start with a complete, valid instruction.
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+    /// The instruction has no operand `idx` of this kind.
+    #[error("no {kind:?} operand at index {idx}")]
+    NoOperand { kind: OperandKind, idx: usize },
+    /// `value` does not fit the operand's encoding of `width` bits.
+    #[error("{value} does not fit a {width}-bit operand")]
+    OutOfRange { value: i64, width: u8 },
+}
+
+/// One encoded instruction that owns its bytes, for patching operands in
+/// place.
+///
+/// The setters keep the opcode, so the instruction never changes size and
+/// can be copied back over the original. Operands are numbered per kind,
+/// as in [`OpcodeInfo::vreg`]: `set_vreg(1, ..)` is the second register
+/// whatever immediates or IDs come before it. Values that do not fit the
+/// encoding are rejected rather than truncated; moving to a wider format
+/// is a job for [`encode`](crate::encode).
+///
+/// ```
+/// use abcd_isa::{InstBuf, Reg};
+///
+/// // mov v1, v2
+/// let mut inst = InstBuf::new(&[0x44, 0x21])?;
+/// inst.set_vreg(1, Reg(7))?;
+/// assert_eq!(inst.as_bytes(), [0x44, 0x71]);
+/// assert!(inst.set_vreg(0, Reg(16)).is_err());
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct InstBuf {
+    info: &'static OpcodeInfo,
+    bytes: Vec<u8>,
+}
+
+impl InstBuf {
+    /// Copy the instruction at the start of `bytes`; anything after it is
+    /// ignored.
+    pub fn new(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let info = row_at(bytes)?;
+        Ok(Self {
+            info,
+            bytes: bytes[..usize::from(info.size)].to_vec(),
+        })
+    }
+
+    /// The instruction's encoding.
+    pub fn info(&self) -> &'static OpcodeInfo {
+        self.info
+    }
+
+    /// Every operand's current value, in signature order.
+    pub fn operands(&self) -> impl Iterator<Item = OperandValue> + '_ {
+        self.info.operand_values(&self.bytes)
+    }
+
+    /// Set register operand `idx`.
+    pub fn set_vreg(&mut self, idx: usize, reg: Reg) -> Result<(), OperandError> {
+        self.set(OperandKind::Reg, idx, i64::from(reg.0))
+    }
+
+    /// Set immediate operand `idx`. Float immediates take the value's
+    /// bits, as [`Imm`] holds them for `fldai`.
+    pub fn set_imm(&mut self, idx: usize, imm: Imm) -> Result<(), OperandError> {
+        self.set(OperandKind::Imm, idx, imm.0)
+    }
+
+    /// Set ID operand `idx`, an index into the method's region.
+    pub fn set_id(&mut self, idx: usize, id: EntityId) -> Result<(), OperandError> {
+        self.set(OperandKind::Id, idx, i64::from(id.0))
+    }
+
+    /// This instruction in its smallest encoding: the narrowest format of
+    /// its mnemonic that holds the current operands, trying the plain
+    /// mnemonic first for `wide.*`. An IC slot the wide form lacks is set
+    /// to 0, as in [`Bytecode::to_narrow`](abcd_isa_sys::Bytecode::to_narrow).
+    /// Already minimal instructions come back unchanged.
+    ///
+    /// Jump offsets are copied as they are, so canonicalizing one jump in a
+    /// method body moves the code after it; to shrink whole bodies,
+    /// [`encode`](crate::encode) picks minimal formats and fixes jumps.
+    ///
+    /// ```
+    /// use abcd_isa::InstBuf;
+    ///
+    /// // mov v1, v2 in op_v1_16_v2_16
+    /// let wide = InstBuf::new(&[0x8f, 0x01, 0x00, 0x02, 0x00])?;
+    /// assert_eq!(wide.canonicalize().as_bytes(), [0x44, 0x21]);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn canonicalize(&self) -> InstBuf {
+        let values: Vec<i64> = self
+            .info
+            .operands
+            .iter()
+            .map(|desc| desc.value(&self.bytes))
+            .collect();
+        let mut candidates: Vec<&'static OpcodeInfo> = opcode_table()
+            .iter()
+            .filter(|row| row.mnemonic == self.info.mnemonic)
+            .collect();
+        if let Some(plain) = self.info.mnemonic.strip_prefix("wide.") {
+            candidates.extend(opcode_table().iter().filter(|row| row.mnemonic == plain));
+        }
+        candidates.sort_by_key(|row| row.size);
+        candidates
+            .into_iter()
+            .take_while(|row| row.size < self.info.size)
+            .find_map(|row| self.reencode(row, &values))
+            .unwrap_or_else(|| self.clone())
+    }
+
+    /// `values` in `row`'s encoding, if every one fits. Operands `row` has
+    /// beyond `values` lead and are 0.
+    fn reencode(&self, row: &'static OpcodeInfo, values: &[i64]) -> Option<InstBuf> {
+        let lead = row.operands.len().checked_sub(values.len())?;
+        let mut bytes = vec![0; usize::from(row.size)];
+        let [low, high] = row.opcode.to_le_bytes();
+        bytes[0] = low;
+        if row.opcode > 0xff {
+            bytes[1] = high;
+        }
+        let operands = row.operands[lead..].iter().zip(self.info.operands);
+        for ((desc, old), &value) in operands.zip(values) {
+            let same_kind = desc.kind == old.kind && desc.float == old.float;
+            if !same_kind || (desc.float && desc.width != old.width) || !fits(desc, value) {
+                return None;
+            }
+            desc.insert(&mut bytes, value as u64);
+        }
+        Some(InstBuf { info: row, bytes })
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    fn set(&mut self, kind: OperandKind, idx: usize, value: i64) -> Result<(), OperandError> {
+        patch(self.info, &mut self.bytes, kind, idx, value)
+    }
+}
+
+/// Overwrite register operand `idx` of the instruction at the start of
+/// `bytes`, numbering operands as [`InstBuf::set_vreg`] does.
+///
+/// For one-off patches of a method body where copying the instruction
+/// into an [`InstBuf`] and back is not worth it. Nothing is written unless
+/// the instruction is complete and the register fits the operand.
+pub fn update_vreg(bytes: &mut [u8], idx: usize, reg: Reg) -> Result<(), OperandError> {
+    patch(
+        row_at(bytes)?,
+        bytes,
+        OperandKind::Reg,
+        idx,
+        i64::from(reg.0),
+    )
+}
+
+/// Overwrite immediate operand `idx` in place; see [`update_vreg`].
+pub fn update_imm(bytes: &mut [u8], idx: usize, imm: Imm) -> Result<(), OperandError> {
+    patch(row_at(bytes)?, bytes, OperandKind::Imm, idx, imm.0)
+}
+
+/// Overwrite ID operand `idx` in place; see [`update_vreg`].
+pub fn update_id(bytes: &mut [u8], idx: usize, id: EntityId) -> Result<(), OperandError> {
+    patch(row_at(bytes)?, bytes, OperandKind::Id, idx, i64::from(id.0))
+}
+
+/// Write `value` into the `idx`-th operand of `kind` of `info`'s encoding,
+/// which `bytes` holds in full.
+fn patch(
+    info: &OpcodeInfo,
+    bytes: &mut [u8],
+    kind: OperandKind,
+    idx: usize,
+    value: i64,
+) -> Result<(), OperandError> {
+    let desc = info
+        .operands
+        .iter()
+        .filter(|op| op.kind == kind)
+        .nth(idx)
+        .ok_or(OperandError::NoOperand { kind, idx })?;
+    if !fits(desc, value) {
+        return Err(OperandError::OutOfRange {
+            value,
+            width: desc.width,
+        });
+    }
+    desc.insert(bytes, value as u64);
+    Ok(())
+}
+
+/// Whether `value` is representable in `desc`: two's complement for signed
+/// and float immediates, otherwise unsigned. Any `i64` is some 64-bit
+/// pattern.
+fn fits(desc: &OperandDesc, value: i64) -> bool {
+    let bits = u32::from(desc.width);
+    if bits >= 64 {
+        return true;
+    }
+    if desc.signed {
+        (-(1i64 << (bits - 1))..1i64 << (bits - 1)).contains(&value)
+    } else {
+        (0..1i64 << bits).contains(&value)
+    }
+}
+
+impl AsRef<[u8]> for InstBuf {
+    fn as_ref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl PartialEq for InstBuf {
+    fn eq(&self, other: &Self) -> bool {
+        self.bytes == other.bytes
+    }
+}
+
+impl Eq for InstBuf {}
@@ -1,16 +1,78 @@
 //! Safe Rust API for the ArkCompiler bytecode instruction set.
 //!
-//! This crate provides three main capabilities:
+//! This crate provides these main capabilities:
 //!
 //! - [`decode`] — parse raw bytecode bytes into `(Bytecode, byte_offset)` pairs
-//!   with resolved jump targets.
+//!   with resolved jump targets. [`decode_pure`] does the same from the
+//!   generated opcode table alone, without calling into C, and
+//!   [`decode_all`] also keeps each instruction's size, encoding row and
+//!   operands, read in the same pass.
 //! - [`encode`] — assemble a slice of [`Bytecode`] instructions back into raw
 //!   bytes, resolving [`Label`] indices to byte offsets.
+//! - [`Emitter`] — build a method incrementally with forward labels, and
+//!   roll back speculative code with [`Emitter::snapshot`] /
+//!   [`Emitter::restore`]; [`Emitter::emit_raw`] copies already encoded
+//!   code into the stream, and [`Emitter::build_optimized`] cleans up
+//!   redundant moves, jump chains and dead code on the way out.
+//! - [`InstBuf`] — one instruction's bytes with range-checked operand
+//!   setters, for patching code in place; [`update_vreg`], [`update_imm`]
+//!   and [`update_id`] do the same directly on a method body.
+//! - [`Patcher`] — insert, replace and delete instructions in a method
+//!   body, re-encoding every jump and widening those that no longer fit.
+//!   [`ic_slot_count`] gives the inline-cache slot count the result needs.
+//! - [`lookup_mnemonic`] — find the [`OpcodeInfo`] of an instruction by its
+//!   textual mnemonic, for assemblers and tests written in mnemonics.
+//! - [`asm`] — assemble a textual listing, labels and ID placeholders
+//!   included, into an [`Emitter`]; it reads back what instruction
+//!   `Display` prints.
 //! - [`Version`] — query and compare `.abc` file format versions.
+//!   [`IsaProfile`] narrows the opcode table to the instructions a given
+//!   version has, and decodes against it.
+//! - [`fmt`] — install a per-thread resolver that makes instruction `Display`
+//!   show the names behind string, method and literal-array IDs.
+//! - [`CostClass`] / [`CostEstimate`] — static instruction costs for ranking
+//!   methods without a profile.
+//! - [`OpcodeCategory`] — what an instruction does (load, call, object
+//!   access, ...), for coloring listings and counting instruction mixes.
+//! - [`PrefixGroup`] — the callruntime, deprecated, wide and throw families
+//!   of prefixed opcodes; [`opcodes_with_prefix`] and
+//!   [`opcode_table_for_namespace`] cut the opcode table by prefix byte and
+//!   by `isa.yaml` namespace.
+//! - [`CallInfo`] — where a call instruction keeps its receiver and
+//!   arguments, as operand indices.
+//! - [`export_metadata`] — the whole opcode table as owned [`OpcodeMeta`]
+//!   rows (format, operand layout, flags, exceptions, namespace, size) in
+//!   an [`IsaMetadata`], for documentation and third-party decoders.
+//! - [`normalize`] — canonicalize a method body so that diffs and clone
+//!   detection see logic rather than encoding choices.
+//!
+//! # `no_std`
+//!
+//! With `default-features = false` the crate builds on `core` and `alloc`
+//! alone, for embedded analysis tools and WASM hosts: decoding, encoding,
+//! operand extraction and classification all work. The `std` feature adds
+//! the thread-local ID resolvers of [`fmt`] and
+//! [`Bytecode::semantic_hash`]. The C++ bridge is still linked either way,
+//! so the target needs a C++ toolchain; [`decode_pure`] is the decoding
+//! path that does not call into it.
+//!
+//! # `serde`
+//!
+//! The `serde` feature (off by default) derives `Serialize` and
+//! `Deserialize` for [`Bytecode`] and its operand types, [`BytecodeFlag`],
+//! [`ExceptionType`] (as `"A | B"` strings in human-readable formats),
+//! [`Version`] (as `"12.0.6.0"`), [`OperandDesc`], [`PrefixGroup`],
+//! [`OpcodeMeta`] and [`IsaMetadata`]. It works without `std`.
 //!
 //! All public types are safe.  `unsafe` is confined to internal FFI calls into
 //! the C bridge provided by [`abcd_isa_sys`].
 //!
+//! # Byte order
+//!
+//! Multi-byte operands are little-endian in the bytecode. Both directions
+//! assemble them a byte at a time, so results are identical on big-endian
+//! hosts.
+//!
 //! # Quick start
 //!
 //! ```no_run
@@ -29,16 +91,52 @@
 //!
 //! The following types are re-exported from [`abcd_isa_sys`] for convenience:
 //! [`Bytecode`], [`Reg`], [`Imm`], [`EntityId`], [`Label`],
-//! [`insn`], [`BytecodeFlag`], [`ExceptionType`].
+//! [`insn`], [`BytecodeFlag`], [`ExceptionType`], [`RangeArgs`], [`OpcodeInfo`],
+//! [`opcode_table`], [`opcodes`], [`OperandDesc`], [`OperandKind`], [`OperandValue`],
+//! [`IdKind`], [`TypedEntityRef`], [`CostClass`], [`CostEstimate`] and [`PrefixGroup`].
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
 
 pub use abcd_isa_sys::{Bytecode, EntityId, Imm, Label, Reg};
 pub use abcd_isa_sys::{BytecodeFlag, ExceptionType, insn};
+pub use abcd_isa_sys::{OpcodeInfo, RangeArgs, opcode_table, opcode_table_for_namespace, opcodes};
+
+pub use abcd_isa_sys::call::{CallArgs, CallInfo, CallKind};
+pub use abcd_isa_sys::category::OpcodeCategory;
+pub use abcd_isa_sys::coroutine::{SuspendKind, SuspendPoint, suspend_points};
+pub use abcd_isa_sys::cost::{CostClass, CostEstimate};
+pub use abcd_isa_sys::fmt;
+pub use abcd_isa_sys::fmt::{IdKind, TypedEntityRef};
+pub use abcd_isa_sys::operand::{OperandDesc, OperandKind, OperandValue};
+pub use abcd_isa_sys::prefix::{PrefixGroup, opcodes_with_prefix};
+
+pub mod asm;
 
 mod decoder;
-pub use decoder::{DecodeError, decode};
+pub use decoder::{DecodeError, DecodedInst, decode, decode_all, decode_pure};
 
 mod emitter;
-pub use emitter::{EncodeError, encode};
+pub use emitter::{Checkpoint, Emitter, EncodeError, encode};
+
+mod inst_buf;
+pub use inst_buf::{InstBuf, OperandError, update_id, update_imm, update_vreg};
+
+mod lookup;
+pub use lookup::{lookup_mnemonic, lookup_mnemonic_ignore_case};
+
+mod meta;
+pub use meta::{IsaMetadata, OpcodeMeta, export_metadata};
+
+mod normalize;
+pub use normalize::normalize;
+
+mod patcher;
+pub use patcher::{PatchError, Patched, Patcher, ic_slot_count};
+
+mod profile;
+pub use profile::{IsaProfile, introduced_in};
 
 mod version;
 pub use version::Version;
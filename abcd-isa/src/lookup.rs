@@ -0,0 +1,76 @@
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+#[cfg(feature = "std")]
+use std::sync::OnceLock;
+
+use abcd_isa_sys::{OpcodeInfo, opcode_table};
+
+/// The encoding of `mnemonic` (as spelled in `isa.yaml`, e.g.
+/// `"ldobjbyname"` or `"callruntime.istrue"`), or `None` if there is no
+/// such instruction.
+///
+/// Mnemonics with several encodings, such as `mov`, give the smallest one;
+/// the others are the [`opcode_table`] rows with the same mnemonic. With
+/// the `std` feature the first call builds a map of every mnemonic, so
+/// assemblers can look up each line cheaply.
+///
+/// ```
+/// use abcd_isa::lookup_mnemonic;
+///
+/// let row = lookup_mnemonic("ldobjbyname").unwrap();
+/// assert_eq!(row.mnemonic, "ldobjbyname");
+/// assert!(lookup_mnemonic("LDOBJBYNAME").is_none());
+/// ```
+pub fn lookup_mnemonic(mnemonic: &str) -> Option<OpcodeInfo> {
+    #[cfg(feature = "std")]
+    {
+        by_mnemonic().get(mnemonic).copied()
+    }
+    #[cfg(not(feature = "std"))]
+    {
+        smallest(|m| m == mnemonic)
+    }
+}
+
+/// [`lookup_mnemonic`], ignoring ASCII case: `"LdObjByName"` finds
+/// `ldobjbyname`.
+pub fn lookup_mnemonic_ignore_case(mnemonic: &str) -> Option<OpcodeInfo> {
+    #[cfg(feature = "std")]
+    {
+        // Every mnemonic in isa.yaml is lowercase.
+        lookup_mnemonic(&mnemonic.to_ascii_lowercase())
+    }
+    #[cfg(not(feature = "std"))]
+    {
+        smallest(|m| m.eq_ignore_ascii_case(mnemonic))
+    }
+}
+
+/// Smallest encoding for every mnemonic.
+#[cfg(feature = "std")]
+fn by_mnemonic() -> &'static HashMap<&'static str, OpcodeInfo> {
+    static MAP: OnceLock<HashMap<&'static str, OpcodeInfo>> = OnceLock::new();
+    MAP.get_or_init(|| {
+        let mut map: HashMap<&'static str, OpcodeInfo> = HashMap::new();
+        for row in opcode_table() {
+            map.entry(row.mnemonic)
+                .and_modify(|best| {
+                    if row.size < best.size {
+                        *best = *row;
+                    }
+                })
+                .or_insert(*row);
+        }
+        map
+    })
+}
+
+/// Smallest encoding whose mnemonic matches, by scanning the table.
+#[cfg(not(feature = "std"))]
+fn smallest(matches: impl Fn(&str) -> bool) -> Option<OpcodeInfo> {
+    opcode_table()
+        .iter()
+        .filter(|row| matches(row.mnemonic))
+        .min_by_key(|row| row.size)
+        .copied()
+}
@@ -0,0 +1,102 @@
+//! Owned snapshots of the opcode table, for exporting ISA metadata.
+//!
+//! [`OpcodeInfo`] borrows from the generated table and leaves flags and
+//! exceptions behind C bridge queries. [`OpcodeMeta`] collects everything
+//! known about one encoding into plain owned data, so that with the `serde`
+//! feature the table can be written out as JSON for external tooling, and
+//! snapshots taken from two ISA versions can be compared field by field.
+//!
+//! [`export_metadata`] wraps the snapshot with the versions it describes:
+//!
+//! ```ignore
+//! let isa = abcd_isa::export_metadata();
+//! std::fs::write("isa.json", serde_json::to_string_pretty(&isa)?)?;
+//! ```
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::{
+    BytecodeFlag, ExceptionType, OpcodeInfo, OperandDesc, PrefixGroup, Version, introduced_in,
+    opcode_table,
+};
+
+/// Everything the ISA says about one encoding.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OpcodeMeta {
+    /// Opcode value; prefixed opcodes carry the prefix in the low byte.
+    pub opcode: u16,
+    pub mnemonic: String,
+    /// Encoded size in bytes, opcode included.
+    pub size: u8,
+    /// `isa.yaml` format name (`op_v1_4_v2_4`); [`operands`](Self::operands)
+    /// gives the same layout as bit positions.
+    pub format: String,
+    /// `isa.yaml` namespace: `ecmascript` or `core`.
+    pub namespace: String,
+    pub prefix: Option<PrefixGroup>,
+    /// Operand positions, in signature order.
+    pub operands: Vec<OperandDesc>,
+    pub flags: BytecodeFlag,
+    /// Exceptions the instruction can throw.
+    pub exceptions: ExceptionType,
+    /// First file version with the instruction, if it came after API 9.
+    pub introduced_in: Option<Version>,
+}
+
+impl OpcodeMeta {
+    /// The metadata of `info`'s encoding. Flags and exceptions are read
+    /// through the C bridge.
+    pub fn of(info: &OpcodeInfo) -> Self {
+        let flags = BytecodeFlag::all()
+            .iter()
+            .filter(|&flag| info.template.has_flag(flag))
+            .collect();
+        let exceptions = ExceptionType::all()
+            .iter()
+            .filter(|&ex| info.template.is_throw_ex(ex))
+            .collect();
+        OpcodeMeta {
+            opcode: info.opcode,
+            mnemonic: info.mnemonic.to_string(),
+            size: info.size,
+            format: info.format.to_string(),
+            namespace: info.namespace.to_string(),
+            prefix: info.prefix_group(),
+            operands: info.operands.to_vec(),
+            flags,
+            exceptions,
+            introduced_in: introduced_in(info.mnemonic),
+        }
+    }
+
+    /// Every row of [`opcode_table`], in opcode order.
+    pub fn snapshot() -> Vec<OpcodeMeta> {
+        opcode_table().iter().map(OpcodeMeta::of).collect()
+    }
+}
+
+/// The whole instruction set, as [`export_metadata`] describes it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IsaMetadata {
+    /// File format version of the ISA the table belongs to.
+    pub version: Version,
+    /// Oldest file format version the ISA still reads.
+    pub min_version: Version,
+    /// Every encoding, in opcode order.
+    pub opcodes: Vec<OpcodeMeta>,
+}
+
+/// Describe every opcode of the linked ISA: mnemonic, format and operand
+/// layout, flags, exceptions, namespace and size. With the `serde` feature
+/// the result serializes to JSON, YAML or anything else serde writes, for
+/// generating documentation or keeping a third-party decoder in sync.
+pub fn export_metadata() -> IsaMetadata {
+    IsaMetadata {
+        version: Version::current(),
+        min_version: Version::min_supported(),
+        opcodes: OpcodeMeta::snapshot(),
+    }
+}
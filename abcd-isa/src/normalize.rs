@@ -0,0 +1,105 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use abcd_isa_sys::{Bytecode, Label};
+
+use crate::{decode, encode};
+
+/// Rewrite a method body so that encodings with the same meaning produce
+/// the same bytes.
+///
+/// Three kinds of encoding noise are removed:
+///
+/// - `wide.*` instructions whose operands fit the narrow form are narrowed
+///   (see [`Bytecode::to_narrow`]), and every instruction is re-emitted in
+///   the smallest format its operands allow.
+/// - Inline-cache slot operands are zeroed (see [`Bytecode::clear_ic_slot`]).
+/// - Redundant moves are dropped: `mov vA, vA`, and a `mov` that repeats
+///   or undoes the `mov` right before it (`mov vA, vB; mov vB, vA`). A move
+///   that is a jump target is kept, since control may reach it on its own.
+///
+/// The result is meant for comparison, not execution: IC slots no longer
+/// match the method's slot count, and byte offsets shift, so try blocks of
+/// the original method do not apply to it. Input that fails to decode or
+/// re-encode is returned unchanged.
+///
+/// ```no_run
+/// // `wide.ldlexvar 0, 1` and `ldlexvar 0, 1` normalize to the same bytes.
+/// let wide = [0xfd, 0x0c, 0x00, 0x00, 0x01, 0x00];
+/// let narrow = [0x3c, 0x10];
+/// assert_eq!(abcd_isa::normalize(&wide), abcd_isa::normalize(&narrow));
+/// ```
+pub fn normalize(bytes: &[u8]) -> Vec<u8> {
+    let Ok(decoded) = decode(bytes) else {
+        return bytes.to_vec();
+    };
+    let mut insns: Vec<Bytecode> = decoded
+        .into_iter()
+        .map(|(bc, _)| bc.semantic_form())
+        .collect();
+    drop_redundant_moves(&mut insns);
+    match encode(&insns) {
+        Ok((out, _)) => out,
+        Err(_) => bytes.to_vec(),
+    }
+}
+
+/// Remove redundant `mov`s and renumber jump labels to match.
+fn drop_redundant_moves(insns: &mut Vec<Bytecode>) {
+    let mut is_target = vec![false; insns.len()];
+    for bc in insns.iter() {
+        if let Some(target) = label_of(bc) {
+            is_target[target as usize] = true;
+        }
+    }
+
+    let mut keep = vec![true; insns.len()];
+    let mut prev_mov: Option<(u16, u16)> = None;
+    for (i, bc) in insns.iter().enumerate() {
+        let mov = match *bc {
+            Bytecode::Mov(dst, src) => Some((dst.0, src.0)),
+            _ => None,
+        };
+        let redundant = match (mov, prev_mov) {
+            _ if is_target[i] => false,
+            (Some((dst, src)), _) if dst == src => true,
+            (Some(cur), Some(prev)) => cur == prev || cur == (prev.1, prev.0),
+            _ => false,
+        };
+        if redundant {
+            keep[i] = false;
+        } else {
+            prev_mov = mov;
+        }
+    }
+    if keep.iter().all(|&k| k) {
+        return;
+    }
+
+    // new_index[i] is the position of instruction `i` after removal. Only
+    // kept instructions are jump targets, so dropped slots are never read.
+    let mut new_index = Vec::with_capacity(insns.len());
+    let mut next = 0u32;
+    for &k in &keep {
+        new_index.push(next);
+        next += u32::from(k);
+    }
+    let mut i = 0;
+    insns.retain(|_| {
+        i += 1;
+        keep[i - 1]
+    });
+    for bc in insns.iter_mut() {
+        if let Some(target) = label_of(bc) {
+            bc.set_label(Label(new_index[target as usize]));
+        }
+    }
+}
+
+/// The instruction index a jump refers to. Labels from [`decode`] are always
+/// in range.
+fn label_of(bc: &Bytecode) -> Option<u32> {
+    let idx = bc.jump_label_arg_index()?;
+    let (_, args, _) = bc.emit_args();
+    Some(args[idx] as u32)
+}
@@ -0,0 +1,243 @@
+use alloc::vec::Vec;
+
+use abcd_isa_sys::{Bytecode, Label};
+
+use crate::decoder::{DecodeError, decode};
+use crate::emitter::{EncodeError, encode};
+
+/// Errors from [`Patcher`].
+#[derive(Debug, thiserror::Error)]
+pub enum PatchError {
+    /// The original code does not decode.
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+    /// The patched code does not encode.
+    #[error(transparent)]
+    Encode(#[from] EncodeError),
+    /// An edit names instruction `idx` of a method that has `len`.
+    #[error("instruction index {idx} is out of range ({len} instructions)")]
+    IndexOutOfRange { idx: usize, len: usize },
+    /// A jump targets original instruction `{0}`, which was deleted with
+    /// nothing left after it to land on.
+    #[error("jump to deleted instruction {0} has nowhere to land")]
+    DanglingJump(u32),
+}
+
+/// Edits at one original instruction, each list in call order.
+#[derive(Clone, Debug, Default)]
+struct Slot {
+    before: Vec<Bytecode>,
+    /// `None` keeps the original instruction.
+    body: Option<Vec<Bytecode>>,
+    after: Vec<Bytecode>,
+}
+
+/// Splices instructions into an existing method body and re-encodes it,
+/// keeping every jump on target.
+///
+/// Edits name instructions by their index in the original code (see
+/// [`index_at`](Self::index_at)), however many edits came before, and take
+/// effect together in [`finish`](Self::finish). Original jumps follow the
+/// instruction they targeted: onto code inserted before it, onto the first
+/// instruction replacing it, or past it when it was deleted. A jump whose
+/// displacement no longer fits its format is widened by [`encode`].
+///
+/// [`Label`]s in inserted code also name original instructions, but land on
+/// the instruction itself rather than on code inserted before it, so a
+/// probe can jump past itself.
+///
+/// ```
+/// use abcd_isa::{Label, Patcher, decode, encode, insn};
+///
+/// // jmp +1; ldundefined; returnundefined
+/// let (code, _) = encode(&[
+///     insn::Jmp::new(Label(2)),
+///     insn::Ldundefined::new(),
+///     insn::Returnundefined::new(),
+/// ])?;
+///
+/// let mut patcher = Patcher::new(&code)?;
+/// patcher.insert_before(2, &[insn::Ldnull::new()])?;
+/// let patched = patcher.finish()?;
+///
+/// // The jump now lands on the inserted ldnull.
+/// let insns = decode(&patched.bytes)?;
+/// assert_eq!(insns[0].0.emit_args(), insn::Jmp::new(Label(2)).emit_args());
+/// assert_eq!(insns[2].0.mnemonic(), "ldnull");
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct Patcher {
+    original: Vec<(Bytecode, u32)>,
+    code_len: u32,
+    slots: Vec<Slot>,
+}
+
+/// The result of [`Patcher::finish`].
+#[derive(Clone, Debug)]
+pub struct Patched {
+    pub bytes: Vec<u8>,
+    /// Byte offset of every instruction of `bytes`, as [`encode`] reports.
+    pub offsets: Vec<u32>,
+    /// Inline-cache slots the patched code needs; see [`ic_slot_count`].
+    /// Inserted instructions that take fresh slots raise it past what the
+    /// method records, and the record has to follow.
+    pub ic_slots: u32,
+    /// `(old, new)` byte offset of every original instruction, ascending.
+    moved: Vec<(u32, u32)>,
+    code_len: u32,
+}
+
+impl Patched {
+    /// Where the original instruction at byte offset `old` went, for
+    /// moving try blocks and other offsets into the code. The original
+    /// code's length maps to the new length. Deleted instructions map to
+    /// whatever took their place.
+    pub fn map_offset(&self, old: u32) -> Option<u32> {
+        if old == self.code_len {
+            return Some(self.bytes.len() as u32);
+        }
+        let i = self.moved.binary_search_by_key(&old, |&(o, _)| o).ok()?;
+        Some(self.moved[i].1)
+    }
+
+    /// `(old, new)` byte offset of every original instruction, ascending
+    /// by old offset, as [`map_offset`](Self::map_offset) maps them.
+    pub fn moves(&self) -> &[(u32, u32)] {
+        &self.moved
+    }
+}
+
+impl Patcher {
+    /// Decode `code`, a whole method body.
+    pub fn new(code: &[u8]) -> Result<Self, PatchError> {
+        let original = decode(code)?;
+        Ok(Self {
+            slots: alloc::vec![Slot::default(); original.len()],
+            original,
+            code_len: code.len() as u32,
+        })
+    }
+
+    /// Number of original instructions.
+    pub fn len(&self) -> usize {
+        self.original.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.original.is_empty()
+    }
+
+    /// Index of the original instruction starting at byte offset `offset`.
+    pub fn index_at(&self, offset: u32) -> Option<usize> {
+        self.original
+            .binary_search_by_key(&offset, |&(_, off)| off)
+            .ok()
+    }
+
+    /// Insert `code` ahead of instruction `idx`. Jumps to `idx` run it.
+    pub fn insert_before(&mut self, idx: usize, code: &[Bytecode]) -> Result<(), PatchError> {
+        self.slot(idx)?.before.extend_from_slice(code);
+        Ok(())
+    }
+
+    /// Insert `code` right after instruction `idx`.
+    pub fn insert_after(&mut self, idx: usize, code: &[Bytecode]) -> Result<(), PatchError> {
+        self.slot(idx)?.after.extend_from_slice(code);
+        Ok(())
+    }
+
+    /// Replace instruction `idx` with `code`, which may be empty.
+    pub fn replace(&mut self, idx: usize, code: &[Bytecode]) -> Result<(), PatchError> {
+        self.slot(idx)?.body = Some(code.to_vec());
+        Ok(())
+    }
+
+    /// Remove instruction `idx`.
+    pub fn delete(&mut self, idx: usize) -> Result<(), PatchError> {
+        self.replace(idx, &[])
+    }
+
+    /// Apply every edit and encode the result.
+    pub fn finish(&self) -> Result<Patched, PatchError> {
+        // `entry[i]` is where jumps in the original code to instruction
+        // `i` land, `own[i]` where those in inserted code do.
+        let mut out: Vec<Bytecode> = Vec::new();
+        let mut inserted: Vec<bool> = Vec::new();
+        let mut entry = Vec::with_capacity(self.slots.len());
+        let mut own = Vec::with_capacity(self.slots.len());
+        for (slot, &(bc, _)) in self.slots.iter().zip(&self.original) {
+            entry.push(out.len());
+            push(&mut out, &mut inserted, &slot.before, true);
+            own.push(out.len());
+            match &slot.body {
+                Some(code) => push(&mut out, &mut inserted, code, true),
+                None => push(&mut out, &mut inserted, &[bc], false),
+            }
+            push(&mut out, &mut inserted, &slot.after, true);
+        }
+
+        for (bc, &new) in out.iter_mut().zip(&inserted) {
+            let Some(target) = label_of(bc) else {
+                continue;
+            };
+            let map = if new { &own } else { &entry };
+            let at = *map
+                .get(target as usize)
+                .ok_or(EncodeError::LabelOutOfBounds(target, self.original.len()))?;
+            if at >= inserted.len() {
+                return Err(PatchError::DanglingJump(target));
+            }
+            bc.set_label(Label(at as u32));
+        }
+
+        let (bytes, offsets) = encode(&out)?;
+        let ic_slots = ic_slot_count(&out);
+        let moved = self
+            .original
+            .iter()
+            .zip(&entry)
+            .map(|(&(_, old), &at)| {
+                let new = offsets.get(at).copied().unwrap_or(bytes.len() as u32);
+                (old, new)
+            })
+            .collect();
+        Ok(Patched {
+            bytes,
+            offsets,
+            ic_slots,
+            moved,
+            code_len: self.code_len,
+        })
+    }
+
+    fn slot(&mut self, idx: usize) -> Result<&mut Slot, PatchError> {
+        let len = self.slots.len();
+        self.slots
+            .get_mut(idx)
+            .ok_or(PatchError::IndexOutOfRange { idx, len })
+    }
+}
+
+/// Inline-cache slots a method with `code` needs: one past the last slot
+/// any instruction occupies. This is the count the runtime sizes the
+/// method's IC array by, recorded in its slot number annotation.
+pub fn ic_slot_count(code: &[Bytecode]) -> u32 {
+    code.iter()
+        .filter_map(Bytecode::ic_slots)
+        .map(|slots| slots.end)
+        .max()
+        .unwrap_or(0)
+}
+
+fn push(out: &mut Vec<Bytecode>, inserted: &mut Vec<bool>, code: &[Bytecode], new: bool) {
+    out.extend_from_slice(code);
+    inserted.resize(out.len(), new);
+}
+
+/// The instruction index a jump refers to.
+fn label_of(bc: &Bytecode) -> Option<u32> {
+    let idx = bc.jump_label_arg_index()?;
+    let (_, args, _) = bc.emit_args();
+    Some(args[idx] as u32)
+}
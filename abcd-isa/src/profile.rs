@@ -0,0 +1,147 @@
+//! Which instructions exist in which `.abc` version.
+
+use alloc::vec::Vec;
+
+use abcd_isa_sys::{Bytecode, OpcodeInfo, opcode_table};
+
+use crate::decoder::{DecodeError, decode_rows};
+use crate::version::Version;
+
+/// Instructions added after the API 9 instruction set, with the first
+/// file version es2abc emits them in. Everything else in [`opcode_table`]
+/// has been there since.
+const INTRODUCED: [(&str, Version); 28] = [
+    ("definefieldbyname", Version::new(11, 0, 2, 0)),
+    ("definepropertybyname", Version::new(11, 0, 2, 0)),
+    (
+        "callruntime.notifyconcurrentresult",
+        Version::new(11, 0, 2, 0),
+    ),
+    ("callruntime.definefieldbyvalue", Version::new(11, 0, 2, 0)),
+    ("callruntime.definefieldbyindex", Version::new(11, 0, 2, 0)),
+    ("callruntime.topropertykey", Version::new(11, 0, 2, 0)),
+    (
+        "callruntime.createprivateproperty",
+        Version::new(11, 0, 2, 0),
+    ),
+    (
+        "callruntime.defineprivateproperty",
+        Version::new(11, 0, 2, 0),
+    ),
+    ("callruntime.callinit", Version::new(11, 0, 2, 0)),
+    ("callruntime.definesendableclass", Version::new(11, 0, 2, 0)),
+    ("callruntime.ldsendableclass", Version::new(11, 0, 2, 0)),
+    (
+        "callruntime.ldsendableexternalmodulevar",
+        Version::new(12, 0, 6, 0),
+    ),
+    (
+        "callruntime.wideldsendableexternalmodulevar",
+        Version::new(12, 0, 6, 0),
+    ),
+    ("callruntime.newsendableenv", Version::new(12, 0, 6, 0)),
+    ("callruntime.widenewsendableenv", Version::new(12, 0, 6, 0)),
+    ("callruntime.stsendablevar", Version::new(12, 0, 6, 0)),
+    ("callruntime.widestsendablevar", Version::new(12, 0, 6, 0)),
+    ("callruntime.ldsendablevar", Version::new(12, 0, 6, 0)),
+    ("callruntime.wideldsendablevar", Version::new(12, 0, 6, 0)),
+    ("callruntime.istrue", Version::new(12, 0, 6, 0)),
+    ("callruntime.isfalse", Version::new(12, 0, 6, 0)),
+    ("callruntime.ldlazymodulevar", Version::new(12, 0, 6, 0)),
+    ("callruntime.wideldlazymodulevar", Version::new(12, 0, 6, 0)),
+    (
+        "callruntime.ldlazysendablemodulevar",
+        Version::new(12, 0, 6, 0),
+    ),
+    (
+        "callruntime.wideldlazysendablemodulevar",
+        Version::new(12, 0, 6, 0),
+    ),
+    (
+        "callruntime.supercallforwardallargs",
+        Version::new(12, 0, 6, 0),
+    ),
+    (
+        "callruntime.ldsendablelocalmodulevar",
+        Version::new(12, 0, 6, 0),
+    ),
+    (
+        "callruntime.wideldsendablelocalmodulevar",
+        Version::new(12, 0, 6, 0),
+    ),
+];
+
+/// The instruction set as of one file version.
+///
+/// Since API 9 the ISA has only grown: opcodes keep their numbers, and
+/// instructions es2abc stopped emitting moved under the `deprecated`
+/// prefix, which every later runtime still executes. A profile is
+/// therefore the latest [`opcode_table`] minus what the version predates.
+/// Decoding with one rejects such opcodes instead of reading bytes an older
+/// runtime would refuse as instructions it could not have run.
+///
+/// Versions below [`Version::min_supported`] have no profile: their
+/// instruction set is not the API 9 one this table starts from.
+///
+/// ```
+/// use abcd_isa::{IsaProfile, Version};
+///
+/// let api9 = IsaProfile::for_version(Version::new(9, 0, 0, 0)).unwrap();
+/// assert!(!api9.has_mnemonic("definefieldbyname"));
+/// assert!(IsaProfile::for_version(Version::new(0, 0, 0, 1)).is_none());
+/// assert!(IsaProfile::latest().has_mnemonic("definefieldbyname"));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IsaProfile {
+    version: Version,
+}
+
+impl IsaProfile {
+    /// The instructions a file of version `version` may contain, or `None`
+    /// when `version` is below [`Version::min_supported`].
+    pub fn for_version(version: Version) -> Option<Self> {
+        (version >= Version::min_supported()).then_some(Self { version })
+    }
+
+    /// The instruction set of [`Version::current`].
+    pub fn latest() -> Self {
+        Self {
+            version: Version::current(),
+        }
+    }
+
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    /// Whether files of this version may contain `mnemonic`. Mnemonics the
+    /// ISA does not know are not checked and count as present.
+    pub fn has_mnemonic(&self, mnemonic: &str) -> bool {
+        introduced_in(mnemonic).is_none_or(|since| since <= self.version)
+    }
+
+    /// Whether files of this version may contain the encoding `info`.
+    pub fn has(&self, info: &OpcodeInfo) -> bool {
+        self.has_mnemonic(info.mnemonic)
+    }
+
+    /// Rows of [`opcode_table`] this version has, sorted by opcode.
+    pub fn opcodes(&self) -> Vec<&'static OpcodeInfo> {
+        opcode_table().iter().filter(|row| self.has(row)).collect()
+    }
+
+    /// [`decode_pure`](crate::decode_pure) against this instruction set:
+    /// opcodes the version predates fail with [`DecodeError::InvalidOpcode`].
+    pub fn decode(&self, bytes: &[u8]) -> Result<Vec<(Bytecode, u32)>, DecodeError> {
+        decode_rows(bytes, |row| self.has(row))
+    }
+}
+
+/// First file version with `mnemonic`, for instructions added after the
+/// API 9 instruction set; `None` for the rest.
+pub fn introduced_in(mnemonic: &str) -> Option<Version> {
+    INTRODUCED
+        .iter()
+        .find(|&&(m, _)| m == mnemonic)
+        .map(|&(_, since)| since)
+}
@@ -1,5 +1,6 @@
-use std::ffi::CString;
-use std::fmt;
+use alloc::ffi::CString;
+use alloc::vec::Vec;
+use core::fmt;
 
 /// .abc file format version (`major.minor.patch.build`).
 ///
@@ -154,3 +155,52 @@ impl From<Version> for [u8; 4] {
         v.0
     }
 }
+
+/// Human-readable formats carry the dotted string (`"12.0.6.0"`), others
+/// the four bytes.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Version {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            self.0.serialize(serializer)
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Version {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Dotted;
+
+        impl serde::de::Visitor<'_> for Dotted {
+            type Value = Version;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a version like \"12.0.6.0\"")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, s: &str) -> Result<Version, E> {
+                let mut bytes = [0u8; 4];
+                let mut parts = s.split('.');
+                for b in &mut bytes {
+                    *b = parts
+                        .next()
+                        .and_then(|p| p.parse().ok())
+                        .ok_or_else(|| E::invalid_value(serde::de::Unexpected::Str(s), &self))?;
+                }
+                if parts.next().is_some() {
+                    return Err(E::invalid_value(serde::de::Unexpected::Str(s), &self));
+                }
+                Ok(Version(bytes))
+            }
+        }
+
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_str(Dotted)
+        } else {
+            <[u8; 4]>::deserialize(deserializer).map(Version)
+        }
+    }
+}
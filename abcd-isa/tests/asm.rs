@@ -0,0 +1,146 @@
+use abcd_isa::asm::{self, AsmErrorKind};
+use abcd_isa::*;
+
+#[test]
+fn labels_and_placeholders() {
+    let source = "\
+# count down from v0
+loop:
+    lda.str \"hello\"        // greeting
+    definefunc 0, @f, 1
+    mov v1, v0
+    jnez loop
+    fldai 1.5
+    returnundefined
+";
+    let program = asm::assemble_with(source, |kind, name| match (kind, name) {
+        (IdKind::String, "hello") => Some(EntityId(7)),
+        (IdKind::Method, "f") => Some(EntityId(9)),
+        _ => None,
+    })
+    .unwrap();
+
+    let mut e = Emitter::new();
+    let top = e.create_label();
+    e.bind(top);
+    e.emit(insn::LdaStr::new(EntityId(7)));
+    e.emit(insn::Definefunc::new(Imm(0), EntityId(9), Imm(1)));
+    e.emit(insn::Mov::new(Reg(1), Reg(0)));
+    e.emit(insn::Jnez::new(top));
+    e.emit(insn::Fldai::new(Imm(1.5f64.to_bits() as i64)));
+    e.emit(insn::Returnundefined::new());
+    let (got, want) = (program.emitter.instructions(), e.instructions());
+    assert_eq!(got.len(), want.len());
+    for (a, b) in got.iter().zip(want) {
+        assert!(a.semantic_eq(b), "{a} != {b}");
+    }
+    assert_eq!(program.labels["loop"], top);
+}
+
+#[test]
+fn display_output_reassembles() {
+    let mut e = Emitter::new();
+    let done = e.create_label();
+    let again = e.create_label();
+    e.bind(again);
+    e.emit(insn::Ldai::new(Imm(-3)));
+    e.emit(insn::Sta::new(Reg(2)));
+    e.emit(insn::Jeqz::new(done));
+    e.emit(insn::Fldai::new(Imm(0.25f64.to_bits() as i64)));
+    e.emit(insn::Jmp::new(again));
+    e.bind(done);
+    e.emit(insn::LdaStr::new(EntityId(0x20)));
+    e.emit(insn::Return::new());
+    let (bytes, _) = e.build().unwrap();
+
+    let listing: Vec<String> = decode(&bytes)
+        .unwrap()
+        .iter()
+        .map(|(bc, _)| bc.to_string())
+        .collect();
+    let program = asm::assemble(&listing.join("\n")).unwrap();
+    assert_eq!(program.emitter.build().unwrap().0, bytes);
+}
+
+#[test]
+fn errors_point_at_the_source() {
+    let source = "ldtrue\n  frobnicate v0\n";
+    let err = asm::assemble(source).unwrap_err();
+    assert_eq!(err.line, 2);
+    assert_eq!(&source[err.span.start..err.span.end], "frobnicate");
+    assert_eq!(err.kind, AsmErrorKind::UnknownMnemonic("frobnicate".into()));
+
+    let source = "mov v1, 7";
+    let err = asm::assemble(source).unwrap_err();
+    assert_eq!(&source[err.span.start..err.span.end], "7");
+    assert_eq!(err.kind, AsmErrorKind::Expected("a register"));
+
+    let err = asm::assemble("mov v1").unwrap_err();
+    assert_eq!(
+        err.kind,
+        AsmErrorKind::OperandCount {
+            expected: 2,
+            found: 1
+        }
+    );
+
+    let source = "jmp nowhere\nreturnundefined";
+    let err = asm::assemble(source).unwrap_err();
+    assert_eq!(&source[err.span.start..err.span.end], "nowhere");
+    assert_eq!(err.kind, AsmErrorKind::UndefinedLabel("nowhere".into()));
+
+    let err = asm::assemble("a:\nldtrue\na:\nreturn").unwrap_err();
+    assert_eq!(err.line, 3);
+    assert_eq!(err.kind, AsmErrorKind::DuplicateLabel("a".into()));
+
+    let err = asm::assemble("lda.str \"open").unwrap_err();
+    assert_eq!(err.kind, AsmErrorKind::UnterminatedString);
+
+    let err = asm::assemble("lda.str \"hello\"").unwrap_err();
+    assert_eq!(err.kind, AsmErrorKind::UnresolvedId("hello".into()));
+    assert!(err.to_string().starts_with("line 1: "));
+}
+
+#[test]
+fn format_with_output_reassembles() {
+    struct Names;
+    impl fmt::IdResolver for Names {
+        fn resolve(&self, kind: IdKind, id: EntityId) -> Option<String> {
+            match (kind, id.0) {
+                (IdKind::String, 1) => Some("hi there".into()),
+                (IdKind::Method, 2) => Some("#~@0>#foo".into()),
+                (IdKind::String, 3) => Some(AWKWARD_STRING.into()),
+                (IdKind::Method, 4) => Some("<lambda>(a, b); \"x\"".into()),
+                (IdKind::LiteralArray, 5) => Some("0x1c4".into()),
+                _ => None,
+            }
+        }
+    }
+    const AWKWARD_STRING: &str = "tab\there\r\0\u{1}\\ \"é\"\n";
+    let program = [
+        insn::LdaStr::new(EntityId(1)),
+        insn::Definefunc::new(Imm(0), EntityId(2), Imm(1)),
+        insn::LdaStr::new(EntityId(3)),
+        insn::Definefunc::new(Imm(0), EntityId(4), Imm(2)),
+        insn::Createarraywithbuffer::new(Imm(0), EntityId(5)),
+        insn::Returnundefined::new(),
+    ];
+    let listing = program
+        .map(|bc| bc.format_with(&Names).to_string())
+        .join("\n");
+    assert_eq!(listing.lines().count(), program.len(), "{listing}");
+    let reassembled = asm::assemble_with(&listing, |kind, name| match (kind, name) {
+        (IdKind::String, "hi there") => Some(EntityId(1)),
+        (IdKind::Method, "#~@0>#foo") => Some(EntityId(2)),
+        (IdKind::String, AWKWARD_STRING) => Some(EntityId(3)),
+        (IdKind::Method, "<lambda>(a, b); \"x\"") => Some(EntityId(4)),
+        (IdKind::LiteralArray, "0x1c4") => Some(EntityId(5)),
+        _ => None,
+    })
+    .unwrap_or_else(|e| panic!("{e}\n{listing}"));
+    let insns = reassembled.emitter.instructions();
+    assert_eq!(insns.len(), program.len());
+    for (got, want) in insns.iter().zip(&program) {
+        assert!(got.semantic_eq(want), "{got} != {want}\n{listing}");
+    }
+}
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use abcd_isa::*;
 
 // --- is_jump ---
@@ -55,6 +57,126 @@ fn is_suspend() {
     assert!(!insn::Return::new().is_suspend());
 }
 
+// --- suspend_points ---
+
+fn suspend_kinds(code: &[Bytecode]) -> Vec<(usize, SuspendKind)> {
+    suspend_points(code)
+        .into_iter()
+        .map(|point| (point.index, point.kind))
+        .collect()
+}
+
+#[test]
+fn generator_yield_is_one_suspend_point() {
+    let code = [
+        insn::Lda::new(Reg(1)),
+        insn::Suspendgenerator::new(Reg(0)),
+        insn::Resumegenerator::new(),
+        insn::Returnundefined::new(),
+    ];
+    assert_eq!(suspend_kinds(&code), [(1, SuspendKind::Generator)]);
+}
+
+#[test]
+fn await_is_counted_once() {
+    let code = [
+        insn::Asyncfunctionawaituncaught::new(Reg(0)),
+        insn::Suspendgenerator::new(Reg(0)),
+        insn::Resumegenerator::new(),
+        insn::Getresumemode::new(),
+        insn::Asyncfunctionawaituncaught::new(Reg(0)),
+        insn::Suspendgenerator::new(Reg(0)),
+        insn::Resumegenerator::new(),
+        insn::Returnundefined::new(),
+    ];
+    assert_eq!(
+        suspend_kinds(&code),
+        [(1, SuspendKind::Await), (5, SuspendKind::Await)]
+    );
+}
+
+#[test]
+fn async_generator_return_is_not_a_suspend_point() {
+    // yield x; return; — the resolve before the return settles the last
+    // next() and nothing resumes after it.
+    let code = [
+        insn::Asyncgeneratorresolve::new(Reg(0), Reg(1), Reg(2)),
+        insn::Suspendgenerator::new(Reg(0)),
+        insn::Resumegenerator::new(),
+        insn::Asyncgeneratorresolve::new(Reg(0), Reg(1), Reg(3)),
+        insn::Return::new(),
+        insn::Lda::new(Reg(1)),
+        insn::Suspendgenerator::new(Reg(0)),
+    ];
+    assert_eq!(
+        suspend_kinds(&code),
+        [
+            (1, SuspendKind::AsyncGenerator),
+            (6, SuspendKind::Generator)
+        ]
+    );
+}
+
+// --- suspend_kind ---
+
+fn row(mnemonic: &str) -> &'static OpcodeInfo {
+    opcode_table()
+        .iter()
+        .find(|r| r.mnemonic == mnemonic)
+        .unwrap()
+}
+
+#[test]
+fn suspend_kind_per_opcode() {
+    assert_eq!(
+        row("suspendgenerator").suspend_kind(),
+        Some(SuspendKind::Generator)
+    );
+    assert_eq!(
+        row("asyncfunctionawaituncaught").suspend_kind(),
+        Some(SuspendKind::Await)
+    );
+    assert_eq!(
+        row("asyncgeneratorresolve").suspend_kind(),
+        Some(SuspendKind::AsyncGenerator)
+    );
+    assert_eq!(row("resumegenerator").suspend_kind(), None);
+    assert_eq!(row("asyncfunctionresolve").suspend_kind(), None);
+}
+
+#[test]
+fn suspending_opcodes_have_a_resume_opcode() {
+    let mut kinds = Vec::new();
+    for info in opcode_table() {
+        let Some(kind) = info.suspend_kind() else {
+            assert!(info.resume_opcode().is_none(), "{}", info.mnemonic);
+            continue;
+        };
+        kinds.push(kind);
+        // An async generator's resolve may settle its return instead.
+        if kind == SuspendKind::AsyncGenerator {
+            assert!(info.resume_opcode().is_none(), "{}", info.mnemonic);
+            continue;
+        }
+        let resume = info.resume_opcode().unwrap();
+        assert_eq!(resume.suspend_kind(), None);
+        assert!(resume.mnemonic.ends_with("resumegenerator"));
+        assert_eq!(
+            resume.mnemonic.starts_with("deprecated."),
+            info.mnemonic.starts_with("deprecated."),
+            "{}",
+            info.mnemonic
+        );
+    }
+    for kind in [
+        SuspendKind::Generator,
+        SuspendKind::Await,
+        SuspendKind::AsyncGenerator,
+    ] {
+        assert!(kinds.contains(&kind), "{kind}");
+    }
+}
+
 // --- is_range ---
 
 #[test]
@@ -133,6 +255,128 @@ fn debug_format() {
     assert_eq!(s, "Bytecode(ldundefined)");
 }
 
+struct Names;
+
+impl fmt::IdResolver for Names {
+    fn resolve(&self, kind: fmt::IdKind, id: EntityId) -> Option<String> {
+        match (kind, id.0) {
+            (fmt::IdKind::String, 1) => Some("hello".into()),
+            (fmt::IdKind::Method, 2) => Some("foo".into()),
+            (fmt::IdKind::LiteralArray, 3) => Some("arr".into()),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn display_with_resolver() {
+    let lda = insn::LdaStr::new(EntityId(1));
+    assert_eq!(format!("{lda}"), "lda.str id:1");
+    {
+        let _guard = fmt::scoped_display_resolver(Arc::new(Names));
+        assert_eq!(format!("{lda}"), "lda.str id:1 (\"hello\")");
+        assert_eq!(
+            format!("{}", insn::Definefunc::new(Imm(0), EntityId(2), Imm(1))),
+            "definefunc 0 id:2 (foo) 1"
+        );
+        assert_eq!(
+            format!("{}", insn::Createarraywithbuffer::new(Imm(0), EntityId(3))),
+            "createarraywithbuffer 0 id:3 (arr)"
+        );
+        // Unknown IDs keep the bare form.
+        assert_eq!(
+            format!("{}", insn::LdaStr::new(EntityId(9))),
+            "lda.str id:9"
+        );
+    }
+    assert_eq!(format!("{lda}"), "lda.str id:1");
+}
+
+#[test]
+fn display_resolver_is_thread_local() {
+    let _guard = fmt::scoped_display_resolver(Arc::new(Names));
+    let other = std::thread::spawn(|| format!("{}", insn::LdaStr::new(EntityId(1))));
+    assert_eq!(other.join().unwrap(), "lda.str id:1");
+}
+
+#[test]
+fn write_formatted_matches_display() {
+    let mut buf = String::from("    ");
+    let add = insn::Add2::new(Imm(5), Reg(3));
+    add.write_formatted(&mut buf).unwrap();
+    assert_eq!(buf, format!("    {add}"));
+
+    buf.clear();
+    let _guard = fmt::scoped_display_resolver(Arc::new(Names));
+    insn::LdaStr::new(EntityId(1))
+        .write_formatted(&mut buf)
+        .unwrap();
+    assert_eq!(buf, "lda.str id:1 (\"hello\")");
+}
+
+fn token_texts(inst: Bytecode) -> Vec<(String, fmt::TokenKind)> {
+    let text = inst.to_string();
+    fmt::tokenize(&inst)
+        .into_iter()
+        .map(|(span, kind)| (text[span.range()].to_string(), kind))
+        .collect()
+}
+
+#[test]
+fn tokenize_classifies_operands() {
+    use fmt::TokenKind as K;
+    assert_eq!(
+        token_texts(insn::Add2::new(Imm(5), Reg(3))),
+        [
+            ("add2".into(), K::Mnemonic),
+            ("5".into(), K::Immediate),
+            ("v3".into(), K::Register)
+        ]
+    );
+    assert_eq!(
+        token_texts(insn::Jeqz::new(Label(2))),
+        [("jeqz".into(), K::Mnemonic), ("label_2".into(), K::Label)]
+    );
+    assert_eq!(
+        token_texts(insn::Ldai::new(Imm(-7))),
+        [("ldai".into(), K::Mnemonic), ("-7".into(), K::Immediate)]
+    );
+}
+
+#[test]
+fn tokenize_marks_resolved_names_as_comments() {
+    use fmt::TokenKind as K;
+    let def = insn::Definefunc::new(Imm(0), EntityId(2), Imm(1));
+    assert_eq!(
+        token_texts(def),
+        [
+            ("definefunc".into(), K::Mnemonic),
+            ("0".into(), K::Immediate),
+            ("id:2".into(), K::Id),
+            ("1".into(), K::Immediate)
+        ]
+    );
+    let _guard = fmt::scoped_display_resolver(Arc::new(Names));
+    assert_eq!(
+        token_texts(def),
+        [
+            ("definefunc".into(), K::Mnemonic),
+            ("0".into(), K::Immediate),
+            ("id:2".into(), K::Id),
+            ("(foo)".into(), K::Comment),
+            ("1".into(), K::Immediate)
+        ]
+    );
+    assert_eq!(
+        token_texts(insn::LdaStr::new(EntityId(1))),
+        [
+            ("lda.str".into(), K::Mnemonic),
+            ("id:1".into(), K::Id),
+            ("(\"hello\")".into(), K::Comment)
+        ]
+    );
+}
+
 // --- jump_label_arg_index ---
 
 #[test]
@@ -265,6 +509,153 @@ fn is_range_extended() {
     assert!(!insn::Callarg0::new(Imm(0)).is_range());
 }
 
+// --- range_args ---
+
+#[test]
+fn range_args_call() {
+    let range = insn::Callrange::new(Imm(7), Imm(3), Reg(10))
+        .range_args()
+        .unwrap();
+    assert_eq!(range.start_reg, Reg(10));
+    assert_eq!(range.count, 3);
+    assert!(!range.includes_this);
+    assert_eq!(
+        range.arg_regs().collect::<Vec<_>>(),
+        [Reg(10), Reg(11), Reg(12)]
+    );
+    assert_eq!(
+        insn::WideCallrange::new(Imm(300), Reg(1))
+            .range_args()
+            .map(|r| r.count),
+        Some(300)
+    );
+}
+
+#[test]
+fn range_args_this_receiver() {
+    // Both encodings count arguments only; the window adds the receiver.
+    for bc in [
+        insn::Callthisrange::new(Imm(0), Imm(2), Reg(4)),
+        insn::WideCallthisrange::new(Imm(2), Reg(4)),
+    ] {
+        let range = bc.range_args().unwrap();
+        assert!(range.includes_this, "{bc}");
+        assert_eq!(range.count, 3, "{bc}");
+        assert_eq!(range.arg_regs().collect::<Vec<_>>(), [Reg(5), Reg(6)]);
+    }
+}
+
+#[test]
+fn range_args_range_1() {
+    let range = insn::Createobjectwithexcludedkeys::new(Imm(1), Reg(0), Reg(5))
+        .range_args()
+        .unwrap();
+    assert_eq!(range.start_reg, Reg(5));
+    assert_eq!(range.count, 2);
+}
+
+#[test]
+fn range_args_non_range() {
+    assert!(insn::Callarg0::new(Imm(0)).range_args().is_none());
+    assert!(insn::Mov::new(Reg(0), Reg(1)).range_args().is_none());
+}
+
+// --- reg_reads / reg_writes ---
+
+#[test]
+fn reg_reads_and_writes() {
+    let mov = insn::Mov::new(Reg(1), Reg(2));
+    assert_eq!(mov.reg_reads(), [Reg(2)]);
+    assert_eq!(mov.reg_writes(), [Reg(1)]);
+    assert!(!mov.reads_acc() && !mov.writes_acc());
+
+    let sta = insn::Sta::new(Reg(3));
+    assert!(sta.reg_reads().is_empty());
+    assert_eq!(sta.reg_writes(), [Reg(3)]);
+    assert!(sta.reads_acc() && !sta.writes_acc());
+
+    let add = insn::Add2::new(Imm(0), Reg(4));
+    assert_eq!(add.reg_reads(), [Reg(4)]);
+    assert!(add.reg_writes().is_empty());
+    assert!(add.reads_acc() && add.writes_acc());
+
+    let ld = insn::Lda::new(Reg(5));
+    assert_eq!(ld.reg_reads(), [Reg(5)]);
+    assert!(!ld.reads_acc() && ld.writes_acc());
+}
+
+#[test]
+fn reg_reads_expand_range_windows() {
+    assert_eq!(
+        insn::Callthisrange::new(Imm(0), Imm(1), Reg(4)).reg_reads(),
+        [Reg(4), Reg(5)]
+    );
+    assert_eq!(
+        insn::Createobjectwithexcludedkeys::new(Imm(1), Reg(0), Reg(5)).reg_reads(),
+        [Reg(0), Reg(5), Reg(6)]
+    );
+    // An empty window reads nothing, not even its first register.
+    assert!(
+        insn::Callrange::new(Imm(0), Imm(0), Reg(7))
+            .reg_reads()
+            .is_empty()
+    );
+}
+
+// --- call_info ---
+
+#[test]
+fn call_info_per_family() {
+    let call = insn::Callargs2::new(Imm(0), Reg(1), Reg(2))
+        .call_info()
+        .unwrap();
+    assert_eq!(call.kind, CallKind::Arg);
+    assert_eq!(call.args, CallArgs::Fixed { first: 1, count: 2 });
+    assert_eq!(call.this_operand, None);
+
+    let range = insn::WideCallthisrange::new(Imm(2), Reg(4))
+        .call_info()
+        .unwrap();
+    assert_eq!(range.kind, CallKind::Range);
+    assert_eq!(range.args, CallArgs::Range { count: 0, start: 1 });
+    assert_eq!(range.this_operand, Some(1));
+
+    assert_eq!(
+        insn::Supercallspread::new(Imm(0), Reg(3)).call_kind(),
+        Some(CallKind::Super)
+    );
+    assert_eq!(
+        insn::Newobjrange::new(Imm(0), Imm(1), Reg(0)).call_kind(),
+        None
+    );
+    assert_eq!(insn::Lda::new(Reg(0)).call_kind(), None);
+}
+
+#[test]
+fn call_info_points_at_the_right_operands() {
+    for info in opcode_table() {
+        let Some(call) = info.call_info() else {
+            continue;
+        };
+        let kind = |i: usize| info.operands[i].kind;
+        match call.args {
+            CallArgs::Fixed { first, count } => {
+                assert_eq!(first + count, info.operands.len(), "{}", info.mnemonic);
+                assert!((first..first + count).all(|i| kind(i) == OperandKind::Reg));
+            }
+            CallArgs::Range { count, start } => {
+                assert_eq!(kind(count), OperandKind::Imm, "{}", info.mnemonic);
+                assert_eq!(kind(start), OperandKind::Reg, "{}", info.mnemonic);
+                assert!(info.template.range_args().is_some(), "{}", info.mnemonic);
+            }
+            CallArgs::Spread { operand } => assert_eq!(kind(operand), OperandKind::Reg),
+        }
+        if let Some(this) = call.this_operand {
+            assert_eq!(kind(this), OperandKind::Reg, "{}", info.mnemonic);
+        }
+    }
+}
+
 // --- set_label ---
 
 #[test]
@@ -283,3 +674,336 @@ fn set_label_noop_on_non_jump() {
     let (_, _, num_args) = ld.emit_args();
     assert_eq!(num_args, 0, "set_label on non-jump should be a no-op");
 }
+
+// --- cost_class ---
+
+#[test]
+fn cost_class_per_instruction() {
+    assert_eq!(insn::Lda::new(Reg(0)).cost_class(), CostClass::Cheap);
+    assert_eq!(insn::Jmp::new(Label(0)).cost_class(), CostClass::Cheap);
+    assert_eq!(
+        insn::Add2::new(Imm(0), Reg(0)).cost_class(),
+        CostClass::Moderate
+    );
+    assert_eq!(
+        insn::Ldobjbyname::new(Imm(0), EntityId(0)).cost_class(),
+        CostClass::Moderate
+    );
+    assert_eq!(
+        insn::Createemptyobject::new().cost_class(),
+        CostClass::Alloc
+    );
+    assert_eq!(
+        insn::WideNewlexenv::new(Imm(0)).cost_class(),
+        CostClass::Alloc
+    );
+    assert_eq!(
+        insn::Callarg1::new(Imm(0), Reg(0)).cost_class(),
+        CostClass::Call
+    );
+    assert_eq!(
+        insn::Suspendgenerator::new(Reg(0)).cost_class(),
+        CostClass::Suspend
+    );
+}
+
+#[test]
+fn cost_class_wide_matches_narrow() {
+    let table = opcode_table();
+    for wide in table.iter().filter(|i| i.mnemonic.starts_with("wide.")) {
+        let narrow = &wide.mnemonic["wide.".len()..];
+        if let Some(narrow) = table.iter().find(|i| i.mnemonic == narrow) {
+            assert_eq!(wide.cost_class(), narrow.cost_class(), "{}", wide.mnemonic);
+        }
+    }
+}
+
+#[test]
+fn cost_estimate_weights_counts() {
+    let code = [
+        insn::Lda::new(Reg(0)),
+        insn::Callarg1::new(Imm(0), Reg(1)),
+        insn::Callarg1::new(Imm(1), Reg(1)),
+        insn::Returnundefined::new(),
+    ];
+    let estimate = CostEstimate::of(&code);
+    assert_eq!(estimate.count(CostClass::Call), 2);
+    assert_eq!(estimate.count(CostClass::Cheap), 2);
+    assert_eq!(
+        estimate.total(),
+        u64::from(2 * CostClass::Call.weight() + 2 * CostClass::Cheap.weight())
+    );
+}
+
+// --- id_kind / typed_id ---
+
+#[test]
+fn typed_id_per_operand() {
+    let class = insn::Defineclasswithbuffer::new(Imm(0), EntityId(7), EntityId(8), Imm(0), Reg(0));
+    assert_eq!(class.typed_id(0), Some(TypedEntityRef::Method(EntityId(7))));
+    assert_eq!(
+        class.typed_id(1),
+        Some(TypedEntityRef::LiteralArray(EntityId(8)))
+    );
+    assert_eq!(class.typed_id(2), None);
+    assert_eq!(
+        insn::LdaStr::new(EntityId(1)).id_kind(0),
+        Some(IdKind::String)
+    );
+    assert_eq!(
+        insn::Definefunc::new(Imm(0), EntityId(2), Imm(1)).id_kind(0),
+        Some(IdKind::Method)
+    );
+    assert_eq!(insn::Ldai::new(Imm(1)).id_kind(0), None);
+}
+
+#[test]
+fn typed_entity_ref_accessors() {
+    let id = TypedEntityRef::LiteralArray(EntityId(3));
+    assert_eq!(id.kind(), IdKind::LiteralArray);
+    assert_eq!(id.id(), EntityId(3));
+}
+
+/// The generated table agrees with the bridge's `IsIdMatchFlag`.
+#[test]
+fn id_kind_matches_bridge() {
+    for row in opcode_table() {
+        let mut bytes = vec![0u8; usize::from(row.size)];
+        bytes[0] = row.opcode as u8;
+        if row.opcode > 0xff {
+            bytes[1] = (row.opcode >> 8) as u8;
+        }
+        let ids = row
+            .operands
+            .iter()
+            .filter(|d| d.kind == OperandKind::Id)
+            .count();
+        for idx in 0..ids {
+            let ptr = bytes.as_ptr();
+            let bridge = unsafe {
+                if abcd_isa_sys::isa_is_id_string(ptr, idx) != 0 {
+                    Some(IdKind::String)
+                } else if abcd_isa_sys::isa_is_id_method(ptr, idx) != 0 {
+                    Some(IdKind::Method)
+                } else if abcd_isa_sys::isa_is_id_literal_array(ptr, idx) != 0 {
+                    Some(IdKind::LiteralArray)
+                } else {
+                    None
+                }
+            };
+            assert_eq!(row.id_kind(idx), bridge, "{} id {idx}", row.mnemonic);
+        }
+        assert_eq!(row.id_kind(ids), None, "{}", row.mnemonic);
+    }
+}
+
+// --- category ---
+
+#[test]
+fn category_per_instruction() {
+    assert_eq!(insn::Lda::new(Reg(0)).category(), OpcodeCategory::Load);
+    assert_eq!(insn::Sta::new(Reg(0)).category(), OpcodeCategory::Store);
+    assert_eq!(
+        insn::Add2::new(Imm(0), Reg(0)).category(),
+        OpcodeCategory::Arithmetic
+    );
+    assert_eq!(
+        insn::Stricteq::new(Imm(0), Reg(0)).category(),
+        OpcodeCategory::Compare
+    );
+    assert_eq!(insn::Jmp::new(Label(0)).category(), OpcodeCategory::Jump);
+    assert_eq!(
+        insn::Returnundefined::new().category(),
+        OpcodeCategory::Jump
+    );
+    assert_eq!(
+        insn::Callarg1::new(Imm(0), Reg(0)).category(),
+        OpcodeCategory::Call
+    );
+    assert_eq!(
+        insn::Ldobjbyname::new(Imm(0), EntityId(0)).category(),
+        OpcodeCategory::ObjectOp
+    );
+    assert_eq!(
+        insn::Getiterator::new(Imm(0)).category(),
+        OpcodeCategory::IteratorOp
+    );
+    assert_eq!(
+        insn::Ldexternalmodulevar::new(Imm(0)).category(),
+        OpcodeCategory::ModuleOp
+    );
+    assert_eq!(
+        insn::ThrowNotexists::new().category(),
+        OpcodeCategory::ExceptionOp
+    );
+    assert_eq!(
+        insn::Suspendgenerator::new(Reg(0)).category(),
+        OpcodeCategory::Async
+    );
+    assert_eq!(insn::Nop::new().category(), OpcodeCategory::Misc);
+}
+
+#[test]
+fn category_ignores_prefixes() {
+    let table = opcode_table();
+    for info in table {
+        let base = ["wide.", "deprecated.", "callruntime."]
+            .iter()
+            .find_map(|p| info.mnemonic.strip_prefix(p));
+        let Some(base) = base else {
+            continue;
+        };
+        if let Some(plain) = table.iter().find(|i| i.mnemonic == base) {
+            assert_eq!(info.category(), plain.category(), "{}", info.mnemonic);
+        }
+    }
+}
+
+#[test]
+fn category_misc_is_small() {
+    // A new ISA mnemonic lands in `Misc` until the tables learn about it.
+    let misc: Vec<&str> = opcode_table()
+        .iter()
+        .filter(|i| i.category() == OpcodeCategory::Misc)
+        .map(|i| i.mnemonic)
+        .collect();
+    for mnemonic in &misc {
+        assert!(
+            mnemonic.contains("lexenv")
+                || mnemonic.contains("sendableenv")
+                || ["nop", "debugger", "callruntime.notifyconcurrentresult"].contains(mnemonic),
+            "{mnemonic} is uncategorized"
+        );
+    }
+}
+
+// --- prefix_group ---
+
+#[test]
+fn prefix_group_per_instruction() {
+    assert_eq!(insn::Lda::new(Reg(0)).prefix_group(), None);
+    assert_eq!(insn::Throw::new().prefix_group(), Some(PrefixGroup::Throw));
+    assert_eq!(
+        insn::WideLdlexvar::new(Imm(0), Imm(0)).prefix_group(),
+        Some(PrefixGroup::Wide)
+    );
+    assert_eq!(
+        insn::DeprecatedLdlexenv::new().prefix_group(),
+        Some(PrefixGroup::Deprecated)
+    );
+    assert_eq!(
+        insn::CallruntimeNotifyconcurrentresult::new().prefix_group(),
+        Some(PrefixGroup::CallRuntime)
+    );
+}
+
+#[test]
+fn prefix_group_matches_mnemonic_prefix() {
+    for info in opcode_table() {
+        let expected = match info.mnemonic.split_once('.') {
+            Some((prefix, _)) => PrefixGroup::ALL.into_iter().find(|g| g.name() == prefix),
+            None if info.mnemonic == "throw" => Some(PrefixGroup::Throw),
+            None => None,
+        };
+        assert_eq!(info.prefix_group(), expected, "{}", info.mnemonic);
+        assert_eq!(info.template.prefix_group(), expected, "{}", info.mnemonic);
+    }
+}
+
+#[test]
+fn prefix_group_opcodes_partition_prefixed_rows() {
+    let prefixed = opcode_table()
+        .iter()
+        .filter(|i| i.prefix_group().is_some())
+        .count();
+    let mut total = 0;
+    for group in PrefixGroup::ALL {
+        assert_eq!(
+            PrefixGroup::from_prefix_byte(group.prefix_byte()),
+            Some(group)
+        );
+        for info in group.opcodes() {
+            assert_eq!(info.opcode as u8, group.prefix_byte(), "{}", info.mnemonic);
+            total += 1;
+        }
+    }
+    assert_eq!(total, prefixed);
+    assert!(
+        PrefixGroup::ALL
+            .iter()
+            .all(|g| g.opcodes().next().is_some())
+    );
+}
+
+#[test]
+fn opcodes_with_prefix_follows_the_byte() {
+    for group in PrefixGroup::ALL {
+        let by_byte: Vec<u16> = opcodes_with_prefix(group.prefix_byte())
+            .map(|i| i.opcode)
+            .collect();
+        let by_group: Vec<u16> = group.opcodes().map(|i| i.opcode).collect();
+        assert_eq!(by_byte, by_group, "{group}");
+    }
+    assert_eq!(opcodes_with_prefix(0x00).count(), 0);
+    assert_eq!(opcodes_with_prefix(0xff).count(), 0);
+}
+
+#[test]
+fn namespaces_split_the_opcode_table() {
+    let ecmascript: Vec<&str> = opcode_table_for_namespace("ecmascript")
+        .map(|i| i.mnemonic)
+        .collect();
+    let core: Vec<&str> = opcode_table_for_namespace("core")
+        .map(|i| i.mnemonic)
+        .collect();
+    assert_eq!(ecmascript.len() + core.len(), opcode_table().len());
+    assert!(ecmascript.contains(&"callruntime.istrue"));
+    assert!(ecmascript.contains(&"return"));
+    assert!(core.contains(&"mov"));
+    assert!(core.contains(&"jmp"));
+    assert_eq!(opcode_table_for_namespace("panda").count(), 0);
+}
+
+#[test]
+fn format_with_puts_names_in_place_of_ids() {
+    let lda = insn::LdaStr::new(EntityId(1));
+    assert_eq!(lda.format_with(&Names).to_string(), "lda.str \"hello\"");
+    assert_eq!(
+        insn::Definefunc::new(Imm(0), EntityId(2), Imm(1))
+            .format_with(&Names)
+            .to_string(),
+        "definefunc 0 @foo 1"
+    );
+    assert_eq!(
+        insn::LdaStr::new(EntityId(9))
+            .format_with(&Names)
+            .to_string(),
+        "lda.str id:9"
+    );
+    // Everything else prints as `Display` does, installed resolver or not.
+    let _guard = fmt::scoped_display_resolver(Arc::new(Names));
+    let add = insn::Add2::new(Imm(5), Reg(3));
+    assert_eq!(add.format_with(&Names).to_string(), add.to_string());
+    assert_eq!(lda.format_with(&Names).to_string(), "lda.str \"hello\"");
+}
+
+#[test]
+fn flags_print_their_names() {
+    let flags = BytecodeFlag::JUMP | BytecodeFlag::CONDITIONAL;
+    assert_eq!(flags.to_string(), "JUMP|CONDITIONAL");
+    let set: Vec<_> = flags.iter_set().collect();
+    assert_eq!(
+        set,
+        [
+            (BytecodeFlag::JUMP, "JUMP"),
+            (BytecodeFlag::CONDITIONAL, "CONDITIONAL")
+        ]
+    );
+    assert_eq!(BytecodeFlag::empty().to_string(), "0");
+    let stray = BytecodeFlag::JUMP | BytecodeFlag::from_bits_retain(1 << 31);
+    assert_eq!(stray.to_string(), "JUMP|0x80000000");
+
+    let ex = ExceptionType::X_NULL | ExceptionType::X_BOUNDS;
+    assert_eq!(ex.to_string(), "X_NULL|X_BOUNDS");
+    assert_eq!(ex.iter_set().count(), 2);
+}
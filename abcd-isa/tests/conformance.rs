@@ -0,0 +1,45 @@
+//! The generated conformance vectors, one per `isa.yaml` encoding.
+//!
+//! `isa.yaml` ships no example encodings, so the vectors are built from each
+//! format at build time (see `templates/conformance.rs.erb`). Every decoder
+//! and the encoder must agree with them byte for byte.
+
+use abcd_isa::*;
+use abcd_isa_sys::conformance::VECTORS;
+
+#[test]
+fn vectors_cover_the_opcode_table() {
+    let table = opcode_table();
+    assert_eq!(VECTORS.len(), table.len());
+    for (v, row) in VECTORS.iter().zip(table) {
+        assert_eq!(v.opcode, row.opcode, "{}", v.mnemonic);
+        assert_eq!(v.mnemonic, row.mnemonic);
+        assert_eq!(v.bytes.len(), usize::from(row.size), "{}", v.mnemonic);
+    }
+}
+
+#[test]
+fn vectors_decode() {
+    for v in &VECTORS {
+        for decoded in [decode(v.bytes), decode_pure(v.bytes)] {
+            let insns = decoded.unwrap_or_else(|e| panic!("{}: {e}", v.mnemonic));
+            assert_eq!(insns.len(), 1, "{}", v.mnemonic);
+            assert_eq!(insns[0].0.mnemonic(), v.mnemonic);
+            assert_eq!(insns[0].0.to_string(), v.text);
+        }
+    }
+}
+
+#[test]
+fn vectors_encode() {
+    for v in &VECTORS {
+        let (inst, _) = decode_pure(v.bytes).unwrap()[0];
+        // A self-jump always fits the narrowest offset, whatever format
+        // the vector was built from.
+        if inst.is_jump() {
+            continue;
+        }
+        let (bytes, _) = encode(&[inst]).unwrap_or_else(|e| panic!("{}: {e}", v.mnemonic));
+        assert_eq!(bytes, v.bytes, "{}", v.text);
+    }
+}
@@ -0,0 +1,142 @@
+//! `decode_pure` reads the same instructions as the bridge-backed `decode`.
+
+use abcd_isa::*;
+
+/// Every encoding in the table, with operand bytes `fill`.
+fn each_encoding(fill: u8) -> impl Iterator<Item = (&'static OpcodeInfo, Vec<u8>)> {
+    opcode_table().iter().map(move |row| {
+        let mut bytes = vec![fill; usize::from(row.size)];
+        let opcode = row.opcode.to_le_bytes();
+        let len = if row.opcode > 0xff { 2 } else { 1 };
+        bytes[..len].copy_from_slice(&opcode[..len]);
+        (row, bytes)
+    })
+}
+
+#[test]
+fn every_encoding_matches_decode() {
+    for fill in [0x00, 0x5a, 0xff] {
+        for (row, bytes) in each_encoding(fill) {
+            // A jump operand would point outside a one-instruction method.
+            if row.template.is_jump() && fill != 0 {
+                continue;
+            }
+            let pure = decode_pure(&bytes).unwrap_or_else(|e| panic!("{}: {e}", row.mnemonic));
+            let bridged = decode(&bytes).unwrap();
+            assert_eq!(pure.len(), 1, "{}", row.mnemonic);
+            assert_eq!(pure[0].1, bridged[0].1);
+            assert_eq!(
+                pure[0].0.emit_args(),
+                bridged[0].0.emit_args(),
+                "{} {bytes:02x?}",
+                row.mnemonic
+            );
+        }
+    }
+}
+
+#[test]
+fn signed_operands_are_sign_extended() {
+    // ldai -2; ldlexvar 15, 15 (unsigned 4-bit)
+    let decoded = decode_pure(&[0x62, 0xfe, 0xff, 0xff, 0xff, 0x3c, 0xff]).unwrap();
+    assert_eq!(
+        decoded[0].0.emit_args(),
+        insn::Ldai::new(Imm(-2)).emit_args()
+    );
+    assert_eq!(
+        decoded[1].0.emit_args(),
+        insn::Ldlexvar::new(Imm(15), Imm(15)).emit_args()
+    );
+}
+
+#[test]
+fn jumps_resolve_to_instruction_indices() {
+    // ldundefined; jmp -1; jmp -3
+    let decoded = decode_pure(&[0x00, 0x4d, 0xff, 0x4d, 0xfd]).unwrap();
+    let offsets: Vec<u32> = decoded.iter().map(|&(_, off)| off).collect();
+    assert_eq!(offsets, [0, 1, 3]);
+    assert_eq!(
+        decoded[1].0.emit_args(),
+        insn::Jmp::new(Label(0)).emit_args()
+    );
+    assert_eq!(
+        decoded[2].0.emit_args(),
+        insn::Jmp::new(Label(0)).emit_args()
+    );
+}
+
+#[test]
+fn errors_match_decode() {
+    let cases: [&[u8]; 4] = [
+        // Prefix byte with nothing after it.
+        &[0x00, 0xfd],
+        // ldai cut short.
+        &[0x62, 0x01],
+        // jmp into the middle of itself.
+        &[0x4d, 0x01],
+        // jmp before the start.
+        &[0x4d, 0x80],
+    ];
+    for bytes in cases {
+        assert_eq!(
+            decode_pure(bytes).unwrap_err(),
+            decode(bytes).unwrap_err(),
+            "{bytes:02x?}"
+        );
+    }
+}
+
+#[test]
+fn unknown_opcode_is_invalid() {
+    let unused = (0..=u8::MAX)
+        .find(|&b| opcode_table().iter().all(|row| row.opcode as u8 != b))
+        .unwrap();
+    assert_eq!(
+        decode_pure(&[0x00, unused, 0x00, 0x00]).unwrap_err(),
+        DecodeError::InvalidOpcode(1)
+    );
+}
+
+#[test]
+fn from_args_inverts_emit_args() {
+    for row in opcode_table() {
+        let (_, args, n) = row.template.emit_args();
+        let bc = Bytecode::from_args(row.opcode, &args[..n]).unwrap();
+        assert_eq!(bc.emit_args(), row.template.emit_args(), "{}", row.mnemonic);
+        assert!(Bytecode::from_args(row.opcode, &[0; 9]).is_none());
+    }
+}
+
+#[test]
+fn decode_all_matches_decode() {
+    // ldundefined; ldai -2; jmp -6; wide.ldlexvar 256, 1
+    let bytes = [
+        0x00, 0x62, 0xfe, 0xff, 0xff, 0xff, 0x4d, 0xfa, 0xfd, 0x0c, 0x00, 0x01, 0x01, 0x00,
+    ];
+    let all = decode_all(&bytes).unwrap();
+    let decoded = decode(&bytes).unwrap();
+    assert_eq!(all.len(), decoded.len());
+    for (inst, &(bc, offset)) in all.iter().zip(&decoded) {
+        assert_eq!(inst.bc, bc);
+        assert_eq!(inst.offset, offset);
+        assert_eq!(inst.info.mnemonic, bc.mnemonic());
+        assert_eq!(inst.operands().len(), inst.info.operands.len());
+    }
+    let sizes: Vec<u8> = all.iter().map(|inst| inst.size).collect();
+    assert_eq!(sizes, [1, 5, 2, 6]);
+    assert_eq!(all[3].next_offset() as usize, bytes.len());
+    assert_eq!(all[1].operands(), [-2]);
+    assert_eq!(all[2].operands(), [-6]);
+    assert_eq!(all[3].operands(), [256, 1]);
+}
+
+#[test]
+fn decode_all_errors_match_decode() {
+    for bytes in [&[0x00, 0xfd][..], &[0x62, 0x01], &[0x4d, 0x01]] {
+        assert_eq!(
+            decode_all(bytes).unwrap_err(),
+            decode(bytes).unwrap_err(),
+            "{bytes:02x?}"
+        );
+    }
+}
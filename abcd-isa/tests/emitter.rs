@@ -0,0 +1,310 @@
+use abcd_isa::*;
+
+#[test]
+fn build_resolves_labels_to_instructions() {
+    let mut e = Emitter::new();
+    let end = e.create_label();
+    e.emit(insn::Jmp::new(end));
+    e.emit(insn::Ldundefined::new());
+    e.bind(end);
+    e.emit(insn::Returnundefined::new());
+
+    let expected = encode(&[
+        insn::Jmp::new(Label(2)),
+        insn::Ldundefined::new(),
+        insn::Returnundefined::new(),
+    ])
+    .unwrap();
+    assert_eq!(e.build().unwrap(), expected);
+}
+
+#[test]
+fn unbound_label_is_an_error() {
+    let mut e = Emitter::new();
+    let nowhere = e.create_label();
+    e.emit(insn::Jmp::new(nowhere));
+    assert!(matches!(e.build(), Err(EncodeError::UnboundLabel(0))));
+}
+
+#[test]
+fn restore_drops_later_instructions_and_labels() {
+    let mut e = Emitter::new();
+    let kept = e.create_label();
+    e.emit(insn::Ldtrue::new());
+    let before = e.snapshot();
+
+    let dropped = e.create_label();
+    e.emit(insn::Jnez::new(dropped));
+    e.bind(kept);
+    e.bind(dropped);
+    e.emit(insn::Ldundefined::new());
+    e.restore(before);
+
+    assert_eq!(e.len(), 1);
+    assert!(e.instructions()[0].semantic_eq(&insn::Ldtrue::new()));
+    // `dropped` is gone, so the next label reuses its number, and `kept`
+    // can be bound again.
+    assert_eq!(e.create_label(), dropped);
+    e.bind(kept);
+}
+
+#[test]
+fn restore_matches_a_clone() {
+    let mut e = Emitter::new();
+    let top = e.create_label();
+    e.bind(top);
+    e.emit(insn::Ldfalse::new());
+    let copy = e.clone();
+    let before = e.snapshot();
+    e.emit(insn::Jmp::new(top));
+    e.restore(before);
+    assert_eq!(e.snapshot(), copy.snapshot());
+    assert_eq!(
+        format!("{:?}", e.instructions()),
+        format!("{:?}", copy.instructions())
+    );
+}
+
+#[test]
+fn snapshots_nest() {
+    let mut e = Emitter::new();
+    let outer = e.snapshot();
+    e.emit(insn::Ldtrue::new());
+    let inner = e.snapshot();
+    e.emit(insn::Ldfalse::new());
+    e.restore(inner);
+    assert_eq!(e.len(), 1);
+    e.restore(outer);
+    assert!(e.is_empty());
+}
+
+#[test]
+#[should_panic(expected = "already bound")]
+fn binding_twice_panics() {
+    let mut e = Emitter::new();
+    let l = e.create_label();
+    e.bind(l);
+    e.bind(l);
+}
+
+#[test]
+fn mov_auto_widens_with_the_registers() {
+    let mut e = Emitter::new();
+    e.mov_auto(Reg(1), Reg(2));
+    e.mov_auto(Reg(1), Reg(200));
+    e.mov_auto(Reg(300), Reg(2));
+    let (_, offsets) = e.build().unwrap();
+    assert_eq!(offsets, [0, 2, 5]);
+}
+
+#[test]
+fn jmp_auto_widens_with_the_distance() {
+    let mut e = Emitter::new();
+    let near = e.create_label();
+    let far = e.create_label();
+    e.jmp_auto(near);
+    e.bind(near);
+    e.jmp_auto(far);
+    for _ in 0..200 {
+        e.emit(insn::Ldundefined::new());
+    }
+    e.bind(far);
+    e.emit(insn::Returnundefined::new());
+    let (bytes, offsets) = e.build().unwrap();
+    assert_eq!(&offsets[..3], [0, 2, 5]);
+    assert_eq!(bytes[0], 0x4d);
+    assert_eq!(bytes[2], 0x4e);
+}
+
+#[test]
+fn emit_auto_records_the_wide_form() {
+    let mut e = Emitter::new();
+    let narrow = e.emit_auto(insn::Ldobjbyindex::new(Imm(0), Imm(7)));
+    assert_eq!(narrow.mnemonic(), "ldobjbyindex");
+    let wide = e.emit_auto(insn::Ldobjbyindex::new(Imm(1), Imm(1 << 20)));
+    assert_eq!(wide.mnemonic(), "wide.ldobjbyindex");
+    assert!(e.instructions()[1].semantic_eq(&wide));
+    // Only the narrow form indexes IC slots (two, from slot 0).
+    assert_eq!(ic_slot_count(e.instructions()), 2);
+    assert_eq!(e.build().unwrap(), encode(e.instructions()).unwrap());
+}
+
+#[test]
+fn pc_and_label_offsets_match_the_build() {
+    let mut e = Emitter::new();
+    let handler = e.create_label();
+    let done = e.create_label();
+    let try_start = e.pc().unwrap();
+    e.mov_auto(Reg(1), Reg(300));
+    e.emit(insn::Ldai::new(Imm(7)));
+    e.emit_auto(insn::Ldobjbyindex::new(Imm(0), Imm(1 << 20)));
+    e.jmp_auto(done);
+    for _ in 0..200 {
+        e.emit(insn::Ldundefined::new());
+    }
+    e.bind(done);
+    let try_end = e.pc().unwrap();
+    assert_eq!(e.label_offset(handler).unwrap(), None);
+    e.bind(handler);
+    e.emit(insn::Returnundefined::new());
+
+    let (_, offsets) = e.build().unwrap();
+    assert_eq!(try_start, 0);
+    assert_eq!(try_end, offsets[204] as usize);
+    assert_eq!(e.label_offset(done).unwrap(), Some(try_end));
+    assert_eq!(e.label_offset(handler).unwrap(), Some(try_end));
+    assert_eq!(e.pc().unwrap(), try_end + 1);
+    assert_eq!(e.instruction_count(), 205);
+}
+
+#[test]
+fn pc_counts_pending_forward_jumps_as_narrow() {
+    let mut e = Emitter::new();
+    let later = e.create_label();
+    e.jmp_auto(later);
+    assert_eq!(e.pc().unwrap(), 2);
+    for _ in 0..200 {
+        e.emit(insn::Ldundefined::new());
+    }
+    // The jump now skips 200 bytes and needs a 16-bit offset.
+    assert_eq!(e.pc().unwrap(), 203);
+    e.bind(later);
+    e.emit(insn::Returnundefined::new());
+    assert_eq!(e.label_offset(later).unwrap(), Some(203));
+}
+
+#[test]
+fn pc_fails_where_build_does_and_follows_restore() {
+    let mut e = Emitter::new();
+    e.emit(insn::Ldundefined::new());
+    let good = e.snapshot();
+    assert_eq!(e.pc().unwrap(), 1);
+    e.emit(insn::Lda::new(Reg(256)));
+    assert!(
+        matches!(e.pc(), Err(EncodeError::OperandOutOfRange { index: 1, .. })),
+        "{:?}",
+        e.pc()
+    );
+    e.restore(good);
+    assert_eq!(e.pc().unwrap(), 1);
+    e.emit(insn::Lda::new(Reg(255)));
+    assert_eq!(e.pc().unwrap(), 3);
+}
+
+#[test]
+fn emit_raw_copies_code_with_its_jumps() {
+    let (original, _) = encode(&[
+        insn::Jmp::new(Label(2)),
+        insn::Ldundefined::new(),
+        insn::Returnundefined::new(),
+    ])
+    .unwrap();
+    let mut e = Emitter::new();
+    e.emit(insn::Ldtrue::new());
+    assert_eq!(e.emit_raw(&original).unwrap(), 3);
+    e.emit(insn::Returnundefined::new());
+
+    let expected = encode(&[
+        insn::Ldtrue::new(),
+        insn::Jmp::new(Label(3)),
+        insn::Ldundefined::new(),
+        insn::Returnundefined::new(),
+        insn::Returnundefined::new(),
+    ])
+    .unwrap();
+    assert_eq!(e.build().unwrap(), expected);
+}
+
+#[test]
+fn emit_raw_rejects_partial_code() {
+    let (ldai, _) = encode(&[insn::Ldai::new(Imm(7))]).unwrap();
+    let (jump, _) = encode(&[insn::Jmp::new(Label(1)), insn::Ldundefined::new()]).unwrap();
+    let mut e = Emitter::new();
+    assert_eq!(
+        e.emit_raw(&ldai[..ldai.len() - 1]),
+        Err(DecodeError::Truncated(0))
+    );
+    // The jump's target is not part of the copy.
+    assert!(matches!(
+        e.emit_raw(&jump[..2]),
+        Err(DecodeError::InvalidJumpTarget { offset: 0, .. })
+    ));
+    assert!(e.is_empty());
+    assert_eq!(e.create_label(), Label(0));
+}
+
+#[test]
+fn emit_inst_appends_a_patched_instruction() {
+    // mov v1, v2
+    let mut inst = InstBuf::new(&[0x44, 0x21]).unwrap();
+    inst.set_vreg(1, Reg(7)).unwrap();
+    let mut e = Emitter::new();
+    let bc = e.emit_inst(&inst).unwrap();
+    assert!(bc.semantic_eq(&insn::Mov::new(Reg(1), Reg(7))));
+    assert_eq!(e.build().unwrap().0, inst.as_bytes());
+}
+
+#[test]
+fn build_optimized_threads_jumps_and_drops_dead_code() {
+    let mut e = Emitter::new();
+    let hop = e.create_label();
+    let end = e.create_label();
+    e.emit(insn::Ldtrue::new());
+    e.emit(insn::Jnez::new(hop));
+    e.emit(insn::Returnundefined::new());
+    e.bind(hop);
+    e.emit(insn::Jmp::new(end));
+    e.emit(insn::Ldundefined::new());
+    e.bind(end);
+    e.emit(insn::Returnundefined::new());
+
+    let expected = encode(&[
+        insn::Ldtrue::new(),
+        insn::Jnez::new(Label(4)),
+        insn::Returnundefined::new(),
+        // Still bound to `hop`, so kept.
+        insn::Jmp::new(Label(4)),
+        insn::Returnundefined::new(),
+    ])
+    .unwrap();
+    let (bytes, offsets) = e.build_optimized().unwrap();
+    assert_eq!(bytes, expected.0);
+    assert_eq!(offsets.len(), e.instruction_count());
+    assert_eq!(offsets[4], offsets[5]);
+    assert_eq!(offsets[5], expected.1[4]);
+}
+
+#[test]
+fn build_optimized_drops_accumulator_round_trips() {
+    let mut e = Emitter::new();
+    let entry = e.create_label();
+    e.emit(insn::Lda::new(Reg(1)));
+    e.emit(insn::Sta::new(Reg(1)));
+    e.emit(insn::Sta::new(Reg(2)));
+    e.emit(insn::Lda::new(Reg(2)));
+    e.bind(entry);
+    e.emit(insn::Lda::new(Reg(2)));
+    e.emit(insn::Return::new());
+
+    let expected = encode(&[
+        insn::Lda::new(Reg(1)),
+        insn::Sta::new(Reg(2)),
+        insn::Lda::new(Reg(2)),
+        insn::Return::new(),
+    ])
+    .unwrap();
+    assert_eq!(e.build_optimized().unwrap().0, expected.0);
+}
+
+#[test]
+fn build_optimized_maps_dropped_tail_to_the_end() {
+    let mut e = Emitter::new();
+    e.emit(insn::Returnundefined::new());
+    e.emit(insn::Ldundefined::new());
+    e.emit(insn::Returnundefined::new());
+    let (bytes, offsets) = e.build_optimized().unwrap();
+    assert_eq!(bytes.len(), 1);
+    assert_eq!(offsets, [0, 1, 1]);
+    // The plain build is untouched.
+    assert_eq!(e.build().unwrap().1.len(), 3);
+}
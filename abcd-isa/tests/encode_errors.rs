@@ -67,3 +67,53 @@ fn encode_error_display() {
         "LabelOutOfBounds message: {msg}"
     );
 }
+
+#[test]
+fn encode_rejects_operands_no_format_holds() {
+    let err = encode(&[insn::Ldundefined::new(), insn::Lda::new(Reg(256))]).unwrap_err();
+    assert!(
+        matches!(
+            err,
+            EncodeError::OperandOutOfRange {
+                index: 1,
+                mnemonic: "lda",
+                operand: 0,
+                value: 256,
+                bits: 8,
+            }
+        ),
+        "{err}"
+    );
+
+    // `wide.newlexenv` holds what `newlexenv` cannot.
+    assert!(encode(&[insn::Newlexenv::new(Imm(256))]).is_ok());
+    let err = encode(&[insn::Newlexenv::new(Imm(1 << 16))]).unwrap_err();
+    assert!(matches!(
+        err,
+        EncodeError::OperandOutOfRange {
+            mnemonic: "wide.newlexenv",
+            bits: 16,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn emitter_reports_out_of_range_operands_at_build() {
+    let mut e = Emitter::new();
+    e.emit(insn::Ldai::new(Imm(i64::from(i32::MIN))));
+    e.emit(insn::Ldai::new(Imm(i64::from(i32::MAX) + 1)));
+    let err = e.build().unwrap_err();
+    assert!(
+        matches!(
+            err,
+            EncodeError::OperandOutOfRange {
+                index: 1,
+                bits: 32,
+                ..
+            }
+        ),
+        "{err}"
+    );
+    assert!(err.to_string().contains("does not fit in 32 bits"), "{err}");
+}
@@ -0,0 +1,167 @@
+use abcd_isa::*;
+
+#[test]
+fn copies_one_instruction() {
+    // mov v1, v2; returnundefined
+    let inst = InstBuf::new(&[0x44, 0x21, 0x65]).unwrap();
+    assert_eq!(inst.as_bytes(), [0x44, 0x21]);
+    assert_eq!(inst.info().mnemonic, "mov");
+    assert_eq!(
+        inst.operands().collect::<Vec<_>>(),
+        [OperandValue::Reg(1), OperandValue::Reg(2)]
+    );
+}
+
+#[test]
+fn rejects_what_decode_rejects() {
+    assert_eq!(InstBuf::new(&[]), Err(DecodeError::Truncated(0)));
+    assert_eq!(InstBuf::new(&[0x45, 0x10]), Err(DecodeError::Truncated(0)));
+    assert_eq!(InstBuf::new(&[0xfd]), Err(DecodeError::Truncated(0)));
+    assert_eq!(InstBuf::new(&[0xdd]), Err(DecodeError::InvalidOpcode(0)));
+    assert_eq!(
+        InstBuf::new(&[0xff, 0xff]),
+        Err(DecodeError::InvalidOpcode(0))
+    );
+}
+
+#[test]
+fn setters_count_operands_per_kind() {
+    // stobjbyname 5, id 0x1234, v7
+    let mut inst = InstBuf::new(&[0x43, 0x05, 0x34, 0x12, 0x07]).unwrap();
+    inst.set_vreg(0, Reg(9)).unwrap();
+    inst.set_imm(0, Imm(6)).unwrap();
+    inst.set_id(0, EntityId(0xabcd)).unwrap();
+    assert_eq!(inst.as_bytes(), [0x43, 0x06, 0xcd, 0xab, 0x09]);
+    assert_eq!(
+        inst.set_vreg(1, Reg(0)),
+        Err(OperandError::NoOperand {
+            kind: OperandKind::Reg,
+            idx: 1
+        })
+    );
+}
+
+#[test]
+fn packed_operands_keep_their_neighbour() {
+    // mov v1, v2 in op_v1_4_v2_4
+    let mut inst = InstBuf::new(&[0x44, 0x21]).unwrap();
+    inst.set_vreg(0, Reg(0xf)).unwrap();
+    assert_eq!(inst.as_bytes(), [0x44, 0x2f]);
+    inst.set_vreg(1, Reg(0)).unwrap();
+    assert_eq!(inst.as_bytes(), [0x44, 0x0f]);
+}
+
+#[test]
+fn values_must_fit_the_encoding() {
+    let mut mov = InstBuf::new(&[0x44, 0x21]).unwrap();
+    assert_eq!(
+        mov.set_vreg(0, Reg(16)),
+        Err(OperandError::OutOfRange {
+            value: 16,
+            width: 4
+        })
+    );
+    assert_eq!(mov.as_bytes(), [0x44, 0x21]);
+
+    // jmp +2: signed 8-bit offset
+    let mut jmp = InstBuf::new(&[0x4d, 0x02]).unwrap();
+    jmp.set_imm(0, Imm(-128)).unwrap();
+    assert_eq!(jmp.as_bytes(), [0x4d, 0x80]);
+    assert!(jmp.set_imm(0, Imm(128)).is_err());
+
+    // ldobjbyname 0, id 0: unsigned IC slot
+    let mut ld = InstBuf::new(&[0x42, 0x00, 0x00, 0x00]).unwrap();
+    assert!(ld.set_imm(0, Imm(-1)).is_err());
+    ld.set_imm(0, Imm(0xff)).unwrap();
+    assert!(ld.set_id(0, EntityId(0x1_0000)).is_err());
+}
+
+#[test]
+fn float_immediates_take_bits() {
+    let mut bytes = vec![0x63];
+    bytes.extend_from_slice(&1.0f64.to_le_bytes());
+    let mut inst = InstBuf::new(&bytes).unwrap();
+    inst.set_imm(0, Imm((-2.5f64).to_bits() as i64)).unwrap();
+    assert_eq!(
+        inst.operands().collect::<Vec<_>>(),
+        [OperandValue::FloatImm(-2.5)]
+    );
+}
+
+#[test]
+fn patched_bytes_decode() {
+    // ldai 1
+    let mut inst = InstBuf::new(&[0x62, 0x01, 0x00, 0x00, 0x00]).unwrap();
+    inst.set_imm(0, Imm(-7)).unwrap();
+    let decoded = decode_pure(inst.as_ref()).unwrap();
+    assert_eq!(
+        decoded[0].0.emit_args(),
+        insn::Ldai::new(Imm(-7)).emit_args()
+    );
+    assert_eq!(inst.into_bytes().len(), 5);
+}
+
+#[test]
+fn update_functions_patch_in_place() {
+    // mov v1, v2; stobjbyname 5, id 0x1234, v7
+    let mut code = [0x44, 0x21, 0x43, 0x05, 0x34, 0x12, 0x07];
+    update_vreg(&mut code, 1, Reg(3)).unwrap();
+    update_imm(&mut code[2..], 0, Imm(6)).unwrap();
+    update_id(&mut code[2..], 0, EntityId(0xabcd)).unwrap();
+    assert_eq!(code, [0x44, 0x31, 0x43, 0x06, 0xcd, 0xab, 0x07]);
+}
+
+#[test]
+fn update_functions_reject_without_writing() {
+    let mut code = [0x44, 0x21];
+    assert_eq!(
+        update_vreg(&mut code, 0, Reg(16)),
+        Err(OperandError::OutOfRange {
+            value: 16,
+            width: 4
+        })
+    );
+    assert_eq!(
+        update_imm(&mut code, 0, Imm(1)),
+        Err(OperandError::NoOperand {
+            kind: OperandKind::Imm,
+            idx: 0
+        })
+    );
+    assert_eq!(
+        update_vreg(&mut code[..1], 0, Reg(0)),
+        Err(OperandError::Decode(DecodeError::Truncated(0)))
+    );
+    assert_eq!(code, [0x44, 0x21]);
+}
+
+#[test]
+fn canonicalize_picks_the_smallest_format_that_fits() {
+    // jmp +5 in op_imm_32
+    let jmp = InstBuf::new(&[0x98, 0x05, 0x00, 0x00, 0x00]).unwrap();
+    assert_eq!(jmp.canonicalize().as_bytes(), [0x4d, 0x05]);
+    // mov v1, v300 in op_v1_16_v2_16 has nowhere smaller to go
+    let mov = InstBuf::new(&[0x8f, 0x01, 0x00, 0x2c, 0x01]).unwrap();
+    assert_eq!(mov.canonicalize(), mov);
+    // mov v1, v2 in op_v1_4_v2_4 is already minimal
+    let mov = InstBuf::new(&[0x44, 0x21]).unwrap();
+    assert_eq!(mov.canonicalize(), mov);
+}
+
+#[test]
+fn canonicalize_narrows_wide_mnemonics() {
+    // wide.ldlexvar 1, 2
+    let wide = InstBuf::new(&[0xfd, 0x0c, 0x01, 0x00, 0x02, 0x00]).unwrap();
+    assert_eq!(wide.canonicalize().as_bytes(), [0x3c, 0x21]);
+    // wide.ldlexvar 32, 1 needs op_imm1_8_imm2_8
+    let wide = InstBuf::new(&[0xfd, 0x0c, 0x20, 0x00, 0x01, 0x00]).unwrap();
+    assert_eq!(wide.canonicalize().as_bytes(), [0x8a, 0x20, 0x01]);
+    // wide.ldlexvar 256, 1 only fits the wide form
+    let wide = InstBuf::new(&[0xfd, 0x0c, 0x00, 0x01, 0x01, 0x00]).unwrap();
+    assert_eq!(wide.canonicalize(), wide);
+    // wide.callrange 3, v4 gains the IC slot callrange has
+    let wide = InstBuf::new(&[0xfd, 0x04, 0x03, 0x00, 0x04]).unwrap();
+    let narrow = wide.canonicalize();
+    assert_eq!(narrow.info().mnemonic, "callrange");
+    assert_eq!(narrow.as_bytes(), [0x73, 0x00, 0x03, 0x04]);
+}
@@ -0,0 +1,54 @@
+use abcd_isa::*;
+
+/// `definefieldbyname 0, @0, v0; returnundefined`
+fn code() -> Vec<u8> {
+    encode(&[
+        insn::Definefieldbyname::new(Imm(0), EntityId(0), Reg(0)),
+        insn::Returnundefined::new(),
+    ])
+    .unwrap()
+    .0
+}
+
+#[test]
+fn older_versions_lack_later_instructions() {
+    let api9 = IsaProfile::for_version(Version::new(9, 0, 0, 0)).unwrap();
+    let api11 = IsaProfile::for_version(Version::new(11, 0, 2, 0)).unwrap();
+    assert!(!api9.has_mnemonic("definefieldbyname"));
+    assert!(api11.has_mnemonic("definefieldbyname"));
+    assert!(!api11.has_mnemonic("callruntime.istrue"));
+    assert!(api9.has_mnemonic("ldobjbyname"));
+    assert!(api9.has_mnemonic("deprecated.ldlexenv"));
+
+    assert_eq!(
+        introduced_in("callruntime.istrue"),
+        Some(Version::new(12, 0, 6, 0))
+    );
+    assert_eq!(introduced_in("ldobjbyname"), None);
+    assert!(api9.opcodes().len() < api11.opcodes().len());
+    assert!(api11.opcodes().len() < opcode_table().len());
+}
+
+#[test]
+fn decoding_follows_the_file_version() {
+    let code = code();
+    assert!(matches!(
+        IsaProfile::for_version(Version::new(9, 0, 0, 0))
+            .unwrap()
+            .decode(&code),
+        Err(DecodeError::InvalidOpcode(0))
+    ));
+    let decoded = IsaProfile::for_version(Version::new(12, 0, 6, 0))
+        .unwrap()
+        .decode(&code)
+        .unwrap();
+    assert_eq!(decoded.len(), 2);
+    assert_eq!(decoded[0].0.mnemonic(), "definefieldbyname");
+}
+
+#[test]
+fn versions_below_the_minimum_have_no_profile() {
+    let min = Version::min_supported();
+    assert_eq!(IsaProfile::for_version(min).map(|p| p.version()), Some(min));
+    assert!(IsaProfile::for_version(Version::new(0, 0, 0, 0)).is_none());
+}
@@ -0,0 +1,67 @@
+use abcd_isa::{lookup_mnemonic, lookup_mnemonic_ignore_case, opcode_table, opcodes};
+
+#[test]
+fn every_mnemonic_is_found() {
+    for row in opcode_table() {
+        let found = lookup_mnemonic(row.mnemonic).unwrap();
+        assert_eq!(found.mnemonic, row.mnemonic);
+    }
+}
+
+#[test]
+fn smallest_encoding_wins() {
+    for row in opcode_table() {
+        let found = lookup_mnemonic(row.mnemonic).unwrap();
+        assert!(found.size <= row.size, "{}", row.mnemonic);
+    }
+    let mov = lookup_mnemonic("mov").unwrap();
+    assert_eq!(mov.opcode, 0x44);
+    assert_eq!(mov.size, 2);
+}
+
+#[test]
+fn prefixed_mnemonics() {
+    let row = lookup_mnemonic("wide.newlexenv").unwrap();
+    assert!(row.opcode > 0xff);
+    assert!(lookup_mnemonic("newlexenv").unwrap().opcode <= 0xff);
+}
+
+#[test]
+fn opcode_constants_name_table_rows() {
+    let named = [
+        (opcodes::LDUNDEFINED, "ldundefined", 1),
+        (opcodes::MOV_V4_V4, "mov", 2),
+        (opcodes::MOV_V16_V16, "mov", 5),
+        (opcodes::JMP_IMM32, "jmp", 5),
+        (opcodes::WIDE_NEWLEXENV_PREF_IMM16, "wide.newlexenv", 4),
+        (
+            opcodes::DEPRECATED_LDLEXENV_PREF_NONE,
+            "deprecated.ldlexenv",
+            2,
+        ),
+    ];
+    for (opcode, mnemonic, size) in named {
+        let row = opcode_table().iter().find(|r| r.opcode == opcode).unwrap();
+        assert_eq!((row.mnemonic, row.size), (mnemonic, size));
+    }
+    assert_eq!(lookup_mnemonic("mov").unwrap().opcode, opcodes::MOV_V4_V4);
+}
+
+#[test]
+fn unknown_mnemonics() {
+    assert!(lookup_mnemonic("").is_none());
+    assert!(lookup_mnemonic("ldobjbynam").is_none());
+    assert!(lookup_mnemonic("LDOBJBYNAME").is_none());
+    assert!(lookup_mnemonic_ignore_case("nosuchop").is_none());
+}
+
+#[test]
+fn ignore_case() {
+    let exact = lookup_mnemonic("ldobjbyname").unwrap();
+    for spelling in ["LDOBJBYNAME", "LdObjByName", "ldobjbyname"] {
+        let found = lookup_mnemonic_ignore_case(spelling).unwrap();
+        assert_eq!(found.opcode, exact.opcode);
+    }
+    let wide = lookup_mnemonic_ignore_case("Wide.NewLexEnv").unwrap();
+    assert_eq!(wide.mnemonic, "wide.newlexenv");
+}
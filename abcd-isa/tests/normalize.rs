@@ -0,0 +1,177 @@
+use abcd_isa::*;
+
+fn bytes(program: &[Bytecode]) -> Vec<u8> {
+    encode(program).unwrap().0
+}
+
+/// Normalize `program` and decode the result.
+fn normalized(program: &[Bytecode]) -> Vec<Bytecode> {
+    decode(&normalize(&bytes(program)))
+        .unwrap()
+        .into_iter()
+        .map(|(bc, _)| bc)
+        .collect()
+}
+
+fn assert_same(a: &[Bytecode], b: &[Bytecode]) {
+    assert_eq!(a.len(), b.len(), "length mismatch");
+    for (i, (x, y)) in a.iter().zip(b).enumerate() {
+        assert_eq!(x.emit_args(), y.emit_args(), "mismatch at {i}: {x} vs {y}");
+    }
+}
+
+#[test]
+fn to_narrow_inverts_to_wide() {
+    let narrow = insn::Ldlexvar::new(Imm(3), Imm(300));
+    let wide = narrow.to_wide().unwrap();
+    assert_same(&[wide.to_narrow().unwrap()], &[narrow]);
+
+    // The slot the wide form dropped comes back as 0.
+    let with_slot = insn::Ldobjbyindex::new(Imm(9), Imm(0x1234));
+    let back = with_slot.to_wide().unwrap().to_narrow().unwrap();
+    assert_same(&[back], &[insn::Ldobjbyindex::new(Imm(0), Imm(0x1234))]);
+}
+
+#[test]
+fn to_narrow_refuses_operands_that_do_not_fit() {
+    assert!(
+        insn::WideLdobjbyindex::new(Imm(0x10000))
+            .to_narrow()
+            .is_none()
+    );
+    assert!(insn::Ldlexvar::new(Imm(0), Imm(0)).to_narrow().is_none());
+}
+
+#[test]
+fn clear_ic_slot_only_touches_slots() {
+    let mut load = insn::Stobjbyname::new(Imm(5), EntityId(0x88), Reg(7));
+    load.clear_ic_slot();
+    assert_same(
+        &[load],
+        &[insn::Stobjbyname::new(Imm(0), EntityId(0x88), Reg(7))],
+    );
+
+    let mut call = insn::Callthis1::new(Imm(9), Reg(0), Reg(1));
+    call.clear_ic_slot();
+    assert_same(&[call], &[insn::Callthis1::new(Imm(0), Reg(0), Reg(1))]);
+
+    let mut lexvar = insn::Ldlexvar::new(Imm(1), Imm(2));
+    lexvar.clear_ic_slot();
+    assert_same(&[lexvar], &[insn::Ldlexvar::new(Imm(1), Imm(2))]);
+}
+
+#[test]
+fn ic_slot_marks_only_slot_operands() {
+    assert_eq!(
+        insn::Stobjbyname::new(Imm(5), EntityId(0x88), Reg(7)).ic_slot(),
+        Some(0)
+    );
+    // Leading immediates that are not slots.
+    assert_eq!(insn::Ldlexvar::new(Imm(1), Imm(2)).ic_slot(), None);
+    assert_eq!(insn::Newlexenv::new(Imm(3)).ic_slot(), None);
+    // The wide form has no slot to point at.
+    assert_eq!(insn::WideLdobjbyindex::new(Imm(0x1234)).ic_slot(), None);
+
+    for row in opcode_table() {
+        assert_eq!(row.has_ic_slot(), row.template.ic_slots().is_some());
+        if let Some(i) = row.template.ic_slot() {
+            assert_eq!(row.operands[i].kind, OperandKind::Imm, "{}", row.mnemonic);
+        }
+    }
+}
+
+#[test]
+fn wide_and_narrow_encodings_normalize_alike() {
+    let narrow = bytes(&[
+        insn::Ldlexvar::new(Imm(1), Imm(2)),
+        insn::Returnundefined::new(),
+    ]);
+    let wide = bytes(&[
+        insn::WideLdlexvar::new(Imm(1), Imm(2)),
+        insn::Returnundefined::new(),
+    ]);
+    assert_ne!(narrow, wide);
+    assert_eq!(normalize(&narrow), normalize(&wide));
+}
+
+#[test]
+fn ic_slot_numbers_are_ignored() {
+    let a = bytes(&[insn::Stobjbyname::new(Imm(1), EntityId(4), Reg(0))]);
+    let b = bytes(&[insn::Stobjbyname::new(Imm(7), EntityId(4), Reg(0))]);
+    assert_eq!(normalize(&a), normalize(&b));
+}
+
+#[test]
+fn semantic_eq_ignores_slots_and_width() {
+    let a = insn::Stobjbyname::new(Imm(1), EntityId(4), Reg(0));
+    let b = insn::Stobjbyname::new(Imm(7), EntityId(4), Reg(0));
+    assert!(a.semantic_eq(&b));
+    assert_eq!(a.semantic_hash(), b.semantic_hash());
+
+    let narrow = insn::Ldobjbyindex::new(Imm(3), Imm(0x12));
+    let wide = insn::WideLdobjbyindex::new(Imm(0x12));
+    assert!(narrow.semantic_eq(&wide));
+    assert_eq!(narrow.semantic_hash(), wide.semantic_hash());
+}
+
+#[test]
+fn semantic_eq_keeps_other_operands() {
+    let a = insn::Stobjbyname::new(Imm(1), EntityId(4), Reg(0));
+    assert!(!a.semantic_eq(&insn::Stobjbyname::new(Imm(1), EntityId(5), Reg(0))));
+    assert!(!a.semantic_eq(&insn::Stobjbyname::new(Imm(1), EntityId(4), Reg(1))));
+    // Too wide for the narrow form, so it stays a different instruction.
+    let wide = insn::WideLdobjbyindex::new(Imm(0x10000));
+    assert!(!wide.semantic_eq(&insn::Ldobjbyindex::new(Imm(0), Imm(0x10000))));
+    // Not an IC slot.
+    assert!(!insn::Ldlexvar::new(Imm(1), Imm(2)).semantic_eq(&insn::Ldlexvar::new(Imm(0), Imm(2))));
+}
+
+#[test]
+fn redundant_moves_are_dropped() {
+    let program = [
+        insn::Mov::new(Reg(1), Reg(2)),
+        insn::Mov::new(Reg(2), Reg(1)),
+        insn::Mov::new(Reg(1), Reg(2)),
+        insn::Mov::new(Reg(3), Reg(3)),
+        insn::Lda::new(Reg(1)),
+        insn::Return::new(),
+    ];
+    assert_same(
+        &normalized(&program),
+        &[
+            insn::Mov::new(Reg(1), Reg(2)),
+            insn::Lda::new(Reg(1)),
+            insn::Return::new(),
+        ],
+    );
+}
+
+#[test]
+fn moves_that_are_jump_targets_stay() {
+    // The repeated move at 2 goes; the one at 4 is reached from the `jeqz`
+    // without passing through 1, so it stays and both labels shift down.
+    let program = [
+        insn::Jeqz::new(Label(4)),
+        insn::Mov::new(Reg(1), Reg(2)),
+        insn::Mov::new(Reg(1), Reg(2)),
+        insn::Jmp::new(Label(5)),
+        insn::Mov::new(Reg(1), Reg(2)),
+        insn::Returnundefined::new(),
+    ];
+    assert_same(
+        &normalized(&program),
+        &[
+            insn::Jeqz::new(Label(3)),
+            insn::Mov::new(Reg(1), Reg(2)),
+            insn::Jmp::new(Label(4)),
+            insn::Mov::new(Reg(1), Reg(2)),
+            insn::Returnundefined::new(),
+        ],
+    );
+}
+
+#[test]
+fn undecodable_input_is_returned_unchanged() {
+    let truncated = [0xfd];
+    assert_eq!(normalize(&truncated), truncated);
+}
@@ -0,0 +1,249 @@
+//! Exact encodings for every operand shape, checked in both directions.
+//!
+//! Bytecode is little-endian whatever the host, so these vectors must pass
+//! unchanged on big-endian targets too.
+
+use abcd_isa::*;
+
+/// `(bytes, instruction)` pairs; each `bytes` is one complete instruction in
+/// the narrowest format that holds its operands.
+fn vectors() -> Vec<(&'static [u8], Bytecode)> {
+    vec![
+        // Two 4-bit registers share a byte, first operand in the low nibble.
+        (&[0x44, 0x21], insn::Mov::new(Reg(1), Reg(2))),
+        (&[0x45, 0x10, 0x20], insn::Mov::new(Reg(0x10), Reg(0x20))),
+        (
+            &[0x8f, 0x34, 0x12, 0x78, 0x56],
+            insn::Mov::new(Reg(0x1234), Reg(0x5678)),
+        ),
+        // Packed 4-bit immediates.
+        (&[0x3c, 0x21], insn::Ldlexvar::new(Imm(1), Imm(2))),
+        (
+            &[0x62, 0x78, 0x56, 0x34, 0x12],
+            insn::Ldai::new(Imm(0x1234_5678)),
+        ),
+        // Signed immediates are sign-extended from their encoded width.
+        (&[0x62, 0xfe, 0xff, 0xff, 0xff], insn::Ldai::new(Imm(-2))),
+        (
+            &[0x62, 0x00, 0x00, 0x00, 0x80],
+            insn::Ldai::new(Imm(i32::MIN as i64)),
+        ),
+        (&[0x3e, 0x34, 0x12], insn::LdaStr::new(EntityId(0x1234))),
+        (
+            &[0x43, 0x05, 0x34, 0x12, 0x07],
+            insn::Stobjbyname::new(Imm(5), EntityId(0x1234), Reg(7)),
+        ),
+        (
+            &[0x74, 0x00, 0x01, 0x34, 0x12, 0x03],
+            insn::Definefunc::new(Imm(0x100), EntityId(0x1234), Imm(3)),
+        ),
+        // Prefixed opcodes: prefix byte first, then the sub-opcode.
+        (&[0xfc, 0x00], insn::DeprecatedLdlexenv::new()),
+        (
+            &[0xfe, 0x08, 0x34, 0x12],
+            insn::ThrowIfsupernotcorrectcall::new(Imm(0x1234)),
+        ),
+        (
+            &[0xfb, 0x01, 0x09, 0x01, 0x02],
+            insn::CallruntimeDefinefieldbyvalue::new(Imm(9), Reg(1), Reg(2)),
+        ),
+        (
+            &[0xfd, 0x08, 0x78, 0x56, 0x34, 0x12],
+            insn::WideLdobjbyindex::new(Imm(0x1234_5678)),
+        ),
+        (
+            &[0xfd, 0x0d, 0x34, 0x12, 0x78, 0x56],
+            insn::WideStlexvar::new(Imm(0x1234), Imm(0x5678)),
+        ),
+    ]
+}
+
+#[test]
+fn vectors_decode() {
+    for (bytes, expected) in vectors() {
+        let decoded = decode(bytes).unwrap_or_else(|e| panic!("{bytes:02x?}: {e}"));
+        assert_eq!(decoded.len(), 1, "{bytes:02x?}");
+        let (bc, offset) = decoded[0];
+        assert_eq!(offset, 0);
+        assert_eq!(bc.emit_args(), expected.emit_args(), "{bytes:02x?}: {bc}");
+    }
+}
+
+#[test]
+fn vectors_decode_pure() {
+    for (bytes, expected) in vectors() {
+        let decoded = decode_pure(bytes).unwrap_or_else(|e| panic!("{bytes:02x?}: {e}"));
+        assert_eq!(decoded.len(), 1, "{bytes:02x?}");
+        assert_eq!(
+            decoded[0].0.emit_args(),
+            expected.emit_args(),
+            "{bytes:02x?}"
+        );
+    }
+}
+
+#[test]
+fn vectors_encode() {
+    for (bytes, bc) in vectors() {
+        let (encoded, _) = encode(&[bc]).unwrap();
+        assert_eq!(encoded, bytes, "{bc}");
+    }
+}
+
+/// The opcode table row for the encoding at the start of `bytes`.
+fn row_for(bytes: &[u8]) -> &'static OpcodeInfo {
+    let opcode = if PrefixGroup::from_prefix_byte(bytes[0]).is_some() {
+        u16::from_le_bytes([bytes[0], bytes[1]])
+    } else {
+        u16::from(bytes[0])
+    };
+    opcode_table()
+        .iter()
+        .find(|row| row.opcode == opcode)
+        .unwrap_or_else(|| panic!("no row for {opcode:#06x}"))
+}
+
+#[test]
+fn vectors_extract() {
+    for (bytes, bc) in vectors() {
+        let row = row_for(bytes);
+        let (_, args, n) = bc.emit_args();
+        assert_eq!(row.operands.len(), n, "{bc}");
+        for (desc, &arg) in row.operands.iter().zip(&args) {
+            let mask = if desc.width >= 64 {
+                u64::MAX
+            } else {
+                (1 << desc.width) - 1
+            };
+            assert_eq!(desc.extract(bytes), arg as u64 & mask, "{bc}: {desc:?}");
+        }
+    }
+}
+
+#[test]
+fn vreg_matches_decoded_registers() {
+    for (bytes, bc) in vectors() {
+        let row = row_for(bytes);
+        let (regs, n) = bc.reg_operands();
+        for (i, reg) in regs[..n].iter().enumerate() {
+            assert_eq!(row.vreg(bytes, i), Some(*reg), "{bc}");
+        }
+        assert_eq!(row.vreg(bytes, n), None, "{bc}");
+    }
+}
+
+#[test]
+fn packed_registers_split_at_the_nibble() {
+    // mov v1, v2 in op_v1_4_v2_4.
+    let row = row_for(&[0x44]);
+    assert_eq!(
+        row.operands[1],
+        OperandDesc {
+            kind: OperandKind::Reg,
+            byte_offset: 1,
+            bit_offset: 4,
+            width: 4,
+            signed: false,
+            float: false,
+        }
+    );
+    assert_eq!(row.vreg(&[0x44, 0xa5], 0), Some(Reg(5)));
+    assert_eq!(row.vreg(&[0x44, 0xa5], 1), Some(Reg(10)));
+}
+
+#[test]
+fn f64_immediate_is_little_endian() {
+    let value = -1.5f64;
+    let mut bytes = vec![0x63];
+    bytes.extend_from_slice(&value.to_le_bytes());
+    let bc = insn::Fldai::new(Imm(value.to_bits() as i64));
+
+    let decoded = decode(&bytes).unwrap();
+    assert_eq!(decoded[0].0.emit_args(), bc.emit_args());
+    assert_eq!(encode(&[bc]).unwrap().0, bytes);
+}
+
+#[test]
+fn backward_jump_offset_is_signed() {
+    // ldundefined; jmp -1 (back to offset 0)
+    let bytes = [0x00, 0x4d, 0xff];
+    let decoded = decode(&bytes).unwrap();
+    assert_eq!(decoded.len(), 2);
+    assert_eq!(decoded[1].1, 1);
+    assert_eq!(
+        decoded[1].0.emit_args(),
+        insn::Jmp::new(Label(0)).emit_args()
+    );
+
+    let program: Vec<Bytecode> = decoded.iter().map(|(bc, _)| *bc).collect();
+    assert_eq!(encode(&program).unwrap().0, bytes);
+}
+
+#[test]
+fn operand_values_follow_the_signature() {
+    for (bytes, bc) in vectors() {
+        let row = row_for(bytes);
+        let (_, args, n) = bc.emit_args();
+        let values: Vec<OperandValue> = row.operand_values(bytes).collect();
+        assert_eq!(values.len(), n, "{bc}");
+        for (value, &arg) in values.iter().zip(&args) {
+            let got = match *value {
+                OperandValue::Reg(r) => i64::from(r),
+                OperandValue::Imm(v) => v,
+                OperandValue::Id(id) => i64::from(id),
+                OperandValue::FloatImm(f) => panic!("{bc}: float {f}"),
+            };
+            assert_eq!(got, arg, "{bc}: {value:?}");
+        }
+    }
+}
+
+#[test]
+fn operand_values_type_each_operand() {
+    // stobjbyname 5, id 0x1234, v7
+    let bytes = [0x43, 0x05, 0x34, 0x12, 0x07];
+    let values: Vec<OperandValue> = row_for(&bytes).operand_values(&bytes).collect();
+    assert_eq!(
+        values,
+        [
+            OperandValue::Imm(5),
+            OperandValue::Id(0x1234),
+            OperandValue::Reg(7)
+        ]
+    );
+
+    let mut fldai = vec![0x63];
+    fldai.extend_from_slice(&(-1.5f64).to_le_bytes());
+    let values: Vec<OperandValue> = row_for(&fldai).operand_values(&fldai).collect();
+    assert_eq!(values, [OperandValue::FloatImm(-1.5)]);
+
+    // jmp -1
+    let values: Vec<OperandValue> = row_for(&[0x4d]).operand_values(&[0x4d, 0xff]).collect();
+    assert_eq!(values, [OperandValue::Imm(-1)]);
+}
+
+#[test]
+fn bytecode_operand_values_match_the_encoding() {
+    for (bytes, bc) in vectors() {
+        let decoded: Vec<OperandValue> = row_for(bytes).operand_values(bytes).collect();
+        assert_eq!(bc.operand_values().collect::<Vec<_>>(), decoded, "{bc}");
+    }
+
+    let fldai = insn::Fldai::new(Imm((-1.5f64).to_bits() as i64));
+    assert_eq!(
+        fldai.operand_values().collect::<Vec<_>>(),
+        [OperandValue::FloatImm(-1.5)]
+    );
+    let ldai = insn::Ldai::new(Imm(-3));
+    assert_eq!(
+        ldai.operand_values().collect::<Vec<_>>(),
+        [OperandValue::Imm(-3)]
+    );
+    assert_eq!(insn::Return::new().operand_values().count(), 0);
+}
+
+#[test]
+#[should_panic]
+fn operand_values_reject_truncated_bytes() {
+    let _ = row_for(&[0x45]).operand_values(&[0x45, 0x10]);
+}
@@ -0,0 +1,166 @@
+use abcd_isa::*;
+
+/// `program` encoded.
+fn code(program: &[Bytecode]) -> Vec<u8> {
+    encode(program).unwrap().0
+}
+
+/// Mnemonic and, for jumps, target instruction index of each instruction.
+fn listing(bytes: &[u8]) -> Vec<(&'static str, Option<u32>)> {
+    decode(bytes)
+        .unwrap()
+        .iter()
+        .map(|(bc, _)| {
+            let label = bc
+                .jump_label_arg_index()
+                .map(|i| bc.emit_args().1[i] as u32);
+            (bc.mnemonic(), label)
+        })
+        .collect()
+}
+
+/// jeqz -> 3; ldundefined; ldnull; returnundefined
+fn branchy() -> Vec<u8> {
+    code(&[
+        insn::Jeqz::new(Label(3)),
+        insn::Ldundefined::new(),
+        insn::Ldnull::new(),
+        insn::Returnundefined::new(),
+    ])
+}
+
+#[test]
+fn untouched_code_round_trips() {
+    let original = branchy();
+    let patched = Patcher::new(&original).unwrap().finish().unwrap();
+    assert_eq!(patched.bytes, original);
+}
+
+#[test]
+fn jumps_land_on_code_inserted_before_their_target() {
+    let mut p = Patcher::new(&branchy()).unwrap();
+    p.insert_before(3, &[insn::Ldtrue::new()]).unwrap();
+    p.insert_after(0, &[insn::Ldnull::new()]).unwrap();
+    let patched = p.finish().unwrap();
+    assert_eq!(
+        listing(&patched.bytes),
+        [
+            ("jeqz", Some(4)),
+            ("ldnull", None),
+            ("ldundefined", None),
+            ("ldnull", None),
+            ("ldtrue", None),
+            ("returnundefined", None),
+        ]
+    );
+}
+
+#[test]
+fn inserted_jumps_skip_code_inserted_before_their_target() {
+    let mut p = Patcher::new(&branchy()).unwrap();
+    p.insert_before(3, &[insn::Jmp::new(Label(3)), insn::Ldtrue::new()])
+        .unwrap();
+    assert_eq!(
+        listing(&p.finish().unwrap().bytes),
+        [
+            ("jeqz", Some(3)),
+            ("ldundefined", None),
+            ("ldnull", None),
+            ("jmp", Some(5)),
+            ("ldtrue", None),
+            ("returnundefined", None),
+        ]
+    );
+}
+
+#[test]
+fn jumps_to_deleted_code_fall_through() {
+    let mut p = Patcher::new(&branchy()).unwrap();
+    p.delete(2).unwrap();
+    p.replace(3, &[insn::Ldtrue::new(), insn::Return::new()])
+        .unwrap();
+    assert_eq!(
+        listing(&p.finish().unwrap().bytes),
+        [
+            ("jeqz", Some(2)),
+            ("ldundefined", None),
+            ("ldtrue", None),
+            ("return", None),
+        ]
+    );
+
+    let mut p = Patcher::new(&branchy()).unwrap();
+    p.delete(3).unwrap();
+    assert!(matches!(p.finish(), Err(PatchError::DanglingJump(3))));
+}
+
+#[test]
+fn narrow_jumps_widen_when_code_grows() {
+    let original = branchy();
+    let mut p = Patcher::new(&original).unwrap();
+    p.insert_after(1, &vec![insn::Ldundefined::new(); 200])
+        .unwrap();
+    let patched = p.finish().unwrap();
+
+    let insns = decode(&patched.bytes).unwrap();
+    assert_eq!(insns.len(), 204);
+    assert!(
+        insns[1].1 > decode(&original).unwrap()[1].1,
+        "jeqz not widened"
+    );
+    assert_eq!(listing(&patched.bytes)[0], ("jeqz", Some(203)));
+}
+
+#[test]
+fn offsets_map_through_the_patch() {
+    let original = branchy();
+    let old = decode(&original).unwrap();
+    let mut p = Patcher::new(&original).unwrap();
+    assert_eq!(p.index_at(old[2].1), Some(2));
+    // Inside the two-byte jeqz.
+    assert_eq!(p.index_at(old[0].1 + 1), None);
+    p.insert_before(2, &[insn::Ldtrue::new()]).unwrap();
+    p.delete(1).unwrap();
+    let patched = p.finish().unwrap();
+
+    let new = decode(&patched.bytes).unwrap();
+    assert_eq!(patched.map_offset(old[0].1), Some(0));
+    assert_eq!(patched.map_offset(old[1].1), Some(new[1].1));
+    assert_eq!(patched.map_offset(old[2].1), Some(new[1].1));
+    assert_eq!(
+        patched.map_offset(original.len() as u32),
+        Some(patched.bytes.len() as u32)
+    );
+}
+
+#[test]
+fn edits_are_bounds_checked() {
+    let mut p = Patcher::new(&branchy()).unwrap();
+    assert!(matches!(
+        p.insert_before(4, &[]),
+        Err(PatchError::IndexOutOfRange { idx: 4, len: 4 })
+    ));
+    assert!(matches!(
+        Patcher::new(&[0xdd]),
+        Err(PatchError::Decode(DecodeError::InvalidOpcode(0)))
+    ));
+}
+
+#[test]
+fn inserted_slots_raise_the_slot_count() {
+    // add2 takes one slot, ldobjbyname two.
+    let original = code(&[
+        insn::Add2::new(Imm(0), Reg(0)),
+        insn::Returnundefined::new(),
+    ]);
+    assert_eq!(
+        Patcher::new(&original).unwrap().finish().unwrap().ic_slots,
+        1
+    );
+
+    let mut p = Patcher::new(&original).unwrap();
+    p.insert_before(1, &[insn::Ldobjbyname::new(Imm(1), EntityId(0))])
+        .unwrap();
+    assert_eq!(p.finish().unwrap().ic_slots, 3);
+    assert_eq!(ic_slot_count(&[insn::Ldnull::new()]), 0);
+}
@@ -0,0 +1,91 @@
+//! JSON round trips of the `serde` feature.
+//!
+//! Run with `cargo test -p abcd-isa --features serde --test serde`.
+
+#![cfg(feature = "serde")]
+
+use abcd_isa::*;
+
+fn round_trip<T>(value: &T) -> T
+where
+    T: serde::Serialize + serde::de::DeserializeOwned,
+{
+    serde_json::from_str(&serde_json::to_string(value).unwrap()).unwrap()
+}
+
+#[test]
+fn bytecode_round_trips() {
+    let program = [
+        insn::Mov::new(Reg(1), Reg(200)),
+        insn::Ldai::new(Imm(-42)),
+        insn::LdaStr::new(EntityId(0x1234)),
+        insn::Jmp::new(Label(3)),
+        insn::Returnundefined::new(),
+    ];
+    for bc in program {
+        assert_eq!(round_trip(&bc).to_string(), bc.to_string());
+    }
+}
+
+#[test]
+fn version_is_a_dotted_string() {
+    let v = Version::new(12, 0, 6, 0);
+    assert_eq!(serde_json::to_string(&v).unwrap(), "\"12.0.6.0\"");
+    assert_eq!(round_trip(&v), v);
+    assert!(serde_json::from_str::<Version>("\"12.0.6\"").is_err());
+    assert!(serde_json::from_str::<Version>("\"12.0.6.0.1\"").is_err());
+    assert!(serde_json::from_str::<Version>("\"12.0.256.0\"").is_err());
+}
+
+#[test]
+fn flags_are_named() {
+    let flags = BytecodeFlag::JUMP | BytecodeFlag::CONDITIONAL;
+    assert_eq!(
+        serde_json::to_string(&flags).unwrap(),
+        "\"JUMP | CONDITIONAL\""
+    );
+    assert_eq!(round_trip(&flags), flags);
+}
+
+#[test]
+fn snapshot_round_trips_and_describes_the_table() {
+    let table = OpcodeMeta::snapshot();
+    assert_eq!(table.len(), opcode_table().len());
+    assert_eq!(round_trip(&table), table);
+
+    let jeqz = table.iter().find(|m| m.mnemonic == "jeqz").unwrap();
+    assert!(
+        jeqz.flags
+            .contains(BytecodeFlag::JUMP | BytecodeFlag::CONDITIONAL)
+    );
+    assert_eq!(jeqz.namespace, "core");
+    assert_eq!(jeqz.prefix, None);
+
+    let json = serde_json::to_value(&table).unwrap();
+    let wide = json
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|m| m["mnemonic"] == "wide.ldlexvar")
+        .unwrap();
+    assert_eq!(wide["prefix"], "Wide");
+}
+
+#[test]
+fn exported_metadata_names_formats_and_versions() {
+    let isa = export_metadata();
+    assert_eq!(isa.version, Version::current());
+    assert_eq!(isa.opcodes.len(), opcode_table().len());
+    let mov = isa
+        .opcodes
+        .iter()
+        .find(|m| m.opcode == opcodes::MOV_V4_V4)
+        .unwrap();
+    assert_eq!(mov.format, "op_v1_4_v2_4");
+    assert_eq!(mov.size, 2);
+
+    let json = serde_json::to_value(&isa).unwrap();
+    assert_eq!(json["version"], isa.version.to_string());
+    assert_eq!(json["opcodes"][0]["mnemonic"], isa.opcodes[0].mnemonic);
+    assert_eq!(round_trip(&isa), isa);
+}
@@ -0,0 +1,159 @@
+mod common;
+
+use abcd_isa::*;
+use common::assert_roundtrip;
+
+/// Encode `program`, decode it back and compare against `expected`.
+fn assert_encodes_as(program: &[Bytecode], expected: &[Bytecode]) {
+    let (bytes, offsets) = encode(program).unwrap();
+    assert_eq!(offsets.len(), program.len(), "offsets must stay one-to-one");
+    let decoded = decode(&bytes).unwrap();
+    assert_eq!(decoded.len(), expected.len(), "length mismatch");
+    for (i, (a, (b, _))) in expected.iter().zip(&decoded).enumerate() {
+        assert_eq!(a.mnemonic(), b.mnemonic(), "mnemonic mismatch at {i}");
+        assert_eq!(a.emit_args(), b.emit_args(), "mismatch at {i}: {a} vs {b}");
+    }
+}
+
+#[test]
+fn mov_register_boundary() {
+    let (narrow, _) = encode(&[insn::Mov::new(Reg(255), Reg(0))]).unwrap();
+    let (wide, _) = encode(&[insn::Mov::new(Reg(256), Reg(0))]).unwrap();
+    assert_eq!(narrow.len(), 3, "mov v8_v8");
+    assert_eq!(wide.len(), 5, "mov v16_v16");
+    assert_roundtrip(&[
+        insn::Mov::new(Reg(255), Reg(255)),
+        insn::Mov::new(Reg(256), Reg(255)),
+        insn::Mov::new(Reg(255), Reg(256)),
+        insn::Mov::new(Reg(65535), Reg(256)),
+    ]);
+}
+
+#[test]
+fn fits_encoding_boundary() {
+    assert!(insn::Lda::new(Reg(255)).fits_encoding());
+    assert!(!insn::Lda::new(Reg(256)).fits_encoding());
+    assert!(insn::Mov::new(Reg(256), Reg(65535)).fits_encoding());
+    assert!(insn::Newlexenv::new(Imm(255)).fits_encoding());
+    assert!(!insn::Newlexenv::new(Imm(256)).fits_encoding());
+    assert!(insn::Ldai::new(Imm(i32::MIN as i64)).fits_encoding());
+    assert!(!insn::Ldai::new(Imm(i32::MAX as i64 + 1)).fits_encoding());
+}
+
+#[test]
+fn to_wide_drops_ic_slot() {
+    let wide = insn::Callrange::new(Imm(3), Imm(300), Reg(4))
+        .to_wide()
+        .unwrap();
+    assert_eq!(wide.mnemonic(), "wide.callrange");
+    assert_eq!(
+        wide.emit_args(),
+        insn::WideCallrange::new(Imm(300), Reg(4)).emit_args()
+    );
+    assert!(insn::Lda::new(Reg(0)).to_wide().is_none());
+}
+
+#[test]
+fn newlexenv_boundary() {
+    assert_encodes_as(
+        &[
+            insn::Newlexenv::new(Imm(255)),
+            insn::Newlexenv::new(Imm(256)),
+        ],
+        &[
+            insn::Newlexenv::new(Imm(255)),
+            insn::WideNewlexenv::new(Imm(256)),
+        ],
+    );
+}
+
+#[test]
+fn callrange_argc_boundary() {
+    assert_encodes_as(
+        &[
+            insn::Callrange::new(Imm(0), Imm(255), Reg(1)),
+            insn::Callrange::new(Imm(0), Imm(256), Reg(1)),
+            insn::Callthisrange::new(Imm(0), Imm(256), Reg(1)),
+        ],
+        &[
+            insn::Callrange::new(Imm(0), Imm(255), Reg(1)),
+            insn::WideCallrange::new(Imm(256), Reg(1)),
+            insn::WideCallthisrange::new(Imm(256), Reg(1)),
+        ],
+    );
+}
+
+#[test]
+fn lexvar_boundary() {
+    assert_encodes_as(
+        &[
+            insn::Ldlexvar::new(Imm(255), Imm(255)),
+            insn::Ldlexvar::new(Imm(256), Imm(0)),
+            insn::Stlexvar::new(Imm(0), Imm(256)),
+        ],
+        &[
+            insn::Ldlexvar::new(Imm(255), Imm(255)),
+            insn::WideLdlexvar::new(Imm(256), Imm(0)),
+            insn::WideStlexvar::new(Imm(0), Imm(256)),
+        ],
+    );
+}
+
+#[test]
+fn objbyindex_boundary() {
+    assert_encodes_as(
+        &[
+            insn::Ldobjbyindex::new(Imm(0), Imm(65535)),
+            insn::Ldobjbyindex::new(Imm(0), Imm(65536)),
+            insn::Stobjbyindex::new(Imm(0), Reg(2), Imm(65536)),
+        ],
+        &[
+            insn::Ldobjbyindex::new(Imm(0), Imm(65535)),
+            insn::WideLdobjbyindex::new(Imm(65536)),
+            insn::WideStobjbyindex::new(Reg(2), Imm(65536)),
+        ],
+    );
+}
+
+#[test]
+fn jump_over_widened_instruction() {
+    let program = [
+        insn::Jmp::new(Label(2)),
+        insn::Newlexenv::new(Imm(1000)),
+        insn::Returnundefined::new(),
+    ];
+    let (bytes, offsets) = encode(&program).unwrap();
+    let decoded = decode(&bytes).unwrap();
+    assert_eq!(decoded[1].0.mnemonic(), "wide.newlexenv");
+    assert_eq!(decoded[2].1, offsets[2]);
+    match decoded[0].0 {
+        Bytecode::Jmp(Label(target)) => assert_eq!(target, 2),
+        other => panic!("expected jmp, got {other}"),
+    }
+}
+
+#[test]
+fn narrow_and_wide_equivalents() {
+    let row = |mnemonic: &str, format: &str| {
+        opcode_table()
+            .iter()
+            .find(|r| r.mnemonic == mnemonic && r.format == format)
+            .unwrap()
+    };
+    let mov4 = row("mov", "op_v1_4_v2_4");
+    let mov8 = row("mov", "op_v1_8_v2_8");
+    let mov16 = row("mov", "op_v1_16_v2_16");
+    assert_eq!(mov8.narrow_equivalent().unwrap().opcode, mov4.opcode);
+    assert_eq!(mov8.wide_equivalent().unwrap().opcode, mov16.opcode);
+    assert!(mov4.narrow_equivalent().is_none());
+    assert!(mov16.wide_equivalent().is_none());
+
+    let ldlexvar = row("ldlexvar", "op_imm1_4_imm2_4");
+    let wide = row("wide.ldlexvar", "pref_op_imm1_16_imm2_16");
+    assert_eq!(ldlexvar.wide_equivalent().unwrap().opcode, wide.opcode);
+    assert_eq!(wide.narrow_equivalent().unwrap().opcode, ldlexvar.opcode);
+    assert!(wide.wide_equivalent().is_none());
+
+    let lda = opcode_table().iter().find(|r| r.mnemonic == "lda").unwrap();
+    assert!(lda.narrow_equivalent().is_none() && lda.wide_equivalent().is_none());
+}
@@ -0,0 +1,287 @@
+//! Method bodies whose instructions name strings, methods and literal
+//! arrays.
+//!
+//! Code refers to those entities by 16-bit index into its region's index,
+//! and the builder assigns indexes only when it lays the file out. Bodies
+//! are therefore encoded with placeholder ids (`id:k` names the body's
+//! `k`th dependency), the file is finalized once to fix the indexes, the
+//! placeholders are overwritten in place and the file is finalized again.
+//! Ids are always 16 bits wide, so patching never changes a code size and
+//! the second layout matches the first.
+
+use std::collections::HashMap;
+
+use abcd_file::builder::{
+    Builder, CatchBlockDef, ClassHandle, CodeHandle, IndexDep, LiteralArrayHandle, MethodHandle,
+    ProtoHandle, StringHandle,
+};
+use abcd_file::{ACC_PUBLIC, FunctionKind, TypeId};
+use abcd_isa::{Bytecode, EntityId, Label, OperandKind, PrefixGroup, encode, opcode_table};
+
+/// An Ecma literal array element, written as its tag byte and value.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Lit<'a> {
+    Bool(bool),
+    Integer(u32),
+    Double(f64),
+    String(&'a str),
+    Method(MethodHandle),
+    MethodAffiliate(u16),
+}
+
+impl Lit<'_> {
+    fn tag(self) -> u8 {
+        match self {
+            Lit::Bool(_) => 0x01,
+            Lit::Integer(_) => 0x02,
+            Lit::Double(_) => 0x04,
+            Lit::String(_) => 0x05,
+            Lit::Method(_) => 0x06,
+            Lit::MethodAffiliate(_) => 0x09,
+        }
+    }
+}
+
+/// A file under construction.
+pub(crate) struct Assembler {
+    b: Builder,
+    proto: ProtoHandle,
+    bodies: Vec<Encoded>,
+    literal_arrays: u32,
+}
+
+/// A body waiting for its ids to be patched.
+struct Encoded {
+    method: MethodHandle,
+    code: CodeHandle,
+    bytes: Vec<u8>,
+    offsets: Vec<u32>,
+    deps: Vec<IndexDep>,
+}
+
+impl Assembler {
+    pub(crate) fn new() -> Self {
+        let mut b = Builder::new().expect("builder");
+        b.set_api(12, "").expect("API 12 is known");
+        let proto = b.create_proto(TypeId::Tagged, &[]);
+        Self {
+            b,
+            proto,
+            bodies: Vec::new(),
+            literal_arrays: 0,
+        }
+    }
+
+    pub(crate) fn builder(&mut self) -> &mut Builder {
+        &mut self.b
+    }
+
+    pub(crate) fn string(&mut self, s: &str) -> StringHandle {
+        self.b.add_string(s).expect("fixture strings have no NUL")
+    }
+
+    /// A record class compiled from `source_file`.
+    pub(crate) fn record(&mut self, descriptor: &str, source_file: &str) -> ClassHandle {
+        let class = self.b.add_class(descriptor).expect("valid descriptor");
+        let file = self.string(source_file);
+        self.b.class_set_source_file(class, file);
+        class
+    }
+
+    /// A method of `class` with no code yet; see [`define`](Self::define).
+    pub(crate) fn declare(
+        &mut self,
+        class: ClassHandle,
+        name: &str,
+        kind: FunctionKind,
+    ) -> MethodHandle {
+        let method = self
+            .b
+            .class_add_method_with_proto(class, name, self.proto, ACC_PUBLIC, &[], 0, 0)
+            .expect("valid method name");
+        self.b.method_set_function_kind(method, kind);
+        method
+    }
+
+    /// A fresh literal array holding `items`.
+    pub(crate) fn literals(&mut self, items: &[Lit<'_>]) -> LiteralArrayHandle {
+        let lit = self.literal_array();
+        for &item in items {
+            self.b.literal_array_add_u8(lit, item.tag());
+            match item {
+                Lit::Bool(v) => self.b.literal_array_add_bool(lit, v),
+                Lit::Integer(v) => self.b.literal_array_add_u32(lit, v),
+                Lit::Double(v) => self.b.literal_array_add_f64(lit, v),
+                Lit::String(s) => {
+                    let s = self.string(s);
+                    self.b.literal_array_add_string(lit, s);
+                }
+                Lit::Method(m) => self.b.literal_array_add_method(lit, m),
+                Lit::MethodAffiliate(v) => self.b.literal_array_add_u16(lit, v),
+            }
+        }
+        lit
+    }
+
+    /// A fresh, empty literal array, for layouts without tags.
+    pub(crate) fn literal_array(&mut self) -> LiteralArrayHandle {
+        let id = self.literal_arrays.to_string();
+        self.literal_arrays += 1;
+        self.b
+            .add_literal_array(&id)
+            .expect("literal array ids are unique")
+    }
+
+    /// Give `method` the code `build` emits. It has `num_vregs` locals and
+    /// `num_params` declared parameters after the implicit function,
+    /// new.target and this.
+    pub(crate) fn define(
+        &mut self,
+        method: MethodHandle,
+        num_vregs: u32,
+        num_params: u32,
+        build: impl FnOnce(&mut Body<'_>),
+    ) {
+        let mut body = Body {
+            b: &mut self.b,
+            insns: Vec::new(),
+            deps: Vec::new(),
+            strings: HashMap::new(),
+            tries: Vec::new(),
+        };
+        build(&mut body);
+        let Body {
+            insns, deps, tries, ..
+        } = body;
+
+        let (bytes, offsets) = encode(&insns).expect("fixture code encodes");
+        let pc = |i: usize| offsets.get(i).copied().unwrap_or(bytes.len() as u32);
+        let code = self.b.create_code(num_vregs, 3 + num_params, &bytes);
+        for t in &tries {
+            let catch = CatchBlockDef {
+                type_class: None,
+                handler_pc: pc(t.handler.0),
+                code_size: pc(t.handler.1) - pc(t.handler.0),
+            };
+            self.b
+                .code_add_try_block(code, pc(t.body.0), pc(t.body.1) - pc(t.body.0), &[catch]);
+        }
+        self.b.method_set_code(method, code);
+        for &dep in &deps {
+            self.b.method_add_index_dependency(method, dep);
+        }
+        self.bodies.push(Encoded {
+            method,
+            code,
+            bytes,
+            offsets,
+            deps,
+        });
+    }
+
+    /// Lay the file out, patch every body's ids and return the bytes.
+    pub(crate) fn finish(mut self) -> Vec<u8> {
+        self.b.finalize().expect("corpus file lays out");
+        for body in &mut self.bodies {
+            for &off in &body.offsets {
+                let insn = &mut body.bytes[off as usize..];
+                patch_ids(&self.b, body.method, &body.deps, insn);
+            }
+            self.b.code_set_instructions(body.code, &body.bytes);
+        }
+        self.b.finalize().expect("corpus file lays out")
+    }
+}
+
+/// Replace the placeholder ids of the instruction at the start of `insn`
+/// with the indexes `method`'s region assigned.
+fn patch_ids(b: &Builder, method: MethodHandle, deps: &[IndexDep], insn: &mut [u8]) {
+    let opcode = match PrefixGroup::from_prefix_byte(insn[0]) {
+        Some(_) => u16::from_le_bytes([insn[0], insn[1]]),
+        None => u16::from(insn[0]),
+    };
+    let row = opcode_table()
+        .iter()
+        .find(|row| row.opcode == opcode)
+        .expect("encoded opcodes are in the table");
+    for desc in row.operands.iter().filter(|d| d.kind == OperandKind::Id) {
+        let dep = deps[desc.extract(insn) as usize];
+        let index = b
+            .method_index_of(method, dep)
+            .expect("dependencies are indexed after finalize");
+        let at = usize::from(desc.byte_offset);
+        insn[at..at + 2].copy_from_slice(&index.to_le_bytes());
+    }
+}
+
+/// Instruction ranges of one try block, as `[start, end)` instruction
+/// indexes.
+struct TryRange {
+    body: (usize, usize),
+    handler: (usize, usize),
+}
+
+/// Instructions of one method, in order.
+pub(crate) struct Body<'a> {
+    b: &'a mut Builder,
+    insns: Vec<Bytecode>,
+    deps: Vec<IndexDep>,
+    /// Placeholders by content: `add_string` hands out a new handle for
+    /// each call even when the string already exists.
+    strings: HashMap<String, EntityId>,
+    tries: Vec<TryRange>,
+}
+
+impl Body<'_> {
+    /// Append `bc` and return its index.
+    pub(crate) fn emit(&mut self, bc: Bytecode) -> usize {
+        self.insns.push(bc);
+        self.insns.len() - 1
+    }
+
+    /// Index of the next instruction.
+    pub(crate) fn here(&self) -> usize {
+        self.insns.len()
+    }
+
+    /// Point the jump at `jump` to the next instruction.
+    pub(crate) fn bind(&mut self, jump: usize) {
+        let target = Label(self.here() as u32);
+        self.insns[jump].set_label(target);
+    }
+
+    /// Cover instructions `body` with a catch-all handler at `handler`.
+    pub(crate) fn try_catch(&mut self, body: (usize, usize), handler: (usize, usize)) {
+        self.tries.push(TryRange { body, handler });
+    }
+
+    pub(crate) fn string(&mut self, s: &str) -> EntityId {
+        if let Some(&id) = self.strings.get(s) {
+            return id;
+        }
+        let handle = self.b.add_string(s).expect("fixture strings have no NUL");
+        let id = self.dep(IndexDep::String(handle));
+        self.strings.insert(s.to_string(), id);
+        id
+    }
+
+    pub(crate) fn method(&mut self, method: MethodHandle) -> EntityId {
+        self.dep(IndexDep::Method(method))
+    }
+
+    pub(crate) fn literal_array(&mut self, lit: LiteralArrayHandle) -> EntityId {
+        self.dep(IndexDep::LiteralArray(lit))
+    }
+
+    /// The placeholder id for `dep`.
+    fn dep(&mut self, dep: IndexDep) -> EntityId {
+        let k = match self.deps.iter().position(|&d| d == dep) {
+            Some(k) => k,
+            None => {
+                self.deps.push(dep);
+                self.deps.len() - 1
+            }
+        };
+        EntityId(k as u32)
+    }
+}
@@ -0,0 +1,94 @@
+//! Small hand-made files, for tests that need one particular shape rather
+//! than a corpus program.
+
+use abcd_file::builder::{Builder, ClassHandle, MethodHandle, ProtoHandle};
+use abcd_file::{ACC_PUBLIC, File, TypeId};
+use abcd_isa::Version;
+
+/// `returnundefined`, the smallest method body.
+pub const RETURN_UNDEFINED: [u8; 1] = [0x65];
+
+/// Header layout: magic[8], checksum u32, version[4].
+const VERSION_OFFSET: usize = 12;
+
+/// An empty [`Builder`] for a file of API level `api`.
+pub fn builder(api: u8) -> Builder {
+    let mut b = Builder::new().unwrap();
+    b.set_api(api, "").unwrap();
+    b
+}
+
+/// Overwrite the version in the header of the file `data`, for versions
+/// the builder does not write.
+pub fn stamp_version(data: &mut [u8], version: Version) {
+    data[VERSION_OFFSET..VERSION_OFFSET + 4].copy_from_slice(version.as_bytes());
+}
+
+/// A [`Builder`] with the `L_GLOBAL;` class es2abc puts a module's
+/// functions in, and the untyped proto they all share. Everything else is
+/// added through [`builder`](Self::builder).
+pub struct GlobalClass {
+    pub builder: Builder,
+    pub class: ClassHandle,
+    pub proto: ProtoHandle,
+}
+
+impl GlobalClass {
+    /// An API 12 file.
+    pub fn new() -> Self {
+        Self::with_api(12)
+    }
+
+    pub fn with_api(api: u8) -> Self {
+        let mut builder = builder(api);
+        let class = builder.add_class("L_GLOBAL;").unwrap();
+        let proto = builder.create_proto(TypeId::Tagged, &[]);
+        GlobalClass {
+            builder,
+            class,
+            proto,
+        }
+    }
+
+    /// Add a public method running `code` with one register and the three
+    /// arguments every es2abc function takes (the function, `new.target`
+    /// and `this`).
+    pub fn method(&mut self, name: &str, code: &[u8]) -> MethodHandle {
+        self.builder
+            .class_add_method_with_proto(self.class, name, self.proto, ACC_PUBLIC, code, 1, 3)
+            .unwrap()
+    }
+
+    /// Add a public method with no code yet, for code built separately
+    /// and attached with [`Builder::method_set_code`].
+    pub fn method_without_code(&mut self, name: &str) -> MethodHandle {
+        self.builder
+            .class_add_method_with_proto(self.class, name, self.proto, ACC_PUBLIC, &[], 0, 0)
+            .unwrap()
+    }
+
+    /// The file's bytes.
+    pub fn finish(mut self) -> Vec<u8> {
+        self.builder.finalize().unwrap()
+    }
+
+    /// The file, parsed.
+    pub fn open(self) -> File {
+        File::open(self.finish()).unwrap()
+    }
+}
+
+impl Default for GlobalClass {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An API 12 file with the classes `L_GLOBAL;` and `Lcom/example/A;` and
+/// nothing else.
+pub fn two_classes() -> Vec<u8> {
+    let mut b = builder(12);
+    b.add_class("L_GLOBAL;").unwrap();
+    b.add_class("Lcom/example/A;").unwrap();
+    b.finalize().unwrap()
+}
@@ -0,0 +1,395 @@
+//! One program per [`Feature`](crate::Feature), compiled by hand the way
+//! es2abc compiles it. Each function adds its records under `record` so
+//! that copies can share a file.
+
+use abcd_file::builder::ClassHandle;
+use abcd_file::{ACC_PUBLIC, FunctionKind, TypeId};
+use abcd_isa::{Imm, Label, Reg, insn};
+
+use crate::asm::{Assembler, Lit};
+
+fn script(asm: &mut Assembler, record: &str) -> ClassHandle {
+    asm.record(&format!("L{record};"), &format!("{record}.js"))
+}
+
+/// ```js
+/// class Point {
+///     constructor(x, y) { this.x = x; this.y = y; }
+///     norm() { return this.x * this.x + this.y * this.y; }
+/// }
+/// print(new Point(3, 4).norm());
+/// ```
+pub(crate) fn classes(asm: &mut Assembler, record: &str) {
+    let class = script(asm, record);
+    let main = asm.declare(class, "func_main_0", FunctionKind::Function);
+    let ctor = asm.declare(class, "#~@0=#Point", FunctionKind::Function);
+    let norm = asm.declare(class, "#~@0>#norm", FunctionKind::NcFunction);
+    // Prototype methods as (name, method, parameter count), then the
+    // number of static methods.
+    let members = asm.literals(&[
+        Lit::String("norm"),
+        Lit::Method(norm),
+        Lit::MethodAffiliate(0),
+        Lit::Integer(0),
+    ]);
+
+    // No locals: the implicit function, new.target and this are v0..v2.
+    asm.define(ctor, 0, 2, |c| {
+        let (this, x, y) = (Reg(2), Reg(3), Reg(4));
+        c.emit(insn::Lda::new(x));
+        let name = c.string("x");
+        c.emit(insn::Stobjbyname::new(Imm(0), name, this));
+        c.emit(insn::Lda::new(y));
+        let name = c.string("y");
+        c.emit(insn::Stobjbyname::new(Imm(2), name, this));
+        c.emit(insn::Lda::new(this));
+        c.emit(insn::Return::new());
+    });
+
+    asm.define(norm, 2, 0, |c| {
+        let x = c.string("x");
+        let y = c.string("y");
+        c.emit(insn::Ldthisbyname::new(Imm(0), x));
+        c.emit(insn::Sta::new(Reg(0)));
+        c.emit(insn::Ldthisbyname::new(Imm(2), x));
+        c.emit(insn::Mul2::new(Imm(4), Reg(0)));
+        c.emit(insn::Sta::new(Reg(0)));
+        c.emit(insn::Ldthisbyname::new(Imm(5), y));
+        c.emit(insn::Sta::new(Reg(1)));
+        c.emit(insn::Ldthisbyname::new(Imm(7), y));
+        c.emit(insn::Mul2::new(Imm(9), Reg(1)));
+        c.emit(insn::Add2::new(Imm(10), Reg(0)));
+        c.emit(insn::Return::new());
+    });
+
+    asm.define(main, 3, 0, |c| {
+        let ctor = c.method(ctor);
+        let members = c.literal_array(members);
+        c.emit(insn::Ldhole::new());
+        c.emit(insn::Sta::new(Reg(0)));
+        c.emit(insn::Defineclasswithbuffer::new(
+            Imm(0),
+            ctor,
+            members,
+            Imm(2),
+            Reg(0),
+        ));
+        c.emit(insn::Sta::new(Reg(0)));
+        c.emit(insn::Ldai::new(Imm(3)));
+        c.emit(insn::Sta::new(Reg(1)));
+        c.emit(insn::Ldai::new(Imm(4)));
+        c.emit(insn::Sta::new(Reg(2)));
+        c.emit(insn::Newobjrange::new(Imm(1), Imm(3), Reg(0)));
+        c.emit(insn::Sta::new(Reg(1)));
+        let name = c.string("norm");
+        c.emit(insn::Ldobjbyname::new(Imm(3), name));
+        c.emit(insn::Callthis0::new(Imm(5), Reg(1)));
+        c.emit(insn::Sta::new(Reg(2)));
+        let print = c.string("print");
+        c.emit(insn::Tryldglobalbyname::new(Imm(7), print));
+        c.emit(insn::Callarg1::new(Imm(8), Reg(2)));
+        c.emit(insn::Returnundefined::new());
+    });
+}
+
+/// ```js
+/// function makeCounter() {
+///     let n = 0;
+///     return function increment() { n += 1; return n; };
+/// }
+/// const next = makeCounter();
+/// next();
+/// ```
+pub(crate) fn closures(asm: &mut Assembler, record: &str) {
+    let class = script(asm, record);
+    let main = asm.declare(class, "func_main_0", FunctionKind::Function);
+    let make = asm.declare(class, "#*#makeCounter", FunctionKind::Function);
+    let increment = asm.declare(class, "#*@0*#increment", FunctionKind::Function);
+
+    asm.define(make, 0, 0, |c| {
+        let increment = c.method(increment);
+        c.emit(insn::Newlexenv::new(Imm(1)));
+        c.emit(insn::Ldai::new(Imm(0)));
+        c.emit(insn::Stlexvar::new(Imm(0), Imm(0)));
+        c.emit(insn::Definefunc::new(Imm(0), increment, Imm(0)));
+        c.emit(insn::Return::new());
+    });
+
+    asm.define(increment, 0, 0, |c| {
+        c.emit(insn::Ldlexvar::new(Imm(0), Imm(0)));
+        c.emit(insn::Inc::new(Imm(0)));
+        c.emit(insn::Stlexvar::new(Imm(0), Imm(0)));
+        c.emit(insn::Ldlexvar::new(Imm(0), Imm(0)));
+        c.emit(insn::Return::new());
+    });
+
+    asm.define(main, 1, 0, |c| {
+        let make = c.method(make);
+        c.emit(insn::Definefunc::new(Imm(0), make, Imm(0)));
+        c.emit(insn::Callarg0::new(Imm(1)));
+        c.emit(insn::Sta::new(Reg(0)));
+        c.emit(insn::Lda::new(Reg(0)));
+        c.emit(insn::Callarg0::new(Imm(3)));
+        c.emit(insn::Returnundefined::new());
+    });
+}
+
+/// ```js
+/// function parse(text) {
+///     try { return JSON.parse(text); } catch (e) { return "invalid"; }
+/// }
+/// parse('{"ok":true}');
+/// ```
+pub(crate) fn try_catch(asm: &mut Assembler, record: &str) {
+    let class = script(asm, record);
+    let main = asm.declare(class, "func_main_0", FunctionKind::Function);
+    let parse = asm.declare(class, "#*#parse", FunctionKind::Function);
+
+    // v0 is a scratch local; text is v4.
+    asm.define(parse, 1, 1, |c| {
+        let start = c.here();
+        let json = c.string("JSON");
+        c.emit(insn::Tryldglobalbyname::new(Imm(0), json));
+        c.emit(insn::Sta::new(Reg(0)));
+        let name = c.string("parse");
+        c.emit(insn::Ldobjbyname::new(Imm(1), name));
+        c.emit(insn::Callthis1::new(Imm(3), Reg(0), Reg(4)));
+        c.emit(insn::Return::new());
+        let handler = c.here();
+        c.emit(insn::Sta::new(Reg(0)));
+        let invalid = c.string("invalid");
+        c.emit(insn::LdaStr::new(invalid));
+        c.emit(insn::Return::new());
+        c.try_catch((start, handler), (handler, c.here()));
+    });
+
+    asm.define(main, 2, 0, |c| {
+        let parse = c.method(parse);
+        c.emit(insn::Definefunc::new(Imm(0), parse, Imm(1)));
+        c.emit(insn::Sta::new(Reg(0)));
+        let text = c.string("{\"ok\":true}");
+        c.emit(insn::LdaStr::new(text));
+        c.emit(insn::Sta::new(Reg(1)));
+        c.emit(insn::Lda::new(Reg(0)));
+        c.emit(insn::Callarg1::new(Imm(1), Reg(1)));
+        c.emit(insn::Returnundefined::new());
+    });
+}
+
+/// ```js
+/// async function load(url) {
+///     return await fetch(url);
+/// }
+/// load("https://example.com/data.json");
+/// ```
+pub(crate) fn async_fn(asm: &mut Assembler, record: &str) {
+    let class = script(asm, record);
+    let main = asm.declare(class, "func_main_0", FunctionKind::Function);
+    let load = asm.declare(class, "#*#load", FunctionKind::AsyncFunction);
+
+    // v0 holds the async function object, v1 the resumed value, v2 the
+    // resume mode; url is v6. The body runs under a handler that turns
+    // exceptions into a rejected promise.
+    asm.define(load, 3, 1, |c| {
+        let (func, value, mode, url) = (Reg(0), Reg(1), Reg(2), Reg(6));
+        c.emit(insn::Asyncfunctionenter::new());
+        c.emit(insn::Sta::new(func));
+        let start = c.here();
+        let fetch = c.string("fetch");
+        c.emit(insn::Tryldglobalbyname::new(Imm(0), fetch));
+        c.emit(insn::Callarg1::new(Imm(1), url));
+        c.emit(insn::Asyncfunctionawaituncaught::new(func));
+        c.emit(insn::Suspendgenerator::new(func));
+        c.emit(insn::Resumegenerator::new());
+        c.emit(insn::Sta::new(value));
+        c.emit(insn::Getresumemode::new());
+        c.emit(insn::Sta::new(mode));
+        // Mode 1 means the awaited promise rejected.
+        c.emit(insn::Ldai::new(Imm(1)));
+        c.emit(insn::Stricteq::new(Imm(3), mode));
+        let resumed = c.emit(insn::Jeqz::new(Label(0)));
+        c.emit(insn::Lda::new(value));
+        c.emit(insn::Throw::new());
+        c.bind(resumed);
+        c.emit(insn::Lda::new(value));
+        c.emit(insn::Asyncfunctionresolve::new(func));
+        c.emit(insn::Return::new());
+        let handler = c.here();
+        c.emit(insn::Sta::new(value));
+        c.emit(insn::Lda::new(value));
+        c.emit(insn::Asyncfunctionreject::new(func));
+        c.emit(insn::Return::new());
+        c.try_catch((start, handler), (handler, c.here()));
+    });
+
+    asm.define(main, 2, 0, |c| {
+        let load = c.method(load);
+        c.emit(insn::Definefunc::new(Imm(0), load, Imm(1)));
+        c.emit(insn::Sta::new(Reg(0)));
+        let url = c.string("https://example.com/data.json");
+        c.emit(insn::LdaStr::new(url));
+        c.emit(insn::Sta::new(Reg(1)));
+        c.emit(insn::Lda::new(Reg(0)));
+        c.emit(insn::Callarg1::new(Imm(1), Reg(1)));
+        c.emit(insn::Returnundefined::new());
+    });
+}
+
+/// Two ES module records, `util` and `main`:
+///
+/// ```js
+/// // util.js
+/// export function double(x) { return x + x; }
+/// // main.js
+/// import { double } from "./util";
+/// export * from "./util";
+/// print(double(21));
+/// ```
+pub(crate) fn modules(asm: &mut Assembler, record: &str) {
+    let util = module(
+        asm,
+        &format!("{record}/util"),
+        &[],
+        &[],
+        &[("double", "double")],
+        false,
+    );
+    let util_main = asm.declare(util, "func_main_0", FunctionKind::Function);
+    let double = asm.declare(util, "#*#double", FunctionKind::Function);
+    asm.define(double, 0, 1, |c| {
+        c.emit(insn::Lda::new(Reg(3)));
+        c.emit(insn::Add2::new(Imm(0), Reg(3)));
+        c.emit(insn::Return::new());
+    });
+    asm.define(util_main, 0, 0, |c| {
+        let double = c.method(double);
+        c.emit(insn::Definefunc::new(Imm(0), double, Imm(1)));
+        c.emit(insn::Stmodulevar::new(Imm(0)));
+        c.emit(insn::Returnundefined::new());
+    });
+
+    let main = module(
+        asm,
+        &format!("{record}/main"),
+        &["./util"],
+        &[("double", "double", 0)],
+        &[],
+        true,
+    );
+    let main_main = asm.declare(main, "func_main_0", FunctionKind::Function);
+    asm.define(main_main, 2, 0, |c| {
+        c.emit(insn::Ldexternalmodulevar::new(Imm(0)));
+        let name = c.string("double");
+        c.emit(insn::ThrowUndefinedifholewithname::new(name));
+        c.emit(insn::Sta::new(Reg(0)));
+        c.emit(insn::Ldai::new(Imm(21)));
+        c.emit(insn::Sta::new(Reg(1)));
+        c.emit(insn::Lda::new(Reg(0)));
+        c.emit(insn::Callarg1::new(Imm(0), Reg(1)));
+        c.emit(insn::Sta::new(Reg(1)));
+        let print = c.string("print");
+        c.emit(insn::Tryldglobalbyname::new(Imm(2), print));
+        c.emit(insn::Callarg1::new(Imm(3), Reg(1)));
+        c.emit(insn::Returnundefined::new());
+    });
+}
+
+/// A module record class and its module literal. `imports` are
+/// `(local, imported, request)`, `exports` are `(local, exported)`, and
+/// `star` re-exports everything from request 0.
+fn module(
+    asm: &mut Assembler,
+    record: &str,
+    requests: &[&str],
+    imports: &[(&str, &str, u16)],
+    exports: &[(&str, &str)],
+    star: bool,
+) -> ClassHandle {
+    let class = asm.record(&format!("L{record};"), &format!("{record}.js"));
+    let lit = asm.literal_array();
+    let strings: Vec<_> = requests.iter().map(|r| asm.string(r)).collect();
+    let imports: Vec<_> = imports
+        .iter()
+        .map(|&(local, imported, req)| (asm.string(local), asm.string(imported), req))
+        .collect();
+    let exports: Vec<_> = exports
+        .iter()
+        .map(|&(local, exported)| (asm.string(local), asm.string(exported)))
+        .collect();
+
+    let b = asm.builder();
+    b.literal_array_add_u32(lit, strings.len() as u32);
+    for &s in &strings {
+        b.literal_array_add_string(lit, s);
+    }
+    b.literal_array_add_u32(lit, imports.len() as u32);
+    for &(local, imported, req) in &imports {
+        b.literal_array_add_string(lit, local);
+        b.literal_array_add_string(lit, imported);
+        b.literal_array_add_u16(lit, req);
+    }
+    // No namespace imports.
+    b.literal_array_add_u32(lit, 0);
+    b.literal_array_add_u32(lit, exports.len() as u32);
+    for &(local, exported) in &exports {
+        b.literal_array_add_string(lit, local);
+        b.literal_array_add_string(lit, exported);
+    }
+    // No indirect exports.
+    b.literal_array_add_u32(lit, 0);
+    b.literal_array_add_u32(lit, u32::from(star));
+    if star {
+        b.literal_array_add_u16(lit, 0);
+    }
+
+    let field = b
+        .class_add_field(class, "moduleRecordIdx", TypeId::U32, ACC_PUBLIC)
+        .expect("valid field name");
+    b.field_set_value_literal_array(field, lit);
+    class
+}
+
+/// ```js
+/// point = { name: "origin", x: 0.5, visible: true };
+/// primes = [2, 3, 5, 7];
+/// pattern = /^ab+c$/i;
+/// ratio = 2.5;
+/// ```
+pub(crate) fn literals(asm: &mut Assembler, record: &str) {
+    let class = script(asm, record);
+    let main = asm.declare(class, "func_main_0", FunctionKind::Function);
+    let point = asm.literals(&[
+        Lit::String("name"),
+        Lit::String("origin"),
+        Lit::String("x"),
+        Lit::Double(0.5),
+        Lit::String("visible"),
+        Lit::Bool(true),
+    ]);
+    let primes = asm.literals(&[
+        Lit::Integer(2),
+        Lit::Integer(3),
+        Lit::Integer(5),
+        Lit::Integer(7),
+    ]);
+
+    asm.define(main, 0, 0, |c| {
+        let point = c.literal_array(point);
+        c.emit(insn::Createobjectwithbuffer::new(Imm(0), point));
+        let name = c.string("point");
+        c.emit(insn::Stglobalvar::new(Imm(1), name));
+        let primes = c.literal_array(primes);
+        c.emit(insn::Createarraywithbuffer::new(Imm(2), primes));
+        let name = c.string("primes");
+        c.emit(insn::Stglobalvar::new(Imm(3), name));
+        // Flag bit 1 is `i`.
+        let source = c.string("^ab+c$");
+        c.emit(insn::Createregexpwithliteral::new(Imm(4), source, Imm(2)));
+        let name = c.string("pattern");
+        c.emit(insn::Stglobalvar::new(Imm(5), name));
+        c.emit(insn::Fldai::new(Imm(2.5f64.to_bits() as i64)));
+        let name = c.string("ratio");
+        c.emit(insn::Stglobalvar::new(Imm(6), name));
+        c.emit(insn::Returnundefined::new());
+    });
+}
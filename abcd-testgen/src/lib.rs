@@ -0,0 +1,122 @@
+//! Deterministic `.abc` inputs for tests and benchmarks.
+//!
+//! [`generate`] builds a small corpus shaped like es2abc output (classes,
+//! closures, try/catch, async functions, modules and literal buffers) from
+//! nothing but [`abcd_file::builder::Builder`] and [`abcd_isa::encode`].
+//! No file is read from disk and the bytes are the same on every run, so
+//! tests can assert on them and benchmarks can be compared across commits.
+//!
+//! ```no_run
+//! use abcd_testgen::{CorpusSpec, Feature};
+//!
+//! let spec = CorpusSpec {
+//!     features: vec![Feature::Closures],
+//!     copies: 1,
+//! };
+//! for (name, bytes) in abcd_testgen::generate(&spec) {
+//!     let abc = abcd_file::File::open(bytes).unwrap();
+//!     println!("{name}: {} classes", abc.class_offsets().len());
+//! }
+//! ```
+//!
+//! Tests that need one particular shape rather than a whole program build
+//! it from [`files`]: a method body in `L_GLOBAL;`, or two bare classes.
+
+mod asm;
+pub mod files;
+mod fixtures;
+
+use asm::Assembler;
+
+/// Which programs to build.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorpusSpec {
+    /// One file per feature, in this order.
+    pub features: Vec<Feature>,
+    /// Copies of each file's records. Copy `n > 0` has `_n` appended to its
+    /// record names and is otherwise the same program, which gives larger
+    /// inputs for benchmarks and ready-made clones. `0` builds one copy.
+    pub copies: u32,
+}
+
+impl Default for CorpusSpec {
+    /// Every feature, once.
+    fn default() -> Self {
+        Self {
+            features: Feature::ALL.to_vec(),
+            copies: 1,
+        }
+    }
+}
+
+/// A language feature with its own file in the corpus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Feature {
+    /// A class with a constructor and a method, built from a class literal.
+    Classes,
+    /// A function returning a closure over a lexical variable.
+    Closures,
+    /// A function whose body is covered by a catch-all handler.
+    TryCatch,
+    /// An async function that awaits a call and rejects on error.
+    Async,
+    /// Two module records, one importing and re-exporting the other.
+    Modules,
+    /// Array, object and regular expression literals.
+    Literals,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 6] = [
+        Feature::Classes,
+        Feature::Closures,
+        Feature::TryCatch,
+        Feature::Async,
+        Feature::Modules,
+        Feature::Literals,
+    ];
+
+    /// Stem of the generated file's name, also used for its records.
+    pub fn name(self) -> &'static str {
+        match self {
+            Feature::Classes => "classes",
+            Feature::Closures => "closures",
+            Feature::TryCatch => "try_catch",
+            Feature::Async => "async",
+            Feature::Modules => "modules",
+            Feature::Literals => "literals",
+        }
+    }
+}
+
+/// Build the corpus described by `spec` as `(file name, bytes)` pairs.
+pub fn generate(spec: &CorpusSpec) -> Vec<(String, Vec<u8>)> {
+    spec.features
+        .iter()
+        .map(|&feature| {
+            (
+                format!("{}.abc", feature.name()),
+                build(feature, spec.copies),
+            )
+        })
+        .collect()
+}
+
+fn build(feature: Feature, copies: u32) -> Vec<u8> {
+    let mut asm = Assembler::new();
+    for copy in 0..copies.max(1) {
+        let record = match copy {
+            0 => feature.name().to_string(),
+            n => format!("{}_{n}", feature.name()),
+        };
+        match feature {
+            Feature::Classes => fixtures::classes(&mut asm, &record),
+            Feature::Closures => fixtures::closures(&mut asm, &record),
+            Feature::TryCatch => fixtures::try_catch(&mut asm, &record),
+            Feature::Async => fixtures::async_fn(&mut asm, &record),
+            Feature::Modules => fixtures::modules(&mut asm, &record),
+            Feature::Literals => fixtures::literals(&mut asm, &record),
+        }
+    }
+    asm.finish()
+}
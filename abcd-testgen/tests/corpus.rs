@@ -0,0 +1,142 @@
+use abcd_file::{EntityId, File};
+use abcd_isa::{OperandKind, PrefixGroup, decode, opcode_table};
+use abcd_testgen::{CorpusSpec, Feature, generate};
+
+fn open(feature: Feature, copies: u32) -> File {
+    let spec = CorpusSpec {
+        features: vec![feature],
+        copies,
+    };
+    let (_, bytes) = generate(&spec).pop().unwrap();
+    File::open(bytes).unwrap()
+}
+
+fn method_named(abc: &File, class: &str, name: &str) -> EntityId {
+    let class = abc.class_id_by_name(class).unwrap().unwrap();
+    abc.class(class)
+        .unwrap()
+        .method_offsets()
+        .into_iter()
+        .find(|&m| abc.method_name(m).unwrap() == name)
+        .unwrap()
+}
+
+/// What each id operand of `method` resolves to, in instruction order.
+fn resolved_ids(abc: &File, method: EntityId) -> Vec<EntityId> {
+    let code = abc
+        .code(abc.method(method).unwrap().code_off().unwrap())
+        .unwrap();
+    let bytes = code.instructions();
+    let mut ids = Vec::new();
+    for (_, off) in decode(bytes).unwrap() {
+        let insn = &bytes[off as usize..];
+        let opcode = match PrefixGroup::from_prefix_byte(insn[0]) {
+            Some(_) => u16::from_le_bytes([insn[0], insn[1]]),
+            None => u16::from(insn[0]),
+        };
+        let row = opcode_table().iter().find(|r| r.opcode == opcode).unwrap();
+        for desc in row.operands.iter().filter(|d| d.kind == OperandKind::Id) {
+            let idx = desc.extract(insn) as u16;
+            ids.push(abc.resolve_offset_by_index(method, idx).unwrap());
+        }
+    }
+    ids
+}
+
+fn strings(abc: &File, ids: &[EntityId]) -> Vec<String> {
+    ids.iter()
+        .filter_map(|&id| abc.get_string(id).ok())
+        .collect()
+}
+
+#[test]
+fn default_spec_has_one_file_per_feature() {
+    let names: Vec<String> = generate(&CorpusSpec::default())
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "classes.abc",
+            "closures.abc",
+            "try_catch.abc",
+            "async.abc",
+            "modules.abc",
+            "literals.abc",
+        ]
+    );
+}
+
+#[test]
+fn output_is_deterministic() {
+    let spec = CorpusSpec {
+        copies: 2,
+        ..CorpusSpec::default()
+    };
+    assert_eq!(generate(&spec), generate(&spec));
+}
+
+#[test]
+fn every_file_opens_and_every_id_resolves() {
+    for (name, bytes) in generate(&CorpusSpec::default()) {
+        let abc = File::open(bytes).unwrap_or_else(|e| panic!("{name}: {e}"));
+        for class_off in abc.class_offsets() {
+            if abc.is_external(class_off) {
+                continue;
+            }
+            for method in abc.class(class_off).unwrap().method_offsets() {
+                resolved_ids(&abc, method);
+            }
+        }
+    }
+}
+
+#[test]
+fn string_operands_name_their_strings() {
+    let abc = open(Feature::TryCatch, 1);
+    let parse = method_named(&abc, "Ltry_catch;", "#*#parse");
+    assert_eq!(
+        strings(&abc, &resolved_ids(&abc, parse)),
+        ["JSON", "parse", "invalid"]
+    );
+    let code_off = abc.method(parse).unwrap().code_off().unwrap();
+    assert_eq!(abc.code(code_off).unwrap().try_blocks().len(), 1);
+}
+
+#[test]
+fn method_operands_name_their_methods() {
+    let abc = open(Feature::Closures, 1);
+    let make = method_named(&abc, "Lclosures;", "#*#makeCounter");
+    let ids = resolved_ids(&abc, make);
+    assert_eq!(ids, [method_named(&abc, "Lclosures;", "#*@0*#increment")]);
+}
+
+#[test]
+fn module_records_point_at_their_literals() {
+    let abc = open(Feature::Modules, 1);
+    let class_off = abc.class_id_by_name("Lmodules/main;").unwrap().unwrap();
+    let class = abc.class(class_off).unwrap();
+    let field = class
+        .field_offsets()
+        .into_iter()
+        .map(|f| abc.field(f).unwrap())
+        .find(|f| abc.get_string(f.name_off()).unwrap() == "moduleRecordIdx")
+        .unwrap();
+    let module = abc
+        .module_record(field.value_i32().unwrap() as u32)
+        .unwrap();
+    assert_eq!(module.num_requests(), 1);
+    let request = module.request_off(0).unwrap();
+    assert_eq!(abc.get_string(request).unwrap(), "./util");
+    // One regular import and one star export.
+    assert_eq!(module.records().len(), 2);
+}
+
+#[test]
+fn copies_get_their_own_records() {
+    let abc = open(Feature::Literals, 3);
+    for record in ["Lliterals;", "Lliterals_1;", "Lliterals_2;"] {
+        method_named(&abc, record, "func_main_0");
+    }
+}